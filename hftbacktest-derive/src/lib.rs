@@ -242,6 +242,15 @@ pub fn build_asset(input: TokenStream) -> TokenStream {
                                     DataSource::Data(data) => {
                                         reader.add_data(data.clone());
                                     }
+                                    DataSource::Url(url) => {
+                                        reader.add_url(url.to_string());
+                                    }
+                                    DataSource::Query(query) => {
+                                        reader.add_query(query.clone());
+                                    }
+                                    DataSource::Iterator(generator) => {
+                                        reader.add_generator(generator.clone());
+                                    }
                                 }
                             }
 
@@ -251,22 +260,42 @@ pub fn build_asset(input: TokenStream) -> TokenStream {
                             let asset_type = #at_ident::new(#(#at_args.clone()),*);
                             let latency_model = #lm_ident::new(#(#lm_args.clone()),*);
 
-                            let mut market_depth = #depth_construct;
-                            match #asset.initial_snapshot.as_ref() {
-                                Some(DataSource::File(file)) => {
-                                    let data = read_npz_file(&file, "data").unwrap();
-                                    market_depth.apply_snapshot(&data);
+                            let initial_snapshot = match #asset.initial_snapshot.as_ref() {
+                                Some(DataSource::File(file)) => Some(
+                                    read_npz_file(&file, "data")
+                                        .map_err(|error| BuildError::Error(error.into()))?,
+                                ),
+                                Some(DataSource::Data(data)) => Some(data.clone()),
+                                Some(DataSource::Url(_)) => {
+                                    return Err(BuildError::InvalidArgument(
+                                        "a remote initial snapshot is not supported",
+                                    ));
+                                }
+                                Some(DataSource::Query(_)) => {
+                                    return Err(BuildError::InvalidArgument(
+                                        "a queried initial snapshot is not supported",
+                                    ));
                                 }
-                                Some(DataSource::Data(data)) => {
-                                    market_depth.apply_snapshot(data);
+                                Some(DataSource::Iterator(_)) => {
+                                    return Err(BuildError::InvalidArgument(
+                                        "a generated initial snapshot is not supported",
+                                    ));
                                 }
-                                None => {}
+                                None => None,
+                            };
+
+                            let mut market_depth = #depth_construct;
+                            if let Some(snapshot) = initial_snapshot.as_ref() {
+                                market_depth.apply_snapshot(snapshot);
                             }
 
                             let local: Box<dyn LocalProcessor<#marketdepth, Event>> = Box::new(Local::new(
                                 reader.clone(),
                                 market_depth,
-                                State::new(asset_type.clone(), #asset.maker_fee, #asset.taker_fee),
+                                State::new(
+                                    asset_type.clone(),
+                                    Box::new(FlatFee::new(#asset.maker_fee, #asset.taker_fee)),
+                                ),
                                 latency_model.clone(),
                                 #asset.trade_len,
                                 ob_local_to_exch.clone(),
@@ -274,15 +303,8 @@ pub fn build_asset(input: TokenStream) -> TokenStream {
                             ));
 
                             let mut market_depth = #depth_construct;
-                            match #asset.initial_snapshot.as_ref() {
-                                Some(DataSource::File(file)) => {
-                                    let data = read_npz_file(&file, "data").unwrap();
-                                    market_depth.apply_snapshot(&data);
-                                }
-                                Some(DataSource::Data(data)) => {
-                                    market_depth.apply_snapshot(data);
-                                }
-                                None => {}
+                            if let Some(snapshot) = initial_snapshot.as_ref() {
+                                market_depth.apply_snapshot(snapshot);
                             }
 
                             let queue_model = #qm_construct;
@@ -290,17 +312,20 @@ pub fn build_asset(input: TokenStream) -> TokenStream {
                             let exch: Box<dyn Processor> = Box::new(#em_ident::new(
                                 reader,
                                 market_depth,
-                                State::new(asset_type, #asset.maker_fee, #asset.taker_fee),
+                                State::new(
+                                    asset_type,
+                                    Box::new(FlatFee::new(#asset.maker_fee, #asset.taker_fee)),
+                                ),
                                 latency_model,
                                 queue_model,
                                 ob_exch_to_local,
                                 ob_local_to_exch,
                             ));
 
-                            Asset {
+                            Ok(Asset {
                                 local,
                                 exch
-                            }
+                            })
                         },
                     });
                 }
@@ -308,15 +333,18 @@ pub fn build_asset(input: TokenStream) -> TokenStream {
         }
     }
 
+    // Evaluates to `Result<Asset, BuildError>`.
     let output = quote! {
-        match (
-            &#asset.asset_type,
-            &#asset.latency_model,
-            &#asset.queue_model,
-            &#asset.exch_kind
-        ) {
-            #(#arms)*
-        }
+        (|| -> Result<Asset<_, _>, BuildError> {
+            match (
+                &#asset.asset_type,
+                &#asset.latency_model,
+                &#asset.queue_model,
+                &#asset.exch_kind
+            ) {
+                #(#arms)*
+            }
+        })()
     };
 
     output.into()
@@ -0,0 +1,31 @@
+use clap::Parser;
+use hftbacktest::{backtest::DataSource, data::quality::QualityCheck};
+
+#[derive(Parser, Debug)]
+#[command(about = "Reports the quality of event data files", long_about = None)]
+struct Args {
+    #[arg(long)]
+    tick_size: f64,
+    #[arg(long)]
+    lot_size: f64,
+    /// The interval in nanoseconds over which the update rate is counted.
+    #[arg(long, default_value_t = 60_000_000_000)]
+    interval: i64,
+    /// The minimum period in nanoseconds without any events that is reported as a gap.
+    #[arg(long, default_value_t = 1_000_000_000)]
+    gap_threshold: i64,
+    #[arg(long, num_args = 1..)]
+    data_files: Vec<String>,
+}
+
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    let report = QualityCheck::new(args.tick_size, args.lot_size)
+        .interval(args.interval)
+        .gap_threshold(args.gap_threshold)
+        .run(args.data_files.into_iter().map(DataSource::File).collect())
+        .unwrap();
+    println!("{report}");
+}
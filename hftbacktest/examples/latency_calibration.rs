@@ -0,0 +1,51 @@
+use clap::Parser;
+use hftbacktest::backtest::{
+    models::{LatencyCalibration, OrderLatencyRow},
+    reader::read_npz_file,
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Fits the latency models to the order latency recorded by LatencyRecorder",
+    long_about = None
+)]
+struct Args {
+    /// The number of quantiles of the empirical distribution.
+    #[arg(long, default_value_t = 20)]
+    num_quantiles: usize,
+    #[arg(long, num_args = 1..)]
+    data_files: Vec<String>,
+}
+
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+    let mut rows = Vec::new();
+    for file in &args.data_files {
+        let data = read_npz_file::<OrderLatencyRow>(file, "data").unwrap();
+        rows.extend((0..data.len()).map(|i| data[i].clone()));
+    }
+    let calibration = LatencyCalibration::new(&rows);
+    println!("samples: {}", calibration.num_samples());
+    println!("excluded: {}", calibration.num_excluded());
+    println!("rejection rate: {:.4}", calibration.rejection_rate());
+    if let Some((entry, response)) = calibration.min_latency() {
+        println!("min latency: entry={entry} response={response}");
+    }
+    match calibration.lognormal() {
+        Ok((entry, response)) => println!("lognormal: entry={entry:?} response={response:?}"),
+        Err(error) => println!("lognormal: {error}"),
+    }
+    match calibration.gamma() {
+        Ok((entry, response)) => println!("gamma: entry={entry:?} response={response:?}"),
+        Err(error) => println!("gamma: {error}"),
+    }
+    if let Ok((entry, response)) = calibration.empirical(args.num_quantiles) {
+        println!("quantile,entry,response");
+        for i in 0..=args.num_quantiles {
+            let p = i as f64 / args.num_quantiles as f64;
+            println!("{p},{},{}", entry.quantile(p), response.quantile(p));
+        }
+    }
+}
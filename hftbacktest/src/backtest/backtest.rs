@@ -3,18 +3,20 @@ use std::{collections::HashMap, marker::PhantomData};
 use crate::{
     backtest::{
         evs::{EventIntentKind, EventSet},
-        proc::{LocalProcessor, Processor},
+        proc::{LocalProcessor, Processor, Spread, CANCEL_ALL_ORDER_ID},
         Asset,
         BacktestError,
     },
-    depth::{HashMapMarketDepth, MarketDepth},
+    depth::{DepthChange, FusedMarketDepth, HashMapMarketDepth, MarketDepth},
     prelude::{OrderId, OrderRequest},
     types::{
+        BatchRequest,
         Bot,
         BuildError,
         Event,
         OrdType,
         Order,
+        Roll,
         Side,
         StateValues,
         TimeInForce,
@@ -27,9 +29,15 @@ use crate::{
 pub struct BacktestBuilder<MD> {
     local: Vec<Box<dyn LocalProcessor<MD, Event>>>,
     exch: Vec<Box<dyn Processor>>,
+    spreads: HashMap<usize, Spread>,
+    fused_depth: Option<FusedMarketDepth>,
+    depth_changes: HashMap<usize, Vec<DepthChange>>,
 }
 
-impl<MD> BacktestBuilder<MD> {
+impl<MD> BacktestBuilder<MD>
+where
+    MD: MarketDepth,
+{
     /// Adds [`Asset`], which will undergo simulation within the backtester.
     pub fn add(self, asset: Asset<dyn LocalProcessor<MD, Event>, dyn Processor>) -> Self {
         let mut self_ = Self { ..self };
@@ -38,17 +46,68 @@ impl<MD> BacktestBuilder<MD> {
         self_
     }
 
+    /// Makes the asset an exchange-listed spread instrument, whose fills result in the fills of
+    /// its legs. See [`Spread`].
+    pub fn spread(mut self, asset_no: usize, spread: Spread) -> Self {
+        self.spreads.insert(asset_no, spread);
+        self
+    }
+
+    /// Maintains the consolidated market depth of the venues during the backtest, which is
+    /// accessible by [`Backtest::fused_depth`]. See [`FusedMarketDepth`].
+    pub fn fused_depth(self, fused_depth: FusedMarketDepth) -> Self {
+        Self {
+            fused_depth: Some(fused_depth),
+            ..self
+        }
+    }
+
+    /// Tracks the changes of the market depth of the asset made by the feed, which are taken by
+    /// [`Backtest::take_depth_changes`].
+    pub fn track_depth_changes(mut self, asset_no: usize) -> Self {
+        self.depth_changes.insert(asset_no, Vec::new());
+        self
+    }
+
     /// Builds [`Backtest`].
-    pub fn build(self) -> Result<Backtest<MD>, BuildError> {
+    pub fn build(mut self) -> Result<Backtest<MD>, BuildError> {
         let num_assets = self.local.len();
         if self.local.len() != num_assets || self.exch.len() != num_assets {
             panic!();
         }
+        for (&asset_no, spread) in &self.spreads {
+            if asset_no >= num_assets
+                || spread
+                    .legs()
+                    .iter()
+                    .any(|leg| leg.asset_no >= num_assets || leg.asset_no == asset_no)
+            {
+                return Err(BuildError::InvalidArgument("invalid spread asset"));
+            }
+            self.local[asset_no].record_fills();
+        }
+        if let Some(fused_depth) = &self.fused_depth {
+            for &asset_no in fused_depth.venues() {
+                if asset_no >= num_assets {
+                    return Err(BuildError::InvalidArgument("invalid fused depth venue"));
+                }
+                self.local[asset_no].record_depth_changes();
+            }
+        }
+        for &asset_no in self.depth_changes.keys() {
+            if asset_no >= num_assets {
+                return Err(BuildError::InvalidArgument("invalid depth change asset"));
+            }
+            self.local[asset_no].record_depth_changes();
+        }
         Ok(Backtest {
             cur_ts: i64::MAX,
             evs: EventSet::new(num_assets),
             local: self.local,
             exch: self.exch,
+            spreads: self.spreads,
+            fused_depth: self.fused_depth,
+            depth_changes: self.depth_changes,
         })
     }
 }
@@ -61,6 +120,9 @@ pub struct Backtest<MD> {
     evs: EventSet,
     local: Vec<Box<dyn LocalProcessor<MD, Event>>>,
     exch: Vec<Box<dyn Processor>>,
+    spreads: HashMap<usize, Spread>,
+    fused_depth: Option<FusedMarketDepth>,
+    depth_changes: HashMap<usize, Vec<DepthChange>>,
 }
 
 impl<MD> Backtest<MD>
@@ -71,6 +133,9 @@ where
         BacktestBuilder {
             local: vec![],
             exch: vec![],
+            spreads: HashMap::new(),
+            fused_depth: None,
+            depth_changes: HashMap::new(),
         }
     }
 
@@ -87,6 +152,9 @@ where
             evs: EventSet::new(num_assets),
             local,
             exch,
+            spreads: HashMap::new(),
+            fused_depth: None,
+            depth_changes: HashMap::new(),
         }
     }
 
@@ -164,6 +232,7 @@ where
                                     return Err(e);
                                 }
                             }
+                            self.apply_depth_changes(ev.asset_no);
                             if WAIT_NEXT_FEED {
                                 timestamp = ev.timestamp;
                             }
@@ -186,6 +255,7 @@ where
                                 ev.asset_no,
                                 local.earliest_recv_order_timestamp(),
                             );
+                            self.apply_leg_fills(ev.asset_no);
                         }
                         EventIntentKind::ExchData => {
                             let exch = unsafe { self.exch.get_unchecked_mut(ev.asset_no) };
@@ -212,6 +282,10 @@ where
                                 ev.asset_no,
                                 exch.earliest_recv_order_timestamp(),
                             );
+                            self.evs.update_local_order(
+                                ev.asset_no,
+                                exch.earliest_send_order_timestamp(),
+                            );
                         }
                     }
                 }
@@ -221,6 +295,59 @@ where
             }
         }
     }
+
+    /// Returns the consolidated market depth of the venues if it is set by
+    /// [`BacktestBuilder::fused_depth`].
+    pub fn fused_depth(&self) -> Option<&FusedMarketDepth> {
+        self.fused_depth.as_ref()
+    }
+
+    /// Takes the changes of the market depth of the asset made by the feed since the last call, in
+    /// the order they were applied, so that the strategy can follow the levels that changed instead
+    /// of scanning the whole market depth. Only the assets set by
+    /// [`BacktestBuilder::track_depth_changes`] are tracked, and an empty vector is returned for
+    /// the others. The changes accumulate until they are taken.
+    pub fn take_depth_changes(&mut self, asset_no: usize) -> Vec<DepthChange> {
+        self.depth_changes
+            .get_mut(&asset_no)
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    fn apply_depth_changes(&mut self, asset_no: usize) {
+        if self.fused_depth.is_none() && self.depth_changes.is_empty() {
+            return;
+        }
+        let changes = self.local[asset_no].take_depth_changes();
+        if let Some(fused_depth) = self.fused_depth.as_mut() {
+            for change in &changes {
+                fused_depth.apply(asset_no, change);
+            }
+        }
+        if let Some(depth_changes) = self.depth_changes.get_mut(&asset_no) {
+            depth_changes.extend(changes);
+        }
+    }
+
+    /// Applies the fills of the spread orders received by the spread asset to its legs, priced at
+    /// the mid-prices of the legs' local market depth.
+    fn apply_leg_fills(&mut self, asset_no: usize) {
+        let Some(spread) = self.spreads.get(&asset_no) else {
+            return;
+        };
+        for fill in self.local[asset_no].take_fills() {
+            let leg_fills = spread.leg_fills(&fill, |leg_no| {
+                let depth = self.local[leg_no].depth();
+                (
+                    (depth.best_bid() + depth.best_ask()) / 2.0,
+                    depth.tick_size(),
+                )
+            });
+            for (leg_no, leg_fill) in leg_fills {
+                self.local[leg_no].apply_leg_fill(&leg_fill);
+            }
+        }
+    }
 }
 
 impl<MD> Bot<MD> for Backtest<MD>
@@ -346,15 +473,7 @@ where
         wait: bool,
     ) -> Result<bool, Self::Error> {
         let local = self.local.get_mut(asset_no).unwrap();
-        local.submit_order(
-            order.order_id,
-            Side::Sell,
-            order.price,
-            order.qty,
-            order.order_type,
-            order.time_in_force,
-            self.cur_ts,
-        )?;
+        local.submit_order_request(&order, self.cur_ts)?;
 
         if wait {
             return self.goto::<false>(
@@ -365,6 +484,26 @@ where
         Ok(true)
     }
 
+    fn submit_batch(
+        &mut self,
+        asset_no: usize,
+        requests: Vec<BatchRequest>,
+        wait: bool,
+    ) -> Result<bool, Self::Error> {
+        let local = self.local.get_mut(asset_no).unwrap();
+        local.submit_batch(&requests, self.cur_ts)?;
+
+        if wait {
+            if let Some(request) = requests.last() {
+                return self.goto::<false>(
+                    UNTIL_END_OF_DATA,
+                    WaitOrderResponse::Specified(asset_no, request.order_id()),
+                );
+            }
+        }
+        Ok(true)
+    }
+
     #[inline]
     fn cancel(
         &mut self,
@@ -384,6 +523,60 @@ where
         Ok(true)
     }
 
+    #[inline]
+    fn cancel_all(&mut self, asset_no: usize, wait: bool) -> Result<bool, Self::Error> {
+        let local = self.local.get_mut(asset_no).unwrap();
+        local.cancel_all(self.cur_ts)?;
+
+        if wait {
+            return self.goto::<false>(
+                UNTIL_END_OF_DATA,
+                WaitOrderResponse::Specified(asset_no, CANCEL_ALL_ORDER_ID),
+            );
+        }
+        Ok(true)
+    }
+
+    #[inline]
+    fn modify(
+        &mut self,
+        asset_no: usize,
+        order_id: OrderId,
+        price: f64,
+        qty: f64,
+        wait: bool,
+    ) -> Result<bool, Self::Error> {
+        let local = self.local.get_mut(asset_no).unwrap();
+        local.modify(order_id, price, qty, self.cur_ts)?;
+
+        if wait {
+            return self.goto::<false>(
+                UNTIL_END_OF_DATA,
+                WaitOrderResponse::Specified(asset_no, order_id),
+            );
+        }
+        Ok(true)
+    }
+
+    #[inline]
+    fn query_order(
+        &mut self,
+        asset_no: usize,
+        order_id: OrderId,
+        wait: bool,
+    ) -> Result<bool, Self::Error> {
+        let local = self.local.get_mut(asset_no).unwrap();
+        local.query_order(order_id, self.cur_ts)?;
+
+        if wait {
+            return self.goto::<false>(
+                UNTIL_END_OF_DATA,
+                WaitOrderResponse::Specified(asset_no, order_id),
+            );
+        }
+        Ok(true)
+    }
+
     #[inline]
     fn clear_inactive_orders(&mut self, asset_no: Option<usize>) {
         match asset_no {
@@ -473,6 +666,20 @@ where
     fn order_latency(&self, asset_no: usize) -> Option<(i64, i64, i64)> {
         self.local.get(asset_no).unwrap().order_latency()
     }
+
+    #[inline]
+    fn feed_gap_count(&self, asset_no: usize) -> usize {
+        self.local.get(asset_no).unwrap().feed_gap_count()
+    }
+    #[inline]
+    fn aux_event(&self, asset_no: usize, kind: u64) -> Option<&Event> {
+        self.local.get(asset_no).unwrap().aux_event(kind)
+    }
+
+    #[inline]
+    fn rolls(&self, asset_no: usize) -> &[Roll] {
+        self.local.get(asset_no).unwrap().rolls()
+    }
 }
 
 /// `MultiAssetSingleExchangeBacktest` builder.
@@ -659,6 +866,10 @@ where
                                 ev.asset_no,
                                 exch.earliest_recv_order_timestamp(),
                             );
+                            self.evs.update_local_order(
+                                ev.asset_no,
+                                exch.earliest_send_order_timestamp(),
+                            );
                         }
                     }
                 }
@@ -799,15 +1010,7 @@ where
         wait: bool,
     ) -> Result<bool, Self::Error> {
         let local = self.local.get_mut(asset_no).unwrap();
-        local.submit_order(
-            order.order_id,
-            Side::Sell,
-            order.price,
-            order.qty,
-            order.order_type,
-            order.time_in_force,
-            self.cur_ts,
-        )?;
+        local.submit_order_request(&order, self.cur_ts)?;
         self.evs
             .update_exch_order(asset_no, local.earliest_send_order_timestamp());
         self.evs
@@ -822,6 +1025,30 @@ where
         Ok(true)
     }
 
+    fn submit_batch(
+        &mut self,
+        asset_no: usize,
+        requests: Vec<BatchRequest>,
+        wait: bool,
+    ) -> Result<bool, Self::Error> {
+        let local = self.local.get_mut(asset_no).unwrap();
+        local.submit_batch(&requests, self.cur_ts)?;
+        self.evs
+            .update_exch_order(asset_no, local.earliest_send_order_timestamp());
+        self.evs
+            .update_local_order(asset_no, local.earliest_recv_order_timestamp());
+
+        if wait {
+            if let Some(request) = requests.last() {
+                return self.goto::<false>(
+                    UNTIL_END_OF_DATA,
+                    WaitOrderResponse::Specified(asset_no, request.order_id()),
+                );
+            }
+        }
+        Ok(true)
+    }
+
     #[inline]
     fn cancel(
         &mut self,
@@ -845,6 +1072,72 @@ where
         Ok(true)
     }
 
+    #[inline]
+    fn cancel_all(&mut self, asset_no: usize, wait: bool) -> Result<bool, Self::Error> {
+        let local = self.local.get_mut(asset_no).unwrap();
+        local.cancel_all(self.cur_ts)?;
+        self.evs
+            .update_exch_order(asset_no, local.earliest_send_order_timestamp());
+        self.evs
+            .update_local_order(asset_no, local.earliest_recv_order_timestamp());
+
+        if wait {
+            return self.goto::<false>(
+                UNTIL_END_OF_DATA,
+                WaitOrderResponse::Specified(asset_no, CANCEL_ALL_ORDER_ID),
+            );
+        }
+        Ok(true)
+    }
+
+    #[inline]
+    fn modify(
+        &mut self,
+        asset_no: usize,
+        order_id: OrderId,
+        price: f64,
+        qty: f64,
+        wait: bool,
+    ) -> Result<bool, Self::Error> {
+        let local = self.local.get_mut(asset_no).unwrap();
+        local.modify(order_id, price, qty, self.cur_ts)?;
+        self.evs
+            .update_exch_order(asset_no, local.earliest_send_order_timestamp());
+        self.evs
+            .update_local_order(asset_no, local.earliest_recv_order_timestamp());
+
+        if wait {
+            return self.goto::<false>(
+                UNTIL_END_OF_DATA,
+                WaitOrderResponse::Specified(asset_no, order_id),
+            );
+        }
+        Ok(true)
+    }
+
+    #[inline]
+    fn query_order(
+        &mut self,
+        asset_no: usize,
+        order_id: OrderId,
+        wait: bool,
+    ) -> Result<bool, Self::Error> {
+        let local = self.local.get_mut(asset_no).unwrap();
+        local.query_order(order_id, self.cur_ts)?;
+        self.evs
+            .update_exch_order(asset_no, local.earliest_send_order_timestamp());
+        self.evs
+            .update_local_order(asset_no, local.earliest_recv_order_timestamp());
+
+        if wait {
+            return self.goto::<false>(
+                UNTIL_END_OF_DATA,
+                WaitOrderResponse::Specified(asset_no, order_id),
+            );
+        }
+        Ok(true)
+    }
+
     #[inline]
     fn clear_inactive_orders(&mut self, asset_no: Option<usize>) {
         match asset_no {
@@ -933,4 +1226,73 @@ where
     fn order_latency(&self, asset_no: usize) -> Option<(i64, i64, i64)> {
         self.local.get(asset_no).unwrap().order_latency()
     }
+
+    #[inline]
+    fn feed_gap_count(&self, asset_no: usize) -> usize {
+        self.local.get(asset_no).unwrap().feed_gap_count()
+    }
+    #[inline]
+    fn aux_event(&self, asset_no: usize, kind: u64) -> Option<&Event> {
+        self.local.get(asset_no).unwrap().aux_event(kind)
+    }
+
+    #[inline]
+    fn rolls(&self, asset_no: usize) -> &[Roll] {
+        self.local.get(asset_no).unwrap().rolls()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, event},
+            reader::Data,
+            Backtest,
+        },
+        depth::DepthChange,
+        types::{Bot, Side, BUY_EVENT, DEPTH_EVENT, SELL_EVENT},
+    };
+
+    #[test]
+    fn test_take_depth_changes() {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 20, 101.0, 0.0),
+        ]);
+        let mut hbt = Backtest::builder()
+            .add(asset_builder(data, 1.0, 1.0).build().unwrap())
+            .track_depth_changes(0)
+            .build()
+            .unwrap();
+
+        hbt.elapse(10).unwrap();
+        assert_eq!(
+            hbt.take_depth_changes(0),
+            vec![
+                DepthChange::Level {
+                    side: Side::Buy,
+                    price: 100.0,
+                    qty: 10.0
+                },
+                DepthChange::Level {
+                    side: Side::Sell,
+                    price: 101.0,
+                    qty: 10.0
+                },
+            ]
+        );
+        assert!(hbt.take_depth_changes(0).is_empty());
+
+        hbt.elapse(20).unwrap();
+        assert_eq!(
+            hbt.take_depth_changes(0),
+            vec![DepthChange::Level {
+                side: Side::Sell,
+                price: 101.0,
+                qty: 0.0
+            }]
+        );
+    }
 }
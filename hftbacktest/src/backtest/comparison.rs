@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use crate::{
+    backtest::BacktestError,
+    depth::MarketDepth,
+    types::{Bot, OrderId, Recorder, Status},
+};
+
+/// Records the fills and the state values of a backtest run by [`QueueModelComparison`].
+///
+/// Since only the current orders are available, [`record`](Recorder::record) needs to be called
+/// regularly, at least before [`clear_inactive_orders`](Bot::clear_inactive_orders), to capture
+/// the fill timing of all fills. The number of fills is taken from the state values, so it
+/// doesn't depend on the recording interval.
+#[derive(Default)]
+pub struct ComparisonRecorder {
+    // key: (asset_no, order_id), value: the exchange timestamp of the last recorded fill.
+    fills: HashMap<(usize, OrderId), i64>,
+    fill_time_sum: i64,
+    fill_time_count: usize,
+    num_fills: i64,
+    pnl: f64,
+}
+
+impl Recorder for ComparisonRecorder {
+    type Error = BacktestError;
+
+    fn record<MD, I>(&mut self, hbt: &mut I) -> Result<(), Self::Error>
+    where
+        MD: MarketDepth,
+        I: Bot<MD>,
+    {
+        self.num_fills = 0;
+        self.pnl = 0.0;
+        for asset_no in 0..hbt.num_assets() {
+            for order in hbt.orders(asset_no).values() {
+                if order.status != Status::Filled && order.status != Status::PartiallyFilled {
+                    continue;
+                }
+                let last = self.fills.entry((asset_no, order.order_id)).or_insert(0);
+                if *last < order.exch_timestamp {
+                    *last = order.exch_timestamp;
+                    self.fill_time_sum += order.exch_timestamp - order.local_timestamp;
+                    self.fill_time_count += 1;
+                }
+            }
+
+            let depth = hbt.depth(asset_no);
+            let mid_price = (depth.best_bid() + depth.best_ask()) / 2.0;
+            let state_values = hbt.state_values(asset_no);
+            self.num_fills += state_values.num_trades;
+            if mid_price.is_finite() {
+                self.pnl +=
+                    state_values.balance + state_values.position * mid_price - state_values.fee;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The result of a backtest run by [`QueueModelComparison`].
+#[derive(Clone, Debug)]
+pub struct QueueModelReport {
+    /// The name given to the run.
+    pub name: String,
+    /// The number of fills across all assets.
+    pub num_fills: i64,
+    /// The mean time from the order submission to the fill in nanoseconds, or `None` if no fills
+    /// are recorded.
+    pub mean_fill_time: Option<f64>,
+    /// The equity at the last record, the balance plus the position valued at the mid price less
+    /// the fees, summed across all assets.
+    pub pnl: f64,
+    /// The difference in the number of fills from the baseline, the first run.
+    pub num_fills_delta: i64,
+    /// The difference in the PnL from the baseline, the first run.
+    pub pnl_delta: f64,
+}
+
+/// Runs the same strategy on the same data against several queue models, or parameterizations of
+/// a queue model, in one invocation, and reports per-model fill counts, fill timing and PnL
+/// deltas against the first run, the baseline.
+///
+/// Each run is given as a bot, such as a [`Backtest`](crate::backtest::Backtest), built with the
+/// queue model to compare. The strategy is given the bot and a [`ComparisonRecorder`], which it
+/// needs to call regularly, in the same way as
+/// [`BacktestRecorder`](crate::backtest::recorder::BacktestRecorder).
+///
+/// **Example**
+/// ```ignore
+/// let reports = QueueModelComparison::new()
+///     .add("risk_adverse", build_backtest(RiskAdverseQueueModel::new()))
+///     .add("power3", build_backtest(ProbQueueModel::new(PowerProbQueueFunc::new(3.0))))
+///     .run(|hbt, recorder| {
+///         while hbt.elapse(100_000_000)? {
+///             recorder.record(hbt)?;
+///             // Updates quotes.
+///         }
+///         Ok::<_, BacktestError>(())
+///     })?;
+/// for report in reports {
+///     println!("{report:?}");
+/// }
+/// ```
+pub struct QueueModelComparison<I> {
+    runs: Vec<(String, I)>,
+}
+
+impl<I> Default for QueueModelComparison<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> QueueModelComparison<I> {
+    /// Constructs an instance of `QueueModelComparison`.
+    pub fn new() -> Self {
+        Self { runs: Vec::new() }
+    }
+
+    /// Adds a run with the `name` and the bot built with the queue model to compare. The first
+    /// run added is the baseline.
+    pub fn add<N>(mut self, name: N, hbt: I) -> Self
+    where
+        N: Into<String>,
+    {
+        self.runs.push((name.into(), hbt));
+        self
+    }
+
+    /// Runs the `strategy` on each bot in the order added and returns the reports.
+    pub fn run<MD, F, E>(self, mut strategy: F) -> Result<Vec<QueueModelReport>, E>
+    where
+        MD: MarketDepth,
+        I: Bot<MD>,
+        E: From<I::Error> + From<BacktestError>,
+        F: FnMut(&mut I, &mut ComparisonRecorder) -> Result<(), E>,
+    {
+        let mut reports: Vec<QueueModelReport> = Vec::with_capacity(self.runs.len());
+        for (name, mut hbt) in self.runs {
+            let mut recorder = ComparisonRecorder::default();
+            strategy(&mut hbt, &mut recorder)?;
+            recorder.record(&mut hbt)?;
+            hbt.close()?;
+
+            let (base_fills, base_pnl) = reports
+                .first()
+                .map(|base| (base.num_fills, base.pnl))
+                .unwrap_or((recorder.num_fills, recorder.pnl));
+            reports.push(QueueModelReport {
+                name,
+                num_fills: recorder.num_fills,
+                mean_fill_time: (recorder.fill_time_count > 0)
+                    .then(|| recorder.fill_time_sum as f64 / recorder.fill_time_count as f64),
+                pnl: recorder.pnl,
+                num_fills_delta: recorder.num_fills - base_fills,
+                pnl_delta: recorder.pnl - base_pnl,
+            });
+        }
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backtest::{
+            comparison::QueueModelComparison,
+            models::{PowerProbQueueFunc, ProbQueueModel, QueueModel, RiskAdverseQueueModel},
+            proc::test_utils::{asset_builder_with_queue_model, backtest, event},
+            reader::Data,
+            Backtest,
+            BacktestError,
+        },
+        depth::HashMapMarketDepth,
+        types::{
+            Bot,
+            OrdType,
+            Recorder,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+            TRADE_EVENT,
+        },
+    };
+
+    fn build<QM>(queue_model: QM) -> Backtest<HashMapMarketDepth>
+    where
+        QM: QueueModel<HashMapMarketDepth> + 'static,
+    {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+            // 10 joins behind the order and then 8 is cancelled.
+            event(DEPTH_EVENT | BUY_EVENT, 50, 100.0, 20.0),
+            event(DEPTH_EVENT | BUY_EVENT, 100, 100.0, 12.0),
+            event(TRADE_EVENT | SELL_EVENT, 200, 100.0, 9.0),
+            event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 3.0),
+        ]);
+        backtest(asset_builder_with_queue_model(data, 1.0, 1.0, queue_model))
+    }
+
+    #[test]
+    fn test_queue_model_comparison() {
+        let reports = QueueModelComparison::new()
+            .add("risk_adverse", build(RiskAdverseQueueModel::new()))
+            .add(
+                "power3",
+                build(ProbQueueModel::new(PowerProbQueueFunc::new(3.0))),
+            )
+            .run(|hbt, recorder| {
+                hbt.elapse(9)?;
+                hbt.submit_buy_order(0, 1, 100.0, 1.0, TimeInForce::GTC, OrdType::Limit, false)?;
+                while hbt.elapse(50)? {
+                    recorder.record(hbt)?;
+                }
+                Ok::<_, BacktestError>(())
+            })
+            .unwrap();
+
+        assert_eq!(reports.len(), 2);
+        // The risk-averse model doesn't advance the queue position by cancellations, so 1 remains
+        // ahead of the order after the trade.
+        assert_eq!(reports[0].name, "risk_adverse");
+        assert_eq!(reports[0].num_fills, 0);
+        assert_eq!(reports[0].mean_fill_time, None);
+        assert_eq!(reports[0].pnl, 0.0);
+
+        // Half of the cancellations occur ahead of the order, which is filled by the trade.
+        assert_eq!(reports[1].name, "power3");
+        assert_eq!(reports[1].num_fills, 1);
+        assert_eq!(reports[1].mean_fill_time, Some(190.0));
+        assert_eq!(reports[1].num_fills_delta, 1);
+        assert_eq!(reports[1].pnl_delta, 0.5);
+    }
+}
@@ -2,19 +2,77 @@ use std::io::Error as IoError;
 
 pub use backtest::*;
 pub use reader::DataSource;
-use reader::{Cache, Reader};
+use reader::{
+    merge_events,
+    ActivityFeedLatency,
+    Cache,
+    CorrelatedFeedLatency,
+    DepthThinning,
+    EventValidator,
+    FeedDelay,
+    Instrument,
+    Reader,
+    TimeScale,
+    TimeWindow,
+    ValidationMode,
+};
 use thiserror::Error;
 
 use crate::{
     backtest::{
         assettype::AssetType,
-        models::{LatencyModel, QueueModel},
+        models::{
+            derive_seed,
+            ActivityScaling,
+            AdverseFillFilter,
+            FeeModel,
+            FlatFee,
+            LatencyCorrelation,
+            LatencyModel,
+            QueueModel,
+            TakerImpact,
+        },
         order::OrderBus,
-        proc::{Local, LocalProcessor, NoPartialFillExchange, PartialFillExchange, Processor},
+        proc::{
+            AmendRule,
+            BarExchange,
+            BookCrossRule,
+            BorrowCost,
+            ClockSkew,
+            ConstraintViolation,
+            ContractExpiry,
+            FaultInjector,
+            FundingSchedule,
+            IntraBarPath,
+            L1Local,
+            L1NoPartialFillExchange,
+            Local,
+            LocalProcessor,
+            Margin,
+            NoPartialFillExchange,
+            OrderConstraints,
+            PartialFillExchange,
+            PostOnlyRule,
+            PriceBand,
+            Processor,
+            RateLimit,
+            ReduceOnlyRule,
+            SendThrottle,
+            StopTrigger,
+            TradingHalt,
+        },
         state::State,
     },
-    depth::{L2MarketDepth, MarketDepth},
-    types::{BuildError, Event},
+    depth::{L1MarketDepth, L2MarketDepth, MarketDepth},
+    types::{BuildError, Event, PositionMode, TimeUnit},
+};
+#[cfg(any(feature = "unstable_l3", doc))]
+use crate::{
+    backtest::{
+        models::L3QueueModel,
+        proc::{L3Local, L3NoPartialFillExchange},
+    },
+    depth::L3MarketDepth,
 };
 
 /// Provides asset types.
@@ -36,6 +94,9 @@ pub mod state;
 /// Recorder for a bot's trading statistics.
 pub mod recorder;
 
+/// Harness comparing queue models on the same strategy and data.
+pub mod comparison;
+
 mod evs;
 pub mod reader;
 
@@ -52,6 +113,10 @@ pub enum BacktestError {
     InvalidOrderRequest,
     #[error("order status is invalid to proceed the request")]
     InvalidOrderStatus,
+    #[error("order request violates the instrument constraint: {0}")]
+    ConstraintViolation(#[from] ConstraintViolation),
+    #[error("order request is dropped as the send queue is full")]
+    SendQueueFull,
     #[error("end of data")]
     EndOfData,
     #[error("data error: {0:?}")]
@@ -87,6 +152,7 @@ impl<L, E> Asset<L, E> {
 }
 
 /// Exchange model kind.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ExchangeKind {
     /// Uses [NoPartialFillExchange](`NoPartialFillExchange`).
     NoPartialFillExchange,
@@ -94,6 +160,100 @@ pub enum ExchangeKind {
     PartialFillExchange,
 }
 
+type FeeModelBuilder = Box<dyn Fn() -> Box<dyn FeeModel>>;
+
+/// Creates the fee model set by a builder's `fee_model`, or the [`FlatFee`] model with the maker
+/// and taker fee rates if none is set.
+fn create_fee_model(
+    fee_model_builder: Option<&FeeModelBuilder>,
+    maker_fee: f64,
+    taker_fee: f64,
+) -> Box<dyn FeeModel> {
+    match fee_model_builder {
+        Some(builder) => builder(),
+        None => Box::new(FlatFee::new(maker_fee, taker_fee)),
+    }
+}
+
+/// Builds an exchange processor from the [`AssetBuilder`], evaluating to the processor or
+/// returning the [`BuildError`]. [`NoPartialFillExchange`] and [`PartialFillExchange`] have the
+/// same constructor and setters but don't share a trait for them.
+macro_rules! build_exchange {
+    (
+        $exchange:ident,
+        $builder:ident,
+        $reader:expr,
+        $ob_exch_to_local:expr,
+        $ob_local_to_exch:expr
+    ) => {{
+        let create_depth = $builder
+            .depth_builder
+            .as_ref()
+            .ok_or(BuildError::BuilderIncomplete("depth"))?;
+        let state = $builder.build_state()?;
+        let mut order_latency = $builder
+            .latency_model
+            .clone()
+            .ok_or(BuildError::BuilderIncomplete("order_latency"))?;
+        let mut queue_model = $builder
+            .queue_model
+            .ok_or(BuildError::BuilderIncomplete("queue_model"))?;
+        if let Some(seed) = $builder.seed {
+            order_latency.set_seed(derive_seed(seed, 1));
+            queue_model.set_seed(derive_seed(seed, 2));
+        }
+
+        let mut exch = $exchange::new(
+            $reader,
+            create_depth(),
+            state,
+            order_latency,
+            queue_model,
+            $ob_exch_to_local,
+            $ob_local_to_exch,
+        );
+        if let Some(threshold) = $builder.feed_gap_threshold {
+            exch.set_feed_gap_threshold(threshold);
+        }
+        if let Some(mut filter) = $builder.fill_filter {
+            if let Some(seed) = $builder.seed {
+                filter.set_seed(derive_seed(seed, 4));
+            }
+            exch.set_fill_filter(filter);
+        }
+        if let Some(rate_limit) = $builder.rate_limit {
+            exch.set_rate_limit(rate_limit);
+        }
+        if let Some(mut faults) = $builder.faults {
+            if let Some(seed) = $builder.seed {
+                faults.set_seed(derive_seed(seed, 5));
+            }
+            exch.set_fault_injector(faults);
+        }
+        if let Some(impact) = $builder.impact {
+            exch.set_market_impact(impact);
+        }
+        if let Some(price_band) = $builder.price_band {
+            exch.set_price_band(price_band);
+        }
+        if let Some(halt) = $builder.halt {
+            exch.set_trading_halt(halt);
+        }
+        if let Some(contract_expiry) = $builder.contract_expiry {
+            exch.set_contract_expiry(contract_expiry);
+        }
+        if let Some(stop_trigger) = $builder.stop_trigger {
+            exch.set_stop_trigger(stop_trigger);
+        }
+        exch.set_amend_rule($builder.amend_rule);
+        exch.set_reduce_only_rule($builder.reduce_only_rule);
+        exch.set_post_only_rule($builder.post_only_rule);
+        exch.set_book_cross_rule($builder.book_cross_rule);
+        exch.set_excluded_trades($builder.excluded_trades);
+        exch
+    }};
+}
+
 /// A builder for `Asset`.
 pub struct AssetBuilder<LM, AT, QM, MD> {
     latency_model: Option<LM>,
@@ -103,8 +263,38 @@ pub struct AssetBuilder<LM, AT, QM, MD> {
     reader: Reader<Event>,
     maker_fee: f64,
     taker_fee: f64,
+    fee_model_builder: Option<FeeModelBuilder>,
     exch_kind: ExchangeKind,
     trade_len: usize,
+    begin_ts: i64,
+    end_ts: i64,
+    feed_gap_threshold: Option<i64>,
+    order_constraints: Option<OrderConstraints>,
+    clock_skew: Option<ClockSkew>,
+    send_throttle: Option<SendThrottle>,
+    thin_interval: Option<i64>,
+    feed_latency_scaling: Option<ActivityScaling>,
+    feed_latency_correlation: Option<LatencyCorrelation>,
+    feed_delay: Option<FeedDelay>,
+    fill_filter: Option<AdverseFillFilter>,
+    rate_limit: Option<RateLimit>,
+    faults: Option<FaultInjector>,
+    impact: Option<TakerImpact>,
+    price_band: Option<PriceBand>,
+    halt: Option<TradingHalt>,
+    contract_expiry: Option<ContractExpiry>,
+    stop_trigger: Option<StopTrigger>,
+    excluded_trades: u64,
+    amend_rule: AmendRule,
+    reduce_only_rule: ReduceOnlyRule,
+    post_only_rule: PostOnlyRule,
+    book_cross_rule: BookCrossRule,
+    funding_schedule: Option<FundingSchedule>,
+    margin: Option<Margin>,
+    position_mode: PositionMode,
+    borrow_cost: Option<BorrowCost>,
+    seed: Option<u64>,
+    time_unit: TimeUnit,
 }
 
 impl<LM, AT, QM, MD> AssetBuilder<LM, AT, QM, MD>
@@ -127,8 +317,38 @@ where
             reader,
             maker_fee: 0.0,
             taker_fee: 0.0,
+            fee_model_builder: None,
             exch_kind: ExchangeKind::NoPartialFillExchange,
             trade_len: 0,
+            begin_ts: i64::MIN,
+            end_ts: i64::MAX,
+            feed_gap_threshold: None,
+            order_constraints: None,
+            clock_skew: None,
+            send_throttle: None,
+            thin_interval: None,
+            feed_latency_scaling: None,
+            feed_latency_correlation: None,
+            feed_delay: None,
+            fill_filter: None,
+            rate_limit: None,
+            faults: None,
+            impact: None,
+            price_band: None,
+            halt: None,
+            contract_expiry: None,
+            stop_trigger: None,
+            excluded_trades: 0,
+            amend_rule: Default::default(),
+            reduce_only_rule: Default::default(),
+            post_only_rule: Default::default(),
+            book_cross_rule: Default::default(),
+            funding_schedule: None,
+            margin: None,
+            position_mode: Default::default(),
+            borrow_cost: None,
+            seed: None,
+            time_unit: TimeUnit::Nanosecond,
         }
     }
 
@@ -142,11 +362,83 @@ where
                 DataSource::Data(data) => {
                     self.reader.add_data(data);
                 }
+                DataSource::Url(url) => {
+                    self.reader.add_url(url);
+                }
+                DataSource::Query(query) => {
+                    self.reader.add_query(query);
+                }
+                DataSource::Iterator(generator) => {
+                    self.reader.add_generator(generator);
+                }
             }
         }
         self
     }
 
+    /// Sets the feed data of a continuous futures asset stitched from the contracts, which roll
+    /// from one to the next at their roll timestamps. If `back_adjust` is `true`, the prices of
+    /// the earlier contracts are back-adjusted by the price adjustments at the rolls. The rolls are
+    /// available through [`Bot::rolls`](crate::types::Bot::rolls). See
+    /// [`Contract`](reader::Contract).
+    pub fn contracts(mut self, contracts: Vec<reader::Contract>, back_adjust: bool) -> Self {
+        self.reader.add_contracts(contracts, back_adjust);
+        self
+    }
+
+    /// Sets the feed data from the data files listed in the [`Manifest`](reader::Manifest). The
+    /// integrity and the ordering of the files are verified when the asset is built, so that a
+    /// corrupt or out-of-order file list fails before the backtest starts. Can be called
+    /// repeatedly in the order you want to read. Requires the `manifest` feature.
+    #[cfg(feature = "manifest")]
+    pub fn manifest(mut self, manifest: reader::Manifest) -> Self {
+        self.reader.add_manifest(manifest);
+        self
+    }
+
+    /// Sets the feed data from sources that overlap in time, such as a trade file and a depth file
+    /// delivered separately, which are merged by timestamp when read, removing the need to merge
+    /// them in advance. Each source needs to be correctly ordered on its own. Can be called
+    /// repeatedly, for example, once per day, in the order you want to read. See
+    /// [`merge_events`](reader::merge_events).
+    pub fn merged_data(mut self, data: Vec<DataSource<Event>>) -> Self {
+        self.reader.add_merged(data, merge_events);
+        self
+    }
+
+    /// Selects the instrument to read from feed data files that contain multiple instruments, by
+    /// its symbol or instrument ID. See [`Instrument`].
+    pub fn instrument<I: Into<Instrument>>(mut self, instrument: I) -> Self {
+        self.reader.set_instrument(instrument.into());
+        self
+    }
+
+    /// Sets the memory limit in bytes for the feed data kept in the cache. Consumed data is kept
+    /// within the limit, and the least recently used data is evicted once the limit is exceeded,
+    /// to be reloaded if it is needed again. Without the limit, data is dropped as soon as both
+    /// the local and exchange processors have consumed it.
+    pub fn cache_memory_limit(mut self, memory_limit: usize) -> Self {
+        self.reader.set_cache_memory_limit(Some(memory_limit));
+        self
+    }
+
+    /// Reads `.npy` and `.npz` feed data files lazily in chunks of `chunk_size` bytes, such as
+    /// 64MB, on demand rather than reading each file entirely, which avoids long stalls and large
+    /// peak memory usage with large files. See
+    /// [`Reader::set_chunk_size`](crate::backtest::reader::Reader::set_chunk_size).
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.reader.set_chunk_size(Some(chunk_size));
+        self
+    }
+
+    /// Enables validation of the feed data as it is read. The data is checked for non-monotonic
+    /// timestamps, negative quantities, zero prices, and local timestamps earlier than exchange
+    /// timestamps. See [`EventValidator`].
+    pub fn validation(mut self, mode: ValidationMode) -> Self {
+        self.reader.set_validator(EventValidator::new(mode));
+        self
+    }
+
     /// Sets a latency model.
     pub fn latency_model(self, latency_model: LM) -> Self {
         Self {
@@ -163,16 +455,31 @@ where
         }
     }
 
-    /// Sets the maker fee.
+    /// Sets the maker fee rate of the [`FlatFee`] model, which is used unless a fee model is set by
+    /// [`fee_model`](Self::fee_model).
     pub fn maker_fee(self, maker_fee: f64) -> Self {
         Self { maker_fee, ..self }
     }
 
-    /// Sets the taker fee.
+    /// Sets the taker fee rate of the [`FlatFee`] model, which is used unless a fee model is set by
+    /// [`fee_model`](Self::fee_model).
     pub fn taker_fee(self, taker_fee: f64) -> Self {
         Self { taker_fee, ..self }
     }
 
+    /// Sets a fee model, which takes precedence over the flat fee rates set by
+    /// [`maker_fee`](Self::maker_fee) and [`taker_fee`](Self::taker_fee). Both the local and the
+    /// exchange are given their own copy of the model.
+    pub fn fee_model<FM>(self, fee_model: FM) -> Self
+    where
+        FM: FeeModel + Clone + 'static,
+    {
+        Self {
+            fee_model_builder: Some(Box::new(move || Box::new(fee_model.clone()))),
+            ..self
+        }
+    }
+
     /// Sets a queue model.
     pub fn queue_model(self, queue_model: QM) -> Self {
         Self {
@@ -203,74 +510,417 @@ where
         Self { trade_len, ..self }
     }
 
-    /// Builds an `Asset`.
-    pub fn build(self) -> Result<Asset<dyn LocalProcessor<MD, Event>, dyn Processor>, BuildError> {
-        let ob_local_to_exch = OrderBus::new();
-        let ob_exch_to_local = OrderBus::new();
+    /// Sets the timestamp from which the feed data is replayed, inclusive. Events before it are
+    /// skipped even if the supplied data covers a longer period. The default value is
+    /// [`i64::MIN`].
+    pub fn begin_ts(self, begin_ts: i64) -> Self {
+        Self { begin_ts, ..self }
+    }
+
+    /// Sets the timestamp until which the feed data is replayed, exclusive. Events at or after it
+    /// are skipped even if the supplied data covers a longer period. The default value is
+    /// [`i64::MAX`].
+    pub fn end_ts(self, end_ts: i64) -> Self {
+        Self { end_ts, ..self }
+    }
+
+    /// Enables feed gap detection. If no event arrives for longer than the `threshold`, the market
+    /// depth is considered stale, and both the local and exchange processors skip depth events
+    /// until the next snapshot, from which the market depth is rebuilt. The number of gaps is
+    /// available through [`Bot::feed_gap_count`](crate::types::Bot::feed_gap_count).
+    pub fn feed_gap_threshold(self, threshold: i64) -> Self {
+        Self {
+            feed_gap_threshold: Some(threshold),
+            ..self
+        }
+    }
+
+    /// Sets the instrument constraints, so that the local processor rejects the order requests that
+    /// don't conform to the tick size, the lot size, the minimum quantity, or the minimum notional,
+    /// as the venue does, rather than rounding them. See [`OrderConstraints`].
+    pub fn order_constraints(self, constraints: OrderConstraints) -> Self {
+        Self {
+            order_constraints: Some(constraints),
+            ..self
+        }
+    }
+
+    /// Sets the skew of the exchange clock against the local clock, which shifts the exchange
+    /// timestamps of the order responses received by the local processor, so that a strategy
+    /// estimating the latency from them can be tested against a skewed clock. See [`ClockSkew`].
+    pub fn clock_skew(self, clock_skew: ClockSkew) -> Self {
+        Self {
+            clock_skew: Some(clock_skew),
+            ..self
+        }
+    }
+
+    /// Sets the send throttle, which serializes bursts of order requests sent by the strategy, so
+    /// that they depart from the local spaced apart, or are dropped if the send queue is full.
+    /// See [`SendThrottle`].
+    pub fn send_throttle(self, send_throttle: SendThrottle) -> Self {
+        Self {
+            send_throttle: Some(send_throttle),
+            ..self
+        }
+    }
+
+    /// Sets the fill filter of the exchange processor, which marks a fraction of the fills
+    /// triggered by the market trading through a resting order's price as not occurring, to
+    /// counteract the optimistic bias of L2 fill simulation during sweeps. See
+    /// [`AdverseFillFilter`].
+    pub fn fill_filter(self, filter: AdverseFillFilter) -> Self {
+        Self {
+            fill_filter: Some(filter),
+            ..self
+        }
+    }
+
+    /// Sets the rule that determines whether an order modified in place through
+    /// [`Bot::modify`](crate::types::Bot::modify) keeps its queue position at the exchange, which
+    /// differs by venue. The default value is [`AmendRule::KeepOnSizeReduction`].
+    pub fn amend_rule(self, amend_rule: AmendRule) -> Self {
+        Self { amend_rule, ..self }
+    }
+
+    /// Sets the rule that determines how the exchange handles a reduce-only order that would
+    /// increase the position, which differs by venue. The default value is
+    /// [`ReduceOnlyRule::Reject`].
+    pub fn reduce_only_rule(self, reduce_only_rule: ReduceOnlyRule) -> Self {
+        Self {
+            reduce_only_rule,
+            ..self
+        }
+    }
+
+    /// Sets the rule that determines how the exchange handles a post-only order that would take
+    /// liquidity, which differs by venue. The default value is [`PostOnlyRule::Reject`].
+    pub fn post_only_rule(self, post_only_rule: PostOnlyRule) -> Self {
+        Self {
+            post_only_rule,
+            ..self
+        }
+    }
+
+    /// Sets the rule that determines how the exchange handles a resting order that the best price
+    /// on the opposite side crosses without a trade, which calls for a more conservative
+    /// assumption on lower-quality data. The default value is [`BookCrossRule::FillAtOrderPrice`].
+    pub fn book_cross_rule(self, book_cross_rule: BookCrossRule) -> Self {
+        Self {
+            book_cross_rule,
+            ..self
+        }
+    }
+
+    /// Sets the [`FundingSchedule`] by which the funding payments of a perpetual contract are
+    /// settled against the position, for data that doesn't carry
+    /// [`FUNDING_EVENT`](crate::types::FUNDING_EVENT)s.
+    pub fn funding_schedule(self, schedule: FundingSchedule) -> Self {
+        Self {
+            funding_schedule: Some(schedule),
+            ..self
+        }
+    }
+
+    /// Sets the rate limit on order requests at the exchange, which rejects or delays the requests
+    /// exceeding it. See [`RateLimit`].
+    pub fn rate_limit(self, rate_limit: RateLimit) -> Self {
+        Self {
+            rate_limit: Some(rate_limit),
+            ..self
+        }
+    }
+
+    /// Sets the fault injector that randomly rejects order requests, drops acknowledgements, and
+    /// duplicates execution reports at the exchange. See [`FaultInjector`].
+    pub fn fault_injector(self, faults: FaultInjector) -> Self {
+        Self {
+            faults: Some(faults),
+            ..self
+        }
+    }
+
+    /// Sets the market impact model that adjusts the execution price of liquidity-taking orders at
+    /// the exchange for their size and latency. See [`TakerImpact`].
+    pub fn market_impact(self, impact: TakerImpact) -> Self {
+        Self {
+            impact: Some(impact),
+            ..self
+        }
+    }
+
+    /// Sets the [`PriceBand`] that rejects orders outside it and halts matching at the exchange
+    /// while the market is locked at a limit price. The limits can also be updated by
+    /// [`PRICE_BAND_EVENT`](crate::types::PRICE_BAND_EVENT)s in the data.
+    pub fn price_band(self, price_band: PriceBand) -> Self {
+        Self {
+            price_band: Some(price_band),
+            ..self
+        }
+    }
+
+    /// Sets the [`TradingHalt`] that configures the halt periods, during which the exchange rejects
+    /// new orders and pauses matching, in addition to the halts by
+    /// [`HALT_EVENT`](crate::types::HALT_EVENT)s in the data.
+    pub fn trading_halt(self, halt: TradingHalt) -> Self {
+        Self {
+            halt: Some(halt),
+            ..self
+        }
+    }
+
+    /// Sets the [`ContractExpiry`] of a dated contract, upon which the exchange cancels all open
+    /// orders and settles the position at the settlement price, after which the asset is inactive.
+    pub fn contract_expiry(self, contract_expiry: ContractExpiry) -> Self {
+        Self {
+            contract_expiry: Some(contract_expiry),
+            ..self
+        }
+    }
+
+    /// Sets the [`StopTrigger`] that configures the reference price by which the stop orders are
+    /// triggered at the exchange, unless an order specifies its own. By default, the stop orders
+    /// are triggered by the last price.
+    pub fn stop_trigger(self, stop_trigger: StopTrigger) -> Self {
+        Self {
+            stop_trigger: Some(stop_trigger),
+            ..self
+        }
+    }
+
+    /// Excludes the trades flagged with any of the `flags`, such as
+    /// [`LIQUIDATION_TRADE_FLAG`](crate::types::LIQUIDATION_TRADE_FLAG), from the fill and queue
+    /// position simulation. The excluded trades are still delivered to the strategy through
+    /// [`Bot::trade`](crate::types::Bot::trade). By default, no trades are excluded.
+    pub fn excluded_trades(self, flags: u64) -> Self {
+        Self {
+            excluded_trades: flags,
+            ..self
+        }
+    }
+
+    /// Sets the [`Margin`] that determines the margin requirements of a leveraged position. The
+    /// exchange rejects orders the margin balance doesn't cover and liquidates the position when
+    /// the maintenance margin is breached. By default, the account has infinite margin.
+    pub fn margin(self, margin: Margin) -> Self {
+        Self {
+            margin: Some(margin),
+            ..self
+        }
+    }
+
+    /// Sets the [`PositionMode`] of the account. In [`PositionMode::Hedge`], the long and short
+    /// positions are held separately, and a reduce-only order closes the opposite leg, as a
+    /// position-side order does on the venues. The default value is [`PositionMode::OneWay`].
+    pub fn position_mode(self, position_mode: PositionMode) -> Self {
+        Self {
+            position_mode,
+            ..self
+        }
+    }
+
+    /// Sets the [`BorrowCost`] of a spot or margin asset, which charges the borrow interest on the
+    /// short position, or makes the exchange reject short sales if the asset is not shortable. By
+    /// default, the short position is free to carry.
+    pub fn borrow_cost(self, borrow_cost: BorrowCost) -> Self {
+        Self {
+            borrow_cost: Some(borrow_cost),
+            ..self
+        }
+    }
+
+    /// Sets the time unit of the feed data's timestamps. The timestamps are converted into
+    /// nanoseconds, the time unit of the engine, as they are read, so that data recorded in
+    /// microseconds or milliseconds doesn't need to be rewritten. Timestamps and durations given to
+    /// the engine, such as [`begin_ts`](Self::begin_ts), [`elapse`](crate::types::Bot::elapse),
+    /// and the latency models, are always in nanoseconds. The default value is
+    /// [`TimeUnit::Nanosecond`].
+    pub fn time_unit(self, time_unit: TimeUnit) -> Self {
+        Self { time_unit, ..self }
+    }
+
+    /// Uses the depth data thinned to at most one update per price level per `interval`, for fast
+    /// but coarse experiments. Trades and other events are kept as they are. See
+    /// [`DepthThinning`](crate::backtest::reader::DepthThinning).
+    pub fn thin_depth(self, interval: i64) -> Self {
+        Self {
+            thin_interval: Some(interval),
+            ..self
+        }
+    }
+
+    /// Scales the feed latency of the data with the recent market activity. To scale the order
+    /// latency as well, use [`ActivityLatency`](crate::backtest::models::ActivityLatency). See
+    /// [`ActivityFeedLatency`](crate::backtest::reader::ActivityFeedLatency).
+    pub fn feed_latency_scaling(self, scaling: ActivityScaling) -> Self {
+        Self {
+            feed_latency_scaling: Some(scaling),
+            ..self
+        }
+    }
+
+    /// Applies the latency factor that feed latency shares with order latency to the feed latency
+    /// of the data. Use the same [`LatencyCorrelation`] to construct the latency model with
+    /// [`LatencyCorrelation::order_latency`]. See
+    /// [`CorrelatedFeedLatency`](crate::backtest::reader::CorrelatedFeedLatency).
+    pub fn correlated_feed_latency(self, correlation: LatencyCorrelation) -> Self {
+        Self {
+            feed_latency_correlation: Some(correlation),
+            ..self
+        }
+    }
+
+    /// Adds an extra delay on top of the recorded local timestamps of the feed data, which answers
+    /// questions like what if the colocation were 2ms farther away from the exchange, without
+    /// regenerating the data files. See [`FeedDelay`](crate::backtest::reader::FeedDelay).
+    pub fn feed_delay(self, delay: FeedDelay) -> Self {
+        Self {
+            feed_delay: Some(delay),
+            ..self
+        }
+    }
+
+    /// Seeds the stochastic latency model, queue model, and feed delay, so that two runs with the
+    /// same seed give identical results. The models of the local and exchange processors are seeded with
+    /// different seeds derived from it. Use different seeds for different assets to draw
+    /// independent random numbers. See [`LatencyModel::set_seed`] and [`QueueModel::set_seed`].
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+
+    fn build_reader(&self) -> Result<Reader<Event>, BuildError> {
+        #[cfg(feature = "manifest")]
+        self.reader
+            .verify_manifests()
+            .map_err(|error| BuildError::Error(error.into()))?;
+        let mut reader = self.reader.clone();
+        // The timestamps need to be converted first, since the other filters work in nanoseconds.
+        if self.time_unit != TimeUnit::Nanosecond {
+            reader.add_filter(TimeScale::new(self.time_unit));
+        }
+        // The feed latency needs to be scaled before the window is applied to the local
+        // timestamps.
+        if let Some(scaling) = self.feed_latency_scaling {
+            reader.add_filter(ActivityFeedLatency::new(scaling));
+        }
+        if let Some(correlation) = self.feed_latency_correlation {
+            reader.add_filter(CorrelatedFeedLatency::new(correlation));
+        }
+        // The extra delay is added on top of the recorded, possibly scaled, feed latency.
+        if let Some(delay) = &self.feed_delay {
+            let delay = match self.seed {
+                Some(seed) => delay.clone().seed(derive_seed(seed, 3)),
+                None => delay.clone(),
+            };
+            reader.add_filter(delay);
+        }
+        if self.begin_ts != i64::MIN || self.end_ts != i64::MAX {
+            reader.add_filter(TimeWindow::new(self.begin_ts, self.end_ts));
+        }
+        if let Some(interval) = self.thin_interval {
+            reader.add_filter(DepthThinning::new(interval));
+        }
+        Ok(reader)
+    }
+
+    fn build_state(&self) -> Result<State<AT>, BuildError> {
+        let asset_type = self
+            .asset_type
+            .clone()
+            .ok_or(BuildError::BuilderIncomplete("asset_type"))?;
+        let fee_model = create_fee_model(
+            self.fee_model_builder.as_ref(),
+            self.maker_fee,
+            self.taker_fee,
+        );
+        Ok(State::new(asset_type, fee_model)
+            .with_funding_schedule(self.funding_schedule.clone())
+            .with_margin(self.margin.clone())
+            .with_position_mode(self.position_mode)
+            .with_borrow_cost(self.borrow_cost.clone()))
+    }
 
+    fn build_local(
+        &self,
+        reader: Reader<Event>,
+        trade_len: usize,
+        ob_local_to_exch: OrderBus,
+        ob_exch_to_local: OrderBus,
+    ) -> Result<Local<AT, LM, MD>, BuildError> {
         let create_depth = self
             .depth_builder
             .as_ref()
             .ok_or(BuildError::BuilderIncomplete("depth"))?;
-        let order_latency = self
+        let mut order_latency = self
             .latency_model
             .clone()
             .ok_or(BuildError::BuilderIncomplete("order_latency"))?;
-        let asset_type = self
-            .asset_type
-            .clone()
-            .ok_or(BuildError::BuilderIncomplete("asset_type"))?;
+        if let Some(seed) = self.seed {
+            order_latency.set_seed(derive_seed(seed, 0));
+        }
 
-        let local = Local::new(
-            self.reader.clone(),
+        let mut local = Local::new(
+            reader,
             create_depth(),
-            State::new(asset_type, self.maker_fee, self.taker_fee),
+            self.build_state()?,
             order_latency,
+            trade_len,
+            ob_local_to_exch,
+            ob_exch_to_local,
+        );
+        if let Some(threshold) = self.feed_gap_threshold {
+            local.set_feed_gap_threshold(threshold);
+        }
+        if let Some(constraints) = self.order_constraints.clone() {
+            local.set_order_constraints(constraints);
+        }
+        if let Some(clock_skew) = self.clock_skew.clone() {
+            local.set_clock_skew(clock_skew);
+        }
+        if let Some(send_throttle) = self.send_throttle.clone() {
+            local.set_send_throttle(send_throttle);
+        }
+        Ok(local)
+    }
+
+    /// Builds an `Asset`.
+    pub fn build(self) -> Result<Asset<dyn LocalProcessor<MD, Event>, dyn Processor>, BuildError> {
+        let ob_local_to_exch = OrderBus::new();
+        let ob_exch_to_local = OrderBus::new();
+        let reader = self.build_reader()?;
+
+        let local = self.build_local(
+            reader.clone(),
             self.trade_len,
             ob_local_to_exch.clone(),
             ob_exch_to_local.clone(),
-        );
-
-        let order_latency = self
-            .latency_model
-            .clone()
-            .ok_or(BuildError::BuilderIncomplete("order_latency"))?;
-        let queue_model = self
-            .queue_model
-            .ok_or(BuildError::BuilderIncomplete("queue_model"))?;
-        let asset_type = self
-            .asset_type
-            .clone()
-            .ok_or(BuildError::BuilderIncomplete("asset_type"))?;
+        )?;
 
         match self.exch_kind {
             ExchangeKind::NoPartialFillExchange => {
-                let exch = NoPartialFillExchange::new(
-                    self.reader.clone(),
-                    create_depth(),
-                    State::new(asset_type, self.maker_fee, self.taker_fee),
-                    order_latency,
-                    queue_model,
+                let exch = build_exchange!(
+                    NoPartialFillExchange,
+                    self,
+                    reader,
                     ob_exch_to_local,
-                    ob_local_to_exch,
+                    ob_local_to_exch
                 );
-
                 Ok(Asset {
                     local: Box::new(local),
                     exch: Box::new(exch),
                 })
             }
             ExchangeKind::PartialFillExchange => {
-                let exch = PartialFillExchange::new(
-                    self.reader.clone(),
-                    create_depth(),
-                    State::new(asset_type, self.maker_fee, self.taker_fee),
-                    order_latency,
-                    queue_model,
+                let exch = build_exchange!(
+                    PartialFillExchange,
+                    self,
+                    reader,
                     ob_exch_to_local,
-                    ob_local_to_exch,
+                    ob_local_to_exch
                 );
-
                 Ok(Asset {
                     local: Box::new(local),
                     exch: Box::new(exch),
@@ -286,45 +936,505 @@ where
     ) -> Result<Asset<Local<AT, LM, MD>, NoPartialFillExchange<AT, LM, QM, MD>>, BuildError> {
         let ob_local_to_exch = OrderBus::new();
         let ob_exch_to_local = OrderBus::new();
+        let reader = self.build_reader()?;
+
+        let local = self.build_local(
+            reader.clone(),
+            1000,
+            ob_local_to_exch.clone(),
+            ob_exch_to_local.clone(),
+        )?;
+        let exch = build_exchange!(
+            NoPartialFillExchange,
+            self,
+            reader,
+            ob_exch_to_local,
+            ob_local_to_exch
+        );
+
+        Ok(Asset {
+            local: Box::new(local),
+            exch: Box::new(exch),
+        })
+    }
+}
+
+/// A builder for `Asset` with Level1 feed data consisting of the best bid and offer and the trades
+/// only, which uses [`L1Local`] and [`L1NoPartialFillExchange`]. Without the market depth, the
+/// queue position cannot be tracked, so a resting order is filled only when the market trades
+/// through its price.
+///
+/// With OHLCV bar data, [`BAR_EVENT`](crate::types::BAR_EVENT)s, set the intra-bar price path with
+/// [`L1AssetBuilder::bar_path`] to use the [`BarExchange`] instead, so that a strategy prototyped on
+/// bars runs unchanged on the tick-level data later.
+///
+/// **Example**
+/// ```ignore
+/// use hftbacktest::{
+///     backtest::{assettype::LinearAsset, models::ConstantLatency, DataSource, L1AssetBuilder},
+///     depth::BBOMarketDepth,
+/// };
+///
+/// let asset = L1AssetBuilder::new()
+///     .data(vec![DataSource::File("bbo_20240809.npz".to_string())])
+///     .latency_model(ConstantLatency::new(50_000, 50_000))
+///     .asset_type(LinearAsset::new(1.0))
+///     .depth(|| BBOMarketDepth::new(0.1, 0.001))
+///     .build()
+///     .unwrap();
+/// ```
+pub struct L1AssetBuilder<LM, AT, MD> {
+    latency_model: Option<LM>,
+    asset_type: Option<AT>,
+    depth_builder: Option<Box<dyn Fn() -> MD>>,
+    reader: Reader<Event>,
+    maker_fee: f64,
+    taker_fee: f64,
+    fee_model_builder: Option<FeeModelBuilder>,
+    trade_len: usize,
+    seed: Option<u64>,
+    bar_path: Option<IntraBarPath>,
+}
+
+impl<LM, AT, MD> L1AssetBuilder<LM, AT, MD>
+where
+    AT: AssetType + Clone + 'static,
+    MD: MarketDepth + L1MarketDepth + 'static,
+    LM: LatencyModel + Clone + 'static,
+{
+    /// Constructs an instance of `L1AssetBuilder`.
+    pub fn new() -> Self {
+        let cache = Cache::new();
+        let reader = Reader::new(cache);
+
+        Self {
+            latency_model: None,
+            asset_type: None,
+            depth_builder: None,
+            reader,
+            maker_fee: 0.0,
+            taker_fee: 0.0,
+            fee_model_builder: None,
+            trade_len: 0,
+            seed: None,
+            bar_path: None,
+        }
+    }
+
+    /// Sets the feed data.
+    pub fn data(mut self, data: Vec<DataSource<Event>>) -> Self {
+        for item in data {
+            match item {
+                DataSource::File(filename) => {
+                    self.reader.add_file(filename);
+                }
+                DataSource::Data(data) => {
+                    self.reader.add_data(data);
+                }
+                DataSource::Url(url) => {
+                    self.reader.add_url(url);
+                }
+                DataSource::Query(query) => {
+                    self.reader.add_query(query);
+                }
+                DataSource::Iterator(generator) => {
+                    self.reader.add_generator(generator);
+                }
+            }
+        }
+        self
+    }
+
+    /// Sets a latency model.
+    pub fn latency_model(self, latency_model: LM) -> Self {
+        Self {
+            latency_model: Some(latency_model),
+            ..self
+        }
+    }
+
+    /// Sets an asset type.
+    pub fn asset_type(self, asset_type: AT) -> Self {
+        Self {
+            asset_type: Some(asset_type),
+            ..self
+        }
+    }
+
+    /// Sets the maker fee rate of the [`FlatFee`] model, which is used unless a fee model is set by
+    /// [`fee_model`](Self::fee_model).
+    pub fn maker_fee(self, maker_fee: f64) -> Self {
+        Self { maker_fee, ..self }
+    }
+
+    /// Sets the taker fee rate of the [`FlatFee`] model, which is used unless a fee model is set by
+    /// [`fee_model`](Self::fee_model).
+    pub fn taker_fee(self, taker_fee: f64) -> Self {
+        Self { taker_fee, ..self }
+    }
+
+    /// Sets a fee model, which takes precedence over the flat fee rates. See
+    /// [`AssetBuilder::fee_model`].
+    pub fn fee_model<FM>(self, fee_model: FM) -> Self
+    where
+        FM: FeeModel + Clone + 'static,
+    {
+        Self {
+            fee_model_builder: Some(Box::new(move || Box::new(fee_model.clone()))),
+            ..self
+        }
+    }
+
+    /// Sets a market depth builder.
+    pub fn depth<Builder>(self, builder: Builder) -> Self
+    where
+        Builder: Fn() -> MD + 'static,
+    {
+        Self {
+            depth_builder: Some(Box::new(builder)),
+            ..self
+        }
+    }
+
+    /// Sets the length of market trades to be stored in the local processor. The default value is
+    /// `0`.
+    pub fn trade_len(self, trade_len: usize) -> Self {
+        Self { trade_len, ..self }
+    }
+
+    /// Seeds the stochastic latency model, so that two runs with the same seed give identical
+    /// results. See [`AssetBuilder::seed`].
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+
+    /// Simulates the fills from OHLCV bar data with the [`BarExchange`], assuming the given price
+    /// path within a bar. By default, the [`L1NoPartialFillExchange`] is used for the best bid and
+    /// offer data.
+    pub fn bar_path(self, bar_path: IntraBarPath) -> Self {
+        Self {
+            bar_path: Some(bar_path),
+            ..self
+        }
+    }
+
+    fn create_fee_model(&self) -> Box<dyn FeeModel> {
+        create_fee_model(
+            self.fee_model_builder.as_ref(),
+            self.maker_fee,
+            self.taker_fee,
+        )
+    }
+
+    /// Builds an `Asset`.
+    pub fn build(self) -> Result<Asset<dyn LocalProcessor<MD, Event>, dyn Processor>, BuildError> {
+        let ob_local_to_exch = OrderBus::new();
+        let ob_exch_to_local = OrderBus::new();
 
         let create_depth = self
             .depth_builder
             .as_ref()
             .ok_or(BuildError::BuilderIncomplete("depth"))?;
-        let order_latency = self
+        let mut order_latency = self
             .latency_model
             .clone()
             .ok_or(BuildError::BuilderIncomplete("order_latency"))?;
+        if let Some(seed) = self.seed {
+            order_latency.set_seed(derive_seed(seed, 0));
+        }
         let asset_type = self
             .asset_type
             .clone()
             .ok_or(BuildError::BuilderIncomplete("asset_type"))?;
 
-        let local = Local::new(
+        let local = L1Local::new(
             self.reader.clone(),
             create_depth(),
-            State::new(asset_type, self.maker_fee, self.taker_fee),
+            State::new(asset_type, self.create_fee_model()),
             order_latency,
-            1000,
+            self.trade_len,
             ob_local_to_exch.clone(),
             ob_exch_to_local.clone(),
         );
 
-        let order_latency = self
+        let mut order_latency = self
             .latency_model
             .clone()
             .ok_or(BuildError::BuilderIncomplete("order_latency"))?;
-        let queue_model = self
-            .queue_model
-            .ok_or(BuildError::BuilderIncomplete("queue_model"))?;
+        if let Some(seed) = self.seed {
+            order_latency.set_seed(derive_seed(seed, 1));
+        }
+        let asset_type = self
+            .asset_type
+            .clone()
+            .ok_or(BuildError::BuilderIncomplete("asset_type"))?;
+
+        let state = State::new(asset_type, self.create_fee_model());
+        let exch: Box<dyn Processor> = match self.bar_path {
+            Some(bar_path) => Box::new(BarExchange::new(
+                self.reader.clone(),
+                create_depth(),
+                state,
+                order_latency,
+                ob_exch_to_local,
+                ob_local_to_exch,
+                bar_path,
+            )),
+            None => Box::new(L1NoPartialFillExchange::new(
+                self.reader.clone(),
+                create_depth(),
+                state,
+                order_latency,
+                ob_exch_to_local,
+                ob_local_to_exch,
+            )),
+        };
+
+        Ok(Asset {
+            local: Box::new(local),
+            exch,
+        })
+    }
+}
+
+impl<LM, AT, MD> Default for L1AssetBuilder<LM, AT, MD>
+where
+    AT: AssetType + Clone + 'static,
+    MD: MarketDepth + L1MarketDepth + 'static,
+    LM: LatencyModel + Clone + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A builder for `Asset` with Level3 Market-By-Order feed data, which uses [`L3Local`] and
+/// [`L3NoPartialFillExchange`], so that the exact queue position of the backtest order is
+/// simulated with the [`L3QueueModel`].
+///
+/// **Example**
+/// ```ignore
+/// use hftbacktest::{
+///     backtest::{
+///         assettype::LinearAsset,
+///         models::{ConstantLatency, L3FIFOQueueModel},
+///         DataSource,
+///         L3AssetBuilder,
+///     },
+///     depth::HashMapMarketDepth,
+/// };
+///
+/// let asset = L3AssetBuilder::new()
+///     .data(vec![DataSource::File("mbo_20240809.npz".to_string())])
+///     .latency_model(ConstantLatency::new(50_000, 50_000))
+///     .asset_type(LinearAsset::new(1.0))
+///     .queue_model(L3FIFOQueueModel::new())
+///     .depth(|| HashMapMarketDepth::new(0.25, 1.0))
+///     .build()
+///     .unwrap();
+/// ```
+#[cfg(any(feature = "unstable_l3", doc))]
+pub struct L3AssetBuilder<LM, AT, QM, MD> {
+    latency_model: Option<LM>,
+    asset_type: Option<AT>,
+    queue_model: Option<QM>,
+    depth_builder: Option<Box<dyn Fn() -> MD>>,
+    reader: Reader<Event>,
+    maker_fee: f64,
+    taker_fee: f64,
+    fee_model_builder: Option<FeeModelBuilder>,
+    trade_len: usize,
+    seed: Option<u64>,
+}
+
+#[cfg(any(feature = "unstable_l3", doc))]
+impl<LM, AT, QM, MD> L3AssetBuilder<LM, AT, QM, MD>
+where
+    AT: AssetType + Clone + 'static,
+    MD: L3MarketDepth + 'static,
+    QM: L3QueueModel + 'static,
+    LM: LatencyModel + Clone + 'static,
+    BacktestError: From<<MD as L3MarketDepth>::Error> + From<<QM as L3QueueModel>::Error>,
+{
+    /// Constructs an instance of `L3AssetBuilder`.
+    pub fn new() -> Self {
+        let cache = Cache::new();
+        let reader = Reader::new(cache);
+
+        Self {
+            latency_model: None,
+            asset_type: None,
+            queue_model: None,
+            depth_builder: None,
+            reader,
+            maker_fee: 0.0,
+            taker_fee: 0.0,
+            fee_model_builder: None,
+            trade_len: 0,
+            seed: None,
+        }
+    }
+
+    /// Sets the feed data.
+    pub fn data(mut self, data: Vec<DataSource<Event>>) -> Self {
+        for item in data {
+            match item {
+                DataSource::File(filename) => {
+                    self.reader.add_file(filename);
+                }
+                DataSource::Data(data) => {
+                    self.reader.add_data(data);
+                }
+                DataSource::Url(url) => {
+                    self.reader.add_url(url);
+                }
+                DataSource::Query(query) => {
+                    self.reader.add_query(query);
+                }
+                DataSource::Iterator(generator) => {
+                    self.reader.add_generator(generator);
+                }
+            }
+        }
+        self
+    }
+
+    /// Sets a latency model.
+    pub fn latency_model(self, latency_model: LM) -> Self {
+        Self {
+            latency_model: Some(latency_model),
+            ..self
+        }
+    }
+
+    /// Sets an asset type.
+    pub fn asset_type(self, asset_type: AT) -> Self {
+        Self {
+            asset_type: Some(asset_type),
+            ..self
+        }
+    }
+
+    /// Sets the maker fee rate of the [`FlatFee`] model, which is used unless a fee model is set by
+    /// [`fee_model`](Self::fee_model).
+    pub fn maker_fee(self, maker_fee: f64) -> Self {
+        Self { maker_fee, ..self }
+    }
+
+    /// Sets the taker fee rate of the [`FlatFee`] model, which is used unless a fee model is set by
+    /// [`fee_model`](Self::fee_model).
+    pub fn taker_fee(self, taker_fee: f64) -> Self {
+        Self { taker_fee, ..self }
+    }
+
+    /// Sets a fee model, which takes precedence over the flat fee rates. See
+    /// [`AssetBuilder::fee_model`].
+    pub fn fee_model<FM>(self, fee_model: FM) -> Self
+    where
+        FM: FeeModel + Clone + 'static,
+    {
+        Self {
+            fee_model_builder: Some(Box::new(move || Box::new(fee_model.clone()))),
+            ..self
+        }
+    }
+
+    /// Sets a Level3 queue model.
+    pub fn queue_model(self, queue_model: QM) -> Self {
+        Self {
+            queue_model: Some(queue_model),
+            ..self
+        }
+    }
+
+    /// Sets a market depth builder.
+    pub fn depth<Builder>(self, builder: Builder) -> Self
+    where
+        Builder: Fn() -> MD + 'static,
+    {
+        Self {
+            depth_builder: Some(Box::new(builder)),
+            ..self
+        }
+    }
+
+    /// Sets the length of market trades to be stored in the local processor. The default value is
+    /// `0`.
+    pub fn trade_len(self, trade_len: usize) -> Self {
+        Self { trade_len, ..self }
+    }
+
+    /// Seeds the stochastic latency model, so that two runs with the same seed give identical
+    /// results. See [`AssetBuilder::seed`].
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+
+    fn create_fee_model(&self) -> Box<dyn FeeModel> {
+        create_fee_model(
+            self.fee_model_builder.as_ref(),
+            self.maker_fee,
+            self.taker_fee,
+        )
+    }
+
+    /// Builds an `Asset`.
+    pub fn build(self) -> Result<Asset<dyn LocalProcessor<MD, Event>, dyn Processor>, BuildError> {
+        let ob_local_to_exch = OrderBus::new();
+        let ob_exch_to_local = OrderBus::new();
+
+        let create_depth = self
+            .depth_builder
+            .as_ref()
+            .ok_or(BuildError::BuilderIncomplete("depth"))?;
+        let mut order_latency = self
+            .latency_model
+            .clone()
+            .ok_or(BuildError::BuilderIncomplete("order_latency"))?;
+        if let Some(seed) = self.seed {
+            order_latency.set_seed(derive_seed(seed, 0));
+        }
         let asset_type = self
             .asset_type
             .clone()
             .ok_or(BuildError::BuilderIncomplete("asset_type"))?;
-        let exch = NoPartialFillExchange::new(
+
+        let local = L3Local::new(
             self.reader.clone(),
             create_depth(),
-            State::new(asset_type, self.maker_fee, self.taker_fee),
+            State::new(asset_type, self.create_fee_model()),
+            order_latency,
+            self.trade_len,
+            ob_local_to_exch.clone(),
+            ob_exch_to_local.clone(),
+        );
+
+        let mut order_latency = self
+            .latency_model
+            .clone()
+            .ok_or(BuildError::BuilderIncomplete("order_latency"))?;
+        if let Some(seed) = self.seed {
+            order_latency.set_seed(derive_seed(seed, 1));
+        }
+        let asset_type = self
+            .asset_type
+            .clone()
+            .ok_or(BuildError::BuilderIncomplete("asset_type"))?;
+        let state = State::new(asset_type, self.create_fee_model());
+        let queue_model = self
+            .queue_model
+            .ok_or(BuildError::BuilderIncomplete("queue_model"))?;
+
+        let exch = L3NoPartialFillExchange::new(
+            self.reader.clone(),
+            create_depth(),
+            state,
             order_latency,
             queue_model,
             ob_exch_to_local,
@@ -337,3 +1447,17 @@ where
         })
     }
 }
+
+#[cfg(any(feature = "unstable_l3", doc))]
+impl<LM, AT, QM, MD> Default for L3AssetBuilder<LM, AT, QM, MD>
+where
+    AT: AssetType + Clone + 'static,
+    MD: L3MarketDepth + 'static,
+    QM: L3QueueModel + 'static,
+    LM: LatencyModel + Clone + 'static,
+    BacktestError: From<<MD as L3MarketDepth>::Error> + From<<QM as L3QueueModel>::Error>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,138 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Provides the probability that a fill doesn't occur when the market trades through the order's
+/// price.
+pub trait AdverseSelection {
+    /// Returns the probability, in the range `[0, 1]`, that the fill of a resting order doesn't
+    /// occur when the market trades through its price by `through` ticks. `through` is `1` for
+    /// the most marginal trade-through, where the trade or the opposite best reaches just one tick
+    /// beyond the order's price or the order's level is crossed.
+    fn miss_prob(&self, through: i64) -> f64;
+}
+
+impl<F> AdverseSelection for F
+where
+    F: Fn(i64) -> f64,
+{
+    fn miss_prob(&self, through: i64) -> f64 {
+        self(through)
+    }
+}
+
+/// An adverse-selection model in which the probability of a missed fill decays exponentially with
+/// the trade-through distance, `prob * exp(-decay * (through - 1))`. Marginal trade-throughs are
+/// most likely to be missed, while deep sweeps are almost certain to fill the order.
+#[derive(Clone, Debug)]
+pub struct ExpAdverseSelection {
+    prob: f64,
+    decay: f64,
+}
+
+impl ExpAdverseSelection {
+    /// Constructs an instance of `ExpAdverseSelection` with the probability of a missed fill at the
+    /// most marginal trade-through, `prob`, and the `decay` rate per tick.
+    pub fn new(prob: f64, decay: f64) -> Self {
+        Self { prob, decay }
+    }
+}
+
+impl AdverseSelection for ExpAdverseSelection {
+    fn miss_prob(&self, through: i64) -> f64 {
+        self.prob * (-self.decay * (through - 1).max(0) as f64).exp()
+    }
+}
+
+/// Filters the fills of resting orders that are triggered by the market trading through the
+/// order's price, based on a fitted [`AdverseSelection`] model.
+///
+/// L2 fill simulation assumes that a resting order is filled whenever a trade or the opposite best
+/// goes beyond its price. During a sweep, however, the level is often refilled or the liquidity
+/// ahead of the order absorbs the sweep, so that the marginal fills are the ones that don't occur
+/// in reality and are also the ones most adversely selected. The filter marks such fills as not
+/// occurring with the probability given by the model; the order keeps resting and can be filled by
+/// a later trade-through, which effectively delays the fill. Fills at the order's price, which are
+/// determined by the queue model, are not affected.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::models::{AdverseFillFilter, ExpAdverseSelection};
+///
+/// let filter = AdverseFillFilter::new(ExpAdverseSelection::new(0.5, 1.0)).seed(1);
+/// ```
+pub struct AdverseFillFilter {
+    model: Box<dyn AdverseSelection + Send>,
+    rng: StdRng,
+    missed: usize,
+}
+
+impl AdverseFillFilter {
+    /// Constructs an instance of `AdverseFillFilter` with the adverse-selection `model`.
+    pub fn new<M>(model: M) -> Self
+    where
+        M: AdverseSelection + Send + 'static,
+    {
+        Self {
+            model: Box::new(model),
+            rng: StdRng::from_entropy(),
+            missed: 0,
+        }
+    }
+
+    /// Seeds the random number generator, so that the same fills are missed in every run.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Seeds the random number generator. This is invoked by the asset builder if a seed is set.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Returns `true` if the fill of a resting order that the market trades through by `through`
+    /// ticks occurs.
+    pub fn check(&mut self, through: i64) -> bool {
+        let prob = self.model.miss_prob(through).clamp(0.0, 1.0);
+        if prob > 0.0 && self.rng.gen::<f64>() < prob {
+            self.missed += 1;
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Returns the number of fills marked as not occurring so far.
+    pub fn missed(&self) -> usize {
+        self.missed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backtest::models::{AdverseFillFilter, AdverseSelection, ExpAdverseSelection};
+
+    #[test]
+    fn test_adverse_fill_filter() {
+        let model = ExpAdverseSelection::new(0.8, 1.0);
+        assert!((model.miss_prob(1) - 0.8).abs() < 1e-12);
+        assert!((model.miss_prob(3) - 0.8 * (-2.0f64).exp()).abs() < 1e-12);
+
+        // Marginal trade-throughs are always missed, while deeper ones always fill.
+        let mut filter =
+            AdverseFillFilter::new(|through: i64| if through == 1 { 1.0 } else { 0.0 });
+        assert!(!filter.check(1));
+        assert!(filter.check(2));
+        assert!(filter.check(10));
+        assert_eq!(filter.missed(), 1);
+
+        // The same fills are missed with the same seed.
+        let mut a = AdverseFillFilter::new(ExpAdverseSelection::new(0.5, 0.0)).seed(7);
+        let mut b = AdverseFillFilter::new(ExpAdverseSelection::new(0.5, 0.0)).seed(7);
+        let a_res: Vec<bool> = (0..1000).map(|_| a.check(1)).collect();
+        let b_res: Vec<bool> = (0..1000).map(|_| b.check(1)).collect();
+        assert_eq!(a_res, b_res);
+        assert!(a.missed() > 400 && a.missed() < 600);
+    }
+}
@@ -0,0 +1,586 @@
+use std::io::{Error as IoError, ErrorKind};
+
+use crate::{
+    backtest::models::{
+        EmpiricalDistribution,
+        LatencyDistribution,
+        OrderLatencyRow,
+        QueueModel,
+        StochasticLatency,
+    },
+    depth::{HashMapMarketDepth, L2MarketDepth},
+    types::{
+        Event,
+        OrdType,
+        Order,
+        Side,
+        TimeInForce,
+        EXCH_ASK_DEPTH_CLEAR_EVENT,
+        EXCH_ASK_DEPTH_EVENT,
+        EXCH_ASK_DEPTH_SNAPSHOT_EVENT,
+        EXCH_BID_DEPTH_CLEAR_EVENT,
+        EXCH_BID_DEPTH_EVENT,
+        EXCH_BID_DEPTH_SNAPSHOT_EVENT,
+        EXCH_BUY_TRADE_EVENT,
+        EXCH_DEPTH_CLEAR_EVENT,
+        EXCH_SELL_TRADE_EVENT,
+    },
+};
+
+/// Fits the parameters of the stochastic latency models to the order latency data, such as the
+/// data recorded by [`LatencyRecorder`](crate::backtest::recorder::LatencyRecorder) in live
+/// trading, which closes the loop from the live order latency to the backtest latency model. The
+/// data can also be replayed as it is by
+/// [`IntpOrderLatency`](crate::backtest::models::IntpOrderLatency).
+///
+/// The order entry latency is `exch_ts - req_ts`, and the order response latency is
+/// `resp_ts - exch_ts`. Rows whose exchange timestamp is zero or negative are the orders rejected
+/// for technical reasons, and they are only counted in the rejection rate. Rows with negative
+/// latency, which indicates that the local clock is not synchronized with the exchange's, are
+/// excluded.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::backtest::{
+///     models::{LatencyCalibration, OrderLatencyRow},
+///     reader::read_npz_file,
+/// };
+///
+/// let data = read_npz_file::<OrderLatencyRow>("latency_0.npz", "data").unwrap();
+/// let rows = (0..data.len()).map(|i| data[i].clone()).collect::<Vec<_>>();
+/// let latency_model = LatencyCalibration::new(&rows)
+///     .fit_lognormal()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct LatencyCalibration {
+    entry: Vec<f64>,
+    response: Vec<f64>,
+    num_rejected: usize,
+    num_excluded: usize,
+}
+
+impl LatencyCalibration {
+    /// Constructs an instance of `LatencyCalibration` from the order latency data.
+    pub fn new(rows: &[OrderLatencyRow]) -> Self {
+        let mut entry = Vec::with_capacity(rows.len());
+        let mut response = Vec::with_capacity(rows.len());
+        let mut num_rejected = 0;
+        let mut num_excluded = 0;
+        for row in rows {
+            if row.exch_ts <= 0 {
+                num_rejected += 1;
+                continue;
+            }
+            let entry_latency = row.exch_ts - row.req_ts;
+            let response_latency = row.resp_ts - row.exch_ts;
+            if entry_latency < 0 || response_latency < 0 {
+                num_excluded += 1;
+                continue;
+            }
+            entry.push(entry_latency as f64);
+            response.push(response_latency as f64);
+        }
+        entry.sort_by(f64::total_cmp);
+        response.sort_by(f64::total_cmp);
+        Self {
+            entry,
+            response,
+            num_rejected,
+            num_excluded,
+        }
+    }
+
+    /// Returns the number of samples used for fitting.
+    pub fn num_samples(&self) -> usize {
+        self.entry.len()
+    }
+
+    /// Returns the ratio of the orders rejected for technical reasons to all orders.
+    pub fn rejection_rate(&self) -> f64 {
+        let total = self.entry.len() + self.num_rejected + self.num_excluded;
+        if total == 0 {
+            return 0.0;
+        }
+        self.num_rejected as f64 / total as f64
+    }
+
+    /// Returns the number of samples excluded due to negative latency.
+    pub fn num_excluded(&self) -> usize {
+        self.num_excluded
+    }
+
+    /// Returns the minimum order entry latency and the minimum order response latency, which can
+    /// be used as the floor of [`StochasticLatency`].
+    pub fn min_latency(&self) -> Option<(i64, i64)> {
+        Some((*self.entry.first()? as i64, *self.response.first()? as i64))
+    }
+
+    /// Returns the order entry latency distribution and the order response latency distribution
+    /// fitted by the lognormal distribution using the maximum likelihood estimation.
+    pub fn lognormal(&self) -> Result<(LatencyDistribution, LatencyDistribution), IoError> {
+        Ok((fit_lognormal(&self.entry)?, fit_lognormal(&self.response)?))
+    }
+
+    /// Returns the order entry latency distribution and the order response latency distribution
+    /// fitted by the gamma distribution using the method of moments.
+    pub fn gamma(&self) -> Result<(LatencyDistribution, LatencyDistribution), IoError> {
+        Ok((fit_gamma(&self.entry)?, fit_gamma(&self.response)?))
+    }
+
+    /// Returns the [`StochasticLatency`] with the fitted lognormal distributions, floored at the
+    /// minimum observed latency.
+    pub fn fit_lognormal(&self) -> Result<StochasticLatency, IoError> {
+        let (entry, response) = self.lognormal()?;
+        Ok(self.stochastic(entry, response))
+    }
+
+    /// Returns the [`StochasticLatency`] with the fitted gamma distributions, floored at the
+    /// minimum observed latency.
+    pub fn fit_gamma(&self) -> Result<StochasticLatency, IoError> {
+        let (entry, response) = self.gamma()?;
+        Ok(self.stochastic(entry, response))
+    }
+
+    /// Returns the order entry latency distribution and the order response latency distribution
+    /// as quantile tables of `num_quantiles + 1` points, evenly spaced in probability, for
+    /// [`EmpiricalLatency`](crate::backtest::models::EmpiricalLatency).
+    pub fn empirical(
+        &self,
+        num_quantiles: usize,
+    ) -> Result<(EmpiricalDistribution, EmpiricalDistribution), IoError> {
+        Ok((
+            empirical(&self.entry, num_quantiles)?,
+            empirical(&self.response, num_quantiles)?,
+        ))
+    }
+
+    fn stochastic(
+        &self,
+        entry: LatencyDistribution,
+        response: LatencyDistribution,
+    ) -> StochasticLatency {
+        // The floor applies to both, so the smaller one is used.
+        let floor = self
+            .min_latency()
+            .map(|(entry, response)| entry.min(response))
+            .unwrap_or(0);
+        StochasticLatency::new(entry, response).floor(floor)
+    }
+}
+
+fn not_enough_samples() -> IoError {
+    IoError::new(
+        ErrorKind::InvalidData,
+        "at least two samples with positive latency are required",
+    )
+}
+
+fn mean_var(samples: &[f64]) -> Result<(f64, f64), IoError> {
+    if samples.len() < 2 {
+        return Err(not_enough_samples());
+    }
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Ok((mean, var))
+}
+
+fn fit_lognormal(samples: &[f64]) -> Result<LatencyDistribution, IoError> {
+    // The logarithm is undefined for zero latency.
+    let logs = samples
+        .iter()
+        .filter(|&&x| x > 0.0)
+        .map(|x| x.ln())
+        .collect::<Vec<_>>();
+    let (mu, var) = mean_var(&logs)?;
+    Ok(LatencyDistribution::LogNormal {
+        mu,
+        sigma: var.sqrt(),
+    })
+}
+
+fn fit_gamma(samples: &[f64]) -> Result<LatencyDistribution, IoError> {
+    let (mean, var) = mean_var(samples)?;
+    if mean <= 0.0 || var <= 0.0 {
+        return Err(not_enough_samples());
+    }
+    Ok(LatencyDistribution::gamma_from_moments(mean, var.sqrt()))
+}
+
+fn empirical(sorted: &[f64], num_quantiles: usize) -> Result<EmpiricalDistribution, IoError> {
+    if sorted.len() < 2 || num_quantiles == 0 {
+        return Err(not_enough_samples());
+    }
+    let quantiles = (0..=num_quantiles)
+        .map(|i| {
+            let p = i as f64 / num_quantiles as f64;
+            // Linearly interpolates between the order statistics.
+            let pos = p * (sorted.len() - 1) as f64;
+            let lower = pos.floor() as usize;
+            let upper = pos.ceil() as usize;
+            let lat = sorted[lower] + (sorted[upper] - sorted[lower]) * (pos - lower as f64);
+            (p, lat)
+        })
+        .collect();
+    EmpiricalDistribution::from_quantiles(quantiles)
+}
+
+/// A live order recorded for [`QueueCalibration`]. The timestamps are the exchange timestamps.
+#[derive(Clone, Debug)]
+pub struct RecordedOrder {
+    /// The side of the order.
+    pub side: Side,
+    /// The price of the order.
+    pub price: f64,
+    /// Timestamp at which the order enters the queue.
+    pub entry_ts: i64,
+    /// Timestamp at which the order leaves the queue by the fill or cancellation, or the end of
+    /// the observation if the order is still open.
+    pub exit_ts: i64,
+    /// Timestamp at which the order is filled for the first time, or `None` if it's not filled.
+    pub fill_ts: Option<i64>,
+}
+
+/// The goodness of fit of a queue model to the recorded fills, reported by [`QueueCalibration`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueueFit {
+    /// The number of the recorded orders.
+    pub num_orders: usize,
+    /// The ratio of the orders whose predicted fill, whether filled or not, matches the recorded
+    /// fill.
+    pub accuracy: f64,
+    /// The ratio of the recorded orders that are filled.
+    pub actual_fill_rate: f64,
+    /// The ratio of the orders that the queue model predicts to be filled.
+    pub predicted_fill_rate: f64,
+    /// The mean absolute difference in nanoseconds between the predicted fill timestamp and the
+    /// recorded fill timestamp, for the orders that are both predicted and recorded to be filled,
+    /// or `NaN` if there is no such order.
+    pub mean_abs_fill_time_error: f64,
+}
+
+/// Fits the parameters of the queue models to the recorded live fills, instead of guessing them by
+/// trial and error. The recorded orders are replayed with the queue model through the market
+/// events at the exchange side, in the same way as
+/// [`NoPartialFillExchange`](crate::backtest::proc::NoPartialFillExchange) does, and the
+/// predicted fills are compared with the recorded fills.
+///
+/// The market events need to be sorted by the exchange timestamp and need to begin with the
+/// market depth snapshot, so that the queue position is initialized correctly.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::{
+///     backtest::{
+///         models::{PowerProbQueueFunc3, ProbQueueModel, QueueCalibration, RecordedOrder},
+///         reader::read_npz_file,
+///     },
+///     types::Event,
+/// };
+///
+/// let data = read_npz_file::<Event>("btcusdt_20240809.npz", "data").unwrap();
+/// let events = (0..data.len()).map(|i| data[i].clone()).collect::<Vec<_>>();
+/// let orders: Vec<RecordedOrder> = Vec::new(); // Loads the recorded live orders.
+///
+/// let calibration = QueueCalibration::new(orders, events, 0.1, 0.001);
+/// let params = (1..=40).map(|i| i as f64 * 0.25).collect::<Vec<_>>();
+/// let (n, fit) = calibration
+///     .fit(&params, |n| ProbQueueModel::new(PowerProbQueueFunc3::new(n)))
+///     .unwrap();
+/// println!("n={n}, accuracy={}", fit.accuracy);
+/// ```
+#[derive(Clone, Debug)]
+pub struct QueueCalibration {
+    orders: Vec<RecordedOrder>,
+    events: Vec<Event>,
+    tick_size: f64,
+    lot_size: f64,
+}
+
+impl QueueCalibration {
+    /// Constructs an instance of `QueueCalibration` from the recorded orders and the market events.
+    pub fn new(
+        mut orders: Vec<RecordedOrder>,
+        events: Vec<Event>,
+        tick_size: f64,
+        lot_size: f64,
+    ) -> Self {
+        orders.sort_by_key(|order| order.entry_ts);
+        Self {
+            orders,
+            events,
+            tick_size,
+            lot_size,
+        }
+    }
+
+    /// Returns the predicted fill timestamps of the recorded orders, in the order sorted by the
+    /// entry timestamp.
+    pub fn predict<QM>(&self, queue_model: &QM) -> Vec<Option<i64>>
+    where
+        QM: QueueModel<HashMapMarketDepth>,
+    {
+        let mut depth = HashMapMarketDepth::new(self.tick_size, self.lot_size);
+        let mut predicted = vec![None; self.orders.len()];
+        // (index of the recorded order, simulated order)
+        let mut active: Vec<(usize, Order)> = Vec::new();
+        let mut next = 0;
+
+        for ev in &self.events {
+            active.retain(|(i, _)| self.orders[*i].exit_ts >= ev.exch_ts);
+            while next < self.orders.len() && self.orders[next].entry_ts <= ev.exch_ts {
+                let recorded = &self.orders[next];
+                let mut order = Order::new(
+                    next as u64,
+                    (recorded.price / self.tick_size).round() as i64,
+                    self.tick_size,
+                    self.lot_size,
+                    recorded.side,
+                    OrdType::Limit,
+                    TimeInForce::GTC,
+                );
+                queue_model.new_order(&mut order, &depth);
+                active.push((next, order));
+                next += 1;
+            }
+
+            let mut filled = Vec::new();
+            if ev.is(EXCH_BID_DEPTH_CLEAR_EVENT) {
+                depth.clear_depth(Side::Buy, ev.px);
+            } else if ev.is(EXCH_ASK_DEPTH_CLEAR_EVENT) {
+                depth.clear_depth(Side::Sell, ev.px);
+            } else if ev.is(EXCH_DEPTH_CLEAR_EVENT) {
+                depth.clear_depth(Side::None, 0.0);
+            } else if ev.is(EXCH_BID_DEPTH_EVENT) || ev.is(EXCH_BID_DEPTH_SNAPSHOT_EVENT) {
+                let (price_tick, prev_best_tick, best_tick, prev_qty, new_qty, _) =
+                    depth.update_bid_depth(ev.px, ev.qty, ev.exch_ts);
+                for (i, order) in active.iter_mut() {
+                    if order.side == Side::Buy && order.price_tick == price_tick {
+                        queue_model.depth(order, prev_qty, new_qty, &depth);
+                    } else if order.side == Side::Sell
+                        && best_tick > prev_best_tick
+                        && order.price_tick <= best_tick
+                    {
+                        filled.push(*i);
+                    }
+                }
+            } else if ev.is(EXCH_ASK_DEPTH_EVENT) || ev.is(EXCH_ASK_DEPTH_SNAPSHOT_EVENT) {
+                let (price_tick, prev_best_tick, best_tick, prev_qty, new_qty, _) =
+                    depth.update_ask_depth(ev.px, ev.qty, ev.exch_ts);
+                for (i, order) in active.iter_mut() {
+                    if order.side == Side::Sell && order.price_tick == price_tick {
+                        queue_model.depth(order, prev_qty, new_qty, &depth);
+                    } else if order.side == Side::Buy
+                        && best_tick < prev_best_tick
+                        && order.price_tick >= best_tick
+                    {
+                        filled.push(*i);
+                    }
+                }
+            } else if ev.is(EXCH_BUY_TRADE_EVENT) || ev.is(EXCH_SELL_TRADE_EVENT) {
+                // A buy trade fills the sell orders, and vice versa.
+                let side = if ev.is(EXCH_BUY_TRADE_EVENT) {
+                    Side::Sell
+                } else {
+                    Side::Buy
+                };
+                let price_tick = (ev.px / self.tick_size).round() as i64;
+                for (i, order) in active.iter_mut() {
+                    if order.side != side {
+                        continue;
+                    }
+                    let through = match side {
+                        Side::Sell => order.price_tick < price_tick,
+                        _ => order.price_tick > price_tick,
+                    };
+                    if through {
+                        filled.push(*i);
+                    } else if order.price_tick == price_tick {
+                        queue_model.trade(order, ev.qty, &depth);
+                        if queue_model.is_filled(order, &depth) > 0.0 {
+                            filled.push(*i);
+                        }
+                    }
+                }
+            }
+
+            if !filled.is_empty() {
+                for &i in &filled {
+                    predicted[i] = Some(ev.exch_ts);
+                }
+                active.retain(|(i, _)| predicted[*i].is_none());
+            }
+        }
+        predicted
+    }
+
+    /// Returns the goodness of fit of the queue model to the recorded fills.
+    pub fn evaluate<QM>(&self, queue_model: &QM) -> QueueFit
+    where
+        QM: QueueModel<HashMapMarketDepth>,
+    {
+        let predicted = self.predict(queue_model);
+        let num_orders = self.orders.len();
+        let mut num_matched = 0;
+        let mut num_actual = 0;
+        let mut num_predicted = 0;
+        let mut abs_error_sum = 0.0;
+        let mut num_both = 0;
+        for (recorded, predicted) in self.orders.iter().zip(predicted) {
+            num_actual += recorded.fill_ts.is_some() as usize;
+            num_predicted += predicted.is_some() as usize;
+            num_matched += (recorded.fill_ts.is_some() == predicted.is_some()) as usize;
+            if let (Some(actual), Some(predicted)) = (recorded.fill_ts, predicted) {
+                abs_error_sum += (predicted - actual).abs() as f64;
+                num_both += 1;
+            }
+        }
+        let ratio = |n: usize| {
+            if num_orders == 0 {
+                0.0
+            } else {
+                n as f64 / num_orders as f64
+            }
+        };
+        QueueFit {
+            num_orders,
+            accuracy: ratio(num_matched),
+            actual_fill_rate: ratio(num_actual),
+            predicted_fill_rate: ratio(num_predicted),
+            mean_abs_fill_time_error: if num_both == 0 {
+                f64::NAN
+            } else {
+                abs_error_sum / num_both as f64
+            },
+        }
+    }
+
+    /// Searches the parameter values for the queue model built by `build` that fits the recorded
+    /// fills best, and returns the parameter value with its goodness of fit. The best fit has the
+    /// highest accuracy, and the ties are broken by the smaller mean absolute fill time error.
+    /// Returns `None` if `params` is empty.
+    pub fn fit<QM, F>(&self, params: &[f64], build: F) -> Option<(f64, QueueFit)>
+    where
+        QM: QueueModel<HashMapMarketDepth>,
+        F: Fn(f64) -> QM,
+    {
+        let mut best: Option<(f64, QueueFit)> = None;
+        for &param in params {
+            let fit = self.evaluate(&build(param));
+            let better = match &best {
+                None => true,
+                Some((_, best_fit)) => {
+                    fit.accuracy > best_fit.accuracy
+                        || (fit.accuracy == best_fit.accuracy
+                            && fit.mean_abs_fill_time_error < best_fit.mean_abs_fill_time_error)
+                }
+            };
+            if better {
+                best = Some((param, fit));
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LatencyCalibration, QueueCalibration, RecordedOrder};
+    use crate::{
+        backtest::models::{LatencyDistribution, OrderLatencyRow, ProbQueueModel},
+        types::{
+            Event,
+            Side,
+            EXCH_BID_DEPTH_EVENT,
+            EXCH_BID_DEPTH_SNAPSHOT_EVENT,
+            EXCH_SELL_TRADE_EVENT,
+        },
+    };
+
+    fn row(req_ts: i64, exch_ts: i64, resp_ts: i64) -> OrderLatencyRow {
+        OrderLatencyRow {
+            req_ts,
+            exch_ts,
+            resp_ts,
+            _padding: 0,
+        }
+    }
+
+    #[test]
+    fn test_calibration() {
+        let rows = vec![
+            row(0, 100, 300),
+            row(1_000, 1_200, 1_500),
+            row(2_000, 2_300, 2_400),
+            row(3_000, 0, 3_500),
+            row(4_000, 3_900, 4_100),
+        ];
+        let calibration = LatencyCalibration::new(&rows);
+        assert_eq!(calibration.num_samples(), 3);
+        assert_eq!(calibration.num_excluded(), 1);
+        assert_eq!(calibration.rejection_rate(), 0.2);
+        assert_eq!(calibration.min_latency(), Some((100, 100)));
+
+        let (entry, _) = calibration.gamma().unwrap();
+        match entry {
+            LatencyDistribution::Gamma { shape, scale } => {
+                assert!((shape * scale - 200.0).abs() < 1e-9);
+                assert!((shape * scale * scale - 10_000.0).abs() < 1e-6);
+            }
+            _ => panic!(),
+        }
+
+        let (entry, response) = calibration.empirical(4).unwrap();
+        assert_eq!(entry.quantile(0.5), 200.0);
+        assert_eq!(response.quantile(0.25), 150.0);
+
+        assert!(LatencyCalibration::new(&rows[..1]).lognormal().is_err());
+    }
+
+    fn event(ev: u64, exch_ts: i64, px: f64, qty: f64) -> Event {
+        Event {
+            ev,
+            exch_ts,
+            local_ts: exch_ts,
+            px,
+            qty,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_queue_calibration() {
+        let events = vec![
+            event(EXCH_BID_DEPTH_SNAPSHOT_EVENT, 0, 100.0, 10.0),
+            // The order enters with 10 ahead, and then 6 are added behind it.
+            event(EXCH_BID_DEPTH_EVENT, 7, 100.0, 16.0),
+            // 6 are cancelled.
+            event(EXCH_BID_DEPTH_EVENT, 10, 100.0, 10.0),
+            event(EXCH_SELL_TRADE_EVENT, 20, 100.0, 5.0),
+        ];
+        // The order is filled by the trade only if the cancellations occurred ahead of it.
+        let orders = vec![RecordedOrder {
+            side: Side::Buy,
+            price: 100.0,
+            entry_ts: 5,
+            exit_ts: 20,
+            fill_ts: Some(20),
+        }];
+        let calibration = QueueCalibration::new(orders, events, 1.0, 1.0);
+        let (prob, fit) = calibration
+            .fit(&[1.0, 0.0], |prob| {
+                ProbQueueModel::new(move |_: f64, _: f64| prob)
+            })
+            .unwrap();
+        assert_eq!(prob, 0.0);
+        assert_eq!(fit.accuracy, 1.0);
+        assert_eq!(fit.mean_abs_fill_time_error, 0.0);
+
+        let fit = calibration.evaluate(&ProbQueueModel::new(|_: f64, _: f64| 1.0));
+        assert_eq!(fit.accuracy, 0.0);
+        assert_eq!(fit.predicted_fill_rate, 0.0);
+    }
+}
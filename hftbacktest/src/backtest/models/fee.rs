@@ -0,0 +1,407 @@
+use std::{collections::VecDeque, fmt::Debug};
+
+use crate::types::{Order, StateValues};
+
+/// Provides the fee charged on each execution.
+pub trait FeeModel: Debug {
+    /// Returns the fee for the execution of the order, in the currency in which the asset's value
+    /// amount is denominated. A negative fee is a rebate.
+    ///
+    /// * `order` - The executed order, whose [`exec_qty`](Order::exec_qty) and
+    ///   [`maker`](Order::maker) describe the execution.
+    /// * `amount` - The value amount of the execution. See
+    ///   [`AssetType::amount`](crate::backtest::assettype::AssetType::amount).
+    /// * `state_values` - The state values before the execution is applied.
+    fn fee(&mut self, order: &Order, amount: f64, state_values: &StateValues) -> f64;
+}
+
+/// Charges flat fee rates on the value amount, one for the maker and one for the taker. A negative
+/// maker fee represents a maker rebate.
+#[derive(Clone, Debug)]
+pub struct FlatFee {
+    maker_fee: f64,
+    taker_fee: f64,
+}
+
+impl FlatFee {
+    /// Constructs an instance of `FlatFee`.
+    pub fn new(maker_fee: f64, taker_fee: f64) -> Self {
+        Self {
+            maker_fee,
+            taker_fee,
+        }
+    }
+}
+
+impl FeeModel for FlatFee {
+    fn fee(&mut self, order: &Order, amount: f64, _state_values: &StateValues) -> f64 {
+        let fee = if order.maker {
+            self.maker_fee
+        } else {
+            self.taker_fee
+        };
+        amount * fee
+    }
+}
+
+/// A tier of [`TieredFee`], which applies once the trading value reaches `trading_value`.
+#[derive(Clone, Debug)]
+pub struct FeeTier {
+    pub trading_value: f64,
+    pub maker_fee: f64,
+    pub taker_fee: f64,
+}
+
+/// Charges the fee rates of the tier the cumulative trading value has reached, as venues lower
+/// their fees for high-volume traders.
+///
+/// By default, this model uses the cumulative trading value since the beginning of the backtest,
+/// on top of the initial trading value representing the trading value carried into the backtest.
+/// Venues usually determine the tier by the trading value over a trailing window, such as 30 days,
+/// which [`rolling_window`](Self::rolling_window) simulates by accumulating the value of the
+/// executions within the window, so that the tier rises and falls as the strategy trades. In that
+/// case, the initial trading value counts until the window has passed since the first execution.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::models::{FeeTier, TieredFee};
+///
+/// let fee_model = TieredFee::new(vec![
+///     FeeTier { trading_value: 0.0, maker_fee: 0.0002, taker_fee: 0.0005 },
+///     FeeTier { trading_value: 15_000_000.0, maker_fee: 0.00016, taker_fee: 0.0004 },
+///     FeeTier { trading_value: 50_000_000.0, maker_fee: -0.00005, taker_fee: 0.00035 },
+/// ])
+/// .initial_trading_value(20_000_000.0)
+/// .rolling_window(30 * 24 * 60 * 60 * 1_000_000_000);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TieredFee {
+    tiers: Vec<FeeTier>,
+    initial_trading_value: f64,
+    window: Option<i64>,
+    // (exchange timestamp, value amount) of the executions within the window.
+    executions: VecDeque<(i64, f64)>,
+    rolling_value: f64,
+    first_ts: Option<i64>,
+}
+
+impl TieredFee {
+    /// Constructs an instance of `TieredFee`. The tiers are sorted by their trading value, and the
+    /// lowest tier applies below its trading value.
+    pub fn new(mut tiers: Vec<FeeTier>) -> Self {
+        assert!(!tiers.is_empty());
+        tiers.sort_by(|a, b| a.trading_value.total_cmp(&b.trading_value));
+        Self {
+            tiers,
+            initial_trading_value: 0.0,
+            window: None,
+            executions: VecDeque::new(),
+            rolling_value: 0.0,
+            first_ts: None,
+        }
+    }
+
+    /// Sets the trading value carried into the backtest. The default value is `0`.
+    pub fn initial_trading_value(self, initial_trading_value: f64) -> Self {
+        Self {
+            initial_trading_value,
+            ..self
+        }
+    }
+
+    /// Determines the tier by the trading value over the trailing `window`, such as 30 days, instead
+    /// of the cumulative trading value.
+    pub fn rolling_window(self, window: i64) -> Self {
+        assert!(window > 0);
+        Self {
+            window: Some(window),
+            ..self
+        }
+    }
+
+    /// Returns the tier that applies at the cumulative trading value.
+    pub fn tier(&self, trading_value: f64) -> &FeeTier {
+        self.tier_of(self.initial_trading_value + trading_value)
+    }
+
+    /// Returns the trading value over the trailing window ending at the timestamp, including the
+    /// initial trading value while it counts, after discarding the executions that have left the
+    /// window.
+    fn rolling_trading_value(&mut self, window: i64, timestamp: i64) -> f64 {
+        while let Some(&(ts, amount)) = self.executions.front() {
+            if ts > timestamp - window {
+                break;
+            }
+            self.rolling_value -= amount;
+            self.executions.pop_front();
+        }
+        if self.executions.is_empty() {
+            // Prevents the accumulated rounding error.
+            self.rolling_value = 0.0;
+        }
+        let first_ts = *self.first_ts.get_or_insert(timestamp);
+        if timestamp - first_ts < window {
+            self.initial_trading_value + self.rolling_value
+        } else {
+            self.rolling_value
+        }
+    }
+
+    fn tier_of(&self, trading_value: f64) -> &FeeTier {
+        let n = self
+            .tiers
+            .partition_point(|tier| tier.trading_value <= trading_value);
+        &self.tiers[n.saturating_sub(1)]
+    }
+}
+
+impl FeeModel for TieredFee {
+    fn fee(&mut self, order: &Order, amount: f64, state_values: &StateValues) -> f64 {
+        let tier = match self.window {
+            Some(window) => {
+                let trading_value = self.rolling_trading_value(window, order.exch_timestamp);
+                self.executions.push_back((order.exch_timestamp, amount));
+                self.rolling_value += amount;
+                self.tier_of(trading_value)
+            }
+            None => self.tier(state_values.trading_value),
+        };
+        let fee = if order.maker {
+            tier.maker_fee
+        } else {
+            tier.taker_fee
+        };
+        amount * fee
+    }
+}
+
+/// Charges the fee of the underlying model, but no less than the minimum fee on each execution, as
+/// venues and brokers charging per-order commissions do. Rebates are not affected.
+#[derive(Clone, Debug)]
+pub struct MinimumFee<FM> {
+    fee_model: FM,
+    min_fee: f64,
+}
+
+impl<FM> MinimumFee<FM>
+where
+    FM: FeeModel,
+{
+    /// Constructs an instance of `MinimumFee`.
+    pub fn new(fee_model: FM, min_fee: f64) -> Self {
+        Self { fee_model, min_fee }
+    }
+}
+
+impl<FM> FeeModel for MinimumFee<FM>
+where
+    FM: FeeModel,
+{
+    fn fee(&mut self, order: &Order, amount: f64, state_values: &StateValues) -> f64 {
+        let fee = self.fee_model.fee(order, amount, state_values);
+        if fee < 0.0 {
+            fee
+        } else {
+            fee.max(self.min_fee)
+        }
+    }
+}
+
+/// Charges the fee of the underlying model in another currency, such as the venue's token, which
+/// often comes with a discount.
+///
+/// The fee is paid in the other currency at the fixed `rate`, the price of a unit of the other
+/// currency in the currency in which the asset's value amount is denominated, and is reflected in
+/// the equity at the same rate. The amount paid in the other currency is accumulated in
+/// [`paid`](Self::paid). Rebates are paid in the asset's currency and are not discounted.
+#[derive(Clone, Debug)]
+pub struct ForeignCurrencyFee<FM> {
+    fee_model: FM,
+    rate: f64,
+    discount: f64,
+    paid: f64,
+}
+
+impl<FM> ForeignCurrencyFee<FM>
+where
+    FM: FeeModel,
+{
+    /// Constructs an instance of `ForeignCurrencyFee`.
+    ///
+    /// * `fee_model` - The underlying fee model.
+    /// * `rate` - The price of a unit of the other currency in the asset's currency.
+    /// * `discount` - The discount rate applied when paying in the other currency, such as `0.1`
+    ///   for 10%.
+    pub fn new(fee_model: FM, rate: f64, discount: f64) -> Self {
+        assert!(rate > 0.0);
+        Self {
+            fee_model,
+            rate,
+            discount,
+            paid: 0.0,
+        }
+    }
+
+    /// Returns the total fee paid in the other currency.
+    pub fn paid(&self) -> f64 {
+        self.paid
+    }
+}
+
+impl<FM> FeeModel for ForeignCurrencyFee<FM>
+where
+    FM: FeeModel,
+{
+    fn fee(&mut self, order: &Order, amount: f64, state_values: &StateValues) -> f64 {
+        let fee = self.fee_model.fee(order, amount, state_values);
+        if fee < 0.0 {
+            return fee;
+        }
+        let fee = fee * (1.0 - self.discount);
+        self.paid += fee / self.rate;
+        fee
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FeeModel, FeeTier, FlatFee, ForeignCurrencyFee, MinimumFee, TieredFee};
+    use crate::types::{OrdType, Order, Side, StateValues, TimeInForce};
+
+    fn order(maker: bool) -> Order {
+        let mut order = Order::new(
+            1,
+            100,
+            1.0,
+            1.0,
+            Side::Buy,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+        order.maker = maker;
+        order
+    }
+
+    fn state_values(trading_value: f64) -> StateValues {
+        StateValues {
+            position: 0.0,
+            balance: 0.0,
+            fee: 0.0,
+            num_trades: 0,
+            trading_volume: 0.0,
+            trading_value,
+            funding: 0.0,
+            ..Default::default()
+        }
+    }
+
+    fn assert_close(fee: f64, expected: f64) {
+        assert!((fee - expected).abs() < 1e-12, "{fee} != {expected}");
+    }
+
+    #[test]
+    fn test_flat_fee() {
+        let mut fee_model = FlatFee::new(-0.0001, 0.0005);
+        assert_close(
+            fee_model.fee(&order(true), 1000.0, &state_values(0.0)),
+            -0.1,
+        );
+        assert_close(
+            fee_model.fee(&order(false), 1000.0, &state_values(0.0)),
+            0.5,
+        );
+    }
+
+    #[test]
+    fn test_tiered_fee() {
+        let mut fee_model = TieredFee::new(vec![
+            FeeTier {
+                trading_value: 1_000.0,
+                maker_fee: 0.0001,
+                taker_fee: 0.0004,
+            },
+            FeeTier {
+                trading_value: 0.0,
+                maker_fee: 0.0002,
+                taker_fee: 0.0005,
+            },
+        ]);
+        assert_close(
+            fee_model.fee(&order(true), 100.0, &state_values(999.0)),
+            0.02,
+        );
+        assert_close(
+            fee_model.fee(&order(false), 100.0, &state_values(1_000.0)),
+            0.04,
+        );
+
+        let mut fee_model = fee_model.initial_trading_value(500.0);
+        assert_close(
+            fee_model.fee(&order(true), 100.0, &state_values(500.0)),
+            0.01,
+        );
+    }
+
+    #[test]
+    fn test_rolling_tiered_fee() {
+        let mut fee_model = TieredFee::new(vec![
+            FeeTier {
+                trading_value: 0.0,
+                maker_fee: 0.0002,
+                taker_fee: 0.0005,
+            },
+            FeeTier {
+                trading_value: 1_000.0,
+                maker_fee: 0.0001,
+                taker_fee: 0.0004,
+            },
+        ])
+        .initial_trading_value(600.0)
+        .rolling_window(100);
+        let mut fee_at = |ts: i64, amount: f64| {
+            let mut order = order(true);
+            order.exch_timestamp = ts;
+            fee_model.fee(&order, amount, &state_values(0.0))
+        };
+
+        assert_close(fee_at(10, 500.0), 0.1);
+        // The initial trading value and the first execution reach the upper tier.
+        assert_close(fee_at(50, 500.0), 0.05);
+        // The initial trading value and the first execution have left the window.
+        assert_close(fee_at(110, 500.0), 0.1);
+        assert_close(fee_at(120, 100.0), 0.01);
+        // The execution at 50 has left the window.
+        assert_close(fee_at(200, 100.0), 0.02);
+    }
+
+    #[test]
+    fn test_minimum_fee() {
+        let mut fee_model = MinimumFee::new(FlatFee::new(-0.0001, 0.0005), 1.0);
+        assert_close(
+            fee_model.fee(&order(false), 1000.0, &state_values(0.0)),
+            1.0,
+        );
+        assert_close(
+            fee_model.fee(&order(false), 4000.0, &state_values(0.0)),
+            2.0,
+        );
+        assert_close(
+            fee_model.fee(&order(true), 1000.0, &state_values(0.0)),
+            -0.1,
+        );
+    }
+
+    #[test]
+    fn test_foreign_currency_fee() {
+        let mut fee_model = ForeignCurrencyFee::new(FlatFee::new(-0.0001, 0.0005), 5.0, 0.2);
+        assert_close(
+            fee_model.fee(&order(false), 1000.0, &state_values(0.0)),
+            0.4,
+        );
+        assert_close(
+            fee_model.fee(&order(true), 1000.0, &state_values(0.0)),
+            -0.1,
+        );
+        assert_close(fee_model.paid(), 0.08);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::{
+    depth::{MarketDepth, INVALID_MAX, INVALID_MIN},
+    types::{OrdType, Order, Side},
+};
+
+/// The maximum number of price levels an order sweeps from the best. Any quantity that the book
+/// cannot absorb within this range is filled at the farthest level.
+const MAX_SWEEP_TICKS: i64 = 1_000;
+
+/// Returns the price tick at which a liquidity-taking order of `qty` on the `side` is entirely
+/// filled if it sweeps the opposite side of the book from the best, which is the level where the
+/// cumulative quantity covers `qty`. Returns `None` if the opposite side is empty.
+pub fn sweep_price_tick<MD>(depth: &MD, side: Side, qty: f64) -> Option<i64>
+where
+    MD: MarketDepth,
+{
+    let (best_tick, step) = match side {
+        Side::Sell if depth.best_bid_tick() != INVALID_MIN => (depth.best_bid_tick(), -1),
+        Side::Buy if depth.best_ask_tick() != INVALID_MAX => (depth.best_ask_tick(), 1),
+        _ => return None,
+    };
+    let mut cum_qty = 0.0;
+    let mut tick = best_tick;
+    for i in 0..MAX_SWEEP_TICKS {
+        tick = best_tick + i * step;
+        cum_qty += match side {
+            Side::Sell => depth.bid_qty_at_tick(tick),
+            _ => depth.ask_qty_at_tick(tick),
+        };
+        if (cum_qty / depth.lot_size()).round() >= (qty / depth.lot_size()).round() {
+            break;
+        }
+    }
+    Some(tick)
+}
+
+/// Provides the slippage of a liquidity-taking order beyond the prices in the book, caused by the
+/// market impact of the order and by the book moving while the order is in flight.
+pub trait MarketImpact {
+    /// Returns the slippage in ticks, adverse to the taker, of a liquidity-taking order.
+    ///
+    /// * `qty` - The order quantity.
+    /// * `best_qty` - The quantity at the opposite best when the order arrives at the exchange.
+    /// * `latency` - The order entry latency, the time between the order's submission and its
+    ///   arrival at the exchange.
+    fn slippage(&self, qty: f64, best_qty: f64, latency: i64) -> f64;
+}
+
+impl<F> MarketImpact for F
+where
+    F: Fn(f64, f64, i64) -> f64,
+{
+    fn slippage(&self, qty: f64, best_qty: f64, latency: i64) -> f64 {
+        self(qty, best_qty, latency)
+    }
+}
+
+/// A market impact model in which the slippage grows linearly with the order quantity relative to
+/// the quantity at the opposite best, `coef * qty / best_qty`.
+#[derive(Clone, Debug)]
+pub struct LinearImpact {
+    coef: f64,
+}
+
+impl LinearImpact {
+    /// Constructs an instance of `LinearImpact`.
+    pub fn new(coef: f64) -> Self {
+        Self { coef }
+    }
+}
+
+impl MarketImpact for LinearImpact {
+    fn slippage(&self, qty: f64, best_qty: f64, _latency: i64) -> f64 {
+        if best_qty <= 0.0 {
+            return 0.0;
+        }
+        self.coef * qty / best_qty
+    }
+}
+
+/// A market impact model following the square-root law, in which the slippage is
+/// `coef * sqrt(qty / best_qty)`, plus a latency term, `latency_coef * sqrt(latency)` with the
+/// latency in seconds, that accounts for the adverse drift of the price while the order is in
+/// flight. The latency term is `0` by default.
+#[derive(Clone, Debug)]
+pub struct SqrtImpact {
+    coef: f64,
+    latency_coef: f64,
+}
+
+impl SqrtImpact {
+    /// Constructs an instance of `SqrtImpact`.
+    pub fn new(coef: f64) -> Self {
+        Self {
+            coef,
+            latency_coef: 0.0,
+        }
+    }
+
+    /// Sets the slippage in ticks per square root of a second of the order entry latency, which is
+    /// typically a fraction of the price volatility in ticks over a second.
+    pub fn latency_coef(self, latency_coef: f64) -> Self {
+        Self {
+            latency_coef,
+            ..self
+        }
+    }
+}
+
+impl MarketImpact for SqrtImpact {
+    fn slippage(&self, qty: f64, best_qty: f64, latency: i64) -> f64 {
+        let impact = if best_qty > 0.0 {
+            self.coef * (qty / best_qty).sqrt()
+        } else {
+            0.0
+        };
+        impact + self.latency_coef * (latency.max(0) as f64 / 1_000_000_000.0).sqrt()
+    }
+}
+
+/// Adjusts the execution price of liquidity-taking orders for the market impact, based on a
+/// [`MarketImpact`] model.
+///
+/// Without it, a liquidity-taking order is filled at the prices in the book when it arrives at the
+/// exchange, which is optimistic for orders larger than the best or sent with a high latency. With
+/// it, the order walks the book and slips further by the number of ticks given by the model,
+/// rounded to the nearest tick. A limit order is never filled beyond its price, while a market
+/// order, including a triggered stop-market order, has no such bound.
+///
+/// [`NoPartialFillExchange`](crate::backtest::proc::NoPartialFillExchange) fills the entire
+/// quantity at the level where the book covers it, plus the slippage.
+/// [`PartialFillExchange`](crate::backtest::proc::PartialFillExchange) already walks the book
+/// level by level, so each fill only slips by the model's slippage.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::models::{SqrtImpact, TakerImpact};
+///
+/// let impact = TakerImpact::new(SqrtImpact::new(0.5).latency_coef(2.0));
+/// ```
+pub struct TakerImpact {
+    model: Box<dyn MarketImpact + Send>,
+    slippage: i64,
+}
+
+impl TakerImpact {
+    /// Constructs an instance of `TakerImpact` with the market impact `model`.
+    pub fn new<M>(model: M) -> Self
+    where
+        M: MarketImpact + Send + 'static,
+    {
+        Self {
+            model: Box::new(model),
+            slippage: 0,
+        }
+    }
+
+    /// Returns the execution price tick of the liquidity-taking `order` arriving at the exchange at
+    /// the `timestamp`, which is otherwise filled at `exec_price_tick`. If `walk_book` is `true`,
+    /// the order's leaves quantity is also swept through the book.
+    pub fn exec_price_tick<MD>(
+        &mut self,
+        depth: &MD,
+        order: &Order,
+        exec_price_tick: i64,
+        timestamp: i64,
+        walk_book: bool,
+    ) -> i64
+    where
+        MD: MarketDepth,
+    {
+        let (best_qty, sign) = match order.side {
+            Side::Buy => (depth.ask_qty_at_tick(depth.best_ask_tick()), 1),
+            _ => (depth.bid_qty_at_tick(depth.best_bid_tick()), -1),
+        };
+        let mut price_tick = exec_price_tick;
+        if walk_book {
+            if let Some(sweep_tick) = sweep_price_tick(depth, order.side, order.leaves_qty) {
+                price_tick = match order.side {
+                    Side::Buy => price_tick.max(sweep_tick),
+                    _ => price_tick.min(sweep_tick),
+                };
+            }
+        }
+
+        let latency = if order.local_timestamp > 0 {
+            timestamp - order.local_timestamp
+        } else {
+            0
+        };
+        let slippage = self
+            .model
+            .slippage(order.qty, best_qty, latency)
+            .round()
+            .max(0.0) as i64;
+        price_tick += sign * slippage;
+
+        if order.order_type == OrdType::Limit || order.order_type == OrdType::StopLimit {
+            price_tick = match order.side {
+                Side::Buy => price_tick.min(order.price_tick),
+                _ => price_tick.max(order.price_tick),
+            };
+        }
+        self.slippage += (price_tick - exec_price_tick).abs();
+        price_tick
+    }
+
+    /// Returns the total number of ticks by which the liquidity-taking orders have been filled
+    /// worse than the prices they would have been filled at without the market impact.
+    pub fn slippage(&self) -> i64 {
+        self.slippage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sweep_price_tick, LinearImpact, MarketImpact, SqrtImpact, TakerImpact};
+    use crate::{
+        depth::{HashMapMarketDepth, L2MarketDepth},
+        types::{OrdType, Order, Side, TimeInForce},
+    };
+
+    #[test]
+    fn test_sweep_price_tick() {
+        let mut depth = HashMapMarketDepth::new(1.0, 1.0);
+        assert_eq!(sweep_price_tick(&depth, Side::Sell, 1.0), None);
+
+        depth.update_bid_depth(100.0, 2.0, 0);
+        depth.update_bid_depth(98.0, 3.0, 0);
+        depth.update_ask_depth(101.0, 1.0, 0);
+        assert_eq!(sweep_price_tick(&depth, Side::Sell, 2.0), Some(100));
+        assert_eq!(sweep_price_tick(&depth, Side::Sell, 4.0), Some(98));
+        assert_eq!(sweep_price_tick(&depth, Side::Buy, 1.0), Some(101));
+        // The book cannot absorb the quantity within the sweep range.
+        assert_eq!(sweep_price_tick(&depth, Side::Buy, 2.0), Some(1100));
+    }
+
+    #[test]
+    fn test_taker_impact() {
+        assert!((LinearImpact::new(0.5).slippage(4.0, 2.0, 0) - 1.0).abs() < 1e-12);
+        let model = SqrtImpact::new(1.0).latency_coef(2.0);
+        assert!((model.slippage(4.0, 1.0, 250_000_000) - 3.0).abs() < 1e-12);
+
+        let mut depth = HashMapMarketDepth::new(1.0, 1.0);
+        depth.update_bid_depth(100.0, 2.0, 0);
+        depth.update_bid_depth(98.0, 3.0, 0);
+        depth.update_ask_depth(101.0, 1.0, 0);
+        depth.update_ask_depth(102.0, 10.0, 0);
+
+        let mut order = Order::new(
+            1,
+            0,
+            1.0,
+            4.0,
+            Side::Sell,
+            OrdType::Market,
+            TimeInForce::IOC,
+        );
+        order.local_timestamp = 100;
+        // The order sweeps down to 98 and slips further by 2 ticks.
+        let mut impact = TakerImpact::new(LinearImpact::new(1.0));
+        assert_eq!(impact.exec_price_tick(&depth, &order, 100, 200, true), 96);
+        assert_eq!(impact.exec_price_tick(&depth, &order, 100, 200, false), 98);
+        assert_eq!(impact.slippage(), 6);
+
+        // A limit order is not filled beyond its price.
+        let mut order = Order::new(
+            2,
+            103,
+            1.0,
+            5.0,
+            Side::Buy,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+        order.local_timestamp = 100;
+        let mut impact =
+            TakerImpact::new(|_qty: f64, _best_qty: f64, latency: i64| latency as f64 / 100.0);
+        assert_eq!(impact.exec_price_tick(&depth, &order, 101, 300, true), 103);
+        assert_eq!(impact.exec_price_tick(&depth, &order, 101, 200, false), 102);
+    }
+}
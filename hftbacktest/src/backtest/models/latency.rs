@@ -1,22 +1,84 @@
-use std::mem;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    f64::consts::PI,
+    fs,
+    io::{Error as IoError, ErrorKind},
+    mem,
+    rc::Rc,
+};
 
 use hftbacktest_derive::NpyDTyped;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use crate::{
     backtest::{
-        reader::{Cache, Data, DataSource, Reader, POD},
+        models::derive_seed,
+        reader::{
+            read_csv_file,
+            Cache,
+            CsvSchema,
+            Data,
+            DataFilter,
+            DataSource,
+            Reader,
+            TimeScale,
+            POD,
+        },
         BacktestError,
     },
-    types::Order,
+    types::{
+        Event,
+        Order,
+        TimeUnit,
+        ADD_ORDER_EVENT,
+        CANCEL_ORDER_EVENT,
+        DEPTH_EVENT,
+        EXCH_EVENT,
+        FILL_EVENT,
+        MODIFY_ORDER_EVENT,
+        TRADE_EVENT,
+    },
 };
 
+/// The type of order request, by which the order entry latency can differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RequestType {
+    /// Request to open a new order.
+    New,
+    /// Request to cancel an opened order.
+    Cancel,
+    /// Request to modify an opened order.
+    Modify,
+    /// Request to query the current state of an order.
+    Query,
+}
+
 /// Provides the order entry latency and the order response latency.
 pub trait LatencyModel {
     /// Returns the order entry latency for the given timestamp and order.
     fn entry(&mut self, timestamp: i64, order: &Order) -> i64;
 
+    /// Returns the order entry latency for the given timestamp, order, and type of request. On
+    /// many venues, cancels take a different path from new orders and are prioritized, so their
+    /// latency differs. The default implementation returns [`entry`](Self::entry) regardless of
+    /// the type of request.
+    fn request_entry(&mut self, timestamp: i64, order: &Order, _request: RequestType) -> i64 {
+        self.entry(timestamp, order)
+    }
+
     /// Returns the order response latency for the given timestamp and order.
     fn response(&mut self, timestamp: i64, order: &Order) -> i64;
+
+    /// Observes the market event processed by the exchange processor, so that the latency can
+    /// depend on the market activity. The default implementation does nothing.
+    fn observe(&mut self, _ev: &Event) {}
+
+    /// Seeds the random number generator of a stochastic latency model, so that the same latencies
+    /// are drawn in every run. A model that wraps other models should seed them as well, with
+    /// seeds derived by [`derive_seed`]. The default implementation does nothing, which is the
+    /// case for deterministic models.
+    fn set_seed(&mut self, _seed: u64) {}
 }
 
 /// Provides constant order latency.
@@ -33,15 +95,23 @@ pub struct ConstantLatency {
 impl ConstantLatency {
     /// Constructs an instance of `ConstantLatency`.
     ///
-    /// `entry_latency` and `response_latency` should match the time unit of the data's timestamps.
-    /// Using nanoseconds across all datasets is recommended, since the live
-    /// [Bot](crate::live::LiveBot) uses nanoseconds.
+    /// `entry_latency` and `response_latency` are in nanoseconds, the time unit of the engine. See
+    /// [`TimeUnit`].
     pub fn new(entry_latency: i64, response_latency: i64) -> Self {
         Self {
             entry_latency,
             response_latency,
         }
     }
+
+    /// Constructs an instance of `ConstantLatency` with `entry_latency` and `response_latency` in
+    /// the `unit`.
+    pub fn with_time_unit(entry_latency: i64, response_latency: i64, unit: TimeUnit) -> Self {
+        Self::new(
+            unit.to_nanos(entry_latency),
+            unit.to_nanos(response_latency),
+        )
+    }
 }
 
 impl LatencyModel for ConstantLatency {
@@ -54,6 +124,975 @@ impl LatencyModel for ConstantLatency {
     }
 }
 
+/// The probability distribution from which [`StochasticLatency`] draws latencies in nanoseconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LatencyDistribution {
+    /// The lognormal distribution, where `mu` and `sigma` are the mean and the standard deviation
+    /// of the natural logarithm of the latency in nanoseconds.
+    LogNormal { mu: f64, sigma: f64 },
+    /// The gamma distribution with the `shape` and the `scale` in nanoseconds, whose mean is
+    /// `shape * scale`.
+    Gamma { shape: f64, scale: f64 },
+}
+
+impl LatencyDistribution {
+    /// Constructs the lognormal distribution that has the given `mean` and `std` of the latency in
+    /// nanoseconds, which are more readily measured than the parameters of the logarithm.
+    pub fn lognormal_from_moments(mean: f64, std: f64) -> Self {
+        let sigma2 = (1.0 + (std / mean).powi(2)).ln();
+        Self::LogNormal {
+            mu: mean.ln() - sigma2 / 2.0,
+            sigma: sigma2.sqrt(),
+        }
+    }
+
+    /// Constructs the gamma distribution that has the given `mean` and `std` of the latency in
+    /// nanoseconds.
+    pub fn gamma_from_moments(mean: f64, std: f64) -> Self {
+        Self::Gamma {
+            shape: (mean / std).powi(2),
+            scale: std * std / mean,
+        }
+    }
+
+    /// Draws a latency in nanoseconds.
+    pub(crate) fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match *self {
+            Self::LogNormal { mu, sigma } => (mu + sigma * standard_normal(rng)).exp(),
+            Self::Gamma { shape, scale } => standard_gamma(rng, shape) * scale,
+        }
+    }
+}
+
+// Draws from the standard normal distribution using the Box-Muller transform.
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    // `1 - u` is in (0, 1], which keeps the logarithm finite.
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+// Draws from the gamma distribution with a unit scale using Marsaglia and Tsang's method.
+fn standard_gamma<R: Rng>(rng: &mut R, shape: f64) -> f64 {
+    if shape < 1.0 {
+        // Boosts the shape, since the method requires a shape of at least 1.
+        let u = 1.0 - rng.gen::<f64>();
+        return standard_gamma(rng, shape + 1.0) * u.powf(1.0 / shape);
+    }
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = standard_normal(rng);
+        let v = (1.0 + c * x).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+        let u = 1.0 - rng.gen::<f64>();
+        if u < 1.0 - 0.0331 * x.powi(4) || u.ln() < 0.5 * x * x + d * (1.0 - v + v.ln()) {
+            return d * v;
+        }
+    }
+}
+
+/// Provides order latency drawn independently for each order from the parametric
+/// [`LatencyDistribution`]s, for when historical order latency data isn't available. Its
+/// parameters can be calibrated against the observed order latencies, so that the variability and
+/// the tail of the latency are reflected, unlike [`ConstantLatency`].
+///
+/// The latencies drawn are at least the floor, which represents the physical minimum such as the
+/// network round trip. The random number generator is seeded from the system's entropy unless
+/// [`seed`](Self::seed) is given.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::models::{LatencyDistribution, StochasticLatency};
+///
+/// let latency_model = StochasticLatency::new(
+///     LatencyDistribution::lognormal_from_moments(2_000_000.0, 1_000_000.0),
+///     LatencyDistribution::gamma_from_moments(1_500_000.0, 500_000.0),
+/// )
+/// .floor(500_000)
+/// .seed(1);
+/// ```
+#[derive(Clone)]
+pub struct StochasticLatency {
+    entry: LatencyDistribution,
+    response: LatencyDistribution,
+    floor: i64,
+    rng: StdRng,
+}
+
+impl StochasticLatency {
+    /// Constructs an instance of `StochasticLatency` that draws the order entry latency from
+    /// `entry` and the order response latency from `response`.
+    pub fn new(entry: LatencyDistribution, response: LatencyDistribution) -> Self {
+        Self {
+            entry,
+            response,
+            floor: 0,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Sets the minimum latency in nanoseconds. The default value is `0`.
+    pub fn floor(self, floor: i64) -> Self {
+        Self { floor, ..self }
+    }
+
+    /// Seeds the random number generator, so that the same latencies are drawn in every run.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+}
+
+impl LatencyModel for StochasticLatency {
+    fn entry(&mut self, _timestamp: i64, _order: &Order) -> i64 {
+        (self.entry.sample(&mut self.rng) as i64).max(self.floor)
+    }
+
+    fn response(&mut self, _timestamp: i64, _order: &Order) -> i64 {
+        (self.response.sample(&mut self.rng) as i64).max(self.floor)
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+/// An empirical latency distribution given by a quantile table, which maps the cumulative
+/// probability to the latency in nanoseconds. The latency between the points of the table is
+/// linearly interpolated.
+#[derive(Clone, Debug)]
+pub struct EmpiricalDistribution {
+    quantiles: Vec<(f64, f64)>,
+}
+
+impl EmpiricalDistribution {
+    /// Constructs an instance of `EmpiricalDistribution` from the points of the quantile table,
+    /// each of which is the cumulative probability and the latency at it. The probabilities should
+    /// start at `0` and end at `1`, and both the probabilities and the latencies should be
+    /// non-decreasing.
+    pub fn from_quantiles(quantiles: Vec<(f64, f64)>) -> Result<Self, IoError> {
+        let invalid = |msg: &str| Err(IoError::new(ErrorKind::InvalidData, msg.to_string()));
+        if quantiles.len() < 2 {
+            return invalid("at least two quantiles are required");
+        }
+        if quantiles[0].0 != 0.0 || quantiles[quantiles.len() - 1].0 != 1.0 {
+            return invalid("the probabilities should start at 0 and end at 1");
+        }
+        if quantiles
+            .windows(2)
+            .any(|w| w[1].0 < w[0].0 || w[1].1 < w[0].1)
+        {
+            return invalid("the probabilities and the latencies should be non-decreasing");
+        }
+        Ok(Self { quantiles })
+    }
+
+    /// Constructs an instance of `EmpiricalDistribution` from the bins of a histogram, each of
+    /// which is the lower bound and the upper bound of the latency in nanoseconds and the count.
+    /// The latency is uniformly distributed within a bin. The bins should be in increasing order
+    /// without overlapping.
+    pub fn from_histogram(bins: Vec<(f64, f64, f64)>) -> Result<Self, IoError> {
+        let total = bins.iter().map(|bin| bin.2).sum::<f64>();
+        if bins.is_empty() || total <= 0.0 || bins.iter().any(|bin| bin.2 < 0.0) {
+            return Err(IoError::new(
+                ErrorKind::InvalidData,
+                "the histogram should have a positive count",
+            ));
+        }
+        let mut quantiles = Vec::with_capacity(bins.len() * 2);
+        let mut cum = 0.0;
+        for (lower, upper, count) in bins {
+            quantiles.push((cum / total, lower));
+            cum += count;
+            quantiles.push((cum / total, upper));
+        }
+        // Prevents the rounding error from leaving the last probability short of 1.
+        quantiles.last_mut().unwrap().0 = 1.0;
+        Self::from_quantiles(quantiles)
+    }
+
+    /// Reads the quantile table or the histogram from a CSV file. Each line has either two
+    /// columns, the cumulative probability and the latency of the quantile table, or three
+    /// columns, the lower bound, the upper bound, and the count of a histogram bin. A header line
+    /// and lines starting with `#` are skipped.
+    pub fn from_csv_file(filepath: &str) -> Result<Self, IoError> {
+        let content = fs::read_to_string(filepath)?;
+        let mut rows = Vec::new();
+        for (line_no, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let row = line
+                .split(',')
+                .map(|col| col.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>();
+            match row {
+                Ok(row) => rows.push(row),
+                // The header
+                Err(_) if rows.is_empty() => {}
+                Err(_) => {
+                    return Err(IoError::new(
+                        ErrorKind::InvalidData,
+                        format!("invalid number at line {}", line_no + 1),
+                    ));
+                }
+            }
+        }
+        Self::from_rows(rows)
+    }
+
+    /// Reads the quantile table or the histogram from a JSON file, which is an object with either
+    /// the `quantiles` field, an array of `[probability, latency]`, or the `histogram` field, an
+    /// array of `[lower, upper, count]`. Requires the `serde_json` feature.
+    ///
+    /// ```text
+    /// {"quantiles": [[0.0, 500000], [0.5, 1000000], [0.99, 5000000], [1.0, 20000000]]}
+    /// ```
+    #[cfg(feature = "serde_json")]
+    pub fn from_json_file(filepath: &str) -> Result<Self, IoError> {
+        let content = fs::read_to_string(filepath)?;
+        let value = serde_json::from_str::<serde_json::Value>(&content)?;
+        let (field, columns) = if value.get("quantiles").is_some() {
+            ("quantiles", 2)
+        } else {
+            ("histogram", 3)
+        };
+        let rows = value
+            .get(field)
+            .and_then(|rows| serde_json::from_value::<Vec<Vec<f64>>>(rows.clone()).ok())
+            .filter(|rows| rows.iter().all(|row| row.len() == columns))
+            .ok_or_else(|| {
+                IoError::new(
+                    ErrorKind::InvalidData,
+                    "either `quantiles` or `histogram` is required",
+                )
+            })?;
+        Self::from_rows(rows)
+    }
+
+    fn from_rows(rows: Vec<Vec<f64>>) -> Result<Self, IoError> {
+        match rows.first().map(|row| row.len()) {
+            Some(2) if rows.iter().all(|row| row.len() == 2) => {
+                Self::from_quantiles(rows.into_iter().map(|row| (row[0], row[1])).collect())
+            }
+            Some(3) if rows.iter().all(|row| row.len() == 3) => Self::from_histogram(
+                rows.into_iter()
+                    .map(|row| (row[0], row[1], row[2]))
+                    .collect(),
+            ),
+            _ => Err(IoError::new(
+                ErrorKind::InvalidData,
+                "every row should have either two or three columns",
+            )),
+        }
+    }
+
+    /// Returns the latency at the cumulative probability `p`, which is the inverse of the
+    /// cumulative distribution function.
+    pub fn quantile(&self, p: f64) -> f64 {
+        let p = p.clamp(0.0, 1.0);
+        // The first point whose probability is greater than `p`.
+        let i = self
+            .quantiles
+            .partition_point(|&(q, _)| q <= p)
+            .clamp(1, self.quantiles.len() - 1);
+        let (p1, lat1) = self.quantiles[i - 1];
+        let (p2, lat2) = self.quantiles[i];
+        if p2 <= p1 {
+            return lat2;
+        }
+        lat1 + (lat2 - lat1) * (p - p1) / (p2 - p1)
+    }
+}
+
+/// Provides order latency drawn independently for each order from the
+/// [`EmpiricalDistribution`]s by inverse transform sampling. It reproduces the observed shape of
+/// the latency, including its tail, from a small quantile table or histogram, without the full
+/// historical order latency data that [`IntpOrderLatency`] requires.
+///
+/// The random number generator is seeded from the system's entropy unless [`seed`](Self::seed) is
+/// given.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::backtest::models::{EmpiricalDistribution, EmpiricalLatency};
+///
+/// let latency_model = EmpiricalLatency::new(
+///     EmpiricalDistribution::from_csv_file("entry_latency.csv").unwrap(),
+///     EmpiricalDistribution::from_csv_file("response_latency.csv").unwrap(),
+/// )
+/// .seed(1);
+/// ```
+#[derive(Clone)]
+pub struct EmpiricalLatency {
+    entry: EmpiricalDistribution,
+    response: EmpiricalDistribution,
+    rng: StdRng,
+}
+
+impl EmpiricalLatency {
+    /// Constructs an instance of `EmpiricalLatency` that draws the order entry latency from
+    /// `entry` and the order response latency from `response`.
+    pub fn new(entry: EmpiricalDistribution, response: EmpiricalDistribution) -> Self {
+        Self {
+            entry,
+            response,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Seeds the random number generator, so that the same latencies are drawn in every run.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+}
+
+impl LatencyModel for EmpiricalLatency {
+    fn entry(&mut self, _timestamp: i64, _order: &Order) -> i64 {
+        self.entry.quantile(self.rng.gen::<f64>()) as i64
+    }
+
+    fn response(&mut self, _timestamp: i64, _order: &Order) -> i64 {
+        self.response.quantile(self.rng.gen::<f64>()) as i64
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+/// A common stochastic component of feed latency and order latency, such as network congestion,
+/// which makes both of them slow at the same time. Treating them as independent understates the
+/// risk of being slow on both sides simultaneously.
+///
+/// The latency is multiplied by `exp(volatility * x - volatility^2 / 2)`, a lognormal factor whose
+/// mean is `1`, where `x` is a standard normal variable that loads `sqrt(correlation)` on the
+/// congestion shared by feed latency and order latency, and `sqrt(1 - correlation)` on the noise
+/// specific to each latency, so that the correlation between the logarithms of the factors of feed
+/// latency and order latency is `correlation`. The congestion is a deterministic function of time
+/// for the given seed, which changes smoothly over the `interval`.
+///
+/// Use [`order_latency`](Self::order_latency) for order latency, and
+/// [`AssetBuilder::correlated_feed_latency`](crate::backtest::AssetBuilder::correlated_feed_latency)
+/// with the same instance for feed latency.
+#[derive(Clone, Copy, Debug)]
+pub struct LatencyCorrelation {
+    interval: i64,
+    correlation: f64,
+    volatility: f64,
+    seed: u64,
+}
+
+impl LatencyCorrelation {
+    /// Constructs an instance of `LatencyCorrelation`.
+    ///
+    /// * `interval` - The interval in nanoseconds over which the congestion changes.
+    /// * `correlation` - The correlation between feed latency and order latency, from `0` to `1`.
+    /// * `volatility` - The standard deviation of the logarithm of the latency factor.
+    pub fn new(interval: i64, correlation: f64, volatility: f64) -> Self {
+        Self {
+            interval,
+            correlation: correlation.clamp(0.0, 1.0),
+            volatility,
+            seed: 0,
+        }
+    }
+
+    /// Seeds the congestion and the noise, so that the same latencies are drawn in every run. The
+    /// default value is `0`.
+    pub fn seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// Wraps the order latency model to apply the latency factor.
+    pub fn order_latency<LM: LatencyModel>(&self, base: LM) -> CorrelatedLatency<LM> {
+        CorrelatedLatency {
+            base,
+            correlation: *self,
+            rng: StdRng::seed_from_u64(self.seed),
+        }
+    }
+
+    /// Returns the random number generator for the noise specific to the feed latency of the data
+    /// that begins at the `timestamp`.
+    pub(crate) fn feed_rng(&self, timestamp: i64) -> StdRng {
+        StdRng::seed_from_u64(self.bucket_seed(timestamp as u64 ^ 0x5555_5555_5555_5555))
+    }
+
+    /// Returns the latency factor at the `timestamp` with the specific noise drawn from `rng`.
+    pub(crate) fn factor<R: Rng>(&self, timestamp: i64, rng: &mut R) -> f64 {
+        let x = self.correlation.sqrt() * self.congestion(timestamp)
+            + (1.0 - self.correlation).sqrt() * standard_normal(rng);
+        (self.volatility * x - self.volatility * self.volatility / 2.0).exp()
+    }
+
+    fn bucket_seed(&self, bucket: u64) -> u64 {
+        self.seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ bucket
+    }
+
+    // Interpolates the standard normal variables drawn at the bucket boundaries, normalized to
+    // keep the unit variance between the boundaries.
+    fn congestion(&self, timestamp: i64) -> f64 {
+        let bucket = timestamp.div_euclid(self.interval);
+        let w = timestamp.rem_euclid(self.interval) as f64 / self.interval as f64;
+        let z1 = standard_normal(&mut StdRng::seed_from_u64(self.bucket_seed(bucket as u64)));
+        let z2 = standard_normal(&mut StdRng::seed_from_u64(
+            self.bucket_seed((bucket + 1) as u64),
+        ));
+        ((1.0 - w) * z1 + w * z2) / ((1.0 - w).powi(2) + w * w).sqrt()
+    }
+}
+
+/// Provides order latency that shares the common stochastic component with feed latency, on top
+/// of the base latency model. See [`LatencyCorrelation`].
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::models::{ConstantLatency, LatencyCorrelation};
+///
+/// let correlation = LatencyCorrelation::new(1_000_000_000, 0.8, 0.5).seed(1);
+/// let latency_model = correlation.order_latency(ConstantLatency::new(1_000_000, 1_000_000));
+/// ```
+#[derive(Clone)]
+pub struct CorrelatedLatency<LM> {
+    base: LM,
+    correlation: LatencyCorrelation,
+    rng: StdRng,
+}
+
+impl<LM> LatencyModel for CorrelatedLatency<LM>
+where
+    LM: LatencyModel,
+{
+    fn entry(&mut self, timestamp: i64, order: &Order) -> i64 {
+        let latency = self.base.entry(timestamp, order);
+        (latency as f64 * self.correlation.factor(timestamp, &mut self.rng)) as i64
+    }
+
+    fn request_entry(&mut self, timestamp: i64, order: &Order, request: RequestType) -> i64 {
+        let latency = self.base.request_entry(timestamp, order, request);
+        (latency as f64 * self.correlation.factor(timestamp, &mut self.rng)) as i64
+    }
+
+    fn response(&mut self, timestamp: i64, order: &Order) -> i64 {
+        let latency = self.base.response(timestamp, order);
+        (latency as f64 * self.correlation.factor(timestamp, &mut self.rng)) as i64
+    }
+
+    fn observe(&mut self, ev: &Event) {
+        self.base.observe(ev);
+    }
+
+    // The congestion shared with feed latency is seeded by `LatencyCorrelation` and remains the
+    // same, and only the noise specific to order latency is reseeded.
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(derive_seed(seed, 0));
+        self.base.set_seed(derive_seed(seed, 1));
+    }
+}
+
+// Applies `f` to the magnitude of the latency, keeping the sign, since negative latency indicates
+// an order rejection and needs to remain negative.
+fn map_magnitude(latency: i64, f: impl FnOnce(i64) -> i64) -> i64 {
+    if latency < 0 {
+        -f(-latency)
+    } else {
+        f(latency)
+    }
+}
+
+/// Adds random noise drawn from the normal distribution with the standard deviation in
+/// nanoseconds to the latency of the base latency model, which allows sensitivity analysis around
+/// a baseline such as [`IntpOrderLatency`]. The latency with the noise doesn't go below zero, and
+/// the noise applies to the magnitude of negative latency, which indicates an order rejection, so
+/// that the rejection is kept. It can be combined with [`Clamp`] to bound the latency.
+///
+/// The random number generator is seeded from the system's entropy unless [`seed`](Self::seed) is
+/// given.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::backtest::{
+///     models::{Clamp, IntpOrderLatency, Jitter},
+///     DataSource,
+/// };
+///
+/// let latency_model = Clamp::new(
+///     Jitter::new(
+///         IntpOrderLatency::new(vec![DataSource::File("latency_20240215.npz".to_string())]),
+///         200_000,
+///     )
+///     .seed(1),
+///     100_000,
+///     50_000_000,
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Jitter<LM> {
+    base: LM,
+    std: f64,
+    rng: StdRng,
+}
+
+impl<LM> Jitter<LM>
+where
+    LM: LatencyModel,
+{
+    /// Constructs an instance of `Jitter` with the standard deviation of the noise in nanoseconds.
+    pub fn new(base: LM, std: i64) -> Self {
+        Self {
+            base,
+            std: std as f64,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Seeds the random number generator, so that the same noise is drawn in every run.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    fn jitter(&mut self, latency: i64) -> i64 {
+        let noise = (self.std * standard_normal(&mut self.rng)) as i64;
+        // The magnitude of a rejection cannot reach zero, which would turn it into an acceptance.
+        let min = if latency < 0 { 1 } else { 0 };
+        map_magnitude(latency, |latency| (latency + noise).max(min))
+    }
+}
+
+impl<LM> LatencyModel for Jitter<LM>
+where
+    LM: LatencyModel,
+{
+    fn entry(&mut self, timestamp: i64, order: &Order) -> i64 {
+        let latency = self.base.entry(timestamp, order);
+        self.jitter(latency)
+    }
+
+    fn request_entry(&mut self, timestamp: i64, order: &Order, request: RequestType) -> i64 {
+        let latency = self.base.request_entry(timestamp, order, request);
+        self.jitter(latency)
+    }
+
+    fn response(&mut self, timestamp: i64, order: &Order) -> i64 {
+        let latency = self.base.response(timestamp, order);
+        self.jitter(latency)
+    }
+
+    fn observe(&mut self, ev: &Event) {
+        self.base.observe(ev);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(derive_seed(seed, 0));
+        self.base.set_seed(derive_seed(seed, 1));
+    }
+}
+
+/// Bounds the latency of the base latency model within `[min, max]` in nanoseconds, such as the
+/// physical minimum of the network round trip and the timeout. The bounds apply to the magnitude of
+/// negative latency, which indicates an order rejection, so that the rejection is kept. See
+/// [`Jitter`].
+#[derive(Clone)]
+pub struct Clamp<LM> {
+    base: LM,
+    min: i64,
+    max: i64,
+}
+
+impl<LM> Clamp<LM>
+where
+    LM: LatencyModel,
+{
+    /// Constructs an instance of `Clamp`. Use `0` for `min` or [`i64::MAX`] for `max` to bound the
+    /// latency only on one side.
+    pub fn new(base: LM, min: i64, max: i64) -> Self {
+        Self { base, min, max }
+    }
+
+    fn clamp(&self, latency: i64) -> i64 {
+        map_magnitude(latency, |latency| latency.clamp(self.min, self.max))
+    }
+}
+
+impl<LM> LatencyModel for Clamp<LM>
+where
+    LM: LatencyModel,
+{
+    fn entry(&mut self, timestamp: i64, order: &Order) -> i64 {
+        let latency = self.base.entry(timestamp, order);
+        self.clamp(latency)
+    }
+
+    fn request_entry(&mut self, timestamp: i64, order: &Order, request: RequestType) -> i64 {
+        let latency = self.base.request_entry(timestamp, order, request);
+        self.clamp(latency)
+    }
+
+    fn response(&mut self, timestamp: i64, order: &Order) -> i64 {
+        let latency = self.base.response(timestamp, order);
+        self.clamp(latency)
+    }
+
+    fn observe(&mut self, ev: &Event) {
+        self.base.observe(ev);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.base.set_seed(seed);
+    }
+}
+
+/// Applies a fixed scale and offset to the latency of the base latency model, as
+/// `latency * scale + offset` in nanoseconds, so that the assets of a multi-asset backtest can
+/// share the same base latency model, such as [`IntpOrderLatency`] built from the same data, while
+/// reflecting that some symbols are served from different gateways. The scale and offset apply to
+/// the magnitude of negative latency, which indicates an order rejection, so that the rejection is
+/// kept, and the latency doesn't go below zero.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::backtest::{
+///     models::{IntpOrderLatency, LatencyOffset},
+///     DataSource,
+/// };
+///
+/// let base = IntpOrderLatency::new(vec![DataSource::File("latency_20240215.npz".to_string())]);
+/// // The second symbol is served from the gateway 300us farther away.
+/// let asset1 = LatencyOffset::new(base.clone(), 1.0, 0);
+/// let asset2 = LatencyOffset::new(base, 1.0, 300_000);
+/// ```
+#[derive(Clone)]
+pub struct LatencyOffset<LM> {
+    base: LM,
+    scale: f64,
+    offset: i64,
+}
+
+impl<LM> LatencyOffset<LM>
+where
+    LM: LatencyModel,
+{
+    /// Constructs an instance of `LatencyOffset`.
+    pub fn new(base: LM, scale: f64, offset: i64) -> Self {
+        Self {
+            base,
+            scale,
+            offset,
+        }
+    }
+
+    fn apply(&self, latency: i64) -> i64 {
+        map_magnitude(latency, |latency| {
+            ((latency as f64 * self.scale) as i64 + self.offset).max(0)
+        })
+    }
+}
+
+impl<LM> LatencyModel for LatencyOffset<LM>
+where
+    LM: LatencyModel,
+{
+    fn entry(&mut self, timestamp: i64, order: &Order) -> i64 {
+        let latency = self.base.entry(timestamp, order);
+        self.apply(latency)
+    }
+
+    fn request_entry(&mut self, timestamp: i64, order: &Order, request: RequestType) -> i64 {
+        let latency = self.base.request_entry(timestamp, order, request);
+        self.apply(latency)
+    }
+
+    fn response(&mut self, timestamp: i64, order: &Order) -> i64 {
+        let latency = self.base.response(timestamp, order);
+        self.apply(latency)
+    }
+
+    fn observe(&mut self, ev: &Event) {
+        self.base.observe(ev);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.base.set_seed(seed);
+    }
+}
+
+/// The kind of events counted as market activity by [`ActivityScaling`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActivityKind {
+    /// Counts trades, including fills of Level-3 Market-By-Order data.
+    Trades,
+    /// Counts depth updates, including order additions, cancellations, and modifications of
+    /// Level-3 Market-By-Order data.
+    DepthUpdates,
+    /// Counts all exchange-side events.
+    All,
+}
+
+impl ActivityKind {
+    fn matches(&self, ev: &Event) -> bool {
+        if ev.ev & EXCH_EVENT == 0 {
+            return false;
+        }
+        let kind = ev.ev & 0xff;
+        match self {
+            Self::Trades => kind == TRADE_EVENT || kind == FILL_EVENT,
+            Self::DepthUpdates => {
+                kind == DEPTH_EVENT
+                    || kind == ADD_ORDER_EVENT
+                    || kind == CANCEL_ORDER_EVENT
+                    || kind == MODIFY_ORDER_EVENT
+            }
+            Self::All => true,
+        }
+    }
+}
+
+/// Scales latency with the recent market activity, which is the number of events of the
+/// [`ActivityKind`] over the trailing window. With activity at or below the `baseline`, the latency
+/// is unchanged, and above it, the latency is multiplied by
+/// `1 + sensitivity * (activity / baseline - 1)`, up to the maximum scale.
+///
+/// It is used by [`ActivityLatency`] for order latency and by
+/// [`ActivityFeedLatency`](crate::backtest::reader::ActivityFeedLatency) for feed latency.
+#[derive(Clone, Copy, Debug)]
+pub struct ActivityScaling {
+    kind: ActivityKind,
+    window: i64,
+    baseline: f64,
+    sensitivity: f64,
+    max_scale: f64,
+}
+
+impl ActivityScaling {
+    /// Constructs an instance of `ActivityScaling` that counts the events of the `kind` over the
+    /// trailing `window` in nanoseconds, where `baseline` is the number of events in the window
+    /// under normal conditions.
+    pub fn new(kind: ActivityKind, window: i64, baseline: f64) -> Self {
+        Self {
+            kind,
+            window,
+            baseline,
+            sensitivity: 1.0,
+            max_scale: 10.0,
+        }
+    }
+
+    /// Sets how strongly the latency increases with the activity above the baseline. The default
+    /// value is `1`, with which the latency is proportional to the activity above the baseline.
+    pub fn sensitivity(self, sensitivity: f64) -> Self {
+        Self {
+            sensitivity,
+            ..self
+        }
+    }
+
+    /// Sets the maximum scale of the latency. The default value is `10`.
+    pub fn max_scale(self, max_scale: f64) -> Self {
+        Self { max_scale, ..self }
+    }
+
+    /// Returns the scale of the latency for the number of events in the window.
+    pub fn scale(&self, activity: usize) -> f64 {
+        let excess = (activity as f64 / self.baseline - 1.0).max(0.0);
+        (1.0 + self.sensitivity * excess).min(self.max_scale)
+    }
+
+    /// Constructs the meter that counts the events for this scaling.
+    pub(crate) fn meter(&self) -> ActivityMeter {
+        ActivityMeter {
+            kind: self.kind,
+            window: self.window,
+            timestamps: VecDeque::new(),
+        }
+    }
+}
+
+/// Counts the events of the [`ActivityKind`] over the trailing window.
+#[derive(Clone, Debug)]
+pub(crate) struct ActivityMeter {
+    kind: ActivityKind,
+    window: i64,
+    timestamps: VecDeque<i64>,
+}
+
+impl ActivityMeter {
+    /// Records the event if it is of the kind counted, by its exchange timestamp.
+    pub fn observe(&mut self, ev: &Event) {
+        if self.kind.matches(ev) {
+            self.timestamps.push_back(ev.exch_ts);
+        }
+    }
+
+    /// Returns the number of events within the window that ends at the `timestamp`.
+    pub fn count(&mut self, timestamp: i64) -> usize {
+        while let Some(&ts) = self.timestamps.front() {
+            if ts > timestamp - self.window {
+                break;
+            }
+            self.timestamps.pop_front();
+        }
+        self.timestamps.len()
+    }
+}
+
+/// Provides order latency that increases with the market activity observed by the exchange
+/// processor, on top of the base latency model. Exchanges and networks tend to slow down exactly
+/// when the market is busy, which is also when a market maker most needs to cancel its orders, so
+/// the correlation matters for realistic results.
+///
+/// The activity meter is shared by the clones of the model, so that the instance used by the local
+/// processor also sees the activity observed by the exchange processor. Therefore, a separate
+/// instance should be constructed for each asset, rather than cloning one across assets.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::models::{
+///     ActivityKind,
+///     ActivityLatency,
+///     ActivityScaling,
+///     ConstantLatency,
+/// };
+///
+/// let latency_model = ActivityLatency::new(
+///     ConstantLatency::new(1_000_000, 1_000_000),
+///     ActivityScaling::new(ActivityKind::Trades, 100_000_000, 50.0).max_scale(5.0),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct ActivityLatency<LM> {
+    base: LM,
+    scaling: ActivityScaling,
+    meter: Rc<RefCell<ActivityMeter>>,
+}
+
+impl<LM> ActivityLatency<LM>
+where
+    LM: LatencyModel,
+{
+    /// Constructs an instance of `ActivityLatency`.
+    pub fn new(base: LM, scaling: ActivityScaling) -> Self {
+        Self {
+            base,
+            meter: Rc::new(RefCell::new(scaling.meter())),
+            scaling,
+        }
+    }
+
+    fn scale(&self, timestamp: i64, latency: i64) -> i64 {
+        let activity = self.meter.borrow_mut().count(timestamp);
+        (latency as f64 * self.scaling.scale(activity)) as i64
+    }
+}
+
+impl<LM> LatencyModel for ActivityLatency<LM>
+where
+    LM: LatencyModel,
+{
+    fn entry(&mut self, timestamp: i64, order: &Order) -> i64 {
+        let latency = self.base.entry(timestamp, order);
+        self.scale(timestamp, latency)
+    }
+
+    fn request_entry(&mut self, timestamp: i64, order: &Order, request: RequestType) -> i64 {
+        let latency = self.base.request_entry(timestamp, order, request);
+        self.scale(timestamp, latency)
+    }
+
+    fn response(&mut self, timestamp: i64, order: &Order) -> i64 {
+        let latency = self.base.response(timestamp, order);
+        self.scale(timestamp, latency)
+    }
+
+    fn observe(&mut self, ev: &Event) {
+        self.meter.borrow_mut().observe(ev);
+        self.base.observe(ev);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.base.set_seed(seed);
+    }
+}
+
+/// Provides the order entry latency that differs by the [`RequestType`], by using a separate
+/// latency model for each type of request. The order response latency is given by the model for
+/// new orders, which also gives the entry latency of modifications.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::models::{ConstantLatency, RequestTypeLatency};
+///
+/// // Cancels are prioritized and take 200us, while new orders take 1ms.
+/// let latency_model = RequestTypeLatency::new(
+///     ConstantLatency::new(1_000_000, 1_000_000),
+///     ConstantLatency::new(200_000, 1_000_000),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct RequestTypeLatency<NM, CM> {
+    new: NM,
+    cancel: CM,
+}
+
+impl<NM, CM> RequestTypeLatency<NM, CM>
+where
+    NM: LatencyModel,
+    CM: LatencyModel,
+{
+    /// Constructs an instance of `RequestTypeLatency` with the latency model for new orders and
+    /// the latency model for cancels.
+    pub fn new(new: NM, cancel: CM) -> Self {
+        Self { new, cancel }
+    }
+}
+
+impl<NM, CM> LatencyModel for RequestTypeLatency<NM, CM>
+where
+    NM: LatencyModel,
+    CM: LatencyModel,
+{
+    fn entry(&mut self, timestamp: i64, order: &Order) -> i64 {
+        self.new.entry(timestamp, order)
+    }
+
+    fn request_entry(&mut self, timestamp: i64, order: &Order, request: RequestType) -> i64 {
+        match request {
+            RequestType::New | RequestType::Modify => {
+                self.new.request_entry(timestamp, order, request)
+            }
+            RequestType::Cancel | RequestType::Query => {
+                self.cancel.request_entry(timestamp, order, request)
+            }
+        }
+    }
+
+    fn response(&mut self, timestamp: i64, order: &Order) -> i64 {
+        self.new.response(timestamp, order)
+    }
+
+    fn observe(&mut self, ev: &Event) {
+        self.new.observe(ev);
+        self.cancel.observe(ev);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.new.set_seed(derive_seed(seed, 0));
+        self.cancel.set_seed(derive_seed(seed, 1));
+    }
+}
+
 /// The historical order latency data
 #[repr(C, align(32))]
 #[derive(Clone, Debug, NpyDTyped)]
@@ -70,6 +1109,36 @@ pub struct OrderLatencyRow {
 
 unsafe impl POD for OrderLatencyRow {}
 
+impl OrderLatencyRow {
+    /// Returns the [`CsvSchema`] for a CSV file whose header has the `req_ts`, `exch_ts`, and
+    /// `resp_ts` columns. For other column names, map the columns to these fields in a custom
+    /// schema.
+    pub fn csv_schema() -> CsvSchema {
+        CsvSchema::new()
+            .column("req_ts", "req_ts")
+            .column("exch_ts", "exch_ts")
+            .column("resp_ts", "resp_ts")
+    }
+}
+
+impl DataFilter<OrderLatencyRow> for TimeScale {
+    fn filter(&self, data: &Data<OrderLatencyRow>) -> Option<Data<OrderLatencyRow>> {
+        let unit = self.unit();
+        if unit == TimeUnit::Nanosecond {
+            return None;
+        }
+        let rows = (0..data.len())
+            .map(|row| OrderLatencyRow {
+                req_ts: unit.to_nanos(data[row].req_ts),
+                exch_ts: unit.to_nanos(data[row].exch_ts),
+                resp_ts: unit.to_nanos(data[row].resp_ts),
+                ..data[row].clone()
+            })
+            .collect::<Vec<_>>();
+        Some(Data::from_slice(&rows))
+    }
+}
+
 /// Provides order latency based on actual historical order latency data through interpolation.
 ///
 /// However, if you don't have the actual order latency history, you can generate order latencies
@@ -85,6 +1154,10 @@ unsafe impl POD for OrderLatencyRow {}
 /// exchange, and its value represents the latency that the local experiences when receiving the
 /// rejection notification.
 ///
+/// The order latency data can be read from `.npy` and `.npz` files, and, with the `parquet` or
+/// `arrow` feature, from Parquet or Arrow IPC files whose columns are named after the fields of
+/// [`OrderLatencyRow`]. CSV files can be read by [`build_from_csv`](Self::build_from_csv).
+///
 /// **Example**
 /// ```
 /// use hftbacktest::backtest::{DataSource, models::IntpOrderLatency};
@@ -105,7 +1178,20 @@ pub struct IntpOrderLatency {
 impl IntpOrderLatency {
     /// Constructs an instance of `IntpOrderLatency`.
     pub fn build(data: Vec<DataSource<OrderLatencyRow>>) -> Result<Self, BacktestError> {
+        Self::build_with_time_unit(data, TimeUnit::Nanosecond)
+    }
+
+    /// Constructs an instance of `IntpOrderLatency` from the order latency data recorded in the
+    /// `unit`, whose timestamps are converted into nanoseconds, the time unit of the engine, as
+    /// they are read.
+    pub fn build_with_time_unit(
+        data: Vec<DataSource<OrderLatencyRow>>,
+        unit: TimeUnit,
+    ) -> Result<Self, BacktestError> {
         let mut reader = Reader::new(Cache::new());
+        if unit != TimeUnit::Nanosecond {
+            reader.add_filter(TimeScale::new(unit));
+        }
         for file in data {
             match file {
                 DataSource::File(file) => {
@@ -114,6 +1200,15 @@ impl IntpOrderLatency {
                 DataSource::Data(data) => {
                     reader.add_data(data);
                 }
+                DataSource::Url(url) => {
+                    reader.add_url(url);
+                }
+                DataSource::Query(query) => {
+                    reader.add_query(query);
+                }
+                DataSource::Iterator(generator) => {
+                    reader.add_generator(generator);
+                }
             }
         }
         let data = match reader.next() {
@@ -135,6 +1230,36 @@ impl IntpOrderLatency {
         })
     }
 
+    /// Constructs an instance of `IntpOrderLatency` from the order latency data in CSV files,
+    /// whose columns are mapped by the `schema` and whose timestamps are recorded in the `unit`.
+    /// See [`OrderLatencyRow::csv_schema`].
+    ///
+    /// **Example**
+    /// ```no_run
+    /// use hftbacktest::{
+    ///     backtest::models::{IntpOrderLatency, OrderLatencyRow},
+    ///     types::TimeUnit,
+    /// };
+    ///
+    /// let latency_model = IntpOrderLatency::build_from_csv(
+    ///     &["latency_20240215.csv"],
+    ///     &OrderLatencyRow::csv_schema(),
+    ///     TimeUnit::Microsecond,
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn build_from_csv(
+        filepaths: &[&str],
+        schema: &CsvSchema,
+        unit: TimeUnit,
+    ) -> Result<Self, BacktestError> {
+        let data = filepaths
+            .iter()
+            .map(|filepath| Ok(DataSource::Data(read_csv_file(filepath, schema)?)))
+            .collect::<Result<Vec<_>, BacktestError>>()?;
+        Self::build_with_time_unit(data, unit)
+    }
+
     /// Constructs an instance of `IntpOrderLatency`.
     pub fn new(data: Vec<DataSource<OrderLatencyRow>>) -> Self {
         Self::build(data).unwrap()
@@ -270,3 +1395,230 @@ impl LatencyModel for IntpOrderLatency {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{
+        ActivityKind,
+        ActivityLatency,
+        ActivityScaling,
+        Clamp,
+        ConstantLatency,
+        EmpiricalDistribution,
+        IntpOrderLatency,
+        Jitter,
+        LatencyCorrelation,
+        LatencyDistribution,
+        LatencyModel,
+        LatencyOffset,
+        OrderLatencyRow,
+        RequestType,
+        RequestTypeLatency,
+        StochasticLatency,
+    };
+    use crate::types::{
+        Event,
+        OrdType,
+        Order,
+        Side,
+        TimeInForce,
+        TimeUnit,
+        EXCH_EVENT,
+        LOCAL_EVENT,
+        TRADE_EVENT,
+    };
+
+    #[test]
+    fn test_stochastic_latency() {
+        let order = Order::new(1, 0, 0.01, 1.0, Side::Buy, OrdType::Limit, TimeInForce::GTC);
+        for dist in [
+            LatencyDistribution::lognormal_from_moments(2_000_000.0, 1_000_000.0),
+            LatencyDistribution::gamma_from_moments(2_000_000.0, 1_000_000.0),
+            LatencyDistribution::gamma_from_moments(2_000_000.0, 4_000_000.0),
+        ] {
+            let mut model = StochasticLatency::new(dist, dist).floor(100_000).seed(1);
+            let n = 100_000;
+            let latencies = (0..n).map(|i| model.entry(i, &order)).collect::<Vec<_>>();
+            assert!(latencies.iter().all(|&lat| lat >= 100_000));
+            let mean = latencies.iter().sum::<i64>() as f64 / n as f64;
+            assert!((mean - 2_000_000.0).abs() < 100_000.0, "{dist:?} {mean}");
+
+            let mut same = StochasticLatency::new(dist, dist).floor(100_000).seed(1);
+            assert!((0..100).all(|i| same.entry(i, &order) == latencies[i as usize]));
+        }
+    }
+
+    #[test]
+    fn test_activity_latency() {
+        let order = Order::new(1, 0, 0.01, 1.0, Side::Buy, OrdType::Limit, TimeInForce::GTC);
+        let trade = |ts| Event {
+            ev: EXCH_EVENT | LOCAL_EVENT | TRADE_EVENT,
+            exch_ts: ts,
+            local_ts: ts + 1,
+            px: 1.0,
+            qty: 1.0,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        };
+        let mut exch = ActivityLatency::new(
+            ConstantLatency::new(100, 200),
+            ActivityScaling::new(ActivityKind::Trades, 10, 2.0).max_scale(3.0),
+        );
+        let mut local = exch.clone();
+
+        // Only the events observed by the exchange processor's instance count.
+        for ts in [1, 2, 3, 4] {
+            exch.observe(&trade(ts));
+        }
+        assert_eq!(local.entry(5, &order), 200);
+        assert_eq!(local.response(5, &order), 400);
+        // The events at or before 2 fall out of the window that ends at 12.
+        assert_eq!(local.entry(12, &order), 100);
+
+        for ts in 20..40 {
+            exch.observe(&trade(ts));
+        }
+        assert_eq!(local.entry(40, &order), 300);
+    }
+
+    #[test]
+    fn test_request_type_latency() {
+        let order = Order::new(1, 0, 0.01, 1.0, Side::Buy, OrdType::Limit, TimeInForce::GTC);
+        let mut model = RequestTypeLatency::new(
+            ConstantLatency::new(100, 300),
+            ConstantLatency::new(20, 400),
+        );
+        assert_eq!(model.request_entry(0, &order, RequestType::New), 100);
+        assert_eq!(model.request_entry(0, &order, RequestType::Cancel), 20);
+        assert_eq!(model.response(0, &order), 300);
+
+        // Models that don't distinguish the type of request use the same entry latency.
+        let mut model = ConstantLatency::new(100, 300);
+        assert_eq!(model.request_entry(0, &order, RequestType::Cancel), 100);
+    }
+
+    #[test]
+    fn test_empirical_distribution() {
+        let dist = EmpiricalDistribution::from_quantiles(vec![
+            (0.0, 100.0),
+            (0.5, 200.0),
+            (0.9, 1000.0),
+            (1.0, 5000.0),
+        ])
+        .unwrap();
+        assert_eq!(dist.quantile(0.0), 100.0);
+        assert_eq!(dist.quantile(0.25), 150.0);
+        assert!((dist.quantile(0.7) - 600.0).abs() < 1e-9);
+        assert_eq!(dist.quantile(1.0), 5000.0);
+
+        // The empty bin in the middle makes a jump in the latency.
+        let dist = EmpiricalDistribution::from_histogram(vec![
+            (100.0, 200.0, 3.0),
+            (200.0, 300.0, 0.0),
+            (300.0, 400.0, 1.0),
+        ])
+        .unwrap();
+        assert_eq!(dist.quantile(0.375), 150.0);
+        assert_eq!(dist.quantile(0.875), 350.0);
+
+        assert!(EmpiricalDistribution::from_quantiles(vec![(0.0, 1.0), (0.9, 2.0)]).is_err());
+        assert!(EmpiricalDistribution::from_quantiles(vec![(0.0, 2.0), (1.0, 1.0)]).is_err());
+    }
+
+    #[test]
+    fn test_latency_correlation() {
+        let order = Order::new(1, 0, 0.01, 1.0, Side::Buy, OrdType::Limit, TimeInForce::GTC);
+        // Without the specific noise, the order latency factor is the same as the feed latency
+        // factor at the same time.
+        let correlation = LatencyCorrelation::new(1_000, 1.0, 0.5).seed(1);
+        let mut model = correlation.order_latency(ConstantLatency::new(1_000_000, 1_000_000));
+        let mut rng = correlation.feed_rng(0);
+        for ts in (0..10_000).step_by(250) {
+            let factor = correlation.factor(ts, &mut rng);
+            assert_eq!(model.entry(ts, &order), (1_000_000.0 * factor) as i64);
+        }
+
+        // The factor has the mean of 1.
+        let correlation = LatencyCorrelation::new(1_000, 0.5, 0.5).seed(1);
+        let mut rng = correlation.feed_rng(0);
+        let n = 100_000;
+        let mean = (0..n)
+            .map(|i| correlation.factor(i * 100, &mut rng))
+            .sum::<f64>()
+            / n as f64;
+        assert!((mean - 1.0).abs() < 0.05, "{mean}");
+    }
+
+    #[test]
+    fn test_intp_order_latency_from_csv() {
+        let order = Order::new(1, 0, 0.01, 1.0, Side::Buy, OrdType::Limit, TimeInForce::GTC);
+        let path = env::temp_dir().join(format!("{}.csv", uuid::Uuid::new_v4()));
+        fs::write(
+            &path,
+            "req_ts,exch_ts,resp_ts\n100,110,130\n200,230,250\n300,310,320\n",
+        )
+        .unwrap();
+        let mut model = IntpOrderLatency::build_from_csv(
+            &[path.to_str().unwrap()],
+            &OrderLatencyRow::csv_schema(),
+            TimeUnit::Microsecond,
+        )
+        .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(model.entry(150_000, &order), 20_000);
+        assert_eq!(model.response(270_000, &order), 15_000);
+    }
+
+    #[test]
+    fn test_jitter_clamp() {
+        let order = Order::new(1, 0, 0.01, 1.0, Side::Buy, OrdType::Limit, TimeInForce::GTC);
+        let mut model = Clamp::new(
+            Jitter::new(ConstantLatency::new(1_000, -1_000), 500).seed(1),
+            800,
+            1_200,
+        );
+        let mut jittered = false;
+        for ts in 0..1_000 {
+            let entry = model.entry(ts, &order);
+            assert!((800..=1_200).contains(&entry));
+            jittered |= entry != 1_000;
+            // The rejection is kept.
+            let response = model.response(ts, &order);
+            assert!((-1_200..=-800).contains(&response));
+        }
+        assert!(jittered);
+    }
+
+    #[test]
+    fn test_latency_offset() {
+        let order = Order::new(1, 0, 0.01, 1.0, Side::Buy, OrdType::Limit, TimeInForce::GTC);
+        let base = ConstantLatency::new(1_000, -2_000);
+        let mut near = LatencyOffset::new(base.clone(), 1.0, 0);
+        let mut far = LatencyOffset::new(base, 1.5, 300);
+        assert_eq!(near.entry(0, &order), 1_000);
+        assert_eq!(far.entry(0, &order), 1_800);
+        // The rejection is kept.
+        assert_eq!(far.response(0, &order), -3_300);
+        let mut closer = LatencyOffset::new(ConstantLatency::new(1_000, 1_000), 1.0, -1_500);
+        assert_eq!(closer.entry(0, &order), 0);
+    }
+
+    #[test]
+    fn test_set_seed() {
+        let order = Order::new(1, 0, 0.01, 1.0, Side::Buy, OrdType::Limit, TimeInForce::GTC);
+        let dist = LatencyDistribution::lognormal_from_moments(2_000_000.0, 1_000_000.0);
+        let model = Jitter::new(StochasticLatency::new(dist, dist), 100_000);
+        let draw = |mut model: Jitter<StochasticLatency>, seed| {
+            model.set_seed(seed);
+            (0..100)
+                .map(|ts| model.entry(ts, &order))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw(model.clone(), 1), draw(model.clone(), 1));
+        assert_ne!(draw(model.clone(), 1), draw(model, 2));
+    }
+}
@@ -1,23 +1,73 @@
-//! Latency and queue position models
+//! Latency, queue position, and fee models
 //!
 //! Please find more details in the documents below.
 //! * [Latency Models](https://hftbacktest.readthedocs.io/en/latest/latency_models.html)
 //! * [Order Fill](https://hftbacktest.readthedocs.io/en/latest/order_fill.html)
+mod adverse;
+mod calibration;
+mod fee;
+mod impact;
 mod latency;
 mod queue;
 
-pub use latency::{ConstantLatency, IntpOrderLatency, LatencyModel, OrderLatencyRow};
-#[cfg(any(feature = "unstable_l3", doc))]
-pub use queue::{L3FIFOQueueModel, L3OrderId, L3OrderSource, L3QueueModel};
+pub use adverse::{AdverseFillFilter, AdverseSelection, ExpAdverseSelection};
+pub use calibration::{LatencyCalibration, QueueCalibration, QueueFit, RecordedOrder};
+pub use fee::{FeeModel, FeeTier, FlatFee, ForeignCurrencyFee, MinimumFee, TieredFee};
+pub use impact::{sweep_price_tick, LinearImpact, MarketImpact, SqrtImpact, TakerImpact};
+pub use latency::{
+    ActivityKind,
+    ActivityLatency,
+    ActivityScaling,
+    Clamp,
+    ConstantLatency,
+    CorrelatedLatency,
+    EmpiricalDistribution,
+    EmpiricalLatency,
+    IntpOrderLatency,
+    Jitter,
+    LatencyCorrelation,
+    LatencyDistribution,
+    LatencyModel,
+    LatencyOffset,
+    OrderLatencyRow,
+    RequestType,
+    RequestTypeLatency,
+    StochasticLatency,
+};
 pub use queue::{
+    CancelRate,
+    DepthDecayQueueModel,
+    ExpDecayCancelRate,
+    HiddenLiquidityQueueModel,
+    HiddenQueuePos,
     LogProbQueueFunc,
     LogProbQueueFunc2,
+    OrderCountQueueModel,
+    OrderCountQueuePos,
     PowerProbQueueFunc,
     PowerProbQueueFunc2,
     PowerProbQueueFunc3,
+    ProRataQueueModel,
+    ProRataQueuePos,
+    ProbForm,
+    ProbQueueFunc,
     ProbQueueModel,
     Probability,
     QueueModel,
     QueuePos,
     RiskAdverseQueueModel,
+    TradeFlowQueueModel,
+    TradeFlowQueuePos,
 };
+#[cfg(any(feature = "unstable_l3", doc))]
+pub use queue::{L3FIFOQueueModel, L3OrderId, L3OrderSource, L3QueueModel};
+
+/// Derives an independent seed for the `stream` from the `seed`, so that the models seeded from
+/// the same seed, such as the models wrapped by another model, don't draw the same random numbers.
+pub fn derive_seed(seed: u64, stream: u64) -> u64 {
+    // SplitMix64
+    let mut z = seed.wrapping_add(stream.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
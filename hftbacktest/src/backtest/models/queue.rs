@@ -1,12 +1,9 @@
-use std::{
-    any::Any,
-    collections::{hash_map::Entry, HashMap},
-    marker::PhantomData,
-};
+use std::{any::Any, collections::HashMap, marker::PhantomData, mem};
 
+#[cfg(any(feature = "unstable_l3", doc, test))]
+use crate::backtest::BacktestError;
 use crate::{
-    backtest::BacktestError,
-    depth::MarketDepth,
+    depth::{MarketDepth, OrderCountDepth, INVALID_MAX, INVALID_MIN},
     types::{AnyClone, Order, Side},
 };
 
@@ -26,6 +23,19 @@ where
     fn depth(&self, order: &mut Order, prev_qty: f64, new_qty: f64, depth: &MD);
 
     fn is_filled(&self, order: &Order, depth: &MD) -> f64;
+
+    /// Returns the estimated quantity ahead of the order in the queue, by which the orders at the
+    /// same price are put in queue order when a trade is allocated to them. The default
+    /// implementation returns `0.0`, for models that don't track the quantity ahead, in which case
+    /// the orders are put in the order they were accepted.
+    fn front_qty(&self, _order: &Order) -> f64 {
+        0.0
+    }
+
+    /// Seeds the random number generator of a stochastic queue model, so that the same results
+    /// are drawn in every run. The default implementation does nothing, which is the case for
+    /// deterministic models.
+    fn set_seed(&mut self, _seed: u64) {}
 }
 
 /// Provides a conservative queue position model, where your order's queue position advances only
@@ -81,6 +91,10 @@ where
             0.0
         }
     }
+
+    fn front_qty(&self, order: &Order) -> f64 {
+        *order.q.as_any().downcast_ref::<f64>().unwrap()
+    }
 }
 
 /// Stores the values needed for queue position estimation and adjustment for [`ProbQueueModel`].
@@ -115,6 +129,16 @@ pub trait Probability {
     fn prob(&self, front: f64, back: f64) -> f64;
 }
 
+/// A closure taking the quantity ahead and behind the order can be used as the probability model.
+impl<F> Probability for F
+where
+    F: Fn(f64, f64) -> f64,
+{
+    fn prob(&self, front: f64, back: f64) -> f64 {
+        self(front, back)
+    }
+}
+
 /// Provides a probability-based queue position model as described in
 /// * `<https://quant.stackexchange.com/questions/3782/how-do-we-estimate-position-of-our-order-in-order-book>`
 /// * `<https://rigtorp.se/2013/06/08/estimating-order-queue-position.html>`
@@ -201,6 +225,744 @@ where
             0.0
         }
     }
+
+    fn front_qty(&self, order: &Order) -> f64 {
+        order
+            .q
+            .as_any()
+            .downcast_ref::<QueuePos>()
+            .unwrap()
+            .front_q_qty
+    }
+}
+
+/// Stores the values needed for queue position estimation and adjustment for
+/// [`TradeFlowQueueModel`].
+#[derive(Clone, Default)]
+pub struct TradeFlowQueuePos {
+    front_q_qty: f64,
+    pending_trade_qty: f64,
+}
+
+impl AnyClone for TradeFlowQueuePos {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Provides a queue position model informed by the trade flow at the order's price level, for
+/// venues where the depth updates lag the trades.
+///
+/// Your order's queue position advances by the volume traded at the level as soon as the trades
+/// are observed. The traded volume stays pending until the depth updates reflect it, even across
+/// several depth updates, so that the quantity decrease by the trades isn't counted again as
+/// cancellations. Only the decrease in excess of the pending traded volume is regarded as
+/// cancellations, which advance the queue position depending on the [`Probability`] model, as in
+/// [`ProbQueueModel`]. The pending traded volume never exceeds the quantity at the level, since
+/// the trades that aren't reflected yet must be within the quantity still shown.
+pub struct TradeFlowQueueModel<P, MD>
+where
+    P: Probability,
+{
+    prob: P,
+    _md_marker: PhantomData<MD>,
+}
+
+impl<P, MD> TradeFlowQueueModel<P, MD>
+where
+    P: Probability,
+{
+    /// Constructs an instance of `TradeFlowQueueModel` with a [`Probability`] model for the
+    /// cancellations.
+    pub fn new(prob: P) -> Self {
+        Self {
+            prob,
+            _md_marker: Default::default(),
+        }
+    }
+}
+
+impl<P, MD> QueueModel<MD> for TradeFlowQueueModel<P, MD>
+where
+    P: Probability,
+    MD: MarketDepth,
+{
+    fn new_order(&self, order: &mut Order, depth: &MD) {
+        let mut q = TradeFlowQueuePos::default();
+        if order.side == Side::Buy {
+            q.front_q_qty = depth.bid_qty_at_tick(order.price_tick);
+        } else {
+            q.front_q_qty = depth.ask_qty_at_tick(order.price_tick);
+        }
+        order.q = Box::new(q);
+    }
+
+    fn trade(&self, order: &mut Order, qty: f64, _depth: &MD) {
+        let q = order
+            .q
+            .as_any_mut()
+            .downcast_mut::<TradeFlowQueuePos>()
+            .unwrap();
+        q.front_q_qty -= qty;
+        q.pending_trade_qty += qty;
+    }
+
+    fn depth(&self, order: &mut Order, prev_qty: f64, new_qty: f64, _depth: &MD) {
+        let q = order
+            .q
+            .as_any_mut()
+            .downcast_mut::<TradeFlowQueuePos>()
+            .unwrap();
+        let chg = prev_qty - new_qty;
+        if chg <= 0.0 {
+            q.pending_trade_qty = q.pending_trade_qty.min(new_qty);
+            q.front_q_qty = q.front_q_qty.min(new_qty);
+            return;
+        }
+
+        // The decrease is attributed to the pending traded volume first.
+        let traded = chg.min(q.pending_trade_qty);
+        q.pending_trade_qty = (q.pending_trade_qty - traded).min(new_qty);
+        let canceled = chg - traded;
+        if canceled <= 0.0 {
+            q.front_q_qty = q.front_q_qty.min(new_qty);
+            return;
+        }
+
+        // The quantity behind the order excludes the part already traded away.
+        let front = q.front_q_qty.max(0.0);
+        let back = (prev_qty - traded - front).max(0.0);
+        let mut prob = self.prob.prob(front, back);
+        if !prob.is_finite() {
+            prob = 1.0;
+        }
+
+        let est_front = q.front_q_qty - (1.0 - prob) * canceled + (back - prob * canceled).min(0.0);
+        q.front_q_qty = est_front.min(new_qty);
+    }
+
+    fn is_filled(&self, order: &Order, depth: &MD) -> f64 {
+        let q = order
+            .q
+            .as_any()
+            .downcast_ref::<TradeFlowQueuePos>()
+            .unwrap();
+        if (q.front_q_qty / depth.lot_size()).round() < 0.0 {
+            (-q.front_q_qty / depth.lot_size()).floor() * depth.lot_size()
+        } else {
+            0.0
+        }
+    }
+
+    fn front_qty(&self, order: &Order) -> f64 {
+        order
+            .q
+            .as_any()
+            .downcast_ref::<TradeFlowQueuePos>()
+            .unwrap()
+            .front_q_qty
+    }
+}
+
+/// Provides the share of the cancellations at a price level that occur ahead of the order, for
+/// [`DepthDecayQueueModel`].
+pub trait CancelRate {
+    /// Returns the share, between 0 and 1, of the canceled quantity at the price level that is
+    /// `distance` ticks away from the best price on the same side, that occurs ahead of the order.
+    fn rate(&self, distance: i64) -> f64;
+}
+
+/// A closure taking the distance from the best price in ticks can be used as the cancellation rate.
+impl<F> CancelRate for F
+where
+    F: Fn(i64) -> f64,
+{
+    fn rate(&self, distance: i64) -> f64 {
+        self(distance)
+    }
+}
+
+/// The cancellation rate that decays exponentially with the distance from the best price, as
+/// `touch_rate * exp(-decay * distance)`. The orders near the touch are cancelled the fastest in
+/// most venues.
+#[derive(Clone, Copy, Debug)]
+pub struct ExpDecayCancelRate {
+    touch_rate: f64,
+    decay: f64,
+}
+
+impl ExpDecayCancelRate {
+    /// Constructs an instance of `ExpDecayCancelRate`.
+    ///
+    /// Arguments
+    /// - `touch_rate`: the share of the cancellations that occur ahead of the order at the best
+    ///   price, between 0 and 1.
+    /// - `decay`: the decay per tick of the distance from the best price.
+    pub fn new(touch_rate: f64, decay: f64) -> Self {
+        Self { touch_rate, decay }
+    }
+}
+
+impl CancelRate for ExpDecayCancelRate {
+    fn rate(&self, distance: i64) -> f64 {
+        self.touch_rate * (-self.decay * distance as f64).exp()
+    }
+}
+
+/// Provides a queue position model where the orders ahead are assumed to be cancelled at a rate
+/// that depends on the distance of the order's price level from the best price on the same side,
+/// calibrated per venue. The existing models treat every depth decrease alike, which
+/// misestimates fills in books where the orders near the touch are cancelled quickly.
+///
+/// Your order's queue position advances by the volume traded at the level, and by the share of
+/// the cancelled quantity, the decrease in excess of the traded volume, given by the
+/// [`CancelRate`]. If the quantity behind the order can't account for the rest of the
+/// cancellation, the remainder is also regarded as occurring ahead.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::{
+///     backtest::models::{DepthDecayQueueModel, ExpDecayCancelRate},
+///     depth::HashMapMarketDepth,
+/// };
+///
+/// let queue_model =
+///     DepthDecayQueueModel::<_, HashMapMarketDepth>::new(ExpDecayCancelRate::new(0.6, 0.3));
+/// ```
+pub struct DepthDecayQueueModel<R, MD>
+where
+    R: CancelRate,
+{
+    cancel_rate: R,
+    _md_marker: PhantomData<MD>,
+}
+
+impl<R, MD> DepthDecayQueueModel<R, MD>
+where
+    R: CancelRate,
+{
+    /// Constructs an instance of `DepthDecayQueueModel` with a [`CancelRate`].
+    pub fn new(cancel_rate: R) -> Self {
+        Self {
+            cancel_rate,
+            _md_marker: Default::default(),
+        }
+    }
+}
+
+impl<R, MD> QueueModel<MD> for DepthDecayQueueModel<R, MD>
+where
+    R: CancelRate,
+    MD: MarketDepth,
+{
+    fn new_order(&self, order: &mut Order, depth: &MD) {
+        let mut q = QueuePos::default();
+        if order.side == Side::Buy {
+            q.front_q_qty = depth.bid_qty_at_tick(order.price_tick);
+        } else {
+            q.front_q_qty = depth.ask_qty_at_tick(order.price_tick);
+        }
+        order.q = Box::new(q);
+    }
+
+    fn trade(&self, order: &mut Order, qty: f64, _depth: &MD) {
+        let q = order.q.as_any_mut().downcast_mut::<QueuePos>().unwrap();
+        q.front_q_qty -= qty;
+        q.cum_trade_qty += qty;
+    }
+
+    fn depth(&self, order: &mut Order, prev_qty: f64, new_qty: f64, depth: &MD) {
+        let distance = if order.side == Side::Buy {
+            depth.best_bid_tick() - order.price_tick
+        } else {
+            order.price_tick - depth.best_ask_tick()
+        };
+        let q = order.q.as_any_mut().downcast_mut::<QueuePos>().unwrap();
+        // Subtracts the quantity change by trades, which is already reflected in the queue
+        // position.
+        let canceled = prev_qty - new_qty - q.cum_trade_qty;
+        q.cum_trade_qty = 0.0;
+        if canceled <= 0.0 {
+            q.front_q_qty = q.front_q_qty.min(new_qty);
+            return;
+        }
+
+        // The distance is invalid if there is no best price.
+        let rate = self.cancel_rate.rate(distance.max(0)).clamp(0.0, 1.0);
+        let back = (prev_qty - q.front_q_qty).max(0.0);
+        let ahead = (rate * canceled).max(canceled - back);
+        q.front_q_qty = (q.front_q_qty - ahead).min(new_qty);
+    }
+
+    fn is_filled(&self, order: &Order, depth: &MD) -> f64 {
+        let q = order.q.as_any().downcast_ref::<QueuePos>().unwrap();
+        if (q.front_q_qty / depth.lot_size()).round() < 0.0 {
+            (-q.front_q_qty / depth.lot_size()).floor() * depth.lot_size()
+        } else {
+            0.0
+        }
+    }
+
+    fn front_qty(&self, order: &Order) -> f64 {
+        order
+            .q
+            .as_any()
+            .downcast_ref::<QueuePos>()
+            .unwrap()
+            .front_q_qty
+    }
+}
+
+/// Stores the hidden quantity ahead of the order and the values of the base queue model for
+/// [`HiddenLiquidityQueueModel`].
+#[derive(Clone)]
+pub struct HiddenQueuePos {
+    hidden_qty: f64,
+    base: Box<dyn AnyClone + Send>,
+}
+
+impl AnyClone for HiddenQueuePos {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Injects hidden liquidity, such as the hidden part of iceberg orders, ahead of the order at each
+/// price level, on top of the base queue model. Backtests on venues with heavy iceberg usage
+/// otherwise overestimate both the queue position and the fill probability from the visible book.
+///
+/// When the order is accepted, the hidden quantity ahead of the order is set to
+/// `qty + ratio * visible`, where `visible` is the visible quantity at the price level. The trades
+/// at the price level consume the hidden quantity first, since they don't show up in the visible
+/// book, and only the rest is passed to the base queue model. The order can't be filled until the
+/// hidden quantity is consumed.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::{
+///     backtest::models::{HiddenLiquidityQueueModel, PowerProbQueueFunc3, ProbQueueModel},
+///     depth::HashMapMarketDepth,
+/// };
+///
+/// // Assumes that the hidden liquidity is half of the visible quantity.
+/// let queue_model = HiddenLiquidityQueueModel::<_, HashMapMarketDepth>::new(
+///     ProbQueueModel::new(PowerProbQueueFunc3::new(3.0)),
+///     0.0,
+///     0.5,
+/// );
+/// ```
+pub struct HiddenLiquidityQueueModel<QM, MD> {
+    base: QM,
+    qty: f64,
+    ratio: f64,
+    _md_marker: PhantomData<MD>,
+}
+
+impl<QM, MD> HiddenLiquidityQueueModel<QM, MD>
+where
+    QM: QueueModel<MD>,
+    MD: MarketDepth,
+{
+    /// Constructs an instance of `HiddenLiquidityQueueModel`.
+    ///
+    /// Arguments
+    /// - `base`: the queue model for the visible quantity.
+    /// - `qty`: the fixed hidden quantity ahead of the order.
+    /// - `ratio`: the hidden quantity ahead of the order relative to the visible quantity at the
+    ///   price level.
+    pub fn new(base: QM, qty: f64, ratio: f64) -> Self {
+        Self {
+            base,
+            qty,
+            ratio,
+            _md_marker: Default::default(),
+        }
+    }
+
+    // Runs `f` with the base queue model's values in `order.q` and the hidden quantity.
+    fn with_base(&self, order: &mut Order, f: impl FnOnce(&mut Order, &mut f64)) {
+        let pos = order
+            .q
+            .as_any_mut()
+            .downcast_mut::<HiddenQueuePos>()
+            .unwrap();
+        let mut hidden_qty = pos.hidden_qty;
+        let base = mem::replace(&mut pos.base, Box::new(()));
+        let mut outer = mem::replace(&mut order.q, base);
+        f(order, &mut hidden_qty);
+        let pos = outer.as_any_mut().downcast_mut::<HiddenQueuePos>().unwrap();
+        pos.hidden_qty = hidden_qty;
+        pos.base = mem::replace(&mut order.q, Box::new(()));
+        order.q = outer;
+    }
+}
+
+impl<QM, MD> QueueModel<MD> for HiddenLiquidityQueueModel<QM, MD>
+where
+    QM: QueueModel<MD>,
+    MD: MarketDepth,
+{
+    fn new_order(&self, order: &mut Order, depth: &MD) {
+        self.base.new_order(order, depth);
+        let visible = if order.side == Side::Buy {
+            depth.bid_qty_at_tick(order.price_tick)
+        } else {
+            depth.ask_qty_at_tick(order.price_tick)
+        };
+        let base = mem::replace(&mut order.q, Box::new(()));
+        order.q = Box::new(HiddenQueuePos {
+            hidden_qty: self.qty + self.ratio * visible,
+            base,
+        });
+    }
+
+    fn trade(&self, order: &mut Order, qty: f64, depth: &MD) {
+        self.with_base(order, |order, hidden_qty| {
+            let consumed = qty.min(*hidden_qty);
+            *hidden_qty -= consumed;
+            if qty > consumed {
+                self.base.trade(order, qty - consumed, depth);
+            }
+        });
+    }
+
+    fn depth(&self, order: &mut Order, prev_qty: f64, new_qty: f64, depth: &MD) {
+        self.with_base(order, |order, _| {
+            self.base.depth(order, prev_qty, new_qty, depth);
+        });
+    }
+
+    fn is_filled(&self, order: &Order, depth: &MD) -> f64 {
+        let pos = order.q.as_any().downcast_ref::<HiddenQueuePos>().unwrap();
+        if pos.hidden_qty > 0.0 {
+            return 0.0;
+        }
+        let mut order = order.clone();
+        order.q = pos.base.clone();
+        self.base.is_filled(&order, depth)
+    }
+
+    fn front_qty(&self, order: &Order) -> f64 {
+        let pos = order.q.as_any().downcast_ref::<HiddenQueuePos>().unwrap();
+        let mut order = order.clone();
+        order.q = pos.base.clone();
+        pos.hidden_qty + self.base.front_qty(&order)
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.base.set_seed(seed);
+    }
+}
+
+/// Stores the values needed for queue position estimation and adjustment for
+/// [`OrderCountQueueModel`].
+#[derive(Clone, Default)]
+pub struct OrderCountQueuePos {
+    front_q_qty: f64,
+    front_q_count: f64,
+    level_count: i64,
+    cum_trade_qty: f64,
+    cum_trade_count: f64,
+}
+
+impl AnyClone for OrderCountQueuePos {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Provides a queue position model for feeds that publish the number of orders per price level,
+/// such as CME MBP, using the order counts stored by an [`OrderCountDepth`] market depth, such as
+/// [`CountMarketDepth`](crate::depth::CountMarketDepth), along with the quantities.
+///
+/// Each order at the level is assumed to be equally likely to be cancelled or reduced, so the
+/// share of a decrease, in excess of the traded volume, that occurs ahead of your order is the
+/// number of orders ahead of it over the number of orders at the level. The number of orders
+/// ahead is tracked from the change in the order count: trades consume the orders ahead at their
+/// average size, and cancelled orders are taken out in proportion. The number of orders ahead
+/// can't exceed the number of orders at the level. While the order count is unknown, the share is
+/// given by the quantities instead.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::{backtest::models::OrderCountQueueModel, depth::CountMarketDepth};
+///
+/// let queue_model = OrderCountQueueModel::<CountMarketDepth>::new();
+/// ```
+pub struct OrderCountQueueModel<MD>(PhantomData<MD>);
+
+impl<MD> OrderCountQueueModel<MD> {
+    /// Constructs an instance of `OrderCountQueueModel`.
+    pub fn new() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<MD> Default for OrderCountQueueModel<MD> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<MD> QueueModel<MD> for OrderCountQueueModel<MD>
+where
+    MD: OrderCountDepth,
+{
+    fn new_order(&self, order: &mut Order, depth: &MD) {
+        let mut q = OrderCountQueuePos::default();
+        if order.side == Side::Buy {
+            q.front_q_qty = depth.bid_qty_at_tick(order.price_tick);
+            q.level_count = depth.bid_count_at_tick(order.price_tick);
+        } else {
+            q.front_q_qty = depth.ask_qty_at_tick(order.price_tick);
+            q.level_count = depth.ask_count_at_tick(order.price_tick);
+        }
+        q.front_q_count = q.level_count as f64;
+        order.q = Box::new(q);
+    }
+
+    fn trade(&self, order: &mut Order, qty: f64, _depth: &MD) {
+        let q = order
+            .q
+            .as_any_mut()
+            .downcast_mut::<OrderCountQueuePos>()
+            .unwrap();
+        // The trade consumes the orders ahead at their average size.
+        let consumed = if q.front_q_qty > 0.0 {
+            (q.front_q_count * qty / q.front_q_qty).min(q.front_q_count)
+        } else {
+            0.0
+        };
+        q.front_q_qty -= qty;
+        q.front_q_count -= consumed;
+        q.cum_trade_qty += qty;
+        q.cum_trade_count += consumed;
+    }
+
+    fn depth(&self, order: &mut Order, prev_qty: f64, new_qty: f64, depth: &MD) {
+        let new_count = if order.side == Side::Buy {
+            depth.bid_count_at_tick(order.price_tick)
+        } else {
+            depth.ask_count_at_tick(order.price_tick)
+        };
+        let q = order
+            .q
+            .as_any_mut()
+            .downcast_mut::<OrderCountQueuePos>()
+            .unwrap();
+        // Subtracts the quantity and the order count change by trades, which are already
+        // reflected in the queue position.
+        let canceled_qty = prev_qty - new_qty - q.cum_trade_qty;
+        let prev_count = q.level_count as f64 - q.cum_trade_count;
+        let canceled_count = prev_count - new_count as f64;
+        q.cum_trade_qty = 0.0;
+        q.cum_trade_count = 0.0;
+
+        if q.level_count == 0 && new_count > 0 && new_qty > 0.0 {
+            // The order count becomes known; assumes the orders ahead are of the average size.
+            q.front_q_count = new_count as f64 * (q.front_q_qty / new_qty).clamp(0.0, 1.0);
+        } else if canceled_qty > 0.0 {
+            let share = if q.level_count > 0 && prev_count > 0.0 {
+                q.front_q_count / prev_count
+            } else if prev_qty > 0.0 {
+                q.front_q_qty / prev_qty
+            } else {
+                1.0
+            }
+            .clamp(0.0, 1.0);
+            q.front_q_qty -= share * canceled_qty;
+            q.front_q_count -= share * canceled_count.max(0.0);
+        }
+        q.level_count = new_count;
+
+        q.front_q_qty = q.front_q_qty.min(new_qty);
+        q.front_q_count = q.front_q_count.max(0.0);
+        if new_count > 0 {
+            q.front_q_count = q.front_q_count.min(new_count as f64);
+        }
+    }
+
+    fn is_filled(&self, order: &Order, depth: &MD) -> f64 {
+        let q = order
+            .q
+            .as_any()
+            .downcast_ref::<OrderCountQueuePos>()
+            .unwrap();
+        if (q.front_q_qty / depth.lot_size()).round() < 0.0 {
+            (-q.front_q_qty / depth.lot_size()).floor() * depth.lot_size()
+        } else {
+            0.0
+        }
+    }
+
+    fn front_qty(&self, order: &Order) -> f64 {
+        order
+            .q
+            .as_any()
+            .downcast_ref::<OrderCountQueuePos>()
+            .unwrap()
+            .front_q_qty
+    }
+}
+
+/// Stores the values needed for the fill allocation for [`ProRataQueueModel`].
+#[derive(Clone, Default)]
+pub struct ProRataQueuePos {
+    front_q_qty: f64,
+    top_order: bool,
+    alloc_qty: f64,
+}
+
+impl AnyClone for ProRataQueuePos {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Provides pro-rata matching and FIFO/pro-rata hybrid matching, as used on many rates and FX
+/// futures, in place of the price-time priority that the other queue models assume.
+///
+/// Each trade at the order's price level is allocated as follows.
+///
+/// 1. If top-order priority is enabled and your order is the top order, the first order that set
+///    a new best price level, the trade is allocated to your order first.
+/// 2. The `fifo_ratio` of the rest is allocated in price-time priority, where your order receives
+///    the quantity in excess of the quantity ahead of it.
+/// 3. The remainder is allocated pro-rata, in proportion to your order's leaves quantity relative
+///    to the total quantity at the price level including your order, rounded down to the lot size.
+///
+/// The allocation is made on each trade, so this model is meant to be used with
+/// [`PartialFillExchange`](crate::backtest::proc::PartialFillExchange). With
+/// [`NoPartialFillExchange`](crate::backtest::proc::NoPartialFillExchange), any allocation fully fills
+/// the order.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::{backtest::models::ProRataQueueModel, depth::HashMapMarketDepth};
+///
+/// // 40% FIFO and 60% pro-rata with top-order priority.
+/// let queue_model = ProRataQueueModel::<HashMapMarketDepth>::new(0.4, true);
+/// ```
+pub struct ProRataQueueModel<MD> {
+    fifo_ratio: f64,
+    top_order: bool,
+    _md_marker: PhantomData<MD>,
+}
+
+impl<MD> ProRataQueueModel<MD> {
+    /// Constructs an instance of `ProRataQueueModel`.
+    ///
+    /// Arguments
+    /// - `fifo_ratio`: the ratio of the trade quantity allocated in price-time priority, from `0.0`
+    ///   for pure pro-rata matching to `1.0` for pure FIFO matching.
+    /// - `top_order`: whether the top order is given priority.
+    pub fn new(fifo_ratio: f64, top_order: bool) -> Self {
+        Self {
+            fifo_ratio: fifo_ratio.clamp(0.0, 1.0),
+            top_order,
+            _md_marker: Default::default(),
+        }
+    }
+}
+
+impl<MD> QueueModel<MD> for ProRataQueueModel<MD>
+where
+    MD: MarketDepth,
+{
+    fn new_order(&self, order: &mut Order, depth: &MD) {
+        let mut q = ProRataQueuePos::default();
+        if order.side == Side::Buy {
+            q.front_q_qty = depth.bid_qty_at_tick(order.price_tick);
+            q.top_order = self.top_order
+                && q.front_q_qty == 0.0
+                && (depth.best_bid_tick() == INVALID_MIN
+                    || order.price_tick > depth.best_bid_tick());
+        } else {
+            q.front_q_qty = depth.ask_qty_at_tick(order.price_tick);
+            q.top_order = self.top_order
+                && q.front_q_qty == 0.0
+                && (depth.best_ask_tick() == INVALID_MAX
+                    || order.price_tick < depth.best_ask_tick());
+        }
+        order.q = Box::new(q);
+    }
+
+    fn trade(&self, order: &mut Order, qty: f64, depth: &MD) {
+        let level_qty = if order.side == Side::Buy {
+            depth.bid_qty_at_tick(order.price_tick)
+        } else {
+            depth.ask_qty_at_tick(order.price_tick)
+        };
+        let leaves_qty = order.leaves_qty;
+        let q = order
+            .q
+            .as_any_mut()
+            .downcast_mut::<ProRataQueuePos>()
+            .unwrap();
+        let mut remaining = qty;
+        let mut alloc = 0.0;
+
+        if q.top_order {
+            let top = remaining.min(leaves_qty);
+            alloc += top;
+            remaining -= top;
+        }
+
+        let fifo_qty = remaining * self.fifo_ratio;
+        q.front_q_qty -= fifo_qty;
+        if q.front_q_qty < 0.0 {
+            alloc += -q.front_q_qty;
+            // Your order is at the front of the queue.
+            q.front_q_qty = 0.0;
+        }
+
+        let pro_rata_qty = remaining - fifo_qty;
+        let total = level_qty.max(0.0) + leaves_qty;
+        if total > 0.0 {
+            let lot_size = depth.lot_size();
+            alloc += (pro_rata_qty * leaves_qty / total / lot_size).floor() * lot_size;
+        }
+        q.alloc_qty = alloc.min(leaves_qty);
+    }
+
+    fn depth(&self, order: &mut Order, _prev_qty: f64, new_qty: f64, _depth: &MD) {
+        let q = order
+            .q
+            .as_any_mut()
+            .downcast_mut::<ProRataQueuePos>()
+            .unwrap();
+        q.front_q_qty = q.front_q_qty.min(new_qty);
+    }
+
+    fn is_filled(&self, order: &Order, depth: &MD) -> f64 {
+        let q = order.q.as_any().downcast_ref::<ProRataQueuePos>().unwrap();
+        (q.alloc_qty / depth.lot_size()).floor() * depth.lot_size()
+    }
+
+    fn front_qty(&self, order: &Order) -> f64 {
+        order
+            .q
+            .as_any()
+            .downcast_ref::<ProRataQueuePos>()
+            .unwrap()
+            .front_q_qty
+    }
 }
 
 /// This probability model uses a power function `f(x) = x ** n` to adjust the probability which is
@@ -314,9 +1076,73 @@ impl Probability for PowerProbQueueFunc3 {
     }
 }
 
+/// The functional form in which [`ProbQueueFunc`] computes the probability with the function `f`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProbForm {
+    /// `f(back) / (f(back) + f(front))`, as in [`PowerProbQueueFunc`] and [`LogProbQueueFunc`].
+    Ratio,
+    /// `f(back) / f(back + front)`, as in [`PowerProbQueueFunc2`] and [`LogProbQueueFunc2`].
+    Total,
+    /// `1 - f(front / (front + back))`, as in [`PowerProbQueueFunc3`].
+    Front,
+}
+
+/// This probability model uses the user-supplied function `f` in the chosen [`ProbForm`], for the
+/// calibrated functions that don't fit the provided ones, such as a power function with an
+/// offset or a mixture of power and logarithmic functions. For full control, a closure taking the
+/// quantity ahead and behind the order can be used as the [`Probability`] model directly.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::{
+///     backtest::models::{ProbForm, ProbQueueFunc, ProbQueueModel},
+///     depth::HashMapMarketDepth,
+/// };
+///
+/// // A power function with the exponent between those of the provided models.
+/// let queue_model = ProbQueueModel::<_, HashMapMarketDepth>::new(ProbQueueFunc::new(
+///     |x: f64| x.powf(2.5),
+///     ProbForm::Ratio,
+/// ));
+///
+/// // A closure taking the quantity ahead and behind the order.
+/// let queue_model = ProbQueueModel::<_, HashMapMarketDepth>::new(|front: f64, back: f64| {
+///     back.powf(2.0) / (back.powf(2.0) + front.powf(3.0))
+/// });
+/// ```
+#[derive(Clone)]
+pub struct ProbQueueFunc<F> {
+    f: F,
+    form: ProbForm,
+}
+
+impl<F> ProbQueueFunc<F>
+where
+    F: Fn(f64) -> f64,
+{
+    /// Constructs an instance of `ProbQueueFunc`.
+    pub fn new(f: F, form: ProbForm) -> Self {
+        Self { f, form }
+    }
+}
+
+impl<F> Probability for ProbQueueFunc<F>
+where
+    F: Fn(f64) -> f64,
+{
+    fn prob(&self, front: f64, back: f64) -> f64 {
+        let f = &self.f;
+        match self.form {
+            ProbForm::Ratio => f(back) / (f(back) + f(front)),
+            ProbForm::Total => f(back) / f(back + front),
+            ProbForm::Front => 1.0 - f(front / (front + back)),
+        }
+    }
+}
+
 /// Represents the order source for the Level 3 Market-By-Order queue model, which is stored in
 /// [`order.q`](crate::types::Order::q)
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum L3OrderSource {
     /// Represents an order originating from the market feed.
     Market,
@@ -335,7 +1161,7 @@ impl AnyClone for L3OrderSource {
 }
 
 /// Order ID with the order source for Level 3 Market-By-Order.
-#[derive(Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
 pub enum L3OrderId {
     /// Represents an order ID originating from the market feed.
     Market(u64),
@@ -379,6 +1205,25 @@ pub trait L3QueueModel {
     /// immediately or if it should be deleted upon receiving a delete order event, which is handled
     /// by [`cancel_order`](L3QueueModel::cancel_order).
     fn fill(&mut self, order_id: L3OrderId, delete: bool) -> Result<Vec<Order>, Self::Error>;
+
+    /// Returns `true` if the backtest order with the order ID is in the queue.
+    fn contains_backtest_order(&self, order_id: u64) -> bool;
+
+    /// This function is called when the best bid rises. Returns the backtest sell orders priced at
+    /// or below the new best bid, which are filled since the market crosses them.
+    fn on_best_bid_update(
+        &mut self,
+        prev_best_tick: i64,
+        new_best_tick: i64,
+    ) -> Result<Vec<Order>, Self::Error>;
+
+    /// This function is called when the best ask falls. Returns the backtest buy orders priced at
+    /// or above the new best ask, which are filled since the market crosses them.
+    fn on_best_ask_update(
+        &mut self,
+        prev_best_tick: i64,
+        new_best_tick: i64,
+    ) -> Result<Vec<Order>, Self::Error>;
 }
 
 /// This provides a Level 3 Market-By-Order queue model for backtesting in a FIFO manner. This means
@@ -388,6 +1233,7 @@ pub trait L3QueueModel {
 /// Exchanges may have different matching algorithms, such as Pro-Rata, and may have exotic order
 /// types that aren't executed in a FIFO manner. Therefore, you should carefully choose the queue
 /// model, even when dealing with a Level 3 Market-By-Order feed.
+#[derive(Default)]
 pub struct L3FIFOQueueModel {
     // Stores the location of the queue that holds the order by (side, price in ticks).
     pub orders: HashMap<L3OrderId, (Side, i64)>,
@@ -395,242 +1241,548 @@ pub struct L3FIFOQueueModel {
     // linked list, so it is better to use a vector.
     pub bid_queue: HashMap<i64, Vec<Order>>,
     pub ask_queue: HashMap<i64, Vec<Order>>,
+    // Stores the backtest orders separately, since the market orders far outnumber them.
+    backtest_orders: HashMap<u64, (Side, i64)>,
 }
 
+#[cfg(any(feature = "unstable_l3", doc, test))]
 impl L3FIFOQueueModel {
     /// Constructs an instance of `L3FIFOQueueModel`.
     pub fn new() -> Self {
-        Self {
-            orders: Default::default(),
-            bid_queue: Default::default(),
-            ask_queue: Default::default(),
+        Default::default()
+    }
+
+    fn queue_mut(&mut self, side: Side, price_tick: i64) -> &mut Vec<Order> {
+        match side {
+            Side::Buy => self.bid_queue.entry(price_tick).or_default(),
+            Side::Sell => self.ask_queue.entry(price_tick).or_default(),
+            Side::None | Side::Unsupported => unreachable!(),
+        }
+    }
+
+    fn remove_from_queue(
+        &mut self,
+        order_id: L3OrderId,
+        side: Side,
+        price_tick: i64,
+    ) -> Result<Order, BacktestError> {
+        let queue = self.queue_mut(side, price_tick);
+        let pos = queue
+            .iter()
+            .position(|order_in_q| order_id.is(order_in_q))
+            .ok_or(BacktestError::OrderNotFound)?;
+        let order = queue.remove(pos);
+        if queue.is_empty() {
+            match side {
+                Side::Buy => self.bid_queue.remove(&price_tick),
+                _ => self.ask_queue.remove(&price_tick),
+            };
         }
+        Ok(order)
+    }
+
+    fn set_location(&mut self, order_id: L3OrderId, side: Side, price_tick: i64) {
+        self.orders.insert(order_id, (side, price_tick));
+        if let L3OrderId::Backtest(order_id) = order_id {
+            self.backtest_orders.insert(order_id, (side, price_tick));
+        }
+    }
+
+    fn remove_location(&mut self, order_id: L3OrderId) -> Option<(Side, i64)> {
+        if let L3OrderId::Backtest(order_id) = order_id {
+            self.backtest_orders.remove(&order_id);
+        }
+        self.orders.remove(&order_id)
+    }
+
+    fn fill_crossed(&mut self, side: Side, crossed: impl Fn(i64) -> bool) -> Vec<Order> {
+        let order_ids = self
+            .backtest_orders
+            .iter()
+            .filter(|&(_, &(order_side, price_tick))| order_side == side && crossed(price_tick))
+            .map(|(&order_id, _)| L3OrderId::Backtest(order_id))
+            .collect::<Vec<_>>();
+        let mut filled = Vec::with_capacity(order_ids.len());
+        for order_id in order_ids {
+            let (side, price_tick) = self.remove_location(order_id).unwrap();
+            filled.push(self.remove_from_queue(order_id, side, price_tick).unwrap());
+        }
+        filled
     }
 }
 
+#[cfg(any(feature = "unstable_l3", doc, test))]
 impl L3QueueModel for L3FIFOQueueModel {
     type Error = BacktestError;
 
     fn add_order(&mut self, order_id: L3OrderId, order: Order) -> Result<(), Self::Error> {
-        let order_price_tick = order.price_tick;
-        let side = order.side;
-
-        let priority = match side {
-            Side::Buy => self.bid_queue.entry(order_price_tick).or_insert(Vec::new()),
-            Side::Sell => self.ask_queue.entry(order_price_tick).or_insert(Vec::new()),
-            Side::None | Side::Unsupported => unreachable!(),
-        };
-
-        priority.push(order);
-        match self.orders.entry(order_id) {
-            Entry::Occupied(_) => Err(BacktestError::OrderIdExist),
-            Entry::Vacant(entry) => {
-                entry.insert((side, order_price_tick));
-                Ok(())
-            }
+        if self.orders.contains_key(&order_id) {
+            return Err(BacktestError::OrderIdExist);
         }
+        let side = order.side;
+        let price_tick = order.price_tick;
+        self.queue_mut(side, price_tick).push(order);
+        self.set_location(order_id, side, price_tick);
+        Ok(())
     }
 
     fn cancel_order(&mut self, order_id: L3OrderId) -> Result<Order, Self::Error> {
-        let (side, order_price_tick) = self
-            .orders
-            .remove(&order_id)
+        let (side, price_tick) = self
+            .remove_location(order_id)
             .ok_or(BacktestError::OrderNotFound)?;
-
-        match side {
-            Side::Buy => {
-                let queue = self.bid_queue.get_mut(&order_price_tick).unwrap();
-                let mut pos = None;
-                for (i, order_in_q) in queue.iter().enumerate() {
-                    if order_id.is(order_in_q) {
-                        pos = Some(i);
-                        break;
-                    }
-                }
-
-                let order = queue.remove(pos.ok_or(BacktestError::OrderNotFound)?);
-                // if priority.len() == 0 {
-                //     self.bid_priority.remove(&order_price_tick);
-                // }
-                Ok(order)
-            }
-            Side::Sell => {
-                let queue = self.ask_queue.get_mut(&order_price_tick).unwrap();
-                let mut pos = None;
-                for (i, order_in_q) in queue.iter().enumerate() {
-                    if order_id.is(order_in_q) {
-                        pos = Some(i);
-                        break;
-                    }
-                }
-
-                let order = queue.remove(pos.ok_or(BacktestError::OrderNotFound)?);
-                // if priority.len() == 0 {
-                //     self.ask_priority.remove(&order_price_tick);
-                // }
-                Ok(order)
-            }
-            Side::None | Side::Unsupported => unreachable!(),
-        }
+        self.remove_from_queue(order_id, side, price_tick)
     }
 
     fn modify_order(&mut self, order_id: L3OrderId, order: Order) -> Result<(), Self::Error> {
-        let (side, order_price_tick) = self
+        let (side, price_tick) = *self
             .orders
             .get(&order_id)
             .ok_or(BacktestError::OrderNotFound)?;
 
-        match side {
-            Side::Buy => {
-                let queue = self.bid_queue.get_mut(&order_price_tick).unwrap();
-                let mut processed = false;
-                let mut pos = None;
-                for (i, order_in_q) in queue.iter_mut().enumerate() {
-                    if order_id.is(order_in_q) {
-                        if (order_in_q.price_tick != order.price_tick)
-                            || (order_in_q.leaves_qty < order.leaves_qty)
-                        {
-                            pos = Some(i);
-                        } else {
-                            order_in_q.leaves_qty = order.leaves_qty;
-                            order_in_q.qty = order.qty;
-                        }
-                        processed = true;
-                        break;
-                    }
-                }
-
-                if !processed {
-                    return Err(BacktestError::OrderNotFound);
-                }
-
-                if let Some(pos) = pos {
-                    let prev_order = queue.remove(pos);
-                    // if priority.len() == 0 {
-                    //     self.bid_priority.remove(&order_price_tick);
-                    // }
-                    if prev_order.price_tick != order.price_tick {
-                        let queue_ = self.bid_queue.get_mut(&order.price_tick).unwrap();
-                        queue_.push(order);
-                    } else {
-                        queue.push(order);
-                    }
-                }
-            }
-            Side::Sell => {
-                let queue = self.ask_queue.get_mut(&order_price_tick).unwrap();
-                let mut processed = false;
-                let mut pos = None;
-                for (i, order_in_q) in queue.iter_mut().enumerate() {
-                    if order_id.is(order_in_q) {
-                        if (order_in_q.price_tick != order.price_tick)
-                            || (order_in_q.leaves_qty < order.leaves_qty)
-                        {
-                            pos = Some(i);
-                        } else {
-                            order_in_q.leaves_qty = order.leaves_qty;
-                            order_in_q.qty = order.qty;
-                        }
-                        processed = true;
-                        break;
-                    }
-                }
-
-                if !processed {
-                    return Err(BacktestError::OrderNotFound);
-                }
-
-                if let Some(pos) = pos {
-                    let prev_order = queue.remove(pos);
-                    // if priority.len() == 0 {
-                    //     self.bid_priority.remove(&order_price_tick);
-                    // }
-                    if prev_order.price_tick != order.price_tick {
-                        let queue_ = self.ask_queue.get_mut(&order.price_tick).unwrap();
-                        queue_.push(order);
-                    } else {
-                        queue.push(order);
-                    }
-                }
-            }
-            Side::None | Side::Unsupported => unreachable!(),
+        let queue = self.queue_mut(side, price_tick);
+        let pos = queue
+            .iter()
+            .position(|order_in_q| order_id.is(order_in_q))
+            .ok_or(BacktestError::OrderNotFound)?;
+        let order_in_q = &mut queue[pos];
+        // The order keeps its priority only if the quantity is reduced at the same price.
+        if order_in_q.price_tick == order.price_tick && order_in_q.leaves_qty >= order.leaves_qty {
+            order_in_q.leaves_qty = order.leaves_qty;
+            order_in_q.qty = order.qty;
+        } else {
+            self.remove_from_queue(order_id, side, price_tick)?;
+            let price_tick = order.price_tick;
+            self.queue_mut(side, price_tick).push(order);
+            self.set_location(order_id, side, price_tick);
         }
-
         Ok(())
     }
 
     fn fill(&mut self, order_id: L3OrderId, delete: bool) -> Result<Vec<Order>, Self::Error> {
-        let (side, order_price_tick) = self
+        let (side, price_tick) = *self
             .orders
-            .remove(&order_id)
+            .get(&order_id)
             .ok_or(BacktestError::OrderNotFound)?;
 
-        match side {
-            Side::Buy => {
-                let queue = self.bid_queue.get_mut(&order_price_tick).unwrap();
-                let mut filled = Vec::new();
-
-                let mut pos = None;
-                let mut i = 0;
-                while i < queue.len() {
-                    let order_in_q = queue.get(i).unwrap();
-                    let order_source = order_in_q
-                        .q
-                        .as_any()
-                        .downcast_ref::<L3OrderSource>()
-                        .unwrap();
-                    if order_id.is(order_in_q) {
-                        pos = Some(i);
-                        break;
-                    } else if *order_source == L3OrderSource::Backtest {
-                        let order = queue.remove(i);
-                        filled.push(order);
-                    } else {
-                        i += 1;
-                    }
-                }
-                let pos = pos.ok_or(BacktestError::OrderNotFound)?;
-                if delete {
-                    queue.remove(pos);
-                    // if priority.len() == 0 {
-                    //     self.ask_priority.remove(&order_price_tick);
-                    // }
-                }
-                Ok(filled)
-            }
-            Side::Sell => {
-                let queue = self.ask_queue.get_mut(&order_price_tick).unwrap();
-                let mut filled = Vec::new();
-
-                let mut pos = None;
-                let mut i = 0;
-                while i < queue.len() {
-                    let order_in_q = queue.get(i).unwrap();
-                    let order_source = order_in_q
-                        .q
-                        .as_any()
-                        .downcast_ref::<L3OrderSource>()
-                        .unwrap();
-                    if order_id.is(order_in_q) {
-                        pos = Some(i);
-                        break;
-                    } else if *order_source == L3OrderSource::Backtest {
-                        let order = queue.remove(i);
-                        filled.push(order);
-                    } else {
-                        i += 1;
-                    }
-                }
-                let pos = pos.ok_or(BacktestError::OrderNotFound)?;
-                if delete {
-                    queue.remove(pos);
-                    // if priority.len() == 0 {
-                    //     self.ask_priority.remove(&order_price_tick);
-                    // }
-                }
-                Ok(filled)
+        // The backtest orders ahead of the filled order are filled first.
+        let queue = self.queue_mut(side, price_tick);
+        let pos = queue
+            .iter()
+            .position(|order_in_q| order_id.is(order_in_q))
+            .ok_or(BacktestError::OrderNotFound)?;
+        let mut filled = Vec::new();
+        let mut i = 0;
+        let mut pos = pos;
+        while i < pos {
+            let source = queue[i].q.as_any().downcast_ref::<L3OrderSource>().unwrap();
+            if *source == L3OrderSource::Backtest {
+                filled.push(queue.remove(i));
+                pos -= 1;
+            } else {
+                i += 1;
             }
-            Side::None | Side::Unsupported => unreachable!(),
         }
+        for order in &filled {
+            self.remove_location(L3OrderId::Backtest(order.order_id));
+        }
+        if delete {
+            self.remove_location(order_id);
+            self.remove_from_queue(order_id, side, price_tick)?;
+        }
+        Ok(filled)
+    }
+
+    fn contains_backtest_order(&self, order_id: u64) -> bool {
+        self.backtest_orders.contains_key(&order_id)
+    }
+
+    fn on_best_bid_update(
+        &mut self,
+        _prev_best_tick: i64,
+        new_best_tick: i64,
+    ) -> Result<Vec<Order>, Self::Error> {
+        Ok(self.fill_crossed(Side::Sell, |price_tick| price_tick <= new_best_tick))
+    }
+
+    fn on_best_ask_update(
+        &mut self,
+        _prev_best_tick: i64,
+        new_best_tick: i64,
+    ) -> Result<Vec<Order>, Self::Error> {
+        Ok(self.fill_crossed(Side::Buy, |price_tick| price_tick >= new_best_tick))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CancelRate,
+        DepthDecayQueueModel,
+        ExpDecayCancelRate,
+        HiddenLiquidityQueueModel,
+        L3FIFOQueueModel,
+        L3OrderId,
+        L3OrderSource,
+        L3QueueModel,
+        OrderCountQueueModel,
+        OrderCountQueuePos,
+        PowerProbQueueFunc,
+        PowerProbQueueFunc2,
+        PowerProbQueueFunc3,
+        ProRataQueueModel,
+        ProbForm,
+        ProbQueueFunc,
+        Probability,
+        QueueModel,
+        QueuePos,
+        RiskAdverseQueueModel,
+        TradeFlowQueueModel,
+        TradeFlowQueuePos,
+    };
+    use crate::{
+        depth::{CountMarketDepth, HashMapMarketDepth, L2MarketDepth},
+        types::{OrdType, Order, Side, TimeInForce},
+    };
+
+    fn order(order_id: u64, price_tick: i64, side: Side, source: L3OrderSource) -> Order {
+        let mut order = Order::new(
+            order_id,
+            price_tick,
+            0.1,
+            1.0,
+            side,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+        order.q = Box::new(source);
+        order
+    }
+
+    #[test]
+    fn test_l3_fifo_queue_model() {
+        let mut qm = L3FIFOQueueModel::new();
+        let market = L3OrderSource::Market;
+        let backtest = L3OrderSource::Backtest;
+        qm.add_order(L3OrderId::Market(1), order(1, 100, Side::Buy, market))
+            .unwrap();
+        qm.add_order(L3OrderId::Backtest(1), order(1, 100, Side::Buy, backtest))
+            .unwrap();
+        qm.add_order(L3OrderId::Market(2), order(2, 100, Side::Buy, market))
+            .unwrap();
+        assert!(qm.contains_backtest_order(1));
+
+        // The market order ahead of the backtest order is filled, and it's deleted later.
+        assert!(qm.fill(L3OrderId::Market(1), false).unwrap().is_empty());
+        qm.cancel_order(L3OrderId::Market(1)).unwrap();
+
+        // The backtest order is filled when the market order behind it is filled.
+        let filled = qm.fill(L3OrderId::Market(2), true).unwrap();
+        assert_eq!(filled.len(), 1);
+        assert_eq!(filled[0].order_id, 1);
+        assert!(!qm.contains_backtest_order(1));
+        assert!(qm.bid_queue.is_empty());
+
+        // The backtest order is filled when the market crosses it.
+        qm.add_order(L3OrderId::Backtest(2), order(2, 105, Side::Sell, backtest))
+            .unwrap();
+        assert!(qm.on_best_bid_update(100, 104).unwrap().is_empty());
+        let filled = qm.on_best_bid_update(104, 105).unwrap();
+        assert_eq!(filled.len(), 1);
+        assert!(qm.orders.is_empty());
+
+        // The market order loses its priority when its quantity increases.
+        qm.add_order(L3OrderId::Market(3), order(3, 95, Side::Buy, market))
+            .unwrap();
+        qm.add_order(L3OrderId::Backtest(3), order(3, 95, Side::Buy, backtest))
+            .unwrap();
+        let mut modified = order(3, 95, Side::Buy, market);
+        modified.qty = 2.0;
+        modified.leaves_qty = 2.0;
+        qm.modify_order(L3OrderId::Market(3), modified).unwrap();
+        let filled = qm.fill(L3OrderId::Market(3), true).unwrap();
+        assert_eq!(filled.len(), 1);
+        assert_eq!(filled[0].order_id, 3);
+
+        qm.add_order(L3OrderId::Backtest(4), order(4, 90, Side::Buy, backtest))
+            .unwrap();
+        assert!(qm.on_best_ask_update(100, 91).unwrap().is_empty());
+        let filled = qm.on_best_ask_update(91, 90).unwrap();
+        assert_eq!(filled.len(), 1);
+        assert!(qm.orders.is_empty());
+    }
+
+    #[test]
+    fn test_prob_queue_func() {
+        let (front, back) = (3.0, 5.0);
+        let power = |x: f64| x.powf(2.0);
+        let ratio = ProbQueueFunc::new(power, ProbForm::Ratio);
+        assert_eq!(
+            ratio.prob(front, back),
+            PowerProbQueueFunc::new(2.0).prob(front, back)
+        );
+        let total = ProbQueueFunc::new(power, ProbForm::Total);
+        assert_eq!(
+            total.prob(front, back),
+            PowerProbQueueFunc2::new(2.0).prob(front, back)
+        );
+        let front_form = ProbQueueFunc::new(power, ProbForm::Front);
+        assert_eq!(
+            front_form.prob(front, back),
+            PowerProbQueueFunc3::new(2.0).prob(front, back)
+        );
+
+        let closure = |front: f64, back: f64| back / (front + back);
+        assert_eq!(closure.prob(front, back), 0.625);
+    }
+
+    #[test]
+    fn test_trade_flow_queue_model() {
+        let depth = HashMapMarketDepth::new(0.1, 1.0);
+        // All cancellations are assumed to occur ahead of the order.
+        let qm = TradeFlowQueueModel::<_, HashMapMarketDepth>::new(|_: f64, _: f64| 0.0);
+        let mut order = Order::new(
+            1,
+            100,
+            0.1,
+            1.0,
+            Side::Buy,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+        order.q = Box::new(TradeFlowQueuePos {
+            front_q_qty: 20.0,
+            pending_trade_qty: 0.0,
+        });
+        let front = |order: &Order| {
+            order
+                .q
+                .as_any()
+                .downcast_ref::<TradeFlowQueuePos>()
+                .unwrap()
+                .front_q_qty
+        };
+
+        qm.trade(&mut order, 5.0, &depth);
+        assert_eq!(front(&order), 15.0);
+
+        // The depth updates lag the trade, reflecting it in two steps.
+        qm.depth(&mut order, 20.0, 18.0, &depth);
+        qm.depth(&mut order, 18.0, 15.0, &depth);
+        assert_eq!(front(&order), 15.0);
+
+        // The decrease beyond the traded volume is a cancellation.
+        qm.depth(&mut order, 15.0, 12.0, &depth);
+        assert_eq!(front(&order), 12.0);
+
+        qm.trade(&mut order, 13.0, &depth);
+        assert_eq!(qm.is_filled(&order, &depth), 1.0);
+    }
+
+    #[test]
+    fn test_depth_decay_queue_model() {
+        let mut depth = HashMapMarketDepth::new(1.0, 1.0);
+        depth.update_bid_depth(100.0, 10.0, 0);
+        let rate = ExpDecayCancelRate::new(0.6, 0.3);
+        assert_eq!(rate.rate(0), 0.6);
+        assert!((rate.rate(2) - 0.6 * (-0.6f64).exp()).abs() < 1e-12);
+
+        let qm = DepthDecayQueueModel::<_, HashMapMarketDepth>::new(|distance: i64| {
+            if distance == 0 {
+                0.5
+            } else {
+                0.0
+            }
+        });
+        let new_order = |price_tick| {
+            let mut order = Order::new(
+                1,
+                price_tick,
+                1.0,
+                1.0,
+                Side::Buy,
+                OrdType::Limit,
+                TimeInForce::GTC,
+            );
+            order.q = Box::new(QueuePos {
+                front_q_qty: 10.0,
+                cum_trade_qty: 0.0,
+            });
+            order
+        };
+        let front = |order: &Order| {
+            order
+                .q
+                .as_any()
+                .downcast_ref::<QueuePos>()
+                .unwrap()
+                .front_q_qty
+        };
+
+        // Half of the cancellations occur ahead of the order at the touch.
+        let mut order = new_order(100);
+        qm.depth(&mut order, 20.0, 16.0, &depth);
+        assert_eq!(front(&order), 8.0);
+
+        // Away from the touch, the cancellations occur behind the order unless the quantity
+        // behind can't account for them.
+        let mut order = new_order(99);
+        qm.depth(&mut order, 20.0, 16.0, &depth);
+        assert_eq!(front(&order), 10.0);
+        qm.depth(&mut order, 16.0, 8.0, &depth);
+        assert_eq!(front(&order), 8.0);
+
+        // The decrease by the trade isn't counted as cancellations.
+        qm.trade(&mut order, 3.0, &depth);
+        qm.depth(&mut order, 8.0, 5.0, &depth);
+        assert_eq!(front(&order), 5.0);
+    }
+
+    #[test]
+    fn test_hidden_liquidity_queue_model() {
+        let mut depth = HashMapMarketDepth::new(1.0, 1.0);
+        depth.update_bid_depth(100.0, 10.0, 0);
+        let qm = HiddenLiquidityQueueModel::new(RiskAdverseQueueModel::new(), 2.0, 0.5);
+        let mut order = Order::new(
+            1,
+            100,
+            1.0,
+            1.0,
+            Side::Buy,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+        qm.new_order(&mut order, &depth);
+
+        // 7 hidden and 10 visible are ahead of the order.
+        qm.trade(&mut order, 7.0, &depth);
+        assert_eq!(qm.is_filled(&order, &depth), 0.0);
+        qm.depth(&mut order, 10.0, 8.0, &depth);
+        qm.trade(&mut order, 8.0, &depth);
+        assert_eq!(qm.is_filled(&order, &depth), 0.0);
+        qm.trade(&mut order, 1.0, &depth);
+        assert_eq!(qm.is_filled(&order, &depth), 1.0);
+    }
+
+    #[test]
+    fn test_order_count_queue_model() {
+        let mut depth = CountMarketDepth::new(0.1, 1.0);
+        let qm = OrderCountQueueModel::new();
+        let pos = |order: &Order| {
+            let q = order
+                .q
+                .as_any()
+                .downcast_ref::<OrderCountQueuePos>()
+                .unwrap();
+            (q.front_q_qty, q.front_q_count)
+        };
+
+        // Two orders of 10 in total are ahead of the order.
+        depth.update_bid_depth_with_count(100.0, 10.0, 2, 0);
+        let mut order = Order::new(
+            1,
+            1000,
+            0.1,
+            1.0,
+            Side::Buy,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+        qm.new_order(&mut order, &depth);
+        assert_eq!(pos(&order), (10.0, 2.0));
+
+        // Six orders of 1 join behind the order.
+        depth.update_bid_depth_with_count(100.0, 16.0, 8, 0);
+        qm.depth(&mut order, 10.0, 16.0, &depth);
+        assert_eq!(pos(&order), (10.0, 2.0));
+
+        // One order is cancelled, which is ahead of the order with the probability of 2/8, not
+        // 10/16 as the quantities would give.
+        depth.update_bid_depth_with_count(100.0, 15.0, 7, 0);
+        qm.depth(&mut order, 16.0, 15.0, &depth);
+        assert_eq!(pos(&order), (9.75, 1.75));
+
+        // The decrease by the trade isn't counted as cancellations.
+        qm.trade(&mut order, 5.0, &depth);
+        depth.update_bid_depth_with_count(100.0, 10.0, 6, 0);
+        qm.depth(&mut order, 15.0, 10.0, &depth);
+        assert_eq!(pos(&order).0, 4.75);
+        assert_eq!(qm.is_filled(&order, &depth), 0.0);
+        qm.trade(&mut order, 6.0, &depth);
+        assert_eq!(qm.is_filled(&order, &depth), 1.0);
+
+        // Without the order count, the share is given by the quantities.
+        depth.update_ask_depth(100.1, 10.0, 0);
+        let mut order = Order::new(
+            2,
+            1001,
+            0.1,
+            1.0,
+            Side::Sell,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+        qm.new_order(&mut order, &depth);
+        depth.update_ask_depth(100.1, 20.0, 0);
+        qm.depth(&mut order, 10.0, 20.0, &depth);
+        depth.update_ask_depth(100.1, 15.0, 0);
+        qm.depth(&mut order, 20.0, 15.0, &depth);
+        assert_eq!(pos(&order), (7.5, 0.0));
+
+        // Once the order count is known, the orders ahead are assumed to be of the average size.
+        depth.update_ask_depth_with_count(100.1, 15.0, 6, 0);
+        qm.depth(&mut order, 15.0, 15.0, &depth);
+        assert_eq!(pos(&order), (7.5, 3.0));
+    }
+
+    #[test]
+    fn test_pro_rata_queue_model() {
+        let mut depth = HashMapMarketDepth::new(1.0, 1.0);
+        depth.update_bid_depth(99.0, 5.0, 0);
+        depth.update_bid_depth(100.0, 30.0, 0);
+        let new_order = |order_id: u64, price_tick: i64| {
+            Order::new(
+                order_id,
+                price_tick,
+                1.0,
+                10.0,
+                Side::Buy,
+                OrdType::Limit,
+                TimeInForce::GTC,
+            )
+        };
+
+        // Pure pro-rata: 10 / (30 + 10) of the trade quantity.
+        let qm = ProRataQueueModel::new(0.0, false);
+        let mut order = new_order(1, 100);
+        qm.new_order(&mut order, &depth);
+        qm.trade(&mut order, 20.0, &depth);
+        assert_eq!(qm.is_filled(&order, &depth), 5.0);
+
+        // Half of the trade quantity is allocated in price-time priority, where 30 is ahead of the
+        // order.
+        let qm = ProRataQueueModel::new(0.5, false);
+        let mut order = new_order(2, 100);
+        qm.new_order(&mut order, &depth);
+        qm.trade(&mut order, 20.0, &depth);
+        assert_eq!(qm.is_filled(&order, &depth), 2.0);
+        // 10 from FIFO after consuming the 20 ahead and 7 from pro-rata, capped by the leaves
+        // quantity.
+        qm.trade(&mut order, 60.0, &depth);
+        assert_eq!(qm.is_filled(&order, &depth), 10.0);
+
+        // The top order, which set the new best bid, is allocated first.
+        let qm = ProRataQueueModel::new(0.0, true);
+        let mut order = new_order(3, 101);
+        qm.new_order(&mut order, &depth);
+        depth.update_bid_depth(101.0, 20.0, 0);
+        qm.depth(&mut order, 0.0, 20.0, &depth);
+        qm.trade(&mut order, 8.0, &depth);
+        assert_eq!(qm.is_filled(&order, &depth), 8.0);
+
+        // An order joining an existing level isn't the top order.
+        let mut order = new_order(4, 101);
+        qm.new_order(&mut order, &depth);
+        qm.trade(&mut order, 8.0, &depth);
+        assert_eq!(qm.is_filled(&order, &depth), 2.0);
     }
 }
@@ -0,0 +1,150 @@
+use crate::types::Order;
+
+/// Determines whether an order modified in place keeps its queue position at the exchange, which
+/// differs by venue.
+///
+/// Regardless of the rule, a modification that changes the order price always loses the queue
+/// position, as the order moves to the back of the queue at the new price.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AmendRule {
+    /// A pure size reduction keeps the queue position, while a size increase re-queues the order.
+    /// This is the rule most venues offering in-place amends follow.
+    #[default]
+    KeepOnSizeReduction,
+    /// Any size change keeps the queue position.
+    KeepOnSizeChange,
+    /// Every modification re-queues the order, as if it were canceled and resubmitted, but without
+    /// the risk of being filled in between.
+    AlwaysRequeue,
+}
+
+impl AmendRule {
+    /// Returns `true` if the order keeps its queue position when its price and leaves quantity are
+    /// modified to `price_tick` and `leaves_qty`.
+    pub fn keeps_queue_position(&self, order: &Order, price_tick: i64, leaves_qty: f64) -> bool {
+        if price_tick != order.price_tick {
+            return false;
+        }
+        match self {
+            AmendRule::KeepOnSizeReduction => leaves_qty <= order.leaves_qty,
+            AmendRule::KeepOnSizeChange => true,
+            AmendRule::AlwaysRequeue => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AmendRule;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{
+            Bot,
+            OrdType,
+            Order,
+            Side,
+            Status,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+            TRADE_EVENT,
+        },
+    };
+
+    #[test]
+    fn test_amend_rule() {
+        let order = Order::new(
+            1,
+            100,
+            1.0,
+            2.0,
+            Side::Buy,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+
+        let rule = AmendRule::KeepOnSizeReduction;
+        assert!(rule.keeps_queue_position(&order, 100, 1.0));
+        assert!(rule.keeps_queue_position(&order, 100, 2.0));
+        assert!(!rule.keeps_queue_position(&order, 100, 3.0));
+        assert!(!rule.keeps_queue_position(&order, 99, 1.0));
+
+        let rule = AmendRule::KeepOnSizeChange;
+        assert!(rule.keeps_queue_position(&order, 100, 3.0));
+        assert!(!rule.keeps_queue_position(&order, 101, 2.0));
+
+        let rule = AmendRule::AlwaysRequeue;
+        assert!(!rule.keeps_queue_position(&order, 100, 1.0));
+    }
+
+    fn run(exch_kind: ExchangeKind, rule: AmendRule, qty: f64) -> Status {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+            event(TRADE_EVENT | SELL_EVENT, 100, 100.0, 10.0),
+            event(TRADE_EVENT | SELL_EVENT, 200, 100.0, 1.0),
+            event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 10.0),
+        ]);
+        let mut hbt = backtest(
+            asset_builder(data, 1.0, 1.0)
+                .exchange(exch_kind)
+                .amend_rule(rule),
+        );
+
+        hbt.elapse(9).unwrap();
+        hbt.submit_buy_order(0, 1, 100.0, 2.0, TimeInForce::GTC, OrdType::Limit, false)
+            .unwrap();
+        // The trade at time 100 clears the queue ahead of the order.
+        hbt.elapse(140).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::New);
+        hbt.modify(0, 1, 100.0, qty, true).unwrap();
+        assert_eq!(hbt.orders(0)[&1].qty, qty);
+        hbt.elapse(100).unwrap();
+        hbt.orders(0)[&1].status
+    }
+
+    #[test]
+    fn test_modify_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            // The size reduction keeps the order at the front of the queue, so the next trade fills
+            // it.
+            assert_eq!(
+                run(exch_kind, AmendRule::KeepOnSizeReduction, 1.0),
+                Status::Filled
+            );
+        }
+        // The size increase re-queues the order behind the quantity at its price.
+        assert_eq!(
+            run(
+                ExchangeKind::NoPartialFillExchange,
+                AmendRule::KeepOnSizeReduction,
+                3.0
+            ),
+            Status::New
+        );
+        assert_eq!(
+            run(
+                ExchangeKind::NoPartialFillExchange,
+                AmendRule::AlwaysRequeue,
+                1.0
+            ),
+            Status::New
+        );
+        assert_eq!(
+            run(
+                ExchangeKind::NoPartialFillExchange,
+                AmendRule::KeepOnSizeChange,
+                3.0
+            ),
+            Status::Filled
+        );
+    }
+}
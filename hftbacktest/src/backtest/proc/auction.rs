@@ -0,0 +1,295 @@
+use crate::{
+    depth::{MarketDepth, INVALID_MAX, INVALID_MIN},
+    types::{Order, OrderId, Side},
+};
+
+/// The number of price levels beyond the orders and the best bid and ask that the equilibrium
+/// price calculation scans for the market depth.
+const MAX_AUCTION_TICKS: i64 = 1_000;
+
+/// The outcome of the uncross of an [`Auction`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Uncross {
+    /// The price tick at which the auction uncrosses.
+    pub price_tick: i64,
+    /// Whether buy orders at the uncross price are filled.
+    pub fill_buy_at_price: bool,
+    /// Whether sell orders at the uncross price are filled.
+    pub fill_sell_at_price: bool,
+}
+
+impl Uncross {
+    /// Returns `true` if the collected order is filled at the uncross.
+    pub fn is_filled(&self, order: &Order) -> bool {
+        match order.side {
+            Side::Buy => {
+                order.price_tick > self.price_tick
+                    || (order.price_tick == self.price_tick && self.fill_buy_at_price)
+            }
+            _ => {
+                order.price_tick < self.price_tick
+                    || (order.price_tick == self.price_tick && self.fill_sell_at_price)
+            }
+        }
+    }
+}
+
+/// Holds the orders collected by the exchange during the call period of an opening or closing
+/// auction, which are matched at a single equilibrium price at the uncross.
+///
+/// The auction is driven by [`AUCTION_EVENT`](crate::types::AUCTION_EVENT)s in the data. When the
+/// call period begins, the resting orders are moved into the auction and matching halts; orders
+/// received during the call period are collected without being matched, and can be canceled or
+/// modified. At the uncross, buy orders priced above and sell orders priced below the uncross
+/// price are filled at the uncross price. Orders at the uncross price are filled only on the side
+/// that is entirely executed, which is known only if the exchange calculates the uncross price.
+/// The unfilled orders rest in the book as the continuous trading resumes.
+#[derive(Default)]
+pub struct Auction {
+    open: bool,
+    // Orders in the time priority.
+    orders: Vec<Order>,
+}
+
+impl Auction {
+    /// Returns `true` if the call period is in progress.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Begins the call period.
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Ends the call period, and returns the collected orders in the time priority.
+    pub fn close(&mut self) -> Vec<Order> {
+        self.open = false;
+        self.take_all()
+    }
+
+    /// Returns `true` if a collected order with the order ID exists.
+    pub fn contains(&self, order_id: &OrderId) -> bool {
+        self.orders.iter().any(|order| order.order_id == *order_id)
+    }
+
+    /// Collects an order.
+    pub fn insert(&mut self, order: Order) {
+        self.orders.push(order);
+    }
+
+    /// Returns the collected order with the order ID.
+    pub fn get(&self, order_id: &OrderId) -> Option<&Order> {
+        self.orders.iter().find(|order| order.order_id == *order_id)
+    }
+
+    /// Returns the mutable collected order with the order ID.
+    pub fn get_mut(&mut self, order_id: &OrderId) -> Option<&mut Order> {
+        self.orders
+            .iter_mut()
+            .find(|order| order.order_id == *order_id)
+    }
+
+    /// Removes the collected order with the order ID, which is canceled.
+    pub fn remove(&mut self, order_id: &OrderId) -> Option<Order> {
+        let i = self
+            .orders
+            .iter()
+            .position(|order| order.order_id == *order_id)?;
+        Some(self.orders.remove(i))
+    }
+
+    /// Removes and returns all the collected orders in the time priority.
+    pub fn take_all(&mut self) -> Vec<Order> {
+        std::mem::take(&mut self.orders)
+    }
+}
+
+/// Calculates the uncross of the collected `orders` together with the market depth, which is the
+/// venue's indicative book during the call period.
+///
+/// The uncross price is the price that maximizes the executable quantity, the lesser of the buy
+/// quantity at or above it and the sell quantity at or below it. Among such prices, the one with
+/// the least imbalance between the two is chosen, and if there are still several, the middle one.
+/// Returns `None` if nothing is executable.
+pub fn calculate_uncross<MD>(depth: &MD, orders: &[Order]) -> Option<Uncross>
+where
+    MD: MarketDepth,
+{
+    let ticks = orders
+        .iter()
+        .map(|order| order.price_tick)
+        .chain([depth.best_bid_tick(), depth.best_ask_tick()])
+        .filter(|&tick| tick != INVALID_MIN && tick != INVALID_MAX);
+    let (lo, hi) = ticks.fold(None, |range: Option<(i64, i64)>, tick| match range {
+        Some((lo, hi)) => Some((lo.min(tick), hi.max(tick))),
+        None => Some((tick, tick)),
+    })?;
+    let (lo, hi) = (lo - MAX_AUCTION_TICKS, hi + MAX_AUCTION_TICKS);
+    let n = (hi - lo + 1) as usize;
+
+    // The sell quantity at or below each price, and the buy quantity at or above it.
+    let mut supply = vec![0.0; n];
+    let mut demand = vec![0.0; n];
+    for order in orders {
+        let i = (order.price_tick - lo) as usize;
+        match order.side {
+            Side::Buy => demand[i] += order.leaves_qty,
+            _ => supply[i] += order.leaves_qty,
+        }
+    }
+    for i in 0..n {
+        let tick = lo + i as i64;
+        supply[i] += depth.ask_qty_at_tick(tick);
+        demand[i] += depth.bid_qty_at_tick(tick);
+    }
+    for i in 1..n {
+        supply[i] += supply[i - 1];
+        demand[n - 1 - i] += demand[n - i];
+    }
+
+    let lot_size = depth.lot_size();
+    let mut best: Option<(f64, f64, usize, usize)> = None;
+    for i in 0..n {
+        let exec_qty = (supply[i].min(demand[i]) / lot_size).round();
+        let imbalance = ((supply[i] - demand[i]).abs() / lot_size).round();
+        if exec_qty <= 0.0 {
+            continue;
+        }
+        best = match best {
+            Some((best_qty, best_imbalance, first, _))
+                if exec_qty == best_qty && imbalance == best_imbalance =>
+            {
+                Some((best_qty, best_imbalance, first, i))
+            }
+            Some((best_qty, best_imbalance, ..))
+                if exec_qty < best_qty || (exec_qty == best_qty && imbalance > best_imbalance) =>
+            {
+                best
+            }
+            _ => Some((exec_qty, imbalance, i, i)),
+        };
+    }
+    let (_, _, first, last) = best?;
+    let i = (first + last) / 2;
+    Some(Uncross {
+        price_tick: lo + i as i64,
+        fill_buy_at_price: demand[i] <= supply[i],
+        fill_sell_at_price: supply[i] <= demand[i],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{calculate_uncross, Uncross};
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        depth::{HashMapMarketDepth, L2MarketDepth},
+        types::{
+            Bot,
+            Event,
+            OrdType,
+            Order,
+            Side,
+            Status,
+            TimeInForce,
+            AUCTION_EVENT,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+        },
+    };
+
+    fn order(side: Side, price_tick: i64, qty: f64) -> Order {
+        Order::new(
+            1,
+            price_tick,
+            1.0,
+            qty,
+            side,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        )
+    }
+
+    #[test]
+    fn test_calculate_uncross() {
+        let mut depth = HashMapMarketDepth::new(1.0, 1.0);
+        assert_eq!(calculate_uncross(&depth, &[]), None);
+
+        // The indicative book is crossed.
+        depth.update_bid_depth(101.0, 5.0, 0);
+        depth.update_bid_depth(100.0, 5.0, 0);
+        depth.update_ask_depth(99.0, 3.0, 0);
+        depth.update_ask_depth(100.0, 4.0, 0);
+        assert_eq!(
+            calculate_uncross(&depth, &[]),
+            Some(Uncross {
+                price_tick: 100,
+                fill_buy_at_price: false,
+                fill_sell_at_price: true,
+            })
+        );
+
+        // The sell order balances the buy and sell quantities at 100.
+        let uncross = calculate_uncross(&depth, &[order(Side::Sell, 100, 3.0)]).unwrap();
+        assert_eq!(
+            uncross,
+            Uncross {
+                price_tick: 100,
+                fill_buy_at_price: true,
+                fill_sell_at_price: true,
+            }
+        );
+        assert!(uncross.is_filled(&order(Side::Buy, 100, 1.0)));
+        assert!(!uncross.is_filled(&order(Side::Buy, 99, 1.0)));
+        assert!(uncross.is_filled(&order(Side::Sell, 99, 1.0)));
+    }
+
+    #[test]
+    fn test_auction_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            let data = Data::from_slice(&[
+                event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                Event {
+                    ival: 1,
+                    ..event(AUCTION_EVENT, 50, 0.0, 0.0)
+                },
+                event(AUCTION_EVENT, 200, 0.0, 0.0),
+                event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 10.0),
+            ]);
+            let mut hbt = backtest(asset_builder(data, 1.0, 1.0).exchange(exch_kind));
+
+            hbt.elapse(9).unwrap();
+            // The resting order participates in the auction.
+            hbt.submit_buy_order(0, 1, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+
+            hbt.elapse(100).unwrap();
+            // The order crossing the book is collected without being matched.
+            hbt.submit_sell_order(0, 2, 100.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            assert_eq!(hbt.orders(0)[&2].status, Status::New);
+            hbt.submit_buy_order(0, 3, 98.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            hbt.cancel(0, 3, true).unwrap();
+            assert_eq!(hbt.orders(0)[&3].status, Status::Canceled);
+            assert_eq!(hbt.position(0), 0.0);
+
+            // The auction uncrosses at 100, where only the sell order is entirely executed.
+            hbt.elapse(100).unwrap();
+            assert_eq!(hbt.orders(0)[&2].status, Status::Filled);
+            assert_eq!(hbt.orders(0)[&2].exec_price_tick, 100);
+            assert_eq!(hbt.orders(0)[&1].status, Status::New);
+            assert_eq!(hbt.position(0), -1.0);
+        }
+    }
+}
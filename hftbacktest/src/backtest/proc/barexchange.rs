@@ -0,0 +1,544 @@
+use std::{collections::HashMap, mem};
+
+use crate::{
+    backtest::{
+        assettype::AssetType,
+        models::LatencyModel,
+        order::OrderBus,
+        proc::proc::Processor,
+        reader::{Data, Reader},
+        state::State,
+        BacktestError,
+    },
+    depth::{L1MarketDepth, MarketDepth, INVALID_MAX, INVALID_MIN},
+    types::{
+        Event,
+        OrdType,
+        Order,
+        OrderId,
+        Side,
+        Status,
+        TimeInForce,
+        BUY_EVENT,
+        EXCH_BAR_EVENT,
+        EXCH_EVENT,
+        SELL_EVENT,
+    },
+};
+
+/// Determines the assumed path of the price within a bar, which decides the sequence in which the
+/// resting orders are filled, since a bar only tells the extremes the price has reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntraBarPath {
+    /// The price moves from the open to the high, then to the low, and then to the close.
+    OpenHighLowClose,
+    /// The price moves from the open to the low, then to the high, and then to the close.
+    OpenLowHighClose,
+    /// The price moves from the open to whichever of the high and the low is nearer to the open
+    /// first, then to the other, and then to the close. The high is visited first in a tie.
+    #[default]
+    NearestExtremeFirst,
+}
+
+impl IntraBarPath {
+    /// Returns the price ticks visited within the bar, in the order of the visit.
+    pub fn path(&self, open_tick: i64, high_tick: i64, low_tick: i64, close_tick: i64) -> [i64; 4] {
+        let high_first = match self {
+            IntraBarPath::OpenHighLowClose => true,
+            IntraBarPath::OpenLowHighClose => false,
+            IntraBarPath::NearestExtremeFirst => high_tick - open_tick <= open_tick - low_tick,
+        };
+        if high_first {
+            [open_tick, high_tick, low_tick, close_tick]
+        } else {
+            [open_tick, low_tick, high_tick, close_tick]
+        }
+    }
+}
+
+/// The bar-driven exchange model, which simulates the fills from OHLCV bars, [`BAR_EVENT`]s, for
+/// coarse strategy prototyping with the same [`Bot`](crate::types::Bot) API as the tick-level
+/// backtesting.
+///
+/// The bar is processed at its close time. Orders resting in the book at that time are considered
+/// to have rested throughout the bar, and are filled at their price as the price moves along the
+/// [`IntraBarPath`] from the open to the close. As with [`L1NoPartialFillExchange`], the fill model
+/// is conservative: an order is filled only when the price trades through it, and a bar without
+/// volume fills no orders. After the bar, the market is assumed to be the tightest at the close:
+/// the best bid is the close price and the best ask is one tick above it.
+///
+/// Support order types: [`OrdType::Limit`], [`OrdType::Market`]
+/// Support time-in-force: [`TimeInForce::GTC`], [`TimeInForce::GTX`], [`TimeInForce::FOK`],
+/// [`TimeInForce::IOC`]
+///
+/// **Conditions for Full Execution**
+///
+/// Buy order in the order book
+///
+/// - The price along the path falls below your order price
+///
+/// Sell order in the order book
+///
+/// - The price along the path rises above your order price
+///
+/// **Liquidity-Taking Order**
+///
+/// Liquidity-taking orders are fully executed at the best of the close regardless of the quantity.
+/// An order that would take liquidity when there is no opposite best, or an [`TimeInForce::IOC`] or
+/// [`TimeInForce::FOK`] order that wouldn't, is expired.
+///
+/// [`BAR_EVENT`]: crate::types::BAR_EVENT
+/// [`L1NoPartialFillExchange`]: crate::backtest::proc::L1NoPartialFillExchange
+pub struct BarExchange<AT, LM, MD>
+where
+    AT: AssetType,
+    LM: LatencyModel,
+    MD: MarketDepth + L1MarketDepth,
+{
+    reader: Reader<Event>,
+    data: Data<Event>,
+    row_num: usize,
+    orders_to: OrderBus,
+    orders_from: OrderBus,
+    orders: HashMap<OrderId, Order>,
+
+    depth: MD,
+    state: State<AT>,
+    order_latency: LM,
+    path: IntraBarPath,
+    // The open and high price ticks of the bar, whose low and close are yet to be received.
+    pending_bar: Option<(i64, i64)>,
+}
+
+impl<AT, LM, MD> BarExchange<AT, LM, MD>
+where
+    AT: AssetType,
+    LM: LatencyModel,
+    MD: MarketDepth + L1MarketDepth,
+{
+    /// Constructs an instance of `BarExchange`.
+    pub fn new(
+        reader: Reader<Event>,
+        depth: MD,
+        state: State<AT>,
+        order_latency: LM,
+        orders_to: OrderBus,
+        orders_from: OrderBus,
+        path: IntraBarPath,
+    ) -> Self {
+        Self {
+            reader,
+            data: Data::empty(),
+            row_num: 0,
+            orders_to,
+            orders_from,
+            orders: Default::default(),
+            depth,
+            state,
+            order_latency,
+            path,
+            pending_bar: None,
+        }
+    }
+
+    fn process_recv_order_(
+        &mut self,
+        mut order: Order,
+        recv_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        // Processes a new order.
+        if order.req == Status::New {
+            order.req = Status::None;
+            self.ack_new(order, recv_timestamp)?;
+        }
+        // Processes a cancel order.
+        else if order.req == Status::Canceled {
+            order.req = Status::None;
+            self.ack_cancel(order, recv_timestamp)?;
+        } else {
+            return Err(BacktestError::InvalidOrderRequest);
+        }
+        Ok(())
+    }
+
+    fn send_response(&mut self, mut order: Order, timestamp: i64) {
+        order.exch_timestamp = timestamp;
+        let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+        self.orders_to.append(order, local_recv_timestamp);
+    }
+
+    fn fill(
+        &mut self,
+        order: &mut Order,
+        timestamp: i64,
+        maker: bool,
+        exec_price_tick: i64,
+    ) -> Result<(), BacktestError> {
+        if order.status == Status::Expired
+            || order.status == Status::Canceled
+            || order.status == Status::Filled
+        {
+            return Err(BacktestError::InvalidOrderStatus);
+        }
+
+        order.maker = maker;
+        if maker {
+            order.exec_price_tick = order.price_tick;
+        } else {
+            order.exec_price_tick = exec_price_tick;
+        }
+
+        order.exec_qty = order.leaves_qty;
+        order.leaves_qty = 0.0;
+        order.status = Status::Filled;
+        order.exch_timestamp = timestamp;
+
+        self.state.apply_fill(order);
+        self.send_response(order.clone(), timestamp);
+        Ok(())
+    }
+
+    /// Fills the resting orders that the price moving to `price_tick` trades through, in the order
+    /// the price reaches them, and then in the order of their order IDs.
+    fn fill_through(&mut self, price_tick: i64, timestamp: i64) -> Result<(), BacktestError> {
+        let mut crossed: Vec<(i64, OrderId)> = self
+            .orders
+            .values()
+            .filter_map(|order| match order.side {
+                Side::Buy if order.price_tick > price_tick => {
+                    Some((-order.price_tick, order.order_id))
+                }
+                Side::Sell if order.price_tick < price_tick => {
+                    Some((order.price_tick, order.order_id))
+                }
+                _ => None,
+            })
+            .collect();
+        crossed.sort_unstable();
+        for (_, order_id) in crossed {
+            let mut order = self.orders.remove(&order_id).unwrap();
+            let price_tick = order.price_tick;
+            self.fill(&mut order, timestamp, true, price_tick)?;
+        }
+        Ok(())
+    }
+
+    fn process_bar(
+        &mut self,
+        low_tick: i64,
+        close_tick: i64,
+        volume: f64,
+        timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        let Some((open_tick, high_tick)) = self.pending_bar.take() else {
+            // The first half of the bar is missing, for example, when the data starts in the middle
+            // of a bar.
+            return Ok(());
+        };
+        if (volume / self.depth.lot_size()).round() as i64 <= 0 {
+            return Ok(());
+        }
+
+        for price_tick in self.path.path(open_tick, high_tick, low_tick, close_tick) {
+            self.fill_through(price_tick, timestamp)?;
+        }
+
+        let tick_size = self.depth.tick_size();
+        self.depth
+            .update_best_bid(close_tick as f64 * tick_size, volume, timestamp);
+        self.depth
+            .update_best_ask((close_tick + 1) as f64 * tick_size, volume, timestamp);
+        Ok(())
+    }
+
+    fn ack_new(&mut self, mut order: Order, timestamp: i64) -> Result<(), BacktestError> {
+        if self.orders.contains_key(&order.order_id) {
+            return Err(BacktestError::OrderIdExist);
+        }
+
+        let is_market = order.order_type == OrdType::Market;
+        let (opposite_best_tick, crosses) = match order.side {
+            Side::Buy => {
+                let best_ask_tick = self.depth.best_ask_tick();
+                (
+                    best_ask_tick,
+                    best_ask_tick != INVALID_MAX
+                        && (is_market || order.price_tick >= best_ask_tick),
+                )
+            }
+            Side::Sell => {
+                let best_bid_tick = self.depth.best_bid_tick();
+                (
+                    best_bid_tick,
+                    best_bid_tick != INVALID_MIN
+                        && (is_market || order.price_tick <= best_bid_tick),
+                )
+            }
+            Side::None | Side::Unsupported => return Err(BacktestError::InvalidOrderRequest),
+        };
+
+        if crosses {
+            if order.time_in_force == TimeInForce::GTX {
+                order.status = Status::Expired;
+                self.send_response(order, timestamp);
+                Ok(())
+            } else {
+                // Takes the market.
+                self.fill(&mut order, timestamp, false, opposite_best_tick)
+            }
+        } else if is_market
+            || (order.time_in_force != TimeInForce::GTC && order.time_in_force != TimeInForce::GTX)
+        {
+            order.status = Status::Expired;
+            self.send_response(order, timestamp);
+            Ok(())
+        } else {
+            order.status = Status::New;
+            order.exch_timestamp = timestamp;
+            self.orders.insert(order.order_id, order.clone());
+            self.send_response(order, timestamp);
+            Ok(())
+        }
+    }
+
+    fn ack_cancel(&mut self, mut order: Order, timestamp: i64) -> Result<(), BacktestError> {
+        match self.orders.remove(&order.order_id) {
+            Some(mut exch_order) => {
+                exch_order.status = Status::Canceled;
+                self.send_response(exch_order, timestamp);
+            }
+            None => {
+                order.req = Status::Rejected;
+                self.send_response(order, timestamp);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<AT, LM, MD> Processor for BarExchange<AT, LM, MD>
+where
+    AT: AssetType,
+    LM: LatencyModel,
+    MD: MarketDepth + L1MarketDepth,
+{
+    fn initialize_data(&mut self) -> Result<i64, BacktestError> {
+        self.data = self.reader.next()?;
+        for rn in 0..self.data.len() {
+            if self.data[rn].is(EXCH_EVENT) {
+                self.row_num = rn;
+                return Ok(self.data[rn].exch_ts);
+            }
+        }
+        Err(BacktestError::EndOfData)
+    }
+
+    fn process_data(&mut self) -> Result<(i64, i64), BacktestError> {
+        let row_num = self.row_num;
+        self.order_latency.observe(&self.data[row_num]);
+        let timestamp = self.data[row_num].exch_ts;
+        let tick_size = self.depth.tick_size();
+        if self.data[row_num].is(EXCH_BAR_EVENT | BUY_EVENT) {
+            let high_tick = (self.data[row_num].px / tick_size).round() as i64;
+            let open_tick = (self.data[row_num].fval / tick_size).round() as i64;
+            self.pending_bar = Some((open_tick, high_tick));
+        } else if self.data[row_num].is(EXCH_BAR_EVENT | SELL_EVENT) {
+            let low_tick = (self.data[row_num].px / tick_size).round() as i64;
+            let close_tick = (self.data[row_num].fval / tick_size).round() as i64;
+            let volume = self.data[row_num].qty;
+            self.process_bar(low_tick, close_tick, volume, timestamp)?;
+        }
+
+        // Checks
+        let mut next_ts = 0;
+        for rn in (self.row_num + 1)..self.data.len() {
+            if self.data[rn].is(EXCH_EVENT) {
+                self.row_num = rn;
+                next_ts = self.data[rn].exch_ts;
+                break;
+            }
+        }
+
+        if next_ts <= 0 {
+            let next_data = self.reader.next()?;
+            let next_row = &next_data[0];
+            next_ts = next_row.exch_ts;
+            let data = mem::replace(&mut self.data, next_data);
+            self.reader.release(data);
+            self.row_num = 0;
+        }
+        Ok((next_ts, i64::MAX))
+    }
+
+    fn process_recv_order(
+        &mut self,
+        timestamp: i64,
+        _wait_resp_order_id: Option<OrderId>,
+    ) -> Result<bool, BacktestError> {
+        // Processes the order part.
+        while self.orders_from.len() > 0 {
+            let recv_timestamp = self.orders_from.earliest_timestamp().unwrap();
+            if timestamp == recv_timestamp {
+                let (order, _) = self.orders_from.pop_front().unwrap();
+                self.process_recv_order_(order, recv_timestamp)?;
+            } else {
+                assert!(recv_timestamp > timestamp);
+                break;
+            }
+        }
+        Ok(false)
+    }
+
+    fn earliest_recv_order_timestamp(&self) -> i64 {
+        self.orders_from.earliest_timestamp().unwrap_or(i64::MAX)
+    }
+
+    fn earliest_send_order_timestamp(&self) -> i64 {
+        self.orders_to.earliest_timestamp().unwrap_or(i64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntraBarPath;
+    use crate::{
+        backtest::{
+            assettype::LinearAsset,
+            models::ConstantLatency,
+            reader::Data,
+            Backtest,
+            DataSource,
+            L1AssetBuilder,
+        },
+        depth::{BBOMarketDepth, MarketDepth},
+        types::{
+            Bot,
+            Event,
+            OrdType,
+            Status,
+            TimeInForce,
+            BAR_EVENT,
+            BUY_EVENT,
+            EXCH_EVENT,
+            LOCAL_EVENT,
+            SELL_EVENT,
+        },
+    };
+
+    #[test]
+    fn test_path() {
+        assert_eq!(
+            IntraBarPath::OpenHighLowClose.path(100, 103, 98, 101),
+            [100, 103, 98, 101]
+        );
+        assert_eq!(
+            IntraBarPath::OpenLowHighClose.path(100, 103, 98, 101),
+            [100, 98, 103, 101]
+        );
+        assert_eq!(
+            IntraBarPath::NearestExtremeFirst.path(100, 103, 98, 101),
+            [100, 98, 103, 101]
+        );
+        assert_eq!(
+            IntraBarPath::NearestExtremeFirst.path(100, 101, 98, 101),
+            [100, 101, 98, 101]
+        );
+    }
+
+    fn bar(ts: i64, open: f64, high: f64, low: f64, close: f64, volume: f64) -> [Event; 2] {
+        [
+            Event {
+                ev: BAR_EVENT | BUY_EVENT | EXCH_EVENT | LOCAL_EVENT,
+                exch_ts: ts,
+                local_ts: ts,
+                px: high,
+                qty: volume,
+                order_id: 0,
+                ival: 0,
+                fval: open,
+            },
+            Event {
+                ev: BAR_EVENT | SELL_EVENT | EXCH_EVENT | LOCAL_EVENT,
+                exch_ts: ts,
+                local_ts: ts,
+                px: low,
+                qty: volume,
+                order_id: 0,
+                ival: 0,
+                fval: close,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_bar_backtest() {
+        let data = Data::from_slice(
+            &[
+                bar(10, 100.0, 101.0, 99.0, 100.0, 10.0),
+                bar(100, 100.0, 102.0, 99.0, 101.0, 10.0),
+                bar(200, 101.0, 103.0, 98.0, 98.0, 10.0),
+                bar(300, 98.0, 98.0, 96.0, 98.0, 0.0),
+                bar(400, 98.0, 98.0, 96.0, 97.0, 5.0),
+                // The backtest ends at the end of the local data, before the exchange processes
+                // the last bar at the same timestamp.
+                bar(500, 97.0, 97.0, 97.0, 97.0, 0.0),
+            ]
+            .concat(),
+        );
+        let mut hbt = Backtest::builder()
+            .add(
+                L1AssetBuilder::new()
+                    .data(vec![DataSource::Data(data)])
+                    .latency_model(ConstantLatency::new(1, 1))
+                    .asset_type(LinearAsset::new(1.0))
+                    .depth(|| BBOMarketDepth::new(1.0, 1.0))
+                    .bar_path(IntraBarPath::NearestExtremeFirst)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        // The market is the tightest at the close of the bar.
+        hbt.elapse(10).unwrap();
+        assert_eq!(hbt.depth(0).best_bid_tick(), 100);
+        assert_eq!(hbt.depth(0).best_ask_tick(), 101);
+
+        hbt.submit_buy_order(0, 1, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+        hbt.submit_sell_order(0, 2, 102.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+
+        // The bar reaching the order prices doesn't fill the orders.
+        hbt.elapse(90).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::New);
+        assert_eq!(hbt.orders(0)[&2].status, Status::New);
+
+        // The bar trading through the order prices does.
+        hbt.elapse(100).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::Filled);
+        assert_eq!(hbt.orders(0)[&1].exec_price_tick, 99);
+        assert_eq!(hbt.orders(0)[&2].status, Status::Filled);
+        assert_eq!(hbt.orders(0)[&2].exec_price_tick, 102);
+        assert_eq!(hbt.position(0), 0.0);
+
+        // The marketable orders take the market at the close.
+        hbt.submit_buy_order(0, 3, 0.0, 1.0, TimeInForce::GTC, OrdType::Market, true)
+            .unwrap();
+        assert_eq!(hbt.orders(0)[&3].status, Status::Filled);
+        assert_eq!(hbt.orders(0)[&3].exec_price_tick, 99);
+        hbt.submit_sell_order(0, 4, 98.0, 1.0, TimeInForce::GTX, OrdType::Limit, true)
+            .unwrap();
+        assert_eq!(hbt.orders(0)[&4].status, Status::Expired);
+
+        // A bar without volume fills no orders.
+        hbt.submit_buy_order(0, 5, 97.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+        hbt.elapse(100).unwrap();
+        assert_eq!(hbt.orders(0)[&5].status, Status::New);
+
+        hbt.elapse(100).unwrap();
+        assert_eq!(hbt.orders(0)[&5].status, Status::Filled);
+        assert_eq!(hbt.position(0), 2.0);
+    }
+}
@@ -0,0 +1,105 @@
+/// Determines how the exchange handles a resting limit order whose price the best price on the
+/// opposite side crosses without a trade, which calls for a different assumption depending on
+/// the quality of the venue's data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BookCrossRule {
+    /// The order is filled at its price as a maker order.
+    #[default]
+    FillAtOrderPrice,
+    /// The order is filled at the crossing best price, which is at or better than its price, as a
+    /// maker order.
+    FillAtCrossPrice,
+    /// The order is not filled by the book crossing it, and waits for a trade print through its
+    /// price.
+    RequireTrade,
+}
+
+impl BookCrossRule {
+    /// Returns `true` if the best price on the opposite side crossing a resting order fills it.
+    pub fn fills_on_cross(&self) -> bool {
+        *self != BookCrossRule::RequireTrade
+    }
+
+    /// Returns the price in ticks at which the order at `price_tick` is filled when the best price
+    /// on the opposite side crosses it at `best_tick`.
+    pub fn exec_price_tick(&self, price_tick: i64, best_tick: i64) -> i64 {
+        match self {
+            BookCrossRule::FillAtCrossPrice => best_tick,
+            BookCrossRule::FillAtOrderPrice | BookCrossRule::RequireTrade => price_tick,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BookCrossRule;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{
+            Bot,
+            OrdType,
+            Status,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+            TRADE_EVENT,
+        },
+    };
+
+    #[test]
+    fn test_book_cross_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            for (rule, expected) in [
+                (BookCrossRule::FillAtOrderPrice, Some(102)),
+                (BookCrossRule::FillAtCrossPrice, Some(103)),
+                (BookCrossRule::RequireTrade, None),
+            ] {
+                let data = Data::from_slice(&[
+                    event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                    event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                    // The best bid jumps through the resting sell order's price.
+                    event(DEPTH_EVENT | SELL_EVENT, 100, 101.0, 0.0),
+                    event(DEPTH_EVENT | SELL_EVENT, 100, 104.0, 10.0),
+                    event(DEPTH_EVENT | BUY_EVENT, 100, 103.0, 10.0),
+                    event(TRADE_EVENT | BUY_EVENT, 200, 103.0, 1.0),
+                    event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 5.0),
+                ]);
+                let mut hbt = backtest(
+                    asset_builder(data, 1.0, 1.0)
+                        .exchange(exch_kind)
+                        .book_cross_rule(rule),
+                );
+
+                hbt.elapse(9).unwrap();
+                hbt.submit_sell_order(0, 1, 102.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                    .unwrap();
+
+                hbt.elapse(140).unwrap();
+                let order = &hbt.orders(0)[&1];
+                match expected {
+                    Some(exec_price_tick) => {
+                        assert_eq!(order.status, Status::Filled);
+                        assert!(order.maker);
+                        assert_eq!(order.exec_price_tick, exec_price_tick);
+                    }
+                    None => {
+                        assert_eq!(order.status, Status::New);
+                        // The trade print through the order's price fills it.
+                        hbt.elapse(100).unwrap();
+                        let order = &hbt.orders(0)[&1];
+                        assert_eq!(order.status, Status::Filled);
+                        assert_eq!(order.exec_price_tick, 102);
+                    }
+                }
+            }
+        }
+    }
+}
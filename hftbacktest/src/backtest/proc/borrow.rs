@@ -0,0 +1,147 @@
+/// The number of nanoseconds in a year of 365 days, over which the borrow rate is annualized.
+const YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0 * 1_000_000_000.0;
+
+/// Charges the carrying cost of the short position of a spot or margin asset, which is borrowed
+/// to be sold short, or disallows the short sale if the asset cannot be borrowed.
+///
+/// The borrow interest accrues continuously on the notional value of the short position at the
+/// mid-price, at the annualized borrow rate, which can change over time following the rate
+/// schedule. The interest accrues in
+/// [`StateValues::borrow_cost`](crate::types::StateValues::borrow_cost) and is deducted from the
+/// equity, like the fee. If the asset is not shortable, the exchange rejects a sell order that,
+/// along with the open sell orders, would take the position below zero.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::BorrowCost;
+///
+/// // Borrows at 5% a year, which rises to 8% from 2024-07-01 00:00 UTC.
+/// let borrow = BorrowCost::new(0.05).rate_from(1_719_792_000_000_000_000, 0.08);
+///
+/// // Disallows short sales.
+/// let no_short = BorrowCost::new(0.0).shortable(false);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BorrowCost {
+    // (start timestamp, annualized rate) in the order of the start timestamp.
+    rates: Vec<(i64, f64)>,
+    shortable: bool,
+    last_ts: Option<i64>,
+}
+
+impl BorrowCost {
+    /// Constructs an instance of `BorrowCost` with the annualized borrow `rate`.
+    pub fn new(rate: f64) -> Self {
+        Self {
+            rates: vec![(i64::MIN, rate)],
+            shortable: true,
+            last_ts: None,
+        }
+    }
+
+    /// Changes the annualized borrow rate to `rate` from the `timestamp`, which must be later than
+    /// that of the previous change.
+    pub fn rate_from(mut self, timestamp: i64, rate: f64) -> Self {
+        assert!(timestamp > self.rates.last().unwrap().0);
+        self.rates.push((timestamp, rate));
+        self
+    }
+
+    /// Sets whether the asset can be borrowed to be sold short. The default value is `true`.
+    pub fn shortable(self, shortable: bool) -> Self {
+        Self { shortable, ..self }
+    }
+
+    /// Returns `true` if the asset can be sold short.
+    pub fn is_shortable(&self) -> bool {
+        self.shortable
+    }
+
+    /// Returns the annualized borrow rate at the timestamp.
+    pub fn rate(&self, timestamp: i64) -> f64 {
+        self.rates
+            .iter()
+            .rev()
+            .find(|&&(start, _)| start <= timestamp)
+            .map(|&(_, rate)| rate)
+            .unwrap()
+    }
+
+    /// Returns the borrow rate accrued from the last call up to `timestamp`, which is the
+    /// annualized rate integrated over the elapsed time in years. The first call only sets the
+    /// start of the accrual, as there is no position before the backtest begins.
+    pub fn take_accrual(&mut self, timestamp: i64) -> f64 {
+        let from = *self.last_ts.get_or_insert(timestamp);
+        if timestamp <= from {
+            return 0.0;
+        }
+        self.last_ts = Some(timestamp);
+
+        let mut accrual = 0.0;
+        for (i, &(start, rate)) in self.rates.iter().enumerate() {
+            let end = self.rates.get(i + 1).map_or(i64::MAX, |&(next, _)| next);
+            if start >= timestamp {
+                break;
+            }
+            if end <= from {
+                continue;
+            }
+            accrual += rate * (end.min(timestamp) - start.max(from)) as f64;
+        }
+        accrual / YEAR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BorrowCost, YEAR};
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+        },
+        types::{Bot, OrdType, Status, TimeInForce, BUY_EVENT, DEPTH_EVENT, SELL_EVENT},
+    };
+
+    #[test]
+    fn test_take_accrual() {
+        let mut borrow = BorrowCost::new(0.1).rate_from(200, 0.2);
+        assert_eq!(borrow.rate(199), 0.1);
+        assert_eq!(borrow.rate(200), 0.2);
+        assert_eq!(borrow.take_accrual(100), 0.0);
+        assert!((borrow.take_accrual(150) - 0.1 * 50.0 / YEAR).abs() < 1e-24);
+        assert!((borrow.take_accrual(300) - (0.1 * 50.0 + 0.2 * 100.0) / YEAR).abs() < 1e-24);
+        assert_eq!(borrow.take_accrual(300), 0.0);
+    }
+
+    #[test]
+    fn test_borrow_backtest() {
+        for shortable in [true, false] {
+            let data = Data::from_slice(&[
+                event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 2, 102.0, 10.0),
+                event(DEPTH_EVENT | BUY_EVENT, 1_000_000_011, 100.0, 5.0),
+            ]);
+            let mut hbt = backtest(
+                asset_builder(data, 1.0, 1.0)
+                    .borrow_cost(BorrowCost::new(0.1).shortable(shortable)),
+            );
+
+            hbt.elapse(9).unwrap();
+            hbt.submit_sell_order(0, 1, 100.0, 2.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            if !shortable {
+                assert_eq!(hbt.orders(0)[&1].status, Status::Expired);
+                assert_eq!(hbt.position(0), 0.0);
+                continue;
+            }
+            assert_eq!(hbt.position(0), -2.0);
+
+            // The interest accrues on the short position at the mid-price for a second.
+            hbt.elapse(1_000_000_000).unwrap();
+            let borrow_cost = hbt.state_values(0).borrow_cost;
+            let expected = 2.0 * 101.0 * 0.1 * 1_000_000_000.0 / YEAR;
+            assert!((borrow_cost - expected).abs() < 1e-15);
+        }
+    }
+}
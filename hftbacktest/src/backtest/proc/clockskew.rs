@@ -0,0 +1,100 @@
+/// Simulates the skew of the exchange clock against the local clock, which shifts the exchange
+/// timestamps of the order responses that the strategy receives.
+///
+/// The exchange clock is ahead of the local clock by the `offset`, which drifts at the `drift`
+/// rate, the skew accumulated per unit of time, from the first order response. For example, a
+/// drift of `1e-6` makes the exchange clock run fast by 1 microsecond per second. The skew only
+/// affects the reported [`Order::exch_timestamp`](crate::types::Order::exch_timestamp), and thus
+/// the order latency observed through
+/// [`Bot::order_latency`](crate::types::Bot::order_latency), while the simulation itself runs on
+/// the timestamps in the data.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::ClockSkew;
+///
+/// // The exchange clock is 2ms ahead and runs fast by 5 microseconds per second.
+/// let skew = ClockSkew::new(2_000_000).drift(5e-6);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ClockSkew {
+    offset: i64,
+    drift: f64,
+    origin: Option<i64>,
+}
+
+impl ClockSkew {
+    /// Constructs an instance of `ClockSkew` with the constant `offset` by which the exchange clock
+    /// is ahead of the local clock. A negative offset means the exchange clock is behind.
+    pub fn new(offset: i64) -> Self {
+        Self {
+            offset,
+            drift: 0.0,
+            origin: None,
+        }
+    }
+
+    /// Sets the drift rate of the exchange clock, which must be greater than `-1` so that the
+    /// exchange clock still runs forward. The default value is `0`.
+    pub fn drift(self, drift: f64) -> Self {
+        assert!(drift > -1.0);
+        Self { drift, ..self }
+    }
+
+    /// Returns the skew of the exchange clock at the exchange timestamp.
+    pub fn skew(&mut self, exch_timestamp: i64) -> i64 {
+        let origin = *self.origin.get_or_insert(exch_timestamp);
+        self.offset + (self.drift * (exch_timestamp - origin) as f64).round() as i64
+    }
+
+    /// Returns the exchange timestamp read from the skewed exchange clock.
+    pub fn apply(&mut self, exch_timestamp: i64) -> i64 {
+        exch_timestamp + self.skew(exch_timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClockSkew;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+        },
+        types::{Bot, OrdType, TimeInForce, BUY_EVENT, DEPTH_EVENT, SELL_EVENT},
+    };
+
+    #[test]
+    fn test_apply() {
+        let mut skew = ClockSkew::new(-50).drift(0.01);
+        assert_eq!(skew.apply(1_000), 950);
+        assert_eq!(skew.apply(2_000), 1_960);
+        assert_eq!(skew.skew(11_000), 50);
+    }
+
+    #[test]
+    fn test_skew_backtest() {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+            event(DEPTH_EVENT | BUY_EVENT, 1_000, 100.0, 5.0),
+        ]);
+        let mut hbt =
+            backtest(asset_builder(data, 1.0, 1.0).clock_skew(ClockSkew::new(-5).drift(0.5)));
+
+        hbt.elapse(9).unwrap();
+        hbt.submit_buy_order(0, 1, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+        // The exchange clock is behind, so the order appears to reach the exchange before it is
+        // sent.
+        assert_eq!(hbt.orders(0)[&1].exch_timestamp, 6);
+        assert_eq!(hbt.order_latency(0), Some((10, 6, 12)));
+
+        // The skew drifts from the first response: the cancel reaches the exchange at 113, when
+        // the skew is -5 + 0.5 * (113 - 11).
+        hbt.elapse(100).unwrap();
+        hbt.cancel(0, 1, true).unwrap();
+        assert_eq!(hbt.orders(0)[&1].exch_timestamp, 159);
+        assert_eq!(hbt.order_latency(0).unwrap().1, 159);
+    }
+}
@@ -0,0 +1,178 @@
+use thiserror::Error;
+
+use crate::types::OrdType;
+
+/// The relative tolerance, in ticks or lots, within which a price or quantity is considered a
+/// multiple of the tick size or the lot size, allowing for the floating-point error.
+const MULTIPLE_TOLERANCE: f64 = 1e-6;
+
+/// The instrument constraint that an order request violates.
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConstraintViolation {
+    #[error("price is not a multiple of the tick size")]
+    TickSize,
+    #[error("quantity is not a multiple of the lot size")]
+    LotSize,
+    #[error("quantity is less than the minimum quantity")]
+    MinQty,
+    #[error("notional value is less than the minimum notional")]
+    MinNotional,
+}
+
+/// Enforces the instrument constraints on the order requests at the local, as the venue rejects a
+/// non-conforming order upon submission instead of rounding it.
+///
+/// The price must be a multiple of the tick size and the quantity a multiple of the lot size of
+/// the market depth; the price of a market order is not checked, as the venue ignores it.
+/// Optionally, the quantity must be at least the minimum quantity, and the notional value, the
+/// price times the quantity, at least the minimum notional. A non-conforming request is rejected
+/// with [`BacktestError::ConstraintViolation`](crate::backtest::BacktestError::ConstraintViolation).
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::OrderConstraints;
+///
+/// let constraints = OrderConstraints::new().min_qty(0.001).min_notional(5.0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OrderConstraints {
+    min_qty: f64,
+    min_notional: f64,
+}
+
+impl OrderConstraints {
+    /// Constructs an instance of `OrderConstraints` that only enforces the tick size and the lot
+    /// size.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the minimum order quantity. The default value is `0`.
+    pub fn min_qty(self, min_qty: f64) -> Self {
+        Self { min_qty, ..self }
+    }
+
+    /// Sets the minimum notional value of an order. The default value is `0`.
+    pub fn min_notional(self, min_notional: f64) -> Self {
+        Self {
+            min_notional,
+            ..self
+        }
+    }
+
+    /// Checks the order request against the constraints.
+    pub fn check(
+        &self,
+        order_type: OrdType,
+        price: f64,
+        qty: f64,
+        tick_size: f64,
+        lot_size: f64,
+    ) -> Result<(), ConstraintViolation> {
+        let is_market = matches!(order_type, OrdType::Market | OrdType::StopMarket);
+        if !is_market && !is_multiple(price, tick_size) {
+            return Err(ConstraintViolation::TickSize);
+        }
+        if !is_multiple(qty, lot_size) {
+            return Err(ConstraintViolation::LotSize);
+        }
+        if qty < self.min_qty {
+            return Err(ConstraintViolation::MinQty);
+        }
+        if price * qty < self.min_notional {
+            return Err(ConstraintViolation::MinNotional);
+        }
+        Ok(())
+    }
+}
+
+fn is_multiple(value: f64, unit: f64) -> bool {
+    let units = value / unit;
+    (units - units.round()).abs() <= MULTIPLE_TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConstraintViolation, OrderConstraints};
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            BacktestError,
+        },
+        types::{Bot, OrdType, Status, TimeInForce, BUY_EVENT, DEPTH_EVENT, SELL_EVENT},
+    };
+
+    #[test]
+    fn test_check() {
+        let constraints = OrderConstraints::new().min_qty(0.002).min_notional(5.0);
+        assert_eq!(
+            constraints.check(OrdType::Limit, 2500.1, 0.003, 0.1, 0.001),
+            Ok(())
+        );
+        assert_eq!(
+            constraints.check(OrdType::Limit, 2500.15, 0.003, 0.1, 0.001),
+            Err(ConstraintViolation::TickSize)
+        );
+        assert_eq!(
+            constraints.check(OrdType::Market, 2500.15, 0.003, 0.1, 0.001),
+            Ok(())
+        );
+        assert_eq!(
+            constraints.check(OrdType::Limit, 2500.1, 0.0035, 0.1, 0.001),
+            Err(ConstraintViolation::LotSize)
+        );
+        assert_eq!(
+            constraints.check(OrdType::Limit, 2500.1, 0.001, 0.1, 0.001),
+            Err(ConstraintViolation::MinQty)
+        );
+        assert_eq!(
+            constraints.check(OrdType::Limit, 1000.0, 0.002, 0.1, 0.001),
+            Err(ConstraintViolation::MinNotional)
+        );
+    }
+
+    #[test]
+    fn test_constraints_backtest() {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 2, 100.5, 10.0),
+            event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 5.0),
+        ]);
+        let mut hbt = backtest(
+            asset_builder(data, 0.5, 0.1)
+                .order_constraints(OrderConstraints::new().min_notional(20.0)),
+        );
+
+        hbt.elapse(9).unwrap();
+        assert!(matches!(
+            hbt.submit_buy_order(0, 1, 99.7, 0.5, TimeInForce::GTC, OrdType::Limit, true),
+            Err(BacktestError::ConstraintViolation(
+                ConstraintViolation::TickSize
+            ))
+        ));
+        assert!(matches!(
+            hbt.submit_buy_order(0, 1, 99.5, 0.15, TimeInForce::GTC, OrdType::Limit, true),
+            Err(BacktestError::ConstraintViolation(
+                ConstraintViolation::LotSize
+            ))
+        ));
+        assert!(matches!(
+            hbt.submit_buy_order(0, 1, 99.5, 0.1, TimeInForce::GTC, OrdType::Limit, true),
+            Err(BacktestError::ConstraintViolation(
+                ConstraintViolation::MinNotional
+            ))
+        ));
+        assert!(hbt.orders(0).is_empty());
+
+        hbt.submit_buy_order(0, 1, 99.5, 0.5, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::New);
+        assert!(matches!(
+            hbt.modify(0, 1, 99.5, 0.1, true),
+            Err(BacktestError::ConstraintViolation(
+                ConstraintViolation::MinNotional
+            ))
+        ));
+    }
+}
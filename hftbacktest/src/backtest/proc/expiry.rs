@@ -0,0 +1,162 @@
+use std::collections::BTreeSet;
+
+use crate::types::{Order, OrderId, TimeInForce};
+
+/// Schedules the expiry of [`TimeInForce::GTD`] orders at the exchange.
+///
+/// The exchange registers a GTD order when it receives the order, and expires it once the
+/// exchange time reaches its [`expire_timestamp`](Order::expire_timestamp), unless it has been
+/// executed or canceled by then. Since the exchange time advances by the events in the data and the
+/// order requests, the expiration is reported at the first of them at or after the expiry.
+#[derive(Default)]
+pub struct OrderExpiry {
+    // (expire_timestamp, order_id) in the expiry order.
+    expiries: BTreeSet<(i64, OrderId)>,
+}
+
+impl OrderExpiry {
+    /// Returns `true` if the order is a GTD order that has expired at the timestamp.
+    pub fn is_expired(order: &Order, timestamp: i64) -> bool {
+        order.time_in_force == TimeInForce::GTD && order.expire_timestamp <= timestamp
+    }
+
+    /// Schedules the expiry of the order if it is a GTD order.
+    pub fn insert(&mut self, order: &Order) {
+        if order.time_in_force == TimeInForce::GTD {
+            self.expiries
+                .insert((order.expire_timestamp, order.order_id));
+        }
+    }
+
+    /// Removes and returns the IDs of the orders scheduled to expire at or before the timestamp,
+    /// in the expiry order. Some of them may have already been executed or canceled.
+    pub fn take_expired(&mut self, timestamp: i64) -> Vec<OrderId> {
+        match self.expiries.first() {
+            Some(&(expire_timestamp, _)) if expire_timestamp <= timestamp => {}
+            _ => return Vec::new(),
+        }
+        let rest = match timestamp.checked_add(1) {
+            Some(next) => self.expiries.split_off(&(next, 0)),
+            None => BTreeSet::new(),
+        };
+        std::mem::replace(&mut self.expiries, rest)
+            .into_iter()
+            .map(|(_, order_id)| order_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OrderExpiry;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{
+            Bot,
+            OrdType,
+            Order,
+            OrderRequest,
+            Side,
+            Status,
+            TimeInForce,
+            TriggerBy,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+        },
+    };
+
+    fn gtd(order_id: u64, expire_timestamp: i64) -> Order {
+        let mut order = Order::new(
+            order_id,
+            100,
+            1.0,
+            1.0,
+            Side::Buy,
+            OrdType::Limit,
+            TimeInForce::GTD,
+        );
+        order.expire_timestamp = expire_timestamp;
+        order
+    }
+
+    #[test]
+    fn test_take_expired() {
+        let mut expiry = OrderExpiry::default();
+        expiry.insert(&gtd(1, 200));
+        expiry.insert(&gtd(2, 100));
+        expiry.insert(&gtd(3, 300));
+        expiry.insert(&Order::new(
+            4,
+            100,
+            1.0,
+            1.0,
+            Side::Buy,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        ));
+
+        assert!(expiry.take_expired(99).is_empty());
+        assert_eq!(expiry.take_expired(200), vec![2, 1]);
+        assert!(expiry.take_expired(299).is_empty());
+        assert_eq!(expiry.take_expired(i64::MAX), vec![3]);
+        assert!(OrderExpiry::is_expired(&gtd(1, 200), 200));
+        assert!(!OrderExpiry::is_expired(&gtd(1, 200), 199));
+    }
+
+    #[test]
+    fn test_gtd_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            let data = Data::from_slice(&[
+                event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                event(DEPTH_EVENT | BUY_EVENT, 100, 100.0, 5.0),
+                event(DEPTH_EVENT | BUY_EVENT, 200, 100.0, 10.0),
+                event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 5.0),
+            ]);
+            let mut hbt = backtest(asset_builder(data, 1.0, 1.0).exchange(exch_kind));
+
+            // A GTD order requires the expiry.
+            assert!(hbt
+                .submit_buy_order(0, 1, 99.0, 1.0, TimeInForce::GTD, OrdType::Limit, true)
+                .is_err());
+
+            hbt.elapse(9).unwrap();
+            let order = |order_id, expire_timestamp| OrderRequest {
+                order_id,
+                price: 99.0,
+                qty: 1.0,
+                side: Side::Buy,
+                time_in_force: TimeInForce::GTD,
+                order_type: OrdType::Limit,
+                trigger_price: 0.0,
+                expire_timestamp,
+                reduce_only: false,
+                trigger_by: TriggerBy::Default,
+            };
+            hbt.submit_order(0, order(2, 150), true).unwrap();
+            hbt.submit_order(0, order(3, 250), true).unwrap();
+            assert_eq!(hbt.orders(0)[&2].status, Status::New);
+            assert_eq!(hbt.orders(0)[&2].expire_timestamp, 150);
+
+            // The exchange expires the order at the first event after the expiry, and the
+            // expiration report arrives with the response latency.
+            hbt.elapse(186).unwrap();
+            assert_eq!(hbt.orders(0)[&2].status, Status::New);
+            hbt.elapse(10).unwrap();
+            assert_eq!(hbt.orders(0)[&2].status, Status::Expired);
+            assert_eq!(hbt.orders(0)[&2].exch_timestamp, 200);
+            assert_eq!(hbt.orders(0)[&3].status, Status::New);
+
+            hbt.cancel(0, 3, true).unwrap();
+            assert_eq!(hbt.orders(0)[&3].status, Status::Canceled);
+        }
+    }
+}
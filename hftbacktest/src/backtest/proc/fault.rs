@@ -0,0 +1,226 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    backtest::order::OrderBus,
+    types::{Order, Status},
+};
+
+/// Injects faults into the exchange's order handling, so that a strategy can verify that its
+/// order-state machine is robust against what live venues occasionally do.
+///
+/// * Rejection - An order request is rejected upon receipt, as if the venue were overloaded.
+/// * Dropped acknowledgement - The response acknowledging a new order, a cancel, or a modification
+///   never reaches the local, while the exchange has processed the request.
+/// * Duplicated execution report - The response reporting a fill is delivered twice. The local's
+///   position accounting disregards the duplicate, but the strategy sees the response twice.
+///
+/// The faults occur randomly at the specified rates.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::FaultInjector;
+///
+/// let faults = FaultInjector::new()
+///     .reject_rate(0.01)
+///     .drop_ack_rate(0.001)
+///     .duplicate_fill_rate(0.001)
+///     .seed(1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FaultInjector {
+    reject_rate: f64,
+    drop_ack_rate: f64,
+    duplicate_fill_rate: f64,
+    rng: StdRng,
+    rejected: usize,
+    dropped: usize,
+    duplicated: usize,
+}
+
+impl FaultInjector {
+    /// Constructs an instance of `FaultInjector` that injects no faults.
+    pub fn new() -> Self {
+        Self {
+            reject_rate: 0.0,
+            drop_ack_rate: 0.0,
+            duplicate_fill_rate: 0.0,
+            rng: StdRng::from_entropy(),
+            rejected: 0,
+            dropped: 0,
+            duplicated: 0,
+        }
+    }
+
+    /// Sets the probability that an order request is rejected upon receipt.
+    pub fn reject_rate(self, reject_rate: f64) -> Self {
+        Self {
+            reject_rate,
+            ..self
+        }
+    }
+
+    /// Sets the probability that an acknowledgement is dropped.
+    pub fn drop_ack_rate(self, drop_ack_rate: f64) -> Self {
+        Self {
+            drop_ack_rate,
+            ..self
+        }
+    }
+
+    /// Sets the probability that an execution report is duplicated.
+    pub fn duplicate_fill_rate(self, duplicate_fill_rate: f64) -> Self {
+        Self {
+            duplicate_fill_rate,
+            ..self
+        }
+    }
+
+    /// Seeds the random number generator, so that the same faults occur in every run.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Seeds the random number generator. This is invoked by the asset builder if a seed is set.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    fn occurs(&mut self, rate: f64) -> bool {
+        rate > 0.0 && self.rng.gen::<f64>() < rate
+    }
+
+    /// Returns `true` if the order request received by the exchange is to be rejected.
+    pub fn reject(&mut self) -> bool {
+        let reject = self.occurs(self.reject_rate);
+        if reject {
+            self.rejected += 1;
+        }
+        reject
+    }
+
+    /// Sends the order response to the local, dropping or duplicating it at the fault rates.
+    pub fn send(&mut self, orders_to: &mut OrderBus, order: Order, timestamp: i64) {
+        let is_fill = order.status == Status::Filled || order.status == Status::PartiallyFilled;
+        let is_ack = order.req != Status::Rejected
+            && order.req != Status::Queried
+            && (order.status == Status::New || order.status == Status::Canceled);
+        if is_ack && self.occurs(self.drop_ack_rate) {
+            self.dropped += 1;
+            return;
+        }
+        if is_fill && self.occurs(self.duplicate_fill_rate) {
+            self.duplicated += 1;
+            orders_to.append(order.clone(), timestamp);
+        }
+        orders_to.append(order, timestamp);
+    }
+
+    /// Returns the number of order requests rejected so far.
+    pub fn rejected_count(&self) -> usize {
+        self.rejected
+    }
+
+    /// Returns the number of acknowledgements dropped so far.
+    pub fn dropped_count(&self) -> usize {
+        self.dropped
+    }
+
+    /// Returns the number of execution reports duplicated so far.
+    pub fn duplicated_count(&self) -> usize {
+        self.duplicated
+    }
+}
+
+impl Default for FaultInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sends the order response to the local through the fault injector, if any.
+pub fn send_response(
+    faults: Option<&mut FaultInjector>,
+    orders_to: &mut OrderBus,
+    order: Order,
+    timestamp: i64,
+) {
+    match faults {
+        Some(faults) => faults.send(orders_to, order, timestamp),
+        None => orders_to.append(order, timestamp),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FaultInjector;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{
+            Bot,
+            OrdType,
+            Status,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+            TRADE_EVENT,
+        },
+    };
+
+    fn run(exch_kind: ExchangeKind, faults: FaultInjector) -> (Status, Status, f64) {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+            event(TRADE_EVENT | SELL_EVENT, 100, 99.0, 1.0),
+            event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 10.0),
+        ]);
+        let mut hbt = backtest(
+            asset_builder(data, 1.0, 1.0)
+                .exchange(exch_kind)
+                .fault_injector(faults),
+        );
+
+        hbt.elapse(9).unwrap();
+        hbt.submit_buy_order(0, 1, 100.0, 1.0, TimeInForce::GTC, OrdType::Limit, false)
+            .unwrap();
+        hbt.elapse(50).unwrap();
+        let ack_status = hbt.orders(0)[&1].status;
+        // The trade through the order's price fills it.
+        hbt.elapse(100).unwrap();
+        (ack_status, hbt.orders(0)[&1].status, hbt.position(0))
+    }
+
+    #[test]
+    fn test_faults() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            assert_eq!(
+                run(exch_kind, FaultInjector::new()),
+                (Status::New, Status::Filled, 1.0)
+            );
+            assert_eq!(
+                run(exch_kind, FaultInjector::new().reject_rate(1.0)),
+                (Status::Expired, Status::Expired, 0.0)
+            );
+            // The local doesn't know that the order is open until it is filled.
+            assert_eq!(
+                run(exch_kind, FaultInjector::new().drop_ack_rate(1.0)),
+                (Status::None, Status::Filled, 1.0)
+            );
+            // The duplicated execution report doesn't double the position.
+            assert_eq!(
+                run(exch_kind, FaultInjector::new().duplicate_fill_rate(1.0)),
+                (Status::New, Status::Filled, 1.0)
+            );
+        }
+    }
+}
@@ -0,0 +1,391 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    marker::PhantomData,
+    mem,
+};
+
+use crate::{
+    backtest::{
+        assettype::AssetType,
+        models::{LatencyModel, RequestType},
+        order::OrderBus,
+        proc::proc::{LocalProcessor, Processor},
+        reader::{Data, Reader},
+        state::State,
+        BacktestError,
+    },
+    depth::MarketDepth,
+    types::{
+        Event,
+        OrdType,
+        Order,
+        OrderId,
+        Roll,
+        Side,
+        StateValues,
+        Status,
+        TimeInForce,
+        LOCAL_EVENT,
+        LOCAL_ROLL_EVENT,
+    },
+};
+
+/// The level of the feed data processed by a [`FeedLocal`], which determines how the local market
+/// depth is built from the feed events and which events are the market trades.
+pub trait LocalFeed<MD> {
+    /// The event flag of the market trades stored in the local processor.
+    const TRADE_EVENT: u64;
+
+    /// Applies the event to the market depth. Returns `false` if the event isn't a depth event of
+    /// this feed level.
+    fn apply(depth: &mut MD, ev: &Event) -> Result<bool, BacktestError>;
+}
+
+/// The local model for the feed data levels other than Level-2, such as [`L1Local`] and
+/// [`L3Local`], which differ only in how the feed events build the local market depth. Feed gap
+/// detection is not supported, since only Level-2 data has the sequence of depth updates to check.
+///
+/// [`L1Local`]: crate::backtest::proc::L1Local
+/// [`L3Local`]: crate::backtest::proc::L3Local
+pub struct FeedLocal<AT, LM, MD, F>
+where
+    AT: AssetType,
+    LM: LatencyModel,
+    MD: MarketDepth,
+    F: LocalFeed<MD>,
+{
+    reader: Reader<Event>,
+    data: Data<Event>,
+    row_num: usize,
+    orders: HashMap<OrderId, Order>,
+    orders_to: OrderBus,
+    orders_from: OrderBus,
+    depth: MD,
+    state: State<AT>,
+    order_latency: LM,
+    trades: Vec<Event>,
+    last_feed_latency: Option<(i64, i64)>,
+    last_order_latency: Option<(i64, i64, i64)>,
+    aux: HashMap<u64, Event>,
+    rolls: Vec<Roll>,
+    _feed: PhantomData<F>,
+}
+
+impl<AT, LM, MD, F> FeedLocal<AT, LM, MD, F>
+where
+    AT: AssetType,
+    LM: LatencyModel,
+    MD: MarketDepth,
+    F: LocalFeed<MD>,
+{
+    /// Constructs an instance of `FeedLocal`.
+    pub fn new(
+        reader: Reader<Event>,
+        depth: MD,
+        state: State<AT>,
+        order_latency: LM,
+        trade_len: usize,
+        orders_to: OrderBus,
+        orders_from: OrderBus,
+    ) -> Self {
+        Self {
+            reader,
+            data: Data::empty(),
+            row_num: 0,
+            orders: Default::default(),
+            orders_to,
+            orders_from,
+            depth,
+            state,
+            order_latency,
+            trades: Vec::with_capacity(trade_len),
+            last_feed_latency: None,
+            last_order_latency: None,
+            aux: Default::default(),
+            rolls: Vec::new(),
+            _feed: PhantomData,
+        }
+    }
+
+    fn process_recv_order_(&mut self, order: Order) -> Result<(), BacktestError> {
+        if order.status == Status::Filled {
+            self.state.apply_fill(&order);
+        }
+        // Applies the received order response to the local orders.
+        match self.orders.entry(order.order_id) {
+            Entry::Occupied(mut entry) => {
+                let local_order = entry.get_mut();
+                if order.req == Status::Rejected {
+                    if order.local_timestamp == local_order.local_timestamp {
+                        if local_order.req == Status::New {
+                            local_order.req = Status::None;
+                            local_order.status = Status::Expired;
+                        } else {
+                            local_order.req = Status::None;
+                        }
+                    }
+                } else {
+                    local_order.update(&order);
+                }
+            }
+            Entry::Vacant(entry) => {
+                if order.req != Status::Rejected {
+                    entry.insert(order);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<AT, LM, MD, F> LocalProcessor<MD, Event> for FeedLocal<AT, LM, MD, F>
+where
+    AT: AssetType,
+    LM: LatencyModel,
+    MD: MarketDepth,
+    F: LocalFeed<MD>,
+{
+    fn submit_order(
+        &mut self,
+        order_id: OrderId,
+        side: Side,
+        price: f64,
+        qty: f64,
+        order_type: OrdType,
+        time_in_force: TimeInForce,
+        current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        if order_type.is_stop() {
+            // Stop orders are not supported by the L1 and L3 exchange models.
+            return Err(BacktestError::InvalidOrderRequest);
+        }
+        if self.orders.contains_key(&order_id) {
+            return Err(BacktestError::OrderIdExist);
+        }
+
+        let price_tick = (price / self.depth.tick_size()).round() as i64;
+        let mut order = Order::new(
+            order_id,
+            price_tick,
+            self.depth.tick_size(),
+            qty,
+            side,
+            order_type,
+            time_in_force,
+        );
+        order.req = Status::New;
+        order.local_timestamp = current_timestamp;
+        self.orders.insert(order.order_id, order.clone());
+
+        let order_entry_latency =
+            self.order_latency
+                .request_entry(current_timestamp, &order, RequestType::New);
+        // Negative latency indicates that the order is rejected for technical reasons, and its
+        // value represents the latency that the local experiences when receiving the rejection
+        // notification.
+        if order_entry_latency < 0 {
+            // Rejects the order.
+            order.req = Status::Rejected;
+            let rej_recv_timestamp = current_timestamp - order_entry_latency;
+            self.orders_from.append(order, rej_recv_timestamp);
+        } else {
+            let exch_recv_timestamp = current_timestamp + order_entry_latency;
+            self.orders_to.append(order, exch_recv_timestamp);
+        }
+        Ok(())
+    }
+
+    fn cancel(&mut self, order_id: OrderId, current_timestamp: i64) -> Result<(), BacktestError> {
+        let order = self
+            .orders
+            .get_mut(&order_id)
+            .ok_or(BacktestError::OrderNotFound)?;
+
+        if order.req != Status::None {
+            return Err(BacktestError::OrderRequestInProcess);
+        }
+
+        order.req = Status::Canceled;
+        let order_entry_latency =
+            self.order_latency
+                .request_entry(current_timestamp, order, RequestType::Cancel);
+        // Negative latency indicates that the order is rejected for technical reasons, and its
+        // value represents the latency that the local experiences when receiving the rejection
+        // notification.
+        if order_entry_latency < 0 {
+            // Rejects the order.
+            let mut order_ = order.clone();
+            order_.req = Status::Rejected;
+            let rej_recv_timestamp = current_timestamp - order_entry_latency;
+            self.orders_from.append(order_, rej_recv_timestamp);
+        } else {
+            let exch_recv_timestamp = current_timestamp + order_entry_latency;
+            self.orders_to.append(order.clone(), exch_recv_timestamp);
+        }
+        Ok(())
+    }
+
+    fn clear_inactive_orders(&mut self) {
+        self.orders.retain(|_, order| {
+            order.status != Status::Expired
+                && order.status != Status::Filled
+                && order.status != Status::Canceled
+        })
+    }
+
+    fn position(&self) -> f64 {
+        self.state_values().position
+    }
+
+    fn state_values(&self) -> &StateValues {
+        self.state.values()
+    }
+
+    fn depth(&self) -> &MD {
+        &self.depth
+    }
+
+    fn orders(&self) -> &HashMap<OrderId, Order> {
+        &self.orders
+    }
+
+    fn trade(&self) -> &[Event] {
+        self.trades.as_slice()
+    }
+
+    fn clear_last_trades(&mut self) {
+        self.trades.clear();
+    }
+
+    fn feed_latency(&self) -> Option<(i64, i64)> {
+        self.last_feed_latency
+    }
+
+    fn order_latency(&self) -> Option<(i64, i64, i64)> {
+        self.last_order_latency
+    }
+
+    fn feed_gap_count(&self) -> usize {
+        0
+    }
+
+    fn rolls(&self) -> &[Roll] {
+        &self.rolls
+    }
+
+    fn aux_event(&self, kind: u64) -> Option<&Event> {
+        self.aux.get(&kind)
+    }
+}
+
+impl<AT, LM, MD, F> Processor for FeedLocal<AT, LM, MD, F>
+where
+    AT: AssetType,
+    LM: LatencyModel,
+    MD: MarketDepth,
+    F: LocalFeed<MD>,
+{
+    fn initialize_data(&mut self) -> Result<i64, BacktestError> {
+        self.data = self.reader.next()?;
+        for rn in 0..self.data.len() {
+            if self.data[rn].is(LOCAL_EVENT) {
+                self.row_num = rn;
+                let tmp = self.data[rn].local_ts;
+                return Ok(tmp);
+            }
+        }
+        Err(BacktestError::EndOfData)
+    }
+
+    fn process_data(&mut self) -> Result<(i64, i64), BacktestError> {
+        let ev = &self.data[self.row_num];
+        // Processes a depth event, or else the other kinds of event
+        if !F::apply(&mut self.depth, ev)? {
+            // Processes a trade event
+            if ev.is(F::TRADE_EVENT) {
+                if self.trades.capacity() > 0 {
+                    self.trades.push(ev.clone());
+                }
+            }
+            // Processes a contract roll
+            else if ev.is(LOCAL_ROLL_EVENT) {
+                self.rolls.push(Roll {
+                    timestamp: ev.local_ts,
+                    contract: ev.order_id as usize,
+                    adjustment: ev.px,
+                });
+            }
+            // Processes an auxiliary event
+            else if ev.is_aux() {
+                self.aux.insert(ev.ev & 0xff, ev.clone());
+            }
+        }
+
+        // Stores the current feed latency
+        self.last_feed_latency = Some((ev.exch_ts, ev.local_ts));
+
+        // Checks
+        let mut next_ts = 0;
+        for rn in (self.row_num + 1)..self.data.len() {
+            if self.data[rn].is(LOCAL_EVENT) {
+                self.row_num = rn;
+                next_ts = self.data[rn].local_ts;
+                break;
+            }
+        }
+
+        if next_ts <= 0 {
+            let next_data = self.reader.next()?;
+            let next_row = &next_data[0];
+            next_ts = next_row.local_ts;
+            let data = mem::replace(&mut self.data, next_data);
+            self.reader.release(data);
+            self.row_num = 0;
+        }
+
+        Ok((next_ts, i64::MAX))
+    }
+
+    fn process_recv_order(
+        &mut self,
+        timestamp: i64,
+        wait_resp_order_id: Option<OrderId>,
+    ) -> Result<bool, BacktestError> {
+        // Processes the order part.
+        let mut wait_resp_order_received = false;
+        while self.orders_from.len() > 0 {
+            let recv_timestamp = self.orders_from.earliest_timestamp().unwrap();
+            if timestamp == recv_timestamp {
+                let (order, _) = self.orders_from.pop_front().unwrap();
+
+                // Updates the order latency only if it has a valid exchange timestamp. When the
+                // order is rejected before it reaches the matching engine, it has no exchange
+                // timestamp. This situation occurs in crypto exchanges.
+                if order.exch_timestamp > 0 {
+                    self.last_order_latency =
+                        Some((order.local_timestamp, order.exch_timestamp, recv_timestamp));
+                }
+
+                if let Some(wait_resp_order_id) = wait_resp_order_id {
+                    if order.order_id == wait_resp_order_id {
+                        wait_resp_order_received = true;
+                    }
+                }
+
+                self.process_recv_order_(order)?;
+            } else {
+                assert!(recv_timestamp > timestamp);
+                break;
+            }
+        }
+        Ok(wait_resp_order_received)
+    }
+
+    fn earliest_recv_order_timestamp(&self) -> i64 {
+        self.orders_from.earliest_timestamp().unwrap_or(i64::MAX)
+    }
+
+    fn earliest_send_order_timestamp(&self) -> i64 {
+        self.orders_to.earliest_timestamp().unwrap_or(i64::MAX)
+    }
+}
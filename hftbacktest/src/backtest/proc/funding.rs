@@ -0,0 +1,125 @@
+/// Settles the funding payments of a perpetual contract on a fixed schedule, for data that doesn't
+/// carry [`FUNDING_EVENT`](crate::types::FUNDING_EVENT)s.
+///
+/// The funding is settled every `interval` at the timestamps `offset + k * interval`, at the
+/// constant funding rate and the mid-price, against the position held at the funding timestamp. A
+/// positive funding rate means that long positions pay short positions. The payments accrue in
+/// [`StateValues::funding`](crate::types::StateValues::funding).
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::FundingSchedule;
+///
+/// // Settles 0.01% every 8 hours, at 00:00, 08:00, and 16:00 UTC.
+/// let schedule = FundingSchedule::new(8 * 60 * 60 * 1_000_000_000, 0.0001);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FundingSchedule {
+    interval: i64,
+    offset: i64,
+    rate: f64,
+    next_ts: Option<i64>,
+}
+
+impl FundingSchedule {
+    /// Constructs an instance of `FundingSchedule` that settles the funding at the `rate` every
+    /// `interval` in nanoseconds.
+    pub fn new(interval: i64, rate: f64) -> Self {
+        assert!(interval > 0);
+        Self {
+            interval,
+            offset: 0,
+            rate,
+            next_ts: None,
+        }
+    }
+
+    /// Sets the offset of the funding timestamps from the Unix epoch. The default value is `0`.
+    pub fn offset(self, offset: i64) -> Self {
+        Self { offset, ..self }
+    }
+
+    /// Returns the funding rate.
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Returns the number of funding timestamps that have passed up to and including `timestamp`
+    /// since the last call. The first call only sets the next funding timestamp after `timestamp`,
+    /// as there is no position before the backtest begins.
+    pub fn take_due(&mut self, timestamp: i64) -> usize {
+        let (offset, interval) = (self.offset, self.interval);
+        let next_ts = *self.next_ts.get_or_insert_with(|| {
+            offset + ((timestamp - offset).div_euclid(interval) + 1) * interval
+        });
+        if timestamp < next_ts {
+            return 0;
+        }
+        let due = ((timestamp - next_ts).div_euclid(self.interval) + 1) as usize;
+        self.next_ts = Some(next_ts + due as i64 * self.interval);
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FundingSchedule;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+        },
+        types::{
+            Bot,
+            Event,
+            OrdType,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            FUNDING_EVENT,
+            SELL_EVENT,
+        },
+    };
+
+    #[test]
+    fn test_take_due() {
+        let mut schedule = FundingSchedule::new(100, 0.001).offset(10);
+        assert_eq!(schedule.take_due(5), 0);
+        assert_eq!(schedule.take_due(10), 1);
+        assert_eq!(schedule.take_due(109), 0);
+        assert_eq!(schedule.take_due(110), 1);
+        assert_eq!(schedule.take_due(420), 3);
+        assert_eq!(schedule.take_due(509), 0);
+        assert_eq!(schedule.take_due(510), 1);
+    }
+
+    #[test]
+    fn test_funding_backtest() {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 2, 102.0, 10.0),
+            // Long positions pay 0.1% at the mark price of 105.
+            Event {
+                fval: 0.001,
+                ..event(FUNDING_EVENT, 100, 105.0, 0.0)
+            },
+            event(DEPTH_EVENT | BUY_EVENT, 150, 100.0, 5.0),
+            // Long positions receive 0.2% at the mid-price of 101.
+            Event {
+                fval: -0.002,
+                ..event(FUNDING_EVENT, 200, 0.0, 0.0)
+            },
+            event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 10.0),
+        ]);
+        let mut hbt = backtest(asset_builder(data, 1.0, 1.0));
+
+        hbt.elapse(9).unwrap();
+        hbt.submit_buy_order(0, 1, 102.0, 2.0, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+        assert_eq!(hbt.position(0), 2.0);
+
+        hbt.elapse(200).unwrap();
+        let funding = hbt.state_values(0).funding;
+        assert!((funding - (2.0 * 105.0 * 0.001 - 2.0 * 101.0 * 0.002)).abs() < 1e-9);
+    }
+}
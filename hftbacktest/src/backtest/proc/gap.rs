@@ -0,0 +1,136 @@
+use crate::types::{Event, DEPTH_BBO_EVENT, DEPTH_CLEAR_EVENT, DEPTH_EVENT, DEPTH_SNAPSHOT_EVENT};
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+enum GapState {
+    #[default]
+    Normal,
+    /// A gap is detected and the market depth is stale until the next snapshot.
+    Stale,
+    /// The market depth is being rebuilt from the snapshot.
+    Recovering,
+}
+
+/// Determines how a processor handles an event after [`FeedGap::check`].
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum GapAction {
+    /// Processes the event as usual.
+    Process,
+    /// Skips the depth event since the market depth is stale.
+    Skip,
+    /// Clears the market depth and then processes the event, which is the first snapshot event
+    /// after a gap.
+    Reset,
+}
+
+/// Detects gaps in the feed data, where no event arrives for longer than the threshold, and tracks
+/// the recovery of the market depth. Once a gap is detected, depth events are skipped until the
+/// next snapshot, from which the market depth is rebuilt, instead of continuing with a stale market
+/// depth.
+#[derive(Clone, Debug, Default)]
+pub struct FeedGap {
+    threshold: Option<i64>,
+    last_ts: Option<i64>,
+    state: GapState,
+    count: usize,
+}
+
+impl FeedGap {
+    /// Constructs an instance of `FeedGap` that detects a gap if the interval between consecutive
+    /// events exceeds the `threshold`.
+    pub fn new(threshold: i64) -> Self {
+        Self {
+            threshold: Some(threshold),
+            ..Default::default()
+        }
+    }
+
+    /// Checks the event at the timestamp, which is the exchange timestamp for the exchange
+    /// processor and the local timestamp for the local processor, and returns how it should be
+    /// handled.
+    pub fn check(&mut self, ev: &Event, timestamp: i64) -> GapAction {
+        let Some(threshold) = self.threshold else {
+            return GapAction::Process;
+        };
+        if let Some(last_ts) = self.last_ts {
+            if timestamp - last_ts > threshold {
+                self.count += 1;
+                self.state = GapState::Stale;
+            }
+        }
+        self.last_ts = Some(timestamp);
+
+        let kind = ev.ev & 0xff;
+        let is_snapshot = kind == DEPTH_SNAPSHOT_EVENT;
+        let is_depth = is_snapshot
+            || kind == DEPTH_EVENT
+            || kind == DEPTH_CLEAR_EVENT
+            || kind == DEPTH_BBO_EVENT;
+        if !is_depth {
+            return GapAction::Process;
+        }
+        match (self.state, is_snapshot) {
+            (GapState::Stale, true) => {
+                self.state = GapState::Recovering;
+                GapAction::Reset
+            }
+            (GapState::Stale, false) => GapAction::Skip,
+            (GapState::Recovering, false) => {
+                self.state = GapState::Normal;
+                GapAction::Process
+            }
+            _ => GapAction::Process,
+        }
+    }
+
+    /// Returns the number of gaps detected so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FeedGap, GapAction};
+    use crate::types::{
+        Event,
+        LOCAL_BID_DEPTH_EVENT,
+        LOCAL_BID_DEPTH_SNAPSHOT_EVENT,
+        LOCAL_BUY_TRADE_EVENT,
+    };
+
+    fn event(ev: u64) -> Event {
+        Event {
+            ev,
+            exch_ts: 0,
+            local_ts: 0,
+            px: 0.0,
+            qty: 0.0,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_feed_gap() {
+        let mut gap = FeedGap::new(10);
+        let depth = event(LOCAL_BID_DEPTH_EVENT);
+        let snapshot = event(LOCAL_BID_DEPTH_SNAPSHOT_EVENT);
+        let trade = event(LOCAL_BUY_TRADE_EVENT);
+
+        assert_eq!(gap.check(&depth, 0), GapAction::Process);
+        assert_eq!(gap.check(&depth, 10), GapAction::Process);
+        assert_eq!(gap.count(), 0);
+
+        // The depth is stale until the next snapshot, but trades are still processed.
+        assert_eq!(gap.check(&depth, 21), GapAction::Skip);
+        assert_eq!(gap.count(), 1);
+        assert_eq!(gap.check(&trade, 22), GapAction::Process);
+        assert_eq!(gap.check(&depth, 23), GapAction::Skip);
+
+        assert_eq!(gap.check(&snapshot, 24), GapAction::Reset);
+        assert_eq!(gap.check(&snapshot, 24), GapAction::Process);
+        assert_eq!(gap.check(&depth, 25), GapAction::Process);
+        assert_eq!(gap.count(), 1);
+    }
+}
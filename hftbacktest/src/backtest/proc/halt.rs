@@ -0,0 +1,175 @@
+/// Simulates trading halts at the exchange, such as volatility halts and regulatory halts, during
+/// which the book is frozen but not tradable.
+///
+/// A halt begins and ends by [`HALT_EVENT`](crate::types::HALT_EVENT)s in the data or by the
+/// configured halt periods. During a halt, new orders and modifications are rejected, while
+/// cancels are still accepted, and matching pauses: resting orders are neither filled by the
+/// market nor triggered. Optionally, the resting orders are canceled when the halt begins, as some
+/// venues do.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::TradingHalt;
+///
+/// // A scheduled halt between 12:00 and 12:05 UTC on 2024-01-02.
+/// let halt = TradingHalt::new()
+///     .period(1_704_196_800_000_000_000, 1_704_197_100_000_000_000)
+///     .cancel_resting(true);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TradingHalt {
+    periods: Vec<(i64, i64)>,
+    cancel_resting: bool,
+    halted_by_event: bool,
+    halted: bool,
+    halts: usize,
+}
+
+impl TradingHalt {
+    /// Constructs an instance of `TradingHalt` without any halt periods.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a halt period from `start`, inclusive, to `end`, exclusive.
+    pub fn period(mut self, start: i64, end: i64) -> Self {
+        assert!(start < end);
+        self.periods.push((start, end));
+        self
+    }
+
+    /// Sets whether the resting orders are canceled when a halt begins. The default value is
+    /// `false`.
+    pub fn cancel_resting(self, cancel_resting: bool) -> Self {
+        Self {
+            cancel_resting,
+            ..self
+        }
+    }
+
+    /// Returns `true` if the resting orders are to be canceled when a halt begins.
+    pub fn cancels_resting(&self) -> bool {
+        self.cancel_resting
+    }
+
+    /// Sets whether trading is halted by a [`HALT_EVENT`](crate::types::HALT_EVENT).
+    pub fn set_halted(&mut self, halted: bool) {
+        self.halted_by_event = halted;
+    }
+
+    /// Updates the halt state at the timestamp, and returns `true` if a halt has just begun.
+    pub fn update(&mut self, timestamp: i64) -> bool {
+        let halted = self.halted_by_event
+            || self
+                .periods
+                .iter()
+                .any(|&(start, end)| start <= timestamp && timestamp < end);
+        let begun = halted && !self.halted;
+        if begun {
+            self.halts += 1;
+        }
+        self.halted = halted;
+        begun
+    }
+
+    /// Returns `true` if trading is halted.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Returns the number of halts so far.
+    pub fn halt_count(&self) -> usize {
+        self.halts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TradingHalt;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{
+            Bot,
+            Event,
+            OrdType,
+            Status,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            HALT_EVENT,
+            SELL_EVENT,
+            TRADE_EVENT,
+        },
+    };
+
+    #[test]
+    fn test_update() {
+        let mut halt = TradingHalt::new().period(100, 200);
+        assert!(!halt.update(99));
+        assert!(halt.update(100));
+        assert!(!halt.update(150));
+        assert!(halt.is_halted());
+        assert!(!halt.update(200));
+        assert!(!halt.is_halted());
+
+        halt.set_halted(true);
+        assert!(halt.update(300));
+        halt.set_halted(false);
+        assert!(!halt.update(400));
+        assert!(!halt.is_halted());
+        assert_eq!(halt.halt_count(), 2);
+    }
+
+    #[test]
+    fn test_halt_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            for cancel_resting in [false, true] {
+                let data = Data::from_slice(&[
+                    event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                    event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                    Event {
+                        ival: 1,
+                        ..event(HALT_EVENT, 50, 0.0, 0.0)
+                    },
+                    event(TRADE_EVENT | SELL_EVENT, 100, 98.0, 1.0),
+                    event(HALT_EVENT, 200, 0.0, 0.0),
+                    event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 10.0),
+                ]);
+                let mut hbt = backtest(
+                    asset_builder(data, 1.0, 1.0)
+                        .exchange(exch_kind)
+                        .trading_halt(TradingHalt::new().cancel_resting(cancel_resting)),
+                );
+
+                hbt.elapse(9).unwrap();
+                hbt.submit_buy_order(0, 1, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                    .unwrap();
+
+                hbt.elapse(100).unwrap();
+                hbt.submit_buy_order(0, 2, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                    .unwrap();
+                assert_eq!(hbt.orders(0)[&2].status, Status::Expired);
+                // The trade through the resting order's price doesn't fill it during the halt.
+                assert_eq!(hbt.position(0), 0.0);
+                if cancel_resting {
+                    assert_eq!(hbt.orders(0)[&1].status, Status::Canceled);
+                } else {
+                    assert_eq!(hbt.orders(0)[&1].status, Status::New);
+                }
+
+                // Trading resumes.
+                hbt.elapse(100).unwrap();
+                hbt.submit_buy_order(0, 3, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                    .unwrap();
+                assert_eq!(hbt.orders(0)[&3].status, Status::New);
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{
+    backtest::{
+        proc::feed_local::{FeedLocal, LocalFeed},
+        BacktestError,
+    },
+    depth::{L1MarketDepth, MarketDepth},
+    types::{
+        Event,
+        LOCAL_ASK_DEPTH_BBO_EVENT,
+        LOCAL_BAR_EVENT,
+        LOCAL_BID_DEPTH_BBO_EVENT,
+        LOCAL_TRADE_EVENT,
+        SELL_EVENT,
+    },
+};
+
+/// The Level1 local model, which maintains the best bid and offer from
+/// [`DEPTH_BBO_EVENT`](crate::types::DEPTH_BBO_EVENT)s and the trades only. Other depth events in
+/// the data are ignored.
+///
+/// With OHLCV bar data, [`BAR_EVENT`](crate::types::BAR_EVENT)s, the best bid is the close price of
+/// the latest bar with volume, and the best ask is one tick above it, as in
+/// [`BarExchange`](crate::backtest::proc::BarExchange).
+pub type L1Local<AT, LM, MD> = FeedLocal<AT, LM, MD, L1Feed>;
+
+/// The Level1 feed data level of [`L1Local`].
+pub struct L1Feed;
+
+impl<MD> LocalFeed<MD> for L1Feed
+where
+    MD: MarketDepth + L1MarketDepth,
+{
+    const TRADE_EVENT: u64 = LOCAL_TRADE_EVENT;
+
+    fn apply(depth: &mut MD, ev: &Event) -> Result<bool, BacktestError> {
+        // Processes a best bid and offer event
+        if ev.is(LOCAL_BID_DEPTH_BBO_EVENT) {
+            depth.update_best_bid(ev.px, ev.qty, ev.local_ts);
+        } else if ev.is(LOCAL_ASK_DEPTH_BBO_EVENT) {
+            depth.update_best_ask(ev.px, ev.qty, ev.local_ts);
+        }
+        // Processes the closing half of a bar
+        else if ev.is(LOCAL_BAR_EVENT | SELL_EVENT) {
+            if (ev.qty / depth.lot_size()).round() as i64 > 0 {
+                let tick_size = depth.tick_size();
+                let close_tick = (ev.fval / tick_size).round() as i64;
+                depth.update_best_bid(close_tick as f64 * tick_size, ev.qty, ev.local_ts);
+                depth.update_best_ask((close_tick + 1) as f64 * tick_size, ev.qty, ev.local_ts);
+            }
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
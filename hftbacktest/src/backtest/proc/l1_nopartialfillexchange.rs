@@ -0,0 +1,452 @@
+use std::{collections::HashMap, mem};
+
+use crate::{
+    backtest::{
+        assettype::AssetType,
+        models::LatencyModel,
+        order::OrderBus,
+        proc::proc::Processor,
+        reader::{Data, Reader},
+        state::State,
+        BacktestError,
+    },
+    depth::{L1MarketDepth, MarketDepth, INVALID_MAX, INVALID_MIN},
+    types::{
+        Event,
+        OrdType,
+        Order,
+        OrderId,
+        Side,
+        Status,
+        TimeInForce,
+        EXCH_ASK_DEPTH_BBO_EVENT,
+        EXCH_BID_DEPTH_BBO_EVENT,
+        EXCH_BUY_TRADE_EVENT,
+        EXCH_EVENT,
+        EXCH_SELL_TRADE_EVENT,
+    },
+};
+
+/// The Level1 exchange model without partial fills, for feed data consisting of the best bid and
+/// offer, [`DEPTH_BBO_EVENT`](crate::types::DEPTH_BBO_EVENT)s, and the trades only.
+///
+/// Since the quantity ahead of the order in the queue cannot be tracked without the market depth,
+/// the fill model is conservative: an order resting in the book is filled only when the market
+/// trades through its price, and never by a trade at its price.
+///
+/// Support order types: [`OrdType::Limit`], [`OrdType::Market`]
+/// Support time-in-force: [`TimeInForce::GTC`], [`TimeInForce::GTX`], [`TimeInForce::FOK`],
+/// [`TimeInForce::IOC`]
+///
+/// **Conditions for Full Execution**
+///
+/// Buy order in the order book
+///
+/// - A sell trade occurs at a price lower than your order price
+/// - The best ask price falls to or below your order price
+///
+/// Sell order in the order book
+///
+/// - A buy trade occurs at a price higher than your order price
+/// - The best bid price rises to or above your order price
+///
+/// **Liquidity-Taking Order**
+///
+/// Regardless of the quantity at the best, liquidity-taking orders will be fully executed at the
+/// best. Be aware that this may cause unrealistic fill simulations if you attempt to execute a
+/// large quantity. An order that would take liquidity when there is no opposite best, or an
+/// [`TimeInForce::IOC`] or [`TimeInForce::FOK`] order that wouldn't, is expired.
+pub struct L1NoPartialFillExchange<AT, LM, MD>
+where
+    AT: AssetType,
+    LM: LatencyModel,
+    MD: MarketDepth + L1MarketDepth,
+{
+    reader: Reader<Event>,
+    data: Data<Event>,
+    row_num: usize,
+    orders_to: OrderBus,
+    orders_from: OrderBus,
+    orders: HashMap<OrderId, Order>,
+
+    depth: MD,
+    state: State<AT>,
+    order_latency: LM,
+}
+
+impl<AT, LM, MD> L1NoPartialFillExchange<AT, LM, MD>
+where
+    AT: AssetType,
+    LM: LatencyModel,
+    MD: MarketDepth + L1MarketDepth,
+{
+    /// Constructs an instance of `L1NoPartialFillExchange`.
+    pub fn new(
+        reader: Reader<Event>,
+        depth: MD,
+        state: State<AT>,
+        order_latency: LM,
+        orders_to: OrderBus,
+        orders_from: OrderBus,
+    ) -> Self {
+        Self {
+            reader,
+            data: Data::empty(),
+            row_num: 0,
+            orders_to,
+            orders_from,
+            orders: Default::default(),
+            depth,
+            state,
+            order_latency,
+        }
+    }
+
+    fn process_recv_order_(
+        &mut self,
+        mut order: Order,
+        recv_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        // Processes a new order.
+        if order.req == Status::New {
+            order.req = Status::None;
+            self.ack_new(order, recv_timestamp)?;
+        }
+        // Processes a cancel order.
+        else if order.req == Status::Canceled {
+            order.req = Status::None;
+            self.ack_cancel(order, recv_timestamp)?;
+        } else {
+            return Err(BacktestError::InvalidOrderRequest);
+        }
+        Ok(())
+    }
+
+    fn send_response(&mut self, mut order: Order, timestamp: i64) {
+        order.exch_timestamp = timestamp;
+        let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+        self.orders_to.append(order, local_recv_timestamp);
+    }
+
+    fn fill(
+        &mut self,
+        order: &mut Order,
+        timestamp: i64,
+        maker: bool,
+        exec_price_tick: i64,
+    ) -> Result<(), BacktestError> {
+        if order.status == Status::Expired
+            || order.status == Status::Canceled
+            || order.status == Status::Filled
+        {
+            return Err(BacktestError::InvalidOrderStatus);
+        }
+
+        order.maker = maker;
+        if maker {
+            order.exec_price_tick = order.price_tick;
+        } else {
+            order.exec_price_tick = exec_price_tick;
+        }
+
+        order.exec_qty = order.leaves_qty;
+        order.leaves_qty = 0.0;
+        order.status = Status::Filled;
+        order.exch_timestamp = timestamp;
+
+        self.state.apply_fill(order);
+        self.send_response(order.clone(), timestamp);
+        Ok(())
+    }
+
+    /// Fills the resting orders on the `side` whose price ticks satisfy `is_crossed`, in the order
+    /// of their order IDs.
+    fn fill_crossed_orders<F>(
+        &mut self,
+        side: Side,
+        is_crossed: F,
+        timestamp: i64,
+    ) -> Result<(), BacktestError>
+    where
+        F: Fn(i64) -> bool,
+    {
+        let mut order_ids: Vec<OrderId> = self
+            .orders
+            .values()
+            .filter(|order| order.side == side && is_crossed(order.price_tick))
+            .map(|order| order.order_id)
+            .collect();
+        order_ids.sort_unstable();
+        for order_id in order_ids {
+            let mut order = self.orders.remove(&order_id).unwrap();
+            let price_tick = order.price_tick;
+            self.fill(&mut order, timestamp, true, price_tick)?;
+        }
+        Ok(())
+    }
+
+    fn ack_new(&mut self, mut order: Order, timestamp: i64) -> Result<(), BacktestError> {
+        if self.orders.contains_key(&order.order_id) {
+            return Err(BacktestError::OrderIdExist);
+        }
+
+        let is_market = order.order_type == OrdType::Market;
+        let (opposite_best_tick, crosses) = match order.side {
+            Side::Buy => {
+                let best_ask_tick = self.depth.best_ask_tick();
+                (
+                    best_ask_tick,
+                    best_ask_tick != INVALID_MAX
+                        && (is_market || order.price_tick >= best_ask_tick),
+                )
+            }
+            Side::Sell => {
+                let best_bid_tick = self.depth.best_bid_tick();
+                (
+                    best_bid_tick,
+                    best_bid_tick != INVALID_MIN
+                        && (is_market || order.price_tick <= best_bid_tick),
+                )
+            }
+            Side::None | Side::Unsupported => return Err(BacktestError::InvalidOrderRequest),
+        };
+
+        if crosses {
+            if order.time_in_force == TimeInForce::GTX {
+                order.status = Status::Expired;
+                self.send_response(order, timestamp);
+                Ok(())
+            } else {
+                // Takes the market.
+                self.fill(&mut order, timestamp, false, opposite_best_tick)
+            }
+        } else if is_market
+            || (order.time_in_force != TimeInForce::GTC && order.time_in_force != TimeInForce::GTX)
+        {
+            order.status = Status::Expired;
+            self.send_response(order, timestamp);
+            Ok(())
+        } else {
+            order.status = Status::New;
+            order.exch_timestamp = timestamp;
+            self.orders.insert(order.order_id, order.clone());
+            self.send_response(order, timestamp);
+            Ok(())
+        }
+    }
+
+    fn ack_cancel(&mut self, mut order: Order, timestamp: i64) -> Result<(), BacktestError> {
+        match self.orders.remove(&order.order_id) {
+            Some(mut exch_order) => {
+                exch_order.status = Status::Canceled;
+                self.send_response(exch_order, timestamp);
+            }
+            None => {
+                order.req = Status::Rejected;
+                self.send_response(order, timestamp);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<AT, LM, MD> Processor for L1NoPartialFillExchange<AT, LM, MD>
+where
+    AT: AssetType,
+    LM: LatencyModel,
+    MD: MarketDepth + L1MarketDepth,
+{
+    fn initialize_data(&mut self) -> Result<i64, BacktestError> {
+        self.data = self.reader.next()?;
+        for rn in 0..self.data.len() {
+            if self.data[rn].is(EXCH_EVENT) {
+                self.row_num = rn;
+                return Ok(self.data[rn].exch_ts);
+            }
+        }
+        Err(BacktestError::EndOfData)
+    }
+
+    fn process_data(&mut self) -> Result<(i64, i64), BacktestError> {
+        let row_num = self.row_num;
+        self.order_latency.observe(&self.data[row_num]);
+        let timestamp = self.data[row_num].exch_ts;
+        if self.data[row_num].is(EXCH_BID_DEPTH_BBO_EVENT) {
+            let (_, prev_best_bid_tick, best_bid_tick, _, _, _) = self.depth.update_best_bid(
+                self.data[row_num].px,
+                self.data[row_num].qty,
+                timestamp,
+            );
+            // The sell orders at or below the new best bid would have been executed against it.
+            if best_bid_tick != INVALID_MIN && best_bid_tick > prev_best_bid_tick {
+                self.fill_crossed_orders(
+                    Side::Sell,
+                    |price_tick| price_tick <= best_bid_tick,
+                    timestamp,
+                )?;
+            }
+        } else if self.data[row_num].is(EXCH_ASK_DEPTH_BBO_EVENT) {
+            let (_, prev_best_ask_tick, best_ask_tick, _, _, _) = self.depth.update_best_ask(
+                self.data[row_num].px,
+                self.data[row_num].qty,
+                timestamp,
+            );
+            // The buy orders at or above the new best ask would have been executed against it.
+            if best_ask_tick != INVALID_MAX && best_ask_tick < prev_best_ask_tick {
+                self.fill_crossed_orders(
+                    Side::Buy,
+                    |price_tick| price_tick >= best_ask_tick,
+                    timestamp,
+                )?;
+            }
+        } else if self.data[row_num].is(EXCH_BUY_TRADE_EVENT) {
+            let trade_price_tick = (self.data[row_num].px / self.depth.tick_size()).round() as i64;
+            self.fill_crossed_orders(
+                Side::Sell,
+                |price_tick| price_tick < trade_price_tick,
+                timestamp,
+            )?;
+        } else if self.data[row_num].is(EXCH_SELL_TRADE_EVENT) {
+            let trade_price_tick = (self.data[row_num].px / self.depth.tick_size()).round() as i64;
+            self.fill_crossed_orders(
+                Side::Buy,
+                |price_tick| price_tick > trade_price_tick,
+                timestamp,
+            )?;
+        }
+
+        // Checks
+        let mut next_ts = 0;
+        for rn in (self.row_num + 1)..self.data.len() {
+            if self.data[rn].is(EXCH_EVENT) {
+                self.row_num = rn;
+                next_ts = self.data[rn].exch_ts;
+                break;
+            }
+        }
+
+        if next_ts <= 0 {
+            let next_data = self.reader.next()?;
+            let next_row = &next_data[0];
+            next_ts = next_row.exch_ts;
+            let data = mem::replace(&mut self.data, next_data);
+            self.reader.release(data);
+            self.row_num = 0;
+        }
+        Ok((next_ts, i64::MAX))
+    }
+
+    fn process_recv_order(
+        &mut self,
+        timestamp: i64,
+        _wait_resp_order_id: Option<OrderId>,
+    ) -> Result<bool, BacktestError> {
+        // Processes the order part.
+        while self.orders_from.len() > 0 {
+            let recv_timestamp = self.orders_from.earliest_timestamp().unwrap();
+            if timestamp == recv_timestamp {
+                let (order, _) = self.orders_from.pop_front().unwrap();
+                self.process_recv_order_(order, recv_timestamp)?;
+            } else {
+                assert!(recv_timestamp > timestamp);
+                break;
+            }
+        }
+        Ok(false)
+    }
+
+    fn earliest_recv_order_timestamp(&self) -> i64 {
+        self.orders_from.earliest_timestamp().unwrap_or(i64::MAX)
+    }
+
+    fn earliest_send_order_timestamp(&self) -> i64 {
+        self.orders_to.earliest_timestamp().unwrap_or(i64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backtest::{
+            assettype::LinearAsset,
+            models::ConstantLatency,
+            proc::test_utils::event,
+            reader::Data,
+            Backtest,
+            DataSource,
+            L1AssetBuilder,
+        },
+        depth::{BBOMarketDepth, MarketDepth, INVALID_MAX},
+        types::{
+            Bot,
+            OrdType,
+            Status,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_BBO_EVENT,
+            SELL_EVENT,
+            TRADE_EVENT,
+        },
+    };
+
+    #[test]
+    fn test_l1_backtest() {
+        let data = Data::from_slice(&[
+            event(DEPTH_BBO_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_BBO_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+            event(TRADE_EVENT | SELL_EVENT, 50, 100.0, 1.0),
+            event(TRADE_EVENT | SELL_EVENT, 100, 99.5, 1.0),
+            event(DEPTH_BBO_EVENT | BUY_EVENT, 150, 102.0, 10.0),
+            event(DEPTH_BBO_EVENT | SELL_EVENT, 300, 103.0, 10.0),
+        ]);
+        let mut hbt = Backtest::builder()
+            .add(
+                L1AssetBuilder::new()
+                    .data(vec![DataSource::Data(data)])
+                    .latency_model(ConstantLatency::new(1, 1))
+                    .asset_type(LinearAsset::new(1.0))
+                    .depth(|| BBOMarketDepth::new(0.5, 1.0))
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        hbt.elapse(9).unwrap();
+        assert_eq!(hbt.depth(0).best_bid_tick(), 200);
+        assert_eq!(hbt.depth(0).best_ask_tick(), 202);
+
+        hbt.submit_buy_order(0, 1, 100.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+        hbt.submit_sell_order(0, 2, 102.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::New);
+        assert_eq!(hbt.orders(0)[&2].status, Status::New);
+
+        // The IOC order that doesn't take liquidity is expired.
+        hbt.submit_buy_order(0, 3, 100.5, 1.0, TimeInForce::IOC, OrdType::Limit, true)
+            .unwrap();
+        assert_eq!(hbt.orders(0)[&3].status, Status::Expired);
+
+        // A trade at the order price doesn't fill the order, as its queue position is unknown.
+        hbt.elapse(40).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::New);
+
+        // A trade through the order price does.
+        hbt.elapse(50).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::Filled);
+        assert_eq!(hbt.orders(0)[&1].exec_price_tick, 200);
+        assert_eq!(hbt.position(0), 1.0);
+
+        // The best bid rising to the sell order price fills it.
+        hbt.elapse(50).unwrap();
+        assert_eq!(hbt.orders(0)[&2].status, Status::Filled);
+        assert_eq!(hbt.position(0), 0.0);
+
+        // The best bid crossing the best ask makes the ask stale, so there is nothing to take.
+        assert_eq!(hbt.depth(0).best_ask_tick(), INVALID_MAX);
+        hbt.submit_buy_order(0, 4, 0.0, 1.0, TimeInForce::GTC, OrdType::Market, true)
+            .unwrap();
+        assert_eq!(hbt.orders(0)[&4].status, Status::Expired);
+    }
+}
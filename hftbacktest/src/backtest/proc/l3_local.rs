@@ -1,361 +1,57 @@
-use std::{
-    collections::{hash_map::Entry, HashMap},
-    mem,
-};
-
 use crate::{
     backtest::{
-        assettype::AssetType,
-        models::LatencyModel,
-        order::OrderBus,
-        proc::proc::{LocalProcessor, Processor},
-        reader::reader::{Data, Reader},
-        state::State,
+        proc::feed_local::{FeedLocal, LocalFeed},
         BacktestError,
     },
     depth::L3MarketDepth,
     types::{
         Event,
-        OrdType,
-        Order,
-        OrderId,
         Side,
-        StateValues,
-        Status,
-        TimeInForce,
         LOCAL_ASK_ADD_ORDER_EVENT,
         LOCAL_ASK_DEPTH_CLEAR_EVENT,
         LOCAL_BID_ADD_ORDER_EVENT,
         LOCAL_BID_DEPTH_CLEAR_EVENT,
         LOCAL_CANCEL_ORDER_EVENT,
         LOCAL_DEPTH_CLEAR_EVENT,
-        LOCAL_EVENT,
         LOCAL_FILL_EVENT,
         LOCAL_MODIFY_ORDER_EVENT,
     },
 };
 
 /// The Level3 Market-By-Order local model.
-pub struct L3Local<AT, LM, MD>
-where
-    AT: AssetType,
-    LM: LatencyModel,
-    MD: L3MarketDepth,
-{
-    reader: Reader<Event>,
-    data: Data<Event>,
-    row_num: usize,
-    orders: HashMap<OrderId, Order>,
-    orders_to: OrderBus,
-    orders_from: OrderBus,
-    depth: MD,
-    state: State<AT>,
-    order_latency: LM,
-    trades: Vec<Event>,
-    last_feed_latency: Option<(i64, i64)>,
-    last_order_latency: Option<(i64, i64, i64)>,
-}
+pub type L3Local<AT, LM, MD> = FeedLocal<AT, LM, MD, L3Feed>;
 
-impl<AT, LM, MD> L3Local<AT, LM, MD>
-where
-    AT: AssetType,
-    LM: LatencyModel,
-    MD: L3MarketDepth,
-{
-    /// Constructs an instance of `L3Local`.
-    pub fn new(
-        reader: Reader<Event>,
-        depth: MD,
-        state: State<AT>,
-        order_latency: LM,
-        trade_len: usize,
-        orders_to: OrderBus,
-        orders_from: OrderBus,
-    ) -> Self {
-        Self {
-            reader,
-            data: Data::empty(),
-            row_num: 0,
-            orders: Default::default(),
-            orders_to,
-            orders_from,
-            depth,
-            state,
-            order_latency,
-            trades: Vec::with_capacity(trade_len),
-            last_feed_latency: None,
-            last_order_latency: None,
-        }
-    }
+/// The Level3 Market-By-Order feed data level of [`L3Local`].
+pub struct L3Feed;
 
-    fn process_recv_order_(&mut self, order: Order) -> Result<(), BacktestError> {
-        if order.status == Status::Filled {
-            self.state.apply_fill(&order);
-        }
-        // Applies the received order response to the local orders.
-        match self.orders.entry(order.order_id) {
-            Entry::Occupied(mut entry) => {
-                let local_order = entry.get_mut();
-                if order.req == Status::Rejected {
-                    if order.local_timestamp == local_order.local_timestamp {
-                        if local_order.req == Status::New {
-                            local_order.req = Status::None;
-                            local_order.status = Status::Expired;
-                        } else {
-                            local_order.req = Status::None;
-                        }
-                    }
-                } else {
-                    local_order.update(&order);
-                }
-            }
-            Entry::Vacant(entry) => {
-                if order.req != Status::Rejected {
-                    entry.insert(order);
-                }
-            }
-        }
-        Ok(())
-    }
-}
-
-impl<AT, LM, MD> LocalProcessor<MD, Event> for L3Local<AT, LM, MD>
+impl<MD> LocalFeed<MD> for L3Feed
 where
-    AT: AssetType,
-    LM: LatencyModel,
     MD: L3MarketDepth,
     BacktestError: From<<MD as L3MarketDepth>::Error>,
 {
-    fn submit_order(
-        &mut self,
-        order_id: OrderId,
-        side: Side,
-        price: f64,
-        qty: f64,
-        order_type: OrdType,
-        time_in_force: TimeInForce,
-        current_timestamp: i64,
-    ) -> Result<(), BacktestError> {
-        if self.orders.contains_key(&order_id) {
-            return Err(BacktestError::OrderIdExist);
-        }
-
-        let price_tick = (price / self.depth.tick_size()).round() as i64;
-        let mut order = Order::new(
-            order_id,
-            price_tick,
-            self.depth.tick_size(),
-            qty,
-            side,
-            order_type,
-            time_in_force,
-        );
-        order.req = Status::New;
-        order.local_timestamp = current_timestamp;
-        self.orders.insert(order.order_id, order.clone());
-
-        let order_entry_latency = self.order_latency.entry(current_timestamp, &order);
-        // Negative latency indicates that the order is rejected for technical reasons, and its
-        // value represents the latency that the local experiences when receiving the rejection
-        // notification.
-        if order_entry_latency < 0 {
-            // Rejects the order.
-            order.req = Status::Rejected;
-            let rej_recv_timestamp = current_timestamp - order_entry_latency;
-            self.orders_from.append(order, rej_recv_timestamp);
-        } else {
-            let exch_recv_timestamp = current_timestamp + order_entry_latency;
-            self.orders_to.append(order, exch_recv_timestamp);
-        }
-        Ok(())
-    }
-
-    fn cancel(&mut self, order_id: OrderId, current_timestamp: i64) -> Result<(), BacktestError> {
-        let order = self
-            .orders
-            .get_mut(&order_id)
-            .ok_or(BacktestError::OrderNotFound)?;
-
-        if order.req != Status::None {
-            return Err(BacktestError::OrderRequestInProcess);
-        }
-
-        order.req = Status::Canceled;
-        let order_entry_latency = self.order_latency.entry(current_timestamp, order);
-        // Negative latency indicates that the order is rejected for technical reasons, and its
-        // value represents the latency that the local experiences when receiving the rejection
-        // notification.
-        if order_entry_latency < 0 {
-            // Rejects the order.
-            let mut order_ = order.clone();
-            order_.req = Status::Rejected;
-            let rej_recv_timestamp = current_timestamp - order_entry_latency;
-            self.orders_from.append(order_, rej_recv_timestamp);
-        } else {
-            let exch_recv_timestamp = current_timestamp + order_entry_latency;
-            self.orders_to.append(order.clone(), exch_recv_timestamp);
-        }
-        Ok(())
-    }
-
-    fn clear_inactive_orders(&mut self) {
-        self.orders.retain(|_, order| {
-            order.status != Status::Expired
-                && order.status != Status::Filled
-                && order.status != Status::Canceled
-        })
-    }
-
-    fn position(&self) -> f64 {
-        self.state_values().position
-    }
-
-    fn state_values(&self) -> &StateValues {
-        self.state.values()
-    }
-
-    fn depth(&self) -> &MD {
-        &self.depth
-    }
+    // todo: based on Databento's data, CME sends a separate cancel message for filled orders, so
+    //       the fill event doesn't need to remove the order. However, it needs to be checked if
+    //       the same applies to other exchanges.
+    const TRADE_EVENT: u64 = LOCAL_FILL_EVENT;
 
-    fn orders(&self) -> &HashMap<OrderId, Order> {
-        &self.orders
-    }
-
-    fn trade(&self) -> &[Event] {
-        self.trades.as_slice()
-    }
-
-    fn clear_last_trades(&mut self) {
-        self.trades.clear();
-    }
-
-    fn feed_latency(&self) -> Option<(i64, i64)> {
-        self.last_feed_latency
-    }
-
-    fn order_latency(&self) -> Option<(i64, i64, i64)> {
-        self.last_order_latency
-    }
-}
-
-impl<AT, LM, MD> Processor for L3Local<AT, LM, MD>
-where
-    AT: AssetType,
-    LM: LatencyModel,
-    MD: L3MarketDepth,
-    BacktestError: From<<MD as L3MarketDepth>::Error>,
-{
-    fn initialize_data(&mut self) -> Result<i64, BacktestError> {
-        self.data = self.reader.next()?;
-        for rn in 0..self.data.len() {
-            if self.data[rn].is(LOCAL_EVENT) {
-                self.row_num = rn;
-                let tmp = self.data[rn].local_ts;
-                return Ok(tmp);
-            }
-        }
-        Err(BacktestError::EndOfData)
-    }
-
-    fn process_data(&mut self) -> Result<(i64, i64), BacktestError> {
-        let ev = &self.data[self.row_num];
-        // Processes a depth event
+    fn apply(depth: &mut MD, ev: &Event) -> Result<bool, BacktestError> {
         if ev.is(LOCAL_BID_DEPTH_CLEAR_EVENT) {
-            self.depth.clear_depth(Side::Buy);
+            depth.clear_depth(Side::Buy);
         } else if ev.is(LOCAL_ASK_DEPTH_CLEAR_EVENT) {
-            self.depth.clear_depth(Side::Sell);
+            depth.clear_depth(Side::Sell);
         } else if ev.is(LOCAL_DEPTH_CLEAR_EVENT) {
-            self.depth.clear_depth(Side::None);
+            depth.clear_depth(Side::None);
         } else if ev.is(LOCAL_BID_ADD_ORDER_EVENT) {
-            self.depth
-                .add_buy_order(ev.order_id, ev.px, ev.qty, ev.local_ts)?;
+            depth.add_buy_order(ev.order_id, ev.px, ev.qty, ev.local_ts)?;
         } else if ev.is(LOCAL_ASK_ADD_ORDER_EVENT) {
-            self.depth
-                .add_sell_order(ev.order_id, ev.px, ev.qty, ev.local_ts)?;
+            depth.add_sell_order(ev.order_id, ev.px, ev.qty, ev.local_ts)?;
         } else if ev.is(LOCAL_MODIFY_ORDER_EVENT) {
-            self.depth
-                .modify_order(ev.order_id, ev.px, ev.qty, ev.local_ts)?;
+            depth.modify_order(ev.order_id, ev.px, ev.qty, ev.local_ts)?;
         } else if ev.is(LOCAL_CANCEL_ORDER_EVENT) {
-            self.depth.delete_order(ev.order_id, ev.local_ts)?;
-        } else if ev.is(LOCAL_FILL_EVENT) {
-            // todo: based on Databento's data, CME sends a separate cancel message for filled
-            //       orders, so the fill event doesn't need to remove the order. However, it needs
-            //       to be checked if the same applies to other exchanges.
-            // self.depth.delete_order(ev.order_id, ev.local_ts)?;
-        }
-        // Processes a trade event
-        else if ev.is(LOCAL_FILL_EVENT) {
-            if self.trades.capacity() > 0 {
-                self.trades.push(ev.clone());
-            }
-        }
-
-        // Stores the current feed latency
-        self.last_feed_latency = Some((ev.exch_ts, ev.local_ts));
-
-        // Checks
-        let mut next_ts = 0;
-        for rn in (self.row_num + 1)..self.data.len() {
-            if self.data[rn].is(LOCAL_EVENT) {
-                self.row_num = rn;
-                next_ts = self.data[rn].local_ts;
-                break;
-            }
-        }
-
-        if next_ts <= 0 {
-            let next_data = self.reader.next()?;
-            let next_row = &next_data[0];
-            next_ts = next_row.local_ts;
-            let data = mem::replace(&mut self.data, next_data);
-            self.reader.release(data);
-            self.row_num = 0;
-        }
-
-        Ok((next_ts, i64::MAX))
-    }
-
-    fn process_recv_order(
-        &mut self,
-        timestamp: i64,
-        wait_resp_order_id: Option<OrderId>,
-    ) -> Result<bool, BacktestError> {
-        // Processes the order part.
-        let mut wait_resp_order_received = false;
-        while self.orders_from.len() > 0 {
-            let recv_timestamp = self.orders_from.earliest_timestamp().unwrap();
-            if timestamp == recv_timestamp {
-                let (order, _) = self.orders_from.pop_front().unwrap();
-
-                // Updates the order latency only if it has a valid exchange timestamp. When the
-                // order is rejected before it reaches the matching engine, it has no exchange
-                // timestamp. This situation occurs in crypto exchanges.
-                if order.exch_timestamp > 0 {
-                    self.last_order_latency =
-                        Some((order.local_timestamp, order.exch_timestamp, recv_timestamp));
-                }
-
-                if let Some(wait_resp_order_id) = wait_resp_order_id {
-                    if order.order_id == wait_resp_order_id {
-                        wait_resp_order_received = true;
-                    }
-                }
-
-                self.process_recv_order_(order)?;
-            } else {
-                assert!(recv_timestamp > timestamp);
-                break;
-            }
+            depth.delete_order(ev.order_id, ev.local_ts)?;
+        } else {
+            return Ok(false);
         }
-        Ok(wait_resp_order_received)
-    }
-
-    fn earliest_recv_order_timestamp(&self) -> i64 {
-        self.orders_from.earliest_timestamp().unwrap_or(i64::MAX)
-    }
-
-    fn earliest_send_order_timestamp(&self) -> i64 {
-        self.orders_to.earliest_timestamp().unwrap_or(i64::MAX)
+        Ok(true)
     }
 }
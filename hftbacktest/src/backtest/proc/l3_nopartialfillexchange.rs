@@ -3,16 +3,17 @@ use std::mem;
 use crate::{
     backtest::{
         assettype::AssetType,
-        models::{L3FIFOQueueModel, L3OrderId, L3OrderSource, L3QueueModel, LatencyModel},
+        models::{L3OrderId, L3OrderSource, L3QueueModel, LatencyModel},
         order::OrderBus,
         proc::proc::Processor,
-        reader::reader::{Data, Reader},
+        reader::{Data, Reader},
         state::State,
         BacktestError,
     },
-    depth::{L3MarketDepth, INVALID_MAX, INVALID_MIN},
+    depth::L3MarketDepth,
     types::{
         Event,
+        OrdType,
         Order,
         OrderId,
         Side,
@@ -30,7 +31,9 @@ use crate::{
     },
 };
 
-/// The exchange model without partial fills.
+/// The Level3 Market-By-Order exchange model without partial fills. The orders from the market
+/// feed and the backtest orders are managed together by the [`L3QueueModel`], so that the exact
+/// queue position of the backtest order is known.
 ///
 /// Support order types: [OrdType::Limit](crate::types::OrdType::Limit)
 /// Support time-in-force: [`TimeInForce::GTC`], [`TimeInForce::GTX`]
@@ -40,14 +43,12 @@ use crate::{
 /// Buy order in the order book
 ///
 /// - Your order price >= the best ask price
-/// - Your order price > sell trade price
-/// - Your order is at the front of the queue and your order price == sell trade price
+/// - The market order behind your order in the queue is filled, as determined by the queue model
 ///
 /// Sell order in the order book
 ///
 /// - Your order price <= the best bid price
-/// - Your order price < buy trade price
-/// - Your order is at the front of the queue && your order price == buy trade price
+/// - The market order behind your order in the queue is filled, as determined by the queue model
 ///
 /// **Liquidity-Taking Order**
 ///
@@ -55,10 +56,11 @@ use crate::{
 /// best. Be aware that this may cause unrealistic fill simulations if you attempt to execute a
 /// large quantity.
 ///
-pub struct L3NoPartialFillExchange<AT, LM, MD>
+pub struct L3NoPartialFillExchange<AT, LM, QM, MD>
 where
     AT: AssetType,
     LM: LatencyModel,
+    QM: L3QueueModel,
     MD: L3MarketDepth,
 {
     reader: Reader<Event>,
@@ -70,22 +72,24 @@ where
     depth: MD,
     state: State<AT>,
     order_latency: LM,
-    queue_model: L3FIFOQueueModel,
+    queue_model: QM,
 }
 
-impl<AT, LM, MD> L3NoPartialFillExchange<AT, LM, MD>
+impl<AT, LM, QM, MD> L3NoPartialFillExchange<AT, LM, QM, MD>
 where
     AT: AssetType,
     LM: LatencyModel,
+    QM: L3QueueModel,
     MD: L3MarketDepth,
-    BacktestError: From<<MD as L3MarketDepth>::Error>,
+    BacktestError: From<<MD as L3MarketDepth>::Error> + From<<QM as L3QueueModel>::Error>,
 {
-    /// Constructs an instance of `NoPartialFillExchange`.
+    /// Constructs an instance of `L3NoPartialFillExchange`.
     pub fn new(
         reader: Reader<Event>,
         depth: MD,
         state: State<AT>,
         order_latency: LM,
+        queue_model: QM,
         orders_to: OrderBus,
         orders_from: OrderBus,
     ) -> Self {
@@ -98,7 +102,7 @@ where
             depth,
             state,
             order_latency,
-            queue_model: L3FIFOQueueModel::new(),
+            queue_model,
         }
     }
 
@@ -161,41 +165,9 @@ where
         new_best_tick: i64,
         timestamp: i64,
     ) -> Result<(), BacktestError> {
-        // If the best has been significantly updated compared to the previous best, it would be
-        // better to iterate orders dict instead of order price ladder.
-        let mut filled_orders = Vec::new();
-        if prev_best_tick == INVALID_MIN
-            || (self.queue_model.orders.len() as i64) < new_best_tick - prev_best_tick
-        {
-            let mut filled_orders = Vec::new();
-            for (order_id, &mut (side, price_tick)) in self.queue_model.orders.iter_mut() {
-                if side == Side::Sell && price_tick <= new_best_tick {
-                    let priority = self.queue_model.ask_queue.get_mut(&price_tick).unwrap();
-                    let mut i = 0;
-                    while i < priority.len() {
-                        let order = priority.get(i).unwrap();
-                        if order_id.is(order) {
-                            filled_orders.push(priority.remove(i));
-                            break;
-                        }
-                    }
-                }
-            }
-        } else {
-            for t in (prev_best_tick + 1)..=new_best_tick {
-                if let Some(orders) = self.queue_model.ask_queue.get_mut(&t) {
-                    let mut i = 0;
-                    while i < orders.len() {
-                        let order = orders.get(i).unwrap();
-                        let order_source =
-                            order.q.as_any().downcast_ref::<L3OrderSource>().unwrap();
-                        if *order_source == L3OrderSource::Backtest {
-                            filled_orders.push(orders.remove(i));
-                        }
-                    }
-                }
-            }
-        }
+        let filled_orders = self
+            .queue_model
+            .on_best_bid_update(prev_best_tick, new_best_tick)?;
         for mut order in filled_orders {
             let price_tick = order.price_tick;
             self.fill(&mut order, timestamp, true, price_tick)?;
@@ -209,40 +181,9 @@ where
         new_best_tick: i64,
         timestamp: i64,
     ) -> Result<(), BacktestError> {
-        // If the best has been significantly updated compared to the previous best, it would be
-        // better to iterate orders dict instead of order price ladder.
-        let mut filled_orders = Vec::new();
-        if prev_best_tick == INVALID_MAX
-            || (self.queue_model.orders.len() as i64) < prev_best_tick - new_best_tick
-        {
-            for (order_id, &mut (side, price_tick)) in self.queue_model.orders.iter_mut() {
-                if side == Side::Buy && price_tick >= new_best_tick {
-                    let priority = self.queue_model.ask_queue.get_mut(&price_tick).unwrap();
-                    let mut i = 0;
-                    while i < priority.len() {
-                        let order = priority.get(i).unwrap();
-                        if order_id.is(order) {
-                            filled_orders.push(priority.remove(i));
-                            break;
-                        }
-                    }
-                }
-            }
-        } else {
-            for t in new_best_tick..prev_best_tick {
-                if let Some(orders) = self.queue_model.bid_queue.get_mut(&t) {
-                    let mut i = 0;
-                    while i < orders.len() {
-                        let order = orders.get(i).unwrap();
-                        let order_source =
-                            order.q.as_any().downcast_ref::<L3OrderSource>().unwrap();
-                        if *order_source == L3OrderSource::Backtest {
-                            filled_orders.push(orders.remove(i));
-                        }
-                    }
-                }
-            }
-        }
+        let filled_orders = self
+            .queue_model
+            .on_best_ask_update(prev_best_tick, new_best_tick)?;
         for mut order in filled_orders {
             let price_tick = order.price_tick;
             self.fill(&mut order, timestamp, true, price_tick)?;
@@ -250,14 +191,26 @@ where
         Ok(())
     }
 
+    fn market_order(&self, ev: &Event, side: Side) -> Order {
+        let tick_size = self.depth.tick_size();
+        let mut order = Order::new(
+            ev.order_id,
+            (ev.px / tick_size).round() as i64,
+            tick_size,
+            ev.qty,
+            side,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+        order.q = Box::new(L3OrderSource::Market);
+        order
+    }
+
     fn ack_new(&mut self, mut order: Order, timestamp: i64) -> Result<(), BacktestError> {
-        if self
-            .queue_model
-            .orders
-            .contains_key(&L3OrderId::Backtest(order.order_id))
-        {
+        if self.queue_model.contains_backtest_order(order.order_id) {
             return Err(BacktestError::OrderIdExist);
         }
+        order.q = Box::new(L3OrderSource::Backtest);
 
         if order.side == Side::Buy {
             // Checks if the buy order price is greater than or equal to the current best ask.
@@ -322,6 +275,7 @@ where
         match self
             .queue_model
             .cancel_order(L3OrderId::Backtest(order.order_id))
+            .map_err(BacktestError::from)
         {
             Ok(mut exch_order) => {
                 // Makes the response.
@@ -350,12 +304,25 @@ where
     }
 }
 
-impl<AT, LM, MD> Processor for L3NoPartialFillExchange<AT, LM, MD>
+// The market order may not be found in the queue if it was submitted before the data begins.
+fn ignore_not_found<T, E>(result: Result<T, E>) -> Result<Option<T>, BacktestError>
+where
+    BacktestError: From<E>,
+{
+    match result.map_err(BacktestError::from) {
+        Ok(value) => Ok(Some(value)),
+        Err(BacktestError::OrderNotFound) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+impl<AT, LM, QM, MD> Processor for L3NoPartialFillExchange<AT, LM, QM, MD>
 where
     AT: AssetType,
     LM: LatencyModel,
+    QM: L3QueueModel,
     MD: L3MarketDepth,
-    BacktestError: From<<MD as L3MarketDepth>::Error>,
+    BacktestError: From<<MD as L3MarketDepth>::Error> + From<<QM as L3QueueModel>::Error>,
 {
     fn initialize_data(&mut self) -> Result<i64, BacktestError> {
         self.data = self.reader.next()?;
@@ -370,6 +337,7 @@ where
 
     fn process_data(&mut self) -> Result<(i64, i64), BacktestError> {
         let row_num = self.row_num;
+        self.order_latency.observe(&self.data[row_num]);
         if self.data[row_num].is(EXCH_BID_DEPTH_CLEAR_EVENT) {
             self.depth.clear_depth(Side::Buy);
         } else if self.data[row_num].is(EXCH_ASK_DEPTH_CLEAR_EVENT) {
@@ -383,6 +351,9 @@ where
                 self.data[row_num].qty,
                 self.data[row_num].exch_ts,
             )?;
+            let order = self.market_order(&self.data[row_num], Side::Buy);
+            self.queue_model
+                .add_order(L3OrderId::Market(order.order_id), order)?;
             if best_bid_tick > prev_best_bid_tick {
                 self.on_best_bid_update(
                     prev_best_bid_tick,
@@ -397,6 +368,9 @@ where
                 self.data[row_num].qty,
                 self.data[row_num].exch_ts,
             )?;
+            let order = self.market_order(&self.data[row_num], Side::Sell);
+            self.queue_model
+                .add_order(L3OrderId::Market(order.order_id), order)?;
             if best_ask_tick < prev_best_ask_tick {
                 self.on_best_ask_update(
                     prev_best_ask_tick,
@@ -411,6 +385,11 @@ where
                 self.data[row_num].qty,
                 self.data[row_num].exch_ts,
             )?;
+            let order = self.market_order(&self.data[row_num], side);
+            ignore_not_found(
+                self.queue_model
+                    .modify_order(L3OrderId::Market(order.order_id), order),
+            )?;
             if side == Side::Buy {
                 if best_tick > prev_best_tick {
                     self.on_best_bid_update(prev_best_tick, best_tick, self.data[row_num].exch_ts)?;
@@ -424,12 +403,16 @@ where
             let _ = self
                 .depth
                 .delete_order(self.data[row_num].order_id, self.data[row_num].exch_ts)?;
-            self.queue_model
-                .cancel_order(L3OrderId::Market(self.data[row_num].order_id))?;
+            ignore_not_found(
+                self.queue_model
+                    .cancel_order(L3OrderId::Market(self.data[row_num].order_id)),
+            )?;
         } else if self.data[row_num].is(EXCH_FILL_EVENT) {
-            let filled_orders = self
-                .queue_model
-                .fill(L3OrderId::Market(self.data[row_num].order_id), false)?;
+            let filled_orders = ignore_not_found(
+                self.queue_model
+                    .fill(L3OrderId::Market(self.data[row_num].order_id), false),
+            )?
+            .unwrap_or_default();
             let timestamp = self.data[row_num].exch_ts;
             for mut order in filled_orders {
                 let price_tick = order.price_tick;
@@ -485,3 +468,91 @@ where
         self.orders_to.earliest_timestamp().unwrap_or(i64::MAX)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backtest::{
+            assettype::LinearAsset,
+            models::{ConstantLatency, L3FIFOQueueModel},
+            proc::test_utils::event,
+            reader::Data,
+            Backtest,
+            DataSource,
+            L3AssetBuilder,
+        },
+        depth::{HashMapMarketDepth, MarketDepth},
+        types::{
+            Bot,
+            Event,
+            OrdType,
+            Status,
+            TimeInForce,
+            ADD_ORDER_EVENT,
+            BUY_EVENT,
+            FILL_EVENT,
+            SELL_EVENT,
+        },
+    };
+
+    fn mbo(ev: u64, ts: i64, order_id: u64, px: f64, qty: f64) -> Event {
+        Event {
+            order_id,
+            ..event(ev, ts, px, qty)
+        }
+    }
+
+    #[test]
+    fn test_l3_backtest() {
+        let data = Data::from_slice(&[
+            mbo(ADD_ORDER_EVENT | BUY_EVENT, 1, 1, 100.0, 1.0),
+            mbo(ADD_ORDER_EVENT | SELL_EVENT, 2, 2, 101.0, 1.0),
+            mbo(ADD_ORDER_EVENT | BUY_EVENT, 20, 3, 100.0, 1.0),
+            mbo(FILL_EVENT | BUY_EVENT, 50, 1, 100.0, 1.0),
+            mbo(FILL_EVENT | BUY_EVENT, 100, 3, 100.0, 1.0),
+            mbo(ADD_ORDER_EVENT | BUY_EVENT, 150, 4, 102.0, 1.0),
+            mbo(ADD_ORDER_EVENT | SELL_EVENT, 300, 5, 103.0, 1.0),
+        ]);
+        let mut hbt = Backtest::builder()
+            .add(
+                L3AssetBuilder::new()
+                    .data(vec![DataSource::Data(data)])
+                    .latency_model(ConstantLatency::new(1, 1))
+                    .asset_type(LinearAsset::new(1.0))
+                    .queue_model(L3FIFOQueueModel::new())
+                    .depth(|| HashMapMarketDepth::new(1.0, 1.0))
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        hbt.elapse(9).unwrap();
+        assert_eq!(hbt.depth(0).best_bid_tick(), 100);
+        assert_eq!(hbt.depth(0).best_ask_tick(), 101);
+
+        // The buy order is queued behind market order 1 and ahead of market order 3.
+        hbt.submit_buy_order(0, 1, 100.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+        hbt.submit_sell_order(0, 2, 102.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::New);
+        assert_eq!(hbt.orders(0)[&2].status, Status::New);
+
+        // The fill of the order ahead doesn't fill the backtest order.
+        hbt.elapse(40).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::New);
+
+        // The fill of the order behind does.
+        hbt.elapse(50).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::Filled);
+        assert_eq!(hbt.orders(0)[&1].exec_price_tick, 100);
+        assert_eq!(hbt.position(0), 1.0);
+
+        // The best bid rising to the sell order price fills it.
+        hbt.elapse(100).unwrap();
+        assert_eq!(hbt.depth(0).best_bid_tick(), 102);
+        assert_eq!(hbt.orders(0)[&2].status, Status::Filled);
+        assert_eq!(hbt.position(0), 0.0);
+    }
+}
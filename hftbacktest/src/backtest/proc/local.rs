@@ -6,23 +6,33 @@ use std::{
 use crate::{
     backtest::{
         assettype::AssetType,
-        models::LatencyModel,
+        models::{LatencyModel, RequestType},
         order::OrderBus,
-        proc::proc::{LocalProcessor, Processor},
+        proc::{
+            clockskew::ClockSkew,
+            constraint::OrderConstraints,
+            gap::{FeedGap, GapAction},
+            proc::{LocalProcessor, Processor},
+            throttle::SendThrottle,
+        },
         reader::{Data, Reader},
         state::State,
         BacktestError,
     },
-    depth::{L2MarketDepth, MarketDepth},
+    depth::{DepthChange, L2MarketDepth, MarketDepth},
     types::{
+        BatchRequest,
         Event,
         OrdType,
         Order,
         OrderId,
+        OrderRequest,
+        Roll,
         Side,
         StateValues,
         Status,
         TimeInForce,
+        TriggerBy,
         LOCAL_ASK_DEPTH_CLEAR_EVENT,
         LOCAL_ASK_DEPTH_EVENT,
         LOCAL_ASK_DEPTH_SNAPSHOT_EVENT,
@@ -31,10 +41,16 @@ use crate::{
         LOCAL_BID_DEPTH_SNAPSHOT_EVENT,
         LOCAL_DEPTH_CLEAR_EVENT,
         LOCAL_EVENT,
+        LOCAL_FUNDING_EVENT,
+        LOCAL_ROLL_EVENT,
         LOCAL_TRADE_EVENT,
     },
 };
 
+/// The order ID of the mass-cancel message that cancels all open orders at the exchange. It is
+/// reserved and should not be used for other orders.
+pub const CANCEL_ALL_ORDER_ID: OrderId = OrderId::MAX - 1;
+
 /// The local model.
 pub struct Local<AT, LM, MD>
 where
@@ -54,6 +70,14 @@ where
     trades: Vec<Event>,
     last_feed_latency: Option<(i64, i64)>,
     last_order_latency: Option<(i64, i64, i64)>,
+    aux: HashMap<u64, Event>,
+    rolls: Vec<Roll>,
+    feed_gap: FeedGap,
+    constraints: Option<OrderConstraints>,
+    clock_skew: Option<ClockSkew>,
+    throttle: Option<SendThrottle>,
+    fills: Option<Vec<Order>>,
+    depth_changes: Option<Vec<DepthChange>>,
 }
 
 impl<AT, LM, MD> Local<AT, LM, MD>
@@ -85,12 +109,75 @@ where
             trades: Vec::with_capacity(trade_len),
             last_feed_latency: None,
             last_order_latency: None,
+            aux: Default::default(),
+            rolls: Vec::new(),
+            feed_gap: Default::default(),
+            constraints: None,
+            clock_skew: None,
+            throttle: None,
+            fills: None,
+            depth_changes: None,
+        }
+    }
+
+    /// Enables feed gap detection. If no local event arrives for longer than the `threshold`, the
+    /// market depth is considered stale, and depth events are skipped until the next snapshot,
+    /// from which the market depth is rebuilt. See [`FeedGap`].
+    pub fn set_feed_gap_threshold(&mut self, threshold: i64) {
+        self.feed_gap = FeedGap::new(threshold);
+    }
+
+    /// Sets the instrument constraints, so that non-conforming order requests are rejected, as
+    /// the venue does. See [`OrderConstraints`].
+    pub fn set_order_constraints(&mut self, constraints: OrderConstraints) {
+        self.constraints = Some(constraints);
+    }
+
+    /// Sets the skew of the exchange clock, which shifts the exchange timestamps of the received
+    /// order responses. See [`ClockSkew`].
+    pub fn set_clock_skew(&mut self, clock_skew: ClockSkew) {
+        self.clock_skew = Some(clock_skew);
+    }
+
+    /// Sets the send throttle, which serializes bursts of order requests before they enter the
+    /// order entry latency. See [`SendThrottle`].
+    pub fn set_send_throttle(&mut self, throttle: SendThrottle) {
+        self.throttle = Some(throttle);
+    }
+
+    /// Returns the timestamp at which an order request sent at the timestamp departs from the
+    /// local, after waiting in the send queue.
+    fn departure(&mut self, current_timestamp: i64) -> Result<i64, BacktestError> {
+        match self.throttle.as_mut() {
+            Some(throttle) => throttle
+                .depart(current_timestamp)
+                .ok_or(BacktestError::SendQueueFull),
+            None => Ok(current_timestamp),
         }
     }
 
     fn process_recv_order_(&mut self, order: Order) -> Result<(), BacktestError> {
-        if order.status == Status::Filled {
-            self.state.apply_fill(&order);
+        if order.order_id == CANCEL_ALL_ORDER_ID {
+            // The mass-cancel response isn't an order; each canceled order is reported separately.
+            return Ok(());
+        }
+        if order.req == Status::Queried {
+            self.apply_query_response(order);
+            return Ok(());
+        }
+        if order.status == Status::Filled || order.status == Status::PartiallyFilled {
+            // A duplicated execution report doesn't change the position.
+            let duplicate = self.orders.get(&order.order_id).is_some_and(|local_order| {
+                local_order.status == order.status
+                    && local_order.exch_timestamp == order.exch_timestamp
+                    && local_order.leaves_qty == order.leaves_qty
+            });
+            if !duplicate {
+                self.state.apply_fill(&order);
+                if let Some(fills) = self.fills.as_mut() {
+                    fills.push(order.clone());
+                }
+            }
         }
         // Applies the received order response to the local orders.
         match self.orders.entry(order.order_id) {
@@ -117,6 +204,112 @@ where
         }
         Ok(())
     }
+
+    fn apply_query_response(&mut self, mut order: Order) {
+        if order.status == Status::Rejected {
+            return;
+        }
+        order.req = Status::None;
+        match self.orders.entry(order.order_id) {
+            Entry::Occupied(mut entry) => {
+                let local_order = entry.get_mut();
+                if order.status != Status::None {
+                    local_order.update(&order);
+                } else if local_order.active() {
+                    // The exchange no longer has the order, so the response to the pending
+                    // cancel, if any, or the notification of its expiry has been lost.
+                    local_order.status = if local_order.req == Status::Canceled {
+                        Status::Canceled
+                    } else {
+                        Status::Expired
+                    };
+                    local_order.req = Status::None;
+                    local_order.exch_timestamp = order.exch_timestamp;
+                }
+            }
+            Entry::Vacant(entry) => {
+                // Recovers the open order unknown to the local.
+                if order.status != Status::None {
+                    entry.insert(order);
+                }
+            }
+        }
+    }
+
+    fn create_order_from_request(
+        &self,
+        order: &OrderRequest,
+        current_timestamp: i64,
+    ) -> Result<Order, BacktestError> {
+        let expire_timestamp = if order.time_in_force == TimeInForce::GTD {
+            if order.expire_timestamp <= 0 {
+                // A GTD order requires the expiry.
+                return Err(BacktestError::InvalidOrderRequest);
+            }
+            order.expire_timestamp
+        } else {
+            0
+        };
+        if self.orders.contains_key(&order.order_id) {
+            return Err(BacktestError::OrderIdExist);
+        }
+        if let Some(constraints) = &self.constraints {
+            constraints.check(
+                order.order_type,
+                order.price,
+                order.qty,
+                self.depth.tick_size(),
+                self.depth.lot_size(),
+            )?;
+        }
+
+        let price_tick = (order.price / self.depth.tick_size()).round() as i64;
+        let mut new_order = Order::new(
+            order.order_id,
+            price_tick,
+            self.depth.tick_size(),
+            order.qty,
+            order.side,
+            order.order_type,
+            order.time_in_force,
+        );
+        if order.order_type.is_stop() {
+            new_order.trigger_price_tick =
+                (order.trigger_price / self.depth.tick_size()).round() as i64;
+            new_order.trigger_by = order.trigger_by;
+        }
+        new_order.expire_timestamp = expire_timestamp;
+        new_order.reduce_only = order.reduce_only;
+        new_order.req = Status::New;
+        new_order.local_timestamp = current_timestamp;
+        Ok(new_order)
+    }
+
+    fn send_new_order(
+        &mut self,
+        mut order: Order,
+        current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        let send_timestamp = self.departure(current_timestamp)?;
+        self.orders.insert(order.order_id, order.clone());
+
+        let order_entry_latency =
+            self.order_latency
+                .request_entry(send_timestamp, &order, RequestType::New);
+        // Negative latency indicates that the order is rejected for technical reasons, and its
+        // value represents the latency that the local experiences when receiving the rejection
+        // notification.
+        if order_entry_latency < 0 {
+            // Rejects the order.
+            order.req = Status::Rejected;
+            let rej_recv_timestamp = send_timestamp - order_entry_latency;
+            self.orders_from.append(order, rej_recv_timestamp);
+        } else {
+            let exch_recv_timestamp = send_timestamp + order_entry_latency;
+            self.orders_to.append(order, exch_recv_timestamp);
+        }
+        Ok(())
+    }
 }
 
 impl<AT, LM, MD> LocalProcessor<MD, Event> for Local<AT, LM, MD>
@@ -135,64 +328,315 @@ where
         time_in_force: TimeInForce,
         current_timestamp: i64,
     ) -> Result<(), BacktestError> {
-        if self.orders.contains_key(&order_id) {
-            return Err(BacktestError::OrderIdExist);
+        if order_type.is_stop() || time_in_force == TimeInForce::GTD {
+            // A stop order requires the trigger price, and a GTD order requires the expiry.
+            return Err(BacktestError::InvalidOrderRequest);
+        }
+        self.submit_order_request(
+            &OrderRequest {
+                order_id,
+                price,
+                qty,
+                side,
+                time_in_force,
+                order_type,
+                trigger_price: 0.0,
+                expire_timestamp: 0,
+                reduce_only: false,
+                trigger_by: TriggerBy::Default,
+            },
+            current_timestamp,
+        )
+    }
+
+    fn submit_stop_order(
+        &mut self,
+        order_id: OrderId,
+        side: Side,
+        trigger_price: f64,
+        price: f64,
+        qty: f64,
+        order_type: OrdType,
+        time_in_force: TimeInForce,
+        current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        if !order_type.is_stop() || time_in_force == TimeInForce::GTD {
+            return Err(BacktestError::InvalidOrderRequest);
+        }
+        self.submit_order_request(
+            &OrderRequest {
+                order_id,
+                price,
+                qty,
+                side,
+                time_in_force,
+                order_type,
+                trigger_price,
+                expire_timestamp: 0,
+                reduce_only: false,
+                trigger_by: TriggerBy::Default,
+            },
+            current_timestamp,
+        )
+    }
+
+    fn submit_gtd_order(
+        &mut self,
+        order_id: OrderId,
+        side: Side,
+        price: f64,
+        qty: f64,
+        order_type: OrdType,
+        expire_timestamp: i64,
+        current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        if order_type.is_stop() {
+            return Err(BacktestError::InvalidOrderRequest);
+        }
+        self.submit_order_request(
+            &OrderRequest {
+                order_id,
+                price,
+                qty,
+                side,
+                time_in_force: TimeInForce::GTD,
+                order_type,
+                trigger_price: 0.0,
+                expire_timestamp,
+                reduce_only: false,
+                trigger_by: TriggerBy::Default,
+            },
+            current_timestamp,
+        )
+    }
+
+    fn submit_order_request(
+        &mut self,
+        order: &OrderRequest,
+        current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        let order = self.create_order_from_request(order, current_timestamp)?;
+        self.send_new_order(order, current_timestamp)
+    }
+
+    fn submit_batch(
+        &mut self,
+        requests: &[BatchRequest],
+        current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        // Validates the entire batch before sending any request, so that the batch is rejected as
+        // a whole if any request is invalid.
+        let mut batch: Vec<Order> = Vec::with_capacity(requests.len());
+        for request in requests {
+            let order = match request {
+                BatchRequest::Submit(order) => {
+                    if batch.iter().any(|o| o.order_id == order.order_id) {
+                        return Err(BacktestError::OrderIdExist);
+                    }
+                    self.create_order_from_request(order, current_timestamp)?
+                }
+                BatchRequest::Cancel(order_id) => {
+                    let order = self
+                        .orders
+                        .get(order_id)
+                        .ok_or(BacktestError::OrderNotFound)?;
+                    if order.req != Status::None || batch.iter().any(|o| o.order_id == *order_id) {
+                        return Err(BacktestError::OrderRequestInProcess);
+                    }
+                    let mut order = order.clone();
+                    order.req = Status::Canceled;
+                    order
+                }
+            };
+            batch.push(order);
+        }
+        let Some(first) = batch.first() else {
+            return Ok(());
+        };
+
+        // The batch reaches the exchange as a single message, so every request shares the order
+        // entry latency.
+        let request_type = if first.req == Status::New {
+            RequestType::New
+        } else {
+            RequestType::Cancel
+        };
+        let send_timestamp = self.departure(current_timestamp)?;
+        let order_entry_latency =
+            self.order_latency
+                .request_entry(send_timestamp, first, request_type);
+        for mut order in batch {
+            if order.req == Status::New {
+                self.orders.insert(order.order_id, order.clone());
+            } else {
+                self.orders.get_mut(&order.order_id).unwrap().req = order.req;
+            }
+            // Negative latency indicates that the batch is rejected for technical reasons, and its
+            // value represents the latency that the local experiences when receiving the rejection
+            // notification.
+            if order_entry_latency < 0 {
+                order.req = Status::Rejected;
+                let rej_recv_timestamp = send_timestamp - order_entry_latency;
+                self.orders_from.append(order, rej_recv_timestamp);
+            } else {
+                let exch_recv_timestamp = send_timestamp + order_entry_latency;
+                self.orders_to.append(order, exch_recv_timestamp);
+            }
+        }
+        Ok(())
+    }
+
+    fn cancel(&mut self, order_id: OrderId, current_timestamp: i64) -> Result<(), BacktestError> {
+        let order = self
+            .orders
+            .get(&order_id)
+            .ok_or(BacktestError::OrderNotFound)?;
+
+        if order.req != Status::None {
+            return Err(BacktestError::OrderRequestInProcess);
+        }
+
+        let send_timestamp = self.departure(current_timestamp)?;
+        let order = self.orders.get_mut(&order_id).unwrap();
+        order.req = Status::Canceled;
+        let order_entry_latency =
+            self.order_latency
+                .request_entry(send_timestamp, order, RequestType::Cancel);
+        // Negative latency indicates that the order is rejected for technical reasons, and its
+        // value represents the latency that the local experiences when receiving the rejection
+        // notification.
+        if order_entry_latency < 0 {
+            // Rejects the order.
+            let mut order_ = order.clone();
+            order_.req = Status::Rejected;
+            let rej_recv_timestamp = send_timestamp - order_entry_latency;
+            self.orders_from.append(order_, rej_recv_timestamp);
+        } else {
+            let exch_recv_timestamp = send_timestamp + order_entry_latency;
+            self.orders_to.append(order.clone(), exch_recv_timestamp);
         }
+        Ok(())
+    }
 
-        let price_tick = (price / self.depth.tick_size()).round() as i64;
+    fn cancel_all(&mut self, current_timestamp: i64) -> Result<(), BacktestError> {
         let mut order = Order::new(
-            order_id,
-            price_tick,
+            CANCEL_ALL_ORDER_ID,
+            0,
             self.depth.tick_size(),
-            qty,
-            side,
-            order_type,
-            time_in_force,
+            0.0,
+            Side::None,
+            OrdType::Limit,
+            TimeInForce::GTC,
         );
-        order.req = Status::New;
+        order.req = Status::Canceled;
         order.local_timestamp = current_timestamp;
-        self.orders.insert(order.order_id, order.clone());
-
-        let order_entry_latency = self.order_latency.entry(current_timestamp, &order);
+        let send_timestamp = self.departure(current_timestamp)?;
+        let order_entry_latency =
+            self.order_latency
+                .request_entry(send_timestamp, &order, RequestType::Cancel);
         // Negative latency indicates that the order is rejected for technical reasons, and its
         // value represents the latency that the local experiences when receiving the rejection
         // notification.
         if order_entry_latency < 0 {
-            // Rejects the order.
             order.req = Status::Rejected;
-            let rej_recv_timestamp = current_timestamp - order_entry_latency;
+            let rej_recv_timestamp = send_timestamp - order_entry_latency;
             self.orders_from.append(order, rej_recv_timestamp);
         } else {
-            let exch_recv_timestamp = current_timestamp + order_entry_latency;
+            let exch_recv_timestamp = send_timestamp + order_entry_latency;
             self.orders_to.append(order, exch_recv_timestamp);
         }
         Ok(())
     }
 
-    fn cancel(&mut self, order_id: OrderId, current_timestamp: i64) -> Result<(), BacktestError> {
+    fn query_order(
+        &mut self,
+        order_id: OrderId,
+        current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        let mut order = match self.orders.get(&order_id) {
+            Some(order) => order.clone(),
+            // An order unknown to the local can also be queried to recover it.
+            None => Order::new(
+                order_id,
+                0,
+                self.depth.tick_size(),
+                0.0,
+                Side::None,
+                OrdType::Limit,
+                TimeInForce::GTC,
+            ),
+        };
+        order.req = Status::Queried;
+        order.local_timestamp = current_timestamp;
+        let send_timestamp = self.departure(current_timestamp)?;
+        let order_entry_latency =
+            self.order_latency
+                .request_entry(send_timestamp, &order, RequestType::Query);
+        // Negative latency indicates that the order is rejected for technical reasons, and its
+        // value represents the latency that the local experiences when receiving the rejection
+        // notification.
+        if order_entry_latency < 0 {
+            // Rejects the query. The request status marks the query response, so the rejection is
+            // reported by the order status.
+            order.status = Status::Rejected;
+            let rej_recv_timestamp = send_timestamp - order_entry_latency;
+            self.orders_from.append(order, rej_recv_timestamp);
+        } else {
+            let exch_recv_timestamp = send_timestamp + order_entry_latency;
+            self.orders_to.append(order, exch_recv_timestamp);
+        }
+        Ok(())
+    }
+
+    fn modify(
+        &mut self,
+        order_id: OrderId,
+        price: f64,
+        qty: f64,
+        current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
         let order = self
             .orders
-            .get_mut(&order_id)
+            .get(&order_id)
             .ok_or(BacktestError::OrderNotFound)?;
 
         if order.req != Status::None {
             return Err(BacktestError::OrderRequestInProcess);
         }
+        if let Some(constraints) = &self.constraints {
+            constraints.check(
+                order.order_type,
+                price,
+                qty,
+                self.depth.tick_size(),
+                self.depth.lot_size(),
+            )?;
+        }
 
-        order.req = Status::Canceled;
-        let order_entry_latency = self.order_latency.entry(current_timestamp, order);
+        let send_timestamp = self.departure(current_timestamp)?;
+        // The local order keeps its price and quantity until the exchange accepts the
+        // modification.
+        let order = self.orders.get_mut(&order_id).unwrap();
+        order.req = Status::Replaced;
+        order.local_timestamp = current_timestamp;
+        let mut order_ = order.clone();
+        order_.price_tick = (price / self.depth.tick_size()).round() as i64;
+        order_.qty = qty;
+
+        let order_entry_latency =
+            self.order_latency
+                .request_entry(send_timestamp, &order_, RequestType::Modify);
         // Negative latency indicates that the order is rejected for technical reasons, and its
         // value represents the latency that the local experiences when receiving the rejection
         // notification.
         if order_entry_latency < 0 {
             // Rejects the order.
-            let mut order_ = order.clone();
             order_.req = Status::Rejected;
-            let rej_recv_timestamp = current_timestamp - order_entry_latency;
+            let rej_recv_timestamp = send_timestamp - order_entry_latency;
             self.orders_from.append(order_, rej_recv_timestamp);
         } else {
-            let exch_recv_timestamp = current_timestamp + order_entry_latency;
-            self.orders_to.append(order.clone(), exch_recv_timestamp);
+            let exch_recv_timestamp = send_timestamp + order_entry_latency;
+            self.orders_to.append(order_, exch_recv_timestamp);
         }
         Ok(())
     }
@@ -236,6 +680,40 @@ where
     fn order_latency(&self) -> Option<(i64, i64, i64)> {
         self.last_order_latency
     }
+
+    fn feed_gap_count(&self) -> usize {
+        self.feed_gap.count()
+    }
+    fn aux_event(&self, kind: u64) -> Option<&Event> {
+        self.aux.get(&kind)
+    }
+
+    fn rolls(&self) -> &[Roll] {
+        &self.rolls
+    }
+
+    fn record_fills(&mut self) {
+        self.fills.get_or_insert_with(Vec::new);
+    }
+
+    fn take_fills(&mut self) -> Vec<Order> {
+        self.fills.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    fn apply_leg_fill(&mut self, order: &Order) {
+        self.state.apply_fill(order);
+    }
+
+    fn record_depth_changes(&mut self) {
+        self.depth_changes.get_or_insert_with(Vec::new);
+    }
+
+    fn take_depth_changes(&mut self) -> Vec<DepthChange> {
+        self.depth_changes
+            .as_mut()
+            .map(mem::take)
+            .unwrap_or_default()
+    }
 }
 
 impl<AT, LM, MD> Processor for Local<AT, LM, MD>
@@ -258,17 +736,60 @@ where
 
     fn process_data(&mut self) -> Result<(i64, i64), BacktestError> {
         let ev = &self.data[self.row_num];
+        // Settles the scheduled funding and accrues the borrow interest on the position held before
+        // this event.
+        self.state.apply_scheduled_funding(ev.local_ts, || {
+            (self.depth.best_bid() + self.depth.best_ask()) / 2.0
+        });
+        self.state.apply_borrow_cost(ev.local_ts, || {
+            (self.depth.best_bid() + self.depth.best_ask()) / 2.0
+        });
+        let action = self.feed_gap.check(ev, ev.local_ts);
+        let mut depth_change = None;
+        if action == GapAction::Reset {
+            self.depth.clear_depth(Side::None, 0.0);
+            depth_change = Some(DepthChange::Clear {
+                side: Side::None,
+                price: 0.0,
+            });
+        }
         // Processes a depth event
-        if ev.is(LOCAL_BID_DEPTH_CLEAR_EVENT) {
+        if action == GapAction::Skip {
+            // The market depth is stale until the next snapshot.
+        } else if ev.is(LOCAL_BID_DEPTH_CLEAR_EVENT) {
             self.depth.clear_depth(Side::Buy, ev.px);
+            depth_change = Some(DepthChange::Clear {
+                side: Side::Buy,
+                price: ev.px,
+            });
         } else if ev.is(LOCAL_ASK_DEPTH_CLEAR_EVENT) {
             self.depth.clear_depth(Side::Sell, ev.px);
+            depth_change = Some(DepthChange::Clear {
+                side: Side::Sell,
+                price: ev.px,
+            });
         } else if ev.is(LOCAL_DEPTH_CLEAR_EVENT) {
             self.depth.clear_depth(Side::None, 0.0);
+            depth_change = Some(DepthChange::Clear {
+                side: Side::None,
+                price: 0.0,
+            });
         } else if ev.is(LOCAL_BID_DEPTH_EVENT) || ev.is(LOCAL_BID_DEPTH_SNAPSHOT_EVENT) {
-            self.depth.update_bid_depth(ev.px, ev.qty, ev.local_ts);
+            self.depth
+                .update_bid_depth_with_count(ev.px, ev.qty, ev.ival, ev.exch_ts);
+            depth_change = Some(DepthChange::Level {
+                side: Side::Buy,
+                price: ev.px,
+                qty: ev.qty,
+            });
         } else if ev.is(LOCAL_ASK_DEPTH_EVENT) || ev.is(LOCAL_ASK_DEPTH_SNAPSHOT_EVENT) {
-            self.depth.update_ask_depth(ev.px, ev.qty, ev.local_ts);
+            self.depth
+                .update_ask_depth_with_count(ev.px, ev.qty, ev.ival, ev.exch_ts);
+            depth_change = Some(DepthChange::Level {
+                side: Side::Sell,
+                price: ev.px,
+                qty: ev.qty,
+            });
         }
         // Processes a trade event
         else if ev.is(LOCAL_TRADE_EVENT) {
@@ -276,6 +797,30 @@ where
                 self.trades.push(ev.clone());
             }
         }
+        // Processes a contract roll
+        else if ev.is(LOCAL_ROLL_EVENT) {
+            self.rolls.push(Roll {
+                timestamp: ev.local_ts,
+                contract: ev.order_id as usize,
+                adjustment: ev.px,
+            });
+        }
+        // Processes a funding payment
+        else if ev.is(LOCAL_FUNDING_EVENT) {
+            let price = if ev.px > 0.0 {
+                ev.px
+            } else {
+                (self.depth.best_bid() + self.depth.best_ask()) / 2.0
+            };
+            self.state.apply_funding(price, ev.fval);
+        }
+        // Processes an auxiliary event
+        else if ev.is_aux() {
+            self.aux.insert(ev.ev & 0xff, ev.clone());
+        }
+        if let (Some(change), Some(depth_changes)) = (depth_change, self.depth_changes.as_mut()) {
+            depth_changes.push(change);
+        }
 
         // Stores the current feed latency
         self.last_feed_latency = Some((ev.exch_ts, ev.local_ts));
@@ -307,12 +852,24 @@ where
         timestamp: i64,
         wait_resp_order_id: Option<OrderId>,
     ) -> Result<bool, BacktestError> {
+        self.state.apply_scheduled_funding(timestamp, || {
+            (self.depth.best_bid() + self.depth.best_ask()) / 2.0
+        });
+        self.state.apply_borrow_cost(timestamp, || {
+            (self.depth.best_bid() + self.depth.best_ask()) / 2.0
+        });
+
         // Processes the order part.
         let mut wait_resp_order_received = false;
         while self.orders_from.len() > 0 {
             let recv_timestamp = self.orders_from.earliest_timestamp().unwrap();
             if timestamp == recv_timestamp {
-                let (order, _) = self.orders_from.pop_front().unwrap();
+                let (mut order, _) = self.orders_from.pop_front().unwrap();
+                if order.exch_timestamp > 0 {
+                    if let Some(clock_skew) = self.clock_skew.as_mut() {
+                        order.exch_timestamp = clock_skew.apply(order.exch_timestamp);
+                    }
+                }
 
                 // Updates the order latency only if it has a valid exchange timestamp. When the
                 // order is rejected before it reaches the matching engine, it has no exchange
@@ -345,3 +902,166 @@ where
         self.orders_to.earliest_timestamp().unwrap_or(i64::MAX)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CANCEL_ALL_ORDER_ID;
+    use crate::{
+        backtest::{
+            proc::{
+                test_utils::{asset_builder, backtest, event},
+                FaultInjector,
+            },
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{
+            BatchRequest,
+            Bot,
+            OrdType,
+            OrderRequest,
+            Side,
+            Status,
+            TimeInForce,
+            TriggerBy,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+        },
+    };
+
+    fn submit(order_id: u64, price: f64) -> BatchRequest {
+        BatchRequest::Submit(OrderRequest {
+            order_id,
+            price,
+            qty: 1.0,
+            side: Side::Buy,
+            time_in_force: TimeInForce::GTC,
+            order_type: OrdType::Limit,
+            trigger_price: 0.0,
+            expire_timestamp: 0,
+            reduce_only: false,
+            trigger_by: TriggerBy::Default,
+        })
+    }
+
+    #[test]
+    fn test_submit_batch() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            let data = Data::from_slice(&[
+                event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 5.0),
+            ]);
+            let mut hbt = backtest(asset_builder(data, 1.0, 1.0).exchange(exch_kind));
+
+            hbt.elapse(9).unwrap();
+            hbt.submit_buy_order(0, 1, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+
+            // The requests in the batch reach the exchange together.
+            hbt.submit_batch(
+                0,
+                vec![submit(2, 98.0), submit(3, 97.0), BatchRequest::Cancel(1)],
+                true,
+            )
+            .unwrap();
+            let orders = hbt.orders(0);
+            assert_eq!(orders[&1].status, Status::Canceled);
+            assert_eq!(orders[&2].status, Status::New);
+            assert_eq!(orders[&3].status, Status::New);
+            assert_eq!(orders[&2].exch_timestamp, orders[&1].exch_timestamp);
+            assert_eq!(orders[&3].exch_timestamp, orders[&1].exch_timestamp);
+
+            // The batch is rejected as a whole if any request is invalid.
+            assert!(hbt
+                .submit_batch(0, vec![submit(4, 96.0), submit(2, 96.0)], false)
+                .is_err());
+            assert!(hbt
+                .submit_batch(0, vec![submit(4, 96.0), submit(4, 95.0)], false)
+                .is_err());
+            assert!(hbt
+                .submit_batch(0, vec![submit(4, 96.0), BatchRequest::Cancel(5)], false)
+                .is_err());
+            assert!(!hbt.orders(0).contains_key(&4));
+        }
+    }
+
+    #[test]
+    fn test_cancel_all() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            let data = Data::from_slice(&[
+                event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 5.0),
+            ]);
+            let mut hbt = backtest(asset_builder(data, 1.0, 1.0).exchange(exch_kind));
+
+            hbt.elapse(9).unwrap();
+            hbt.submit_buy_order(0, 1, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            hbt.submit_sell_order(0, 2, 102.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            // The order reaches the exchange ahead of the mass-cancel message.
+            hbt.submit_buy_order(0, 3, 98.0, 1.0, TimeInForce::GTC, OrdType::Limit, false)
+                .unwrap();
+
+            hbt.cancel_all(0, true).unwrap();
+            let orders = hbt.orders(0);
+            for order_id in 1..=3 {
+                assert_eq!(orders[&order_id].status, Status::Canceled);
+                assert_eq!(orders[&order_id].exch_timestamp, orders[&1].exch_timestamp);
+            }
+            assert!(!orders.contains_key(&CANCEL_ALL_ORDER_ID));
+        }
+    }
+
+    #[test]
+    fn test_query_order() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            let data = Data::from_slice(&[
+                event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 5.0),
+            ]);
+            let mut hbt = backtest(
+                asset_builder(data, 1.0, 1.0)
+                    .exchange(exch_kind)
+                    .fault_injector(FaultInjector::new().drop_ack_rate(1.0)),
+            );
+
+            // The acknowledgement of the new order is lost.
+            hbt.elapse(9).unwrap();
+            hbt.submit_buy_order(0, 1, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, false)
+                .unwrap();
+            hbt.elapse(10).unwrap();
+            assert_eq!(hbt.orders(0)[&1].req, Status::New);
+
+            hbt.query_order(0, 1, true).unwrap();
+            assert_eq!(hbt.orders(0)[&1].req, Status::None);
+            assert_eq!(hbt.orders(0)[&1].status, Status::New);
+
+            // The acknowledgement of the cancel is lost.
+            hbt.cancel(0, 1, false).unwrap();
+            hbt.elapse(10).unwrap();
+            assert_eq!(hbt.orders(0)[&1].req, Status::Canceled);
+
+            hbt.query_order(0, 1, true).unwrap();
+            assert_eq!(hbt.orders(0)[&1].req, Status::None);
+            assert_eq!(hbt.orders(0)[&1].status, Status::Canceled);
+
+            // The order unknown to both is not added.
+            hbt.query_order(0, 2, true).unwrap();
+            assert!(!hbt.orders(0).contains_key(&2));
+        }
+    }
+}
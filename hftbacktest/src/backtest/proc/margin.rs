@@ -0,0 +1,154 @@
+use crate::types::OrderId;
+
+/// The order ID of the order by which the exchange force-closes the position upon liquidation. The
+/// order's fill is reported to the local like any other order response.
+pub const LIQUIDATION_ORDER_ID: OrderId = OrderId::MAX;
+
+/// Provides the margin requirements of a leveraged position.
+///
+/// The margin balance is the collateral plus the equity of the position, which includes the
+/// realized and unrealized P&L, the fees, and the funding payments. The exchange rejects a new
+/// order that increases the exposure if the initial margin required for the exposure, the
+/// position plus the open orders on the heavier side, exceeds the margin balance. When the margin
+/// balance falls below the maintenance margin of the position, the exchange liquidates the
+/// position: it cancels all open orders and force-closes the position by sweeping the book, charging
+/// the liquidation fee on top of the taker fee. The margin is marked to the mid-price.
+///
+/// Only [`NoPartialFillExchange`](crate::backtest::proc::NoPartialFillExchange) and
+/// [`PartialFillExchange`](crate::backtest::proc::PartialFillExchange) support the margin.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::Margin;
+///
+/// // 1,000 USDT collateral at 20x leverage with a 0.5% maintenance margin rate.
+/// let margin = Margin::new(1_000.0, 20.0, 0.005).liquidation_fee_rate(0.0125);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Margin {
+    collateral: f64,
+    initial_margin_rate: f64,
+    maintenance_margin_rate: f64,
+    liquidation_fee_rate: f64,
+}
+
+impl Margin {
+    /// Constructs an instance of `Margin`.
+    ///
+    /// * `collateral` - The collateral deposited in the account, in the currency in which the
+    ///   asset's equity is denominated.
+    /// * `leverage` - The maximum leverage; the initial margin rate is its reciprocal.
+    /// * `maintenance_margin_rate` - The margin rate of the notional value below which the position
+    ///   is liquidated.
+    pub fn new(collateral: f64, leverage: f64, maintenance_margin_rate: f64) -> Self {
+        assert!(leverage > 0.0);
+        Self {
+            collateral,
+            initial_margin_rate: 1.0 / leverage,
+            maintenance_margin_rate,
+            liquidation_fee_rate: 0.0,
+        }
+    }
+
+    /// Sets the fee rate charged on the notional value of the liquidated position, in addition to
+    /// the taker fee. The default value is `0`.
+    pub fn liquidation_fee_rate(self, liquidation_fee_rate: f64) -> Self {
+        Self {
+            liquidation_fee_rate,
+            ..self
+        }
+    }
+
+    /// Returns the collateral.
+    pub fn collateral(&self) -> f64 {
+        self.collateral
+    }
+
+    /// Returns the maximum leverage.
+    pub fn leverage(&self) -> f64 {
+        1.0 / self.initial_margin_rate
+    }
+
+    /// Returns the initial margin required for the notional value.
+    pub fn initial_margin(&self, notional: f64) -> f64 {
+        notional.abs() * self.initial_margin_rate
+    }
+
+    /// Returns the maintenance margin required for the notional value.
+    pub fn maintenance_margin(&self, notional: f64) -> f64 {
+        notional.abs() * self.maintenance_margin_rate
+    }
+
+    /// Returns the liquidation fee charged on the notional value.
+    pub fn liquidation_fee(&self, notional: f64) -> f64 {
+        notional.abs() * self.liquidation_fee_rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Margin, LIQUIDATION_ORDER_ID};
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{Bot, OrdType, Side, Status, TimeInForce, BUY_EVENT, DEPTH_EVENT, SELL_EVENT},
+    };
+
+    #[test]
+    fn test_liquidation_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            let data = Data::from_slice(&[
+                event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                event(DEPTH_EVENT | BUY_EVENT, 100, 85.0, 10.0),
+                event(DEPTH_EVENT | BUY_EVENT, 101, 100.0, 0.0),
+                event(DEPTH_EVENT | SELL_EVENT, 102, 86.0, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 103, 101.0, 0.0),
+                event(DEPTH_EVENT | BUY_EVENT, 200, 82.0, 3.0),
+                event(DEPTH_EVENT | BUY_EVENT, 201, 81.0, 10.0),
+                // The mid-price falls to 84, where the equity of 15 falls below the maintenance
+                // margin of 21.
+                event(DEPTH_EVENT | BUY_EVENT, 202, 85.0, 0.0),
+                event(DEPTH_EVENT | BUY_EVENT, 300, 81.0, 10.0),
+            ]);
+            let mut hbt = backtest(
+                asset_builder(data, 1.0, 1.0)
+                    .exchange(exch_kind)
+                    .margin(Margin::new(100.0, 10.0, 0.05).liquidation_fee_rate(0.01)),
+            );
+
+            hbt.elapse(9).unwrap();
+            hbt.submit_buy_order(0, 1, 101.0, 5.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            assert_eq!(hbt.position(0), 5.0);
+
+            // The initial margin of 251.25 for the position of 25 exceeds the margin balance.
+            hbt.submit_buy_order(0, 2, 101.0, 20.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            assert_eq!(hbt.orders(0)[&2].status, Status::Expired);
+
+            // A resting order that is canceled upon liquidation.
+            hbt.submit_sell_order(0, 3, 110.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            assert_eq!(hbt.orders(0)[&3].status, Status::New);
+
+            hbt.elapse(180).unwrap();
+            assert_eq!(hbt.position(0), 5.0);
+
+            hbt.elapse(100).unwrap();
+            assert_eq!(hbt.position(0), 0.0);
+            assert_eq!(hbt.orders(0)[&3].status, Status::Canceled);
+            let liquidation = &hbt.orders(0)[&LIQUIDATION_ORDER_ID];
+            assert_eq!(liquidation.status, Status::Filled);
+            assert_eq!(liquidation.side, Side::Sell);
+            assert_eq!(liquidation.exec_price_tick, 81);
+            assert!((hbt.state_values(0).fee - 5.0 * 81.0 * 0.01).abs() < 1e-9);
+        }
+    }
+}
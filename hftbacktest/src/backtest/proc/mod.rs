@@ -1,12 +1,62 @@
+mod amend;
+mod auction;
+mod barexchange;
+mod bookcross;
+mod borrow;
+mod clockskew;
+mod constraint;
+mod expiry;
+mod fault;
+mod feed_local;
+mod funding;
+mod gap;
+mod halt;
+mod l1_local;
+mod l1_nopartialfillexchange;
 mod local;
+mod margin;
 mod nopartialfillexchange;
 mod partialfillexchange;
+mod postonly;
+mod priceband;
 mod proc;
+mod ratelimit;
+mod reduceonly;
+mod settlement;
+mod spread;
+mod stop;
+#[cfg(test)]
+pub(crate) mod test_utils;
+mod throttle;
 
-pub use local::Local;
+pub use amend::AmendRule;
+pub use auction::{calculate_uncross, Auction, Uncross};
+pub use barexchange::{BarExchange, IntraBarPath};
+pub use bookcross::BookCrossRule;
+pub use borrow::BorrowCost;
+pub use clockskew::ClockSkew;
+pub use constraint::{ConstraintViolation, OrderConstraints};
+pub use expiry::OrderExpiry;
+pub use fault::FaultInjector;
+pub use feed_local::{FeedLocal, LocalFeed};
+pub use funding::FundingSchedule;
+pub use gap::{FeedGap, GapAction};
+pub use halt::TradingHalt;
+pub use l1_local::{L1Feed, L1Local};
+pub use l1_nopartialfillexchange::L1NoPartialFillExchange;
+pub use local::{Local, CANCEL_ALL_ORDER_ID};
+pub use margin::{Margin, LIQUIDATION_ORDER_ID};
 pub use nopartialfillexchange::NoPartialFillExchange;
 pub use partialfillexchange::PartialFillExchange;
+pub use postonly::PostOnlyRule;
+pub use priceband::PriceBand;
 pub use proc::{LocalProcessor, Processor};
+pub use ratelimit::{RateLimit, RateLimitAction, RateLimitDecision, RateLimitRule};
+pub use reduceonly::ReduceOnlyRule;
+pub use settlement::{ContractExpiry, SETTLEMENT_ORDER_ID};
+pub use spread::{Spread, SpreadLeg};
+pub use stop::{StopOrders, StopTrigger};
+pub use throttle::SendThrottle;
 
 #[cfg(any(feature = "unstable_l3", doc))]
 mod l3_local;
@@ -15,6 +65,6 @@ mod l3_local;
 mod l3_nopartialfillexchange;
 
 #[cfg(any(feature = "unstable_l3", doc))]
-pub use l3_local::L3Local;
+pub use l3_local::{L3Feed, L3Local};
 #[cfg(any(feature = "unstable_l3", doc))]
 pub use l3_nopartialfillexchange::L3NoPartialFillExchange;
@@ -8,9 +8,33 @@ use std::{
 use crate::{
     backtest::{
         assettype::AssetType,
-        models::{LatencyModel, QueueModel},
+        models::{
+            sweep_price_tick,
+            AdverseFillFilter,
+            LatencyModel,
+            QueueModel,
+            RequestType,
+            TakerImpact,
+        },
         order::OrderBus,
-        proc::proc::Processor,
+        proc::{
+            amend::AmendRule,
+            auction::{calculate_uncross, Auction, Uncross},
+            bookcross::BookCrossRule,
+            expiry::OrderExpiry,
+            fault::{send_response, FaultInjector},
+            gap::{FeedGap, GapAction},
+            halt::TradingHalt,
+            local::CANCEL_ALL_ORDER_ID,
+            margin::LIQUIDATION_ORDER_ID,
+            postonly::PostOnlyRule,
+            priceband::PriceBand,
+            proc::Processor,
+            ratelimit::{RateLimit, RateLimitDecision},
+            reduceonly::{clamp_leaves_qty, ReduceOnlyRule},
+            settlement::{ContractExpiry, SETTLEMENT_ORDER_ID},
+            stop::{prepare_triggered, StopOrders, StopTrigger},
+        },
         reader::{Data, Reader},
         state::State,
         BacktestError,
@@ -18,6 +42,7 @@ use crate::{
     depth::{L2MarketDepth, MarketDepth, INVALID_MAX, INVALID_MIN},
     types::{
         Event,
+        OrdType,
         Order,
         OrderId,
         Side,
@@ -26,20 +51,27 @@ use crate::{
         EXCH_ASK_DEPTH_CLEAR_EVENT,
         EXCH_ASK_DEPTH_EVENT,
         EXCH_ASK_DEPTH_SNAPSHOT_EVENT,
+        EXCH_AUCTION_EVENT,
         EXCH_BID_DEPTH_CLEAR_EVENT,
         EXCH_BID_DEPTH_EVENT,
         EXCH_BID_DEPTH_SNAPSHOT_EVENT,
         EXCH_BUY_TRADE_EVENT,
         EXCH_DEPTH_CLEAR_EVENT,
         EXCH_EVENT,
+        EXCH_FUNDING_EVENT,
+        EXCH_HALT_EVENT,
+        EXCH_PRICE_BAND_EVENT,
         EXCH_SELL_TRADE_EVENT,
+        EXCH_SETTLEMENT_EVENT,
     },
 };
 
 /// The exchange model without partial fills.
 ///
-/// Support order types: [OrdType::Limit](crate::types::OrdType::Limit)
-/// Support time-in-force: [`TimeInForce::GTC`], [`TimeInForce::GTX`]
+/// Support order types: [OrdType::Limit](crate::types::OrdType::Limit),
+/// [OrdType::StopMarket](crate::types::OrdType::StopMarket),
+/// [OrdType::StopLimit](crate::types::OrdType::StopLimit)
+/// Support time-in-force: [`TimeInForce::GTC`], [`TimeInForce::GTX`], [`TimeInForce::GTD`]
 ///
 /// **Conditions for Full Execution**
 ///
@@ -55,11 +87,104 @@ use crate::{
 /// - Your order price < buy trade price
 /// - Your order is at the front of the queue && your order price == buy trade price
 ///
+/// An order that the best price crosses without a trade is filled at its price by default, at the
+/// crossing best price, or not until a trade print, as determined by the [`BookCrossRule`].
+///
 /// **Liquidity-Taking Order**
 ///
 /// Regardless of the quantity at the best, liquidity-taking orders will be fully executed at the
 /// best. Be aware that this may cause unrealistic fill simulations if you attempt to execute a
-/// large quantity.
+/// large quantity. If a [`TakerImpact`] is set, liquidity-taking orders are instead fully executed
+/// at the level where the book covers their quantity, plus the slippage given by the model.
+///
+/// **Flagged Trade**
+///
+/// Trades flagged as excluded, such as liquidation or block trades marked with
+/// [`LIQUIDATION_TRADE_FLAG`](crate::types::LIQUIDATION_TRADE_FLAG) or
+/// [`BLOCK_TRADE_FLAG`](crate::types::BLOCK_TRADE_FLAG), neither fill orders nor advance their
+/// queue positions, while the local still receives them. See
+/// [`set_excluded_trades`](Self::set_excluded_trades).
+///
+/// **Stop Order**
+///
+/// A stop order is held by the exchange until it is triggered: a buy stop order by a trade at or
+/// above the trigger price or the best bid rising to or above it, and a sell stop order by a trade
+/// at or below the trigger price or the best ask falling to or below it. Once triggered, a
+/// stop-limit order is processed as a limit order at its price, and a stop-market order as a
+/// liquidity-taking order at the opposite best. A stop order can instead be triggered by the mark
+/// price or the index price supplied by auxiliary events, per order or for the asset. See
+/// [`StopTrigger`].
+///
+/// **GTD Order**
+///
+/// A [`TimeInForce::GTD`] order is expired once the exchange time reaches its expiry, which is
+/// reported at the first event or order request at or after the expiry. See [`OrderExpiry`].
+///
+/// **Order Modification**
+///
+/// An order modified in place keeps its queue position as determined by the [`AmendRule`]; a price
+/// change always re-queues the order, which is then processed as a new order at the new price.
+/// Untriggered stop orders cannot be modified.
+///
+/// **Mass Cancel**
+///
+/// A mass-cancel message, whose order ID is [`CANCEL_ALL_ORDER_ID`], cancels all open orders,
+/// including untriggered stop orders, at once, and is acknowledged after the cancellations.
+///
+/// **Order Query**
+///
+/// An order query is answered with the current state of the order, even while the asset is
+/// inactive. An order that is no longer open, or has not yet arrived, is reported with
+/// [`Status::None`].
+///
+/// **Rate Limit**
+///
+/// If a [`RateLimit`] is set, order requests exceeding it are rejected or held until the budget is
+/// available.
+///
+/// **Fault Injection**
+///
+/// If a [`FaultInjector`] is set, order requests are randomly rejected, acknowledgements dropped,
+/// and execution reports duplicated.
+///
+/// **Price Band**
+///
+/// If a [`PriceBand`] is set or [`PRICE_BAND_EVENT`](crate::types::PRICE_BAND_EVENT)s are in the
+/// data, an order outside the band is rejected, and matching halts while the market is locked at a
+/// limit price.
+///
+/// **Trading Halt**
+///
+/// During a trading halt by [`HALT_EVENT`](crate::types::HALT_EVENT)s in the data or the
+/// [`TradingHalt`]'s halt periods, new orders and modifications are rejected and matching pauses.
+///
+/// **Contract Expiry**
+///
+/// If a [`ContractExpiry`] is set, all open orders are canceled and the position is settled at the
+/// settlement price upon the expiry, after which new orders and modifications are rejected.
+///
+/// **Auction**
+///
+/// [`AUCTION_EVENT`](crate::types::AUCTION_EVENT)s in the data drive the opening and closing
+/// auctions. During the call period, orders are collected without being matched, and at the
+/// uncross, they are filled at the uncross price, charged the taker fee. See [`Auction`].
+///
+/// **Post-Only Order**
+///
+/// A post-only order, [`TimeInForce::GTX`], that would take liquidity is rejected, canceled, or
+/// re-priced to one tick passive of the opposite best, as determined by the [`PostOnlyRule`].
+///
+/// **Reduce-Only Order**
+///
+/// A reduce-only order that would increase the position is rejected or clamped to the position, as
+/// determined by the [`ReduceOnlyRule`], when it is accepted and whenever the position changes
+/// while it rests in the book.
+///
+/// **Margin**
+///
+/// If a [`Margin`](crate::backtest::proc::Margin) is set, an order that increases the exposure
+/// beyond what the margin balance covers is rejected, and the position is liquidated when the
+/// margin balance falls below the maintenance margin.
 ///
 pub struct NoPartialFillExchange<AT, LM, QM, MD>
 where
@@ -87,6 +212,22 @@ where
     queue_model: QM,
 
     filled_orders: Vec<OrderId>,
+    feed_gap: FeedGap,
+    fill_filter: Option<AdverseFillFilter>,
+    stop_orders: StopOrders,
+    amend_rule: AmendRule,
+    reduce_only: ReduceOnlyRule,
+    post_only: PostOnlyRule,
+    book_cross: BookCrossRule,
+    rate_limit: Option<RateLimit>,
+    faults: Option<FaultInjector>,
+    impact: Option<TakerImpact>,
+    price_band: Option<PriceBand>,
+    auction: Auction,
+    halt: TradingHalt,
+    expiry: OrderExpiry,
+    contract_expiry: Option<ContractExpiry>,
+    excluded_trades: u64,
 }
 
 impl<AT, LM, QM, MD> NoPartialFillExchange<AT, LM, QM, MD>
@@ -120,6 +261,162 @@ where
             order_latency,
             queue_model,
             filled_orders: Default::default(),
+            feed_gap: Default::default(),
+            fill_filter: None,
+            stop_orders: Default::default(),
+            amend_rule: Default::default(),
+            reduce_only: Default::default(),
+            post_only: Default::default(),
+            book_cross: Default::default(),
+            rate_limit: None,
+            faults: None,
+            impact: None,
+            price_band: None,
+            auction: Default::default(),
+            halt: Default::default(),
+            expiry: Default::default(),
+            contract_expiry: None,
+            excluded_trades: 0,
+        }
+    }
+
+    /// Enables feed gap detection. If no exchange event arrives for longer than the `threshold`,
+    /// the market depth is considered stale, and depth events are skipped until the next snapshot,
+    /// from which the market depth is rebuilt. See [`FeedGap`].
+    pub fn set_feed_gap_threshold(&mut self, threshold: i64) {
+        self.feed_gap = FeedGap::new(threshold);
+    }
+
+    /// Sets the fill filter that marks a fraction of the fills triggered by the market trading
+    /// through a resting order's price as not occurring. See [`AdverseFillFilter`].
+    pub fn set_fill_filter(&mut self, filter: AdverseFillFilter) {
+        self.fill_filter = Some(filter);
+    }
+
+    /// Sets the rule that determines whether an order modified in place keeps its queue position.
+    /// The default is [`AmendRule::KeepOnSizeReduction`].
+    pub fn set_amend_rule(&mut self, rule: AmendRule) {
+        self.amend_rule = rule;
+    }
+
+    /// Sets the rule that determines how a reduce-only order that would increase the position is
+    /// handled. The default is [`ReduceOnlyRule::Reject`].
+    pub fn set_reduce_only_rule(&mut self, rule: ReduceOnlyRule) {
+        self.reduce_only = rule;
+    }
+
+    /// Sets the rule that determines how a post-only order that would take liquidity is handled.
+    /// The default is [`PostOnlyRule::Reject`].
+    pub fn set_post_only_rule(&mut self, rule: PostOnlyRule) {
+        self.post_only = rule;
+    }
+
+    /// Sets the rule that determines how a resting order that the best price on the opposite side
+    /// crosses without a trade is handled. The default is [`BookCrossRule::FillAtOrderPrice`].
+    pub fn set_book_cross_rule(&mut self, rule: BookCrossRule) {
+        self.book_cross = rule;
+    }
+
+    /// Sets the rate limit on order requests. Requests exceeding it are rejected or delayed. See
+    /// [`RateLimit`].
+    pub fn set_rate_limit(&mut self, rate_limit: RateLimit) {
+        self.rate_limit = Some(rate_limit);
+    }
+
+    /// Returns the rate limit on order requests, if any.
+    pub fn rate_limit(&self) -> Option<&RateLimit> {
+        self.rate_limit.as_ref()
+    }
+
+    /// Sets the fault injector that randomly rejects order requests, drops acknowledgements, and
+    /// duplicates execution reports. See [`FaultInjector`].
+    pub fn set_fault_injector(&mut self, faults: FaultInjector) {
+        self.faults = Some(faults);
+    }
+
+    /// Returns the fault injector, if any.
+    pub fn fault_injector(&self) -> Option<&FaultInjector> {
+        self.faults.as_ref()
+    }
+
+    /// Sets the market impact model that adjusts the execution price of liquidity-taking orders.
+    /// See [`TakerImpact`].
+    pub fn set_market_impact(&mut self, impact: TakerImpact) {
+        self.impact = Some(impact);
+    }
+
+    /// Returns the market impact model, if any.
+    pub fn market_impact(&self) -> Option<&TakerImpact> {
+        self.impact.as_ref()
+    }
+
+    /// Sets the price band that rejects orders outside it and halts matching while the market is
+    /// locked at a limit price. See [`PriceBand`].
+    pub fn set_price_band(&mut self, price_band: PriceBand) {
+        self.price_band = Some(price_band);
+    }
+
+    /// Returns the price band, if any.
+    pub fn price_band(&self) -> Option<&PriceBand> {
+        self.price_band.as_ref()
+    }
+
+    /// Sets the trading halt, which rejects new orders and pauses matching during the halt periods
+    /// in addition to the halts by [`HALT_EVENT`](crate::types::HALT_EVENT)s in the data. See
+    /// [`TradingHalt`].
+    pub fn set_trading_halt(&mut self, halt: TradingHalt) {
+        self.halt = halt;
+    }
+
+    /// Returns the trading halt.
+    pub fn trading_halt(&self) -> &TradingHalt {
+        &self.halt
+    }
+
+    /// Sets the contract expiry, upon which all open orders are canceled and the position is
+    /// settled. See [`ContractExpiry`].
+    pub fn set_contract_expiry(&mut self, contract_expiry: ContractExpiry) {
+        self.contract_expiry = Some(contract_expiry);
+    }
+
+    /// Sets the trigger price source of the stop orders that follow the asset's configuration. See
+    /// [`StopTrigger`].
+    pub fn set_stop_trigger(&mut self, trigger: StopTrigger) {
+        self.stop_orders.set_trigger(trigger);
+    }
+
+    /// Excludes the trades flagged with any of the `flags` from the fill and queue position
+    /// simulation, as they don't represent the flow that the resting orders compete for. For
+    /// example, `LIQUIDATION_TRADE_FLAG | BLOCK_TRADE_FLAG` excludes both liquidation and block
+    /// trades. By default, no trades are excluded.
+    pub fn set_excluded_trades(&mut self, flags: u64) {
+        self.excluded_trades = flags;
+    }
+
+    /// Returns the contract expiry, if any.
+    pub fn contract_expiry(&self) -> Option<&ContractExpiry> {
+        self.contract_expiry.as_ref()
+    }
+
+    /// Returns the number of fills marked as not occurring by the fill filter.
+    pub fn missed_fill_count(&self) -> usize {
+        self.fill_filter.as_ref().map(|f| f.missed()).unwrap_or(0)
+    }
+
+    fn check_trade_through(&mut self, through: i64) -> bool {
+        // Matching halts while the market is locked at a limit price.
+        if self.price_band.as_ref().is_some_and(|band| {
+            band.is_locked(
+                self.depth.tick_size(),
+                self.depth.best_bid_tick(),
+                self.depth.best_ask_tick(),
+            )
+        }) {
+            return false;
+        }
+        match self.fill_filter.as_mut() {
+            Some(filter) => filter.check(through),
+            None => true,
         }
     }
 
@@ -128,6 +425,67 @@ where
         mut order: Order,
         recv_timestamp: i64,
     ) -> Result<(), BacktestError> {
+        if let Some(rate_limit) = self.rate_limit.as_mut() {
+            let req_type = match order.req {
+                Status::New => RequestType::New,
+                Status::Canceled => RequestType::Cancel,
+                Status::Replaced => RequestType::Modify,
+                Status::Queried => RequestType::Query,
+                _ => return Err(BacktestError::InvalidOrderRequest),
+            };
+            match rate_limit.check(req_type, recv_timestamp) {
+                RateLimitDecision::Accept => {}
+                RateLimitDecision::Reject => {
+                    // The request status of a query marks its response, so the rejection is
+                    // reported by the order status instead.
+                    if order.req == Status::Queried {
+                        order.status = Status::Rejected;
+                    } else {
+                        order.req = Status::Rejected;
+                    }
+                    order.exch_timestamp = recv_timestamp;
+                    let local_recv_timestamp =
+                        recv_timestamp + self.order_latency.response(recv_timestamp, &order);
+                    send_response(
+                        self.faults.as_mut(),
+                        &mut self.orders_to,
+                        order,
+                        local_recv_timestamp,
+                    );
+                    return Ok(());
+                }
+                RateLimitDecision::Delay(timestamp) => {
+                    self.orders_from.append(order, timestamp);
+                    return Ok(());
+                }
+            }
+        }
+        // Queries are answered regardless of the asset's state.
+        if order.req == Status::Queried {
+            return self.ack_query(order, recv_timestamp);
+        }
+        // New orders and modifications are rejected during a trading halt and after the contract
+        // expiry, while cancels are accepted.
+        let inactive = (self.halt.is_halted()
+            || self
+                .contract_expiry
+                .as_ref()
+                .is_some_and(|expiry| expiry.is_expired()))
+            && order.req != Status::Canceled;
+        if inactive || self.faults.as_mut().is_some_and(|faults| faults.reject()) {
+            order.req = Status::Rejected;
+            order.exch_timestamp = recv_timestamp;
+            let local_recv_timestamp =
+                recv_timestamp + self.order_latency.response(recv_timestamp, &order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                order,
+                local_recv_timestamp,
+            );
+            return Ok(());
+        }
+
         // Processes a new order.
         if order.req == Status::New {
             order.req = Status::None;
@@ -137,6 +495,11 @@ where
         else if order.req == Status::Canceled {
             order.req = Status::None;
             self.ack_cancel(order, recv_timestamp)?;
+        }
+        // Processes a modify order.
+        else if order.req == Status::Replaced {
+            order.req = Status::None;
+            self.ack_modify(order, recv_timestamp)?;
         } else {
             return Err(BacktestError::InvalidOrderRequest);
         }
@@ -151,6 +514,9 @@ where
         timestamp: i64,
     ) -> Result<(), BacktestError> {
         if order.price_tick < price_tick {
+            if !self.check_trade_through(price_tick - order.price_tick) {
+                return Ok(());
+            }
             self.filled_orders.push(order.order_id);
             return self.fill(order, timestamp, true, order.price_tick);
         } else if order.price_tick == price_tick {
@@ -172,6 +538,9 @@ where
         timestamp: i64,
     ) -> Result<(), BacktestError> {
         if order.price_tick > price_tick {
+            if !self.check_trade_through(order.price_tick - price_tick) {
+                return Ok(());
+            }
             self.filled_orders.push(order.order_id);
             return self.fill(order, timestamp, true, order.price_tick);
         } else if order.price_tick == price_tick {
@@ -201,9 +570,14 @@ where
 
         order.maker = maker;
         if maker {
-            order.exec_price_tick = order.price_tick;
-        } else {
             order.exec_price_tick = exec_price_tick;
+        } else {
+            order.exec_price_tick = match self.impact.as_mut() {
+                Some(impact) => {
+                    impact.exec_price_tick(&self.depth, order, exec_price_tick, timestamp, true)
+                }
+                None => exec_price_tick,
+            };
         }
 
         order.exec_qty = order.leaves_qty;
@@ -214,7 +588,12 @@ where
             order.exch_timestamp + self.order_latency.response(timestamp, &order);
 
         self.state.apply_fill(order);
-        self.orders_to.append(order.clone(), local_recv_timestamp);
+        send_response(
+            self.faults.as_mut(),
+            &mut self.orders_to,
+            order.clone(),
+            local_recv_timestamp,
+        );
         Ok(())
     }
 
@@ -268,6 +647,9 @@ where
         new_best_tick: i64,
         timestamp: i64,
     ) -> Result<(), BacktestError> {
+        if !self.book_cross.fills_on_cross() {
+            return Ok(());
+        }
         // If the best has been significantly updated compared to the previous best, it would be
         // better to iterate orders dict instead of order price ladder.
         {
@@ -277,18 +659,30 @@ where
                 || (orders_borrowed.len() as i64) < new_best_tick - prev_best_tick
             {
                 for (_, order) in orders_borrowed.iter_mut() {
-                    if order.side == Side::Sell && order.price_tick <= new_best_tick {
+                    if order.side == Side::Sell
+                        && order.price_tick <= new_best_tick
+                        && self.check_trade_through(new_best_tick - order.price_tick + 1)
+                    {
                         self.filled_orders.push(order.order_id);
-                        self.fill(order, timestamp, true, order.price_tick)?;
+                        let exec_price_tick = self
+                            .book_cross
+                            .exec_price_tick(order.price_tick, new_best_tick);
+                        self.fill(order, timestamp, true, exec_price_tick)?;
                     }
                 }
             } else {
                 for t in (prev_best_tick + 1)..=new_best_tick {
                     if let Some(order_ids) = self.sell_orders.get(&t) {
                         for order_id in order_ids.clone().iter() {
+                            if !self.check_trade_through(new_best_tick - t + 1) {
+                                continue;
+                            }
                             self.filled_orders.push(*order_id);
                             let order = orders_borrowed.get_mut(order_id).unwrap();
-                            self.fill(order, timestamp, true, order.price_tick)?;
+                            let exec_price_tick = self
+                                .book_cross
+                                .exec_price_tick(order.price_tick, new_best_tick);
+                            self.fill(order, timestamp, true, exec_price_tick)?;
                         }
                     }
                 }
@@ -304,6 +698,9 @@ where
         new_best_tick: i64,
         timestamp: i64,
     ) -> Result<(), BacktestError> {
+        if !self.book_cross.fills_on_cross() {
+            return Ok(());
+        }
         // If the best has been significantly updated compared to the previous best, it would be
         // better to iterate orders dict instead of order price ladder.
         {
@@ -313,18 +710,30 @@ where
                 || (orders_borrowed.len() as i64) < prev_best_tick - new_best_tick
             {
                 for (_, order) in orders_borrowed.iter_mut() {
-                    if order.side == Side::Buy && order.price_tick >= new_best_tick {
+                    if order.side == Side::Buy
+                        && order.price_tick >= new_best_tick
+                        && self.check_trade_through(order.price_tick - new_best_tick + 1)
+                    {
                         self.filled_orders.push(order.order_id);
-                        self.fill(order, timestamp, true, order.price_tick)?;
+                        let exec_price_tick = self
+                            .book_cross
+                            .exec_price_tick(order.price_tick, new_best_tick);
+                        self.fill(order, timestamp, true, exec_price_tick)?;
                     }
                 }
             } else {
                 for t in new_best_tick..prev_best_tick {
                     if let Some(order_ids) = self.buy_orders.get(&t) {
                         for order_id in order_ids.clone().iter() {
+                            if !self.check_trade_through(t - new_best_tick + 1) {
+                                continue;
+                            }
                             self.filled_orders.push(*order_id);
                             let order = orders_borrowed.get_mut(order_id).unwrap();
-                            self.fill(order, timestamp, true, order.price_tick)?;
+                            let exec_price_tick = self
+                                .book_cross
+                                .exec_price_tick(order.price_tick, new_best_tick);
+                            self.fill(order, timestamp, true, exec_price_tick)?;
                         }
                     }
                 }
@@ -335,20 +744,470 @@ where
     }
 
     fn ack_new(&mut self, mut order: Order, timestamp: i64) -> Result<(), BacktestError> {
-        if self.orders.borrow().contains_key(&order.order_id) {
+        if self.orders.borrow().contains_key(&order.order_id)
+            || self.stop_orders.contains(&order.order_id)
+            || self.auction.contains(&order.order_id)
+        {
             return Err(BacktestError::OrderIdExist);
         }
 
+        if OrderExpiry::is_expired(&order, timestamp) {
+            // The GTD order has already expired when it arrives at the exchange.
+            order.status = Status::Expired;
+            order.exch_timestamp = timestamp;
+            let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                order,
+                local_recv_timestamp,
+            );
+            return Ok(());
+        }
+        self.expiry.insert(&order);
+
+        if order.order_type.is_stop() {
+            // The exchange holds the stop order until it is triggered.
+            order.status = Status::New;
+            order.exch_timestamp = timestamp;
+            let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                order.clone(),
+                local_recv_timestamp,
+            );
+            self.stop_orders.insert(order);
+            // The stop order is triggered immediately if the market is already beyond the trigger
+            // price.
+            return self.check_stop_orders(None, timestamp);
+        }
+        self.accept_order(order, timestamp)
+    }
+
+    fn check_stop_orders(
+        &mut self,
+        trade_tick: Option<i64>,
+        timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        let best_bid_tick = self.depth.best_bid_tick();
+        let best_ask_tick = self.depth.best_ask_tick();
+        for mut order in self
+            .stop_orders
+            .take_triggered(trade_tick, best_bid_tick, best_ask_tick)
+        {
+            if prepare_triggered(&mut order, best_bid_tick, best_ask_tick) {
+                self.accept_order(order, timestamp)?;
+            } else {
+                // There is no liquidity to take.
+                order.status = Status::Expired;
+                order.exch_timestamp = timestamp;
+                let local_recv_timestamp =
+                    timestamp + self.order_latency.response(timestamp, &order);
+                send_response(
+                    self.faults.as_mut(),
+                    &mut self.orders_to,
+                    order,
+                    local_recv_timestamp,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn has_initial_margin(&self, order: &Order) -> bool {
+        if self.state.margin.is_none() {
+            return true;
+        }
+        let (open_buy_qty, open_sell_qty) =
+            self.orders
+                .borrow()
+                .values()
+                .fold(
+                    (0.0, 0.0),
+                    |(buy_qty, sell_qty), open_order| match open_order.side {
+                        Side::Buy => (buy_qty + open_order.leaves_qty, sell_qty),
+                        _ => (buy_qty, sell_qty + open_order.leaves_qty),
+                    },
+                );
+        let mid = (self.depth.best_bid() + self.depth.best_ask()) / 2.0;
+        self.state.has_initial_margin(
+            mid,
+            open_buy_qty,
+            open_sell_qty,
+            order.side,
+            order.leaves_qty,
+        )
+    }
+
+    fn is_shortable(&self, order: &Order) -> bool {
+        let open_sell_qty = self
+            .orders
+            .borrow()
+            .values()
+            .filter(|open_order| open_order.side == Side::Sell)
+            .map(|open_order| open_order.leaves_qty)
+            .sum();
+        self.state.is_shortable(open_sell_qty, order)
+    }
+
+    fn open_auction(&mut self) {
+        // The resting orders participate in the auction in the time priority.
+        let mut orders: Vec<Order> = self
+            .orders
+            .borrow_mut()
+            .drain()
+            .map(|(_, order)| order)
+            .collect();
+        orders.sort_unstable_by_key(|order| (order.exch_timestamp, order.order_id));
+        self.buy_orders.clear();
+        self.sell_orders.clear();
+        self.auction.open();
+        for order in orders {
+            self.auction.insert(order);
+        }
+    }
+
+    fn collect_auction_order(
+        &mut self,
+        mut order: Order,
+        timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        // An order that must be executed immediately cannot be executed during the call period.
+        if order.time_in_force == TimeInForce::IOC || order.time_in_force == TimeInForce::FOK {
+            order.status = Status::Expired;
+        } else {
+            order.status = Status::New;
+        }
+        order.exch_timestamp = timestamp;
+        let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+        send_response(
+            self.faults.as_mut(),
+            &mut self.orders_to,
+            order.clone(),
+            local_recv_timestamp,
+        );
+        if order.status == Status::New {
+            self.auction.insert(order);
+        }
+        Ok(())
+    }
+
+    fn uncross_auction(&mut self, price: f64, timestamp: i64) -> Result<(), BacktestError> {
+        let orders = self.auction.close();
+        let uncross = if price > 0.0 {
+            // The imbalance at the published uncross price is unknown.
+            Some(Uncross {
+                price_tick: (price / self.depth.tick_size()).round() as i64,
+                fill_buy_at_price: false,
+                fill_sell_at_price: false,
+            })
+        } else {
+            calculate_uncross(&self.depth, &orders)
+        };
+
+        // The auction fills are at the uncross price, without the market impact.
+        let impact = self.impact.take();
+        for mut order in orders {
+            match uncross {
+                Some(uncross) if uncross.is_filled(&order) => {
+                    self.fill(&mut order, timestamp, false, uncross.price_tick)?;
+                }
+                _ => {
+                    // The unfilled order rests in the book as the continuous trading resumes.
+                    self.queue_model.new_order(&mut order, &self.depth);
+                    if order.side == Side::Buy {
+                        self.buy_orders
+                            .entry(order.price_tick)
+                            .or_insert(HashSet::new())
+                            .insert(order.order_id);
+                    } else {
+                        self.sell_orders
+                            .entry(order.price_tick)
+                            .or_insert(HashSet::new())
+                            .insert(order.order_id);
+                    }
+                    self.orders.borrow_mut().insert(order.order_id, order);
+                }
+            }
+        }
+        self.impact = impact;
+        Ok(())
+    }
+
+    fn check_price_band(&mut self, order: &Order) -> bool {
+        match self.price_band.as_mut() {
+            Some(band) => band.check(
+                order,
+                self.depth.tick_size(),
+                self.depth.best_bid_tick(),
+                self.depth.best_ask_tick(),
+            ),
+            None => true,
+        }
+    }
+
+    fn cancel_all_orders(&mut self, timestamp: i64) {
+        let mut orders: Vec<Order> = self
+            .orders
+            .borrow_mut()
+            .drain()
+            .map(|(_, order)| order)
+            .collect();
+        orders.sort_unstable_by_key(|order| order.order_id);
+        self.buy_orders.clear();
+        self.sell_orders.clear();
+        for mut order in orders
+            .into_iter()
+            .chain(self.stop_orders.take_all())
+            .chain(self.auction.take_all())
+        {
+            order.status = Status::Canceled;
+            order.exch_timestamp = timestamp;
+            let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                order,
+                local_recv_timestamp,
+            );
+        }
+    }
+
+    fn check_reduce_only(&self, order: &mut Order) -> bool {
+        if !order.reduce_only {
+            return true;
+        }
+        match self.reduce_only.allowed_qty(
+            order,
+            self.state.reducible_position(order.side),
+            self.depth.lot_size(),
+        ) {
+            Some(leaves_qty) => {
+                clamp_leaves_qty(order, leaves_qty);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn check_reduce_only_orders(&mut self, timestamp: i64) {
+        let mut order_ids: Vec<OrderId> = self
+            .orders
+            .borrow()
+            .values()
+            .filter(|order| order.reduce_only)
+            .map(|order| order.order_id)
+            .collect();
+        order_ids.sort_unstable();
+        for order_id in order_ids {
+            let (mut order, expired) = {
+                let mut orders = self.orders.borrow_mut();
+                let order = orders.get_mut(&order_id).unwrap();
+                let position = self.state.reducible_position(order.side);
+                match self
+                    .reduce_only
+                    .allowed_qty(order, position, self.depth.lot_size())
+                {
+                    Some(leaves_qty) if leaves_qty >= order.leaves_qty => continue,
+                    Some(leaves_qty) => {
+                        clamp_leaves_qty(order, leaves_qty);
+                        (order.clone(), false)
+                    }
+                    None => (orders.remove(&order_id).unwrap(), true),
+                }
+            };
+            if expired {
+                // The resting order no longer reduces the position.
+                if order.side == Side::Buy {
+                    self.buy_orders
+                        .get_mut(&order.price_tick)
+                        .unwrap()
+                        .remove(&order_id);
+                } else {
+                    self.sell_orders
+                        .get_mut(&order.price_tick)
+                        .unwrap()
+                        .remove(&order_id);
+                }
+                order.status = Status::Expired;
+            }
+            order.exch_timestamp = timestamp;
+            let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                order,
+                local_recv_timestamp,
+            );
+        }
+    }
+
+    fn expire_orders(&mut self, timestamp: i64) {
+        for order_id in self.expiry.take_expired(timestamp) {
+            // The order may have already been executed or canceled, and its order ID reused.
+            let expired = move |order: &Order| OrderExpiry::is_expired(order, timestamp);
+            let mut order = if self.orders.borrow().get(&order_id).is_some_and(expired) {
+                let order = self.orders.borrow_mut().remove(&order_id).unwrap();
+                if order.side == Side::Buy {
+                    self.buy_orders
+                        .get_mut(&order.price_tick)
+                        .unwrap()
+                        .remove(&order_id);
+                } else {
+                    self.sell_orders
+                        .get_mut(&order.price_tick)
+                        .unwrap()
+                        .remove(&order_id);
+                }
+                order
+            } else if self.stop_orders.get(&order_id).is_some_and(expired) {
+                self.stop_orders.remove(&order_id).unwrap()
+            } else if self.auction.get(&order_id).is_some_and(expired) {
+                self.auction.remove(&order_id).unwrap()
+            } else {
+                continue;
+            };
+            order.status = Status::Expired;
+            order.exch_timestamp = timestamp;
+            let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                order,
+                local_recv_timestamp,
+            );
+        }
+    }
+
+    fn check_halt(&mut self, timestamp: i64) {
+        if self.halt.update(timestamp) && self.halt.cancels_resting() {
+            self.cancel_all_orders(timestamp);
+        }
+    }
+
+    fn check_contract_expiry(&mut self, timestamp: i64) -> Result<(), BacktestError> {
+        let Some(expiry) = self.contract_expiry.as_mut() else {
+            return Ok(());
+        };
+        if !expiry.update(timestamp) {
+            return Ok(());
+        }
+        let mid = (self.depth.best_bid() + self.depth.best_ask()) / 2.0;
+        let settlement_price = expiry.settlement_price_or(mid);
+
+        self.cancel_all_orders(timestamp);
+        if !settlement_price.is_finite() {
+            return Ok(());
+        }
+
+        // Settles each leg of the position at the settlement price.
+        let exec_price_tick = (settlement_price / self.depth.tick_size()).round() as i64;
+        for (side, qty) in [
+            (Side::Sell, self.state.state_values.long_position),
+            (Side::Buy, self.state.state_values.short_position),
+        ] {
+            if qty <= 0.0 {
+                continue;
+            }
+            let mut order = Order::new(
+                SETTLEMENT_ORDER_ID,
+                exec_price_tick,
+                self.depth.tick_size(),
+                qty,
+                side,
+                OrdType::Market,
+                TimeInForce::IOC,
+            );
+            order.reduce_only = true;
+            // Filled as a maker order, so that the market impact doesn't apply.
+            self.fill(&mut order, timestamp, true, exec_price_tick)?;
+        }
+        Ok(())
+    }
+
+    fn check_liquidation(&mut self, timestamp: i64) -> Result<(), BacktestError> {
+        let mid = (self.depth.best_bid() + self.depth.best_ask()) / 2.0;
+        if !self.state.is_margin_breached(mid) {
+            return Ok(());
+        }
+
+        self.cancel_all_orders(timestamp);
+
+        // Force-closes the position by sweeping the book.
+        let position = self.state.state_values.position;
+        let side = if position > 0.0 {
+            Side::Sell
+        } else {
+            Side::Buy
+        };
+        let qty = position.abs();
+        let Some(exec_price_tick) = sweep_price_tick(&self.depth, side, qty) else {
+            return Ok(());
+        };
+        let mut order = Order::new(
+            LIQUIDATION_ORDER_ID,
+            exec_price_tick,
+            self.depth.tick_size(),
+            qty,
+            side,
+            OrdType::Market,
+            TimeInForce::IOC,
+        );
+        // The liquidation only closes the position, which matters in the hedge position mode.
+        order.reduce_only = true;
+        self.fill(&mut order, timestamp, false, exec_price_tick)
+    }
+
+    fn accept_order(&mut self, mut order: Order, timestamp: i64) -> Result<(), BacktestError> {
+        if !self.check_reduce_only(&mut order)
+            || !self.has_initial_margin(&order)
+            || !self.is_shortable(&order)
+            || !self.check_price_band(&order)
+        {
+            // The exchange rejects the order that would increase the position despite being
+            // reduce-only, for insufficient margin, for a short sale of an asset that is not
+            // shortable, or for a price outside the band.
+            order.status = Status::Expired;
+            order.exch_timestamp = timestamp;
+            let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                order,
+                local_recv_timestamp,
+            );
+            return Ok(());
+        }
+
+        if self.auction.is_open() {
+            return self.collect_auction_order(order, timestamp);
+        }
+
+        if order.time_in_force == TimeInForce::GTX {
+            // The crossing post-only order rests one tick passive of the opposite best instead.
+            self.post_only.reprice(
+                &mut order,
+                self.depth.best_bid_tick(),
+                self.depth.best_ask_tick(),
+            );
+        }
+
         if order.side == Side::Buy {
             // Checks if the buy order price is greater than or equal to the current best ask.
             if order.price_tick >= self.depth.best_ask_tick() {
                 if order.time_in_force == TimeInForce::GTX {
-                    order.status = Status::Expired;
+                    order.status = self.post_only.crossing_status();
 
                     order.exch_timestamp = timestamp;
                     let local_recv_timestamp =
                         timestamp + self.order_latency.response(timestamp, &order);
-                    self.orders_to.append(order.clone(), local_recv_timestamp);
+                    send_response(
+                        self.faults.as_mut(),
+                        &mut self.orders_to,
+                        order.clone(),
+                        local_recv_timestamp,
+                    );
                     Ok(())
                 } else {
                     // Takes the market.
@@ -367,7 +1226,12 @@ where
                 order.exch_timestamp = timestamp;
                 let local_recv_timestamp =
                     timestamp + self.order_latency.response(timestamp, &order);
-                self.orders_to.append(order.clone(), local_recv_timestamp);
+                send_response(
+                    self.faults.as_mut(),
+                    &mut self.orders_to,
+                    order.clone(),
+                    local_recv_timestamp,
+                );
                 self.orders.borrow_mut().insert(order.order_id, order);
                 Ok(())
             }
@@ -375,12 +1239,17 @@ where
             // Checks if the sell order price is less than or equal to the current best bid.
             if order.price_tick <= self.depth.best_bid_tick() {
                 if order.time_in_force == TimeInForce::GTX {
-                    order.status = Status::Expired;
+                    order.status = self.post_only.crossing_status();
 
                     order.exch_timestamp = timestamp;
                     let local_recv_timestamp =
                         timestamp + self.order_latency.response(timestamp, &order);
-                    self.orders_to.append(order.clone(), local_recv_timestamp);
+                    send_response(
+                        self.faults.as_mut(),
+                        &mut self.orders_to,
+                        order.clone(),
+                        local_recv_timestamp,
+                    );
                     Ok(())
                 } else {
                     // Takes the market.
@@ -399,7 +1268,12 @@ where
                 order.exch_timestamp = timestamp;
                 let local_recv_timestamp =
                     timestamp + self.order_latency.response(timestamp, &order);
-                self.orders_to.append(order.clone(), local_recv_timestamp);
+                send_response(
+                    self.faults.as_mut(),
+                    &mut self.orders_to,
+                    order.clone(),
+                    local_recv_timestamp,
+                );
                 self.orders.borrow_mut().insert(order.order_id, order);
                 Ok(())
             }
@@ -407,6 +1281,40 @@ where
     }
 
     fn ack_cancel(&mut self, mut order: Order, timestamp: i64) -> Result<(), BacktestError> {
+        if order.order_id == CANCEL_ALL_ORDER_ID {
+            // Cancels all open orders by the mass-cancel message, and then acknowledges it.
+            self.cancel_all_orders(timestamp);
+            order.status = Status::Canceled;
+            order.exch_timestamp = timestamp;
+            let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                order,
+                local_recv_timestamp,
+            );
+            return Ok(());
+        }
+
+        if let Some(mut exch_order) = self
+            .stop_orders
+            .remove(&order.order_id)
+            .or_else(|| self.auction.remove(&order.order_id))
+        {
+            // Cancels the untriggered stop order or the order collected in the auction.
+            exch_order.status = Status::Canceled;
+            exch_order.exch_timestamp = timestamp;
+            let local_recv_timestamp =
+                timestamp + self.order_latency.response(timestamp, &exch_order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                exch_order,
+                local_recv_timestamp,
+            );
+            return Ok(());
+        }
+
         let exch_order = {
             let mut order_borrowed = self.orders.borrow_mut();
             order_borrowed.remove(&order.order_id)
@@ -416,7 +1324,12 @@ where
             order.req = Status::Rejected;
             order.exch_timestamp = timestamp;
             let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
-            self.orders_to.append(order, local_recv_timestamp);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                order,
+                local_recv_timestamp,
+            );
             return Ok(());
         }
 
@@ -438,149 +1351,136 @@ where
         exch_order.status = Status::Canceled;
         exch_order.exch_timestamp = timestamp;
         let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &exch_order);
-        self.orders_to
-            .append(exch_order.clone(), local_recv_timestamp);
+        send_response(
+            self.faults.as_mut(),
+            &mut self.orders_to,
+            exch_order.clone(),
+            local_recv_timestamp,
+        );
+        Ok(())
+    }
+
+    fn ack_query(&mut self, mut order: Order, timestamp: i64) -> Result<(), BacktestError> {
+        // Reports the current state of the open order, or `Status::None` if the exchange doesn't
+        // have it.
+        let exch_order = self
+            .orders
+            .borrow()
+            .get(&order.order_id)
+            .or_else(|| self.stop_orders.get(&order.order_id))
+            .or_else(|| self.auction.get(&order.order_id))
+            .cloned();
+        let local_timestamp = order.local_timestamp;
+        let mut order = exch_order.unwrap_or_else(|| {
+            order.status = Status::None;
+            order
+        });
+        order.req = Status::Queried;
+        order.local_timestamp = local_timestamp;
+        order.exch_timestamp = timestamp;
+        let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+        send_response(
+            self.faults.as_mut(),
+            &mut self.orders_to,
+            order,
+            local_recv_timestamp,
+        );
         Ok(())
     }
 
     fn ack_modify(&mut self, mut order: Order, timestamp: i64) -> Result<(), BacktestError> {
-        let mut exch_order = {
-            let mut order_borrowed = self.orders.borrow_mut();
-            let exch_order = order_borrowed.remove(&order.order_id);
+        if let Some(exch_order) = self.auction.get_mut(&order.order_id) {
+            // The order collected in the auction is modified in place, as it is not yet matched.
+            exch_order.price_tick = order.price_tick;
+            exch_order.qty = order.qty;
+            exch_order.leaves_qty = order.qty;
+            exch_order.local_timestamp = order.local_timestamp;
+            exch_order.exch_timestamp = timestamp;
+            let exch_order = exch_order.clone();
+            let local_recv_timestamp =
+                timestamp + self.order_latency.response(timestamp, &exch_order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                exch_order,
+                local_recv_timestamp,
+            );
+            return Ok(());
+        }
 
-            // The order can be already deleted due to fill or expiration.
-            if exch_order.is_none() {
-                order.req = Status::Rejected;
-                order.exch_timestamp = timestamp;
-                let local_recv_timestamp =
-                    timestamp + self.order_latency.response(timestamp, &order);
-                self.orders_to.append(order, local_recv_timestamp);
-                return Ok(());
-            }
+        let exch_order = {
+            let mut order_borrowed = self.orders.borrow_mut();
+            order_borrowed.remove(&order.order_id)
+        };
 
-            exch_order.unwrap()
+        // The order can be already deleted due to fill or expiration. An untriggered stop order
+        // cannot be modified either.
+        let Some(mut exch_order) = exch_order else {
+            order.req = Status::Rejected;
+            order.exch_timestamp = timestamp;
+            let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &order);
+            send_response(
+                self.faults.as_mut(),
+                &mut self.orders_to,
+                order,
+                local_recv_timestamp,
+            );
+            return Ok(());
         };
 
-        let prev_price_tick = exch_order.price_tick;
+        // Removes the order from its price level. It is put back if it keeps its queue position.
+        if exch_order.side == Side::Buy {
+            self.buy_orders
+                .get_mut(&exch_order.price_tick)
+                .unwrap()
+                .remove(&exch_order.order_id);
+        } else {
+            self.sell_orders
+                .get_mut(&exch_order.price_tick)
+                .unwrap()
+                .remove(&exch_order.order_id);
+        }
+
+        let keep_q_pos =
+            self.amend_rule
+                .keeps_queue_position(&exch_order, order.price_tick, order.qty);
         exch_order.price_tick = order.price_tick;
         // No partial fill occurs.
         exch_order.qty = order.qty;
-        // The initialization of the order queue position may not occur when the modified quantity
-        // is smaller than the previous quantity, depending on the exchanges. It may need to
-        // implement exchange-specific specialization.
-        let init_q_pos = true;
+        exch_order.leaves_qty = order.qty;
+        exch_order.local_timestamp = order.local_timestamp;
 
-        if exch_order.side == Side::Buy {
-            // Checks if the buy order price is greater than or equal to the current best ask.
-            if exch_order.price_tick >= self.depth.best_ask_tick() {
-                self.buy_orders
-                    .get_mut(&prev_price_tick)
-                    .unwrap()
-                    .remove(&exch_order.order_id);
-
-                if exch_order.time_in_force == TimeInForce::GTX {
-                    exch_order.status = Status::Expired;
-                } else {
-                    // Takes the market.
-                    return self.fill(
-                        &mut exch_order,
-                        timestamp,
-                        false,
-                        self.depth.best_ask_tick(),
-                    );
-                }
-
-                exch_order.exch_timestamp = timestamp;
-                let local_recv_timestamp =
-                    timestamp + self.order_latency.response(timestamp, &exch_order);
-                self.orders_to
-                    .append(exch_order.clone(), local_recv_timestamp);
-                Ok(())
-            } else {
-                // The exchange accepts this order.
-                if prev_price_tick != exch_order.price_tick {
-                    self.buy_orders
-                        .get_mut(&prev_price_tick)
-                        .unwrap()
-                        .remove(&exch_order.order_id);
-                    self.buy_orders
-                        .entry(exch_order.price_tick)
-                        .or_insert(HashSet::new())
-                        .insert(exch_order.order_id);
-                }
-                if init_q_pos || prev_price_tick != exch_order.price_tick {
-                    // Initializes the order's queue position.
-                    self.queue_model.new_order(&mut exch_order, &self.depth);
-                }
-                exch_order.status = Status::New;
-
-                exch_order.exch_timestamp = timestamp;
-                let local_recv_timestamp =
-                    timestamp + self.order_latency.response(timestamp, &exch_order);
-                self.orders_to
-                    .append(exch_order.clone(), local_recv_timestamp);
-
-                let mut order_borrowed = self.orders.borrow_mut();
-                order_borrowed.insert(exch_order.order_id, exch_order);
+        if !keep_q_pos {
+            // The modified order is matched as a new order, which may take the market or be
+            // expired, and otherwise is placed at the back of the queue.
+            return self.accept_order(exch_order, timestamp);
+        }
 
-                Ok(())
-            }
+        // The order keeps its queue position, as its price remains unchanged.
+        if exch_order.side == Side::Buy {
+            self.buy_orders
+                .entry(exch_order.price_tick)
+                .or_insert(HashSet::new())
+                .insert(exch_order.order_id);
         } else {
-            // Checks if the sell order price is less than or equal to the current best bid.
-            if exch_order.price_tick <= self.depth.best_bid_tick() {
-                self.sell_orders
-                    .get_mut(&prev_price_tick)
-                    .unwrap()
-                    .remove(&exch_order.order_id);
-
-                if exch_order.time_in_force == TimeInForce::GTX {
-                    exch_order.status = Status::Expired;
-                } else {
-                    // Takes the market.
-                    return self.fill(
-                        &mut exch_order,
-                        timestamp,
-                        false,
-                        self.depth.best_bid_tick(),
-                    );
-                }
-
-                exch_order.exch_timestamp = timestamp;
-                let local_recv_timestamp =
-                    timestamp + self.order_latency.response(timestamp, &exch_order);
-                self.orders_to
-                    .append(exch_order.clone(), local_recv_timestamp);
-                Ok(())
-            } else {
-                // The exchange accepts this order.
-                if prev_price_tick != exch_order.price_tick {
-                    self.sell_orders
-                        .get_mut(&prev_price_tick)
-                        .unwrap()
-                        .remove(&exch_order.order_id);
-                    self.sell_orders
-                        .entry(exch_order.price_tick)
-                        .or_insert(HashSet::new())
-                        .insert(exch_order.order_id);
-                }
-                if init_q_pos || prev_price_tick != exch_order.price_tick {
-                    // Initialize the order's queue position.
-                    self.queue_model.new_order(&mut exch_order, &self.depth);
-                }
-                exch_order.status = Status::New;
-
-                exch_order.exch_timestamp = timestamp;
-                let local_recv_timestamp =
-                    timestamp + self.order_latency.response(timestamp, &exch_order);
-                self.orders_to
-                    .append(exch_order.clone(), local_recv_timestamp);
-
-                let mut order_borrowed = self.orders.borrow_mut();
-                order_borrowed.insert(exch_order.order_id, exch_order);
-
-                Ok(())
-            }
+            self.sell_orders
+                .entry(exch_order.price_tick)
+                .or_insert(HashSet::new())
+                .insert(exch_order.order_id);
         }
+
+        exch_order.exch_timestamp = timestamp;
+        let local_recv_timestamp = timestamp + self.order_latency.response(timestamp, &exch_order);
+        send_response(
+            self.faults.as_mut(),
+            &mut self.orders_to,
+            exch_order.clone(),
+            local_recv_timestamp,
+        );
+        self.orders
+            .borrow_mut()
+            .insert(exch_order.order_id, exch_order);
+        Ok(())
     }
 }
 
@@ -604,7 +1504,40 @@ where
 
     fn process_data(&mut self) -> Result<(i64, i64), BacktestError> {
         let row_num = self.row_num;
-        if self.data[row_num].is(EXCH_BID_DEPTH_CLEAR_EVENT) {
+        self.order_latency.observe(&self.data[row_num]);
+        // Settles the scheduled funding and accrues the borrow interest on the position held before
+        // this event.
+        self.state
+            .apply_scheduled_funding(self.data[row_num].exch_ts, || {
+                (self.depth.best_bid() + self.depth.best_ask()) / 2.0
+            });
+        self.state
+            .apply_borrow_cost(self.data[row_num].exch_ts, || {
+                (self.depth.best_bid() + self.depth.best_ask()) / 2.0
+            });
+        if self.data[row_num].is(EXCH_HALT_EVENT) {
+            self.halt.set_halted(self.data[row_num].ival == 1);
+        }
+        self.check_halt(self.data[row_num].exch_ts);
+        if self.data[row_num].is(EXCH_SETTLEMENT_EVENT) {
+            if let Some(expiry) = self.contract_expiry.as_mut() {
+                expiry.set_settlement_price(self.data[row_num].px);
+            }
+        }
+        self.check_contract_expiry(self.data[row_num].exch_ts)?;
+        self.expire_orders(self.data[row_num].exch_ts);
+        // Matching pauses during a trading halt.
+        let halted = self.halt.is_halted();
+        let excluded = self.data[row_num].ev & self.excluded_trades != 0;
+        let action = self
+            .feed_gap
+            .check(&self.data[row_num], self.data[row_num].exch_ts);
+        if action == GapAction::Reset {
+            self.depth.clear_depth(Side::None, 0.0);
+        }
+        if action == GapAction::Skip {
+            // The market depth is stale until the next snapshot.
+        } else if self.data[row_num].is(EXCH_BID_DEPTH_CLEAR_EVENT) {
             self.depth.clear_depth(Side::Buy, self.data[row_num].px);
         } else if self.data[row_num].is(EXCH_ASK_DEPTH_CLEAR_EVENT) {
             self.depth.clear_depth(Side::Sell, self.data[row_num].px);
@@ -614,35 +1547,40 @@ where
             || self.data[row_num].is(EXCH_BID_DEPTH_SNAPSHOT_EVENT)
         {
             let (price_tick, prev_best_bid_tick, best_bid_tick, prev_qty, new_qty, timestamp) =
-                self.depth.update_bid_depth(
+                self.depth.update_bid_depth_with_count(
                     self.data[row_num].px,
                     self.data[row_num].qty,
+                    self.data[row_num].ival,
                     self.data[row_num].exch_ts,
                 );
             self.on_bid_qty_chg(price_tick, prev_qty, new_qty);
-            if best_bid_tick > prev_best_bid_tick {
+            if best_bid_tick > prev_best_bid_tick && !halted {
                 self.on_best_bid_update(prev_best_bid_tick, best_bid_tick, timestamp)?;
             }
         } else if self.data[row_num].is(EXCH_ASK_DEPTH_EVENT)
             || self.data[row_num].is(EXCH_ASK_DEPTH_SNAPSHOT_EVENT)
         {
             let (price_tick, prev_best_ask_tick, best_ask_tick, prev_qty, new_qty, timestamp) =
-                self.depth.update_ask_depth(
+                self.depth.update_ask_depth_with_count(
                     self.data[row_num].px,
                     self.data[row_num].qty,
+                    self.data[row_num].ival,
                     self.data[row_num].exch_ts,
                 );
             self.on_ask_qty_chg(price_tick, prev_qty, new_qty);
-            if best_ask_tick < prev_best_ask_tick {
+            if best_ask_tick < prev_best_ask_tick && !halted {
                 self.on_best_ask_update(prev_best_ask_tick, best_ask_tick, timestamp)?;
             }
-        } else if self.data[row_num].is(EXCH_BUY_TRADE_EVENT) {
+        } else if self.data[row_num].is(EXCH_BUY_TRADE_EVENT) && !halted && !excluded {
             let price_tick = (self.data[row_num].px / self.depth.tick_size()).round() as i64;
             let qty = self.data[row_num].qty;
             {
                 let orders = self.orders.clone();
                 let mut orders_borrowed = orders.borrow_mut();
-                if self.depth.best_bid_tick() == INVALID_MIN
+                // Under BookCrossRule::RequireTrade, sell orders can rest at or below the best bid,
+                // outside the price ladder range below.
+                if !self.book_cross.fills_on_cross()
+                    || self.depth.best_bid_tick() == INVALID_MIN
                     || (orders_borrowed.len() as i64) < price_tick - self.depth.best_bid_tick()
                 {
                     for (_, order) in orders_borrowed.iter_mut() {
@@ -672,13 +1610,16 @@ where
                 }
             }
             self.remove_filled_orders();
-        } else if self.data[row_num].is(EXCH_SELL_TRADE_EVENT) {
+        } else if self.data[row_num].is(EXCH_SELL_TRADE_EVENT) && !halted && !excluded {
             let price_tick = (self.data[row_num].px / self.depth.tick_size()).round() as i64;
             let qty = self.data[row_num].qty;
             {
                 let orders = self.orders.clone();
                 let mut orders_borrowed = orders.borrow_mut();
-                if self.depth.best_ask_tick() == INVALID_MAX
+                // Under BookCrossRule::RequireTrade, buy orders can rest at or above the best ask,
+                // outside the price ladder range below.
+                if !self.book_cross.fills_on_cross()
+                    || self.depth.best_ask_tick() == INVALID_MAX
                     || (orders_borrowed.len() as i64) < self.depth.best_ask_tick() - price_tick
                 {
                     for (_, order) in orders_borrowed.iter_mut() {
@@ -708,6 +1649,39 @@ where
                 }
             }
             self.remove_filled_orders();
+        } else if self.data[row_num].is(EXCH_FUNDING_EVENT) {
+            let price = if self.data[row_num].px > 0.0 {
+                self.data[row_num].px
+            } else {
+                (self.depth.best_bid() + self.depth.best_ask()) / 2.0
+            };
+            self.state.apply_funding(price, self.data[row_num].fval);
+        } else if self.data[row_num].is(EXCH_AUCTION_EVENT) {
+            if self.data[row_num].ival == 1 {
+                if !self.auction.is_open() {
+                    self.open_auction();
+                }
+            } else if self.auction.is_open() {
+                self.uncross_auction(self.data[row_num].px, self.data[row_num].exch_ts)?;
+            }
+        } else if self.data[row_num].is(EXCH_PRICE_BAND_EVENT) {
+            self.price_band
+                .get_or_insert_with(PriceBand::new)
+                .set_limits(self.data[row_num].px, self.data[row_num].fval);
+        }
+
+        self.stop_orders
+            .update_reference(&self.data[row_num], self.depth.tick_size());
+        if !self.stop_orders.is_empty() && !halted {
+            let trade_tick = (self.data[row_num].is(EXCH_BUY_TRADE_EVENT)
+                || self.data[row_num].is(EXCH_SELL_TRADE_EVENT))
+            .then(|| (self.data[row_num].px / self.depth.tick_size()).round() as i64);
+            self.check_stop_orders(trade_tick, self.data[row_num].exch_ts)?;
+        }
+
+        self.check_reduce_only_orders(self.data[row_num].exch_ts);
+        if self.state.margin.is_some() {
+            self.check_liquidation(self.data[row_num].exch_ts)?;
         }
 
         // Checks
@@ -736,6 +1710,16 @@ where
         timestamp: i64,
         _wait_resp_order_id: Option<OrderId>,
     ) -> Result<bool, BacktestError> {
+        self.state.apply_scheduled_funding(timestamp, || {
+            (self.depth.best_bid() + self.depth.best_ask()) / 2.0
+        });
+        self.state.apply_borrow_cost(timestamp, || {
+            (self.depth.best_bid() + self.depth.best_ask()) / 2.0
+        });
+        self.check_halt(timestamp);
+        self.check_contract_expiry(timestamp)?;
+        self.expire_orders(timestamp);
+
         // Processes the order part.
         while self.orders_from.len() > 0 {
             let recv_timestamp = self.orders_from.earliest_timestamp().unwrap();
@@ -747,6 +1731,11 @@ where
                 break;
             }
         }
+
+        self.check_reduce_only_orders(timestamp);
+        if self.state.margin.is_some() {
+            self.check_liquidation(timestamp)?;
+        }
         Ok(false)
     }
 
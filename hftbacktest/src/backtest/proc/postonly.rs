@@ -0,0 +1,137 @@
+use crate::{
+    depth::{INVALID_MAX, INVALID_MIN},
+    types::{Order, Side, Status},
+};
+
+/// Determines how the exchange handles a post-only order, [`TimeInForce::GTX`], that would take
+/// liquidity on arrival, which differs by venue.
+///
+/// [`TimeInForce::GTX`]: crate::types::TimeInForce::GTX
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PostOnlyRule {
+    /// The order is rejected and reported as [`Status::Expired`], as Binance Futures does.
+    #[default]
+    Reject,
+    /// The order is accepted and immediately canceled, and reported as [`Status::Canceled`].
+    Cancel,
+    /// The order is re-priced to one tick passive of the opposite best, and rests in the book as a
+    /// maker order at the new price.
+    Reprice,
+}
+
+impl PostOnlyRule {
+    /// Returns the status reported for the crossing post-only order that is not re-priced.
+    pub fn crossing_status(&self) -> Status {
+        match self {
+            PostOnlyRule::Reject => Status::Expired,
+            PostOnlyRule::Cancel | PostOnlyRule::Reprice => Status::Canceled,
+        }
+    }
+
+    /// Re-prices the post-only `order` to one tick passive of the opposite best if the rule is
+    /// [`PostOnlyRule::Reprice`] and the order would take liquidity. Returns `true` if the order is
+    /// re-priced.
+    pub fn reprice(&self, order: &mut Order, best_bid_tick: i64, best_ask_tick: i64) -> bool {
+        if *self != PostOnlyRule::Reprice {
+            return false;
+        }
+        match order.side {
+            Side::Buy if best_ask_tick != INVALID_MAX && order.price_tick >= best_ask_tick => {
+                order.price_tick = best_ask_tick - 1;
+                true
+            }
+            Side::Sell if best_bid_tick != INVALID_MIN && order.price_tick <= best_bid_tick => {
+                order.price_tick = best_bid_tick + 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostOnlyRule;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{
+            Bot,
+            OrdType,
+            Order,
+            Side,
+            Status,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+        },
+    };
+
+    fn order(side: Side, price_tick: i64) -> Order {
+        Order::new(
+            1,
+            price_tick,
+            1.0,
+            1.0,
+            side,
+            OrdType::Limit,
+            TimeInForce::GTX,
+        )
+    }
+
+    #[test]
+    fn test_reprice() {
+        let mut buy = order(Side::Buy, 102);
+        assert!(!PostOnlyRule::Reject.reprice(&mut buy, 100, 101));
+        assert!(PostOnlyRule::Reprice.reprice(&mut buy, 100, 101));
+        assert_eq!(buy.price_tick, 100);
+        assert!(!PostOnlyRule::Reprice.reprice(&mut buy, 100, 101));
+
+        let mut sell = order(Side::Sell, 99);
+        assert!(PostOnlyRule::Reprice.reprice(&mut sell, 100, 101));
+        assert_eq!(sell.price_tick, 101);
+    }
+
+    #[test]
+    fn test_post_only_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            for rule in [
+                PostOnlyRule::Reject,
+                PostOnlyRule::Cancel,
+                PostOnlyRule::Reprice,
+            ] {
+                let data = Data::from_slice(&[
+                    event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                    event(DEPTH_EVENT | SELL_EVENT, 2, 102.0, 10.0),
+                    event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 5.0),
+                ]);
+                let mut hbt = backtest(
+                    asset_builder(data, 1.0, 1.0)
+                        .exchange(exch_kind)
+                        .post_only_rule(rule),
+                );
+
+                hbt.elapse(9).unwrap();
+                hbt.submit_buy_order(0, 1, 103.0, 1.0, TimeInForce::GTX, OrdType::Limit, true)
+                    .unwrap();
+                let order = &hbt.orders(0)[&1];
+                match rule {
+                    PostOnlyRule::Reject => assert_eq!(order.status, Status::Expired),
+                    PostOnlyRule::Cancel => assert_eq!(order.status, Status::Canceled),
+                    PostOnlyRule::Reprice => {
+                        assert_eq!(order.status, Status::New);
+                        assert_eq!(order.price_tick, 101);
+                    }
+                }
+                assert_eq!(hbt.position(0), 0.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{
+    depth::{INVALID_MAX, INVALID_MIN},
+    types::{Order, Side},
+};
+
+/// Enforces the venue's price band on orders at the exchange.
+///
+/// * Static limits - The lower and upper limit prices, such as the daily price limits of futures,
+///   which are set by [`limits`](Self::limits) or updated by
+///   [`PRICE_BAND_EVENT`](crate::types::PRICE_BAND_EVENT)s in the data.
+/// * Percent band - The band around the mid-price within which the order price must be, such as
+///   the percent price filter of crypto venues.
+///
+/// An order whose price is outside the band is rejected. While the market is locked at a limit
+/// price, that is, the best bid is at or above the upper limit or the best ask is at or below the
+/// lower limit, matching halts: an order that would take liquidity is rejected, and resting orders
+/// are not filled by the market trading through their prices. Orders at the limit price can still
+/// be filled by trades at the limit price once they reach the front of the queue.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::PriceBand;
+///
+/// // Daily limits of 90 and 110, and orders within 5% of the mid-price.
+/// let price_band = PriceBand::new().limits(90.0, 110.0).percent(0.05);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PriceBand {
+    lower: f64,
+    upper: f64,
+    percent: f64,
+    rejected: usize,
+}
+
+impl PriceBand {
+    /// Constructs an instance of `PriceBand` without any limits.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the static lower and upper limit prices. A limit of `0` means no limit on that side.
+    pub fn limits(self, lower: f64, upper: f64) -> Self {
+        Self {
+            lower,
+            upper,
+            ..self
+        }
+    }
+
+    /// Sets the band around the mid-price as a fraction of it, such as `0.05` for 5%. A value of
+    /// `0` means no band.
+    pub fn percent(self, percent: f64) -> Self {
+        Self { percent, ..self }
+    }
+
+    /// Updates the static lower and upper limit prices. A limit of `0` means no limit on that side.
+    pub fn set_limits(&mut self, lower: f64, upper: f64) {
+        self.lower = lower;
+        self.upper = upper;
+    }
+
+    /// Returns the static lower and upper limit prices.
+    pub fn limit_prices(&self) -> (f64, f64) {
+        (self.lower, self.upper)
+    }
+
+    fn limit_ticks(&self, tick_size: f64) -> (i64, i64) {
+        let lower_tick = if self.lower > 0.0 {
+            (self.lower / tick_size).round() as i64
+        } else {
+            INVALID_MIN
+        };
+        let upper_tick = if self.upper > 0.0 {
+            (self.upper / tick_size).round() as i64
+        } else {
+            INVALID_MAX
+        };
+        (lower_tick, upper_tick)
+    }
+
+    /// Returns `true` if the market is locked at a limit price, in which case matching halts.
+    pub fn is_locked(&self, tick_size: f64, best_bid_tick: i64, best_ask_tick: i64) -> bool {
+        let (lower_tick, upper_tick) = self.limit_ticks(tick_size);
+        (upper_tick != INVALID_MAX && best_bid_tick != INVALID_MIN && best_bid_tick >= upper_tick)
+            || (lower_tick != INVALID_MIN
+                && best_ask_tick != INVALID_MAX
+                && best_ask_tick <= lower_tick)
+    }
+
+    /// Returns `true` if the order is accepted, that is, its price is within the band and it
+    /// doesn't take liquidity while matching halts.
+    pub fn check(
+        &mut self,
+        order: &Order,
+        tick_size: f64,
+        best_bid_tick: i64,
+        best_ask_tick: i64,
+    ) -> bool {
+        let (mut lower_tick, mut upper_tick) = self.limit_ticks(tick_size);
+        if self.percent > 0.0 && best_bid_tick != INVALID_MIN && best_ask_tick != INVALID_MAX {
+            let mid_tick = (best_bid_tick + best_ask_tick) as f64 / 2.0;
+            lower_tick = lower_tick.max((mid_tick * (1.0 - self.percent)).ceil() as i64);
+            upper_tick = upper_tick.min((mid_tick * (1.0 + self.percent)).floor() as i64);
+        }
+        let within_band = order.price_tick >= lower_tick && order.price_tick <= upper_tick;
+        let takes_liquidity = match order.side {
+            Side::Buy => order.price_tick >= best_ask_tick,
+            _ => order.price_tick <= best_bid_tick,
+        };
+        let accepted = within_band
+            && !(takes_liquidity && self.is_locked(tick_size, best_bid_tick, best_ask_tick));
+        if !accepted {
+            self.rejected += 1;
+        }
+        accepted
+    }
+
+    /// Returns the number of orders rejected so far.
+    pub fn rejected_count(&self) -> usize {
+        self.rejected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PriceBand;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{
+            Bot,
+            Event,
+            OrdType,
+            Order,
+            Side,
+            Status,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            PRICE_BAND_EVENT,
+            SELL_EVENT,
+            TRADE_EVENT,
+        },
+    };
+
+    fn order(side: Side, price_tick: i64) -> Order {
+        Order::new(
+            1,
+            price_tick,
+            1.0,
+            1.0,
+            side,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        )
+    }
+
+    #[test]
+    fn test_check() {
+        let mut band = PriceBand::new().limits(90.0, 110.0).percent(0.05);
+        // The percent band around the mid-price of 100 is narrower than the static limits.
+        assert!(band.check(&order(Side::Buy, 95), 1.0, 99, 101));
+        assert!(!band.check(&order(Side::Buy, 94), 1.0, 99, 101));
+        assert!(band.check(&order(Side::Sell, 105), 1.0, 99, 101));
+        assert!(!band.check(&order(Side::Sell, 106), 1.0, 99, 101));
+
+        // The market is locked at the upper limit.
+        assert!(band.is_locked(1.0, 110, 111));
+        assert!(band.check(&order(Side::Buy, 110), 1.0, 110, 111));
+        assert!(!band.check(&order(Side::Sell, 110), 1.0, 110, 111));
+        assert_eq!(band.rejected_count(), 3);
+
+        band.set_limits(0.0, 0.0);
+        assert!(!band.is_locked(1.0, 110, 111));
+    }
+
+    #[test]
+    fn test_price_band_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            let data = Data::from_slice(&[
+                Event {
+                    fval: 102.0,
+                    ..event(PRICE_BAND_EVENT, 1, 98.0, 0.0)
+                },
+                event(DEPTH_EVENT | BUY_EVENT, 2, 100.0, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 3, 101.0, 10.0),
+                // The market is locked at the upper limit.
+                event(DEPTH_EVENT | SELL_EVENT, 100, 101.0, 0.0),
+                event(DEPTH_EVENT | SELL_EVENT, 101, 103.0, 10.0),
+                event(DEPTH_EVENT | BUY_EVENT, 102, 102.0, 10.0),
+                event(TRADE_EVENT | BUY_EVENT, 150, 102.0, 1.0),
+                event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 10.0),
+            ]);
+            let mut hbt = backtest(asset_builder(data, 1.0, 1.0).exchange(exch_kind));
+
+            hbt.elapse(9).unwrap();
+            hbt.submit_buy_order(0, 1, 97.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            assert_eq!(hbt.orders(0)[&1].status, Status::Expired);
+            hbt.submit_sell_order(0, 2, 101.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            assert_eq!(hbt.orders(0)[&2].status, Status::New);
+
+            hbt.elapse(100).unwrap();
+            // The sell order taking the locked market is rejected.
+            hbt.submit_sell_order(0, 3, 102.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            assert_eq!(hbt.orders(0)[&3].status, Status::Expired);
+
+            // The resting sell order is not filled while matching halts.
+            hbt.elapse(100).unwrap();
+            assert_eq!(hbt.orders(0)[&2].status, Status::New);
+            assert_eq!(hbt.position(0), 0.0);
+        }
+    }
+}
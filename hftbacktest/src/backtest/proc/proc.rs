@@ -2,9 +2,19 @@ use std::collections::HashMap;
 
 use crate::{
     backtest::BacktestError,
-    depth::MarketDepth,
+    depth::{DepthChange, MarketDepth},
     prelude::OrderId,
-    types::{Event, OrdType, Order, Side, StateValues, TimeInForce},
+    types::{
+        BatchRequest,
+        Event,
+        OrdType,
+        Order,
+        OrderRequest,
+        Roll,
+        Side,
+        StateValues,
+        TimeInForce,
+    },
 };
 
 /// Provides local-specific interaction.
@@ -34,12 +44,170 @@ where
         current_timestamp: i64,
     ) -> Result<(), BacktestError>;
 
+    /// Submits a new stop order, which the exchange holds until the market reaches the trigger
+    /// price and then processes as a market order, [`OrdType::StopMarket`], or as a limit order,
+    /// [`OrdType::StopLimit`]. The default implementation rejects the request, as not every
+    /// processor supports stop orders.
+    ///
+    /// * `order_id` - The unique order ID; there should not be any existing order with the same ID
+    ///                on both local and exchange sides.
+    /// * `trigger_price` - The price at which the order is triggered.
+    /// * `price` - Order price once triggered. This is ignored for [`OrdType::StopMarket`].
+    /// * `qty` - Quantity to buy.
+    /// * `order_type` - Either [`OrdType::StopMarket`] or [`OrdType::StopLimit`].
+    /// * `time_in_force` - Available [`TimeInForce`] options vary depending on the exchange model.
+    ///                     See to the exchange model for details.
+    /// * `current_timestamp` - The current backtesting timestamp.
+    fn submit_stop_order(
+        &mut self,
+        _order_id: OrderId,
+        _side: Side,
+        _trigger_price: f64,
+        _price: f64,
+        _qty: f64,
+        _order_type: OrdType,
+        _time_in_force: TimeInForce,
+        _current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        Err(BacktestError::InvalidOrderRequest)
+    }
+
+    /// Submits a new [`TimeInForce::GTD`] order, which the exchange expires at the
+    /// `expire_timestamp` if it has not been executed or canceled by then. The default
+    /// implementation rejects the request, as not every processor supports GTD orders.
+    ///
+    /// * `order_id` - The unique order ID; there should not be any existing order with the same ID
+    ///                on both local and exchange sides.
+    /// * `price` - Order price.
+    /// * `qty` - Quantity to buy.
+    /// * `order_type` - Available [`OrdType`] options vary depending on the exchange model. See to
+    ///                   the exchange model for details.
+    /// * `expire_timestamp` - The exchange timestamp at which the order expires.
+    /// * `current_timestamp` - The current backtesting timestamp.
+    fn submit_gtd_order(
+        &mut self,
+        _order_id: OrderId,
+        _side: Side,
+        _price: f64,
+        _qty: f64,
+        _order_type: OrdType,
+        _expire_timestamp: i64,
+        _current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        Err(BacktestError::InvalidOrderRequest)
+    }
+
+    /// Submits a new order described by the [`OrderRequest`]. The default implementation
+    /// dispatches it to [`submit_stop_order`](Self::submit_stop_order),
+    /// [`submit_gtd_order`](Self::submit_gtd_order), or [`submit_order`](Self::submit_order) by its
+    /// order type and time-in-force, and rejects a reduce-only request, as not every processor
+    /// supports reduce-only orders.
+    ///
+    /// * `order` - The order request.
+    /// * `current_timestamp` - The current backtesting timestamp.
+    fn submit_order_request(
+        &mut self,
+        order: &OrderRequest,
+        current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        if order.reduce_only {
+            Err(BacktestError::InvalidOrderRequest)
+        } else if order.order_type.is_stop() {
+            self.submit_stop_order(
+                order.order_id,
+                order.side,
+                order.trigger_price,
+                order.price,
+                order.qty,
+                order.order_type,
+                order.time_in_force,
+                current_timestamp,
+            )
+        } else if order.time_in_force == TimeInForce::GTD {
+            self.submit_gtd_order(
+                order.order_id,
+                order.side,
+                order.price,
+                order.qty,
+                order.order_type,
+                order.expire_timestamp,
+                current_timestamp,
+            )
+        } else {
+            self.submit_order(
+                order.order_id,
+                order.side,
+                order.price,
+                order.qty,
+                order.order_type,
+                order.time_in_force,
+                current_timestamp,
+            )
+        }
+    }
+
+    /// Submits the order requests as a batch, which reaches the exchange as a single message with
+    /// one order entry latency, so that the exchange processes the requests atomically in the
+    /// given order. The batch is rejected as a whole if any request is invalid. The default
+    /// implementation rejects the batch, as not every processor supports batches.
+    ///
+    /// * `requests` - The order requests.
+    /// * `current_timestamp` - The current backtesting timestamp.
+    fn submit_batch(
+        &mut self,
+        _requests: &[BatchRequest],
+        _current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        Err(BacktestError::InvalidOrderRequest)
+    }
+
     /// Cancels the specified order.
     ///
     /// * `order_id` - Order ID to cancel.
     /// * `current_timestamp` - The current backtesting timestamp.
     fn cancel(&mut self, order_id: OrderId, current_timestamp: i64) -> Result<(), BacktestError>;
 
+    /// Cancels all open orders with a single mass-cancel message, whose order ID is
+    /// [`CANCEL_ALL_ORDER_ID`](crate::backtest::proc::CANCEL_ALL_ORDER_ID). The default
+    /// implementation rejects the request, as not every processor supports mass cancels.
+    ///
+    /// * `current_timestamp` - The current backtesting timestamp.
+    fn cancel_all(&mut self, _current_timestamp: i64) -> Result<(), BacktestError> {
+        Err(BacktestError::InvalidOrderRequest)
+    }
+
+    /// Modifies the price and quantity of the specified order in place. Whether the order keeps
+    /// its queue position is determined by the exchange model's
+    /// [`AmendRule`](crate::backtest::proc::AmendRule). The default implementation rejects the
+    /// request, as not every processor supports order modification.
+    ///
+    /// * `order_id` - Order ID to modify.
+    /// * `price` - New order price.
+    /// * `qty` - New order quantity, including any quantity already executed.
+    /// * `current_timestamp` - The current backtesting timestamp.
+    fn modify(
+        &mut self,
+        _order_id: OrderId,
+        _price: f64,
+        _qty: f64,
+        _current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        Err(BacktestError::InvalidOrderRequest)
+    }
+
+    /// Queries the current state of the specified order at the exchange. The default
+    /// implementation rejects the request, as not every processor supports order queries.
+    ///
+    /// * `order_id` - Order ID to query.
+    /// * `current_timestamp` - The current backtesting timestamp.
+    fn query_order(
+        &mut self,
+        _order_id: OrderId,
+        _current_timestamp: i64,
+    ) -> Result<(), BacktestError> {
+        Err(BacktestError::InvalidOrderRequest)
+    }
+
     /// Clears inactive orders from the local orders whose status is neither
     /// [`Status::New`](crate::types::Status::New) nor
     /// [`Status::PartiallyFilled`](crate::types::Status::PartiallyFilled).
@@ -69,6 +237,39 @@ where
     /// Returns the last order's request timestamp, exchange timestamp, and response receipt
     /// timestamp.
     fn order_latency(&self) -> Option<(i64, i64, i64)>;
+
+    /// Returns the number of feed gaps detected so far. See [`FeedGap`](super::FeedGap).
+    fn feed_gap_count(&self) -> usize;
+
+    /// Returns the contract rolls that have occurred so far. See [`Roll`].
+    fn rolls(&self) -> &[Roll];
+
+    /// Returns the latest auxiliary event of the specified kind. See
+    /// [`AUX_EVENT`](crate::types::AUX_EVENT).
+    fn aux_event(&self, kind: u64) -> Option<&Event>;
+
+    /// Starts recording the fills received, which are taken by [`take_fills`](Self::take_fills).
+    /// The default implementation doesn't record them.
+    fn record_fills(&mut self) {}
+
+    /// Takes the fills recorded since the last call.
+    fn take_fills(&mut self) -> Vec<Order> {
+        Vec::new()
+    }
+
+    /// Applies the fill of a leg of a spread order, which is executed through another asset, to
+    /// the state. See [`Spread`](super::Spread). The default implementation ignores it.
+    fn apply_leg_fill(&mut self, _order: &Order) {}
+
+    /// Starts recording the changes of the market depth made by the feed, which are taken by
+    /// [`take_depth_changes`](Self::take_depth_changes). The default implementation doesn't record
+    /// them.
+    fn record_depth_changes(&mut self) {}
+
+    /// Takes the changes of the market depth recorded since the last call.
+    fn take_depth_changes(&mut self) -> Vec<DepthChange> {
+        Vec::new()
+    }
 }
 
 /// Processes the historical feed data and the order interaction.
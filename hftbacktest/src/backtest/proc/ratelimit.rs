@@ -0,0 +1,289 @@
+use crate::backtest::models::RequestType;
+
+/// Determines what the exchange does with a request that exceeds the rate limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RateLimitAction {
+    /// Rejects the request, as most venues do.
+    #[default]
+    Reject,
+    /// Holds the request until the window in which the budget is available, as a venue queueing
+    /// requests or a client-side throttle does.
+    Delay,
+}
+
+/// The outcome of checking a request against the [`RateLimit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    /// The request is within the budget, which it has consumed.
+    Accept,
+    /// The request is rejected.
+    Reject,
+    /// The request is to be checked again at the timestamp.
+    Delay(i64),
+}
+
+/// A budget of request weight per fixed window of `interval` nanoseconds, such as 300 orders per
+/// 10 seconds or 1,200 weight per minute. The windows are aligned to the Unix epoch, as venues
+/// usually reset their counters at the top of the interval.
+///
+/// Each type of request consumes its own weight, which is `1` by default. A weight of `0` exempts
+/// the type of request from the rule, for instance, to model an order count limit that doesn't
+/// count cancels.
+#[derive(Clone, Debug)]
+pub struct RateLimitRule {
+    interval: i64,
+    limit: u64,
+    new_weight: u64,
+    cancel_weight: u64,
+    modify_weight: u64,
+    query_weight: u64,
+    window_start: i64,
+    used: u64,
+}
+
+impl RateLimitRule {
+    /// Constructs an instance of `RateLimitRule` that allows the `limit` weight per `interval`.
+    pub fn new(interval: i64, limit: u64) -> Self {
+        assert!(interval > 0);
+        Self {
+            interval,
+            limit,
+            new_weight: 1,
+            cancel_weight: 1,
+            modify_weight: 1,
+            query_weight: 1,
+            window_start: i64::MIN,
+            used: 0,
+        }
+    }
+
+    /// Sets the weights of new, cancel, and modify requests.
+    pub fn weights(self, new_weight: u64, cancel_weight: u64, modify_weight: u64) -> Self {
+        Self {
+            new_weight,
+            cancel_weight,
+            modify_weight,
+            ..self
+        }
+    }
+
+    /// Sets the weight of order query requests.
+    pub fn query_weight(self, query_weight: u64) -> Self {
+        Self {
+            query_weight,
+            ..self
+        }
+    }
+
+    fn weight(&self, req_type: RequestType) -> u64 {
+        match req_type {
+            RequestType::New => self.new_weight,
+            RequestType::Cancel => self.cancel_weight,
+            RequestType::Modify => self.modify_weight,
+            RequestType::Query => self.query_weight,
+        }
+    }
+
+    fn roll(&mut self, timestamp: i64) {
+        let window_start = timestamp - timestamp.rem_euclid(self.interval);
+        if window_start != self.window_start {
+            self.window_start = window_start;
+            self.used = 0;
+        }
+    }
+
+    fn exceeds(&self, weight: u64) -> bool {
+        weight > 0 && self.used + weight > self.limit
+    }
+}
+
+/// Simulates the venue's rate limits on order requests at the exchange. A request is accepted only
+/// if it is within the budget of every [`RateLimitRule`]; otherwise, it is rejected or delayed
+/// according to the [`RateLimitAction`]. A rejected request doesn't consume the budget.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::{RateLimit, RateLimitAction, RateLimitRule};
+///
+/// // 300 orders per 10 seconds, not counting cancels, and 1,200 weight per minute.
+/// let rate_limit = RateLimit::new(RateLimitAction::Reject)
+///     .rule(RateLimitRule::new(10_000_000_000, 300).weights(1, 0, 1))
+///     .rule(RateLimitRule::new(60_000_000_000, 1_200));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    rules: Vec<RateLimitRule>,
+    action: RateLimitAction,
+    rejected: usize,
+    delayed: usize,
+}
+
+impl RateLimit {
+    /// Constructs an instance of `RateLimit` without any rules.
+    pub fn new(action: RateLimitAction) -> Self {
+        Self {
+            rules: Vec::new(),
+            action,
+            rejected: 0,
+            delayed: 0,
+        }
+    }
+
+    /// Adds a rule.
+    pub fn rule(mut self, rule: RateLimitRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Checks the request received at the timestamp against the rules, and consumes the budget if
+    /// it is accepted.
+    pub fn check(&mut self, req_type: RequestType, timestamp: i64) -> RateLimitDecision {
+        let mut next_window = None;
+        for rule in self.rules.iter_mut() {
+            rule.roll(timestamp);
+            if rule.exceeds(rule.weight(req_type)) {
+                let window_end = rule.window_start + rule.interval;
+                next_window = Some(next_window.map_or(window_end, |ts: i64| ts.max(window_end)));
+            }
+        }
+        match (next_window, self.action) {
+            (None, _) => {
+                for rule in self.rules.iter_mut() {
+                    rule.used += rule.weight(req_type);
+                }
+                RateLimitDecision::Accept
+            }
+            (Some(_), RateLimitAction::Reject) => {
+                self.rejected += 1;
+                RateLimitDecision::Reject
+            }
+            (Some(next_window), RateLimitAction::Delay) => {
+                self.delayed += 1;
+                RateLimitDecision::Delay(next_window)
+            }
+        }
+    }
+
+    /// Returns the number of requests rejected so far.
+    pub fn rejected_count(&self) -> usize {
+        self.rejected
+    }
+
+    /// Returns the number of times requests have been delayed so far.
+    pub fn delayed_count(&self) -> usize {
+        self.delayed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimit, RateLimitAction, RateLimitDecision, RateLimitRule};
+    use crate::{
+        backtest::{
+            models::RequestType,
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{Bot, OrdType, Status, TimeInForce, BUY_EVENT, DEPTH_EVENT, SELL_EVENT},
+    };
+
+    #[test]
+    fn test_check() {
+        let mut rate_limit = RateLimit::new(RateLimitAction::Reject)
+            .rule(RateLimitRule::new(100, 2).weights(1, 0, 1))
+            .rule(RateLimitRule::new(1000, 3));
+        assert_eq!(
+            rate_limit.check(RequestType::New, 10),
+            RateLimitDecision::Accept
+        );
+        assert_eq!(
+            rate_limit.check(RequestType::New, 20),
+            RateLimitDecision::Accept
+        );
+        assert_eq!(
+            rate_limit.check(RequestType::New, 30),
+            RateLimitDecision::Reject
+        );
+        // Cancels are exempt from the first rule.
+        assert_eq!(
+            rate_limit.check(RequestType::Cancel, 40),
+            RateLimitDecision::Accept
+        );
+        // The first rule's window is reset, but the second rule's budget is exhausted.
+        assert_eq!(
+            rate_limit.check(RequestType::New, 100),
+            RateLimitDecision::Reject
+        );
+        assert_eq!(
+            rate_limit.check(RequestType::New, 1000),
+            RateLimitDecision::Accept
+        );
+        assert_eq!(rate_limit.rejected_count(), 2);
+
+        let mut rate_limit =
+            RateLimit::new(RateLimitAction::Delay).rule(RateLimitRule::new(100, 1));
+        assert_eq!(
+            rate_limit.check(RequestType::New, 10),
+            RateLimitDecision::Accept
+        );
+        assert_eq!(
+            rate_limit.check(RequestType::New, 20),
+            RateLimitDecision::Delay(100)
+        );
+        assert_eq!(
+            rate_limit.check(RequestType::New, 100),
+            RateLimitDecision::Accept
+        );
+        assert_eq!(rate_limit.delayed_count(), 1);
+    }
+
+    #[test]
+    fn test_rate_limit_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            for action in [RateLimitAction::Reject, RateLimitAction::Delay] {
+                let data = Data::from_slice(&[
+                    event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                    event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                    event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 10.0),
+                ]);
+                let mut hbt = backtest(
+                    asset_builder(data, 1.0, 1.0)
+                        .exchange(exch_kind)
+                        .rate_limit(RateLimit::new(action).rule(RateLimitRule::new(100, 2))),
+                );
+
+                hbt.elapse(9).unwrap();
+                for order_id in 1..=3 {
+                    hbt.submit_buy_order(
+                        0,
+                        order_id,
+                        99.0,
+                        1.0,
+                        TimeInForce::GTC,
+                        OrdType::Limit,
+                        false,
+                    )
+                    .unwrap();
+                }
+                hbt.elapse(50).unwrap();
+                assert_eq!(hbt.orders(0)[&1].status, Status::New);
+                assert_eq!(hbt.orders(0)[&2].status, Status::New);
+                match action {
+                    RateLimitAction::Reject => {
+                        assert_eq!(hbt.orders(0)[&3].status, Status::Expired);
+                    }
+                    RateLimitAction::Delay => {
+                        assert_eq!(hbt.orders(0)[&3].status, Status::None);
+                        hbt.elapse(100).unwrap();
+                        assert_eq!(hbt.orders(0)[&3].status, Status::New);
+                        assert_eq!(hbt.orders(0)[&3].exch_timestamp, 100);
+                    }
+                }
+            }
+        }
+    }
+}
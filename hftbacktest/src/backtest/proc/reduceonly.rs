@@ -0,0 +1,171 @@
+use crate::types::{Order, Side};
+
+/// Determines how the exchange handles a reduce-only order that would increase the position,
+/// which differs by venue.
+///
+/// A reduce-only order can only reduce the position. The exchange checks it when the order is
+/// accepted, including when a stop order is triggered, and checks the resting reduce-only orders
+/// again whenever the position changes, so that an order that no longer reduces the position, for
+/// example, because the position has been closed by another order, is handled by the rule as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReduceOnlyRule {
+    /// A reduce-only order whose leaves quantity exceeds the position to reduce is rejected, or
+    /// expired if it is resting. This is the rule of Binance Futures.
+    #[default]
+    Reject,
+    /// The leaves quantity of a reduce-only order is clamped to the position to reduce, and the
+    /// order is rejected, or expired if it is resting, only if there is no position to reduce. This
+    /// is the rule of Bybit.
+    Clamp,
+}
+
+impl ReduceOnlyRule {
+    /// Returns the leaves quantity the reduce-only `order` is allowed to have given the
+    /// `position`, or `None` if the order is to be rejected. The quantities are compared in lots.
+    pub fn allowed_qty(&self, order: &Order, position: f64, lot_size: f64) -> Option<f64> {
+        let reducible = match order.side {
+            Side::Buy => -position,
+            _ => position,
+        };
+        let reducible_lots = (reducible / lot_size).round();
+        if reducible_lots <= 0.0 {
+            return None;
+        }
+        if (order.leaves_qty / lot_size).round() <= reducible_lots {
+            return Some(order.leaves_qty);
+        }
+        match self {
+            ReduceOnlyRule::Reject => None,
+            ReduceOnlyRule::Clamp => Some(reducible_lots * lot_size),
+        }
+    }
+}
+
+/// Clamps the leaves quantity of the order to `leaves_qty`, reducing the order quantity as well.
+pub(crate) fn clamp_leaves_qty(order: &mut Order, leaves_qty: f64) {
+    order.qty -= order.leaves_qty - leaves_qty;
+    order.leaves_qty = leaves_qty;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReduceOnlyRule;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{
+            Bot,
+            OrdType,
+            Order,
+            OrderRequest,
+            Side,
+            Status,
+            TimeInForce,
+            TriggerBy,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+        },
+    };
+
+    fn order(side: Side, qty: f64) -> Order {
+        let mut order = Order::new(1, 100, 1.0, qty, side, OrdType::Limit, TimeInForce::GTC);
+        order.reduce_only = true;
+        order
+    }
+
+    #[test]
+    fn test_allowed_qty() {
+        let rule = ReduceOnlyRule::Reject;
+        assert_eq!(
+            rule.allowed_qty(&order(Side::Sell, 1.0), 2.0, 1.0),
+            Some(1.0)
+        );
+        assert_eq!(rule.allowed_qty(&order(Side::Sell, 3.0), 2.0, 1.0), None);
+        assert_eq!(rule.allowed_qty(&order(Side::Buy, 1.0), 2.0, 1.0), None);
+        assert_eq!(rule.allowed_qty(&order(Side::Buy, 1.0), 0.0, 1.0), None);
+
+        let rule = ReduceOnlyRule::Clamp;
+        assert_eq!(
+            rule.allowed_qty(&order(Side::Sell, 3.0), 2.0, 1.0),
+            Some(2.0)
+        );
+        assert_eq!(
+            rule.allowed_qty(&order(Side::Buy, 3.0), -1.0, 1.0),
+            Some(1.0)
+        );
+        assert_eq!(rule.allowed_qty(&order(Side::Buy, 1.0), 2.0, 1.0), None);
+    }
+
+    fn reduce_only(order_id: u64, side: Side, price: f64, qty: f64) -> OrderRequest {
+        OrderRequest {
+            order_id,
+            price,
+            qty,
+            side,
+            time_in_force: TimeInForce::GTC,
+            order_type: OrdType::Limit,
+            trigger_price: 0.0,
+            expire_timestamp: 0,
+            reduce_only: true,
+            trigger_by: TriggerBy::Default,
+        }
+    }
+
+    #[test]
+    fn test_reduce_only_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            for rule in [ReduceOnlyRule::Reject, ReduceOnlyRule::Clamp] {
+                let data = Data::from_slice(&[
+                    event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                    event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                    event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 5.0),
+                ]);
+                let mut hbt = backtest(
+                    asset_builder(data, 1.0, 1.0)
+                        .exchange(exch_kind)
+                        .reduce_only_rule(rule),
+                );
+
+                hbt.elapse(9).unwrap();
+                // There is no position to reduce.
+                hbt.submit_order(0, reduce_only(1, Side::Sell, 102.0, 1.0), true)
+                    .unwrap();
+                assert_eq!(hbt.orders(0)[&1].status, Status::Expired);
+
+                hbt.submit_buy_order(0, 2, 101.0, 2.0, TimeInForce::GTC, OrdType::Limit, true)
+                    .unwrap();
+                assert_eq!(hbt.position(0), 2.0);
+
+                // The order would flip the position.
+                hbt.submit_order(0, reduce_only(3, Side::Sell, 102.0, 3.0), true)
+                    .unwrap();
+                match rule {
+                    ReduceOnlyRule::Reject => {
+                        assert_eq!(hbt.orders(0)[&3].status, Status::Expired);
+                    }
+                    ReduceOnlyRule::Clamp => {
+                        assert_eq!(hbt.orders(0)[&3].status, Status::New);
+                        assert_eq!(hbt.orders(0)[&3].leaves_qty, 2.0);
+                    }
+                }
+
+                // The resting reduce-only order is expired once the position is closed.
+                hbt.submit_order(0, reduce_only(4, Side::Sell, 103.0, 1.0), true)
+                    .unwrap();
+                assert_eq!(hbt.orders(0)[&4].status, Status::New);
+                hbt.submit_sell_order(0, 5, 100.0, 2.0, TimeInForce::GTC, OrdType::Limit, true)
+                    .unwrap();
+                assert_eq!(hbt.position(0), 0.0);
+                hbt.elapse(10).unwrap();
+                assert_eq!(hbt.orders(0)[&4].status, Status::Expired);
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::types::OrderId;
+
+/// The order ID of the order by which the exchange settles the position upon the contract expiry.
+/// The order's fill is reported to the local like any other order response.
+pub const SETTLEMENT_ORDER_ID: OrderId = OrderId::MAX - 2;
+
+/// Simulates the expiry of a dated contract, such as a dated futures or an option.
+///
+/// Once the exchange time reaches the expiry, the exchange cancels all open orders and settles the
+/// position at the settlement price, which is the latest
+/// [`SETTLEMENT_EVENT`](crate::types::SETTLEMENT_EVENT)'s price in the data, the price given by
+/// [`ContractExpiry::settlement_price`], or otherwise the last mid-price, rounded to the tick size.
+/// The settlement is reported as the fill of an order with [`SETTLEMENT_ORDER_ID`] for each leg of
+/// the position, and no fee is charged on it. After the expiry, the asset is inactive: new orders
+/// and modifications are rejected. Since the exchange time advances by the events in the data and
+/// the order requests, the settlement occurs at the first of them at or after the expiry.
+///
+/// Only [`NoPartialFillExchange`](crate::backtest::proc::NoPartialFillExchange) and
+/// [`PartialFillExchange`](crate::backtest::proc::PartialFillExchange) support the contract expiry.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::ContractExpiry;
+///
+/// // Expires at 08:00 UTC on 2024-03-29.
+/// let expiry = ContractExpiry::new(1_711_699_200_000_000_000);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ContractExpiry {
+    expiry: i64,
+    settlement_price: Option<f64>,
+    expired: bool,
+}
+
+impl ContractExpiry {
+    /// Constructs an instance of `ContractExpiry` that expires at the timestamp.
+    pub fn new(expiry: i64) -> Self {
+        Self {
+            expiry,
+            settlement_price: None,
+            expired: false,
+        }
+    }
+
+    /// Sets the settlement price. Without it, the position is settled at the price of the latest
+    /// [`SETTLEMENT_EVENT`](crate::types::SETTLEMENT_EVENT) in the data, or at the last mid-price if
+    /// there is none.
+    pub fn settlement_price(self, settlement_price: f64) -> Self {
+        Self {
+            settlement_price: Some(settlement_price),
+            ..self
+        }
+    }
+
+    /// Sets the settlement price published by a [`SETTLEMENT_EVENT`](crate::types::SETTLEMENT_EVENT)
+    /// in the data.
+    pub fn set_settlement_price(&mut self, settlement_price: f64) {
+        self.settlement_price = Some(settlement_price);
+    }
+
+    /// Returns the settlement price, or the `mark` price if it is not given.
+    pub fn settlement_price_or(&self, mark: f64) -> f64 {
+        self.settlement_price.unwrap_or(mark)
+    }
+
+    /// Returns the expiry timestamp.
+    pub fn expiry(&self) -> i64 {
+        self.expiry
+    }
+
+    /// Updates the expiry state at the timestamp, and returns `true` if the contract has just
+    /// expired.
+    pub fn update(&mut self, timestamp: i64) -> bool {
+        if self.expired || timestamp < self.expiry {
+            return false;
+        }
+        self.expired = true;
+        true
+    }
+
+    /// Returns `true` if the contract has expired.
+    pub fn is_expired(&self) -> bool {
+        self.expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContractExpiry, SETTLEMENT_ORDER_ID};
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            ExchangeKind,
+        },
+        types::{
+            Bot,
+            OrdType,
+            Status,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+            SETTLEMENT_EVENT,
+        },
+    };
+
+    #[test]
+    fn test_update() {
+        let mut expiry = ContractExpiry::new(100);
+        assert!(!expiry.update(99));
+        assert!(expiry.update(100));
+        assert!(!expiry.update(101));
+        assert!(expiry.is_expired());
+        assert_eq!(expiry.settlement_price_or(50.0), 50.0);
+        expiry.set_settlement_price(49.5);
+        assert_eq!(expiry.settlement_price_or(50.0), 49.5);
+    }
+
+    #[test]
+    fn test_expiry_backtest() {
+        for exch_kind in [
+            ExchangeKind::NoPartialFillExchange,
+            ExchangeKind::PartialFillExchange,
+        ] {
+            let data = Data::from_slice(&[
+                event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                event(SETTLEMENT_EVENT, 90, 105.0, 0.0),
+                event(DEPTH_EVENT | BUY_EVENT, 100, 100.0, 5.0),
+                event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 6.0),
+            ]);
+            let mut hbt = backtest(
+                asset_builder(data, 1.0, 1.0)
+                    .exchange(exch_kind)
+                    .contract_expiry(ContractExpiry::new(100)),
+            );
+
+            hbt.elapse(9).unwrap();
+            hbt.submit_buy_order(0, 1, 101.0, 2.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            hbt.submit_buy_order(0, 2, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            assert_eq!(hbt.position(0), 2.0);
+
+            // Upon the expiry, the open order is canceled and the position is settled at the
+            // settlement price in the data.
+            hbt.elapse(100).unwrap();
+            assert_eq!(hbt.orders(0)[&2].status, Status::Canceled);
+            let settlement = &hbt.orders(0)[&SETTLEMENT_ORDER_ID];
+            assert_eq!(settlement.status, Status::Filled);
+            assert_eq!(settlement.exec_price_tick, 105);
+            assert_eq!(hbt.position(0), 0.0);
+            assert_eq!(hbt.state_values(0).balance, 8.0);
+
+            // The expired asset is inactive.
+            hbt.submit_buy_order(0, 3, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, true)
+                .unwrap();
+            assert_eq!(hbt.orders(0)[&3].status, Status::Expired);
+        }
+    }
+}
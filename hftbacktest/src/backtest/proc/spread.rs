@@ -0,0 +1,208 @@
+use crate::types::{OrdType, Order, Side, Status, TimeInForce};
+
+/// A leg of a [`Spread`], which trades `ratio` units of the asset per unit of the spread. A
+/// positive ratio buys the leg when the spread is bought, and a negative ratio sells it.
+#[derive(Clone, Copy, Debug)]
+pub struct SpreadLeg {
+    pub asset_no: usize,
+    pub ratio: f64,
+}
+
+/// Defines the legs of an exchange-listed spread instrument, such as a calendar spread, whose
+/// single order results in coordinated fills across its leg assets.
+///
+/// The spread is backtested as an asset of its own, with the spread's market data and order
+/// latency, so that its orders share a single latency path. Once the local receives a fill of a
+/// spread order, the fill of each leg is applied to the leg asset's position at the same time. As
+/// venues do, the legs are priced so that they add up to the spread price: every leg but the last
+/// one is priced at the mid-price of its market depth, and the last one makes up the difference.
+/// The leg fills are reported with the spread order's ID, charged the leg asset's fees, and are
+/// only applied to the local; the exchange processors of the leg assets don't see them. As the
+/// spread asset keeps its own position in the spread, its fees should be set to zero to avoid
+/// charging the fees twice.
+///
+/// Only [`Backtest`](crate::backtest::Backtest) with [`Local`](crate::backtest::proc::Local)
+/// supports the spread.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::Spread;
+///
+/// // Buying the calendar spread buys the far contract, asset 1, and sells the near one, asset 0.
+/// let spread = Spread::new().leg(1, 1.0).leg(0, -1.0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Spread {
+    legs: Vec<SpreadLeg>,
+}
+
+impl Spread {
+    /// Constructs an instance of `Spread` without legs.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a leg that trades `ratio` units of the asset per unit of the spread.
+    pub fn leg(mut self, asset_no: usize, ratio: f64) -> Self {
+        assert!(ratio != 0.0);
+        self.legs.push(SpreadLeg { asset_no, ratio });
+        self
+    }
+
+    /// Returns the legs.
+    pub fn legs(&self) -> &[SpreadLeg] {
+        &self.legs
+    }
+
+    /// Returns the fill of each leg for the fill of a spread order, along with the leg's asset
+    /// number.
+    ///
+    /// * `fill` - The fill of the spread order.
+    /// * `reference` - Returns the reference price and the tick size of the leg asset.
+    pub fn leg_fills<F>(&self, fill: &Order, mut reference: F) -> Vec<(usize, Order)>
+    where
+        F: FnMut(usize) -> (f64, f64),
+    {
+        let mut rest = fill.exec_price();
+        let mut leg_fills = Vec::with_capacity(self.legs.len());
+        for (i, leg) in self.legs.iter().enumerate() {
+            let (reference_price, tick_size) = reference(leg.asset_no);
+            let price = if i + 1 < self.legs.len() {
+                reference_price
+            } else {
+                rest / leg.ratio
+            };
+            let price_tick = (price / tick_size).round() as i64;
+            rest -= leg.ratio * price_tick as f64 * tick_size;
+
+            let side = if leg.ratio > 0.0 {
+                fill.side
+            } else if fill.side == Side::Buy {
+                Side::Sell
+            } else {
+                Side::Buy
+            };
+            let qty = leg.ratio.abs() * fill.exec_qty;
+            let mut leg_fill = Order::new(
+                fill.order_id,
+                price_tick,
+                tick_size,
+                qty,
+                side,
+                OrdType::Limit,
+                TimeInForce::GTC,
+            );
+            leg_fill.exec_price_tick = price_tick;
+            leg_fill.exec_qty = qty;
+            leg_fill.leaves_qty = 0.0;
+            leg_fill.status = Status::Filled;
+            leg_fill.maker = fill.maker;
+            leg_fill.exch_timestamp = fill.exch_timestamp;
+            leg_fill.local_timestamp = fill.local_timestamp;
+            leg_fills.push((leg.asset_no, leg_fill));
+        }
+        leg_fills
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Spread;
+    use crate::{
+        backtest::{
+            assettype::LinearAsset,
+            models::{ConstantLatency, RiskAdverseQueueModel},
+            proc::test_utils::event,
+            reader::Data,
+            AssetBuilder,
+            Backtest,
+            DataSource,
+        },
+        depth::HashMapMarketDepth,
+        types::{
+            Bot,
+            OrdType,
+            Order,
+            Side,
+            Status,
+            TimeInForce,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+        },
+    };
+
+    #[test]
+    fn test_leg_fills() {
+        let spread = Spread::new().leg(1, 1.0).leg(0, -2.0);
+        let mut fill = Order::new(
+            7,
+            -90,
+            0.1,
+            3.0,
+            Side::Sell,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+        fill.exec_price_tick = -90;
+        fill.exec_qty = 3.0;
+
+        let leg_fills = spread.leg_fills(&fill, |asset_no| match asset_no {
+            1 => (191.0, 1.0),
+            _ => (100.0, 0.5),
+        });
+        let (asset_no, far) = &leg_fills[0];
+        assert_eq!(*asset_no, 1);
+        assert_eq!(
+            (far.side, far.exec_price_tick, far.exec_qty),
+            (Side::Sell, 191, 3.0)
+        );
+        // -9 = 191 - 2 * 100
+        let (asset_no, near) = &leg_fills[1];
+        assert_eq!(*asset_no, 0);
+        assert_eq!(
+            (near.side, near.exec_price_tick, near.exec_qty),
+            (Side::Buy, 200, 6.0)
+        );
+        assert_eq!(near.order_id, 7);
+        assert_eq!(near.status, Status::Filled);
+    }
+
+    #[test]
+    fn test_spread_backtest() {
+        let asset = |bid: f64, ask: f64| {
+            let data = Data::from_slice(&[
+                event(DEPTH_EVENT | BUY_EVENT, 1, bid, 10.0),
+                event(DEPTH_EVENT | SELL_EVENT, 2, ask, 10.0),
+                event(DEPTH_EVENT | BUY_EVENT, 1_000, bid, 5.0),
+            ]);
+            AssetBuilder::new()
+                .data(vec![DataSource::Data(data)])
+                .latency_model(ConstantLatency::new(1, 1))
+                .asset_type(LinearAsset::new(1.0))
+                .queue_model(RiskAdverseQueueModel::new())
+                .depth(|| HashMapMarketDepth::new(0.5, 1.0))
+                .build()
+                .unwrap()
+        };
+        let mut hbt = Backtest::builder()
+            .add(asset(100.0, 101.0))
+            .add(asset(105.0, 106.0))
+            .add(asset(4.5, 5.0))
+            .spread(2, Spread::new().leg(1, 1.0).leg(0, -1.0))
+            .build()
+            .unwrap();
+
+        hbt.elapse(9).unwrap();
+        hbt.submit_buy_order(2, 1, 5.0, 2.0, TimeInForce::GTC, OrdType::Limit, true)
+            .unwrap();
+        assert_eq!(hbt.orders(2)[&1].status, Status::Filled);
+        assert_eq!(hbt.position(2), 2.0);
+
+        // The far leg is bought at its mid-price, and the near leg is sold 5 below it.
+        assert_eq!(hbt.position(1), 2.0);
+        assert_eq!(hbt.state_values(1).balance, -211.0);
+        assert_eq!(hbt.position(0), -2.0);
+        assert_eq!(hbt.state_values(0).balance, 201.0);
+    }
+}
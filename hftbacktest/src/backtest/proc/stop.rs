@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+
+use crate::{
+    depth::{INVALID_MAX, INVALID_MIN},
+    types::{Event, OrdType, Order, OrderId, Side, TriggerBy},
+};
+
+/// Configures the reference price that triggers the stop orders of an asset, and the auxiliary
+/// events that supply the mark price and the index price.
+///
+/// Stop orders with [`TriggerBy::Default`] follow the asset's trigger price source, while others
+/// follow their own. The mark price and the index price are the `px` of the latest
+/// [`AUX_EVENT`](crate::types::AUX_EVENT)s of the configured kinds, processed by the exchange at
+/// their exchange timestamps. A stop order by the mark price or the index price cannot be
+/// triggered until the first event of the kind arrives.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::{backtest::proc::StopTrigger, types::TriggerBy};
+///
+/// // Triggers the stop orders by the mark price carried by the auxiliary events of kind 130.
+/// let trigger = StopTrigger::new(TriggerBy::MarkPrice).mark_price_event(130);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StopTrigger {
+    trigger_by: TriggerBy,
+    mark_price_event: Option<u64>,
+    index_price_event: Option<u64>,
+}
+
+impl StopTrigger {
+    /// Constructs an instance of `StopTrigger` that triggers the asset's stop orders by the
+    /// `trigger_by` price.
+    pub fn new(trigger_by: TriggerBy) -> Self {
+        Self {
+            trigger_by,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the kind of the auxiliary event that carries the mark price.
+    pub fn mark_price_event(self, kind: u64) -> Self {
+        Self {
+            mark_price_event: Some(kind),
+            ..self
+        }
+    }
+
+    /// Sets the kind of the auxiliary event that carries the index price.
+    pub fn index_price_event(self, kind: u64) -> Self {
+        Self {
+            index_price_event: Some(kind),
+            ..self
+        }
+    }
+
+    /// Returns the reference price that triggers the order, resolving [`TriggerBy::Default`] to
+    /// the asset's configuration.
+    fn resolve(&self, order: &Order) -> TriggerBy {
+        match (order.trigger_by, self.trigger_by) {
+            (TriggerBy::Default, TriggerBy::Default) => TriggerBy::LastPrice,
+            (TriggerBy::Default, trigger_by) | (trigger_by, _) => trigger_by,
+        }
+    }
+}
+
+/// Holds the stop orders that have not yet been triggered at the exchange and monitors their
+/// trigger conditions against the simulated market.
+///
+/// By the last price, a buy stop order is triggered when a trade occurs at or above its trigger
+/// price or the best bid rises to or above it, and a sell stop order is triggered when a trade
+/// occurs at or below its trigger price or the best ask falls to or below it. By the mark price or
+/// the index price, a buy stop order is triggered when the price rises to or above its trigger
+/// price, and a sell stop order when the price falls to or below it. See [`StopTrigger`].
+#[derive(Default)]
+pub struct StopOrders {
+    // key: order_id, value: Order
+    orders: HashMap<OrderId, Order>,
+    trigger: StopTrigger,
+    mark_tick: Option<i64>,
+    index_tick: Option<i64>,
+}
+
+impl StopOrders {
+    /// Sets the trigger price source of the asset's stop orders.
+    pub fn set_trigger(&mut self, trigger: StopTrigger) {
+        self.trigger = trigger;
+    }
+
+    /// Updates the mark price or the index price if the event carries either of them.
+    pub fn update_reference(&mut self, ev: &Event, tick_size: f64) {
+        if !ev.is_aux() {
+            return;
+        }
+        let kind = ev.ev & 0xff;
+        let price_tick = (ev.px / tick_size).round() as i64;
+        if self.trigger.mark_price_event == Some(kind) {
+            self.mark_tick = Some(price_tick);
+        }
+        if self.trigger.index_price_event == Some(kind) {
+            self.index_tick = Some(price_tick);
+        }
+    }
+
+    /// Returns `true` if there are no untriggered stop orders.
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    /// Returns `true` if an untriggered stop order with the order ID exists.
+    pub fn contains(&self, order_id: &OrderId) -> bool {
+        self.orders.contains_key(order_id)
+    }
+
+    /// Inserts an untriggered stop order.
+    pub fn insert(&mut self, order: Order) {
+        self.orders.insert(order.order_id, order);
+    }
+
+    /// Returns the untriggered stop order with the order ID.
+    pub fn get(&self, order_id: &OrderId) -> Option<&Order> {
+        self.orders.get(order_id)
+    }
+
+    /// Removes the untriggered stop order with the order ID, which is canceled.
+    pub fn remove(&mut self, order_id: &OrderId) -> Option<Order> {
+        self.orders.remove(order_id)
+    }
+
+    /// Returns `true` if the stop order is triggered by its reference price: the trade at
+    /// `trade_tick`, if any, or the best bid and ask for the last price, or otherwise the latest
+    /// mark price or index price.
+    pub fn is_triggered(
+        &self,
+        order: &Order,
+        trade_tick: Option<i64>,
+        best_bid_tick: i64,
+        best_ask_tick: i64,
+    ) -> bool {
+        let reference_tick = match self.trigger.resolve(order) {
+            TriggerBy::MarkPrice => self.mark_tick,
+            TriggerBy::IndexPrice => self.index_tick,
+            TriggerBy::LastPrice | TriggerBy::Default => {
+                return Self::is_triggered_by_last(order, trade_tick, best_bid_tick, best_ask_tick)
+            }
+        };
+        match (order.side, reference_tick) {
+            (Side::Buy, Some(tick)) => tick >= order.trigger_price_tick,
+            (Side::Sell, Some(tick)) => tick <= order.trigger_price_tick,
+            _ => false,
+        }
+    }
+
+    fn is_triggered_by_last(
+        order: &Order,
+        trade_tick: Option<i64>,
+        best_bid_tick: i64,
+        best_ask_tick: i64,
+    ) -> bool {
+        match order.side {
+            Side::Buy => {
+                trade_tick.is_some_and(|t| t >= order.trigger_price_tick)
+                    || best_bid_tick >= order.trigger_price_tick
+            }
+            Side::Sell => {
+                trade_tick.is_some_and(|t| t <= order.trigger_price_tick)
+                    || best_ask_tick <= order.trigger_price_tick
+            }
+            Side::None | Side::Unsupported => false,
+        }
+    }
+
+    /// Removes and returns the stop orders triggered by their reference prices, in the order of
+    /// their order IDs. See [`StopOrders::is_triggered`].
+    pub fn take_triggered(
+        &mut self,
+        trade_tick: Option<i64>,
+        best_bid_tick: i64,
+        best_ask_tick: i64,
+    ) -> Vec<Order> {
+        let mut order_ids: Vec<OrderId> = self
+            .orders
+            .values()
+            .filter(|order| self.is_triggered(order, trade_tick, best_bid_tick, best_ask_tick))
+            .map(|order| order.order_id)
+            .collect();
+        order_ids.sort_unstable();
+        order_ids
+            .into_iter()
+            .map(|order_id| self.orders.remove(&order_id).unwrap())
+            .collect()
+    }
+
+    /// Removes and returns all the untriggered stop orders, in the order of their order IDs.
+    pub fn take_all(&mut self) -> Vec<Order> {
+        let mut orders: Vec<Order> = self.orders.drain().map(|(_, order)| order).collect();
+        orders.sort_unstable_by_key(|order| order.order_id);
+        orders
+    }
+}
+
+/// Prepares the triggered stop order to be processed by the matching engine. A stop-market order
+/// takes the opposite best, so its price is set to the opposite best. Returns `false` if there is
+/// no opposite best, in which case the order should be expired.
+pub fn prepare_triggered(order: &mut Order, best_bid_tick: i64, best_ask_tick: i64) -> bool {
+    if order.order_type == OrdType::StopMarket {
+        let price_tick = match order.side {
+            Side::Buy => best_ask_tick,
+            _ => best_bid_tick,
+        };
+        if price_tick == INVALID_MAX || price_tick == INVALID_MIN {
+            return false;
+        }
+        order.price_tick = price_tick;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prepare_triggered, StopOrders, StopTrigger};
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+        },
+        depth::INVALID_MIN,
+        types::{
+            Bot,
+            OrdType,
+            Order,
+            OrderRequest,
+            Side,
+            Status,
+            TimeInForce,
+            TriggerBy,
+            BUY_EVENT,
+            DEPTH_EVENT,
+            SELL_EVENT,
+            TRADE_EVENT,
+        },
+    };
+
+    fn stop(order_id: u64, side: Side, trigger_price_tick: i64, order_type: OrdType) -> Order {
+        let mut order = Order::new(order_id, 0, 1.0, 1.0, side, order_type, TimeInForce::GTC);
+        order.trigger_price_tick = trigger_price_tick;
+        order
+    }
+
+    #[test]
+    fn test_stop_orders() {
+        let mut stops = StopOrders::default();
+        stops.insert(stop(2, Side::Buy, 105, OrdType::StopMarket));
+        stops.insert(stop(1, Side::Buy, 103, OrdType::StopLimit));
+        stops.insert(stop(3, Side::Sell, 95, OrdType::StopMarket));
+
+        assert!(stops.take_triggered(None, 100, 101).is_empty());
+
+        // The trade triggers the buy stop at 103 but not at 105.
+        let triggered = stops.take_triggered(Some(104), 100, 101);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].order_id, 1);
+
+        // The best bid rising to the trigger price triggers the buy stop.
+        let triggered = stops.take_triggered(None, 105, 106);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].order_id, 2);
+
+        // The best ask falling to the trigger price triggers the sell stop.
+        assert!(stops.contains(&3));
+        let mut triggered = stops.take_triggered(None, 94, 95);
+        assert_eq!(triggered.len(), 1);
+        assert!(stops.is_empty());
+
+        // The stop-market order takes the opposite best, unless there is none.
+        assert!(!prepare_triggered(&mut triggered[0], INVALID_MIN, 95));
+        assert!(prepare_triggered(&mut triggered[0], 94, 95));
+        assert_eq!(triggered[0].price_tick, 94);
+    }
+
+    #[test]
+    fn test_stop_trigger() {
+        let mut stops = StopOrders::default();
+        stops.set_trigger(
+            StopTrigger::new(TriggerBy::MarkPrice)
+                .mark_price_event(130)
+                .index_price_event(131),
+        );
+        stops.insert(stop(1, Side::Buy, 103, OrdType::StopMarket));
+        let mut by_index = stop(2, Side::Sell, 95, OrdType::StopMarket);
+        by_index.trigger_by = TriggerBy::IndexPrice;
+        stops.insert(by_index);
+        let mut by_last = stop(3, Side::Buy, 103, OrdType::StopMarket);
+        by_last.trigger_by = TriggerBy::LastPrice;
+        stops.insert(by_last);
+
+        // Only the order by the last price is triggered by the trade, and the others are not
+        // triggered before their reference prices arrive.
+        let triggered = stops.take_triggered(Some(104), 94, 95);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].order_id, 3);
+
+        // The order following the asset's configuration is triggered by the mark price.
+        stops.update_reference(&event(130, 0, 103.0, 0.0), 1.0);
+        stops.update_reference(&event(131, 0, 96.0, 0.0), 1.0);
+        let triggered = stops.take_triggered(None, 94, 95);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].order_id, 1);
+
+        stops.update_reference(&event(131, 0, 95.0, 0.0), 1.0);
+        let triggered = stops.take_triggered(None, 100, 101);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].order_id, 2);
+    }
+
+    #[test]
+    fn test_stop_order_backtest() {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 100, 104.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 105, 101.0, 0.0),
+            event(TRADE_EVENT | BUY_EVENT, 200, 104.0, 1.0),
+            event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 5.0),
+        ]);
+        let mut hbt = backtest(asset_builder(data, 1.0, 1.0));
+
+        hbt.elapse(9).unwrap();
+        let stop = |order_id, side, trigger_price, price, order_type| OrderRequest {
+            order_id,
+            price,
+            qty: 1.0,
+            side,
+            time_in_force: TimeInForce::GTC,
+            order_type,
+            trigger_price,
+            expire_timestamp: 0,
+            reduce_only: false,
+            trigger_by: TriggerBy::Default,
+        };
+        hbt.submit_order(
+            0,
+            stop(1, Side::Buy, 103.0, 0.0, OrdType::StopMarket),
+            false,
+        )
+        .unwrap();
+        hbt.submit_order(
+            0,
+            stop(2, Side::Sell, 99.0, 98.0, OrdType::StopLimit),
+            false,
+        )
+        .unwrap();
+
+        // Both are held by the exchange since neither is triggered.
+        hbt.elapse(100).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::New);
+        assert_eq!(hbt.orders(0)[&1].trigger_price(), 103.0);
+        assert_eq!(hbt.orders(0)[&2].status, Status::New);
+        hbt.cancel(0, 2, false).unwrap();
+
+        // The trade triggers the buy stop-market order, which takes the best ask.
+        hbt.elapse(100).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::Filled);
+        assert_eq!(hbt.orders(0)[&1].exec_price(), 104.0);
+        assert_eq!(hbt.position(0), 1.0);
+        assert_eq!(hbt.orders(0)[&2].status, Status::Canceled);
+    }
+
+    #[test]
+    fn test_mark_price_trigger_backtest() {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+            event(TRADE_EVENT | BUY_EVENT, 50, 104.0, 1.0),
+            event(130, 100, 103.0, 0.0),
+            event(DEPTH_EVENT | BUY_EVENT, 300, 100.0, 5.0),
+        ]);
+        let mut hbt = backtest(
+            asset_builder(data, 1.0, 1.0)
+                .stop_trigger(StopTrigger::new(TriggerBy::MarkPrice).mark_price_event(130)),
+        );
+
+        hbt.elapse(9).unwrap();
+        hbt.submit_order(
+            0,
+            OrderRequest {
+                order_id: 1,
+                price: 0.0,
+                qty: 1.0,
+                side: Side::Buy,
+                time_in_force: TimeInForce::GTC,
+                order_type: OrdType::StopMarket,
+                trigger_price: 103.0,
+                expire_timestamp: 0,
+                reduce_only: false,
+                trigger_by: TriggerBy::Default,
+            },
+            false,
+        )
+        .unwrap();
+
+        // The trade beyond the trigger price doesn't trigger the order by the mark price.
+        hbt.elapse(50).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::New);
+        assert_eq!(hbt.orders(0)[&1].trigger_by, TriggerBy::Default);
+
+        // The mark price reaching the trigger price triggers the order, which takes the best ask.
+        hbt.elapse(100).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::Filled);
+        assert_eq!(hbt.orders(0)[&1].exec_price(), 101.0);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{
+    backtest::{
+        assettype::LinearAsset,
+        models::{ConstantLatency, QueueModel, RiskAdverseQueueModel},
+        reader::Data,
+        AssetBuilder,
+        Backtest,
+        DataSource,
+    },
+    depth::HashMapMarketDepth,
+    types::{Event, EXCH_EVENT, LOCAL_EVENT},
+};
+
+/// Constructs an event that is both an exchange event and a local event at the timestamp.
+pub(crate) fn event(ev: u64, ts: i64, px: f64, qty: f64) -> Event {
+    Event {
+        ev: ev | EXCH_EVENT | LOCAL_EVENT,
+        exch_ts: ts,
+        local_ts: ts,
+        px,
+        qty,
+        order_id: 0,
+        ival: 0,
+        fval: 0.0,
+    }
+}
+
+type TestAssetBuilder<QM = RiskAdverseQueueModel<HashMapMarketDepth>> =
+    AssetBuilder<ConstantLatency, LinearAsset, QM, HashMapMarketDepth>;
+
+/// Constructs an [`AssetBuilder`] of a linear asset over the data, with a constant latency of 1 in
+/// both directions and the risk-averse queue model.
+pub(crate) fn asset_builder(data: Data<Event>, tick_size: f64, lot_size: f64) -> TestAssetBuilder {
+    asset_builder_with_queue_model(data, tick_size, lot_size, RiskAdverseQueueModel::new())
+}
+
+/// Constructs an [`AssetBuilder`] as [`asset_builder`] does, but with the given queue model.
+pub(crate) fn asset_builder_with_queue_model<QM>(
+    data: Data<Event>,
+    tick_size: f64,
+    lot_size: f64,
+    queue_model: QM,
+) -> TestAssetBuilder<QM>
+where
+    QM: QueueModel<HashMapMarketDepth> + 'static,
+{
+    AssetBuilder::new()
+        .data(vec![DataSource::Data(data)])
+        .latency_model(ConstantLatency::new(1, 1))
+        .asset_type(LinearAsset::new(1.0))
+        .queue_model(queue_model)
+        .depth(move || HashMapMarketDepth::new(tick_size, lot_size))
+}
+
+/// Constructs a single-asset [`Backtest`] from the asset builder.
+pub(crate) fn backtest<QM>(asset: TestAssetBuilder<QM>) -> Backtest<HashMapMarketDepth>
+where
+    QM: QueueModel<HashMapMarketDepth> + 'static,
+{
+    Backtest::builder()
+        .add(asset.build().unwrap())
+        .build()
+        .unwrap()
+}
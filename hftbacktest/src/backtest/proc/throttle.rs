@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+
+/// Simulates the finite outbound queue of the local, such as a client-side throttle or the send
+/// buffer of the connection, which serializes a burst of order requests before they enter the
+/// order entry latency.
+///
+/// Order requests depart from the local at least `interval` apart, in the order they are sent. A
+/// request sent while the previous one has not yet departed waits in the queue, and its order
+/// entry latency starts from its departure, so a strategy firing many requests at once sees them
+/// reach the exchange one after another. If the queue already holds `capacity` waiting requests,
+/// the request is dropped and the local returns
+/// [`BacktestError::SendQueueFull`](crate::backtest::BacktestError::SendQueueFull). The local
+/// timestamp of the request remains the time it is sent. A batch of requests departs as a single
+/// message.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::proc::SendThrottle;
+///
+/// // Sends a request every 100 microseconds, queueing up to 20 requests.
+/// let throttle = SendThrottle::new(100_000).capacity(20);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SendThrottle {
+    interval: i64,
+    capacity: usize,
+    // The departure timestamps of the requests waiting in the queue.
+    queue: VecDeque<i64>,
+    last_departure: Option<i64>,
+    dropped: usize,
+}
+
+impl SendThrottle {
+    /// Constructs an instance of `SendThrottle` with the minimum `interval` between the departures
+    /// of order requests.
+    pub fn new(interval: i64) -> Self {
+        assert!(interval >= 0);
+        Self {
+            interval,
+            capacity: usize::MAX,
+            queue: VecDeque::new(),
+            last_departure: None,
+            dropped: 0,
+        }
+    }
+
+    /// Sets the maximum number of order requests waiting in the queue. The default is unbounded.
+    pub fn capacity(self, capacity: usize) -> Self {
+        Self { capacity, ..self }
+    }
+
+    /// Queues an order request sent at the timestamp, and returns the timestamp at which it
+    /// departs, or `None` if it is dropped as the queue is full.
+    pub fn depart(&mut self, timestamp: i64) -> Option<i64> {
+        while self
+            .queue
+            .front()
+            .is_some_and(|&departure| departure <= timestamp)
+        {
+            self.queue.pop_front();
+        }
+        let departure = self
+            .last_departure
+            .map_or(timestamp, |last| (last + self.interval).max(timestamp));
+        if departure > timestamp {
+            if self.queue.len() >= self.capacity {
+                self.dropped += 1;
+                return None;
+            }
+            self.queue.push_back(departure);
+        }
+        self.last_departure = Some(departure);
+        Some(departure)
+    }
+
+    /// Returns the number of order requests dropped so far.
+    pub fn dropped_count(&self) -> usize {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SendThrottle;
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, backtest, event},
+            reader::Data,
+            BacktestError,
+        },
+        types::{Bot, OrdType, Status, TimeInForce, BUY_EVENT, DEPTH_EVENT, SELL_EVENT},
+    };
+
+    #[test]
+    fn test_depart() {
+        let mut throttle = SendThrottle::new(10).capacity(2);
+        assert_eq!(throttle.depart(100), Some(100));
+        assert_eq!(throttle.depart(100), Some(110));
+        assert_eq!(throttle.depart(100), Some(120));
+        assert_eq!(throttle.depart(100), None);
+        assert_eq!(throttle.dropped_count(), 1);
+        // The request departing at 110 has left the queue.
+        assert_eq!(throttle.depart(110), Some(130));
+        assert_eq!(throttle.depart(200), Some(200));
+    }
+
+    #[test]
+    fn test_throttle_backtest() {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+            event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+            event(DEPTH_EVENT | BUY_EVENT, 1_000, 100.0, 5.0),
+        ]);
+        let mut hbt =
+            backtest(asset_builder(data, 1.0, 1.0).send_throttle(SendThrottle::new(5).capacity(1)));
+
+        hbt.elapse(9).unwrap();
+        for order_id in 1..=2 {
+            hbt.submit_buy_order(
+                0,
+                order_id,
+                99.0,
+                1.0,
+                TimeInForce::GTC,
+                OrdType::Limit,
+                false,
+            )
+            .unwrap();
+        }
+        // The queue is full while the second request waits to depart.
+        assert!(matches!(
+            hbt.submit_buy_order(0, 3, 99.0, 1.0, TimeInForce::GTC, OrdType::Limit, false),
+            Err(BacktestError::SendQueueFull)
+        ));
+        assert!(!hbt.orders(0).contains_key(&3));
+
+        // The burst departs 5ns apart, while the local timestamps remain the time they are sent.
+        hbt.elapse(100).unwrap();
+        assert_eq!(hbt.orders(0)[&1].status, Status::New);
+        assert_eq!(hbt.orders(0)[&1].exch_timestamp, 11);
+        assert_eq!(hbt.orders(0)[&2].exch_timestamp, 16);
+        assert_eq!(hbt.orders(0)[&2].local_timestamp, 10);
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{Error, ErrorKind, Result};
+
+use arrow_array::{
+    cast::AsArray,
+    types::{Float64Type, Int64Type, UInt64Type},
+    ArrayRef,
+    BooleanArray,
+    RecordBatch,
+};
+use arrow_cast::cast;
+use arrow_schema::DataType;
+use arrow_select::filter::filter_record_batch;
+
+use crate::backtest::reader::{
+    layout::{FieldKind, FieldLayout},
+    DataPtr,
+    Instrument,
+};
+
+pub(crate) fn to_io_error<E: ToString>(err: E) -> Error {
+    Error::new(ErrorKind::InvalidData, err.to_string())
+}
+
+/// Writes the column values into the given field of each record, starting at record `row`.
+fn write_column(
+    buf: &mut DataPtr,
+    field: &FieldLayout,
+    column: &ArrayRef,
+    row: usize,
+    record_size: usize,
+) -> Result<()> {
+    match field.kind {
+        FieldKind::Float => {
+            let column = cast(column, &DataType::Float64).map_err(to_io_error)?;
+            for (i, value) in column.as_primitive::<Float64Type>().iter().enumerate() {
+                field.write_f64(buf, (row + i) * record_size, value.unwrap_or(0.0));
+            }
+        }
+        FieldKind::Int | FieldKind::Bool => {
+            let column = cast(column, &DataType::Int64).map_err(to_io_error)?;
+            for (i, value) in column.as_primitive::<Int64Type>().iter().enumerate() {
+                field.write_i64(buf, (row + i) * record_size, value.unwrap_or(0));
+            }
+        }
+        FieldKind::UInt => {
+            let column = cast(column, &DataType::UInt64).map_err(to_io_error)?;
+            for (i, value) in column.as_primitive::<UInt64Type>().iter().enumerate() {
+                field.write_u64(buf, (row + i) * record_size, value.unwrap_or(0));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Copies the columns of the batch into the records starting at record `row`. Columns are matched
+/// to the fields by name. Fields whose names start with an underscore, such as padding fields,
+/// don't need to be present and are left zero-filled.
+pub(crate) fn write_batch(
+    buf: &mut DataPtr,
+    layout: &[FieldLayout],
+    batch: &RecordBatch,
+    row: usize,
+    record_size: usize,
+) -> Result<()> {
+    for field in layout {
+        match batch.column_by_name(&field.name) {
+            Some(column) => write_column(buf, field, column, row, record_size)?,
+            None if field.name.starts_with('_') => {}
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("column `{}` is not found", field.name),
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Selects the rows of the batch that belong to the instrument, by the `symbol` or
+/// `instrument_id` column.
+pub(crate) fn select_rows(batch: &RecordBatch, instrument: &Instrument) -> Result<RecordBatch> {
+    let name = instrument.column_name();
+    let column = batch.column_by_name(name).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("column `{name}` is not found"),
+        )
+    })?;
+    let mask = match instrument {
+        Instrument::Symbol(symbol) => {
+            let column = cast(column, &DataType::Utf8).map_err(to_io_error)?;
+            column
+                .as_string::<i32>()
+                .iter()
+                .map(|value| Some(value == Some(symbol.as_str())))
+                .collect::<BooleanArray>()
+        }
+        Instrument::Id(id) => {
+            let column = cast(column, &DataType::Int64).map_err(to_io_error)?;
+            column
+                .as_primitive::<Int64Type>()
+                .iter()
+                .map(|value| Some(value == Some(*id)))
+                .collect::<BooleanArray>()
+        }
+    };
+    filter_record_batch(batch, &mask).map_err(to_io_error)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, UInt64Array};
+
+    use crate::types::Event;
+
+    /// Converts the events into a record batch with a column for each field of [`Event`].
+    pub(crate) fn record_batch(events: &[Event]) -> RecordBatch {
+        let u64s = |f: fn(&Event) -> u64| -> ArrayRef {
+            Arc::new(events.iter().map(f).collect::<UInt64Array>())
+        };
+        let i64s = |f: fn(&Event) -> i64| -> ArrayRef {
+            Arc::new(events.iter().map(f).collect::<Int64Array>())
+        };
+        let f64s = |f: fn(&Event) -> f64| -> ArrayRef {
+            Arc::new(events.iter().map(f).collect::<Float64Array>())
+        };
+        RecordBatch::try_from_iter([
+            ("ev", u64s(|ev| ev.ev)),
+            ("exch_ts", i64s(|ev| ev.exch_ts)),
+            ("local_ts", i64s(|ev| ev.local_ts)),
+            ("px", f64s(|ev| ev.px)),
+            ("qty", f64s(|ev| ev.qty)),
+            ("order_id", u64s(|ev| ev.order_id)),
+            ("ival", i64s(|ev| ev.ival)),
+            ("fval", f64s(|ev| ev.fval)),
+        ])
+        .unwrap()
+    }
+}
@@ -0,0 +1,363 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Error, ErrorKind, Read, Result},
+    mem::size_of,
+};
+
+use crate::backtest::reader::{
+    layout::{field_layout, FieldKind, FieldLayout},
+    npy::NpyDTyped,
+    Data,
+    DataPtr,
+};
+
+/// Refers to a CSV column either by its header name or by its zero-based position.
+#[derive(Clone, Debug)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl From<&str> for Column {
+    fn from(name: &str) -> Self {
+        Column::Name(name.to_string())
+    }
+}
+
+impl From<String> for Column {
+    fn from(name: String) -> Self {
+        Column::Name(name)
+    }
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Self {
+        Column::Index(index)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Source {
+    Column(Column),
+    Constant(String),
+}
+
+/// Describes how the columns of a CSV file are mapped to the fields of the record, such as
+/// [`Event`](crate::types::Event).
+///
+/// Fields that are neither mapped to a column nor given a constant are zero-filled.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::{
+///     backtest::reader::{read_csv_file, CsvSchema},
+///     types::{Event, EXCH_EVENT, LOCAL_EVENT, TRADE_EVENT},
+/// };
+///
+/// let schema = CsvSchema::new()
+///     .constant("ev", &(EXCH_EVENT | LOCAL_EVENT | TRADE_EVENT).to_string())
+///     .column("exch_ts", "transact_time")
+///     .column("local_ts", "local_time")
+///     .column("px", "price")
+///     .column("qty", 3usize);
+/// let data = read_csv_file::<Event>("trades_20240501.csv", &schema).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct CsvSchema {
+    delimiter: char,
+    has_header: bool,
+    mapping: Vec<(String, Source)>,
+}
+
+impl CsvSchema {
+    /// Constructs an instance of `CsvSchema` for a comma-delimited file with a header row.
+    pub fn new() -> Self {
+        Self {
+            delimiter: ',',
+            has_header: true,
+            mapping: Vec::new(),
+        }
+    }
+
+    /// Sets the delimiter. The default value is `,`.
+    pub fn delimiter(self, delimiter: char) -> Self {
+        Self { delimiter, ..self }
+    }
+
+    /// Sets whether the first row is a header row. The default value is `true`. Columns can only
+    /// be referred to by their index if there is no header row.
+    pub fn has_header(self, has_header: bool) -> Self {
+        Self { has_header, ..self }
+    }
+
+    /// Maps the column to the field of the record.
+    pub fn column<C: Into<Column>>(mut self, field: &str, column: C) -> Self {
+        self.mapping
+            .push((field.to_string(), Source::Column(column.into())));
+        self
+    }
+
+    /// Sets the field of every record to the given value, which is parsed according to the field
+    /// type. This is useful when the file does not have a column for the field, for example, the
+    /// event flag of a trade-only file.
+    pub fn constant(mut self, field: &str, value: &str) -> Self {
+        self.mapping
+            .push((field.to_string(), Source::Constant(value.to_string())));
+        self
+    }
+}
+
+impl Default for CsvSchema {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_value(field: &FieldLayout, buf: &mut DataPtr, pos: usize, value: &str) -> Result<()> {
+    let value = value.trim();
+    let invalid = || {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("cannot parse `{value}` for field `{}`", field.name),
+        )
+    };
+    match field.kind {
+        FieldKind::Float => field.write_f64(buf, pos, value.parse().map_err(|_| invalid())?),
+        FieldKind::Int => field.write_i64(buf, pos, value.parse().map_err(|_| invalid())?),
+        FieldKind::UInt => field.write_u64(buf, pos, value.parse().map_err(|_| invalid())?),
+        FieldKind::Bool => {
+            let value = match value {
+                "1" | "true" | "True" | "TRUE" => 1,
+                "0" | "false" | "False" | "FALSE" => 0,
+                _ => return Err(invalid()),
+            };
+            field.write_u64(buf, pos, value)
+        }
+    }
+    Ok(())
+}
+
+/// Splits the record into its values. A value can be enclosed in double quotes as in RFC 4180, so
+/// that it can contain the delimiter, a line break, or a double quote, which is escaped by another
+/// double quote. Returns `None` if a quoted value is not closed, that is, if the record continues
+/// on the next line.
+fn split_record(record: &str, delimiter: char) -> Result<Option<Vec<String>>> {
+    let mut values = Vec::new();
+    let mut chars = record.chars().peekable();
+    loop {
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        value.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => value.push(c),
+                    None => return Ok(None),
+                }
+            }
+            match chars.next() {
+                Some(c) if c == delimiter => values.push(value),
+                None => {
+                    values.push(value);
+                    return Ok(Some(values));
+                }
+                Some(c) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("unexpected `{c}` after the quoted value `{value}`"),
+                    ));
+                }
+            }
+        } else {
+            loop {
+                match chars.next() {
+                    Some(c) if c == delimiter => break,
+                    Some(c) => value.push(c),
+                    None => {
+                        values.push(value);
+                        return Ok(Some(values));
+                    }
+                }
+            }
+            values.push(value);
+        }
+    }
+}
+
+/// Reads the records, skipping blank lines, and splits them into their values.
+fn read_records<R: Read>(reader: R, delimiter: char) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    // The lines read so far of a record that has a quoted value spanning multiple lines.
+    let mut pending: Option<String> = None;
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let record = match pending.take() {
+            Some(mut record) => {
+                record.push('\n');
+                record.push_str(&line);
+                record
+            }
+            None if line.trim().is_empty() => continue,
+            None => line,
+        };
+        match split_record(&record, delimiter)? {
+            Some(values) => records.push(values),
+            None => pending = Some(record),
+        }
+    }
+    if pending.is_some() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "the quoted value of the last record is not closed",
+        ));
+    }
+    Ok(records)
+}
+
+/// Reads CSV data into `Data` according to the given [`CsvSchema`]. Values can be quoted as in
+/// RFC 4180.
+pub fn read_csv<R: Read, D: NpyDTyped + Clone>(reader: R, schema: &CsvSchema) -> Result<Data<D>> {
+    let mut records = read_records(reader, schema.delimiter)?.into_iter();
+    let header = if schema.has_header {
+        match records.next() {
+            Some(names) => names
+                .iter()
+                .map(|name| name.trim().to_string())
+                .collect::<Vec<_>>(),
+            None => Vec::new(),
+        }
+    } else {
+        Vec::new()
+    };
+
+    let layout = field_layout::<D>()?;
+    let mut mapping = Vec::new();
+    for (name, source) in &schema.mapping {
+        let field = layout
+            .iter()
+            .find(|field| field.name == *name)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("field `{name}` is not found"),
+                )
+            })?;
+        let source = match source {
+            Source::Column(Column::Index(index)) => Source::Column(Column::Index(*index)),
+            Source::Column(Column::Name(column)) => {
+                let index = header.iter().position(|h| h == column).ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("column `{column}` is not found"),
+                    )
+                })?;
+                Source::Column(Column::Index(index))
+            }
+            Source::Constant(value) => Source::Constant(value.clone()),
+        };
+        mapping.push((field, source));
+    }
+
+    let rows = records.collect::<Vec<_>>();
+
+    let record_size = size_of::<D>();
+    let mut buf = DataPtr::new(rows.len() * record_size);
+    for (row_num, values) in rows.iter().enumerate() {
+        let pos = row_num * record_size;
+        for (field, source) in &mapping {
+            match source {
+                Source::Column(Column::Index(index)) => {
+                    let value = values.get(*index).ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::InvalidData,
+                            format!("row {} has no column {index}", row_num + 1),
+                        )
+                    })?;
+                    parse_value(field, &mut buf, pos, value)?;
+                }
+                Source::Constant(value) => parse_value(field, &mut buf, pos, value)?,
+                Source::Column(Column::Name(_)) => unreachable!(),
+            }
+        }
+    }
+    Ok(Data::from_data_ptr(buf, 0))
+}
+
+/// Reads a CSV file into `Data` according to the given [`CsvSchema`].
+pub fn read_csv_file<D: NpyDTyped + Clone>(filepath: &str, schema: &CsvSchema) -> Result<Data<D>> {
+    read_csv(File::open(filepath)?, schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_csv, split_record, CsvSchema};
+    use crate::types::{Event, EXCH_EVENT, LOCAL_EVENT, TRADE_EVENT};
+
+    #[test]
+    fn test_read_csv() {
+        let csv = "time,recv_time,price,size\n\
+                   1000,1005,50000.5,0.01\n\
+                   2000,2003,50001.0,0.25\n";
+        let ev = EXCH_EVENT | LOCAL_EVENT | TRADE_EVENT;
+        let schema = CsvSchema::new()
+            .constant("ev", &ev.to_string())
+            .column("exch_ts", "time")
+            .column("local_ts", 1usize)
+            .column("px", "price")
+            .column("qty", "size");
+        let data = read_csv::<_, Event>(csv.as_bytes(), &schema).unwrap();
+
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].ev, ev);
+        assert_eq!(data[0].exch_ts, 1000);
+        assert_eq!(data[0].local_ts, 1005);
+        assert_eq!(data[0].px, 50000.5);
+        assert_eq!(data[0].qty, 0.01);
+        assert_eq!(data[0].order_id, 0);
+        assert_eq!(data[1].exch_ts, 2000);
+        assert_eq!(data[1].qty, 0.25);
+    }
+
+    #[test]
+    fn test_read_csv_quoted() {
+        assert_eq!(
+            split_record("1000,\"a, \"\"b\"\"\",,\"\"", ',').unwrap(),
+            Some(vec![
+                "1000".to_string(),
+                "a, \"b\"".to_string(),
+                "".to_string(),
+                "".to_string()
+            ])
+        );
+        assert_eq!(split_record("1000,\"a", ',').unwrap(), None);
+        assert!(split_record("1000,\"a\"b", ',').is_err());
+
+        let csv = "\"time\",\"note\",\"price\"\n\
+                   1000,\"a, \"\"b\"\"\",\"50000.5\"\n\
+                   2000,\"multi\nline, note\",50001.0\n";
+        let schema = CsvSchema::new()
+            .column("exch_ts", "time")
+            .column("px", "price");
+        let data = read_csv::<_, Event>(csv.as_bytes(), &schema).unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].exch_ts, 1000);
+        assert_eq!(data[0].px, 50000.5);
+        assert_eq!(data[1].exch_ts, 2000);
+        assert_eq!(data[1].px, 50001.0);
+
+        let unclosed = "time,note\n1000,\"abc\n";
+        let schema = CsvSchema::new().column("exch_ts", "time");
+        assert!(read_csv::<_, Event>(unclosed.as_bytes(), &schema).is_err());
+    }
+
+    #[test]
+    fn test_read_csv_unknown_column() {
+        let csv = "time,price\n1000,1.0\n";
+        let schema = CsvSchema::new().column("exch_ts", "timestamp");
+        assert!(read_csv::<_, Event>(csv.as_bytes(), &schema).is_err());
+    }
+}
@@ -1,8 +1,9 @@
 use std::{
+    alloc::{alloc_zeroed, dealloc, Layout},
     marker::PhantomData,
-    mem::{forget, size_of},
+    mem::size_of,
     ops::{Index, IndexMut},
-    ptr::null_mut,
+    ptr::{null_mut, slice_from_raw_parts_mut},
     rc::Rc,
     slice::SliceIndex,
 };
@@ -53,6 +54,17 @@ where
         Self::from_data_ptr(DataPtr::from_ptr(ptr), offset)
     }
 
+    /// Constructs a `Data` by copying the given rows into a newly allocated buffer.
+    pub fn from_slice(rows: &[D]) -> Self {
+        let size = size_of_val(rows);
+        let mut ptr = DataPtr::new(size);
+        if size > 0 {
+            let bytes = unsafe { std::slice::from_raw_parts(rows.as_ptr() as *const u8, size) };
+            ptr[..].copy_from_slice(bytes);
+        }
+        Self::from_data_ptr(ptr, 0)
+    }
+
     /// Returns a reference to an element, without doing bounds checking.
     #[inline(always)]
     pub unsafe fn get_unchecked(&self, index: usize) -> &D {
@@ -87,14 +99,22 @@ where
 pub struct DataPtr {
     ptr: *mut [u8],
     managed: bool,
+    #[cfg(feature = "mmap")]
+    mmap: Option<memmap2::MmapMut>,
 }
 
 impl DataPtr {
+    /// Allocates a zero-initialized, 64-byte aligned buffer of exactly `size` bytes.
     pub fn new(size: usize) -> Self {
-        let vec = aligned_heap_array(size);
+        if size == 0 {
+            return Default::default();
+        }
+        let ptr = unsafe { alloc_zeroed(aligned_layout(size)) };
         Self {
-            ptr: Box::into_raw(vec),
+            ptr: slice_from_raw_parts_mut(ptr, size),
             managed: true,
+            #[cfg(feature = "mmap")]
+            mmap: None,
         }
     }
 
@@ -102,6 +122,20 @@ impl DataPtr {
         Self {
             ptr,
             managed: false,
+            #[cfg(feature = "mmap")]
+            mmap: None,
+        }
+    }
+
+    /// Constructs a `DataPtr` backed by the memory-mapped file. The pages are loaded by the OS on
+    /// access and can be evicted under memory pressure, so the file doesn't need to fit in memory.
+    #[cfg(feature = "mmap")]
+    pub fn from_mmap(mut mmap: memmap2::MmapMut) -> Self {
+        let ptr = slice_from_raw_parts_mut(mmap.as_mut_ptr(), mmap.len());
+        Self {
+            ptr,
+            managed: false,
+            mmap: Some(mmap),
         }
     }
 
@@ -122,6 +156,8 @@ impl Default for DataPtr {
         Self {
             ptr: null_mut::<[u8; 0]>() as *mut [u8],
             managed: false,
+            #[cfg(feature = "mmap")]
+            mmap: None,
         }
     }
 }
@@ -153,33 +189,12 @@ where
 impl Drop for DataPtr {
     fn drop(&mut self) {
         if self.managed {
-            let _ = unsafe { Box::from_raw(self.ptr) };
+            unsafe { dealloc(self.ptr as *mut u8, aligned_layout(self.ptr.len())) };
         }
     }
 }
 
-#[repr(C, align(64))]
-struct Align64([u8; 64]);
-
-fn aligned_heap_array(size: usize) -> Box<[u8]> {
-    let capacity = (size / size_of::<Align64>()) + 1;
-    let mut aligned: Vec<Align64> = Vec::with_capacity(capacity);
-    unsafe {
-        aligned.set_len(capacity);
-    }
-
-    let ptr = aligned.as_mut_ptr();
-    let len = aligned.len();
-    let cap = aligned.capacity();
-
-    forget(aligned);
-
-    let vec = unsafe {
-        Vec::from_raw_parts(
-            ptr.cast(),
-            len * size_of::<Align64>(),
-            cap * size_of::<Align64>(),
-        )
-    };
-    vec.into_boxed_slice()
+#[inline]
+fn aligned_layout(size: usize) -> Layout {
+    Layout::from_size_align(size, 64).unwrap()
 }
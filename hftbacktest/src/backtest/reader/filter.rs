@@ -0,0 +1,481 @@
+use std::{collections::HashSet, fmt::Debug};
+
+use dyn_clone::DynClone;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    backtest::{
+        models::{ActivityScaling, EmpiricalDistribution, LatencyCorrelation, LatencyDistribution},
+        reader::{Data, POD},
+    },
+    types::{Event, TimeUnit, BUY_EVENT, DEPTH_EVENT, EXCH_EVENT, LOCAL_EVENT, SELL_EVENT},
+};
+
+/// Filters the rows of `Data` as it is loaded by the [`Reader`](crate::backtest::reader::Reader).
+pub trait DataFilter<D>: DynClone + Debug
+where
+    D: POD + Clone,
+{
+    /// Returns the filtered `Data`, or `None` if all rows are kept as they are.
+    fn filter(&self, data: &Data<D>) -> Option<Data<D>>;
+}
+
+dyn_clone::clone_trait_object!(<D> DataFilter<D> where D: POD + Clone);
+
+/// Keeps only the events that occur within the time window `[begin_ts, end_ts)`.
+///
+/// The window is applied to the exchange timestamp for the exchange-side events and to the local
+/// timestamp for the local-side events, so a row whose exchange timestamp is inside the window
+/// but whose local timestamp is after the window remains only as an exchange event.
+#[derive(Clone, Debug)]
+pub struct TimeWindow {
+    begin_ts: i64,
+    end_ts: i64,
+}
+
+impl TimeWindow {
+    /// Constructs an instance of `TimeWindow`.
+    pub fn new(begin_ts: i64, end_ts: i64) -> Self {
+        Self { begin_ts, end_ts }
+    }
+
+    #[inline]
+    fn contains(&self, ts: i64) -> bool {
+        self.begin_ts <= ts && ts < self.end_ts
+    }
+}
+
+impl DataFilter<Event> for TimeWindow {
+    fn filter(&self, data: &Data<Event>) -> Option<Data<Event>> {
+        let mut rows = Vec::with_capacity(data.len());
+        let mut modified = false;
+        for row in 0..data.len() {
+            let ev = &data[row];
+            let mut flags = ev.ev;
+            if ev.is(EXCH_EVENT) && !self.contains(ev.exch_ts) {
+                flags &= !EXCH_EVENT;
+            }
+            if ev.is(LOCAL_EVENT) && !self.contains(ev.local_ts) {
+                flags &= !LOCAL_EVENT;
+            }
+            if flags != ev.ev {
+                modified = true;
+            }
+            if flags & (EXCH_EVENT | LOCAL_EVENT) != 0 {
+                rows.push(Event {
+                    ev: flags,
+                    ..ev.clone()
+                });
+            }
+        }
+        modified.then(|| Data::from_slice(&rows))
+    }
+}
+
+/// Thins depth update events to at most one update per price level per `interval`, while keeping
+/// all other events, such as trades, snapshots, and depth clears, for fast coarse experiments.
+///
+/// Time is divided into buckets of `interval`, and only the last update of each price level in a
+/// bucket is kept, so the market depth at the end of each bucket is the same as with the full
+/// data, but intermediate changes within a bucket are lost. Buckets are based on the exchange
+/// timestamp, or on the local timestamp for local-only events.
+#[derive(Clone, Debug)]
+pub struct DepthThinning {
+    interval: i64,
+}
+
+impl DepthThinning {
+    /// Constructs an instance of `DepthThinning`.
+    pub fn new(interval: i64) -> Self {
+        Self { interval }
+    }
+
+    /// Returns the thinned events. This can be used to write a thinned data file in advance.
+    pub fn thin(&self, events: &[Event]) -> Vec<Event> {
+        let keep = self.keep(events.len(), |row| &events[row]);
+        events
+            .iter()
+            .zip(keep)
+            .filter(|(_, keep)| *keep)
+            .map(|(ev, _)| ev.clone())
+            .collect()
+    }
+
+    fn keep<'a>(&self, len: usize, row: impl Fn(usize) -> &'a Event) -> Vec<bool> {
+        let mut keep = vec![true; len];
+        let mut seen = HashSet::new();
+        // Scans backward so that the last update of each price level in a bucket is kept.
+        for rn in (0..len).rev() {
+            let ev = row(rn);
+            if ev.ev & 0xff != DEPTH_EVENT {
+                continue;
+            }
+            let ts = if ev.is(EXCH_EVENT) {
+                ev.exch_ts
+            } else {
+                ev.local_ts
+            };
+            let key = (
+                ev.ev & (EXCH_EVENT | LOCAL_EVENT | BUY_EVENT | SELL_EVENT),
+                ev.px.to_bits(),
+                ts.div_euclid(self.interval),
+            );
+            if !seen.insert(key) {
+                keep[rn] = false;
+            }
+        }
+        keep
+    }
+}
+
+impl DataFilter<Event> for DepthThinning {
+    fn filter(&self, data: &Data<Event>) -> Option<Data<Event>> {
+        let keep = self.keep(data.len(), |row| &data[row]);
+        if keep.iter().all(|&keep| keep) {
+            return None;
+        }
+        let rows = (0..data.len())
+            .filter(|&row| keep[row])
+            .map(|row| data[row].clone())
+            .collect::<Vec<_>>();
+        Some(Data::from_slice(&rows))
+    }
+}
+
+/// Converts the timestamps of data recorded in the [`TimeUnit`] into nanoseconds, the time unit of
+/// the engine, so that the data doesn't need to be rewritten.
+#[derive(Clone, Debug)]
+pub struct TimeScale {
+    unit: TimeUnit,
+}
+
+impl TimeScale {
+    /// Constructs an instance of `TimeScale` for data recorded in the `unit`.
+    pub fn new(unit: TimeUnit) -> Self {
+        Self { unit }
+    }
+
+    /// Returns the time unit of the data.
+    pub fn unit(&self) -> TimeUnit {
+        self.unit
+    }
+}
+
+impl DataFilter<Event> for TimeScale {
+    fn filter(&self, data: &Data<Event>) -> Option<Data<Event>> {
+        if self.unit == TimeUnit::Nanosecond {
+            return None;
+        }
+        let rows = (0..data.len())
+            .map(|row| Event {
+                exch_ts: self.unit.to_nanos(data[row].exch_ts),
+                local_ts: self.unit.to_nanos(data[row].local_ts),
+                ..data[row].clone()
+            })
+            .collect::<Vec<_>>();
+        Some(Data::from_slice(&rows))
+    }
+}
+
+/// Scales the feed latency, the difference between the local timestamp and the exchange
+/// timestamp, with the recent market activity in the data itself, since a feed also slows down
+/// when the market is busy. See [`ActivityScaling`].
+///
+/// The local timestamps are kept in order, so that an event doesn't overtake the preceding ones.
+/// The activity is counted within each `Data` as it is loaded, so it starts from zero at the
+/// beginning of each file or chunk.
+#[derive(Clone, Debug)]
+pub struct ActivityFeedLatency {
+    scaling: ActivityScaling,
+}
+
+impl ActivityFeedLatency {
+    /// Constructs an instance of `ActivityFeedLatency`.
+    pub fn new(scaling: ActivityScaling) -> Self {
+        Self { scaling }
+    }
+}
+
+impl DataFilter<Event> for ActivityFeedLatency {
+    fn filter(&self, data: &Data<Event>) -> Option<Data<Event>> {
+        let mut meter = self.scaling.meter();
+        let mut last_local_ts = i64::MIN;
+        let mut modified = false;
+        let mut rows = Vec::with_capacity(data.len());
+        for row in 0..data.len() {
+            let ev = &data[row];
+            meter.observe(ev);
+            if !ev.is(LOCAL_EVENT) {
+                rows.push(ev.clone());
+                continue;
+            }
+            let scale = self.scaling.scale(meter.count(ev.exch_ts));
+            let latency = ev.local_ts - ev.exch_ts;
+            let local_ts = (ev.exch_ts + (latency as f64 * scale) as i64).max(last_local_ts);
+            last_local_ts = local_ts;
+            if local_ts != ev.local_ts {
+                modified = true;
+            }
+            rows.push(Event {
+                local_ts,
+                ..ev.clone()
+            });
+        }
+        modified.then(|| Data::from_slice(&rows))
+    }
+}
+
+/// Applies the latency factor of the [`LatencyCorrelation`] to the feed latency, the difference
+/// between the local timestamp and the exchange timestamp, so that feed latency shares the common
+/// stochastic component with order latency.
+///
+/// The local timestamps are kept in order, so that an event doesn't overtake the preceding ones.
+/// The factor is drawn for each row, and the local-side row of an event split into exchange-side
+/// and local-side rows gets its own draw.
+#[derive(Clone, Debug)]
+pub struct CorrelatedFeedLatency {
+    correlation: LatencyCorrelation,
+}
+
+impl CorrelatedFeedLatency {
+    /// Constructs an instance of `CorrelatedFeedLatency`.
+    pub fn new(correlation: LatencyCorrelation) -> Self {
+        Self { correlation }
+    }
+}
+
+impl DataFilter<Event> for CorrelatedFeedLatency {
+    fn filter(&self, data: &Data<Event>) -> Option<Data<Event>> {
+        if data.len() == 0 {
+            return None;
+        }
+        // The noise is seeded by the beginning of the data, so that the same factors are drawn if
+        // the data is reloaded.
+        let mut rng = self.correlation.feed_rng(data[0].exch_ts);
+        let mut last_local_ts = i64::MIN;
+        let mut rows = Vec::with_capacity(data.len());
+        for row in 0..data.len() {
+            let ev = &data[row];
+            if !ev.is(LOCAL_EVENT) {
+                rows.push(ev.clone());
+                continue;
+            }
+            let factor = self.correlation.factor(ev.exch_ts, &mut rng);
+            let latency = ev.local_ts - ev.exch_ts;
+            let local_ts = (ev.exch_ts + (latency as f64 * factor) as i64).max(last_local_ts);
+            last_local_ts = local_ts;
+            rows.push(Event {
+                local_ts,
+                ..ev.clone()
+            });
+        }
+        Some(Data::from_slice(&rows))
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Delay {
+    Constant(i64),
+    Parametric(LatencyDistribution),
+    Empirical(EmpiricalDistribution),
+}
+
+/// Adds an extra delay on top of the recorded local timestamps, which answers questions like what
+/// if the colocation were 2ms farther away from the exchange, without regenerating the data files.
+/// The delay is either constant or drawn for each row from a latency distribution.
+///
+/// The local timestamps are kept in order, so that an event doesn't overtake the preceding ones.
+#[derive(Clone, Debug)]
+pub struct FeedDelay {
+    delay: Delay,
+    seed: u64,
+}
+
+impl FeedDelay {
+    /// Constructs an instance of `FeedDelay` that adds the constant `delay` in nanoseconds.
+    pub fn constant(delay: i64) -> Self {
+        Self {
+            delay: Delay::Constant(delay),
+            seed: 0,
+        }
+    }
+
+    /// Constructs an instance of `FeedDelay` that adds the delay drawn from the parametric
+    /// distribution.
+    pub fn parametric(distribution: LatencyDistribution) -> Self {
+        Self {
+            delay: Delay::Parametric(distribution),
+            seed: 0,
+        }
+    }
+
+    /// Constructs an instance of `FeedDelay` that adds the delay drawn from the empirical
+    /// distribution.
+    pub fn empirical(distribution: EmpiricalDistribution) -> Self {
+        Self {
+            delay: Delay::Empirical(distribution),
+            seed: 0,
+        }
+    }
+
+    /// Seeds the random number generator for drawing the delay. The default value is `0`.
+    pub fn seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+}
+
+impl DataFilter<Event> for FeedDelay {
+    fn filter(&self, data: &Data<Event>) -> Option<Data<Event>> {
+        if data.len() == 0 || matches!(self.delay, Delay::Constant(0)) {
+            return None;
+        }
+        // The random number generator is seeded by the beginning of the data, so that the same
+        // delays are drawn if the data is reloaded.
+        let mut rng = StdRng::seed_from_u64(self.seed ^ data[0].exch_ts as u64);
+        let mut last_local_ts = i64::MIN;
+        let mut rows = Vec::with_capacity(data.len());
+        for row in 0..data.len() {
+            let ev = &data[row];
+            if !ev.is(LOCAL_EVENT) {
+                rows.push(ev.clone());
+                continue;
+            }
+            let delay = match &self.delay {
+                Delay::Constant(delay) => *delay,
+                Delay::Parametric(distribution) => distribution.sample(&mut rng) as i64,
+                Delay::Empirical(distribution) => distribution.quantile(rng.gen::<f64>()) as i64,
+            };
+            let local_ts = (ev.local_ts + delay).max(last_local_ts);
+            last_local_ts = local_ts;
+            rows.push(Event {
+                local_ts,
+                ..ev.clone()
+            });
+        }
+        Some(Data::from_slice(&rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActivityFeedLatency, DataFilter, DepthThinning, FeedDelay, TimeScale, TimeWindow};
+    use crate::{
+        backtest::{
+            models::{ActivityKind, ActivityScaling, EmpiricalDistribution},
+            proc::test_utils,
+            reader::Data,
+        },
+        types::{Event, TimeUnit, BUY_EVENT, DEPTH_EVENT, EXCH_EVENT, LOCAL_EVENT, TRADE_EVENT},
+    };
+
+    fn event(exch_ts: i64, local_ts: i64) -> Event {
+        Event {
+            local_ts,
+            ..test_utils::event(DEPTH_EVENT, exch_ts, 1.0, 1.0)
+        }
+    }
+
+    #[test]
+    fn test_time_window() {
+        let data = Data::from_slice(&[event(5, 15), event(10, 20), event(20, 30), event(30, 40)]);
+        let window = TimeWindow::new(10, 30);
+        let filtered = window.filter(&data).unwrap();
+        assert_eq!(filtered.len(), 3);
+        assert_eq!(filtered[0].ev, LOCAL_EVENT | DEPTH_EVENT);
+        assert_eq!(filtered[0].exch_ts, 5);
+        assert_eq!(filtered[1].ev, EXCH_EVENT | LOCAL_EVENT | DEPTH_EVENT);
+        assert_eq!(filtered[2].ev, EXCH_EVENT | DEPTH_EVENT);
+        assert_eq!(filtered[2].exch_ts, 20);
+
+        assert!(TimeWindow::new(0, 100).filter(&data).is_none());
+    }
+
+    #[test]
+    fn test_depth_thinning() {
+        let depth = |exch_ts, px, qty| Event {
+            local_ts: exch_ts + 1,
+            ..test_utils::event(DEPTH_EVENT | BUY_EVENT, exch_ts, px, qty)
+        };
+        let trade = Event {
+            local_ts: 4,
+            ..test_utils::event(TRADE_EVENT | BUY_EVENT, 3, 1.0, 1.0)
+        };
+        let events = [
+            depth(0, 10.0, 1.0),
+            depth(2, 10.0, 2.0),
+            trade.clone(),
+            depth(5, 11.0, 1.0),
+            depth(9, 10.0, 3.0),
+            depth(10, 10.0, 4.0),
+        ];
+        let thinned = DepthThinning::new(10).thin(&events);
+        assert_eq!(
+            thinned,
+            vec![
+                trade,
+                depth(5, 11.0, 1.0),
+                depth(9, 10.0, 3.0),
+                depth(10, 10.0, 4.0)
+            ]
+        );
+
+        let data = Data::from_slice(&events);
+        assert_eq!(DepthThinning::new(10).filter(&data).unwrap().len(), 4);
+        assert!(DepthThinning::new(1).filter(&data).is_none());
+    }
+
+    #[test]
+    fn test_time_scale() {
+        let data = Data::from_slice(&[event(1, 2), event(3, 5)]);
+        assert!(TimeScale::new(TimeUnit::Nanosecond).filter(&data).is_none());
+
+        let scaled = TimeScale::new(TimeUnit::Microsecond).filter(&data).unwrap();
+        assert_eq!((scaled[0].exch_ts, scaled[0].local_ts), (1_000, 2_000));
+        assert_eq!((scaled[1].exch_ts, scaled[1].local_ts), (3_000, 5_000));
+    }
+
+    #[test]
+    fn test_activity_feed_latency() {
+        let trade = |exch_ts, local_ts| Event {
+            local_ts,
+            ..test_utils::event(TRADE_EVENT, exch_ts, 1.0, 1.0)
+        };
+        let filter = ActivityFeedLatency::new(ActivityScaling::new(ActivityKind::Trades, 10, 1.0));
+        let data = Data::from_slice(&[trade(0, 10), trade(1, 11), trade(2, 12), event(3, 4)]);
+        let filtered = filter.filter(&data).unwrap();
+        let local_ts = (0..filtered.len())
+            .map(|row| filtered[row].local_ts)
+            .collect::<Vec<_>>();
+        // The depth event isn't counted as activity, but it doesn't overtake the trades.
+        assert_eq!(local_ts, vec![10, 21, 32, 32]);
+
+        let quiet = Data::from_slice(&[trade(0, 10), trade(20, 30)]);
+        assert!(filter.filter(&quiet).is_none());
+    }
+
+    #[test]
+    fn test_feed_delay() {
+        let data = Data::from_slice(&[event(0, 10), event(5, 12), event(8, 20)]);
+        let delayed = FeedDelay::constant(100).filter(&data).unwrap();
+        let local_ts = (0..delayed.len())
+            .map(|row| delayed[row].local_ts)
+            .collect::<Vec<_>>();
+        assert_eq!(local_ts, vec![110, 112, 120]);
+        assert!(FeedDelay::constant(0).filter(&data).is_none());
+
+        let distribution =
+            EmpiricalDistribution::from_quantiles(vec![(0.0, 0.0), (1.0, 100.0)]).unwrap();
+        let filter = FeedDelay::empirical(distribution).seed(1);
+        let delayed = filter.filter(&data).unwrap();
+        for row in 0..delayed.len() {
+            assert!(delayed[row].local_ts >= data[row].local_ts);
+            assert!(delayed[row].local_ts <= data[row].local_ts + 100);
+            if row > 0 {
+                assert!(delayed[row].local_ts >= delayed[row - 1].local_ts);
+            }
+        }
+        // The same delays are drawn if the data is reloaded.
+        let reloaded = filter.filter(&data).unwrap();
+        assert!((0..delayed.len()).all(|row| delayed[row].local_ts == reloaded[row].local_ts));
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fmt::{Debug, Formatter},
+    rc::Rc,
+    sync::mpsc::Receiver,
+};
+
+use crate::backtest::reader::{Data, POD};
+
+type Batches<D> = Box<dyn Iterator<Item = Vec<D>>>;
+
+/// Produces data programmatically, such as synthetic order books, stress scenarios, or fuzzing
+/// inputs, to be read by the [`Reader`](crate::backtest::reader::Reader) without writing temporary
+/// files. See [`DataSource::Iterator`](crate::backtest::reader::DataSource::Iterator).
+///
+/// Since the data can only be produced once, a `Generator` can be added to a single `Reader`, and
+/// the produced data is kept until every clone of the `Reader`, such as the ones for the local
+/// processor and the exchange processor, has read it.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::{
+///     backtest::{reader::Generator, DataSource},
+///     types::Event,
+/// };
+///
+/// let source = DataSource::Iterator(Generator::from_iter(
+///     (0..1_000_000).map(|i| Event {
+///         ev: 0,
+///         exch_ts: i,
+///         local_ts: i + 1,
+///         px: 0.0,
+///         qty: 0.0,
+///         order_id: 0,
+///         ival: 0,
+///         fval: 0.0,
+///     }),
+///     10_000,
+/// ));
+/// ```
+#[derive(Clone)]
+pub struct Generator<D>(Rc<RefCell<Option<Batches<D>>>>);
+
+impl<D> Generator<D>
+where
+    D: POD + Clone + 'static,
+{
+    /// Constructs a `Generator` that reads the rows produced by the iterator in batches of
+    /// `batch_size` rows.
+    pub fn from_iter<I>(iter: I, batch_size: usize) -> Self
+    where
+        I: IntoIterator<Item = D>,
+        I::IntoIter: 'static,
+    {
+        let mut iter = iter.into_iter();
+        let batch_size = batch_size.max(1);
+        Self::from_batches(std::iter::from_fn(move || {
+            let batch = iter.by_ref().take(batch_size).collect::<Vec<_>>();
+            (!batch.is_empty()).then_some(batch)
+        }))
+    }
+
+    /// Constructs a `Generator` that reads the batches produced by the iterator. Each batch is read
+    /// as a single `Data`.
+    pub fn from_batches<I>(batches: I) -> Self
+    where
+        I: IntoIterator<Item = Vec<D>>,
+        I::IntoIter: 'static,
+    {
+        Self(Rc::new(RefCell::new(Some(Box::new(batches.into_iter())))))
+    }
+
+    /// Constructs a `Generator` that reads the batches sent through the channel, which allows the
+    /// data to be produced on another thread. Reading blocks until the next batch is sent, and the
+    /// data ends when all senders are dropped.
+    pub fn from_channel(receiver: Receiver<Vec<D>>) -> Self {
+        Self::from_batches(receiver)
+    }
+}
+
+impl<D> Generator<D> {
+    /// Takes the batches to read. Returns `None` if they have already been taken.
+    pub(crate) fn take(&self) -> Option<Batches<D>> {
+        self.0.borrow_mut().take()
+    }
+}
+
+impl<D> Debug for Generator<D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Generator")
+            .field("taken", &self.0.borrow().is_none())
+            .finish()
+    }
+}
+
+struct GeneratorState<D>
+where
+    D: POD + Clone,
+{
+    batches: Batches<D>,
+    done: bool,
+    // The produced data that has not yet been read by every cursor, and the position of its
+    // first entry.
+    produced: VecDeque<Data<D>>,
+    base: usize,
+    positions: HashMap<usize, usize>,
+    next_id: usize,
+}
+
+impl<D> GeneratorState<D>
+where
+    D: POD + Clone,
+{
+    fn register(&mut self, position: usize) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.positions.insert(id, position);
+        id
+    }
+
+    /// Drops the produced data that every cursor has read.
+    fn trim(&mut self) {
+        let min = self.positions.values().copied().min().unwrap_or(usize::MAX);
+        while self.base < min && !self.produced.is_empty() {
+            self.produced.pop_front();
+            self.base += 1;
+        }
+    }
+}
+
+/// A position of a [`Reader`](crate::backtest::reader::Reader) in the data produced by a
+/// [`Generator`]. Cloning the cursor, along with the `Reader`, registers a new position, so that
+/// the produced data is kept until every cursor has read it.
+pub(crate) struct GeneratorCursor<D>
+where
+    D: POD + Clone,
+{
+    state: Rc<RefCell<GeneratorState<D>>>,
+    id: usize,
+}
+
+impl<D> GeneratorCursor<D>
+where
+    D: POD + Clone,
+{
+    pub fn new(batches: Batches<D>) -> Self {
+        let mut state = GeneratorState {
+            batches,
+            done: false,
+            produced: VecDeque::new(),
+            base: 0,
+            positions: HashMap::new(),
+            next_id: 0,
+        };
+        let id = state.register(0);
+        Self {
+            state: Rc::new(RefCell::new(state)),
+            id,
+        }
+    }
+
+    /// Returns the next `Data`, producing it if this cursor is the first to read it, in which case
+    /// `prepare` is applied to it. Returns `None` at the end of the data.
+    pub fn next(&mut self, prepare: impl Fn(Data<D>) -> Data<D>) -> Option<Data<D>> {
+        let mut state = self.state.borrow_mut();
+        let position = state.positions[&self.id];
+        let data = match state.produced.get(position - state.base).cloned() {
+            Some(data) => data,
+            None => loop {
+                if state.done {
+                    return None;
+                }
+                match state.batches.next() {
+                    Some(batch) if batch.is_empty() => {}
+                    Some(batch) => {
+                        let data = prepare(Data::from_slice(&batch));
+                        state.produced.push_back(data.clone());
+                        break data;
+                    }
+                    None => {
+                        state.done = true;
+                    }
+                }
+            },
+        };
+        state.positions.insert(self.id, position + 1);
+        state.trim();
+        Some(data)
+    }
+}
+
+impl<D> Clone for GeneratorCursor<D>
+where
+    D: POD + Clone,
+{
+    fn clone(&self) -> Self {
+        let position = self.state.borrow().positions[&self.id];
+        let id = self.state.borrow_mut().register(position);
+        Self {
+            state: self.state.clone(),
+            id,
+        }
+    }
+}
+
+impl<D> Drop for GeneratorCursor<D>
+where
+    D: POD + Clone,
+{
+    fn drop(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.positions.remove(&self.id);
+        state.trim();
+    }
+}
+
+impl<D> Debug for GeneratorCursor<D>
+where
+    D: POD + Clone,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("GeneratorCursor")
+            .field("id", &self.id)
+            .field("position", &state.positions.get(&self.id))
+            .field("produced", &state.produced.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread};
+
+    use super::{Generator, GeneratorCursor};
+    use crate::types::Event;
+
+    fn event(exch_ts: i64) -> Event {
+        Event {
+            ev: 0,
+            exch_ts,
+            local_ts: exch_ts,
+            px: 0.0,
+            qty: 0.0,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_cursor() {
+        let generator = Generator::from_iter((0..5).map(event), 2);
+        let mut first = GeneratorCursor::new(generator.take().unwrap());
+        assert!(generator.take().is_none());
+        let mut second = first.clone();
+
+        assert_eq!(first.next(|data| data).unwrap()[0].exch_ts, 0);
+        assert_eq!(first.next(|data| data).unwrap()[1].exch_ts, 3);
+        assert_eq!(first.state.borrow().produced.len(), 2);
+
+        // The data is kept until the other cursor reads it.
+        assert_eq!(second.next(|data| data).unwrap()[1].exch_ts, 1);
+        assert_eq!(first.state.borrow().produced.len(), 1);
+        drop(second);
+        assert_eq!(first.state.borrow().produced.len(), 0);
+
+        assert_eq!(first.next(|data| data).unwrap().len(), 1);
+        assert!(first.next(|data| data).is_none());
+    }
+
+    #[test]
+    fn test_channel() {
+        let (sender, receiver) = mpsc::channel();
+        let producer = thread::spawn(move || {
+            for i in 0..3 {
+                sender.send(vec![event(i)]).unwrap();
+            }
+        });
+        let generator = Generator::from_channel(receiver);
+        let mut cursor = GeneratorCursor::new(generator.take().unwrap());
+        for i in 0..3 {
+            assert_eq!(cursor.next(|data| data).unwrap()[0].exch_ts, i);
+        }
+        assert!(cursor.next(|data| data).is_none());
+        producer.join().unwrap();
+    }
+}
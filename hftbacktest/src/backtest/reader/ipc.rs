@@ -0,0 +1,188 @@
+use std::{
+    fmt::{Debug, Formatter},
+    fs::File,
+    io::{Error, ErrorKind, Result},
+    marker::PhantomData,
+    mem::size_of,
+};
+
+use arrow_ipc::reader::FileReader;
+
+use crate::backtest::reader::{
+    columnar::{select_rows, to_io_error, write_batch},
+    layout::field_layout,
+    npy::NpyDTyped,
+    Data,
+    DataPtr,
+    Instrument,
+};
+
+/// Reads an Arrow IPC (Feather V2) file one record batch at a time into `Data`.
+///
+/// The file is opened and its footer is parsed once, and only the requested batch is decoded,
+/// using the footer to seek to it directly.
+///
+/// Columns are matched to the fields of `D` by name and cast to the field types. Fields whose
+/// names start with an underscore, such as padding fields, don't need to be present in the file
+/// and are zero-filled.
+///
+/// The batches are copied rather than memory-mapped. Arrow stores each column contiguously, while
+/// `Data` stores each record contiguously in the layout of `D`, so the columns have to be
+/// interleaved into records, even for a file that is neither compressed nor filtered.
+pub struct ArrowChunkReader<D> {
+    filepath: String,
+    reader: FileReader<File>,
+    _d: PhantomData<D>,
+}
+
+impl<D> ArrowChunkReader<D>
+where
+    D: NpyDTyped + Clone,
+{
+    /// Opens the Arrow IPC file and reads its footer.
+    pub fn open(filepath: &str) -> Result<Self> {
+        let reader = FileReader::try_new(File::open(filepath)?, None).map_err(to_io_error)?;
+        Ok(Self {
+            filepath: filepath.to_string(),
+            reader,
+            _d: PhantomData,
+        })
+    }
+
+    /// Returns the number of record batches in the file.
+    pub fn num_chunks(&self) -> usize {
+        self.reader.num_batches()
+    }
+
+    /// Reads the given record batch.
+    ///
+    /// If `instrument` is given, only the rows of that instrument are read from a file that
+    /// contains multiple instruments. See [`Instrument`].
+    pub fn read_chunk(
+        &mut self,
+        batch_num: usize,
+        instrument: Option<&Instrument>,
+    ) -> Result<Data<D>> {
+        if batch_num >= self.num_chunks() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("record batch {batch_num} is out of range"),
+            ));
+        }
+        self.reader.set_index(batch_num).map_err(to_io_error)?;
+        let batch = self
+            .reader
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "record batch is missing"))?
+            .map_err(to_io_error)?;
+        let batch = match instrument {
+            Some(instrument) => select_rows(&batch, instrument)?,
+            None => batch,
+        };
+
+        let layout = field_layout::<D>()?;
+        let record_size = size_of::<D>();
+        let mut buf = DataPtr::new(batch.num_rows() * record_size);
+        write_batch(&mut buf, &layout, &batch, 0, record_size)?;
+        Ok(Data::from_data_ptr(buf, 0))
+    }
+}
+
+impl<D> Debug for ArrowChunkReader<D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArrowChunkReader")
+            .field("filepath", &self.filepath)
+            .field("num_batches", &self.reader.num_batches())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, fs::File, path::PathBuf, sync::Arc};
+
+    use arrow_array::{ArrayRef, RecordBatch, StringArray};
+    use arrow_ipc::writer::FileWriter;
+
+    use super::ArrowChunkReader;
+    use crate::{
+        backtest::reader::{columnar::tests::record_batch, Data, Instrument},
+        types::{Event, BUY_EVENT, DEPTH_EVENT, EXCH_EVENT, LOCAL_EVENT, SELL_EVENT},
+    };
+
+    fn events(range: std::ops::Range<i64>) -> Vec<Event> {
+        range
+            .map(|i| Event {
+                ev: EXCH_EVENT
+                    | LOCAL_EVENT
+                    | DEPTH_EVENT
+                    | if i % 2 == 0 { BUY_EVENT } else { SELL_EVENT },
+                exch_ts: 1000 * i,
+                local_ts: 1000 * i + 5,
+                px: 100.0 + i as f64 * 0.5,
+                qty: i as f64,
+                order_id: i as u64,
+                ival: -i,
+                fval: i as f64 * 0.25,
+            })
+            .collect()
+    }
+
+    fn write_file(batches: &[RecordBatch]) -> PathBuf {
+        let path = env::temp_dir().join(format!("{}.arrow", uuid::Uuid::new_v4()));
+        let mut writer =
+            FileWriter::try_new(File::create(&path).unwrap(), &batches[0].schema()).unwrap();
+        for batch in batches {
+            writer.write(batch).unwrap();
+        }
+        writer.finish().unwrap();
+        path
+    }
+
+    fn assert_data(data: &Data<Event>, events: &[Event]) {
+        let expected = Data::from_slice(events);
+        assert_eq!(data.len(), expected.len());
+        for row in 0..data.len() {
+            assert_eq!(data[row], expected[row]);
+        }
+    }
+
+    #[test]
+    fn test_read_batches() {
+        let batches = [events(0..3), events(3..5)];
+        let path = write_file(&[record_batch(&batches[0]), record_batch(&batches[1])]);
+
+        let mut reader = ArrowChunkReader::<Event>::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(reader.num_chunks(), 2);
+        // The batches can be read in any order.
+        assert_data(&reader.read_chunk(1, None).unwrap(), &batches[1]);
+        assert_data(&reader.read_chunk(0, None).unwrap(), &batches[0]);
+        assert!(reader.read_chunk(2, None).is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_instrument() {
+        let events = events(0..4);
+        let batch = record_batch(&events);
+        let symbols: ArrayRef = Arc::new(StringArray::from(vec!["A", "B", "A", "B"]));
+        let mut columns = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .zip(batch.columns().iter().cloned())
+            .collect::<Vec<_>>();
+        columns.push(("symbol".to_string(), symbols));
+        let path = write_file(&[RecordBatch::try_from_iter(columns).unwrap()]);
+
+        let mut reader = ArrowChunkReader::<Event>::open(path.to_str().unwrap()).unwrap();
+        let data = reader
+            .read_chunk(0, Some(&Instrument::Symbol("B".to_string())))
+            .unwrap();
+        assert_data(&data, &[events[1].clone(), events[3].clone()]);
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    mem::size_of,
+};
+
+use crate::backtest::reader::{
+    npy::{Field, NpyDTyped},
+    DataPtr,
+};
+
+/// Primitive kind of a structured record field.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub(crate) enum FieldKind {
+    Float,
+    Int,
+    UInt,
+    Bool,
+}
+
+/// Location and type of a field within a `repr(C)` record, derived from its numpy dtype
+/// description.
+#[derive(Clone, Debug)]
+pub(crate) struct FieldLayout {
+    pub name: String,
+    pub kind: FieldKind,
+    pub size: usize,
+    pub offset: usize,
+}
+
+impl FieldLayout {
+    #[inline]
+    fn put(&self, buf: &mut DataPtr, pos: usize, bytes: &[u8]) {
+        let i = pos + self.offset;
+        buf[i..(i + self.size)].copy_from_slice(bytes);
+    }
+
+    /// Writes a floating-point value into this field of the record at `pos`, converting it to the
+    /// field's type.
+    pub fn write_f64(&self, buf: &mut DataPtr, pos: usize, value: f64) {
+        match self.kind {
+            FieldKind::Float => self.write_float(buf, pos, value),
+            FieldKind::Int => self.write_int(buf, pos, value as i64),
+            FieldKind::UInt => self.write_uint(buf, pos, value as u64),
+            FieldKind::Bool => self.put(buf, pos, &[(value != 0.0) as u8]),
+        }
+    }
+
+    /// Writes a signed integer value into this field of the record at `pos`, converting it to the
+    /// field's type.
+    pub fn write_i64(&self, buf: &mut DataPtr, pos: usize, value: i64) {
+        match self.kind {
+            FieldKind::Float => self.write_float(buf, pos, value as f64),
+            FieldKind::Int => self.write_int(buf, pos, value),
+            FieldKind::UInt => self.write_uint(buf, pos, value as u64),
+            FieldKind::Bool => self.put(buf, pos, &[(value != 0) as u8]),
+        }
+    }
+
+    /// Writes an unsigned integer value into this field of the record at `pos`, converting it to
+    /// the field's type.
+    pub fn write_u64(&self, buf: &mut DataPtr, pos: usize, value: u64) {
+        match self.kind {
+            FieldKind::Float => self.write_float(buf, pos, value as f64),
+            FieldKind::Int => self.write_int(buf, pos, value as i64),
+            FieldKind::UInt => self.write_uint(buf, pos, value),
+            FieldKind::Bool => self.put(buf, pos, &[(value != 0) as u8]),
+        }
+    }
+
+    #[inline]
+    fn get<'a>(&self, buf: &'a [u8], pos: usize) -> &'a [u8] {
+        let i = pos + self.offset;
+        &buf[i..(i + self.size)]
+    }
+
+    /// Reads this field of the record at `pos` as a floating-point value.
+    pub fn read_f64(&self, buf: &[u8], pos: usize) -> f64 {
+        match self.kind {
+            FieldKind::Float => match self.size {
+                8 => f64::from_ne_bytes(self.get(buf, pos).try_into().unwrap()),
+                _ => f32::from_ne_bytes(self.get(buf, pos).try_into().unwrap()) as f64,
+            },
+            FieldKind::Int => self.read_i64(buf, pos) as f64,
+            FieldKind::UInt | FieldKind::Bool => self.read_u64(buf, pos) as f64,
+        }
+    }
+
+    /// Reads this field of the record at `pos` as a signed integer value.
+    pub fn read_i64(&self, buf: &[u8], pos: usize) -> i64 {
+        let bytes = self.get(buf, pos);
+        match self.kind {
+            FieldKind::Float => self.read_f64(buf, pos) as i64,
+            FieldKind::Int => match self.size {
+                8 => i64::from_ne_bytes(bytes.try_into().unwrap()),
+                4 => i32::from_ne_bytes(bytes.try_into().unwrap()) as i64,
+                2 => i16::from_ne_bytes(bytes.try_into().unwrap()) as i64,
+                _ => bytes[0] as i8 as i64,
+            },
+            FieldKind::UInt | FieldKind::Bool => self.read_u64(buf, pos) as i64,
+        }
+    }
+
+    /// Reads this field of the record at `pos` as an unsigned integer value.
+    pub fn read_u64(&self, buf: &[u8], pos: usize) -> u64 {
+        let bytes = self.get(buf, pos);
+        match self.kind {
+            FieldKind::Float => self.read_f64(buf, pos) as u64,
+            FieldKind::Int => self.read_i64(buf, pos) as u64,
+            FieldKind::UInt | FieldKind::Bool => match self.size {
+                8 => u64::from_ne_bytes(bytes.try_into().unwrap()),
+                4 => u32::from_ne_bytes(bytes.try_into().unwrap()) as u64,
+                2 => u16::from_ne_bytes(bytes.try_into().unwrap()) as u64,
+                _ => bytes[0] as u64,
+            },
+        }
+    }
+
+    fn write_float(&self, buf: &mut DataPtr, pos: usize, value: f64) {
+        match self.size {
+            8 => self.put(buf, pos, &value.to_ne_bytes()),
+            _ => self.put(buf, pos, &(value as f32).to_ne_bytes()),
+        }
+    }
+
+    fn write_int(&self, buf: &mut DataPtr, pos: usize, value: i64) {
+        match self.size {
+            8 => self.put(buf, pos, &value.to_ne_bytes()),
+            4 => self.put(buf, pos, &(value as i32).to_ne_bytes()),
+            2 => self.put(buf, pos, &(value as i16).to_ne_bytes()),
+            _ => self.put(buf, pos, &(value as i8).to_ne_bytes()),
+        }
+    }
+
+    fn write_uint(&self, buf: &mut DataPtr, pos: usize, value: u64) {
+        match self.size {
+            8 => self.put(buf, pos, &value.to_ne_bytes()),
+            4 => self.put(buf, pos, &(value as u32).to_ne_bytes()),
+            2 => self.put(buf, pos, &(value as u16).to_ne_bytes()),
+            _ => self.put(buf, pos, &[value as u8]),
+        }
+    }
+}
+
+/// Parses a numpy dtype string, such as `<f8`, into its primitive kind and size in bytes.
+fn parse_type(ty: &str) -> Result<(FieldKind, usize)> {
+    let unsupported = || {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("unsupported field type `{ty}`"),
+        )
+    };
+    let ty_ = ty.trim_start_matches(['<', '>', '|', '=']);
+    if ty_ == "bool" || ty_ == "b1" {
+        return Ok((FieldKind::Bool, 1));
+    }
+    let kind = match ty_.get(..1) {
+        Some("f") => FieldKind::Float,
+        Some("i") => FieldKind::Int,
+        Some("u") => FieldKind::UInt,
+        _ => return Err(unsupported()),
+    };
+    let size = ty_[1..].parse::<usize>().map_err(|_| unsupported())?;
+    Ok((kind, size))
+}
+
+/// Computes the field layout of the record type `D` from its numpy dtype description. The record
+/// is assumed to be `repr(C)`, so each field is placed at the next offset aligned to its size.
+pub(crate) fn field_layout<D: NpyDTyped>() -> Result<Vec<FieldLayout>> {
+    let (layout, size) = descr_layout(&D::descr(), true)?;
+    if size > size_of::<D>() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "field layout exceeds the record size",
+        ));
+    }
+    Ok(layout)
+}
+
+/// Computes the field layout from a numpy dtype description, with each field placed at the next
+/// offset aligned to its size if `aligned` is `true`, as in a `repr(C)` record, or packed without
+/// padding otherwise, as numpy lays out structured arrays by default. Returns the layout and the
+/// offset at which the last field ends.
+pub(crate) fn descr_layout(descr: &[Field], aligned: bool) -> Result<(Vec<FieldLayout>, usize)> {
+    let mut layout = Vec::new();
+    let mut offset = 0usize;
+    for field in descr {
+        let (kind, size) = parse_type(&field.ty)?;
+        if aligned {
+            offset = offset.div_ceil(size) * size;
+        }
+        layout.push(FieldLayout {
+            name: field.name.clone(),
+            kind,
+            size,
+            offset,
+        });
+        offset += size;
+    }
+    Ok((layout, offset))
+}
@@ -0,0 +1,217 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Computes the SHA-256 checksum of the file as a lowercase hex string.
+pub fn sha256_file(path: &str) -> Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let hash = hasher.finalize();
+    let mut hex = String::with_capacity(hash.len() * 2);
+    for c in hash {
+        write!(&mut hex, "{:02x}", c).unwrap();
+    }
+    Ok(hex)
+}
+
+/// A data file listed in a [`Manifest`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The path of the data file. A relative path is relative to the directory of the manifest
+    /// file.
+    pub path: String,
+    /// The timestamp of the first event in the file.
+    pub begin_ts: i64,
+    /// The timestamp of the last event in the file.
+    pub end_ts: i64,
+    /// The SHA-256 checksum of the file as a lowercase hex string.
+    pub sha256: String,
+}
+
+/// Provides a dataset manifest that lists data files in the order to read, along with their time
+/// coverage and SHA-256 checksums, so that corrupt files and out-of-order file lists can be
+/// detected before the backtest starts rather than failing deep into a run.
+///
+/// The manifest is stored as a JSON file.
+/// ```json
+/// {
+///   "files": [
+///     {
+///       "path": "btcusdt_20240501.npz",
+///       "begin_ts": 1714521600000000000,
+///       "end_ts": 1714607999999000000,
+///       "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+///     }
+///   ]
+/// }
+/// ```
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::backtest::reader::Manifest;
+///
+/// let mut manifest = Manifest::new();
+/// manifest
+///     .add_file("btcusdt_20240501.npz", 1714521600000000000, 1714607999999000000)
+///     .unwrap();
+/// manifest.save("btcusdt.json").unwrap();
+///
+/// let manifest = Manifest::load("btcusdt.json").unwrap();
+/// manifest.verify().unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: Vec<ManifestEntry>,
+    // The directory against which relative paths are resolved.
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl Manifest {
+    /// Constructs an empty `Manifest` whose relative paths are relative to the current directory.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Loads the manifest file.
+    pub fn load(path: &str) -> Result<Self> {
+        let mut manifest: Manifest = serde_json::from_reader(BufReader::new(File::open(path)?))
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+        manifest.dir = Path::new(path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        Ok(manifest)
+    }
+
+    /// Saves the manifest file.
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)
+            .map_err(|error| Error::new(ErrorKind::Other, error))?;
+        writer.flush()
+    }
+
+    /// Appends the data file, computing its checksum. Files should be added in the order to read.
+    pub fn add_file(&mut self, path: &str, begin_ts: i64, end_ts: i64) -> Result<()> {
+        let sha256 = sha256_file(&self.resolve(path))?;
+        self.files.push(ManifestEntry {
+            path: path.to_string(),
+            begin_ts,
+            end_ts,
+            sha256,
+        });
+        Ok(())
+    }
+
+    fn resolve(&self, path: &str) -> String {
+        self.dir.join(path).to_string_lossy().to_string()
+    }
+
+    /// Returns the paths of the data files in the order to read, with relative paths resolved.
+    pub fn paths(&self) -> Vec<String> {
+        self.files
+            .iter()
+            .map(|entry| self.resolve(&entry.path))
+            .collect()
+    }
+
+    /// Verifies that the time coverage of each file is valid and the files are listed in order
+    /// without overlapping, and then that every file exists and matches its checksum.
+    pub fn verify(&self) -> Result<()> {
+        let mut prev: Option<&ManifestEntry> = None;
+        for entry in &self.files {
+            if entry.begin_ts > entry.end_ts {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "`{}` has an invalid time coverage: begin_ts {} is after end_ts {}",
+                        entry.path, entry.begin_ts, entry.end_ts
+                    ),
+                ));
+            }
+            if let Some(prev) = prev {
+                if entry.begin_ts < prev.end_ts {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "`{}` is out of order: it begins at {}, before `{}` ends at {}",
+                            entry.path, entry.begin_ts, prev.path, prev.end_ts
+                        ),
+                    ));
+                }
+            }
+            prev = Some(entry);
+        }
+
+        for entry in &self.files {
+            let path = self.resolve(&entry.path);
+            let sha256 = sha256_file(&path).map_err(|error| {
+                Error::new(error.kind(), format!("`{path}` cannot be read: {error}"))
+            })?;
+            if !sha256.eq_ignore_ascii_case(&entry.sha256) {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "`{path}` is corrupt: the SHA-256 checksum is {sha256}, but {} is expected",
+                        entry.sha256
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::Manifest;
+
+    #[test]
+    fn test_verify() {
+        let dir = env::temp_dir().join(format!("manifest_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("day1.npz"), b"day1").unwrap();
+        fs::write(dir.join("day2.npz"), b"day2").unwrap();
+
+        let mut manifest = Manifest {
+            dir: dir.clone(),
+            ..Default::default()
+        };
+        manifest.add_file("day1.npz", 0, 10).unwrap();
+        manifest.add_file("day2.npz", 10, 20).unwrap();
+        let path = dir.join("manifest.json");
+        let path = path.to_str().unwrap();
+        manifest.save(path).unwrap();
+
+        let loaded = Manifest::load(path).unwrap();
+        assert_eq!(loaded, manifest);
+        assert!(loaded.verify().is_ok());
+        assert_eq!(loaded.paths()[1], dir.join("day2.npz").to_str().unwrap());
+
+        let mut out_of_order = loaded.clone();
+        out_of_order.files.swap(0, 1);
+        assert!(out_of_order.verify().is_err());
+
+        fs::write(dir.join("day2.npz"), b"corrupt").unwrap();
+        assert!(loaded.verify().is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
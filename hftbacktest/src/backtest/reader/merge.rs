@@ -0,0 +1,127 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::{
+    backtest::reader::Data,
+    types::{Event, EXCH_EVENT, LOCAL_EVENT},
+};
+
+/// Merges the rows of the sources that have the given flag, ordered by the timestamp returned by
+/// `ts`. Ties are broken by the order of the sources, so rows with the same timestamp keep the
+/// source order.
+fn merge_by<F>(data: &[Data<Event>], flag: u64, ts: F) -> Vec<Event>
+where
+    F: Fn(&Event) -> i64,
+{
+    let next_row = |source: usize, mut row: usize| {
+        while row < data[source].len() {
+            if data[source][row].is(flag) {
+                return Some(row);
+            }
+            row += 1;
+        }
+        None
+    };
+
+    let mut heap = BinaryHeap::new();
+    for (source, rows) in data.iter().enumerate() {
+        if let Some(row) = next_row(source, 0) {
+            heap.push(Reverse((ts(&rows[row]), source, row)));
+        }
+    }
+
+    let mut merged = Vec::new();
+    while let Some(Reverse((_, source, row))) = heap.pop() {
+        let ev = &data[source][row];
+        merged.push(Event {
+            ev: (ev.ev & !(EXCH_EVENT | LOCAL_EVENT)) | flag,
+            ..ev.clone()
+        });
+        if let Some(row) = next_row(source, row + 1) {
+            heap.push(Reverse((ts(&data[source][row]), source, row)));
+        }
+    }
+    merged
+}
+
+/// Performs a k-way merge of [`Event`] data that overlap in time, such as a trade file and a depth
+/// file delivered separately. Each source needs to be correctly ordered on its own, that is,
+/// exchange events by exchange timestamp and local events by local timestamp.
+///
+/// The exchange events of all sources are merged by exchange timestamp and the local events by
+/// local timestamp. Since the two orders can differ, a row that is both an exchange and a local
+/// event is split into an exchange-only row and a local-only row.
+pub fn merge_events(data: &[Data<Event>]) -> Data<Event> {
+    let exch = merge_by(data, EXCH_EVENT, |ev| ev.exch_ts);
+    let local = merge_by(data, LOCAL_EVENT, |ev| ev.local_ts);
+
+    let mut merged = Vec::with_capacity(exch.len() + local.len());
+    let mut exch = exch.into_iter().peekable();
+    let mut local = local.into_iter().peekable();
+    loop {
+        let take_exch = match (exch.peek(), local.peek()) {
+            (Some(exch_ev), Some(local_ev)) => exch_ev.exch_ts <= local_ev.local_ts,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        if take_exch {
+            merged.push(exch.next().unwrap());
+        } else {
+            merged.push(local.next().unwrap());
+        }
+    }
+    Data::from_slice(&merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_events;
+    use crate::{
+        backtest::{proc::test_utils, reader::Data},
+        types::{Event, DEPTH_EVENT, EXCH_EVENT, LOCAL_EVENT, TRADE_EVENT},
+    };
+
+    fn event(ev: u64, exch_ts: i64, local_ts: i64) -> Event {
+        Event {
+            local_ts,
+            ..test_utils::event(ev, exch_ts, 1.0, 1.0)
+        }
+    }
+
+    #[test]
+    fn test_merge_events() {
+        let depth = Data::from_slice(&[event(DEPTH_EVENT, 10, 20), event(DEPTH_EVENT, 30, 32)]);
+        let trades = Data::from_slice(&[event(TRADE_EVENT, 15, 33), event(TRADE_EVENT, 31, 35)]);
+        let merged = merge_events(&[depth, trades]);
+
+        let mut exch = Vec::new();
+        let mut local = Vec::new();
+        for row in 0..merged.len() {
+            let ev = &merged[row];
+            if ev.is(EXCH_EVENT) {
+                exch.push((ev.ev & 0xff, ev.exch_ts));
+            }
+            if ev.is(LOCAL_EVENT) {
+                local.push((ev.ev & 0xff, ev.local_ts));
+            }
+        }
+        assert_eq!(
+            exch,
+            vec![
+                (DEPTH_EVENT, 10),
+                (TRADE_EVENT, 15),
+                (DEPTH_EVENT, 30),
+                (TRADE_EVENT, 31)
+            ]
+        );
+        assert_eq!(
+            local,
+            vec![
+                (DEPTH_EVENT, 20),
+                (DEPTH_EVENT, 32),
+                (TRADE_EVENT, 33),
+                (TRADE_EVENT, 35)
+            ]
+        );
+    }
+}
@@ -1,7 +1,60 @@
+#[cfg(any(feature = "parquet", feature = "arrow"))]
+mod columnar;
+mod csv;
 mod data;
+mod filter;
+mod generator;
+#[cfg(feature = "arrow")]
+mod ipc;
+mod layout;
+#[cfg(feature = "manifest")]
+mod manifest;
+mod merge;
 mod npy;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod query;
 mod reader;
+#[cfg(feature = "remote")]
+mod remote;
+mod roll;
+mod validation;
 
+pub use csv::{read_csv, read_csv_file, Column, CsvSchema};
 pub use data::{Data, DataPtr, POD};
-pub use npy::{read_npy_file, read_npz_file, write_npy, Field, NpyDTyped, NpyHeader};
-pub use reader::{Cache, DataSource, Reader};
+pub use filter::{
+    ActivityFeedLatency,
+    CorrelatedFeedLatency,
+    DataFilter,
+    DepthThinning,
+    FeedDelay,
+    TimeScale,
+    TimeWindow,
+};
+pub use generator::Generator;
+#[cfg(feature = "arrow")]
+pub use ipc::ArrowChunkReader;
+#[cfg(feature = "manifest")]
+pub use manifest::{sha256_file, Manifest, ManifestEntry};
+pub use merge::merge_events;
+#[cfg(feature = "mmap")]
+pub use npy::read_npy_file_mmap;
+pub use npy::{
+    read_npy_file,
+    read_npz_file,
+    write_npy,
+    write_npz,
+    Field,
+    NpyChunkReader,
+    NpyDTyped,
+    NpyHeader,
+};
+pub use query::Query;
+pub use reader::{Cache, DataSource, Instrument, Reader};
+#[cfg(feature = "remote")]
+pub use remote::download;
+pub use roll::Contract;
+pub use validation::{DataValidator, EventValidator, ValidationIssue, ValidationMode};
+
+#[cfg(feature = "parquet")]
+pub use self::parquet::ParquetChunkReader;
@@ -0,0 +1,207 @@
+use std::{
+    fmt::{Debug, Formatter},
+    fs::File,
+    io::{copy, sink, BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom},
+    marker::PhantomData,
+    mem::size_of,
+};
+
+use flate2::read::DeflateDecoder;
+use zip::{CompressionMethod, ZipArchive};
+
+use crate::backtest::reader::{
+    npy::{
+        npy::{load_records, parse_npy_header, DType},
+        NpyDTyped,
+    },
+    Data,
+    DataPtr,
+};
+
+/// Opens the array data of a `numpy` file, or of the `data` array in a `numpy` zip archived file,
+/// as a stream, and returns it with the size of the array data including the header.
+fn open_stream(filepath: &str) -> Result<(Box<dyn Read>, usize)> {
+    if filepath.ends_with(".npz") {
+        let mut archive = ZipArchive::new(File::open(filepath)?)?;
+        let file = archive.by_name("data.npy")?;
+        let compression = file.compression();
+        let data_start = file.data_start();
+        let compressed_size = file.compressed_size();
+        let size = file.size() as usize;
+        drop(file);
+
+        // Reads the entry from the file directly, since `ZipFile` borrows the archive.
+        let mut raw = BufReader::new(File::open(filepath)?);
+        raw.seek(SeekFrom::Start(data_start))?;
+        let raw = raw.take(compressed_size);
+        match compression {
+            CompressionMethod::Stored => Ok((Box::new(raw), size)),
+            CompressionMethod::Deflated => Ok((Box::new(DeflateDecoder::new(raw)), size)),
+            _ => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("{compression:?} compression is unsupported for chunked reading"),
+            )),
+        }
+    } else {
+        let file = File::open(filepath)?;
+        let size = file.metadata()?.len() as usize;
+        Ok((Box::new(BufReader::new(file)), size))
+    }
+}
+
+/// Reads a structured array `numpy` file, or a `numpy` zip archived file, in chunks of a fixed
+/// size on demand instead of reading the entire file at once, which reduces the stall and the peak
+/// memory usage when loading large files.
+///
+/// Since a compressed archive can only be decompressed sequentially, chunks should be read in
+/// order. Reading a chunk before the last read chunk reopens the file and reads it from the
+/// beginning.
+pub struct NpyChunkReader<D> {
+    filepath: String,
+    chunk_size: usize,
+    reader: Box<dyn Read>,
+    descr: DType,
+    num_rows: usize,
+    record_size: usize,
+    rows_per_chunk: usize,
+    next_chunk: usize,
+    _d: PhantomData<D>,
+}
+
+impl<D> NpyChunkReader<D>
+where
+    D: NpyDTyped + Clone,
+{
+    /// Opens the file to read in chunks of `chunk_size` bytes. A chunk contains at least one row.
+    pub fn open(filepath: &str, chunk_size: usize) -> Result<Self> {
+        let (mut reader, size) = open_stream(filepath)?;
+        let mut prefix = [0u8; 10];
+        reader.read_exact(&mut prefix)?;
+        let header_len = u16::from_le_bytes([prefix[8], prefix[9]]) as usize;
+        let mut buf = DataPtr::new(10 + header_len);
+        buf[..10].copy_from_slice(&prefix);
+        reader.read_exact(&mut buf[10..])?;
+        let (header, offset) = parse_npy_header(&buf)?;
+
+        let num_rows = header.shape[0];
+        // The records may have a different layout than `D` if they need to be migrated.
+        let record_size = (size - offset)
+            .checked_div(num_rows)
+            .unwrap_or(size_of::<D>());
+        if record_size == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "data is shorter than the shape",
+            ));
+        }
+        Ok(Self {
+            filepath: filepath.to_string(),
+            chunk_size,
+            reader,
+            descr: header.descr,
+            num_rows,
+            record_size,
+            rows_per_chunk: (chunk_size / record_size).max(1),
+            next_chunk: 0,
+            _d: Default::default(),
+        })
+    }
+
+    /// Returns the number of chunks.
+    pub fn num_chunks(&self) -> usize {
+        self.num_rows.div_ceil(self.rows_per_chunk)
+    }
+
+    /// Reads the chunk.
+    pub fn read_chunk(&mut self, chunk_num: usize) -> Result<Data<D>> {
+        if chunk_num >= self.num_chunks() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("chunk {chunk_num} is out of range"),
+            ));
+        }
+        if chunk_num < self.next_chunk {
+            *self = Self::open(&self.filepath, self.chunk_size)?;
+        }
+        let skip = (chunk_num - self.next_chunk) * self.rows_per_chunk * self.record_size;
+        copy(&mut (&mut self.reader).take(skip as u64), &mut sink())?;
+
+        let len = self
+            .rows_per_chunk
+            .min(self.num_rows - chunk_num * self.rows_per_chunk);
+        let mut buf = DataPtr::new(len * self.record_size);
+        self.reader.read_exact(&mut buf[..])?;
+        self.next_chunk = chunk_num + 1;
+        load_records(&self.descr, len, buf, 0)
+    }
+}
+
+impl<D> Debug for NpyChunkReader<D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NpyChunkReader")
+            .field("filepath", &self.filepath)
+            .field("chunk_size", &self.chunk_size)
+            .field("num_rows", &self.num_rows)
+            .field("next_chunk", &self.next_chunk)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs::File, io::BufWriter, mem::size_of};
+
+    use super::NpyChunkReader;
+    use crate::{
+        backtest::reader::{write_npy, write_npz},
+        types::Event,
+    };
+
+    fn event(exch_ts: i64) -> Event {
+        Event {
+            ev: 0,
+            exch_ts,
+            local_ts: exch_ts,
+            px: 0.0,
+            qty: 0.0,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_read_chunk() {
+        let events = (0..5).map(event).collect::<Vec<_>>();
+        let dir = env::temp_dir();
+        let npy = dir.join(format!("{}.npy", uuid::Uuid::new_v4()));
+        let npz = dir.join(format!("{}.npz", uuid::Uuid::new_v4()));
+        write_npy(&mut BufWriter::new(File::create(&npy).unwrap()), &events).unwrap();
+        write_npz(
+            &mut BufWriter::new(File::create(&npz).unwrap()),
+            "data",
+            &events,
+        )
+        .unwrap();
+
+        for path in [&npy, &npz] {
+            let mut reader =
+                NpyChunkReader::<Event>::open(path.to_str().unwrap(), 2 * size_of::<Event>())
+                    .unwrap();
+            assert_eq!(reader.num_chunks(), 3);
+            let chunk = reader.read_chunk(1).unwrap();
+            assert_eq!(chunk.len(), 2);
+            assert_eq!(chunk[0].exch_ts, 2);
+            let chunk = reader.read_chunk(2).unwrap();
+            assert_eq!(chunk.len(), 1);
+            assert_eq!(chunk[0].exch_ts, 4);
+            // Reading an earlier chunk reopens the file.
+            let chunk = reader.read_chunk(0).unwrap();
+            assert_eq!(chunk[1].exch_ts, 1);
+            assert!(reader.read_chunk(3).is_err());
+        }
+
+        std::fs::remove_file(npy).unwrap();
+        std::fs::remove_file(npz).unwrap();
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    mem::size_of,
+};
+
+use crate::backtest::reader::{
+    layout::{descr_layout, field_layout, FieldKind, FieldLayout},
+    npy::{Field, NpyDTyped},
+    DataPtr,
+};
+
+/// Migrates the array data of a `numpy` file, whose records are described by `descr`, to the
+/// layout of `D`.
+///
+/// Every `numpy` file carries the dtype description of its records in its header, which serves as
+/// the schema of the data. Files written with a different schema, such as by an older version with
+/// a 32-bit `qty` or a different field order, are migrated by matching the fields by name and
+/// converting their values to the types of `D`. Fields of `D` that are missing from the file are
+/// zero-filled, and fields of the file that `D` doesn't have are dropped.
+pub(crate) fn migrate<D: NpyDTyped>(descr: &[Field], len: usize, data: &[u8]) -> Result<DataPtr> {
+    let (packed, packed_size) = descr_layout(descr, false)?;
+    let (aligned, aligned_size) = descr_layout(descr, true)?;
+    let record_size = data.len().checked_div(len).unwrap_or(packed_size);
+    if record_size < packed_size {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "data is shorter than the shape",
+        ));
+    }
+    // Numpy packs the fields by default, but records written from a `repr(C)` struct are padded.
+    // The record size in the file tells which one it is.
+    let layout = if record_size >= aligned_size && aligned_size != packed_size {
+        aligned
+    } else {
+        packed
+    };
+
+    let mapping = field_layout::<D>()?
+        .into_iter()
+        .filter_map(|to| {
+            layout
+                .iter()
+                .find(|from| from.name == to.name)
+                .map(|from| (from.clone(), to))
+        })
+        .collect::<Vec<(FieldLayout, FieldLayout)>>();
+    if mapping.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, "struct does not match"));
+    }
+
+    let size = size_of::<D>();
+    let mut buf = DataPtr::new(len * size);
+    for row in 0..len {
+        let from_pos = row * record_size;
+        let to_pos = row * size;
+        for (from, to) in &mapping {
+            match to.kind {
+                FieldKind::Float => to.write_f64(&mut buf, to_pos, from.read_f64(data, from_pos)),
+                FieldKind::Int => to.write_i64(&mut buf, to_pos, from.read_i64(data, from_pos)),
+                FieldKind::UInt | FieldKind::Bool => {
+                    to.write_u64(&mut buf, to_pos, from.read_u64(data, from_pos))
+                }
+            }
+        }
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::migrate;
+    use crate::{
+        backtest::reader::{Data, Field},
+        types::Event,
+    };
+
+    #[test]
+    fn test_migrate() {
+        // A packed record with a different field order, a 32-bit qty, and without order_id, ival,
+        // and fval.
+        let descr = [
+            ("exch_ts", "<i8"),
+            ("ev", "<u8"),
+            ("px", "<f8"),
+            ("qty", "<f4"),
+        ]
+        .iter()
+        .map(|(name, ty)| Field {
+            name: name.to_string(),
+            ty: ty.to_string(),
+        })
+        .collect::<Vec<_>>();
+        let mut data = Vec::new();
+        for (exch_ts, ev, px, qty) in [(1i64, 3u64, 10.5f64, 1.5f32), (2, 4, 11.0, 2.0)] {
+            data.extend_from_slice(&exch_ts.to_ne_bytes());
+            data.extend_from_slice(&ev.to_ne_bytes());
+            data.extend_from_slice(&px.to_ne_bytes());
+            data.extend_from_slice(&qty.to_ne_bytes());
+        }
+
+        let buf = migrate::<Event>(&descr, 2, &data).unwrap();
+        let events = Data::<Event>::from_data_ptr(buf, 0);
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[1],
+            Event {
+                ev: 4,
+                exch_ts: 2,
+                local_ts: 0,
+                px: 11.0,
+                qty: 2.0,
+                order_id: 0,
+                ival: 0,
+                fval: 0.0,
+            }
+        );
+        assert_eq!(events[0].qty, 1.5);
+    }
+}
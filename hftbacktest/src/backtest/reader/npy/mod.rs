@@ -1,4 +1,18 @@
+mod chunk;
+mod migrate;
 mod npy;
 mod parser;
 
-pub use npy::{read_npy, read_npy_file, read_npz_file, write_npy, Field, NpyDTyped, NpyHeader};
+pub use chunk::NpyChunkReader;
+#[cfg(feature = "mmap")]
+pub use npy::read_npy_file_mmap;
+pub use npy::{
+    read_npy,
+    read_npy_file,
+    read_npz_file,
+    write_npy,
+    write_npz,
+    Field,
+    NpyDTyped,
+    NpyHeader,
+};
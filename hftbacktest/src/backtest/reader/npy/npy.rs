@@ -1,10 +1,13 @@
 use std::{
     fs::File,
-    io::{Error, ErrorKind, Read, Result, Write},
+    io::{Error, ErrorKind, Read, Result, Seek, Write},
+    mem::{align_of, size_of},
 };
 
+use zip::{write::SimpleFileOptions, ZipWriter};
+
 use crate::backtest::reader::{
-    npy::{parser, parser::Value},
+    npy::{migrate::migrate, parser, parser::Value},
     Data,
     DataPtr,
     POD,
@@ -143,6 +146,15 @@ pub fn read_npy<R: Read, D: NpyDTyped + Clone>(reader: &mut R, size: usize) -> R
         read_size += reader.read(&mut buf[read_size..])?;
     }
 
+    load_npy(buf)
+}
+
+/// Parses the `numpy` header in the buffer and returns it with the offset at which the array data
+/// begins.
+pub(super) fn parse_npy_header(buf: &DataPtr) -> Result<(NpyHeader, usize)> {
+    if buf.len() < 10 {
+        return Err(Error::new(ErrorKind::InvalidData, "too short"));
+    }
     if buf[0..6].to_vec() != b"\x93NUMPY" {
         return Err(Error::new(
             ErrorKind::InvalidData,
@@ -158,7 +170,7 @@ pub fn read_npy<R: Read, D: NpyDTyped + Clone>(reader: &mut R, size: usize) -> R
     let header_len = u16::from_le_bytes(buf[8..10].try_into().unwrap()) as usize;
     let header = String::from_utf8(buf[10..(10 + header_len)].to_vec())
         .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
-    let header = NpyHeader::from_header(&header).unwrap();
+    let header = NpyHeader::from_header(&header)?;
 
     if header.fortran_order {
         return Err(Error::new(
@@ -167,7 +179,33 @@ pub fn read_npy<R: Read, D: NpyDTyped + Clone>(reader: &mut R, size: usize) -> R
         ));
     }
 
+    if header.shape.len() != 1 {
+        return Err(Error::new(ErrorKind::InvalidData, "only 1-d is supported"));
+    }
+
+    Ok((header, 10 + header_len))
+}
+
+/// Constructs `Data` from the buffer containing a `numpy` file. If the structure of the file
+/// matches `D`, the buffer is used as is. Otherwise, the data is migrated to the layout of `D`. See
+/// [`migrate`].
+fn load_npy<D: NpyDTyped + Clone>(buf: DataPtr) -> Result<Data<D>> {
+    let (header, offset) = parse_npy_header(&buf)?;
+    load_records(&header.descr, header.shape[0], buf, offset)
+}
+
+/// Constructs `Data` from `len` records, described by `file_descr`, that begin at the offset in the
+/// buffer. See [`load_npy`].
+pub(super) fn load_records<D: NpyDTyped + Clone>(
+    file_descr: &DType,
+    len: usize,
+    buf: DataPtr,
+    offset: usize,
+) -> Result<Data<D>> {
     let check_type_only = |a: &DType, b: &DType| -> bool {
+        if a.len() != b.len() {
+            return false;
+        }
         for (a_, b_) in a.iter().zip(b.iter()) {
             if a_.ty != b_.ty {
                 return false;
@@ -176,19 +214,29 @@ pub fn read_npy<R: Read, D: NpyDTyped + Clone>(reader: &mut R, size: usize) -> R
         true
     };
 
-    if D::descr() != header.descr {
-        if check_type_only(&D::descr(), &header.descr) {
-            println!("Warning: Field types match, but the field names are different.")
-        } else {
-            return Err(Error::new(ErrorKind::InvalidData, "struct does not match"));
+    // The records can be used in place only if they're aligned to `D`, which depends on the header
+    // length, since a file written by an older `numpy` aligns the array data only to 16 bytes.
+    let as_is = |buf: DataPtr| -> Data<D> {
+        if (buf.at(offset) as usize).is_multiple_of(align_of::<D>()) {
+            return Data::from_data_ptr(buf, offset);
         }
-    }
+        let mut aligned = DataPtr::new(len * size_of::<D>());
+        aligned[..].copy_from_slice(&buf[offset..(offset + len * size_of::<D>())]);
+        Data::from_data_ptr(aligned, 0)
+    };
 
-    if header.shape.len() != 1 {
-        return Err(Error::new(ErrorKind::InvalidData, "only 1-d is supported"));
+    let descr = D::descr();
+    if descr == *file_descr {
+        return Ok(as_is(buf));
     }
-
-    Ok(Data::from_data_ptr(buf, 10 + header_len))
+    // If none of the field names match, the fields cannot be matched by name, so the data is used
+    // as is, as long as the field types match.
+    if check_type_only(&descr, file_descr) && !file_descr.iter().any(|f| descr.contains(f)) {
+        println!("Warning: Field types match, but the field names are different.");
+        return Ok(as_is(buf));
+    }
+    let migrated = migrate::<D>(file_descr, len, &buf[offset..])?;
+    Ok(Data::from_data_ptr(migrated, 0))
 }
 
 /// Reads a structured array `numpy` file. Currently, it doesn't check if the data structure is the
@@ -202,6 +250,18 @@ pub fn read_npy_file<D: NpyDTyped + Clone>(filepath: &str) -> Result<Data<D>> {
     read_npy(&mut file, size)
 }
 
+/// Memory-maps a structured array `numpy` file instead of reading it into memory. The OS pages the
+/// data in on access, which allows backtesting on files that are larger than the available memory.
+/// The mapping is private, so the file is never modified. A file that needs to be migrated, or whose
+/// records aren't aligned to `D` within the file, is read into memory.
+#[cfg(feature = "mmap")]
+pub fn read_npy_file_mmap<D: NpyDTyped + Clone>(filepath: &str) -> Result<Data<D>> {
+    let file = File::open(filepath)?;
+    let mmap = unsafe { memmap2::MmapOptions::new().map_copy(&file)? };
+    let buf = DataPtr::from_mmap(mmap);
+    load_npy(buf)
+}
+
 /// Reads a structured array `numpy` zip archived file. Currently, it doesn't check if the data
 /// structure is the same as what the file contains. Users should be cautious about this.
 pub fn read_npz_file<D: NpyDTyped + Clone>(filepath: &str, name: &str) -> Result<Data<D>> {
@@ -230,8 +290,111 @@ pub fn write_npy<W: Write, T: NpyDTyped>(write: &mut W, data: &Vec<T>) -> Result
     Ok(())
 }
 
+/// Writes the data as a `numpy` zip archived file containing a single array with the given name,
+/// which can be read by [`read_npz_file`].
+pub fn write_npz<W: Write + Seek, T: NpyDTyped>(
+    write: &mut W,
+    name: &str,
+    data: &Vec<T>,
+) -> Result<()> {
+    let mut zip = ZipWriter::new(write);
+    zip.start_file(format!("{name}.npy"), SimpleFileOptions::default())?;
+    write_npy(&mut zip, data)?;
+    zip.finish()?;
+    Ok(())
+}
+
 fn vec_as_bytes<T>(vec: &[T]) -> &[u8] {
-    let len = vec.len() * std::mem::size_of::<T>();
+    let len = size_of_val(vec);
     let ptr = vec.as_ptr() as *const u8;
     unsafe { std::slice::from_raw_parts(ptr, len) }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, mem::align_of, path::PathBuf};
+
+    use super::{read_npy_file, vec_as_bytes, NpyDTyped, NpyHeader};
+    use crate::{
+        backtest::reader::Data,
+        types::{Event, DEPTH_EVENT, EXCH_EVENT, LOCAL_EVENT},
+    };
+
+    fn events() -> Vec<Event> {
+        (0..5)
+            .map(|i| Event {
+                ev: EXCH_EVENT | LOCAL_EVENT | DEPTH_EVENT,
+                exch_ts: 1000 * i,
+                local_ts: 1000 * i + 5,
+                px: 100.0 + i as f64,
+                qty: i as f64,
+                order_id: i as u64,
+                ival: -i,
+                fval: 0.5,
+            })
+            .collect()
+    }
+
+    /// Writes a `numpy` file whose array data begins at the given offset, which is padded by the
+    /// header.
+    fn write_file(events: &[Event], data_offset: usize) -> PathBuf {
+        let header = NpyHeader {
+            descr: Event::descr(),
+            fortran_order: false,
+            shape: vec![events.len()],
+        };
+        let mut header = header.to_string_padding().trim_end().to_string();
+        assert!(10 + header.len() < data_offset);
+        while 10 + header.len() + 1 < data_offset {
+            header += " ";
+        }
+        header += "\n";
+
+        let mut buf = b"\x93NUMPY\x01\x00".to_vec();
+        buf.extend((header.len() as u16).to_le_bytes());
+        buf.extend(header.as_bytes());
+        buf.extend(vec_as_bytes(events));
+        let path = env::temp_dir().join(format!("{}.npy", uuid::Uuid::new_v4()));
+        fs::write(&path, buf).unwrap();
+        path
+    }
+
+    fn assert_data(data: &Data<Event>, events: &[Event]) {
+        assert_eq!(data.len(), events.len());
+        for (i, event) in events.iter().enumerate() {
+            assert_eq!(data[i], *event);
+            assert_eq!(&data[i] as *const Event as usize % align_of::<Event>(), 0);
+        }
+    }
+
+    #[test]
+    fn test_read_npy_file_data_offset() {
+        let events = events();
+        // 64-byte aligned, as written by `write_npy` and recent `numpy`, and 16-byte aligned, as
+        // written by older `numpy`.
+        for data_offset in [256, 272] {
+            let path = write_file(&events, data_offset);
+            assert_data(&read_npy_file(path.to_str().unwrap()).unwrap(), &events);
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_read_npy_file_mmap() {
+        use super::read_npy_file_mmap;
+
+        let events = events();
+        for data_offset in [256, 272] {
+            let path = write_file(&events, data_offset);
+            let path_str = path.to_str().unwrap();
+            let mapped = read_npy_file_mmap::<Event>(path_str).unwrap();
+            let read = read_npy_file::<Event>(path_str).unwrap();
+            assert_data(&mapped, &events);
+            for i in 0..read.len() {
+                assert_eq!(mapped[i], read[i]);
+            }
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    fmt::{Debug, Formatter},
+    fs::File,
+    io::{Error, ErrorKind, Result},
+    marker::PhantomData,
+    mem::size_of,
+};
+
+use parquet::arrow::arrow_reader::{
+    ArrowReaderMetadata,
+    ArrowReaderOptions,
+    ParquetRecordBatchReaderBuilder,
+};
+
+use crate::backtest::reader::{
+    columnar::{select_rows, to_io_error, write_batch},
+    layout::field_layout,
+    npy::NpyDTyped,
+    Data,
+    DataPtr,
+    Instrument,
+};
+
+/// Reads a Parquet file one row group at a time into `Data`.
+///
+/// The file is opened and its footer is parsed once, and each row group is decoded on demand, so
+/// that reading the row groups of a large file doesn't re-open it or re-parse its metadata.
+///
+/// Columns are matched to the fields of `D` by name and cast to the field types. Fields whose
+/// names start with an underscore, such as padding fields, don't need to be present in the file
+/// and are zero-filled.
+pub struct ParquetChunkReader<D> {
+    filepath: String,
+    file: File,
+    metadata: ArrowReaderMetadata,
+    _d: PhantomData<D>,
+}
+
+impl<D> ParquetChunkReader<D>
+where
+    D: NpyDTyped + Clone,
+{
+    /// Opens the Parquet file and reads its metadata.
+    pub fn open(filepath: &str) -> Result<Self> {
+        let file = File::open(filepath)?;
+        let metadata =
+            ArrowReaderMetadata::load(&file, ArrowReaderOptions::new()).map_err(to_io_error)?;
+        Ok(Self {
+            filepath: filepath.to_string(),
+            file,
+            metadata,
+            _d: PhantomData,
+        })
+    }
+
+    /// Returns the number of row groups in the file.
+    pub fn num_chunks(&self) -> usize {
+        self.metadata.metadata().num_row_groups()
+    }
+
+    /// Reads the given row group.
+    ///
+    /// If `instrument` is given, only the rows of that instrument are read from a file that
+    /// contains multiple instruments. See [`Instrument`].
+    pub fn read_chunk(&self, row_group: usize, instrument: Option<&Instrument>) -> Result<Data<D>> {
+        if row_group >= self.num_chunks() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("row group {row_group} is out of range"),
+            ));
+        }
+        let reader = ParquetRecordBatchReaderBuilder::new_with_metadata(
+            self.file.try_clone()?,
+            self.metadata.clone(),
+        )
+        .with_row_groups(vec![row_group])
+        .build()
+        .map_err(to_io_error)?;
+        let mut batches = Vec::new();
+        for batch in reader {
+            let batch = batch.map_err(to_io_error)?;
+            match instrument {
+                Some(instrument) => batches.push(select_rows(&batch, instrument)?),
+                None => batches.push(batch),
+            }
+        }
+        let num_rows = batches.iter().map(|batch| batch.num_rows()).sum::<usize>();
+
+        let layout = field_layout::<D>()?;
+        let record_size = size_of::<D>();
+        let mut buf = DataPtr::new(num_rows * record_size);
+        let mut row = 0;
+        for batch in batches {
+            write_batch(&mut buf, &layout, &batch, row, record_size)?;
+            row += batch.num_rows();
+        }
+        Ok(Data::from_data_ptr(buf, 0))
+    }
+}
+
+impl<D> Debug for ParquetChunkReader<D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParquetChunkReader")
+            .field("filepath", &self.filepath)
+            .field("num_row_groups", &self.metadata.metadata().num_row_groups())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, fs::File};
+
+    use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+
+    use super::ParquetChunkReader;
+    use crate::{
+        backtest::reader::{columnar::tests::record_batch, Data},
+        types::{Event, BUY_EVENT, DEPTH_EVENT, EXCH_EVENT, LOCAL_EVENT, SELL_EVENT},
+    };
+
+    #[test]
+    fn test_read_row_groups() {
+        let events = (0..5)
+            .map(|i| Event {
+                ev: EXCH_EVENT
+                    | LOCAL_EVENT
+                    | DEPTH_EVENT
+                    | if i % 2 == 0 { BUY_EVENT } else { SELL_EVENT },
+                exch_ts: 1000 * i,
+                local_ts: 1000 * i + 5,
+                px: 100.0 + i as f64 * 0.5,
+                qty: i as f64,
+                order_id: i as u64,
+                ival: -i,
+                fval: i as f64 * 0.25,
+            })
+            .collect::<Vec<_>>();
+
+        let path = env::temp_dir().join(format!("parquet_{}.parquet", uuid::Uuid::new_v4()));
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let mut writer = ArrowWriter::try_new(
+            File::create(&path).unwrap(),
+            record_batch(&events).schema(),
+            Some(props),
+        )
+        .unwrap();
+        writer.write(&record_batch(&events)).unwrap();
+        writer.close().unwrap();
+
+        let reader = ParquetChunkReader::<Event>::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(reader.num_chunks(), 3);
+        let mut rows = Vec::new();
+        for row_group in 0..reader.num_chunks() {
+            let data = reader.read_chunk(row_group, None).unwrap();
+            for row in 0..data.len() {
+                rows.push(data[row].clone());
+            }
+        }
+        assert!(reader.read_chunk(3, None).is_err());
+
+        let expected = Data::from_slice(&events);
+        assert_eq!(rows.len(), expected.len());
+        for (row, event) in rows.iter().enumerate() {
+            assert_eq!(*event, expected[row]);
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+}
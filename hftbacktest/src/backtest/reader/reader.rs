@@ -1,17 +1,34 @@
 use std::{
-    cell::{Cell, RefCell},
-    collections::HashMap,
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap},
     io::{Error as IoError, ErrorKind},
+    mem::size_of,
     rc::Rc,
     slice::SliceIndex,
 };
 
 use uuid::Uuid;
 
+#[cfg(feature = "arrow")]
+use crate::backtest::reader::ipc::ArrowChunkReader;
+#[cfg(feature = "manifest")]
+use crate::backtest::reader::manifest::Manifest;
+#[cfg(not(feature = "mmap"))]
+use crate::backtest::reader::npy::read_npy_file;
+#[cfg(feature = "mmap")]
+use crate::backtest::reader::npy::read_npy_file_mmap;
+#[cfg(feature = "parquet")]
+use crate::backtest::reader::parquet::ParquetChunkReader;
+#[cfg(feature = "remote")]
+use crate::backtest::reader::remote::download;
 use crate::backtest::{
     reader::{
         data::{Data, POD},
-        npy::{read_npy_file, read_npz_file, NpyDTyped},
+        filter::DataFilter,
+        generator::{Generator, GeneratorCursor},
+        npy::{read_npz_file, NpyChunkReader, NpyDTyped},
+        query::{Query, QueryReader},
+        validation::DataValidator,
     },
     BacktestError,
 };
@@ -24,15 +41,160 @@ where
 {
     /// Data needs to be loaded from the specified  file. It will be loaded when needed and released
     /// when no processor is reading the data.
+    ///
+    /// The file format is detected by its extension: `.npy`, `.npz`, and, with the `parquet`
+    /// feature, `.parquet`, and with the `arrow` feature, `.arrow`, `.feather`, or `.ipc`.
+    /// Parquet and Arrow IPC files are read lazily one row group or record batch at a time. With the
+    /// `mmap` feature, `.npy` files are memory-mapped rather than read into memory. `.npy` and
+    /// `.npz` files can also be read lazily in fixed-size chunks; see
+    /// [`Reader::set_chunk_size`].
     File(String),
     /// Data is loaded and set by the user.
     Data(Data<D>),
+    /// Data needs to be downloaded from the specified HTTP(S) URL or `s3://` URI. It will be
+    /// downloaded into a local file when needed, which is then read the same way as
+    /// [`DataSource::File`]. Requires the `remote` feature.
+    Url(String),
+    /// Data needs to be queried from a database, such as ClickHouse or Postgres. The result is
+    /// streamed in batches when needed. Requires the `clickhouse` or `postgres` feature. See
+    /// [`Query`].
+    Query(Query),
+    /// Data is produced programmatically by a [`Generator`], from an iterator or a channel,
+    /// without writing temporary files. The data is read as it is produced.
+    Iterator(Generator<D>),
+}
+
+/// Returns `true` if the path is a URL of a remote data source.
+fn is_remote(filepath: &str) -> bool {
+    filepath.starts_with("http://")
+        || filepath.starts_with("https://")
+        || filepath.starts_with("s3://")
+}
+
+/// Returns the local path of the data file, downloading it first if it is a remote data source.
+fn local_path(filepath: &str) -> Result<String, BacktestError> {
+    if !is_remote(filepath) {
+        return Ok(filepath.to_string());
+    }
+    #[cfg(feature = "remote")]
+    {
+        Ok(download(filepath)?)
+    }
+    #[cfg(not(feature = "remote"))]
+    {
+        Err(BacktestError::DataError(IoError::new(
+            ErrorKind::Unsupported,
+            "remote data sources require the `remote` feature",
+        )))
+    }
+}
+
+/// Selects the rows of a single instrument from a data file that contains multiple instruments.
+///
+/// The file needs to have a `symbol` column for [`Instrument::Symbol`] or an `instrument_id`
+/// column for [`Instrument::Id`], in addition to the columns of the record. Instrument selection
+/// is supported for Parquet and Arrow IPC files.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Instrument {
+    /// Selects the rows whose `symbol` column equals the given value.
+    Symbol(String),
+    /// Selects the rows whose `instrument_id` column equals the given value.
+    Id(i64),
+}
+
+impl Instrument {
+    /// Returns the name of the column used to select the rows.
+    pub fn column_name(&self) -> &'static str {
+        match self {
+            Instrument::Symbol(_) => "symbol",
+            Instrument::Id(_) => "instrument_id",
+        }
+    }
+}
+
+impl From<&str> for Instrument {
+    fn from(symbol: &str) -> Self {
+        Instrument::Symbol(symbol.to_string())
+    }
+}
+
+impl From<String> for Instrument {
+    fn from(symbol: String) -> Self {
+        Instrument::Symbol(symbol)
+    }
+}
+
+impl From<i64> for Instrument {
+    fn from(id: i64) -> Self {
+        Instrument::Id(id)
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry<D>
+where
+    D: POD + Clone,
+{
+    ref_count: usize,
+    data: Data<D>,
+    // Data set by the user cannot be reloaded, so it is never evicted.
+    pinned: bool,
+    last_used: u64,
+}
+
+#[derive(Debug)]
+struct CacheInner<D>
+where
+    D: POD + Clone,
+{
+    entries: HashMap<String, CacheEntry<D>>,
+    memory_limit: Option<usize>,
+    memory_usage: usize,
+    clock: u64,
+}
+
+impl<D> CacheInner<D>
+where
+    D: POD + Clone,
+{
+    fn remove_entry(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.memory_usage -= entry.data.len() * size_of::<D>();
+        }
+    }
+
+    /// Evicts the least recently used `Data` that no reader is accessing, except for `keep`, until
+    /// the memory usage is within the limit.
+    fn evict(&mut self, keep: Option<&str>) {
+        let Some(memory_limit) = self.memory_limit else {
+            return;
+        };
+        while self.memory_usage > memory_limit {
+            let lru = self
+                .entries
+                .iter()
+                .filter(|(key, entry)| {
+                    entry.ref_count == 0 && !entry.pinned && Some(key.as_str()) != keep
+                })
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match lru {
+                Some(key) => self.remove_entry(&key),
+                None => break,
+            }
+        }
+    }
 }
 
 /// Provides a data cache that allows both the local processor and exchange processor to access the
 /// same or different data based on their timestamps without the need for reloading.
+///
+/// By default, `Data` is removed as soon as all retrieved `Data` are released. With a memory limit,
+/// released `Data` is kept so that it can be retrieved again without reloading, and the least
+/// recently used `Data` that no reader is accessing is evicted when the memory usage exceeds the
+/// limit. Evicted `Data` is reloaded by the [`Reader`] on demand.
 #[derive(Clone, Debug)]
-pub struct Cache<D>(Rc<RefCell<HashMap<String, (Cell<usize>, Data<D>)>>>)
+pub struct Cache<D>(Rc<RefCell<CacheInner<D>>>)
 where
     D: POD + Clone;
 
@@ -42,42 +204,155 @@ where
 {
     /// Constructs an instance of `Cache`.
     pub fn new() -> Self {
-        Self(Default::default())
+        Self(Rc::new(RefCell::new(CacheInner {
+            entries: HashMap::new(),
+            memory_limit: None,
+            memory_usage: 0,
+            clock: 0,
+        })))
+    }
+
+    /// Constructs an instance of `Cache` with the memory limit in bytes.
+    pub fn with_memory_limit(memory_limit: usize) -> Self {
+        let cache = Self::new();
+        cache.set_memory_limit(Some(memory_limit));
+        cache
+    }
+
+    /// Sets the memory limit in bytes. `None` removes the limit.
+    ///
+    /// The limit is a soft limit, since `Data` that readers are accessing and `Data` set by the
+    /// user cannot be evicted.
+    pub fn set_memory_limit(&self, memory_limit: Option<usize>) {
+        let mut inner = self.0.borrow_mut();
+        inner.memory_limit = memory_limit;
+        inner.evict(None);
+    }
+
+    /// Returns the memory usage of the cached `Data` in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.0.borrow().memory_usage
+    }
+
+    fn insert_entry(&mut self, key: String, data: Data<D>, pinned: bool) {
+        let mut inner = self.0.borrow_mut();
+        inner.remove_entry(&key);
+        inner.memory_usage += data.len() * size_of::<D>();
+        inner.clock += 1;
+        let last_used = inner.clock;
+        inner.entries.insert(
+            key.clone(),
+            CacheEntry {
+                ref_count: 0,
+                data,
+                pinned,
+                last_used,
+            },
+        );
+        // The inserted `Data` is about to be retrieved, so it is not evicted.
+        inner.evict(Some(&key));
     }
 
     /// Inserts a key-value pair into the `Cache`.
     pub fn insert(&mut self, key: String, data: Data<D>) {
-        self.0.borrow_mut().insert(key, (Cell::new(0), data));
+        self.insert_entry(key, data, false);
+    }
+
+    /// Inserts a key-value pair into the `Cache`, which is never evicted due to the memory limit.
+    pub fn insert_pinned(&mut self, key: String, data: Data<D>) {
+        self.insert_entry(key, data, true);
     }
 
-    /// Removes the `Data` if all retrieved `Data` are released.
+    /// Removes the `Data` if all retrieved `Data` are released. If the memory limit is set, the
+    /// `Data` is kept until it needs to be evicted.
     pub fn remove(&mut self, data: Data<D>) {
+        let mut inner = self.0.borrow_mut();
         let mut remove = None;
-        for (key, (ref_count, cached_data)) in self.0.borrow_mut().iter_mut() {
-            if data.ptr_eq(cached_data) {
-                *ref_count.get_mut() -= 1;
-                if ref_count.get() == 0 {
+        for (key, entry) in inner.entries.iter_mut() {
+            if data.ptr_eq(&entry.data) {
+                entry.ref_count -= 1;
+                if entry.ref_count == 0 {
                     remove = Some(key.clone());
                 }
                 break;
             }
         }
         if let Some(key) = remove {
-            self.0.borrow_mut().remove(&key).unwrap();
+            if inner.memory_limit.is_some() {
+                inner.evict(None);
+            } else {
+                inner.remove_entry(&key);
+            }
         }
     }
 
     /// Returns `true` if the `Cache` contains the `Data` for the specified key.
     pub fn contains(&self, key: &str) -> bool {
-        self.0.borrow().contains_key(key)
+        self.0.borrow().entries.contains_key(key)
     }
 
     /// Returns the `Data` corresponding to the key.
     pub fn get(&mut self, key: &str) -> Data<D> {
-        let mut borrowed = self.0.borrow_mut();
-        let (ref_count, data) = borrowed.get_mut(key).unwrap();
-        *ref_count.get_mut() += 1;
-        data.clone()
+        let mut inner = self.0.borrow_mut();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let entry = inner.entries.get_mut(key).unwrap();
+        entry.ref_count += 1;
+        entry.last_used = clock;
+        entry.data.clone()
+    }
+
+    /// Returns the `Data` corresponding to the key without retrieving it.
+    pub(crate) fn peek(&self, key: &str) -> Option<Data<D>> {
+        self.0
+            .borrow()
+            .entries
+            .get(key)
+            .map(|entry| entry.data.clone())
+    }
+}
+
+fn apply_filters<D>(filters: &[Box<dyn DataFilter<D>>], data: Data<D>) -> Data<D>
+where
+    D: POD + Clone,
+{
+    filters
+        .iter()
+        .fold(data, |data, filter| filter.filter(&data).unwrap_or(data))
+}
+
+/// The state of the validation shared among the clones of a [`Reader`].
+#[derive(Debug)]
+struct Validation<D>
+where
+    D: POD + Clone,
+{
+    validator: Box<dyn DataValidator<D>>,
+    // The number of `Data` validated so far. Since every clone retrieves the same sequence of
+    // `Data`, the `Data` has already been validated if a clone has retrieved fewer than this.
+    num_validated: usize,
+    // The entry in `file_list` of the last validated `Data` and the number of its rows validated so
+    // far, which is the row offset of the next `Data` from the same entry.
+    entry: usize,
+    row_offset: usize,
+}
+
+impl<D> Validation<D>
+where
+    D: NpyDTyped + Clone,
+{
+    fn validate(&mut self, reader: &Reader<D>, data: &Data<D>) -> std::io::Result<()> {
+        if reader.num_read < self.num_validated {
+            return Ok(());
+        }
+        if reader.loaded_entry != self.entry {
+            self.entry = reader.loaded_entry;
+            self.row_offset = 0;
+        }
+        self.validator.validate(data, self.row_offset)?;
+        self.num_validated += 1;
+        self.row_offset += data.len();
+        Ok(())
     }
 }
 
@@ -90,6 +365,39 @@ where
     file_list: Vec<String>,
     cache: Cache<D>,
     data_num: usize,
+    // The position within a file that is read in multiple chunks, such as a Parquet file read by
+    // row groups.
+    chunk_num: usize,
+    num_chunks: Option<usize>,
+    // The chunk size in bytes for reading `numpy` files in chunks, and the open chunk readers,
+    // which are shared among the clones since a compressed file can only be read sequentially.
+    chunk_size: Option<usize>,
+    chunk_readers: Rc<RefCell<HashMap<String, NpyChunkReader<D>>>>,
+    // The open Parquet files, which are shared among the clones so that each file is opened once.
+    #[cfg(feature = "parquet")]
+    parquet_readers: Rc<RefCell<HashMap<String, ParquetChunkReader<D>>>>,
+    #[cfg(feature = "arrow")]
+    arrow_readers: Rc<RefCell<HashMap<String, ArrowChunkReader<D>>>>,
+    // The validation, which is shared among the clones so that each `Data` is validated once.
+    validation: Option<Rc<RefCell<Validation<D>>>>,
+    // The number of `Data` retrieved by this clone, and the entry in `file_list` of the last loaded
+    // `Data`.
+    num_read: usize,
+    loaded_entry: usize,
+    filters: Vec<Box<dyn DataFilter<D>>>,
+    // The filters that apply only to the data of an entry in `file_list`, keyed by the entry.
+    source_filters: HashMap<String, Vec<Box<dyn DataFilter<D>>>>,
+    instrument: Option<Instrument>,
+    // The sources of the merged data, keyed by the entry in `file_list`.
+    merged: HashMap<String, (Vec<DataSource<D>>, fn(&[Data<D>]) -> Data<D>)>,
+    // The queries, keyed by the entry in `file_list`, and the open query readers, which are shared
+    // among the clones since a query result can only be streamed sequentially.
+    queries: HashMap<String, Query>,
+    query_readers: Rc<RefCell<HashMap<String, QueryReader<D>>>>,
+    #[cfg(feature = "manifest")]
+    manifests: Vec<Manifest>,
+    // The positions in the data produced by generators, keyed by the entry in `file_list`.
+    generators: HashMap<String, GeneratorCursor<D>>,
 }
 
 impl<D> Reader<D>
@@ -102,7 +410,109 @@ where
             file_list: Vec::new(),
             cache,
             data_num: 0,
+            chunk_num: 0,
+            num_chunks: None,
+            chunk_size: None,
+            chunk_readers: Default::default(),
+            #[cfg(feature = "parquet")]
+            parquet_readers: Default::default(),
+            #[cfg(feature = "arrow")]
+            arrow_readers: Default::default(),
+            validation: None,
+            num_read: 0,
+            loaded_entry: 0,
+            filters: Vec::new(),
+            source_filters: HashMap::new(),
+            instrument: None,
+            merged: HashMap::new(),
+            queries: HashMap::new(),
+            query_readers: Default::default(),
+            #[cfg(feature = "manifest")]
+            manifests: Vec::new(),
+            generators: HashMap::new(),
+        }
+    }
+
+    /// Sets the instrument to read from data files that contain multiple instruments. Only the
+    /// rows of the instrument are read. This doesn't apply to `Data` added by
+    /// [`add_data`](Reader::add_data). See [`Instrument`].
+    pub fn set_instrument(&mut self, instrument: Instrument) {
+        self.instrument = Some(instrument);
+    }
+
+    /// Sets the memory limit of the `Cache` in bytes. `None` removes the limit. See [`Cache`].
+    pub fn set_cache_memory_limit(&mut self, memory_limit: Option<usize>) {
+        self.cache.set_memory_limit(memory_limit);
+    }
+
+    /// Sets the chunk size in bytes for reading `.npy` and `.npz` files lazily in chunks, rather
+    /// than reading the entire file at once, which avoids the stall and the peak memory usage of
+    /// loading large files. `None` reads the entire file, which is the default. See
+    /// [`NpyChunkReader`].
+    ///
+    /// Since a compressed `.npz` file can only be read sequentially, a chunk that is evicted from
+    /// the `Cache` before every reader has read it requires reading the file from the beginning
+    /// again. Setting a memory limit on the `Cache` keeps consumed chunks available. This doesn't
+    /// apply to files read as part of [`add_merged`](Reader::add_merged).
+    pub fn set_chunk_size(&mut self, chunk_size: Option<usize>) {
+        self.chunk_size = chunk_size;
+    }
+
+    /// Sets a [`DataValidator`] that validates each `Data` as it is retrieved. The validator is
+    /// shared among the clones of the `Reader` made after this call, such as the ones of the local
+    /// processor and the exchange processor, and each `Data` is validated once by the clone that
+    /// retrieves it first, so that the issues are reported once and in the order of the data.
+    pub fn set_validator<V>(&mut self, validator: V)
+    where
+        V: DataValidator<D> + 'static,
+    {
+        self.validation = Some(Rc::new(RefCell::new(Validation {
+            validator: Box::new(validator),
+            num_validated: 0,
+            entry: 0,
+            row_offset: 0,
+        })));
+    }
+
+    /// Adds a [`DataFilter`] that is applied to each `Data` as it is loaded into the `Cache`.
+    /// Filters are applied in the order of their additions. `Data` that has already been added by
+    /// [`add_data`](Reader::add_data) is filtered immediately. `Data` that becomes empty after
+    /// filtering is skipped.
+    pub fn add_filter<F>(&mut self, filter: F)
+    where
+        F: DataFilter<D> + 'static,
+    {
+        for key in &self.file_list {
+            if let Some(data) = self.cache.peek(key) {
+                if let Some(filtered) = filter.filter(&data) {
+                    self.cache.insert_pinned(key.clone(), filtered);
+                }
+            }
         }
+        self.filters.push(Box::new(filter));
+    }
+
+    /// Adds the data source with the [`DataFilter`]s that apply only to its data, after the filters
+    /// added by [`add_filter`](Reader::add_filter). Additions should be made in the same order as
+    /// the order you want to read. `Data` given by [`DataSource::Data`] is filtered immediately.
+    pub fn add_filtered(&mut self, source: DataSource<D>, filters: Vec<Box<dyn DataFilter<D>>>) {
+        let len = self.file_list.len();
+        match source {
+            DataSource::File(filename) => self.add_file(filename),
+            DataSource::Data(data) => self.add_data(data),
+            DataSource::Url(url) => self.add_url(url),
+            DataSource::Query(query) => self.add_query(query),
+            DataSource::Iterator(generator) => self.add_generator(generator),
+        }
+        if self.file_list.len() == len || filters.is_empty() {
+            return;
+        }
+        let key = self.file_list.last().unwrap().clone();
+        if let Some(data) = self.cache.peek(&key) {
+            self.cache
+                .insert_pinned(key.clone(), apply_filters(&filters, data));
+        }
+        self.source_filters.insert(key, filters);
     }
 
     /// Adds a `numpy` file to read. Additions should be made in the same order as the order you
@@ -111,12 +521,68 @@ where
         self.file_list.push(filepath);
     }
 
+    /// Adds a remote data file, given by its HTTP(S) URL or `s3://` URI, to read. Additions should
+    /// be made in the same order as the order you want to read. See [`DataSource::Url`].
+    pub fn add_url(&mut self, url: String) {
+        self.file_list.push(url);
+    }
+
+    /// Adds the data files listed in the [`Manifest`] to read, in the order of the manifest.
+    /// Additions should be made in the same order as the order you want to read. The manifest is
+    /// verified by [`verify_manifests`](Reader::verify_manifests).
+    #[cfg(feature = "manifest")]
+    pub fn add_manifest(&mut self, manifest: Manifest) {
+        self.file_list.extend(manifest.paths());
+        self.manifests.push(manifest);
+    }
+
+    /// Verifies the integrity and the ordering of the data files of all added manifests, which
+    /// should be done before reading so that a corrupt or out-of-order file list is detected
+    /// before the run starts. See [`Manifest::verify`].
+    #[cfg(feature = "manifest")]
+    pub fn verify_manifests(&self) -> Result<(), BacktestError> {
+        for manifest in &self.manifests {
+            manifest.verify()?;
+        }
+        Ok(())
+    }
+
+    /// Adds a database query to read. Additions should be made in the same order as the order you
+    /// want to read. See [`DataSource::Query`].
+    pub fn add_query(&mut self, query: Query) {
+        let id = format!("query:{}", Uuid::new_v4());
+        self.file_list.push(id.clone());
+        self.queries.insert(id, query);
+    }
+
+    /// Adds a [`Generator`] that produces the data to read. Additions should be made in the same
+    /// order as the order you want to read. A `Generator` can only be added once; adding it again
+    /// adds nothing. See [`DataSource::Iterator`].
+    pub fn add_generator(&mut self, generator: Generator<D>) {
+        if let Some(batches) = generator.take() {
+            let id = format!("generator:{}", Uuid::new_v4());
+            self.file_list.push(id.clone());
+            self.generators.insert(id, GeneratorCursor::new(batches));
+        }
+    }
+
     /// Adds a `Data`. Additions should be made in the same order as the order you want to read.
     pub fn add_data(&mut self, data: Data<D>) {
         // todo: Data should not be removed from the cache.
         let id = Uuid::new_v4().to_string();
         self.file_list.push(id.clone());
-        self.cache.insert(id, data);
+        let data = self.apply_filter(&id, data);
+        self.cache.insert_pinned(id, data);
+    }
+
+    /// Adds sources that overlap in time, such as a trade file and a depth file delivered
+    /// separately, to be read as a single `Data` merged by `merge`. The sources are loaded
+    /// entirely and merged when needed. Additions should be made in the same order as the order
+    /// you want to read.
+    pub fn add_merged(&mut self, sources: Vec<DataSource<D>>, merge: fn(&[Data<D>]) -> Data<D>) {
+        let id = format!("merged:{}", Uuid::new_v4());
+        self.file_list.push(id.clone());
+        self.merged.insert(id, (sources, merge));
     }
 
     /// Releases this `Data` from the `Cache`. The `Cache` will delete the `Data` if there are no
@@ -127,27 +593,424 @@ where
 
     /// Retrieves the next `Data` based on the order of your additions.
     pub fn next(&mut self) -> Result<Data<D>, BacktestError> {
+        loop {
+            let data = self.load_next()?;
+            if !self.filters.is_empty() && data.len() == 0 {
+                self.cache.remove(data);
+                continue;
+            }
+            if let Some(validation) = self.validation.clone() {
+                if let Err(error) = validation.borrow_mut().validate(self, &data) {
+                    self.cache.remove(data);
+                    return Err(BacktestError::DataError(error));
+                }
+            }
+            self.num_read += 1;
+            return Ok(data);
+        }
+    }
+
+    fn apply_filter(&self, key: &str, data: Data<D>) -> Data<D> {
+        let data = apply_filters(&self.filters, data);
+        match self.source_filters.get(key) {
+            Some(filters) => apply_filters(filters, data),
+            None => data,
+        }
+    }
+
+    fn load_next(&mut self) -> Result<Data<D>, BacktestError> {
         if self.data_num < self.file_list.len() {
-            let filepath = self.file_list.get(self.data_num).unwrap();
-            if !self.cache.contains(filepath) {
-                if filepath.ends_with(".npy") {
-                    let data = read_npy_file(filepath)?;
-                    self.cache.insert(filepath.to_string(), data);
-                } else if filepath.ends_with(".npz") {
-                    let data = read_npz_file(filepath, "data")?;
-                    self.cache.insert(filepath.to_string(), data);
-                } else {
-                    return Err(BacktestError::DataError(IoError::new(
-                        ErrorKind::InvalidData,
-                        "unsupported data type",
-                    )));
+            // An exhausted entry moves on to the next one by recursion, so this ends up with the
+            // entry of the returned `Data`.
+            self.loaded_entry = self.data_num;
+            let filepath = self.file_list.get(self.data_num).unwrap().clone();
+            if let Some((sources, merge)) = self.merged.get(&filepath) {
+                if !self.cache.contains(&filepath) {
+                    let mut data = Vec::with_capacity(sources.len());
+                    for source in sources {
+                        match source {
+                            DataSource::File(path) | DataSource::Url(path) => {
+                                data.push(self.read_file(path)?)
+                            }
+                            DataSource::Data(source_data) => data.push(source_data.clone()),
+                            DataSource::Query(query) => {
+                                data.push(QueryReader::open(query.clone())?.read_all()?)
+                            }
+                            DataSource::Iterator(generator) => {
+                                let rows = generator.take().into_iter().flatten().flatten();
+                                data.push(Data::from_slice(&rows.collect::<Vec<_>>()))
+                            }
+                        }
+                    }
+                    let merged = self.apply_filter(&filepath, merge(&data));
+                    self.cache.insert(filepath.clone(), merged);
                 }
+                let data = self.cache.get(&filepath);
+                self.data_num += 1;
+                return Ok(data);
+            }
+            if let Some(cursor) = self.generators.get_mut(&filepath) {
+                let filters = &self.filters;
+                let source_filters = self.source_filters.get(&filepath);
+                return match cursor.next(|data| {
+                    let data = apply_filters(filters, data);
+                    match source_filters {
+                        Some(source_filters) => apply_filters(source_filters, data),
+                        None => data,
+                    }
+                }) {
+                    Some(data) => Ok(data),
+                    None => {
+                        self.data_num += 1;
+                        self.load_next()
+                    }
+                };
             }
-            let data = self.cache.get(filepath);
+            if self.queries.contains_key(&filepath) {
+                return self.next_query_batch(filepath);
+            }
+            let path = local_path(&filepath)?;
+            if let Some(chunk_size) = self.chunk_size {
+                if self.instrument.is_none() && (path.ends_with(".npy") || path.ends_with(".npz")) {
+                    let readers = self.chunk_readers.clone();
+                    return self.next_chunk(
+                        filepath,
+                        &path,
+                        |path| Ok(NpyChunkReader::<D>::open(path, chunk_size)?.num_chunks()),
+                        |path, chunk_num, _| {
+                            let mut readers = readers.borrow_mut();
+                            let reader = match readers.entry(path.to_string()) {
+                                Entry::Occupied(entry) => entry.into_mut(),
+                                Entry::Vacant(entry) => {
+                                    entry.insert(NpyChunkReader::open(path, chunk_size)?)
+                                }
+                            };
+                            let data = reader.read_chunk(chunk_num)?;
+                            if chunk_num + 1 == reader.num_chunks() {
+                                readers.remove(path);
+                            }
+                            Ok(data)
+                        },
+                    );
+                }
+            }
+            #[cfg(feature = "parquet")]
+            if path.ends_with(".parquet") {
+                let readers = self.parquet_readers.clone();
+                return self.next_chunk(
+                    filepath,
+                    &path,
+                    |path| {
+                        let mut readers = readers.borrow_mut();
+                        let reader = match readers.entry(path.to_string()) {
+                            Entry::Occupied(entry) => entry.into_mut(),
+                            Entry::Vacant(entry) => entry.insert(ParquetChunkReader::open(path)?),
+                        };
+                        Ok(reader.num_chunks())
+                    },
+                    |path, row_group, instrument| {
+                        let mut readers = readers.borrow_mut();
+                        let reader = match readers.entry(path.to_string()) {
+                            Entry::Occupied(entry) => entry.into_mut(),
+                            Entry::Vacant(entry) => entry.insert(ParquetChunkReader::open(path)?),
+                        };
+                        let data = reader.read_chunk(row_group, instrument)?;
+                        if row_group + 1 == reader.num_chunks() {
+                            readers.remove(path);
+                        }
+                        Ok(data)
+                    },
+                );
+            }
+            #[cfg(feature = "arrow")]
+            if path.ends_with(".arrow") || path.ends_with(".feather") || path.ends_with(".ipc") {
+                let readers = self.arrow_readers.clone();
+                return self.next_chunk(
+                    filepath,
+                    &path,
+                    |path| {
+                        let mut readers = readers.borrow_mut();
+                        let reader = match readers.entry(path.to_string()) {
+                            Entry::Occupied(entry) => entry.into_mut(),
+                            Entry::Vacant(entry) => entry.insert(ArrowChunkReader::open(path)?),
+                        };
+                        Ok(reader.num_chunks())
+                    },
+                    |path, batch_num, instrument| {
+                        let mut readers = readers.borrow_mut();
+                        let reader = match readers.entry(path.to_string()) {
+                            Entry::Occupied(entry) => entry.into_mut(),
+                            Entry::Vacant(entry) => entry.insert(ArrowChunkReader::open(path)?),
+                        };
+                        let data = reader.read_chunk(batch_num, instrument)?;
+                        if batch_num + 1 == reader.num_chunks() {
+                            readers.remove(path);
+                        }
+                        Ok(data)
+                    },
+                );
+            }
+            if !self.cache.contains(&filepath) {
+                let data = self.apply_filter(&filepath, self.read_file(&path)?);
+                self.cache.insert(filepath.to_string(), data);
+            }
+            let data = self.cache.get(&filepath);
             self.data_num += 1;
             Ok(data)
         } else {
             Err(BacktestError::EndOfData)
         }
     }
+
+    /// Retrieves the next batch of the result of a query.
+    fn next_query_batch(&mut self, key: String) -> Result<Data<D>, BacktestError> {
+        let cache_key = format!("{key}#{}", self.chunk_num);
+        if !self.cache.contains(&cache_key) {
+            let readers = self.query_readers.clone();
+            let mut readers = readers.borrow_mut();
+            let reader = match readers.entry(key.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    entry.insert(QueryReader::open(self.queries[&key].clone())?)
+                }
+            };
+            match reader.read_batch(self.chunk_num)? {
+                Some(data) => {
+                    let data = self.apply_filter(&key, data);
+                    self.cache.insert(cache_key.clone(), data);
+                }
+                None => {
+                    drop(readers);
+                    self.data_num += 1;
+                    self.chunk_num = 0;
+                    return self.load_next();
+                }
+            }
+        }
+        let data = self.cache.get(&cache_key);
+        self.chunk_num += 1;
+        Ok(data)
+    }
+
+    /// Reads the entire file. Files that are read in multiple chunks are concatenated.
+    fn read_file(&self, filepath: &str) -> Result<Data<D>, BacktestError> {
+        let path = local_path(filepath)?;
+        let filepath = path.as_str();
+        #[cfg(feature = "parquet")]
+        if filepath.ends_with(".parquet") {
+            let reader = ParquetChunkReader::open(filepath)?;
+            return self.read_chunks(reader.num_chunks(), |row_group| {
+                reader.read_chunk(row_group, self.instrument.as_ref())
+            });
+        }
+        #[cfg(feature = "arrow")]
+        if filepath.ends_with(".arrow")
+            || filepath.ends_with(".feather")
+            || filepath.ends_with(".ipc")
+        {
+            let mut reader = ArrowChunkReader::open(filepath)?;
+            return self.read_chunks(reader.num_chunks(), |batch_num| {
+                reader.read_chunk(batch_num, self.instrument.as_ref())
+            });
+        }
+        if self.instrument.is_some() {
+            return Err(BacktestError::DataError(IoError::new(
+                ErrorKind::InvalidInput,
+                "instrument selection is only supported for Parquet and Arrow IPC files",
+            )));
+        }
+        if filepath.ends_with(".npy") {
+            #[cfg(feature = "mmap")]
+            let data = read_npy_file_mmap(filepath)?;
+            #[cfg(not(feature = "mmap"))]
+            let data = read_npy_file(filepath)?;
+            Ok(data)
+        } else if filepath.ends_with(".npz") {
+            Ok(read_npz_file(filepath, "data")?)
+        } else {
+            Err(BacktestError::DataError(IoError::new(
+                ErrorKind::InvalidData,
+                "unsupported data type",
+            )))
+        }
+    }
+
+    #[cfg(any(feature = "parquet", feature = "arrow"))]
+    fn read_chunks(
+        &self,
+        num_chunks: usize,
+        mut read_chunk: impl FnMut(usize) -> std::io::Result<Data<D>>,
+    ) -> Result<Data<D>, BacktestError> {
+        if num_chunks == 1 {
+            return Ok(read_chunk(0)?);
+        }
+        let mut rows = Vec::new();
+        for chunk_num in 0..num_chunks {
+            let chunk = read_chunk(chunk_num)?;
+            for row in 0..chunk.len() {
+                rows.push(chunk[row].clone());
+            }
+        }
+        Ok(Data::from_slice(&rows))
+    }
+
+    /// Retrieves the next chunk of a file that is read in multiple chunks, such as the row groups
+    /// of a Parquet file.
+    fn next_chunk(
+        &mut self,
+        filepath: String,
+        path: &str,
+        num_chunks: impl FnOnce(&str) -> std::io::Result<usize>,
+        read_chunk: impl FnOnce(&str, usize, Option<&Instrument>) -> std::io::Result<Data<D>>,
+    ) -> Result<Data<D>, BacktestError> {
+        let num_chunks = match self.num_chunks {
+            Some(num_chunks) => num_chunks,
+            None => {
+                let n = num_chunks(path)?;
+                self.num_chunks = Some(n);
+                n
+            }
+        };
+        if num_chunks == 0 {
+            self.data_num += 1;
+            self.num_chunks = None;
+            return self.load_next();
+        }
+
+        // Each chunk is cached separately so that the memory can be released as soon as all
+        // processors have moved on to the next chunk.
+        let key = match &self.instrument {
+            Some(instrument) => format!("{filepath}#{}#{instrument:?}", self.chunk_num),
+            None => format!("{filepath}#{}", self.chunk_num),
+        };
+        if !self.cache.contains(&key) {
+            let data = read_chunk(path, self.chunk_num, self.instrument.as_ref())?;
+            let data = self.apply_filter(&filepath, data);
+            self.cache.insert(key.clone(), data);
+        }
+        let data = self.cache.get(&key);
+
+        self.chunk_num += 1;
+        if self.chunk_num == num_chunks {
+            self.data_num += 1;
+            self.chunk_num = 0;
+            self.num_chunks = None;
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, env, fs::File, io::BufWriter, mem::size_of, rc::Rc};
+
+    use super::{Cache, Reader};
+    use crate::{
+        backtest::{
+            reader::{write_npy, Data, EventValidator, ValidationIssue, ValidationMode},
+            BacktestError,
+        },
+        types::{Event, EXCH_EVENT, LOCAL_EVENT},
+    };
+
+    fn data(len: usize) -> Data<Event> {
+        let mut rows = Vec::new();
+        for _ in 0..len {
+            rows.push(Event {
+                ev: 0,
+                exch_ts: 0,
+                local_ts: 0,
+                px: 0.0,
+                qty: 0.0,
+                order_id: 0,
+                ival: 0,
+                fval: 0.0,
+            });
+        }
+        Data::from_slice(&rows)
+    }
+
+    #[test]
+    fn test_cache_memory_limit() {
+        let size = std::mem::size_of::<Event>();
+        let mut cache = Cache::with_memory_limit(2 * size);
+
+        cache.insert("a".to_string(), data(1));
+        let a = cache.get("a");
+        cache.insert("b".to_string(), data(1));
+        let b = cache.get("b");
+        cache.remove(a);
+        // Released data is kept while it is within the limit.
+        assert!(cache.contains("a"));
+
+        // Exceeding the limit evicts the least recently used data that is released.
+        cache.insert("c".to_string(), data(1));
+        assert!(!cache.contains("a"));
+        assert!(cache.contains("b"));
+        assert!(cache.contains("c"));
+        assert_eq!(cache.memory_usage(), 2 * size);
+
+        // Data in use is not evicted even if the limit is exceeded.
+        let _c = cache.get("c");
+        cache.insert("d".to_string(), data(1));
+        let _d = cache.get("d");
+        assert!(cache.contains("b"));
+        assert_eq!(cache.memory_usage(), 3 * size);
+        cache.remove(b);
+        assert!(!cache.contains("b"));
+    }
+
+    #[test]
+    fn test_validate_once() {
+        let events = [1, 2, 3, 0, 4]
+            .into_iter()
+            .map(|ts| Event {
+                ev: EXCH_EVENT | LOCAL_EVENT,
+                exch_ts: ts,
+                local_ts: ts,
+                ..data(1)[0].clone()
+            })
+            .collect::<Vec<_>>();
+        let path = env::temp_dir().join(format!("{}.npy", uuid::Uuid::new_v4()));
+        write_npy(&mut BufWriter::new(File::create(&path).unwrap()), &events).unwrap();
+
+        let issues = Rc::new(RefCell::new(Vec::new()));
+        let issues_ = issues.clone();
+        let mut reader = Reader::new(Cache::new());
+        reader.set_chunk_size(Some(2 * size_of::<Event>()));
+        reader.set_validator(EventValidator::new(ValidationMode::Warn(Rc::new(
+            move |issue| issues_.borrow_mut().push(issue.clone()),
+        ))));
+        reader.add_file(path.to_str().unwrap().to_string());
+
+        // Both clones read the whole file, but each chunk is validated once, and the rows are
+        // counted from the beginning of the file.
+        let mut clone = reader.clone();
+        for reader in [&mut reader, &mut clone] {
+            loop {
+                match reader.next() {
+                    Ok(data) => reader.release(data),
+                    Err(BacktestError::EndOfData) => break,
+                    Err(error) => panic!("{error:?}"),
+                }
+            }
+        }
+        assert_eq!(
+            *issues.borrow(),
+            vec![
+                ValidationIssue::NonMonotonicExchTs {
+                    row: 3,
+                    prev_ts: 3,
+                    ts: 0
+                },
+                ValidationIssue::NonMonotonicLocalTs {
+                    row: 3,
+                    prev_ts: 3,
+                    ts: 0
+                },
+            ]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
 }
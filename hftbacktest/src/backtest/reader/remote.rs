@@ -0,0 +1,94 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    env,
+    fs,
+    fs::File,
+    hash::{Hash, Hasher},
+    io::{BufWriter, Error, ErrorKind, Result, Write},
+    path::PathBuf,
+};
+
+/// Returns the HTTP(S) URL of the object for an `s3://bucket/key` URI.
+///
+/// Requests are not signed, so the object needs to be publicly readable. For private objects, use
+/// a presigned HTTPS URL instead. The endpoint can be set by the `AWS_ENDPOINT_URL` environment
+/// variable for S3-compatible storage, and the region by `AWS_REGION`.
+fn s3_to_https(uri: &str) -> Result<String> {
+    let (bucket, key) = uri
+        .trim_start_matches("s3://")
+        .split_once('/')
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("invalid S3 URI `{uri}`")))?;
+    if let Ok(endpoint) = env::var("AWS_ENDPOINT_URL") {
+        Ok(format!("{}/{bucket}/{key}", endpoint.trim_end_matches('/')))
+    } else if let Ok(region) = env::var("AWS_REGION") {
+        Ok(format!("https://{bucket}.s3.{region}.amazonaws.com/{key}"))
+    } else {
+        Ok(format!("https://{bucket}.s3.amazonaws.com/{key}"))
+    }
+}
+
+/// Returns the directory where remote files are downloaded, which is set by the
+/// `HFTBACKTEST_DOWNLOAD_DIR` environment variable, or `hftbacktest` under the system's temporary
+/// directory by default.
+fn download_dir() -> PathBuf {
+    match env::var("HFTBACKTEST_DOWNLOAD_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => env::temp_dir().join("hftbacktest"),
+    }
+}
+
+/// Downloads the file at the HTTP(S) URL or the `s3://` URI into the download directory and
+/// returns the local path. The response body is streamed to the file rather than buffered in
+/// memory. The downloaded file keeps the extension of the URL so that its format can be detected,
+/// and is reused if it has already been downloaded.
+pub fn download(url: &str) -> Result<String> {
+    let http_url = if url.starts_with("s3://") {
+        s3_to_https(url)?
+    } else {
+        url.to_string()
+    };
+
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .unwrap_or_default();
+    let dir = download_dir();
+    let path = dir.join(format!("{:016x}_{name}", hasher.finish()));
+    if path.exists() {
+        return Ok(path.to_string_lossy().to_string());
+    }
+
+    fs::create_dir_all(&dir)?;
+    let to_io_error = |error: reqwest::Error| Error::new(ErrorKind::Other, error);
+    let mut response = reqwest::blocking::get(&http_url)
+        .and_then(|response| response.error_for_status())
+        .map_err(to_io_error)?;
+    // Downloads into a temporary file first so that an interrupted download is not mistaken for a
+    // complete one.
+    let part = path.with_extension("part");
+    let mut writer = BufWriter::new(File::create(&part)?);
+    response.copy_to(&mut writer).map_err(to_io_error)?;
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&part, &path)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::s3_to_https;
+
+    #[test]
+    fn test_s3_to_https() {
+        std::env::remove_var("AWS_ENDPOINT_URL");
+        std::env::remove_var("AWS_REGION");
+        assert_eq!(
+            s3_to_https("s3://my-bucket/feed/btcusdt_20240501.npz").unwrap(),
+            "https://my-bucket.s3.amazonaws.com/feed/btcusdt_20240501.npz"
+        );
+        assert!(s3_to_https("s3://my-bucket").is_err());
+    }
+}
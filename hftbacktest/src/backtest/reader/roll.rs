@@ -0,0 +1,420 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fmt::{Debug, Formatter},
+    rc::Rc,
+};
+
+use crate::{
+    backtest::reader::{Data, DataFilter, DataSource, Reader},
+    types::{
+        Event,
+        BUY_EVENT,
+        DEPTH_CLEAR_EVENT,
+        DEPTH_EVENT,
+        DEPTH_SNAPSHOT_EVENT,
+        EXCH_EVENT,
+        LOCAL_EVENT,
+        LOCAL_ROLL_EVENT,
+        SELL_EVENT,
+    },
+};
+
+/// A contract of a continuous futures asset, which is traded from its roll timestamp until the
+/// roll timestamp of the next contract.
+///
+/// The events of each contract's data outside its period are dropped, and at the roll, the market
+/// depth is cleared and rebuilt from the contract's own depth events before the roll, so the
+/// roll timestamp should fall within the first `Data` of the contract's data, or at its start if
+/// it begins with a snapshot. The roll is exposed to the strategy through
+/// [`Bot::rolls`](crate::types::Bot::rolls).
+///
+/// The position carries over the roll as if the contract were rolled at no cost, and open orders
+/// remain at the previous contract's prices, so the strategy should handle them on the roll.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::backtest::{reader::Contract, DataSource};
+///
+/// let contracts = vec![
+///     Contract::new(vec![DataSource::File("esh4.npz".to_string())], 0),
+///     Contract::new(vec![DataSource::File("esm4.npz".to_string())], 1710374400000000000)
+///         .adjustment(55.25),
+/// ];
+/// ```
+pub struct Contract {
+    data: Vec<DataSource<Event>>,
+    roll_ts: i64,
+    adjustment: f64,
+}
+
+impl Contract {
+    /// Constructs an instance of `Contract` that is rolled into at `roll_ts`. The `roll_ts` of
+    /// the first contract only trims its data.
+    pub fn new(data: Vec<DataSource<Event>>, roll_ts: i64) -> Self {
+        Self {
+            data,
+            roll_ts,
+            adjustment: 0.0,
+        }
+    }
+
+    /// Sets the price adjustment at the roll into this contract, which is this contract's price
+    /// minus the previous contract's price at the roll, such as the difference of their
+    /// settlement prices. It should be a multiple of the tick size. The default value is `0`.
+    pub fn adjustment(self, adjustment: f64) -> Self {
+        Self { adjustment, ..self }
+    }
+}
+
+impl Reader<Event> {
+    /// Adds the data of the contracts of a continuous futures asset, in the order of their
+    /// rolls. If `back_adjust` is `true`, the prices of the earlier contracts are shifted by the
+    /// sum of the adjustments of the later contracts, so that the prices are continuous across
+    /// the rolls and are those of the last contract. See [`Contract`].
+    pub fn add_contracts(&mut self, contracts: Vec<Contract>, back_adjust: bool) {
+        let end_ts = contracts
+            .iter()
+            .skip(1)
+            .map(|contract| contract.roll_ts)
+            .chain([i64::MAX])
+            .collect::<Vec<_>>();
+        let mut price_offset = if back_adjust {
+            contracts
+                .iter()
+                .skip(1)
+                .map(|contract| contract.adjustment)
+                .sum::<f64>()
+        } else {
+            0.0
+        };
+        for (index, contract) in contracts.into_iter().enumerate() {
+            if back_adjust && index > 0 {
+                price_offset -= contract.adjustment;
+            }
+            let first = Rc::new(Cell::new(None));
+            for (source_no, source) in contract.data.into_iter().enumerate() {
+                let window = RollWindow {
+                    contract: index,
+                    begin_ts: contract.roll_ts,
+                    end_ts: end_ts[index],
+                    adjustment: contract.adjustment,
+                    price_offset,
+                    first: (source_no == 0).then(|| first.clone()),
+                };
+                self.add_filtered(source, vec![Box::new(window)]);
+            }
+        }
+    }
+}
+
+// The timestamps of the first row and the length of the first `Data` of a contract, which is set
+// once the `Data` is loaded.
+type FirstData = Rc<Cell<Option<(i64, i64, usize)>>>;
+
+/// Keeps the events of a contract within its period and inserts the roll at the beginning.
+#[derive(Clone)]
+struct RollWindow {
+    contract: usize,
+    begin_ts: i64,
+    end_ts: i64,
+    adjustment: f64,
+    price_offset: f64,
+    // Identifies the first `Data` of the contract, by the timestamps of its first row and its
+    // length, so that the roll is inserted again if the `Data` is reloaded.
+    first: Option<FirstData>,
+}
+
+impl Debug for RollWindow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RollWindow")
+            .field("contract", &self.contract)
+            .field("begin_ts", &self.begin_ts)
+            .field("end_ts", &self.end_ts)
+            .field("adjustment", &self.adjustment)
+            .field("price_offset", &self.price_offset)
+            .finish()
+    }
+}
+
+impl RollWindow {
+    #[inline]
+    fn contains(&self, ts: i64) -> bool {
+        self.begin_ts <= ts && ts < self.end_ts
+    }
+
+    fn is_first(&self, data: &Data<Event>) -> bool {
+        let Some(first) = &self.first else {
+            return false;
+        };
+        if data.len() == 0 {
+            return false;
+        }
+        let id = (data[0].exch_ts, data[0].local_ts, data.len());
+        match first.get() {
+            Some(first_id) => first_id == id,
+            None => {
+                first.set(Some(id));
+                true
+            }
+        }
+    }
+
+    fn adjust(&self, ev: &Event) -> Event {
+        let kind = ev.ev & 0xff;
+        if self.price_offset != 0.0 && !ev.is_aux() && kind != DEPTH_CLEAR_EVENT {
+            Event {
+                px: ev.px + self.price_offset,
+                ..ev.clone()
+            }
+        } else {
+            ev.clone()
+        }
+    }
+
+    fn roll(&self, data: &Data<Event>) -> Vec<Event> {
+        let mut rows = vec![Event {
+            ev: EXCH_EVENT | LOCAL_EVENT | DEPTH_CLEAR_EVENT,
+            exch_ts: self.begin_ts,
+            local_ts: self.begin_ts,
+            px: 0.0,
+            qty: 0.0,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        }];
+        if self.contract > 0 {
+            rows.push(Event {
+                ev: LOCAL_ROLL_EVENT,
+                px: self.adjustment,
+                order_id: self.contract as u64,
+                ..rows[0].clone()
+            });
+        }
+        for (flag, book) in [
+            (EXCH_EVENT, Book::before(data, self.begin_ts, EXCH_EVENT)),
+            (LOCAL_EVENT, Book::before(data, self.begin_ts, LOCAL_EVENT)),
+        ] {
+            for (side, px, qty) in book.levels() {
+                rows.push(self.adjust(&Event {
+                    ev: flag | DEPTH_SNAPSHOT_EVENT | side,
+                    px,
+                    qty,
+                    ..rows[0].clone()
+                }));
+            }
+        }
+        rows
+    }
+}
+
+impl DataFilter<Event> for RollWindow {
+    fn filter(&self, data: &Data<Event>) -> Option<Data<Event>> {
+        let mut rows = if self.is_first(data) {
+            self.roll(data)
+        } else {
+            Vec::with_capacity(data.len())
+        };
+        let mut modified = !rows.is_empty() || self.price_offset != 0.0;
+        for row in 0..data.len() {
+            let ev = &data[row];
+            let mut flags = ev.ev;
+            if ev.is(EXCH_EVENT) && !self.contains(ev.exch_ts) {
+                flags &= !EXCH_EVENT;
+            }
+            if ev.is(LOCAL_EVENT) && !self.contains(ev.local_ts) {
+                flags &= !LOCAL_EVENT;
+            }
+            if flags != ev.ev {
+                modified = true;
+            }
+            if flags & (EXCH_EVENT | LOCAL_EVENT) != 0 {
+                rows.push(self.adjust(&Event {
+                    ev: flags,
+                    ..ev.clone()
+                }));
+            }
+        }
+        modified.then(|| Data::from_slice(&rows))
+    }
+}
+
+/// The market depth of one side of the feed, either exchange or local, built from depth events.
+#[derive(Default)]
+struct Book {
+    bids: HashMap<u64, f64>,
+    asks: HashMap<u64, f64>,
+}
+
+impl Book {
+    fn before(data: &Data<Event>, ts: i64, flag: u64) -> Self {
+        let mut book = Self::default();
+        for row in 0..data.len() {
+            let ev = &data[row];
+            if ev.ev & flag == 0 {
+                continue;
+            }
+            let ev_ts = if flag == EXCH_EVENT {
+                ev.exch_ts
+            } else {
+                ev.local_ts
+            };
+            if ev_ts >= ts {
+                continue;
+            }
+            let kind = ev.ev & 0xff;
+            let side = ev.ev & (BUY_EVENT | SELL_EVENT);
+            if kind == DEPTH_CLEAR_EVENT {
+                // The clear up to the price follows the market depth's `clear_depth`.
+                match side {
+                    BUY_EVENT => book.bids.retain(|&px, _| f64::from_bits(px) < ev.px),
+                    SELL_EVENT => book.asks.retain(|&px, _| f64::from_bits(px) > ev.px),
+                    _ => {
+                        book.bids.clear();
+                        book.asks.clear();
+                    }
+                }
+            } else if kind == DEPTH_EVENT || kind == DEPTH_SNAPSHOT_EVENT {
+                let levels = match side {
+                    BUY_EVENT => &mut book.bids,
+                    SELL_EVENT => &mut book.asks,
+                    _ => continue,
+                };
+                if ev.qty > 0.0 {
+                    levels.insert(ev.px.to_bits(), ev.qty);
+                } else {
+                    levels.remove(&ev.px.to_bits());
+                }
+            }
+        }
+        book
+    }
+
+    fn levels(&self) -> Vec<(u64, f64, f64)> {
+        let mut bids = self
+            .bids
+            .iter()
+            .map(|(&px, &qty)| (BUY_EVENT, f64::from_bits(px), qty))
+            .collect::<Vec<_>>();
+        let mut asks = self
+            .asks
+            .iter()
+            .map(|(&px, &qty)| (SELL_EVENT, f64::from_bits(px), qty))
+            .collect::<Vec<_>>();
+        bids.sort_by(|a, b| a.1.total_cmp(&b.1));
+        asks.sort_by(|a, b| a.1.total_cmp(&b.1));
+        bids.extend(asks);
+        bids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backtest::{
+            proc::test_utils,
+            reader::{Cache, Contract, Data, DataSource, Reader},
+            BacktestError,
+        },
+        types::{
+            Event,
+            BUY_EVENT,
+            DEPTH_CLEAR_EVENT,
+            DEPTH_EVENT,
+            DEPTH_SNAPSHOT_EVENT,
+            EXCH_EVENT,
+            LOCAL_EVENT,
+            LOCAL_ROLL_EVENT,
+            SELL_EVENT,
+            TRADE_EVENT,
+        },
+    };
+
+    fn event(ev: u64, ts: i64, px: f64, qty: f64) -> Event {
+        Event {
+            local_ts: ts + 1,
+            ..test_utils::event(ev, ts, px, qty)
+        }
+    }
+
+    #[test]
+    fn test_contracts() {
+        let front = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 10, 100.0, 1.0),
+            event(TRADE_EVENT | BUY_EVENT, 20, 100.0, 1.0),
+            event(TRADE_EVENT | BUY_EVENT, 30, 101.0, 1.0),
+        ]);
+        let back = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 10, 104.0, 1.0),
+            event(DEPTH_EVENT | SELL_EVENT, 20, 106.0, 2.0),
+            event(DEPTH_EVENT | BUY_EVENT, 30, 105.0, 3.0),
+        ]);
+        let mut reader = Reader::new(Cache::new());
+        reader.add_contracts(
+            vec![
+                Contract::new(vec![DataSource::Data(front)], 0),
+                Contract::new(vec![DataSource::Data(back)], 25).adjustment(4.0),
+            ],
+            true,
+        );
+
+        // The front contract's prices are back-adjusted, and its events after the roll are
+        // dropped.
+        let data = reader.next().unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[0].ev, EXCH_EVENT | LOCAL_EVENT | DEPTH_CLEAR_EVENT);
+        assert_eq!(data[1].px, 104.0);
+        assert_eq!(
+            data[2].ev,
+            EXCH_EVENT | LOCAL_EVENT | TRADE_EVENT | BUY_EVENT
+        );
+        assert_eq!(data[2].px, 104.0);
+
+        // The back contract's market depth before the roll is inserted as a snapshot.
+
+        let data = reader.next().unwrap();
+        let summary = (0..data.len())
+            .map(|row| (data[row].ev, data[row].exch_ts, data[row].px, data[row].qty))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (EXCH_EVENT | LOCAL_EVENT | DEPTH_CLEAR_EVENT, 25, 0.0, 0.0),
+                (LOCAL_ROLL_EVENT, 25, 4.0, 0.0),
+                (
+                    EXCH_EVENT | DEPTH_SNAPSHOT_EVENT | BUY_EVENT,
+                    25,
+                    104.0,
+                    1.0
+                ),
+                (
+                    EXCH_EVENT | DEPTH_SNAPSHOT_EVENT | SELL_EVENT,
+                    25,
+                    106.0,
+                    2.0
+                ),
+                (
+                    LOCAL_EVENT | DEPTH_SNAPSHOT_EVENT | BUY_EVENT,
+                    25,
+                    104.0,
+                    1.0
+                ),
+                (
+                    LOCAL_EVENT | DEPTH_SNAPSHOT_EVENT | SELL_EVENT,
+                    25,
+                    106.0,
+                    2.0
+                ),
+                (
+                    EXCH_EVENT | LOCAL_EVENT | DEPTH_EVENT | BUY_EVENT,
+                    30,
+                    105.0,
+                    3.0
+                ),
+            ]
+        );
+        assert_eq!(data[1].order_id, 1);
+
+        assert!(matches!(reader.next(), Err(BacktestError::EndOfData)));
+    }
+}
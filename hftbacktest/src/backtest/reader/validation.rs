@@ -0,0 +1,258 @@
+use std::{
+    fmt::{Debug, Display, Formatter},
+    io::{Error, ErrorKind, Result},
+    rc::Rc,
+};
+
+use dyn_clone::DynClone;
+
+use crate::{
+    backtest::reader::{Data, POD},
+    types::{
+        Event,
+        BAR_EVENT,
+        DEPTH_BBO_EVENT,
+        DEPTH_EVENT,
+        DEPTH_SNAPSHOT_EVENT,
+        EXCH_EVENT,
+        LOCAL_EVENT,
+        SETTLEMENT_EVENT,
+        TRADE_EVENT,
+    },
+};
+
+/// Validates `Data` as it is consumed by the [`Reader`](crate::backtest::reader::Reader).
+pub trait DataValidator<D>: DynClone + Debug
+where
+    D: POD + Clone,
+{
+    /// Validates the data, which is given in the order of reading. `row_offset` is the row within
+    /// the source, such as a file, at which the data begins, since a source can be read in multiple
+    /// chunks. Returns an error if the data is invalid and the reading should stop.
+    fn validate(&mut self, data: &Data<D>, row_offset: usize) -> Result<()>;
+}
+
+dyn_clone::clone_trait_object!(<D> DataValidator<D> where D: POD + Clone);
+
+/// A problem found in the feed data by [`EventValidator`]. The row is the row within the source,
+/// such as a file.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ValidationIssue {
+    /// The exchange timestamp of an exchange event is earlier than that of the previous one.
+    NonMonotonicExchTs { row: usize, prev_ts: i64, ts: i64 },
+    /// The local timestamp of a local event is earlier than that of the previous one.
+    NonMonotonicLocalTs { row: usize, prev_ts: i64, ts: i64 },
+    /// The quantity is negative.
+    NegativeQty { row: usize, qty: f64 },
+    /// The price of a depth, trade, bar, or settlement event is zero.
+    ZeroPrice { row: usize },
+    /// The local timestamp is earlier than the exchange timestamp.
+    LocalTsBeforeExchTs {
+        row: usize,
+        exch_ts: i64,
+        local_ts: i64,
+    },
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::NonMonotonicExchTs { row, prev_ts, ts } => {
+                write!(f, "row {row}: exch_ts {ts} is earlier than {prev_ts}")
+            }
+            ValidationIssue::NonMonotonicLocalTs { row, prev_ts, ts } => {
+                write!(f, "row {row}: local_ts {ts} is earlier than {prev_ts}")
+            }
+            ValidationIssue::NegativeQty { row, qty } => {
+                write!(f, "row {row}: qty {qty} is negative")
+            }
+            ValidationIssue::ZeroPrice { row } => write!(f, "row {row}: px is zero"),
+            ValidationIssue::LocalTsBeforeExchTs {
+                row,
+                exch_ts,
+                local_ts,
+            } => write!(
+                f,
+                "row {row}: local_ts {local_ts} is earlier than exch_ts {exch_ts}"
+            ),
+        }
+    }
+}
+
+/// Determines how [`EventValidator`] reacts to a [`ValidationIssue`].
+#[derive(Clone)]
+pub enum ValidationMode {
+    /// Stops reading with [`BacktestError::DataError`](crate::backtest::BacktestError::DataError).
+    Error,
+    /// Invokes the callback and continues reading.
+    Warn(Rc<dyn Fn(&ValidationIssue)>),
+}
+
+impl Debug for ValidationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationMode::Error => write!(f, "Error"),
+            ValidationMode::Warn(_) => write!(f, "Warn"),
+        }
+    }
+}
+
+/// Validates [`Event`] data for non-monotonic timestamps, negative quantities, zero prices, and
+/// local timestamps earlier than exchange timestamps. Timestamp monotonicity is checked across
+/// consecutive `Data`.
+#[derive(Clone, Debug)]
+pub struct EventValidator {
+    mode: ValidationMode,
+    last_exch_ts: i64,
+    last_local_ts: i64,
+}
+
+impl EventValidator {
+    /// Constructs an instance of `EventValidator`.
+    pub fn new(mode: ValidationMode) -> Self {
+        Self {
+            mode,
+            last_exch_ts: i64::MIN,
+            last_local_ts: i64::MIN,
+        }
+    }
+
+    fn report(&self, issue: ValidationIssue) -> Result<()> {
+        match &self.mode {
+            ValidationMode::Error => Err(Error::new(ErrorKind::InvalidData, issue.to_string())),
+            ValidationMode::Warn(callback) => {
+                callback(&issue);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl DataValidator<Event> for EventValidator {
+    fn validate(&mut self, data: &Data<Event>, row_offset: usize) -> Result<()> {
+        for i in 0..data.len() {
+            let ev = &data[i];
+            let row = row_offset + i;
+            if ev.is(EXCH_EVENT) {
+                if ev.exch_ts < self.last_exch_ts {
+                    self.report(ValidationIssue::NonMonotonicExchTs {
+                        row,
+                        prev_ts: self.last_exch_ts,
+                        ts: ev.exch_ts,
+                    })?;
+                }
+                self.last_exch_ts = ev.exch_ts;
+            }
+            if ev.is(LOCAL_EVENT) {
+                if ev.local_ts < self.last_local_ts {
+                    self.report(ValidationIssue::NonMonotonicLocalTs {
+                        row,
+                        prev_ts: self.last_local_ts,
+                        ts: ev.local_ts,
+                    })?;
+                }
+                self.last_local_ts = ev.local_ts;
+                if ev.local_ts < ev.exch_ts {
+                    self.report(ValidationIssue::LocalTsBeforeExchTs {
+                        row,
+                        exch_ts: ev.exch_ts,
+                        local_ts: ev.local_ts,
+                    })?;
+                }
+            }
+            if ev.qty < 0.0 {
+                self.report(ValidationIssue::NegativeQty { row, qty: ev.qty })?;
+            }
+            let kind = ev.ev & 0xff;
+            if (kind == DEPTH_EVENT
+                || kind == TRADE_EVENT
+                || kind == DEPTH_SNAPSHOT_EVENT
+                || kind == DEPTH_BBO_EVENT
+                || kind == BAR_EVENT
+                || kind == SETTLEMENT_EVENT)
+                && ev.px == 0.0
+            {
+                self.report(ValidationIssue::ZeroPrice { row })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::{DataValidator, EventValidator, ValidationIssue, ValidationMode};
+    use crate::{
+        backtest::reader::Data,
+        types::{Event, EXCH_EVENT, LOCAL_BID_DEPTH_EVENT, LOCAL_EVENT},
+    };
+
+    fn event(exch_ts: i64, local_ts: i64, px: f64, qty: f64) -> Event {
+        Event {
+            ev: EXCH_EVENT | LOCAL_BID_DEPTH_EVENT,
+            exch_ts,
+            local_ts,
+            px,
+            qty,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_validate_error() {
+        let mut validator = EventValidator::new(ValidationMode::Error);
+        assert!(validator
+            .validate(
+                &Data::from_slice(&[event(1, 2, 1.0, 1.0), event(2, 3, 1.0, 1.0)]),
+                0
+            )
+            .is_ok());
+        // Monotonicity is checked across data.
+        assert!(validator
+            .validate(&Data::from_slice(&[event(1, 4, 1.0, 1.0)]), 2)
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate_warn() {
+        let issues = Rc::new(RefCell::new(Vec::new()));
+        let issues_ = issues.clone();
+        let mut validator = EventValidator::new(ValidationMode::Warn(Rc::new(move |issue| {
+            issues_.borrow_mut().push(issue.clone())
+        })));
+        validator
+            .validate(
+                &Data::from_slice(&[
+                    event(5, 4, 1.0, 1.0),
+                    event(6, 7, 0.0, -1.0),
+                    Event {
+                        ev: LOCAL_EVENT | LOCAL_BID_DEPTH_EVENT,
+                        ..event(0, 6, 1.0, 1.0)
+                    },
+                ]),
+                10,
+            )
+            .unwrap();
+        assert_eq!(
+            *issues.borrow(),
+            vec![
+                ValidationIssue::LocalTsBeforeExchTs {
+                    row: 10,
+                    exch_ts: 5,
+                    local_ts: 4
+                },
+                ValidationIssue::NegativeQty { row: 11, qty: -1.0 },
+                ValidationIssue::ZeroPrice { row: 11 },
+                ValidationIssue::NonMonotonicLocalTs {
+                    row: 12,
+                    prev_ts: 7,
+                    ts: 6
+                },
+            ]
+        );
+    }
+}
@@ -8,7 +8,10 @@ use hftbacktest_derive::NpyDTyped;
 use zip::{write::SimpleFileOptions, ZipWriter};
 
 use crate::{
-    backtest::reader::{write_npy, POD},
+    backtest::{
+        models::OrderLatencyRow,
+        reader::{write_npy, write_npz, POD},
+    },
     depth::MarketDepth,
     types::{Bot, Recorder},
 };
@@ -24,6 +27,8 @@ struct Record {
     num_trades: i64,
     trading_volume: f64,
     trading_value: f64,
+    funding: f64,
+    borrow_cost: f64,
 }
 
 unsafe impl POD for Record {}
@@ -57,6 +62,8 @@ impl Recorder for BacktestRecorder {
                 trading_volume: state_values.trading_volume,
                 trading_value: state_values.trading_value,
                 num_trades: state_values.num_trades,
+                funding: state_values.funding,
+                borrow_cost: state_values.borrow_cost,
             });
         }
         Ok(())
@@ -83,8 +90,10 @@ impl BacktestRecorder {
 
     /// Saves record data into a CSV file at the specified path. It creates a separate CSV file for
     /// each asset, with the filename `{prefix}_{asset_no}.csv`.
+    /// The timestamps are in nanoseconds, the time unit of the engine. See
+    /// [`TimeUnit`](crate::types::TimeUnit).
     /// The columns are `timestamp`, `mid`, `balance`, `position`, `fee`, `trade_num`,
-    /// `trade_amount`, `trade_qty`.
+    /// `trade_amount`, `trade_qty`, `funding`, `borrow_cost`.
     pub fn to_csv<Prefix, P>(&self, prefix: Prefix, path: P) -> Result<(), Error>
     where
         Prefix: AsRef<str>,
@@ -96,7 +105,7 @@ impl BacktestRecorder {
             let mut file = File::create(file_path)?;
             write!(
                 file,
-                "timestamp,balance,position,fee,trading_volume,trading_value,num_trades,price\n",
+                "timestamp,balance,position,fee,trading_volume,trading_value,num_trades,price,funding,borrow_cost\n",
             )?;
             for Record {
                 timestamp,
@@ -107,11 +116,13 @@ impl BacktestRecorder {
                 trading_value,
                 num_trades,
                 price: mid_price,
+                funding,
+                borrow_cost,
             } in values
             {
                 write!(
                     file,
-                    "{},{},{},{},{},{},{},{}\n",
+                    "{},{},{},{},{},{},{},{},{},{}\n",
                     timestamp,
                     balance,
                     position,
@@ -120,6 +131,8 @@ impl BacktestRecorder {
                     trading_value,
                     num_trades,
                     mid_price,
+                    funding,
+                    borrow_cost,
                 )?;
             }
         }
@@ -147,3 +160,86 @@ impl BacktestRecorder {
         Ok(())
     }
 }
+
+/// Provides recording of the order latency that the bot experiences, which is the request
+/// timestamp, the exchange timestamp, and the response timestamp of the latest order response
+/// given by [`Bot::order_latency`]. It is meant to be used with
+/// [`LiveBot`](crate::live::LiveBot), so that the live order latency can be replayed by
+/// [`IntpOrderLatency`](crate::backtest::models::IntpOrderLatency) or fitted by
+/// [`LatencyCalibration`](crate::backtest::models::LatencyCalibration).
+///
+/// Since only the latest order response is available, [`record`](Recorder::record) needs to be
+/// called after every order response to record all of them, for example, after each
+/// [`wait_order_response`](Bot::wait_order_response). The exchange timestamp is as reported by
+/// the exchange, so the local clock needs to be synchronized with it for accurate latency.
+pub struct LatencyRecorder {
+    values: Vec<Vec<OrderLatencyRow>>,
+}
+
+impl Recorder for LatencyRecorder {
+    type Error = Error;
+
+    fn record<MD, I>(&mut self, hbt: &mut I) -> Result<(), Self::Error>
+    where
+        MD: MarketDepth,
+        I: Bot<MD>,
+    {
+        for asset_no in 0..hbt.num_assets() {
+            let Some((req_ts, exch_ts, resp_ts)) = hbt.order_latency(asset_no) else {
+                continue;
+            };
+            let values = unsafe { self.values.get_unchecked_mut(asset_no) };
+            // Skips the order response that is already recorded.
+            if let Some(last) = values.last() {
+                if last.req_ts == req_ts && last.exch_ts == exch_ts && last.resp_ts == resp_ts {
+                    continue;
+                }
+            }
+            values.push(OrderLatencyRow {
+                req_ts,
+                exch_ts,
+                resp_ts,
+                _padding: 0,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl LatencyRecorder {
+    /// Constructs an instance of `LatencyRecorder`.
+    pub fn new<I, MD>(hbt: &I) -> Self
+    where
+        MD: MarketDepth,
+        I: Bot<MD>,
+    {
+        Self {
+            values: (0..hbt.num_assets()).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    /// Returns the recorded order latency of the asset, sorted by the request timestamp.
+    pub fn rows(&self, asset_no: usize) -> Vec<OrderLatencyRow> {
+        let mut rows = self.values[asset_no].clone();
+        rows.sort_by_key(|row| row.req_ts);
+        rows
+    }
+
+    /// Saves the recorded order latency into `npz` files that
+    /// [`IntpOrderLatency`](crate::backtest::models::IntpOrderLatency) reads. It creates a separate
+    /// file for each asset, with the filename `{prefix}{asset_no}.npz`. The rows are sorted by the
+    /// request timestamp.
+    pub fn to_npz<Prefix, P>(&self, prefix: Prefix, path: P) -> Result<(), Error>
+    where
+        Prefix: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        let prefix = prefix.as_ref();
+        for asset_no in 0..self.values.len() {
+            let file_path = path.as_ref().join(format!("{prefix}{asset_no}.npz"));
+            let mut file = File::create(file_path)?;
+            write_npz(&mut file, "data", &self.rows(asset_no))?;
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
 use crate::{
-    backtest::assettype::AssetType,
-    types::{Order, StateValues},
+    backtest::{
+        assettype::AssetType,
+        models::FeeModel,
+        proc::{BorrowCost, FundingSchedule, Margin, LIQUIDATION_ORDER_ID, SETTLEMENT_ORDER_ID},
+    },
+    types::{Order, PositionMode, Side, StateValues},
 };
 
 #[derive(Debug)]
@@ -9,16 +13,19 @@ where
     AT: AssetType,
 {
     pub state_values: StateValues,
-    pub maker_fee: f64,
-    pub taker_fee: f64,
+    pub fee_model: Box<dyn FeeModel>,
     pub asset_type: AT,
+    pub funding_schedule: Option<FundingSchedule>,
+    pub margin: Option<Margin>,
+    pub position_mode: PositionMode,
+    pub borrow_cost: Option<BorrowCost>,
 }
 
 impl<AT> State<AT>
 where
     AT: AssetType,
 {
-    pub fn new(asset_type: AT, maker_fee: f64, taker_fee: f64) -> Self {
+    pub fn new(asset_type: AT, fee_model: Box<dyn FeeModel>) -> Self {
         Self {
             state_values: StateValues {
                 position: 0.0,
@@ -27,41 +34,351 @@ where
                 num_trades: 0,
                 trading_volume: 0.0,
                 trading_value: 0.0,
+                funding: 0.0,
+                long_position: 0.0,
+                short_position: 0.0,
+                long_entry_price: 0.0,
+                short_entry_price: 0.0,
+                borrow_cost: 0.0,
             },
-            maker_fee,
-            taker_fee,
+            fee_model,
             asset_type,
+            funding_schedule: None,
+            margin: None,
+            position_mode: PositionMode::OneWay,
+            borrow_cost: None,
+        }
+    }
+
+    /// Sets the [`FundingSchedule`] by which the funding payments are settled, in addition to the
+    /// [`FUNDING_EVENT`](crate::types::FUNDING_EVENT)s in the data.
+    pub fn with_funding_schedule(self, funding_schedule: Option<FundingSchedule>) -> Self {
+        Self {
+            funding_schedule,
+            ..self
+        }
+    }
+
+    /// Sets the [`Margin`] that determines the margin requirements of the position. Without it, the
+    /// account has infinite margin.
+    pub fn with_margin(self, margin: Option<Margin>) -> Self {
+        Self { margin, ..self }
+    }
+
+    /// Sets the [`PositionMode`], which determines whether the long and short positions are held
+    /// as separate legs.
+    pub fn with_position_mode(self, position_mode: PositionMode) -> Self {
+        Self {
+            position_mode,
+            ..self
+        }
+    }
+
+    /// Sets the [`BorrowCost`] that charges the borrow interest on the short position or disallows
+    /// the short sale. Without it, the short position is free to carry.
+    pub fn with_borrow_cost(self, borrow_cost: Option<BorrowCost>) -> Self {
+        Self {
+            borrow_cost,
+            ..self
         }
     }
 
     #[inline]
     pub fn apply_fill(&mut self, order: &Order) {
-        let fee = if order.maker {
-            self.maker_fee
+        let amount = self.asset_type.amount(order.exec_price(), order.exec_qty);
+        // No fee is charged on the settlement upon the contract expiry.
+        let fee = if order.order_id == SETTLEMENT_ORDER_ID {
+            0.0
         } else {
-            self.taker_fee
+            self.fee_model.fee(order, amount, &self.state_values)
         };
-        let amount = self.asset_type.amount(order.exec_price(), order.exec_qty);
         self.state_values.position += order.exec_qty * AsRef::<f64>::as_ref(&order.side);
         self.state_values.balance -= amount * AsRef::<f64>::as_ref(&order.side);
-        self.state_values.fee += amount * fee;
+        self.state_values.fee += fee;
+        if order.order_id == LIQUIDATION_ORDER_ID {
+            if let Some(margin) = &self.margin {
+                self.state_values.fee += margin.liquidation_fee(amount);
+            }
+        }
         self.state_values.num_trades += 1;
         self.state_values.trading_volume += order.exec_qty;
         self.state_values.trading_value += amount;
+        self.apply_fill_to_legs(order);
+    }
+
+    /// Updates the legs of the position and their average entry prices by the fill. In
+    /// [`PositionMode::OneWay`], a fill first reduces the leg opposite to its side, and the rest
+    /// opens or increases the leg of its side. In [`PositionMode::Hedge`], a fill either increases
+    /// or, if the order is reduce-only, reduces the leg given by [`Order::hedge_leg`].
+    fn apply_fill_to_legs(&mut self, order: &Order) {
+        let (reduces, increases) = match self.position_mode {
+            PositionMode::OneWay => (true, true),
+            PositionMode::Hedge => (order.reduce_only, !order.reduce_only),
+        };
+        let sv = &mut self.state_values;
+        let (opposite_qty, opposite_entry_price, qty, entry_price) = match order.side {
+            Side::Buy => (
+                &mut sv.short_position,
+                &mut sv.short_entry_price,
+                &mut sv.long_position,
+                &mut sv.long_entry_price,
+            ),
+            Side::Sell => (
+                &mut sv.long_position,
+                &mut sv.long_entry_price,
+                &mut sv.short_position,
+                &mut sv.short_entry_price,
+            ),
+            Side::None | Side::Unsupported => return,
+        };
+        let mut exec_qty = order.exec_qty;
+        if reduces {
+            let reduced_qty = exec_qty.min(*opposite_qty);
+            *opposite_qty -= reduced_qty;
+            if *opposite_qty <= 0.0 {
+                *opposite_qty = 0.0;
+                *opposite_entry_price = 0.0;
+            }
+            exec_qty -= reduced_qty;
+        }
+        if increases && exec_qty > order.exec_qty * 1e-9 {
+            let new_qty = *qty + exec_qty;
+            *entry_price = (*entry_price * *qty + order.exec_price() * exec_qty) / new_qty;
+            *qty = new_qty;
+        }
+    }
+
+    /// Applies the funding payment on the current position at the price and the funding rate. A
+    /// positive funding rate means that long positions pay short positions.
+    #[inline]
+    pub fn apply_funding(&mut self, price: f64, rate: f64) {
+        if self.state_values.position == 0.0 || !price.is_finite() {
+            return;
+        }
+        self.state_values.funding +=
+            self.asset_type.amount(price, self.state_values.position) * rate;
+    }
+
+    /// Applies the funding payments of the [`FundingSchedule`], if any, whose funding timestamps
+    /// have passed up to `timestamp`. `mid` provides the mid-price at which the payments are
+    /// calculated.
+    #[inline]
+    pub fn apply_scheduled_funding<F>(&mut self, timestamp: i64, mid: F)
+    where
+        F: FnOnce() -> f64,
+    {
+        let Some(schedule) = self.funding_schedule.as_mut() else {
+            return;
+        };
+        let due = schedule.take_due(timestamp);
+        if due > 0 {
+            let rate = schedule.rate() * due as f64;
+            self.apply_funding(mid(), rate);
+        }
+    }
+
+    /// Accrues the borrow interest of the [`BorrowCost`], if any, on the short position held from
+    /// the last accrual up to `timestamp`. `mid` provides the mid-price at which the notional value
+    /// of the short position is calculated.
+    #[inline]
+    pub fn apply_borrow_cost<F>(&mut self, timestamp: i64, mid: F)
+    where
+        F: FnOnce() -> f64,
+    {
+        let Some(borrow_cost) = self.borrow_cost.as_mut() else {
+            return;
+        };
+        let accrual = borrow_cost.take_accrual(timestamp);
+        if accrual == 0.0 || self.state_values.short_position <= 0.0 {
+            return;
+        }
+        let price = mid();
+        if price.is_finite() {
+            self.state_values.borrow_cost += self
+                .asset_type
+                .amount(price, self.state_values.short_position)
+                * accrual;
+        }
     }
 
     #[inline]
     pub fn equity(&self, mid: f64) -> f64 {
+        // The funding payments and the borrow interest are deducted from the equity along with the
+        // fee.
         self.asset_type.equity(
             mid,
             self.state_values.balance,
             self.state_values.position,
-            self.state_values.fee,
+            self.state_values.fee + self.state_values.funding + self.state_values.borrow_cost,
         )
     }
 
+    /// Returns the margin balance, the collateral plus the equity marked to the `mark` price, or
+    /// `None` if no [`Margin`] is set.
+    #[inline]
+    pub fn margin_balance(&self, mark: f64) -> Option<f64> {
+        self.margin
+            .as_ref()
+            .map(|margin| margin.collateral() + self.equity(mark))
+    }
+
+    /// Returns the signed position that a reduce-only order on the `side` reduces: the net
+    /// position in [`PositionMode::OneWay`], and in [`PositionMode::Hedge`], the short leg, which
+    /// is negative, for a buy order and the long leg for a sell order.
+    #[inline]
+    pub fn reducible_position(&self, side: Side) -> f64 {
+        match self.position_mode {
+            PositionMode::OneWay => self.state_values.position,
+            PositionMode::Hedge => match side {
+                Side::Buy => -self.state_values.short_position,
+                _ => self.state_values.long_position,
+            },
+        }
+    }
+
+    /// Returns `true` if the margin balance has fallen below the maintenance margin of the
+    /// position marked to the `mark` price, in which case the position is to be liquidated.
+    #[inline]
+    pub fn is_margin_breached(&self, mark: f64) -> bool {
+        let Some(margin) = &self.margin else {
+            return false;
+        };
+        if self.state_values.position == 0.0 || !mark.is_finite() {
+            return false;
+        }
+        let notional = self.asset_type.amount(mark, self.state_values.position);
+        margin.collateral() + self.equity(mark) < margin.maintenance_margin(notional)
+    }
+
+    /// Returns `true` if the margin balance covers the initial margin for a new order of `qty` on
+    /// the `side` on top of the open buy and sell quantities. An order that doesn't increase the
+    /// exposure on the heavier side is always allowed, as it reduces the risk.
+    #[inline]
+    pub fn has_initial_margin(
+        &self,
+        mark: f64,
+        open_buy_qty: f64,
+        open_sell_qty: f64,
+        side: Side,
+        qty: f64,
+    ) -> bool {
+        let Some(margin) = &self.margin else {
+            return true;
+        };
+        if !mark.is_finite() {
+            return true;
+        }
+        let position = self.state_values.position;
+        let exposure = |buy_qty: f64, sell_qty: f64| {
+            (position + buy_qty).abs().max((position - sell_qty).abs())
+        };
+        let prev_exposure = exposure(open_buy_qty, open_sell_qty);
+        let new_exposure = if side == Side::Buy {
+            exposure(open_buy_qty + qty, open_sell_qty)
+        } else {
+            exposure(open_buy_qty, open_sell_qty + qty)
+        };
+        if new_exposure <= prev_exposure {
+            return true;
+        }
+        let notional = self.asset_type.amount(mark, new_exposure);
+        margin.collateral() + self.equity(mark) >= margin.initial_margin(notional)
+    }
+
+    /// Returns `true` if the asset is shortable or the `order` doesn't sell short: a buy order, or
+    /// a sell order that, on top of the open sell quantity, doesn't take the position below zero
+    /// in [`PositionMode::OneWay`], and a reduce-only sell order in [`PositionMode::Hedge`].
+    #[inline]
+    pub fn is_shortable(&self, open_sell_qty: f64, order: &Order) -> bool {
+        if order.side != Side::Sell
+            || self
+                .borrow_cost
+                .as_ref()
+                .is_none_or(|borrow_cost| borrow_cost.is_shortable())
+        {
+            return true;
+        }
+        match self.position_mode {
+            PositionMode::OneWay => {
+                let qty = order.leaves_qty;
+                self.state_values.position - open_sell_qty - qty >= -qty * 1e-9
+            }
+            PositionMode::Hedge => order.reduce_only,
+        }
+    }
+
     #[inline]
     pub fn values(&self) -> &StateValues {
         &self.state_values
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::State;
+    use crate::{
+        backtest::{assettype::LinearAsset, models::FlatFee},
+        types::{OrdType, Order, PositionMode, Side, TimeInForce},
+    };
+
+    fn fill(side: Side, price_tick: i64, qty: f64, reduce_only: bool) -> Order {
+        let mut order = Order::new(
+            1,
+            price_tick,
+            1.0,
+            qty,
+            side,
+            OrdType::Limit,
+            TimeInForce::GTC,
+        );
+        order.reduce_only = reduce_only;
+        order.exec_price_tick = price_tick;
+        order.exec_qty = qty;
+        order
+    }
+
+    fn state(position_mode: PositionMode) -> State<LinearAsset> {
+        State::new(LinearAsset::new(1.0), Box::new(FlatFee::new(0.0, 0.0)))
+            .with_position_mode(position_mode)
+    }
+
+    #[test]
+    fn test_one_way_legs() {
+        let mut state = state(PositionMode::OneWay);
+        state.apply_fill(&fill(Side::Buy, 100, 1.0, false));
+        state.apply_fill(&fill(Side::Buy, 103, 2.0, false));
+        assert_eq!(state.state_values.long_position, 3.0);
+        assert_eq!(state.state_values.long_entry_price, 102.0);
+
+        // The sell closes the long leg and opens the short leg with the rest.
+        state.apply_fill(&fill(Side::Sell, 105, 4.0, false));
+        assert_eq!(state.state_values.position, -1.0);
+        assert_eq!(state.state_values.long_position, 0.0);
+        assert_eq!(state.state_values.long_entry_price, 0.0);
+        assert_eq!(state.state_values.short_position, 1.0);
+        assert_eq!(state.state_values.short_entry_price, 105.0);
+        assert_eq!(state.reducible_position(Side::Buy), -1.0);
+    }
+
+    #[test]
+    fn test_hedge_legs() {
+        let mut state = state(PositionMode::Hedge);
+        state.apply_fill(&fill(Side::Buy, 100, 2.0, false));
+        state.apply_fill(&fill(Side::Sell, 104, 1.0, false));
+        assert_eq!(state.state_values.position, 1.0);
+        assert_eq!(state.state_values.long_position, 2.0);
+        assert_eq!(state.state_values.long_entry_price, 100.0);
+        assert_eq!(state.state_values.short_position, 1.0);
+        assert_eq!(state.state_values.short_entry_price, 104.0);
+        assert_eq!(state.reducible_position(Side::Buy), -1.0);
+        assert_eq!(state.reducible_position(Side::Sell), 2.0);
+
+        // The reduce-only sell closes the long leg, leaving the short leg.
+        state.apply_fill(&fill(Side::Sell, 101, 2.0, true));
+        assert_eq!(state.state_values.position, -1.0);
+        assert_eq!(state.state_values.long_position, 0.0);
+        assert_eq!(state.state_values.long_entry_price, 0.0);
+        assert_eq!(state.state_values.short_position, 1.0);
+        assert_eq!(state.state_values.short_entry_price, 104.0);
+    }
+}
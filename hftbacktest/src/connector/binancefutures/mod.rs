@@ -17,13 +17,12 @@ use crate::{
         binancefutures::{
             ordermanager::{OrderManager, OrderManagerWrapper},
             rest::BinanceFuturesClient,
-            ws::connect,
+            ws::{connect, FillContext},
         },
         Connector,
     },
     live::Asset,
-    types::{BuildError, ErrorKind, LiveError, LiveEvent, Order, Status},
-    util::get_precision,
+    types::{BuildError, ErrorKind, LiveError, LiveEvent, Order, PositionMode, Side, Status},
 };
 
 #[derive(Clone)]
@@ -47,6 +46,93 @@ impl From<&'static str> for Endpoint {
     }
 }
 
+impl Endpoint {
+    fn stream_url(self, market: Market) -> Option<String> {
+        match (self, market) {
+            (Endpoint::Public, Market::UsdM) => Some("wss://fstream.binance.com".to_string()),
+            (Endpoint::Private, Market::UsdM) => Some("wss://fstream-auth.binance.com".to_string()),
+            (Endpoint::Testnet, Market::UsdM) => {
+                Some("wss://fstream.binancefuture.com".to_string())
+            }
+            (Endpoint::LowLatency, Market::UsdM) => {
+                Some("wss://fstream-mm.binance.com".to_string())
+            }
+            (Endpoint::Public, Market::CoinM) => Some("wss://dstream.binance.com".to_string()),
+            (Endpoint::Testnet, Market::CoinM) => {
+                Some("wss://dstream.binancefuture.com".to_string())
+            }
+            (Endpoint::Private | Endpoint::LowLatency, Market::CoinM) => None,
+            (Endpoint::Custom(url), _) => Some(url),
+        }
+    }
+
+    fn api_url(self, market: Market) -> Option<String> {
+        match (self, market) {
+            (Endpoint::Public | Endpoint::Private, Market::UsdM) => {
+                Some("https://fapi.binance.com".to_string())
+            }
+            (Endpoint::LowLatency, Market::UsdM) => Some("https://fapi-mm.binance.com".to_string()),
+            (Endpoint::Public | Endpoint::Private, Market::CoinM) => {
+                Some("https://dapi.binance.com".to_string())
+            }
+            (Endpoint::LowLatency, Market::CoinM) => None,
+            // The testnet serves both USD-M and COIN-M futures.
+            (Endpoint::Testnet, _) => Some("https://testnet.binancefuture.com".to_string()),
+            (Endpoint::Custom(url), _) => Some(url),
+        }
+    }
+}
+
+/// Binance Futures market, which determines the endpoints and the API paths to use.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Market {
+    /// USDⓈ-M futures, which are linear contracts margined and settled in USDT or USDC.
+    #[default]
+    UsdM,
+    /// COIN-M futures, which are inverse contracts margined and settled in the base coin. The
+    /// quantity is the number of contracts, and each contract is worth a fixed amount of USD, the
+    /// contract size, which is read from the exchange information of the symbol.
+    CoinM,
+}
+
+impl Market {
+    /// Returns the prefix of the REST API paths.
+    fn api_path(&self) -> &'static str {
+        match self {
+            Market::UsdM => "/fapi",
+            Market::CoinM => "/dapi",
+        }
+    }
+
+    /// Formats the order quantity. The quantity of COIN-M futures is an integer number of
+    /// contracts.
+    fn format_qty(&self, qty: f64) -> String {
+        match self {
+            Market::UsdM => format!("{:.5}", qty),
+            Market::CoinM => format!("{}", qty.round() as i64),
+        }
+    }
+
+    /// Returns the value amount of a fill, which is in the quote currency for USDⓈ-M futures and
+    /// in the base coin for COIN-M futures, as `LinearAsset` and `InverseAsset` compute it in the
+    /// backtest.
+    fn amount(&self, contract_size: f64, price: f64, qty: f64) -> f64 {
+        match self {
+            Market::UsdM => contract_size * price * qty,
+            Market::CoinM => contract_size * qty / price,
+        }
+    }
+
+    /// Returns the name of the diff. depth stream. COIN-M futures don't have the real-time
+    /// stream, so the fastest one is used instead.
+    fn depth_stream(&self) -> &'static str {
+        match self {
+            Market::UsdM => "depth@0ms",
+            Market::CoinM => "depth@100ms",
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum BinanceFuturesError {
     #[error("asset not found")]
@@ -59,14 +145,25 @@ pub enum BinanceFuturesError {
     OrderError(i64, String),
 }
 
-/// Binance Futures USD-M connector [`BinanceFutures`] builder.
+/// Binance Futures connector [`BinanceFutures`] builder.
+///
+/// It connects to USDⓈ-M futures by default. To trade COIN-M futures, set the market to
+/// [`Market::CoinM`]. The quantities of COIN-M futures, including the order quantities, the market
+/// depth, the trades, and the positions, are the number of contracts, and the order quantities are
+/// rounded to integers. Since they're inverse contracts, the value amounts of the fills, which
+/// accumulate into the [`StateValues`](crate::types::StateValues) trading value, are in the base
+/// coin, computed with the contract sizes read from the exchange information. Likewise, the
+/// backtest of the recorded data should use
+/// [`InverseAsset`](crate::backtest::assettype::InverseAsset) with the contract size of the symbol.
 pub struct BinanceFuturesBuilder {
-    stream_url: String,
-    api_url: String,
+    stream_url: Option<Endpoint>,
+    api_url: Option<Endpoint>,
+    market: Market,
     order_prefix: String,
     api_key: String,
     secret: String,
     streams: HashSet<String>,
+    position_mode: PositionMode,
 }
 
 impl BinanceFuturesBuilder {
@@ -78,55 +175,30 @@ impl BinanceFuturesBuilder {
         self.stream_url(endpoint.clone()).api_url(endpoint)
     }
 
-    /// Sets the Websocket streams endpoint url.
+    /// Sets the Websocket streams endpoint url. The url of a predefined endpoint is determined
+    /// by the market when it's built.
     pub fn stream_url<E: Into<Endpoint>>(self, endpoint: E) -> Self {
-        match endpoint.into() {
-            Endpoint::Public => {
-                Self {
-                    // wss://ws-fapi.binance.com/ws-fapi/v1
-                    stream_url: "wss://fstream.binance.com".to_string(),
-                    ..self
-                }
-            }
-            Endpoint::Private => Self {
-                stream_url: "wss://fstream-auth.binance.com".to_string(),
-                ..self
-            },
-            Endpoint::Testnet => Self {
-                stream_url: "wss://fstream.binancefuture.com".to_string(),
-                ..self
-            },
-            Endpoint::LowLatency => Self {
-                stream_url: "wss://fstream-mm.binance.com".to_string(),
-                ..self
-            },
-            Endpoint::Custom(stream_url) => Self { stream_url, ..self },
+        Self {
+            stream_url: Some(endpoint.into()),
+            ..self
         }
     }
 
-    /// Sets the REST APIs endpoint url.
+    /// Sets the REST APIs endpoint url. The url of a predefined endpoint is determined by the
+    /// market when it's built.
     pub fn api_url<E: Into<Endpoint>>(self, endpoint: E) -> Self {
-        match endpoint.into() {
-            Endpoint::Public => Self {
-                api_url: "https://fapi.binance.com".to_string(),
-                ..self
-            },
-            Endpoint::Private => Self {
-                api_url: "https://fapi.binance.com".to_string(),
-                ..self
-            },
-            Endpoint::Testnet => Self {
-                api_url: "https://testnet.binancefuture.com".to_string(),
-                ..self
-            },
-            Endpoint::LowLatency => Self {
-                api_url: "https://fapi-mm.binance.com".to_string(),
-                ..self
-            },
-            Endpoint::Custom(api_url) => Self { api_url, ..self },
+        Self {
+            api_url: Some(endpoint.into()),
+            ..self
         }
     }
 
+    /// Sets the market to trade. [`Endpoint::Private`] and [`Endpoint::LowLatency`] streams are
+    /// available only for [`Market::UsdM`]. The default value is [`Market::UsdM`].
+    pub fn market(self, market: Market) -> Self {
+        Self { market, ..self }
+    }
+
     /// Sets the API key
     pub fn api_key(self, api_key: &str) -> Self {
         Self {
@@ -152,6 +224,17 @@ impl BinanceFuturesBuilder {
         }
     }
 
+    /// Sets the position mode, which must match the account's position mode on Binance. In
+    /// [`PositionMode::Hedge`], orders are submitted with the position side determined by
+    /// [`Order::hedge_leg`] instead of the reduce-only flag, and the positions are reported per
+    /// leg. The default value is [`PositionMode::OneWay`].
+    pub fn position_mode(self, position_mode: PositionMode) -> Self {
+        Self {
+            position_mode,
+            ..self
+        }
+    }
+
     /// Adds an additional stream to receive through the WebSocket connection.
     pub fn add_stream(mut self, stream: &str) -> Self {
         self.streams.insert(stream.to_string());
@@ -160,12 +243,20 @@ impl BinanceFuturesBuilder {
 
     /// Builds [`BinanceFutures`] connector.
     pub fn build(self) -> Result<BinanceFutures, BuildError> {
-        if self.stream_url.is_empty() {
-            return Err(BuildError::BuilderIncomplete("stream_url"));
-        }
-        if self.api_url.is_empty() {
-            return Err(BuildError::BuilderIncomplete("api_url"));
-        }
+        let stream_url = self
+            .stream_url
+            .ok_or(BuildError::BuilderIncomplete("stream_url"))?
+            .stream_url(self.market)
+            .ok_or(BuildError::InvalidArgument(
+                "the stream endpoint isn't available for the market",
+            ))?;
+        let api_url = self
+            .api_url
+            .ok_or(BuildError::BuilderIncomplete("api_url"))?
+            .api_url(self.market)
+            .ok_or(BuildError::InvalidArgument(
+                "the API endpoint isn't available for the market",
+            ))?;
         if self.api_key.is_empty() {
             return Err(BuildError::BuilderIncomplete("api_key"));
         }
@@ -176,18 +267,20 @@ impl BinanceFuturesBuilder {
         let order_manager: OrderManagerWrapper =
             Arc::new(Mutex::new(OrderManager::new(&self.order_prefix)));
         Ok(BinanceFutures {
-            url: self.stream_url.to_string(),
+            url: stream_url,
             prefix: self.order_prefix,
             assets: Default::default(),
             inv_assets: Default::default(),
             order_manager,
-            client: BinanceFuturesClient::new(&self.api_url, &self.api_key, &self.secret),
+            client: BinanceFuturesClient::new(&api_url, &self.api_key, &self.secret, self.market),
             streams: self.streams,
+            position_mode: self.position_mode,
+            market: self.market,
         })
     }
 }
 
-/// A connector for Binance USD-m Futures.
+/// A connector for Binance USD-M and COIN-M Futures.
 pub struct BinanceFutures {
     url: String,
     prefix: String,
@@ -196,22 +289,26 @@ pub struct BinanceFutures {
     order_manager: OrderManagerWrapper,
     client: BinanceFuturesClient,
     streams: HashSet<String>,
+    position_mode: PositionMode,
+    market: Market,
 }
 
 impl BinanceFutures {
     /// Gets [`BinanceFuturesBuilder`] to build [`BinanceFutures`] connector.
     pub fn builder() -> BinanceFuturesBuilder {
         BinanceFuturesBuilder {
-            stream_url: "".to_string(),
-            api_url: "".to_string(),
+            stream_url: None,
+            api_url: None,
+            market: Default::default(),
             order_prefix: "".to_string(),
             api_key: "".to_string(),
             secret: "".to_string(),
             streams: Default::default(),
+            position_mode: Default::default(),
         }
     }
 
-    /// Constructs an instance of `BinanceFutures`.
+    /// Constructs an instance of `BinanceFutures` for USD-M futures.
     pub fn new(stream_url: &str, api_url: &str, prefix: &str, api_key: &str, secret: &str) -> Self {
         let order_manager: OrderManagerWrapper = Arc::new(Mutex::new(OrderManager::new(prefix)));
         Self {
@@ -220,12 +317,43 @@ impl BinanceFutures {
             assets: Default::default(),
             inv_assets: Default::default(),
             order_manager,
-            client: BinanceFuturesClient::new(api_url, api_key, secret),
+            client: BinanceFuturesClient::new(api_url, api_key, secret, Market::UsdM),
             streams: Default::default(),
+            position_mode: Default::default(),
+            market: Market::UsdM,
         }
     }
 }
 
+/// Converts the position of the symbol into [`LiveEvent`]. In the hedge mode, Binance reports the
+/// long and short legs separately with the position side, and the amount of the short leg is
+/// negative.
+fn position_event(
+    asset_no: usize,
+    position_side: &str,
+    position_amount: f64,
+    entry_price: f64,
+) -> LiveEvent {
+    match position_side {
+        "LONG" => LiveEvent::PositionLeg {
+            asset_no,
+            side: Side::Buy,
+            qty: position_amount,
+            entry_price,
+        },
+        "SHORT" => LiveEvent::PositionLeg {
+            asset_no,
+            side: Side::Sell,
+            qty: -position_amount,
+            entry_price,
+        },
+        _ => LiveEvent::Position {
+            asset_no,
+            qty: position_amount,
+        },
+    }
+}
+
 impl Connector for BinanceFutures {
     fn add(
         &mut self,
@@ -252,6 +380,8 @@ impl Connector for BinanceFutures {
         let client = self.client.clone();
         let order_manager = self.order_manager.clone();
         let add_streams = self.streams.clone();
+        let market = self.market;
+        let depth_stream = market.depth_stream();
         let mut error_count = 0;
 
         let _ = tokio::spawn(async move {
@@ -291,10 +421,12 @@ impl Connector for BinanceFutures {
                         positions.into_iter().for_each(|position| {
                             assets.get(&position.symbol).map(|asset_info| {
                                 ev_tx
-                                    .send(LiveEvent::Position {
-                                        asset_no: asset_info.asset_no,
-                                        qty: position.position_amount,
-                                    })
+                                    .send(position_event(
+                                        asset_info.asset_no,
+                                        &position.position_side,
+                                        position.position_amount,
+                                        position.entry_price,
+                                    ))
                                     .unwrap();
                             });
                         });
@@ -306,6 +438,24 @@ impl Connector for BinanceFutures {
                     }
                 }
 
+                // Fetches the contract sizes, by which the value amounts of the fills are computed.
+                let contract_sizes = match client.get_exchange_info().await {
+                    Ok(exchange_info) => exchange_info
+                        .symbols
+                        .into_iter()
+                        .filter(|symbol_info| assets.contains_key(&symbol_info.symbol))
+                        // USDⓈ-M futures don't have the contract size.
+                        .map(|symbol_info| {
+                            (symbol_info.symbol, symbol_info.contract_size.unwrap_or(1.0))
+                        })
+                        .collect::<HashMap<_, _>>(),
+                    Err(error) => {
+                        error!(?error, "Couldn't get exchange information.");
+                        error_count += 1;
+                        continue 'connection;
+                    }
+                };
+
                 let listen_key = match client.start_user_data_stream().await {
                     Ok(listen_key) => listen_key,
                     Err(error) => {
@@ -326,8 +476,9 @@ impl Connector for BinanceFutures {
                     .keys()
                     .map(|symbol| {
                         format!(
-                            "{}@depth@0ms/{}@trade",
+                            "{}@{}/{}@trade",
                             symbol.to_lowercase(),
+                            depth_stream,
                             symbol.to_lowercase()
                         )
                     })
@@ -347,6 +498,10 @@ impl Connector for BinanceFutures {
                     &prefix,
                     order_manager.clone(),
                     client.clone(),
+                    FillContext {
+                        market,
+                        contract_sizes,
+                    },
                 )
                 .await
                 {
@@ -383,6 +538,13 @@ impl Connector for BinanceFutures {
         let symbol = asset_info.symbol.clone();
         let client = self.client.clone();
         let orders = self.order_manager.clone();
+        let position_side = match self.position_mode {
+            PositionMode::OneWay => None,
+            PositionMode::Hedge => match order.hedge_leg() {
+                Side::Buy => Some("LONG"),
+                _ => Some("SHORT"),
+            },
+        };
         tokio::spawn(async move {
             let client_order_id = orders
                 .lock()
@@ -391,19 +553,9 @@ impl Connector for BinanceFutures {
 
             match client_order_id {
                 Some(client_order_id) => {
-                    match client
-                        .submit_order(
-                            &client_order_id,
-                            &symbol,
-                            order.side,
-                            order.price_tick as f64 * order.tick_size,
-                            get_precision(order.tick_size),
-                            order.qty,
-                            order.order_type,
-                            order.time_in_force,
-                        )
-                        .await
-                    {
+                    let body =
+                        client.new_order_body(&client_order_id, &symbol, &order, position_side);
+                    match client.submit_order(body).await {
                         Ok(resp) => {
                             let order = orders
                                 .lock()
@@ -509,4 +661,25 @@ impl Connector for BinanceFutures {
         });
         Ok(())
     }
+
+    fn cancel_all(&self, asset_no: usize, tx: Sender<LiveEvent>) -> Result<(), anyhow::Error> {
+        let asset_info = self
+            .inv_assets
+            .get(&asset_no)
+            .ok_or(BinanceFuturesError::AssetNotFound)?;
+        let symbol = asset_info.symbol.clone();
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            // The cancellation of each order is delivered through the user data stream.
+            if let Err(error) = client.cancel_all_orders(&symbol).await {
+                error!(?error, %symbol, "Couldn't cancel all open orders.");
+                tx.send(LiveEvent::Error(LiveError::with(
+                    ErrorKind::OrderError,
+                    error,
+                )))
+                .unwrap();
+            }
+        });
+        Ok(())
+    }
 }
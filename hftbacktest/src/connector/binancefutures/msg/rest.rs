@@ -90,10 +90,14 @@ pub struct OrderResponse {
     #[serde(rename = "priceProtect")]
     pub price_protect: bool,
     #[serde(rename = "priceMatch")]
+    #[serde(default)]
     pub price_match: String,
     #[serde(rename = "selfTradePreventionMode")]
+    #[serde(default)]
     pub self_trade_prevention_mode: String,
+    /// USD-M futures only field
     #[serde(rename = "goodTillDate")]
+    #[serde(default)]
     pub good_till_date: i64,
 }
 
@@ -123,12 +127,21 @@ pub struct PositionInformationV2 {
     #[serde(rename = "markPrice")]
     #[serde(deserialize_with = "from_str_to_f64")]
     pub mark_price: f64,
+    /// USD-M futures only field
     #[serde(rename = "maxNotionalValue")]
+    #[serde(default)]
     #[serde(deserialize_with = "from_str_to_f64")]
     pub max_notional_value: f64,
+    /// COIN-M futures only field
+    #[serde(rename = "maxQty")]
+    #[serde(default)]
+    #[serde(deserialize_with = "from_str_to_f64_opt")]
+    pub max_qty: Option<f64>,
     #[serde(rename = "positionAmt")]
     #[serde(deserialize_with = "from_str_to_f64")]
     pub position_amount: f64,
+    /// It's `notionalValue` for COIN-M futures, which is in the base coin.
+    #[serde(alias = "notionalValue")]
     #[serde(deserialize_with = "from_str_to_f64")]
     pub notional: f64,
     #[serde(rename = "isolatedWallet")]
@@ -153,3 +166,17 @@ pub struct Depth {
     pub bids: Vec<(String, String)>,
     pub asks: Vec<(String, String)>,
 }
+
+#[derive(Deserialize, Debug)]
+pub struct ExchangeInfo {
+    pub symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    /// COIN-M futures only field, which is the value of a contract in USD.
+    #[serde(rename = "contractSize")]
+    #[serde(default)]
+    pub contract_size: Option<f64>,
+}
@@ -35,9 +35,10 @@ pub struct Depth {
     pub event_time: i64,
     #[serde(rename = "s")]
     pub symbol: String,
-    // for Coin-M futures
-    // #[serde(rename = "ps")]
-    // pub pair: String,
+    /// COIN-M futures only field
+    #[serde(rename = "ps")]
+    #[serde(default)]
+    pub pair: Option<String>,
     #[serde(rename = "U")]
     pub first_update_id: i64,
     #[serde(rename = "u")]
@@ -198,8 +199,8 @@ pub struct Order {
     // pub ask_notional: String,
     // #[serde(rename = "m")]
     // pub is_maker_side: bool,
-    // #[serde(rename = "R")]
-    // pub is_reduce_only: bool,
+    #[serde(rename = "R")]
+    pub is_reduce_only: bool,
     // #[serde(rename = "wt")]
     // pub stop_price_working_type: String,
     // #[serde(rename = "ot")]
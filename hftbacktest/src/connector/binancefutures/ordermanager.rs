@@ -12,7 +12,7 @@ use crate::{
         util::gen_random_string,
     },
     prelude::OrderId,
-    types::{Order, Status},
+    types::{Order, Status, TriggerBy},
 };
 
 #[derive(Debug)]
@@ -125,6 +125,10 @@ impl OrderManager {
             // Invalid information
             q: Box::new(()),
             maker: false,
+            trigger_price_tick: (resp.stop_price / order.tick_size).round() as i64,
+            expire_timestamp: resp.good_till_date * 1_000_000,
+            reduce_only: resp.reduce_only,
+            trigger_by: TriggerBy::Default,
         };
         self.update_from_rest(asset_no, resp.client_order_id, order)
     }
@@ -186,6 +190,10 @@ impl OrderManager {
             // Invalid information
             q: Box::new(()),
             maker: false,
+            trigger_price_tick: (resp.stop_price / order.tick_size).round() as i64,
+            expire_timestamp: resp.good_till_date * 1_000_000,
+            reduce_only: resp.reduce_only,
+            trigger_by: TriggerBy::Default,
         };
         self.update_from_rest(asset_no, resp.client_order_id, order)
     }
@@ -15,11 +15,13 @@ use crate::{
             },
             ordermanager::OrderManager,
             BinanceFuturesError,
+            Market,
         },
         util::sign_hmac_sha256,
     },
     live::Asset,
-    types::{OrdType, Order, Side, Status, TimeInForce},
+    types::{OrdType, Order, Side, Status, TimeInForce, TriggerBy},
+    util::get_precision,
 };
 
 #[derive(Clone)]
@@ -28,18 +30,26 @@ pub struct BinanceFuturesClient {
     url: String,
     api_key: String,
     secret: String,
+    market: Market,
 }
 
 impl BinanceFuturesClient {
-    pub fn new(url: &str, api_key: &str, secret: &str) -> Self {
+    pub fn new(url: &str, api_key: &str, secret: &str, market: Market) -> Self {
         Self {
             client: reqwest::Client::new(),
             url: url.to_string(),
             api_key: api_key.to_string(),
             secret: secret.to_string(),
+            market,
         }
     }
 
+    /// Returns the full path of the REST API for the market, such as `/fapi/v1/order` for USD-M
+    /// futures and `/dapi/v1/order` for COIN-M futures.
+    fn path(&self, path: &str) -> String {
+        format!("{}{}", self.market.api_path(), path)
+    }
+
     async fn get<T: for<'a> Deserialize<'a>>(
         &self,
         path: &str,
@@ -150,7 +160,7 @@ impl BinanceFuturesClient {
     pub async fn start_user_data_stream(&self) -> Result<String, reqwest::Error> {
         let resp: Result<ListenKey, _> = self
             .post(
-                "/fapi/v1/listenKey",
+                &self.path("/v1/listenKey"),
                 String::new(),
                 &self.api_key,
                 &self.secret,
@@ -162,7 +172,7 @@ impl BinanceFuturesClient {
     pub async fn keepalive_user_data_stream(&self) -> Result<(), reqwest::Error> {
         let _: serde_json::Value = self
             .put(
-                "/fapi/v1/listenKey",
+                &self.path("/v1/listenKey"),
                 String::new(),
                 &self.api_key,
                 &self.secret,
@@ -171,35 +181,69 @@ impl BinanceFuturesClient {
         Ok(())
     }
 
-    pub async fn submit_order(
+    /// Builds the request body of [`submit_order`](Self::submit_order) for the order.
+    pub fn new_order_body(
         &self,
         client_order_id: &str,
         symbol: &str,
-        side: Side,
-        price: f64,
-        price_prec: usize,
-        qty: f64,
-        order_type: OrdType,
-        time_in_force: TimeInForce,
-    ) -> Result<OrderResponse, BinanceFuturesError> {
+        order: &Order,
+        position_side: Option<&str>,
+    ) -> String {
+        let price_prec = get_precision(order.tick_size);
+        let order_type = order.order_type;
         let mut body = String::with_capacity(200);
         body.push_str("newClientOrderId=");
         body.push_str(&client_order_id);
         body.push_str("&symbol=");
         body.push_str(&symbol);
         body.push_str("&side=");
-        body.push_str(side.as_ref());
-        body.push_str("&price=");
-        body.push_str(&format!("{:.prec$}", price, prec = price_prec));
+        body.push_str(order.side.as_ref());
+        // A stop-market order is executed at the market price once triggered.
+        if order_type != OrdType::StopMarket {
+            body.push_str("&price=");
+            body.push_str(&format!(
+                "{:.prec$}",
+                order.price_tick as f64 * order.tick_size,
+                prec = price_prec
+            ));
+        }
         body.push_str("&quantity=");
-        body.push_str(&format!("{:.5}", qty));
+        body.push_str(&self.market.format_qty(order.qty));
         body.push_str("&type=");
-        body.push_str(order_type.as_ref());
-        body.push_str("&timeInForce=");
-        body.push_str(time_in_force.as_ref());
+        body.push_str(match order_type {
+            OrdType::StopLimit => "STOP",
+            _ => order_type.as_ref(),
+        });
+        if order_type.is_stop() {
+            body.push_str("&stopPrice=");
+            body.push_str(&format!(
+                "{:.prec$}",
+                order.trigger_price(),
+                prec = price_prec
+            ));
+        }
+        if order_type != OrdType::StopMarket {
+            body.push_str("&timeInForce=");
+            body.push_str(order.time_in_force.as_ref());
+        }
+        // In the hedge mode, the position side determines the leg to open or close, and
+        // `reduceOnly` cannot be sent.
+        match position_side {
+            Some(position_side) => {
+                body.push_str("&positionSide=");
+                body.push_str(position_side);
+            }
+            None if order.reduce_only => {
+                body.push_str("&reduceOnly=true");
+            }
+            None => {}
+        }
+        body
+    }
 
+    pub async fn submit_order(&self, body: String) -> Result<OrderResponse, BinanceFuturesError> {
         let resp: OrderResponseResult = self
-            .post("/fapi/v1/order", body, &self.api_key, &self.secret)
+            .post(&self.path("/v1/order"), body, &self.api_key, &self.secret)
             .await?;
         match resp {
             OrderResponseResult::Ok(resp) => Ok(resp),
@@ -231,7 +275,7 @@ impl BinanceFuturesClient {
             body.push_str("\",\"price\":\"");
             body.push_str(&format!("{:.prec$}", order.3, prec = order.4));
             body.push_str("\",\"quantity\":\"");
-            body.push_str(&format!("{:.5}", order.5));
+            body.push_str(&self.market.format_qty(order.5));
             body.push_str("\",\"type\":\"");
             body.push_str(order.6.as_ref());
             body.push_str("\",\"timeInForce\":\"");
@@ -241,7 +285,12 @@ impl BinanceFuturesClient {
         body.push_str("]}");
 
         let resp: Vec<OrderResponseResult> = self
-            .post("/fapi/v1/batchOrders", body, &self.api_key, &self.secret)
+            .post(
+                &self.path("/v1/batchOrders"),
+                body,
+                &self.api_key,
+                &self.secret,
+            )
             .await?;
         Ok(resp
             .into_iter()
@@ -273,10 +322,10 @@ impl BinanceFuturesClient {
         body.push_str("&price=");
         body.push_str(&format!("{:.prec$}", price, prec = price_prec));
         body.push_str("&quantity=");
-        body.push_str(&format!("{:.5}", qty));
+        body.push_str(&self.market.format_qty(qty));
 
         let resp: OrderResponseResult = self
-            .put("/fapi/v1/order", body, &self.api_key, &self.secret)
+            .put(&self.path("/v1/order"), body, &self.api_key, &self.secret)
             .await?;
         match resp {
             OrderResponseResult::Ok(resp) => Ok(resp),
@@ -298,7 +347,7 @@ impl BinanceFuturesClient {
         body.push_str(client_order_id);
 
         let resp: OrderResponseResult = self
-            .delete("/fapi/v1/order", body, &self.api_key, &self.secret)
+            .delete(&self.path("/v1/order"), body, &self.api_key, &self.secret)
             .await?;
         match resp {
             OrderResponseResult::Ok(resp) => Ok(resp),
@@ -330,7 +379,12 @@ impl BinanceFuturesClient {
         }
         body.push_str("]}");
         let resp: Vec<OrderResponseResult> = self
-            .post("/fapi/v1/batchOrders", body, &self.api_key, &self.secret)
+            .post(
+                &self.path("/v1/batchOrders"),
+                body,
+                &self.api_key,
+                &self.secret,
+            )
             .await?;
         Ok(resp
             .into_iter()
@@ -346,7 +400,7 @@ impl BinanceFuturesClient {
     pub async fn cancel_all_orders(&self, symbol: &str) -> Result<(), reqwest::Error> {
         let _: serde_json::Value = self
             .delete(
-                "/fapi/v1/allOpenOrders",
+                &self.path("/v1/allOpenOrders"),
                 format!("symbol={}", symbol),
                 &self.api_key,
                 &self.secret,
@@ -360,7 +414,11 @@ impl BinanceFuturesClient {
     ) -> Result<Vec<PositionInformationV2>, reqwest::Error> {
         let resp: Vec<PositionInformationV2> = self
             .get(
-                "/fapi/v2/positionRisk",
+                // COIN-M futures have only v1.
+                match self.market {
+                    Market::UsdM => "/fapi/v2/positionRisk",
+                    Market::CoinM => "/dapi/v1/positionRisk",
+                },
                 String::new(),
                 &self.api_key,
                 &self.secret,
@@ -375,7 +433,7 @@ impl BinanceFuturesClient {
     ) -> Result<Vec<Order>, reqwest::Error> {
         let resp: Vec<OrderResponse> = self
             .get(
-                "/fapi/v1/openOrders",
+                &self.path("/v1/openOrders"),
                 String::new(),
                 &self.api_key,
                 &self.secret,
@@ -405,6 +463,11 @@ impl BinanceFuturesClient {
                             // Invalid information
                             q: Box::new(()),
                             maker: false,
+                            trigger_price_tick: (data.stop_price / asset_info.tick_size).round()
+                                as i64,
+                            expire_timestamp: data.good_till_date * 1_000_000,
+                            reduce_only: data.reduce_only,
+                            trigger_by: TriggerBy::Default,
                         },
                     )
                 })
@@ -414,10 +477,22 @@ impl BinanceFuturesClient {
             .collect())
     }
 
+    pub async fn get_exchange_info(&self) -> Result<rest::ExchangeInfo, reqwest::Error> {
+        let resp: rest::ExchangeInfo = self
+            .get(
+                &self.path("/v1/exchangeInfo"),
+                String::new(),
+                &self.api_key,
+                &self.secret,
+            )
+            .await?;
+        Ok(resp)
+    }
+
     pub async fn get_depth(&self, symbol: &str) -> Result<rest::Depth, reqwest::Error> {
         let resp: rest::Depth = self
             .get(
-                "/fapi/v1/depth",
+                &self.path("/v1/depth"),
                 format!("symbol={}&limit=1000", symbol),
                 &self.api_key,
                 &self.secret,
@@ -14,8 +14,10 @@ use tracing::{error, info};
 
 use super::{
     msg::stream::{Data, Stream},
+    position_event,
     rest::BinanceFuturesClient,
     BinanceFuturesError,
+    Market,
     OrderManagerWrapper,
 };
 use crate::{
@@ -29,6 +31,7 @@ use crate::{
         LiveEvent,
         Order,
         Status,
+        TriggerBy,
         LOCAL_ASK_DEPTH_EVENT,
         LOCAL_BID_DEPTH_EVENT,
         LOCAL_BUY_TRADE_EVENT,
@@ -61,6 +64,13 @@ pub enum DepthManageMode {
     NaturalRefresh,
 }
 
+/// The market and the contract sizes of the symbols, by which the fills are reported as
+/// [`LiveEvent::Fill`].
+pub struct FillContext {
+    pub market: Market,
+    pub contract_sizes: HashMap<String, f64>,
+}
+
 pub async fn connect(
     url: &str,
     ev_tx: Sender<LiveEvent>,
@@ -68,6 +78,7 @@ pub async fn connect(
     prefix: &str,
     orders: OrderManagerWrapper,
     client: BinanceFuturesClient,
+    fill_ctx: FillContext,
 ) -> Result<(), anyhow::Error> {
     let mut request = url.into_client_request()?;
     let _ = request.headers_mut();
@@ -304,16 +315,40 @@ pub async fn connect(
                                 for position in data.account.position {
                                     if let Some(asset_info) = assets.get(&position.symbol) {
                                         ev_tx.send(
-                                            LiveEvent::Position {
-                                                asset_no: asset_info.asset_no,
-                                                qty: position.position_amount
-                                            }
+                                            position_event(
+                                                asset_info.asset_no,
+                                                &position.position_side,
+                                                position.position_amount,
+                                                position.entry_price,
+                                            )
                                         ).unwrap();
                                     }
                                 }
                             }
                             Data::OrderTradeUpdate(data) => {
                                 if let Some(asset_info) = assets.get(&data.order.symbol) {
+                                    // The fills are reported only for COIN-M futures, whose value
+                                    // amounts are in the base coin.
+                                    if fill_ctx.market == Market::CoinM
+                                        && data.order.execution_type == "TRADE"
+                                    {
+                                        let contract_size = fill_ctx
+                                            .contract_sizes
+                                            .get(&data.order.symbol)
+                                            .copied()
+                                            .unwrap_or(1.0);
+                                        ev_tx.send(
+                                            LiveEvent::Fill {
+                                                asset_no: asset_info.asset_no,
+                                                qty: data.order.order_last_filled_qty,
+                                                amount: fill_ctx.market.amount(
+                                                    contract_size,
+                                                    data.order.last_filled_price,
+                                                    data.order.order_last_filled_qty
+                                                ),
+                                            }
+                                        ).unwrap();
+                                    }
                                     if let Some(order_id) = OrderManager::parse_client_order_id(&data.order.client_order_id, &prefix) {
                                         let order = Order {
                                             qty: data.order.original_qty,
@@ -332,7 +367,11 @@ pub async fn connect(
                                             order_type: data.order.order_type,
                                             // Invalid information
                                             q: Box::new(()),
-                                            maker: false
+                                            maker: false,
+                                            trigger_price_tick: (data.order.stop_price / asset_info.tick_size).round() as i64,
+                                            expire_timestamp: 0,
+                                            reduce_only: data.order.is_reduce_only,
+                                            trigger_by: TriggerBy::Default,
                                         };
 
                                         let order = orders
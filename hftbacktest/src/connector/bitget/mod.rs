@@ -0,0 +1,630 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{mpsc::Sender, Arc, Mutex},
+    time::Duration,
+};
+
+use thiserror::Error;
+use tracing::error;
+
+use crate::{
+    connector::{
+        bitget::{
+            msg::Position,
+            ordermanager::{OrderManager, OrderManagerWrapper, OrderParams},
+            rest::BitgetClient,
+            ws::{connect_private, connect_public, Credentials},
+        },
+        Connector,
+    },
+    live::Asset,
+    types::{BuildError, ErrorKind, LiveError, LiveEvent, Order, PositionMode, Side},
+};
+
+mod msg;
+mod ordermanager;
+mod rest;
+mod ws;
+
+#[derive(Clone)]
+pub enum Endpoint {
+    Public,
+    Demo,
+    Custom(String),
+}
+
+impl From<String> for Endpoint {
+    fn from(value: String) -> Self {
+        Endpoint::Custom(value)
+    }
+}
+
+impl From<&'static str> for Endpoint {
+    fn from(value: &'static str) -> Self {
+        Endpoint::Custom(value.to_string())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum BitgetError {
+    #[error("asset not found")]
+    AssetNotFound,
+    #[error("auth error {0}: {1}")]
+    AuthError(i64, String),
+    #[error("order error {0}: {1}")]
+    OrderError(i64, String),
+    #[error("http error: {0:?}")]
+    ReqError(#[from] reqwest::Error),
+    #[error("serde: {0}")]
+    SerdeError(#[from] serde_json::Error),
+}
+
+/// Bitget V2 futures connector [`Bitget`] builder.
+///
+/// The market data and the order and position updates are received through the WebSocket
+/// streams, and the orders are submitted and canceled through the REST API. The symbol of an
+/// asset is the symbol of the futures, such as `BTCUSDT`, and the quantities are in the base
+/// coin.
+pub struct BitgetBuilder {
+    public_url: String,
+    private_url: String,
+    rest_url: String,
+    demo: bool,
+    product_type: String,
+    margin_coin: String,
+    margin_mode: String,
+    book_channel: String,
+    channels: HashSet<String>,
+    api_key: String,
+    secret: String,
+    passphrase: String,
+    order_prefix: String,
+    position_mode: PositionMode,
+}
+
+impl BitgetBuilder {
+    /// Sets an endpoint to connect.
+    pub fn endpoint(self, endpoint: Endpoint) -> Self {
+        if let Endpoint::Custom(_) = endpoint {
+            panic!(
+                "Use `public_url`, `private_url`, and `rest_url` to set a custom endpoint instead"
+            );
+        }
+        self.public_url(endpoint.clone())
+            .private_url(endpoint.clone())
+            .rest_url(endpoint)
+    }
+
+    /// Sets the public Websocket stream endpoint url.
+    pub fn public_url<E: Into<Endpoint>>(self, endpoint: E) -> Self {
+        match endpoint.into() {
+            Endpoint::Public => Self {
+                public_url: "wss://ws.bitget.com/v2/ws/public".to_string(),
+                ..self
+            },
+            Endpoint::Demo => Self {
+                public_url: "wss://wspap.bitget.com/v2/ws/public".to_string(),
+                ..self
+            },
+            Endpoint::Custom(public_url) => Self { public_url, ..self },
+        }
+    }
+
+    /// Sets the private Websocket stream endpoint url.
+    pub fn private_url<E: Into<Endpoint>>(self, endpoint: E) -> Self {
+        match endpoint.into() {
+            Endpoint::Public => Self {
+                private_url: "wss://ws.bitget.com/v2/ws/private".to_string(),
+                ..self
+            },
+            Endpoint::Demo => Self {
+                private_url: "wss://wspap.bitget.com/v2/ws/private".to_string(),
+                ..self
+            },
+            Endpoint::Custom(private_url) => Self {
+                private_url,
+                ..self
+            },
+        }
+    }
+
+    /// Sets the REST API endpoint url. [`Endpoint::Demo`] also marks the REST requests as demo
+    /// trading.
+    pub fn rest_url<E: Into<Endpoint>>(self, endpoint: E) -> Self {
+        match endpoint.into() {
+            Endpoint::Public => Self {
+                rest_url: "https://api.bitget.com".to_string(),
+                demo: false,
+                ..self
+            },
+            Endpoint::Demo => Self {
+                rest_url: "https://api.bitget.com".to_string(),
+                demo: true,
+                ..self
+            },
+            Endpoint::Custom(rest_url) => Self { rest_url, ..self },
+        }
+    }
+
+    /// Sets the API key
+    pub fn api_key(self, api_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the secret key
+    pub fn secret(self, secret: &str) -> Self {
+        Self {
+            secret: secret.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the passphrase of the API key
+    pub fn passphrase(self, passphrase: &str) -> Self {
+        Self {
+            passphrase: passphrase.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the order prefix, which is used to differentiate the orders submitted through this
+    /// connector. The prefix should be alphanumeric, and its length should not be greater than
+    /// 16.
+    pub fn order_prefix(self, order_prefix: &str) -> Self {
+        Self {
+            order_prefix: order_prefix.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the product type, such as `USDT-FUTURES`, `USDC-FUTURES`, or `COIN-FUTURES`. The
+    /// demo trading has its own product types, such as `SUSDT-FUTURES`. The default value is
+    /// `USDT-FUTURES`.
+    pub fn product_type(self, product_type: &str) -> Self {
+        Self {
+            product_type: product_type.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the margin coin, such as `USDT`, or `SUSDT` for the demo trading. The default value is
+    /// `USDT`.
+    pub fn margin_coin(self, margin_coin: &str) -> Self {
+        Self {
+            margin_coin: margin_coin.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the margin mode of the orders, which is either `crossed` or `isolated`. The default
+    /// value is `crossed`.
+    pub fn margin_mode(self, margin_mode: &str) -> Self {
+        Self {
+            margin_mode: margin_mode.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the position mode, which must match the account's position mode on Bitget. In
+    /// [`PositionMode::Hedge`], orders are submitted to open or close the leg determined by
+    /// [`Order::hedge_leg`] instead of the reduce-only flag, and the positions are reported per
+    /// leg. The default value is [`PositionMode::OneWay`].
+    pub fn position_mode(self, position_mode: PositionMode) -> Self {
+        Self {
+            position_mode,
+            ..self
+        }
+    }
+
+    /// Sets the order book channel, such as `books`, `books5`, or `books15`. The default value is
+    /// `books`, which is the incremental full order book.
+    pub fn book_channel(self, book_channel: &str) -> Self {
+        Self {
+            book_channel: book_channel.to_string(),
+            ..self
+        }
+    }
+
+    /// Adds an additional channel to receive through the public WebSocket stream.
+    pub fn add_channel(mut self, channel: &str) -> Self {
+        self.channels.insert(channel.to_string());
+        self
+    }
+
+    /// Builds [`Bitget`] connector.
+    pub fn build(self) -> Result<Bitget, BuildError> {
+        if self.public_url.is_empty() {
+            return Err(BuildError::BuilderIncomplete("public_url"));
+        }
+        if self.private_url.is_empty() {
+            return Err(BuildError::BuilderIncomplete("private_url"));
+        }
+        if self.rest_url.is_empty() {
+            return Err(BuildError::BuilderIncomplete("rest_url"));
+        }
+        if self.api_key.is_empty() {
+            return Err(BuildError::BuilderIncomplete("api_key"));
+        }
+        if self.secret.is_empty() {
+            return Err(BuildError::BuilderIncomplete("secret"));
+        }
+        if self.passphrase.is_empty() {
+            return Err(BuildError::BuilderIncomplete("passphrase"));
+        }
+
+        if !self.order_prefix.chars().all(|c| c.is_ascii_alphanumeric()) {
+            panic!("order prefix should be alphanumeric.");
+        }
+        if self.order_prefix.len() > 16 {
+            panic!("order prefix length should be not greater than 16.");
+        }
+        Ok(Bitget {
+            public_url: self.public_url,
+            private_url: self.private_url,
+            assets: Default::default(),
+            inv_assets: Default::default(),
+            product_type: self.product_type.clone(),
+            book_channel: self.book_channel,
+            channels: self.channels,
+            credentials: Credentials {
+                api_key: self.api_key.clone(),
+                secret: self.secret.clone(),
+                passphrase: self.passphrase.clone(),
+            },
+            position_mode: self.position_mode,
+            order_man: Arc::new(Mutex::new(OrderManager::new(
+                &self.order_prefix,
+                OrderParams {
+                    product_type: self.product_type.clone(),
+                    margin_mode: self.margin_mode,
+                    margin_coin: self.margin_coin.clone(),
+                    position_mode: self.position_mode,
+                },
+            ))),
+            client: BitgetClient::new(
+                &self.rest_url,
+                &self.api_key,
+                &self.secret,
+                &self.passphrase,
+                self.demo,
+                &self.product_type,
+                &self.margin_coin,
+            ),
+        })
+    }
+}
+
+pub struct Bitget {
+    public_url: String,
+    private_url: String,
+    assets: HashMap<String, Asset>,
+    inv_assets: HashMap<usize, Asset>,
+    product_type: String,
+    book_channel: String,
+    channels: HashSet<String>,
+    credentials: Credentials,
+    position_mode: PositionMode,
+    order_man: OrderManagerWrapper,
+    client: BitgetClient,
+}
+
+impl Bitget {
+    pub fn builder() -> BitgetBuilder {
+        BitgetBuilder {
+            public_url: "".to_string(),
+            private_url: "".to_string(),
+            rest_url: "".to_string(),
+            demo: false,
+            product_type: "USDT-FUTURES".to_string(),
+            margin_coin: "USDT".to_string(),
+            margin_mode: "crossed".to_string(),
+            book_channel: "books".to_string(),
+            channels: Default::default(),
+            api_key: "".to_string(),
+            secret: "".to_string(),
+            passphrase: "".to_string(),
+            order_prefix: "".to_string(),
+            position_mode: Default::default(),
+        }
+    }
+}
+
+/// Converts the positions into [`LiveEvent`]s. Bitget reports all the positions at once, omitting
+/// the closed ones, so the assets without a position are reported as flat. Each position has the
+/// hold side, `long` or `short`, which is the direction of the net position in the one-way mode
+/// and the leg in the hedge mode.
+fn position_events(
+    assets: &HashMap<String, Asset>,
+    positions: &[Position],
+    position_mode: PositionMode,
+) -> Vec<LiveEvent> {
+    let mut events = Vec::new();
+    for (symbol, asset) in assets {
+        let mut long = (0.0, 0.0);
+        let mut short = (0.0, 0.0);
+        for position in positions.iter().filter(|p| p.inst_id == *symbol) {
+            let leg = (position.total, position.open_price_avg.unwrap_or(0.0));
+            if position.hold_side == "short" {
+                short = leg;
+            } else {
+                long = leg;
+            }
+        }
+        match position_mode {
+            PositionMode::OneWay => events.push(LiveEvent::Position {
+                asset_no: asset.asset_no,
+                qty: long.0 - short.0,
+            }),
+            PositionMode::Hedge => {
+                events.push(LiveEvent::PositionLeg {
+                    asset_no: asset.asset_no,
+                    side: Side::Buy,
+                    qty: long.0,
+                    entry_price: long.1,
+                });
+                events.push(LiveEvent::PositionLeg {
+                    asset_no: asset.asset_no,
+                    side: Side::Sell,
+                    qty: short.0,
+                    entry_price: short.1,
+                });
+            }
+        }
+    }
+    events
+}
+
+impl Connector for Bitget {
+    fn add(
+        &mut self,
+        asset_no: usize,
+        symbol: String,
+        tick_size: f64,
+        lot_size: f64,
+    ) -> Result<(), anyhow::Error> {
+        let asset_info = Asset {
+            asset_no,
+            symbol: symbol.clone(),
+            tick_size,
+            lot_size,
+        };
+        self.assets.insert(symbol, asset_info.clone());
+        self.inv_assets.insert(asset_no, asset_info);
+        Ok(())
+    }
+
+    fn run(&mut self, ev_tx: Sender<LiveEvent>) -> Result<(), anyhow::Error> {
+        // Connects to the public stream for the market data.
+        let public_url = self.public_url.clone();
+        let ev_tx_public = ev_tx.clone();
+        let assets_public = self.assets.clone();
+        let product_type_public = self.product_type.clone();
+        let mut channels = vec![self.book_channel.clone(), "trade".to_string()];
+        for channel in self.channels.iter() {
+            channels.push(channel.clone());
+        }
+
+        let _ = tokio::spawn(async move {
+            let mut error_count = 0;
+            loop {
+                if error_count > 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                if let Err(error) = connect_public(
+                    &public_url,
+                    ev_tx_public.clone(),
+                    assets_public.clone(),
+                    &product_type_public,
+                    channels.clone(),
+                )
+                .await
+                {
+                    error!(?error, "A connection error occurred.");
+                    ev_tx_public
+                        .send(LiveEvent::Error(LiveError::with(
+                            ErrorKind::ConnectionInterrupted,
+                            error,
+                        )))
+                        .unwrap();
+                } else {
+                    ev_tx_public
+                        .send(LiveEvent::Error(LiveError::new(
+                            ErrorKind::ConnectionInterrupted,
+                        )))
+                        .unwrap();
+                }
+                error_count += 1;
+            }
+        });
+
+        // Connects to the private stream for the position and order data.
+        let private_url = self.private_url.clone();
+        let ev_tx_private = ev_tx.clone();
+        let assets_private = self.assets.clone();
+        let product_type_private = self.product_type.clone();
+        let credentials_private = self.credentials.clone();
+        let position_mode = self.position_mode;
+        let order_man_private = self.order_man.clone();
+        let client_private = self.client.clone();
+        let _ = tokio::spawn(async move {
+            let mut error_count = 0;
+            'connection: loop {
+                if error_count > 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+
+                // Cancel all orders before connecting to the stream in order to start with the
+                // clean state.
+                for (symbol, _) in assets_private.iter() {
+                    if let Err(error) = client_private.cancel_all_orders(symbol).await {
+                        error!(?error, %symbol, "Couldn't cancel all open orders.");
+                        ev_tx_private
+                            .send(LiveEvent::Error(LiveError::with(
+                                ErrorKind::OrderError,
+                                error,
+                            )))
+                            .unwrap();
+                        error_count += 1;
+                        continue 'connection;
+                    }
+                }
+                {
+                    let mut order_manager_ = order_man_private.lock().unwrap();
+                    let orders = order_manager_.clear_orders();
+                    for (asset_no, order) in orders {
+                        ev_tx_private
+                            .send(LiveEvent::Order { asset_no, order })
+                            .unwrap();
+                    }
+                }
+
+                // Fetches the initial states such as positions.
+                match client_private.get_positions().await {
+                    Ok(positions) => {
+                        for ev in position_events(&assets_private, &positions, position_mode) {
+                            ev_tx_private.send(ev).unwrap();
+                        }
+                    }
+                    Err(error) => {
+                        error!(?error, "Couldn't get position information.");
+                        error_count += 1;
+                        continue 'connection;
+                    }
+                }
+
+                if let Err(error) = connect_private(
+                    &private_url,
+                    &credentials_private,
+                    ev_tx_private.clone(),
+                    assets_private.clone(),
+                    &product_type_private,
+                    position_mode,
+                    order_man_private.clone(),
+                )
+                .await
+                {
+                    error!(?error, "A connection error occurred.");
+                    ev_tx_private
+                        .send(LiveEvent::Error(LiveError::with(
+                            ErrorKind::ConnectionInterrupted,
+                            error,
+                        )))
+                        .unwrap();
+                } else {
+                    ev_tx_private
+                        .send(LiveEvent::Error(LiveError::new(
+                            ErrorKind::ConnectionInterrupted,
+                        )))
+                        .unwrap();
+                }
+                error_count += 1;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn submit(
+        &self,
+        asset_no: usize,
+        order: Order,
+        tx: Sender<LiveEvent>,
+    ) -> Result<(), anyhow::Error> {
+        let asset_info = self
+            .inv_assets
+            .get(&asset_no)
+            .ok_or(BitgetError::AssetNotFound)?;
+        let bitget_order =
+            self.order_man
+                .lock()
+                .unwrap()
+                .new_order(&asset_info.symbol, asset_no, order)?;
+        let client = self.client.clone();
+        let order_man = self.order_man.clone();
+        tokio::spawn(async move {
+            // The order updates are delivered through the `orders` channel.
+            if let Err(error) = client.submit_order(&bitget_order).await {
+                match order_man
+                    .lock()
+                    .unwrap()
+                    .update_submit_fail(&bitget_order.client_oid)
+                {
+                    Ok((asset_no, order)) => {
+                        tx.send(LiveEvent::Order { asset_no, order }).unwrap();
+                    }
+                    Err(error) => {
+                        error!(?error, ?bitget_order, "Couldn't update the order data");
+                    }
+                }
+                tx.send(LiveEvent::Error(LiveError::with(
+                    ErrorKind::OrderError,
+                    error,
+                )))
+                .unwrap();
+            }
+        });
+        Ok(())
+    }
+
+    fn cancel(
+        &self,
+        asset_no: usize,
+        order: Order,
+        tx: Sender<LiveEvent>,
+    ) -> Result<(), anyhow::Error> {
+        let asset_info = self
+            .inv_assets
+            .get(&asset_no)
+            .ok_or(BitgetError::AssetNotFound)?;
+        let cancel_order = self
+            .order_man
+            .lock()
+            .unwrap()
+            .cancel_order(&asset_info.symbol, order.order_id)?;
+        let client = self.client.clone();
+        let order_man = self.order_man.clone();
+        tokio::spawn(async move {
+            if let Err(error) = client.cancel_order(&cancel_order).await {
+                // The order may have been already filled or canceled, in which case it's no
+                // longer managed.
+                if let Ok((asset_no, order)) = order_man
+                    .lock()
+                    .unwrap()
+                    .update_cancel_fail(&cancel_order.client_oid)
+                {
+                    tx.send(LiveEvent::Order { asset_no, order }).unwrap();
+                }
+                tx.send(LiveEvent::Error(LiveError::with(
+                    ErrorKind::OrderError,
+                    error,
+                )))
+                .unwrap();
+            }
+        });
+        Ok(())
+    }
+
+    fn cancel_all(&self, asset_no: usize, tx: Sender<LiveEvent>) -> Result<(), anyhow::Error> {
+        let asset_info = self
+            .inv_assets
+            .get(&asset_no)
+            .ok_or(BitgetError::AssetNotFound)?;
+        let symbol = asset_info.symbol.clone();
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            // The cancellation of each order is delivered through the `orders` channel.
+            if let Err(error) = client.cancel_all_orders(&symbol).await {
+                error!(?error, %symbol, "Couldn't cancel all open orders.");
+                tx.send(LiveEvent::Error(LiveError::with(
+                    ErrorKind::OrderError,
+                    error,
+                )))
+                .unwrap();
+            }
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,257 @@
+use std::{fmt, fmt::Debug};
+
+use serde::{
+    de,
+    de::{Error, Unexpected, Visitor},
+    Deserialize,
+    Deserializer,
+    Serialize,
+};
+
+use crate::{
+    connector::util::{from_str_to_f64, from_str_to_f64_opt, from_str_to_i64},
+    types::{Side, Status},
+};
+
+struct SideVisitor;
+
+impl<'de> Visitor<'de> for SideVisitor {
+    type Value = Side;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string containing \"buy\" or \"sell\"")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match s {
+            "buy" => Ok(Side::Buy),
+            "sell" => Ok(Side::Sell),
+            s => Err(Error::invalid_value(Unexpected::Other(s), &"buy or sell")),
+        }
+    }
+}
+
+fn from_str_to_side<'de, D>(deserializer: D) -> Result<Side, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(SideVisitor)
+}
+
+struct StatusVisitor;
+
+impl<'de> Visitor<'de> for StatusVisitor {
+    type Value = Status;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string containing the order status")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match s {
+            "init" | "new" | "live" => Ok(Status::New),
+            "partially_filled" => Ok(Status::PartiallyFilled),
+            "filled" => Ok(Status::Filled),
+            "canceled" | "cancelled" => Ok(Status::Canceled),
+            s => Err(Error::invalid_value(
+                Unexpected::Other(s),
+                &"init, new, live, partially_filled, filled, or canceled",
+            )),
+        }
+    }
+}
+
+fn from_str_to_status<'de, D>(deserializer: D) -> Result<Status, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(StatusVisitor)
+}
+
+#[derive(Serialize, Debug)]
+pub struct Op<T>
+where
+    T: Serialize + Debug,
+{
+    pub op: String,
+    pub args: Vec<T>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LoginArg {
+    #[serde(rename = "apiKey")]
+    pub api_key: String,
+    pub passphrase: String,
+    pub timestamp: String,
+    pub sign: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Arg {
+    #[serde(rename = "instType")]
+    pub inst_type: String,
+    pub channel: String,
+    /// The symbol for the public channels, and `default` for all the symbols of the private
+    /// channels.
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum StreamMsg {
+    Push(PushMsg),
+    Event(EventMsg),
+}
+
+/// A message pushed through a subscribed channel.
+#[derive(Deserialize, Debug)]
+pub struct PushMsg {
+    /// `snapshot` or `update`.
+    pub action: String,
+    pub arg: Arg,
+    pub data: serde_json::Value,
+}
+
+/// A response to the login and the subscription, or an error.
+#[derive(Deserialize, Debug)]
+pub struct EventMsg {
+    pub event: String,
+    #[serde(default)]
+    pub code: Option<i64>,
+    #[serde(default)]
+    pub msg: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OrderBook {
+    /// Each level consists of the price and the quantity.
+    pub asks: Vec<(String, String)>,
+    pub bids: Vec<(String, String)>,
+    #[serde(deserialize_with = "from_str_to_i64")]
+    pub ts: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Trade {
+    #[serde(deserialize_with = "from_str_to_f64")]
+    pub price: f64,
+    #[serde(deserialize_with = "from_str_to_f64")]
+    pub size: f64,
+    #[serde(deserialize_with = "from_str_to_side")]
+    pub side: Side,
+    #[serde(deserialize_with = "from_str_to_i64")]
+    pub ts: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Position {
+    /// It's `symbol` in the REST API response.
+    #[serde(rename = "instId", alias = "symbol")]
+    pub inst_id: String,
+    /// `long` or `short`, which is the direction of the position in the one-way mode, and the
+    /// leg in the hedge mode.
+    #[serde(rename = "holdSide")]
+    pub hold_side: String,
+    /// The quantity of the position, which is positive.
+    #[serde(deserialize_with = "from_str_to_f64")]
+    pub total: f64,
+    #[serde(rename = "openPriceAvg", default)]
+    #[serde(deserialize_with = "from_str_to_f64_opt")]
+    pub open_price_avg: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PrivateOrder {
+    #[serde(rename = "clientOid")]
+    pub client_oid: String,
+    #[serde(deserialize_with = "from_str_to_f64")]
+    pub size: f64,
+    #[serde(deserialize_with = "from_str_to_status")]
+    pub status: Status,
+    /// The price and the quantity of the last fill, which are absent if the push isn't caused by
+    /// a fill.
+    #[serde(rename = "fillPrice", default)]
+    #[serde(deserialize_with = "from_str_to_f64_opt")]
+    pub fill_price: Option<f64>,
+    #[serde(rename = "baseVolume", default)]
+    #[serde(deserialize_with = "from_str_to_f64_opt")]
+    pub base_volume: Option<f64>,
+    #[serde(rename = "accBaseVolume")]
+    #[serde(deserialize_with = "from_str_to_f64")]
+    pub acc_base_volume: f64,
+    #[serde(rename = "uTime")]
+    #[serde(deserialize_with = "from_str_to_i64")]
+    pub u_time: i64,
+}
+
+/// The request body of the place-order endpoint.
+#[derive(Serialize, Debug)]
+pub struct Order {
+    pub symbol: String,
+    #[serde(rename = "productType")]
+    pub product_type: String,
+    #[serde(rename = "marginMode")]
+    pub margin_mode: String,
+    #[serde(rename = "marginCoin")]
+    pub margin_coin: String,
+    pub size: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<String>,
+    /// The direction of the order in the one-way mode, and the leg in the hedge mode.
+    pub side: String,
+    /// `open` or `close` in the hedge mode. Omitted in the one-way mode.
+    #[serde(rename = "tradeSide")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trade_side: Option<String>,
+    #[serde(rename = "orderType")]
+    pub order_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force: Option<String>,
+    #[serde(rename = "clientOid")]
+    pub client_oid: String,
+    /// `YES` or `NO` in the one-way mode. Omitted in the hedge mode.
+    #[serde(rename = "reduceOnly")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<String>,
+}
+
+/// The request body of the cancel-order endpoint.
+#[derive(Serialize, Debug)]
+pub struct CancelOrder {
+    pub symbol: String,
+    #[serde(rename = "productType")]
+    pub product_type: String,
+    #[serde(rename = "marginCoin")]
+    pub margin_coin: String,
+    #[serde(rename = "clientOid")]
+    pub client_oid: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PendingOrders {
+    /// `null` if there is no pending order.
+    #[serde(rename = "entrustedList")]
+    #[serde(default)]
+    pub entrusted_list: Option<Vec<PendingOrder>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PendingOrder {
+    #[serde(rename = "orderId")]
+    pub order_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RestResponse {
+    pub code: String,
+    pub msg: String,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    num::{ParseFloatError, ParseIntError},
+    sync::{Arc, Mutex},
+};
+
+use thiserror::Error;
+
+use crate::{
+    connector::{
+        bitget::msg::{CancelOrder, Order as BitgetOrder, PrivateOrder},
+        util::gen_random_string,
+    },
+    prelude::{get_precision, OrdType, OrderId, Side, TimeInForce},
+    types::{Order, PositionMode, Status},
+};
+
+pub type OrderManagerWrapper = Arc<Mutex<OrderManager>>;
+
+// The length of the random string between the prefix and the order id in the client order id,
+// which keeps the client order ids unique across the sessions.
+const RAND_ID_LEN: usize = 8;
+
+#[derive(Error, Debug)]
+pub(super) enum HandleError {
+    #[error("px qty parse error: {0}")]
+    InvalidPxQty(#[from] ParseFloatError),
+    #[error("order id parse error: {0}")]
+    InvalidOrderId(ParseIntError),
+    #[error("prefix unmatched")]
+    PrefixUnmatched,
+    #[error("order not found")]
+    OrderNotFound,
+    #[error("asset not found")]
+    AssetNotFound,
+    #[error("invalid argument")]
+    InvalidArg(&'static str),
+    #[error("order already exist")]
+    OrderAlreadyExist,
+    #[error("serde: {0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("tokio: {0}")]
+    TokioError(#[source] Box<tokio_tungstenite::tungstenite::Error>),
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for HandleError {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        HandleError::TokioError(Box::new(error))
+    }
+}
+
+/// The trading parameters of the account, which are sent with every order.
+pub struct OrderParams {
+    pub product_type: String,
+    pub margin_mode: String,
+    pub margin_coin: String,
+    pub position_mode: PositionMode,
+}
+
+pub struct OrderManager {
+    prefix: String,
+    params: OrderParams,
+    orders: HashMap<OrderId, (usize, String, Order)>,
+}
+
+impl OrderManager {
+    pub fn new(prefix: &str, params: OrderParams) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            params,
+            orders: Default::default(),
+        }
+    }
+
+    fn parse_order_id(&self, client_oid: &str) -> Result<OrderId, HandleError> {
+        if !client_oid.starts_with(&self.prefix)
+            || client_oid.len() < self.prefix.len() + RAND_ID_LEN
+        {
+            return Err(HandleError::PrefixUnmatched);
+        }
+        client_oid[(self.prefix.len() + RAND_ID_LEN)..]
+            .parse()
+            .map_err(HandleError::InvalidOrderId)
+    }
+
+    /// Updates the order with the push of the `orders` channel, which also carries the last fill.
+    pub fn update_order(&mut self, data: &PrivateOrder) -> Result<(usize, Order), HandleError> {
+        let order_id = self.parse_order_id(&data.client_oid)?;
+        let (asset_no, _client_oid, order) = self
+            .orders
+            .get_mut(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        order.req = Status::None;
+        order.status = data.status;
+        order.leaves_qty = data.size - data.acc_base_volume;
+        order.exch_timestamp = data.u_time * 1_000_000;
+        if let (Some(fill_price), Some(fill_qty)) = (data.fill_price, data.base_volume) {
+            if fill_qty > 0.0 {
+                order.exec_price_tick = (fill_price / order.tick_size).round() as i64;
+                order.exec_qty = fill_qty;
+            }
+        }
+        let is_active = order.active();
+        if !is_active {
+            let (asset_no, _client_oid, order) = self.orders.remove(&order_id).unwrap();
+            Ok((asset_no, order))
+        } else {
+            Ok((*asset_no, order.clone()))
+        }
+    }
+
+    pub fn new_order(
+        &mut self,
+        symbol: &str,
+        asset_no: usize,
+        order: Order,
+    ) -> Result<BitgetOrder, HandleError> {
+        let price_prec = get_precision(order.tick_size);
+        let rand_id = gen_random_string(RAND_ID_LEN);
+        let force = match (order.order_type, order.time_in_force) {
+            (OrdType::Market, _) => None,
+            (OrdType::Limit, TimeInForce::GTC) => Some("gtc"),
+            (OrdType::Limit, TimeInForce::GTX) => Some("post_only"),
+            (OrdType::Limit, TimeInForce::FOK) => Some("fok"),
+            (OrdType::Limit, TimeInForce::IOC) => Some("ioc"),
+            (OrdType::Limit, _) => return Err(HandleError::InvalidArg("time_in_force")),
+            // Stop orders are placed through the plan order API, which isn't supported.
+            _ => return Err(HandleError::InvalidArg("order_type")),
+        };
+        if matches!(order.side, Side::None | Side::Unsupported) {
+            return Err(HandleError::InvalidArg("side"));
+        }
+        let (side, trade_side) = match self.params.position_mode {
+            PositionMode::OneWay => (order.side, None),
+            // In the hedge mode, the side is the leg, and the trade side tells whether the order
+            // opens or closes it.
+            PositionMode::Hedge => {
+                let leg = order.hedge_leg();
+                let trade_side = if order.side == leg { "open" } else { "close" };
+                (leg, Some(trade_side.to_string()))
+            }
+        };
+        let bitget_order = BitgetOrder {
+            symbol: symbol.to_string(),
+            product_type: self.params.product_type.clone(),
+            margin_mode: self.params.margin_mode.clone(),
+            margin_coin: self.params.margin_coin.clone(),
+            size: format!("{:.5}", order.qty),
+            price: (order.order_type == OrdType::Limit).then(|| {
+                format!(
+                    "{:.prec$}",
+                    order.price_tick as f64 * order.tick_size,
+                    prec = price_prec
+                )
+            }),
+            side: match side {
+                Side::Buy => "buy".to_string(),
+                _ => "sell".to_string(),
+            },
+            trade_side,
+            order_type: match order.order_type {
+                OrdType::Market => "market".to_string(),
+                _ => "limit".to_string(),
+            },
+            force: force.map(|force| force.to_string()),
+            client_oid: format!("{}{}{}", self.prefix, rand_id, order.order_id),
+            reduce_only: match self.params.position_mode {
+                PositionMode::OneWay if order.reduce_only => Some("YES".to_string()),
+                _ => None,
+            },
+        };
+        match self.orders.entry(order.order_id) {
+            Entry::Occupied(_) => {
+                return Err(HandleError::OrderAlreadyExist);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((asset_no, bitget_order.client_oid.clone(), order));
+            }
+        }
+        Ok(bitget_order)
+    }
+
+    pub fn cancel_order(
+        &mut self,
+        symbol: &str,
+        order_id: OrderId,
+    ) -> Result<CancelOrder, HandleError> {
+        let (_, client_oid, _order) = self
+            .orders
+            .get(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        Ok(CancelOrder {
+            symbol: symbol.to_string(),
+            product_type: self.params.product_type.clone(),
+            margin_coin: self.params.margin_coin.clone(),
+            client_oid: client_oid.clone(),
+        })
+    }
+
+    pub fn update_submit_fail(&mut self, client_oid: &str) -> Result<(usize, Order), HandleError> {
+        let order_id = self.parse_order_id(client_oid)?;
+        let (asset_no, _client_oid, mut order) = self
+            .orders
+            .remove(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        order.req = Status::None;
+        order.status = Status::Expired;
+        Ok((asset_no, order))
+    }
+
+    pub fn update_cancel_fail(&mut self, client_oid: &str) -> Result<(usize, Order), HandleError> {
+        let order_id = self.parse_order_id(client_oid)?;
+        let (asset_no, _client_oid, order) = self
+            .orders
+            .get_mut(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        order.req = Status::None;
+        Ok((*asset_no, order.clone()))
+    }
+
+    pub fn clear_orders(&mut self) -> Vec<(usize, Order)> {
+        let mut values: Vec<(usize, Order)> = Vec::new();
+        values.extend(self.orders.drain().map(|(_, (asset_no, _, mut order))| {
+            order.status = Status::Canceled;
+            (asset_no, order)
+        }));
+        values
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::connector::{
+    bitget::{
+        msg::{CancelOrder, Order, PendingOrders, Position, RestResponse},
+        BitgetError,
+    },
+    util::sign_hmac_sha256_base64,
+};
+
+// The maximum number of orders that can be canceled by a single batch cancel request.
+const MAX_BATCH_CANCEL: usize = 50;
+
+// The code of the successful response.
+const SUCCESS: &str = "00000";
+
+#[derive(Clone)]
+pub struct BitgetClient {
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    secret: String,
+    passphrase: String,
+    demo: bool,
+    product_type: String,
+    margin_coin: String,
+}
+
+impl BitgetClient {
+    pub fn new(
+        url: &str,
+        api_key: &str,
+        secret: &str,
+        passphrase: &str,
+        demo: bool,
+        product_type: &str,
+        margin_coin: &str,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            api_key: api_key.to_string(),
+            secret: secret.to_string(),
+            passphrase: passphrase.to_string(),
+            demo,
+            product_type: product_type.to_string(),
+            margin_coin: margin_coin.to_string(),
+        }
+    }
+
+    async fn request<T: for<'a> Deserialize<'a>>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: String,
+    ) -> Result<T, reqwest::Error> {
+        let time = Utc::now().timestamp_millis().to_string();
+        let sign_body = format!("{time}{}{path}{body}", method.as_str());
+        let signature = sign_hmac_sha256_base64(&self.secret, &sign_body);
+        let mut req = self
+            .client
+            .request(method, format!("{}{}", self.url, path))
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .header("locale", "en-US")
+            .header("ACCESS-KEY", &self.api_key)
+            .header("ACCESS-SIGN", signature)
+            .header("ACCESS-TIMESTAMP", time)
+            .header("ACCESS-PASSPHRASE", &self.passphrase);
+        if self.demo {
+            req = req.header("paptrading", "1");
+        }
+        let resp = req.body(body).send().await?.json().await?;
+        Ok(resp)
+    }
+
+    fn check(resp: RestResponse) -> Result<serde_json::Value, BitgetError> {
+        if resp.code != SUCCESS {
+            Err(BitgetError::OrderError(
+                resp.code.parse().unwrap_or_default(),
+                resp.msg,
+            ))
+        } else {
+            Ok(resp.data)
+        }
+    }
+
+    pub async fn submit_order(&self, order: &Order) -> Result<(), BitgetError> {
+        let resp: RestResponse = self
+            .request(
+                reqwest::Method::POST,
+                "/api/v2/mix/order/place-order",
+                serde_json::to_string(order)?,
+            )
+            .await?;
+        Self::check(resp)?;
+        Ok(())
+    }
+
+    pub async fn cancel_order(&self, order: &CancelOrder) -> Result<(), BitgetError> {
+        let resp: RestResponse = self
+            .request(
+                reqwest::Method::POST,
+                "/api/v2/mix/order/cancel-order",
+                serde_json::to_string(order)?,
+            )
+            .await?;
+        Self::check(resp)?;
+        Ok(())
+    }
+
+    pub async fn cancel_all_orders(&self, symbol: &str) -> Result<(), BitgetError> {
+        let resp: RestResponse = self
+            .request(
+                reqwest::Method::GET,
+                &format!(
+                    "/api/v2/mix/order/orders-pending?productType={}&symbol={symbol}",
+                    self.product_type
+                ),
+                String::new(),
+            )
+            .await?;
+        let pending: PendingOrders = serde_json::from_value(Self::check(resp)?)?;
+        let order_ids: Vec<_> = pending
+            .entrusted_list
+            .unwrap_or_default()
+            .into_iter()
+            .map(|order| json!({ "orderId": order.order_id }))
+            .collect();
+        for chunk in order_ids.chunks(MAX_BATCH_CANCEL) {
+            let body = json!({
+                "symbol": symbol,
+                "productType": self.product_type,
+                "marginCoin": self.margin_coin,
+                "orderIdList": chunk,
+            });
+            let resp: RestResponse = self
+                .request(
+                    reqwest::Method::POST,
+                    "/api/v2/mix/order/batch-cancel-orders",
+                    body.to_string(),
+                )
+                .await?;
+            Self::check(resp)?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_positions(&self) -> Result<Vec<Position>, BitgetError> {
+        let resp: RestResponse = self
+            .request(
+                reqwest::Method::GET,
+                &format!(
+                    "/api/v2/mix/position/all-position?productType={}&marginCoin={}",
+                    self.product_type, self.margin_coin
+                ),
+                String::new(),
+            )
+            .await?;
+        let positions: Vec<Position> = serde_json::from_value(Self::check(resp)?)?;
+        Ok(positions)
+    }
+}
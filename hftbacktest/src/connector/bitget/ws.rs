@@ -0,0 +1,398 @@
+use std::{collections::HashMap, sync::mpsc::Sender, time::Duration};
+
+use chrono::Utc;
+use futures_util::{stream::SplitSink, SinkExt, StreamExt};
+use tokio::{net::TcpStream, select, time};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, Message},
+    MaybeTlsStream,
+    WebSocketStream,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    connector::{
+        bitget::{
+            msg::{
+                Arg,
+                EventMsg,
+                LoginArg,
+                Op,
+                OrderBook,
+                Position,
+                PrivateOrder,
+                StreamMsg,
+                Trade,
+            },
+            ordermanager::{HandleError, OrderManagerWrapper},
+            position_events,
+            BitgetError,
+        },
+        util::sign_hmac_sha256_base64,
+    },
+    live::Asset,
+    types::{
+        ErrorKind,
+        Event,
+        LiveError,
+        LiveEvent,
+        PositionMode,
+        Side,
+        LOCAL_ASK_DEPTH_EVENT,
+        LOCAL_BID_DEPTH_EVENT,
+        LOCAL_BUY_TRADE_EVENT,
+        LOCAL_DEPTH_CLEAR_EVENT,
+        LOCAL_SELL_TRADE_EVENT,
+    },
+};
+
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+
+fn event(ev: u64, exch_ts: i64, px: f64, qty: f64) -> Event {
+    Event {
+        ev,
+        exch_ts,
+        local_ts: Utc::now().timestamp_nanos_opt().unwrap(),
+        order_id: 0,
+        px,
+        qty,
+        ival: 0,
+        fval: 0.0,
+    }
+}
+
+/// The API key, the secret key, and the passphrase used to log in to the private stream.
+#[derive(Clone)]
+pub struct Credentials {
+    pub api_key: String,
+    pub secret: String,
+    pub passphrase: String,
+}
+
+fn login_op(credentials: &Credentials) -> String {
+    let timestamp = Utc::now().timestamp().to_string();
+    let sign = sign_hmac_sha256_base64(&credentials.secret, &format!("{timestamp}GET/user/verify"));
+    let op = Op {
+        op: "login".to_string(),
+        args: vec![LoginArg {
+            api_key: credentials.api_key.clone(),
+            passphrase: credentials.passphrase.clone(),
+            timestamp,
+            sign,
+        }],
+    };
+    serde_json::to_string(&op).unwrap()
+}
+
+fn subscribe_op(args: Vec<Arg>) -> String {
+    let op = Op {
+        op: "subscribe".to_string(),
+        args,
+    };
+    serde_json::to_string(&op).unwrap()
+}
+
+// The error codes of the login failures, which cannot be recovered by reconnecting: invalid
+// access key, invalid passphrase, invalid signature, and expired timestamp.
+const AUTH_ERROR_CODES: [i64; 4] = [30011, 30012, 30013, 30014];
+
+/// Returns `true` if the event is the successful login. A login failure is reported as a critical
+/// error.
+fn check_login(resp: &EventMsg, ev_tx: &Sender<LiveEvent>) -> bool {
+    match resp.event.as_str() {
+        "login" if resp.code.unwrap_or(0) == 0 => true,
+        "login" | "error" => {
+            let code = resp.code.unwrap_or_default();
+            let msg = resp.msg.clone().unwrap_or_default();
+            error!(%code, %msg, "Login or subscription failed.");
+            if AUTH_ERROR_CODES.contains(&code) {
+                ev_tx
+                    .send(LiveEvent::Error(LiveError::with(
+                        ErrorKind::CriticalConnectionError,
+                        BitgetError::AuthError(code, msg),
+                    )))
+                    .unwrap();
+            }
+            false
+        }
+        _ => {
+            info!(?resp, "Event");
+            false
+        }
+    }
+}
+
+fn parse_levels(levels: &[(String, String)]) -> Result<Vec<(f64, f64)>, HandleError> {
+    let mut levels_ = Vec::with_capacity(levels.len());
+    for (px, qty) in levels {
+        levels_.push((px.parse()?, qty.parse()?));
+    }
+    Ok(levels_)
+}
+
+fn handle_book(
+    asset: &Asset,
+    snapshot: bool,
+    data: OrderBook,
+    ev_tx: &Sender<LiveEvent>,
+) -> Result<(), HandleError> {
+    let bids = parse_levels(&data.bids)?;
+    let asks = parse_levels(&data.asks)?;
+    let exch_ts = data.ts * 1_000_000;
+
+    let mut events = Vec::with_capacity(bids.len() + asks.len() + 1);
+    if snapshot {
+        // The snapshot replaces the entire order book. The channels of the fixed number of
+        // levels, such as `books5`, push only the snapshots.
+        events.push(event(LOCAL_DEPTH_CLEAR_EVENT, exch_ts, 0.0, 0.0));
+    }
+    events.extend(
+        bids.iter()
+            .map(|&(px, qty)| event(LOCAL_BID_DEPTH_EVENT, exch_ts, px, qty)),
+    );
+    events.extend(
+        asks.iter()
+            .map(|&(px, qty)| event(LOCAL_ASK_DEPTH_EVENT, exch_ts, px, qty)),
+    );
+    ev_tx
+        .send(LiveEvent::FeedBatch {
+            asset_no: asset.asset_no,
+            events,
+        })
+        .unwrap();
+    Ok(())
+}
+
+fn handle_public_stream(
+    text: &str,
+    ev_tx: &Sender<LiveEvent>,
+    assets: &HashMap<String, Asset>,
+) -> Result<(), HandleError> {
+    if text == "pong" {
+        return Ok(());
+    }
+    let stream = serde_json::from_str::<StreamMsg>(text)?;
+    match stream {
+        StreamMsg::Event(resp) => {
+            if resp.event == "error" {
+                error!(?resp, "Subscription failed.");
+            } else {
+                debug!(?resp, "Event");
+            }
+        }
+        StreamMsg::Push(stream) => {
+            let asset = assets
+                .get(&stream.arg.inst_id)
+                .ok_or(HandleError::AssetNotFound)?;
+            if stream.arg.channel.starts_with("books") {
+                let snapshot = stream.action == "snapshot";
+                let data: Vec<OrderBook> = serde_json::from_value(stream.data)?;
+                for item in data {
+                    handle_book(asset, snapshot, item, ev_tx)?;
+                }
+            } else if stream.arg.channel == "trade" {
+                // The snapshot consists of the recent trades before the subscription.
+                if stream.action == "snapshot" {
+                    return Ok(());
+                }
+                let data: Vec<Trade> = serde_json::from_value(stream.data)?;
+                for item in data {
+                    ev_tx
+                        .send(LiveEvent::FeedBatch {
+                            asset_no: asset.asset_no,
+                            events: vec![event(
+                                if item.side == Side::Sell {
+                                    LOCAL_SELL_TRADE_EVENT
+                                } else {
+                                    LOCAL_BUY_TRADE_EVENT
+                                },
+                                item.ts * 1_000_000,
+                                item.price,
+                                item.size,
+                            )],
+                        })
+                        .unwrap();
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn connect_public(
+    url: &str,
+    ev_tx: Sender<LiveEvent>,
+    assets: HashMap<String, Asset>,
+    inst_type: &str,
+    channels: Vec<String>,
+) -> Result<(), HandleError> {
+    let request = url.into_client_request()?;
+    let (ws_stream, _) = connect_async(request).await?;
+    let (mut write, mut read) = ws_stream.split();
+    let mut interval = time::interval(Duration::from_secs(30));
+
+    let mut args = Vec::new();
+    for channel in channels {
+        for symbol in assets.keys() {
+            args.push(Arg {
+                inst_type: inst_type.to_string(),
+                channel: channel.clone(),
+                inst_id: symbol.clone(),
+            });
+        }
+    }
+    write.send(Message::Text(subscribe_op(args))).await?;
+
+    loop {
+        select! {
+            _ = interval.tick() => {
+                write.send(Message::Text("ping".to_string())).await?;
+            }
+            message = read.next() => {
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Err(error) = handle_public_stream(&text, &ev_tx, &assets) {
+                            error!(?error, %text, "Couldn't handle PublicStreamMsg.");
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {}
+                    Some(Ok(Message::Ping(_))) => {
+                        write.send(Message::Pong(Vec::new())).await?;
+                    }
+                    Some(Ok(Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(close_frame))) => {
+                        info!(?close_frame, "close");
+                        break;
+                    }
+                    Some(Ok(Message::Frame(_))) => {}
+                    Some(Err(e)) => {
+                        return Err(HandleError::from(e));
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_private_stream(
+    text: &str,
+    write: &mut WsSink,
+    assets: &HashMap<String, Asset>,
+    inst_type: &str,
+    position_mode: PositionMode,
+    ev_tx: &Sender<LiveEvent>,
+    order_man: &OrderManagerWrapper,
+) -> Result<(), HandleError> {
+    if text == "pong" {
+        return Ok(());
+    }
+    let stream = serde_json::from_str::<StreamMsg>(text)?;
+    match stream {
+        StreamMsg::Event(resp) => {
+            debug!(?resp, "Event");
+            if check_login(&resp, ev_tx) {
+                let args = ["orders", "positions"]
+                    .into_iter()
+                    .map(|channel| Arg {
+                        inst_type: inst_type.to_string(),
+                        channel: channel.to_string(),
+                        inst_id: "default".to_string(),
+                    })
+                    .collect();
+                write.send(Message::Text(subscribe_op(args))).await?;
+            }
+        }
+        StreamMsg::Push(stream) => {
+            if stream.arg.channel == "positions" {
+                let data: Vec<Position> = serde_json::from_value(stream.data)?;
+                debug!(?data, "Position");
+                for ev in position_events(assets, &data, position_mode) {
+                    ev_tx.send(ev).unwrap();
+                }
+            } else if stream.arg.channel == "orders" {
+                let data: Vec<PrivateOrder> = serde_json::from_value(stream.data)?;
+                debug!(?data, "Order");
+                let mut order_man_ = order_man.lock().unwrap();
+                for item in &data {
+                    match order_man_.update_order(item) {
+                        Ok((asset_no, order)) => {
+                            ev_tx.send(LiveEvent::Order { asset_no, order }).unwrap();
+                        }
+                        Err(HandleError::PrefixUnmatched) => {
+                            warn!(?item, "PrefixUnmatched");
+                        }
+                        Err(error) => {
+                            error!(?error, ?item, "Couldn't update the order data");
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn connect_private(
+    url: &str,
+    credentials: &Credentials,
+    ev_tx: Sender<LiveEvent>,
+    assets: HashMap<String, Asset>,
+    inst_type: &str,
+    position_mode: PositionMode,
+    order_man: OrderManagerWrapper,
+) -> Result<(), HandleError> {
+    let request = url.into_client_request()?;
+    let (ws_stream, _) = connect_async(request).await?;
+    let (mut write, mut read) = ws_stream.split();
+    let mut interval = time::interval(Duration::from_secs(30));
+
+    // The private channels are subscribed once the login succeeds.
+    write.send(Message::Text(login_op(credentials))).await?;
+
+    loop {
+        select! {
+            _ = interval.tick() => {
+                write.send(Message::Text("ping".to_string())).await?;
+            }
+            message = read.next() => {
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Err(error) = handle_private_stream(
+                            &text,
+                            &mut write,
+                            &assets,
+                            inst_type,
+                            position_mode,
+                            &ev_tx,
+                            &order_man
+                        ).await {
+                            error!(%text, ?error, "Couldn't properly handle PrivateStreamMsg");
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {}
+                    Some(Ok(Message::Ping(_))) => {
+                        write.send(Message::Pong(Vec::new())).await?;
+                    }
+                    Some(Ok(Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(close_frame))) => {
+                        info!(?close_frame, "close");
+                        break;
+                    }
+                    Some(Ok(Message::Frame(_))) => {}
+                    Some(Err(e)) => {
+                        return Err(HandleError::from(e));
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
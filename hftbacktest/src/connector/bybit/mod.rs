@@ -11,6 +11,7 @@ use tracing::error;
 use crate::{
     connector::{
         bybit::{
+            msg::Position,
             ordermanager::{OrderManager, OrderManagerWrapper},
             rest::BybitClient,
             ws::{connect_private, connect_public, connect_trade, OrderOp},
@@ -18,7 +19,7 @@ use crate::{
         Connector,
     },
     live::Asset,
-    types::{BuildError, ErrorKind, LiveError, LiveEvent, Order},
+    types::{BuildError, ErrorKind, LiveError, LiveEvent, Order, PositionMode, Side},
 };
 
 mod msg;
@@ -67,6 +68,7 @@ pub struct BybitBuilder {
     secret: String,
     category: String,
     order_prefix: String,
+    position_mode: PositionMode,
 }
 
 impl BybitBuilder {
@@ -179,6 +181,17 @@ impl BybitBuilder {
         }
     }
 
+    /// Sets the position mode, which must match the account's position mode on Bybit. In
+    /// [`PositionMode::Hedge`], orders are submitted with the position index of the leg determined
+    /// by [`Order::hedge_leg`], and the positions are reported per leg. The default value is
+    /// [`PositionMode::OneWay`].
+    pub fn position_mode(self, position_mode: PositionMode) -> Self {
+        Self {
+            position_mode,
+            ..self
+        }
+    }
+
     /// Adds an additional topic to receive through the public WebSocket stream.
     pub fn add_topic(mut self, topic: &str) -> Self {
         self.topics.insert(topic.to_string());
@@ -235,7 +248,10 @@ impl BybitBuilder {
             api_key: self.api_key.clone(),
             secret: self.secret.clone(),
             order_tx: None,
-            order_man: Arc::new(Mutex::new(OrderManager::new(&self.order_prefix))),
+            order_man: Arc::new(Mutex::new(OrderManager::new(
+                &self.order_prefix,
+                self.position_mode,
+            ))),
             category: self.category,
             client: BybitClient::new(&self.rest_url, &self.api_key, &self.secret),
         })
@@ -269,10 +285,34 @@ impl Bybit {
             secret: "".to_string(),
             category: "".to_string(),
             order_prefix: "".to_string(),
+            position_mode: Default::default(),
         }
     }
 }
 
+/// Converts the position into [`LiveEvent`]. In the hedge mode, Bybit reports the long and short
+/// legs separately with the position index `1` and `2`, respectively.
+fn position_event(asset_no: usize, position: &Position) -> LiveEvent {
+    match position.position_idx {
+        1 => LiveEvent::PositionLeg {
+            asset_no,
+            side: Side::Buy,
+            qty: position.size,
+            entry_price: position.entry_price.unwrap_or(0.0),
+        },
+        2 => LiveEvent::PositionLeg {
+            asset_no,
+            side: Side::Sell,
+            qty: position.size,
+            entry_price: position.entry_price.unwrap_or(0.0),
+        },
+        _ => LiveEvent::Position {
+            asset_no,
+            qty: position.size,
+        },
+    }
+}
+
 impl Connector for Bybit {
     fn add(
         &mut self,
@@ -388,10 +428,7 @@ impl Connector for Bybit {
                             positions.into_iter().for_each(|position| {
                                 assets_private.get(&position.symbol).map(|asset_info| {
                                     ev_tx_private
-                                        .send(LiveEvent::Position {
-                                            asset_no: asset_info.asset_no,
-                                            qty: position.size,
-                                        })
+                                        .send(position_event(asset_info.asset_no, &position))
                                         .unwrap();
                                 });
                             });
@@ -518,4 +555,26 @@ impl Connector for Bybit {
         })?;
         Ok(())
     }
+
+    fn cancel_all(&self, asset_no: usize, tx: Sender<LiveEvent>) -> Result<(), anyhow::Error> {
+        let asset_info = self
+            .inv_assets
+            .get(&asset_no)
+            .ok_or(BybitError::AssetNotFound)?;
+        let symbol = asset_info.symbol.clone();
+        let category = self.category.clone();
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            // The cancellation of each order is delivered through the private stream.
+            if let Err(error) = client.cancel_all_orders(&category, &symbol).await {
+                error!(?error, %symbol, "Couldn't cancel all open orders.");
+                tx.send(LiveEvent::Error(LiveError::with(
+                    ErrorKind::OrderError,
+                    error,
+                )))
+                .unwrap();
+            }
+        });
+        Ok(())
+    }
 }
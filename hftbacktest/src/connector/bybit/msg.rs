@@ -118,13 +118,13 @@ impl<'de> Visitor<'de> for StatusVisitor {
         match s {
             "New" => Ok(Status::New),
             "PartiallyFilled" => Ok(Status::PartiallyFilled),
-            "Untriggered" => Ok(Status::Unsupported),
+            "Untriggered" => Ok(Status::New),
             "Rejected" => Ok(Status::Expired),
             "PartiallyFilledCanceled" => Ok(Status::Canceled),
             "Filled" => Ok(Status::Filled),
             "Cancelled" => Ok(Status::Canceled),
-            "Triggered" => Ok(Status::Unsupported),
-            "Deactivated" => Ok(Status::Unsupported),
+            "Triggered" => Ok(Status::New),
+            "Deactivated" => Ok(Status::Canceled),
             s => Err(Error::invalid_value(Unexpected::Other(s), &"IOC or GTC")),
         }
     }
@@ -574,6 +574,21 @@ pub struct Order {
     pub time_in_force: Option<String>,
     #[serde(rename = "orderLinkId")]
     pub order_link_id: String,
+    #[serde(rename = "triggerPrice")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_price: Option<String>,
+    /// `1`: triggered when the market price rises to the trigger price, `2`: triggered when it
+    /// falls to the trigger price.
+    #[serde(rename = "triggerDirection")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_direction: Option<i64>,
+    #[serde(rename = "reduceOnly")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+    /// `1`: the long leg, `2`: the short leg in the hedge mode. Omitted in the one-way mode.
+    #[serde(rename = "positionIdx")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position_idx: Option<i64>,
 }
 
 #[derive(Deserialize, Debug)]
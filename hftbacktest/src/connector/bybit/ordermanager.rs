@@ -12,7 +12,7 @@ use crate::{
         util::gen_random_string,
     },
     prelude::{get_precision, OrdType, OrderId, Side, TimeInForce},
-    types::{Order, Status},
+    types::{Order, PositionMode, Status},
 };
 
 pub type OrderManagerWrapper = Arc<Mutex<OrderManager>>;
@@ -43,13 +43,15 @@ pub(super) enum HandleError {
 
 pub struct OrderManager {
     prefix: String,
+    position_mode: PositionMode,
     orders: HashMap<OrderId, (usize, String, Order)>,
 }
 
 impl OrderManager {
-    pub fn new(prefix: &str) -> Self {
+    pub fn new(prefix: &str, position_mode: PositionMode) -> Self {
         Self {
             prefix: prefix.to_string(),
+            position_mode,
             orders: Default::default(),
         }
     }
@@ -129,8 +131,8 @@ impl OrderManager {
             }),
             order_type: Some({
                 match order.order_type {
-                    OrdType::Limit => "Limit".to_string(),
-                    OrdType::Market => "Market".to_string(),
+                    OrdType::Limit | OrdType::StopLimit => "Limit".to_string(),
+                    OrdType::Market | OrdType::StopMarket => "Market".to_string(),
                     OrdType::Unsupported => return Err(HandleError::InvalidArg("order_type")),
                 }
             }),
@@ -147,12 +149,32 @@ impl OrderManager {
                     TimeInForce::GTX => "PostOnly".to_string(),
                     TimeInForce::FOK => "FOK".to_string(),
                     TimeInForce::IOC => "IOC".to_string(),
-                    TimeInForce::Unsupported => {
+                    TimeInForce::GTD | TimeInForce::Unsupported => {
                         return Err(HandleError::InvalidArg("time_in_force"));
                     }
                 }
             }),
             order_link_id: format!("{}{}{}", self.prefix, rand_id, order.order_id),
+            trigger_price: order.order_type.is_stop().then(|| {
+                format!(
+                    "{:.prec$}",
+                    order.trigger_price_tick as f64 * order.tick_size,
+                    prec = price_prec
+                )
+            }),
+            // A buy stop is triggered by the price rising and a sell stop by the price falling.
+            trigger_direction: order.order_type.is_stop().then_some(match order.side {
+                Side::Buy => 1,
+                _ => 2,
+            }),
+            reduce_only: order.reduce_only.then_some(true),
+            position_idx: match self.position_mode {
+                PositionMode::OneWay => None,
+                PositionMode::Hedge => match order.hedge_leg() {
+                    Side::Buy => Some(1),
+                    _ => Some(2),
+                },
+            },
         };
         match self.orders.entry(order.order_id) {
             Entry::Occupied(_) => {
@@ -184,6 +206,10 @@ impl OrderManager {
             category: category.to_string(),
             time_in_force: None,
             order_link_id: order_link_id.clone(),
+            trigger_price: None,
+            trigger_direction: None,
+            reduce_only: None,
+            position_idx: None,
         };
         Ok(bybit_order)
     }
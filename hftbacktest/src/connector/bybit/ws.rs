@@ -26,6 +26,7 @@ use crate::{
                 TradeStreamMsg,
             },
             ordermanager::{HandleError, OrderManagerWrapper},
+            position_event,
             BybitError,
         },
         util::{gen_random_string, sign_hmac_sha256},
@@ -301,12 +302,7 @@ async fn handle_private_stream(
             debug!(?data, "Position");
             for item in data.data {
                 let asset = assets.get(&item.symbol).ok_or(HandleError::AssetNotFound)?;
-                ev_tx
-                    .send(LiveEvent::Position {
-                        asset_no: asset.asset_no,
-                        qty: item.size,
-                    })
-                    .unwrap();
+                ev_tx.send(position_event(asset.asset_no, &item)).unwrap();
             }
         }
         PrivateStreamMsg::Topic(PrivateStreamTopicMsg::Execution(data)) => {
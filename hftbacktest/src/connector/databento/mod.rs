@@ -0,0 +1,232 @@
+use std::{collections::HashMap, sync::mpsc::Sender, time::Duration};
+
+use ::databento::dbn::{SType, Schema};
+use thiserror::Error;
+use tracing::error;
+
+use crate::{
+    connector::{databento::stream::connect, Connector},
+    live::Asset,
+    types::{BuildError, ErrorKind, LiveError, LiveEvent},
+};
+
+mod stream;
+
+/// The Databento schema of the market data to subscribe.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BookSchema {
+    /// Market-by-order, which carries every order event. The local market depth is built by
+    /// aggregating the orders by price level. Since the live subscription doesn't carry the
+    /// resting orders at the time of the subscription, the market depth is incomplete until the
+    /// venue sends a book clear followed by the full book, or until those orders leave the book.
+    Mbo,
+    /// Market-by-price with the top of the book.
+    Mbp1,
+    /// Market-by-price with the top 10 levels of the book.
+    #[default]
+    Mbp10,
+}
+
+impl BookSchema {
+    fn schema(self) -> Schema {
+        match self {
+            BookSchema::Mbo => Schema::Mbo,
+            BookSchema::Mbp1 => Schema::Mbp1,
+            BookSchema::Mbp10 => Schema::Mbp10,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DatabentoError {
+    #[error("client error: {0}")]
+    ClientError(#[from] ::databento::Error),
+    #[error("dbn error: {0}")]
+    DbnError(#[from] ::databento::dbn::Error),
+}
+
+/// Databento live market data connector [`Databento`] builder.
+///
+/// This is a data-only connector: it publishes the market depth and the trades of the subscribed
+/// instruments, but it cannot submit orders, and [`LiveBot`](`crate::live::LiveBot`) rejects the
+/// order requests for its assets. This allows a strategy to trade on one venue while using
+/// another venue's data, by registering this connector alongside a trading connector.
+///
+/// The symbol of an asset is interpreted according to the symbology type, which is the raw
+/// symbol of the venue, such as `ESZ4`, by default. The prices and quantities are converted from
+/// Databento's fixed-point prices and integer sizes.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::{
+///     connector::databento::{BookSchema, Databento},
+///     live::LiveBot,
+///     prelude::HashMapMarketDepth,
+/// };
+///
+/// let databento = Databento::builder()
+///     .api_key("apikey")
+///     .dataset("GLBX.MDP3")
+///     .schema(BookSchema::Mbp10)
+///     .build()
+///     .unwrap();
+///
+/// // Register a trading connector alongside to trade on another venue while using this data.
+/// let hbt = LiveBot::builder()
+///     .register("databento", databento)
+///     .add("databento", "ESZ4", 0.25, 1.0)
+///     .depth(|asset| HashMapMarketDepth::new(asset.tick_size, asset.lot_size))
+///     .build()
+///     .unwrap();
+/// ```
+pub struct DatabentoBuilder {
+    api_key: String,
+    dataset: String,
+    schema: BookSchema,
+    stype_in: SType,
+}
+
+impl DatabentoBuilder {
+    /// Sets the API key.
+    pub fn api_key(self, api_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the dataset, such as `GLBX.MDP3` or `XNAS.ITCH`.
+    pub fn dataset(self, dataset: &str) -> Self {
+        Self {
+            dataset: dataset.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the schema of the market data. The trades are taken from the trade actions of the
+    /// same schema. The default value is [`BookSchema::Mbp10`].
+    pub fn schema(self, schema: BookSchema) -> Self {
+        Self { schema, ..self }
+    }
+
+    /// Sets the symbology type of the asset symbols, such as [`SType::Parent`] or
+    /// [`SType::Continuous`]. The default value is [`SType::RawSymbol`].
+    pub fn stype_in(self, stype_in: SType) -> Self {
+        Self { stype_in, ..self }
+    }
+
+    /// Builds [`Databento`] connector.
+    pub fn build(self) -> Result<Databento, BuildError> {
+        if self.api_key.is_empty() {
+            return Err(BuildError::BuilderIncomplete("api_key"));
+        }
+        if self.dataset.is_empty() {
+            return Err(BuildError::BuilderIncomplete("dataset"));
+        }
+        Ok(Databento {
+            api_key: self.api_key,
+            dataset: self.dataset,
+            schema: self.schema,
+            stype_in: self.stype_in,
+            assets: Default::default(),
+        })
+    }
+}
+
+/// A data-only connector that receives the market data from Databento's live API.
+pub struct Databento {
+    api_key: String,
+    dataset: String,
+    schema: BookSchema,
+    stype_in: SType,
+    assets: HashMap<String, Asset>,
+}
+
+impl Databento {
+    pub fn builder() -> DatabentoBuilder {
+        DatabentoBuilder {
+            api_key: "".to_string(),
+            dataset: "".to_string(),
+            schema: Default::default(),
+            stype_in: SType::RawSymbol,
+        }
+    }
+}
+
+impl Connector for Databento {
+    fn add(
+        &mut self,
+        asset_no: usize,
+        symbol: String,
+        tick_size: f64,
+        lot_size: f64,
+    ) -> Result<(), anyhow::Error> {
+        let asset_info = Asset {
+            asset_no,
+            symbol: symbol.clone(),
+            tick_size,
+            lot_size,
+        };
+        self.assets.insert(symbol, asset_info);
+        Ok(())
+    }
+
+    fn run(&mut self, ev_tx: Sender<LiveEvent>) -> Result<(), anyhow::Error> {
+        let api_key = self.api_key.clone();
+        let dataset = self.dataset.clone();
+        let schema = self.schema;
+        let stype_in = self.stype_in;
+        let assets = self.assets.clone();
+        let _ = tokio::spawn(async move {
+            let mut error_count = 0;
+            loop {
+                if error_count > 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                match connect(
+                    &api_key,
+                    &dataset,
+                    schema,
+                    stype_in,
+                    ev_tx.clone(),
+                    assets.clone(),
+                )
+                .await
+                {
+                    // An invalid API key cannot be recovered by reconnecting.
+                    Err(DatabentoError::ClientError(::databento::Error::Auth(msg))) => {
+                        error!(%msg, "Authentication failed.");
+                        ev_tx
+                            .send(LiveEvent::Error(LiveError::with(
+                                ErrorKind::CriticalConnectionError,
+                                DatabentoError::ClientError(::databento::Error::Auth(msg)),
+                            )))
+                            .unwrap();
+                    }
+                    Err(error) => {
+                        error!(?error, "A connection error occurred.");
+                        ev_tx
+                            .send(LiveEvent::Error(LiveError::with(
+                                ErrorKind::ConnectionInterrupted,
+                                error,
+                            )))
+                            .unwrap();
+                    }
+                    Ok(()) => {
+                        ev_tx
+                            .send(LiveEvent::Error(LiveError::new(
+                                ErrorKind::ConnectionInterrupted,
+                            )))
+                            .unwrap();
+                    }
+                }
+                error_count += 1;
+            }
+        });
+        Ok(())
+    }
+
+    fn order_entry(&self) -> bool {
+        false
+    }
+}
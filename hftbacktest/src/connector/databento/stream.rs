@@ -0,0 +1,431 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::mpsc::Sender,
+};
+
+use ::databento::{
+    dbn::{
+        BidAskPair,
+        ErrorMsg,
+        MboMsg,
+        Mbp10Msg,
+        Mbp1Msg,
+        Record,
+        SType,
+        SymbolMappingMsg,
+        SystemMsg,
+        FIXED_PRICE_SCALE,
+        UNDEF_PRICE,
+    },
+    live::Subscription,
+    LiveClient,
+};
+use chrono::Utc;
+use tracing::{debug, error};
+
+use crate::{
+    connector::databento::{BookSchema, DatabentoError},
+    live::Asset,
+    types::{
+        Event,
+        LiveEvent,
+        LOCAL_ASK_DEPTH_EVENT,
+        LOCAL_BID_DEPTH_EVENT,
+        LOCAL_BUY_TRADE_EVENT,
+        LOCAL_DEPTH_CLEAR_EVENT,
+        LOCAL_SELL_TRADE_EVENT,
+        LOCAL_TRADE_EVENT,
+    },
+};
+
+fn to_px(price: i64) -> f64 {
+    price as f64 / FIXED_PRICE_SCALE as f64
+}
+
+fn event(ev: u64, exch_ts: u64, px: f64, qty: f64) -> Event {
+    Event {
+        ev,
+        exch_ts: exch_ts as i64,
+        local_ts: Utc::now().timestamp_nanos_opt().unwrap(),
+        order_id: 0,
+        px,
+        qty,
+        ival: 0,
+        fval: 0.0,
+    }
+}
+
+fn trade_event(side: i8, exch_ts: u64, price: i64, size: u32) -> Event {
+    // The side of a trade is the aggressor's side.
+    let ev = match side as u8 {
+        b'B' => LOCAL_BUY_TRADE_EVENT,
+        b'A' => LOCAL_SELL_TRADE_EVENT,
+        _ => LOCAL_TRADE_EVENT,
+    };
+    event(ev, exch_ts, to_px(price), size as f64)
+}
+
+/// The local state of an instrument's book, which is used to convert the records into the
+/// changes of the price levels.
+#[derive(Default)]
+struct Book {
+    bids: HashMap<i64, u64>,
+    asks: HashMap<i64, u64>,
+    /// The resting orders, consisting of the side, the price, and the size, for MBO.
+    orders: HashMap<u64, (u8, i64, u32)>,
+}
+
+impl Book {
+    fn clear(&mut self, exch_ts: u64, events: &mut Vec<Event>) {
+        self.bids.clear();
+        self.asks.clear();
+        self.orders.clear();
+        events.push(event(LOCAL_DEPTH_CLEAR_EVENT, exch_ts, 0.0, 0.0));
+    }
+
+    /// Applies the change of an order's size to its price level.
+    fn update_level(
+        &mut self,
+        side: u8,
+        price: i64,
+        add: u32,
+        sub: u32,
+        exch_ts: u64,
+        events: &mut Vec<Event>,
+    ) {
+        let (levels, ev) = match side {
+            b'B' => (&mut self.bids, LOCAL_BID_DEPTH_EVENT),
+            b'A' => (&mut self.asks, LOCAL_ASK_DEPTH_EVENT),
+            _ => return,
+        };
+        let qty = levels.entry(price).or_insert(0);
+        *qty = (*qty + add as u64).saturating_sub(sub as u64);
+        let qty = *qty;
+        if qty == 0 {
+            levels.remove(&price);
+        }
+        events.push(event(ev, exch_ts, to_px(price), qty as f64));
+    }
+
+    fn handle_mbo(&mut self, msg: &MboMsg, events: &mut Vec<Event>) {
+        let exch_ts = msg.hd.ts_event;
+        match msg.action as u8 {
+            b'A' => {
+                if let Entry::Vacant(entry) = self.orders.entry(msg.order_id) {
+                    entry.insert((msg.side as u8, msg.price, msg.size));
+                    self.update_level(msg.side as u8, msg.price, msg.size, 0, exch_ts, events);
+                }
+            }
+            b'M' => {
+                // An order resting before the subscription is unknown, and is added when it's
+                // modified.
+                if let Some((side, price, size)) = self.orders.remove(&msg.order_id) {
+                    self.update_level(side, price, 0, size, exch_ts, events);
+                }
+                self.orders
+                    .insert(msg.order_id, (msg.side as u8, msg.price, msg.size));
+                self.update_level(msg.side as u8, msg.price, msg.size, 0, exch_ts, events);
+            }
+            b'C' => {
+                // A cancel can partially cancel an order.
+                if let Some((side, price, size)) = self.orders.get_mut(&msg.order_id) {
+                    let (side, price) = (*side, *price);
+                    let canceled = msg.size.min(*size);
+                    *size -= canceled;
+                    if *size == 0 {
+                        self.orders.remove(&msg.order_id);
+                    }
+                    self.update_level(side, price, 0, canceled, exch_ts, events);
+                }
+            }
+            b'T' => {
+                events.push(trade_event(msg.side, exch_ts, msg.price, msg.size));
+            }
+            b'R' => {
+                self.clear(exch_ts, events);
+            }
+            // A fill doesn't change the book by itself since the filled quantity is removed from
+            // the resting order by the following cancel or modify.
+            _ => {}
+        }
+    }
+
+    /// Handles an MBP record. `trade` consists of the side, the price, and the size of the record,
+    /// which describe the trade if the action is a trade.
+    fn handle_mbp(
+        &mut self,
+        exch_ts: u64,
+        action: i8,
+        trade: (i8, i64, u32),
+        levels: &[BidAskPair],
+        events: &mut Vec<Event>,
+    ) {
+        match action as u8 {
+            b'R' => {
+                self.clear(exch_ts, events);
+                return;
+            }
+            b'T' => {
+                let (side, price, size) = trade;
+                events.push(trade_event(side, exch_ts, price, size));
+            }
+            _ => {}
+        }
+
+        // Each record carries the levels after the event, which are compared with the previous
+        // ones to find the changed levels.
+        let mut bids = HashMap::new();
+        let mut asks = HashMap::new();
+        for level in levels {
+            if level.bid_px != UNDEF_PRICE && level.bid_sz > 0 {
+                bids.insert(level.bid_px, level.bid_sz as u64);
+            }
+            if level.ask_px != UNDEF_PRICE && level.ask_sz > 0 {
+                asks.insert(level.ask_px, level.ask_sz as u64);
+            }
+        }
+        for (ev, curr, prev) in [
+            (LOCAL_BID_DEPTH_EVENT, &bids, &self.bids),
+            (LOCAL_ASK_DEPTH_EVENT, &asks, &self.asks),
+        ] {
+            for (&px, &qty) in curr {
+                if prev.get(&px) != Some(&qty) {
+                    events.push(event(ev, exch_ts, to_px(px), qty as f64));
+                }
+            }
+            for &px in prev.keys() {
+                if !curr.contains_key(&px) {
+                    events.push(event(ev, exch_ts, to_px(px), 0.0));
+                }
+            }
+        }
+        self.bids = bids;
+        self.asks = asks;
+    }
+}
+
+pub async fn connect(
+    api_key: &str,
+    dataset: &str,
+    schema: BookSchema,
+    stype_in: SType,
+    ev_tx: Sender<LiveEvent>,
+    assets: HashMap<String, Asset>,
+) -> Result<(), DatabentoError> {
+    let mut client = LiveClient::builder()
+        .key(api_key)?
+        .dataset(dataset)
+        .build()
+        .await?;
+    client
+        .subscribe(
+            &Subscription::builder()
+                .symbols(assets.keys().cloned().collect::<Vec<_>>())
+                .schema(schema.schema())
+                .stype_in(stype_in)
+                .build(),
+        )
+        .await?;
+    client.start().await?;
+
+    // The instrument ids are assigned by Databento, and are delivered through the symbol mapping
+    // messages before the market data of the instruments. The events are kept with each
+    // instrument's book until the last record of an event.
+    let mut books: HashMap<u32, (usize, Book, Vec<Event>)> = HashMap::new();
+    while let Some(rec) = client.next_record().await? {
+        if let Some(msg) = rec.get::<SymbolMappingMsg>() {
+            let symbol = msg.stype_in_symbol()?;
+            match assets.get(symbol) {
+                Some(asset) => {
+                    debug!(%symbol, instrument_id = msg.hd.instrument_id, "Symbol mapping");
+                    books.insert(
+                        msg.hd.instrument_id,
+                        (asset.asset_no, Default::default(), Vec::new()),
+                    );
+                }
+                None => {
+                    error!(%symbol, "Received a symbol mapping for an unknown symbol.");
+                }
+            }
+            continue;
+        }
+        if let Some(msg) = rec.get::<ErrorMsg>() {
+            // The gateway errors, such as a symbol that cannot be resolved, don't end the
+            // session.
+            error!(error = msg.err()?, "Gateway error");
+            continue;
+        }
+        if let Some(msg) = rec.get::<SystemMsg>() {
+            debug!(msg = msg.msg()?, "System");
+            continue;
+        }
+
+        let Some((asset_no, book, batch)) = books.get_mut(&rec.header().instrument_id) else {
+            continue;
+        };
+        let record_flags = if let Some(msg) = rec.get::<MboMsg>() {
+            book.handle_mbo(msg, batch);
+            msg.flags
+        } else if let Some(msg) = rec.get::<Mbp10Msg>() {
+            book.handle_mbp(
+                msg.hd.ts_event,
+                msg.action,
+                (msg.side, msg.price, msg.size),
+                &msg.levels,
+                batch,
+            );
+            msg.flags
+        } else if let Some(msg) = rec.get::<Mbp1Msg>() {
+            book.handle_mbp(
+                msg.hd.ts_event,
+                msg.action,
+                (msg.side, msg.price, msg.size),
+                &msg.levels,
+                batch,
+            );
+            msg.flags
+        } else {
+            continue;
+        };
+
+        // The records of a single event are delivered together, and the last one is flagged.
+        if record_flags.is_last() && !batch.is_empty() {
+            ev_tx
+                .send(LiveEvent::FeedBatch {
+                    asset_no: *asset_no,
+                    events: std::mem::take(batch),
+                })
+                .unwrap();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ::databento::dbn::{BidAskPair, MboMsg, FIXED_PRICE_SCALE, UNDEF_PRICE};
+
+    use super::Book;
+    use crate::types::{
+        Event,
+        LOCAL_ASK_DEPTH_EVENT,
+        LOCAL_BID_DEPTH_EVENT,
+        LOCAL_BUY_TRADE_EVENT,
+        LOCAL_DEPTH_CLEAR_EVENT,
+        LOCAL_SELL_TRADE_EVENT,
+    };
+
+    fn mbo(action: u8, side: u8, order_id: u64, price: f64, size: u32) -> MboMsg {
+        let mut msg = MboMsg::default();
+        msg.hd.ts_event = 1;
+        msg.action = action as i8;
+        msg.side = side as i8;
+        msg.order_id = order_id;
+        msg.price = (price * FIXED_PRICE_SCALE as f64) as i64;
+        msg.size = size;
+        msg
+    }
+
+    fn level(bid: Option<(f64, u32)>, ask: Option<(f64, u32)>) -> BidAskPair {
+        let mut level = BidAskPair::default();
+        if let Some((px, sz)) = bid {
+            level.bid_px = (px * FIXED_PRICE_SCALE as f64) as i64;
+            level.bid_sz = sz;
+        }
+        if let Some((px, sz)) = ask {
+            level.ask_px = (px * FIXED_PRICE_SCALE as f64) as i64;
+            level.ask_sz = sz;
+        }
+        level
+    }
+
+    /// Asserts the emitted changes regardless of their order, since the changed levels of an MBP
+    /// record are found by iterating over the maps.
+    fn assert_changes(events: &mut Vec<Event>, mut expected: Vec<(u64, f64, f64)>) {
+        let mut changes: Vec<_> = events.drain(..).map(|ev| (ev.ev, ev.px, ev.qty)).collect();
+        changes.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(changes, expected);
+    }
+
+    #[test]
+    fn test_mbo_to_events() {
+        let mut book = Book::default();
+        let mut events = Vec::new();
+
+        book.handle_mbo(&mbo(b'A', b'B', 1, 100.0, 3), &mut events);
+        book.handle_mbo(&mbo(b'A', b'B', 2, 100.0, 2), &mut events);
+        book.handle_mbo(&mbo(b'A', b'A', 3, 101.0, 4), &mut events);
+        assert_changes(
+            &mut events,
+            vec![
+                (LOCAL_BID_DEPTH_EVENT, 100.0, 3.0),
+                (LOCAL_BID_DEPTH_EVENT, 100.0, 5.0),
+                (LOCAL_ASK_DEPTH_EVENT, 101.0, 4.0),
+            ],
+        );
+
+        // A partial cancel reduces the level by the canceled size.
+        book.handle_mbo(&mbo(b'C', b'B', 1, 100.0, 1), &mut events);
+        assert_changes(&mut events, vec![(LOCAL_BID_DEPTH_EVENT, 100.0, 4.0)]);
+
+        // A modify moves the order to the new price.
+        book.handle_mbo(&mbo(b'M', b'B', 2, 99.0, 2), &mut events);
+        assert_changes(
+            &mut events,
+            vec![
+                (LOCAL_BID_DEPTH_EVENT, 99.0, 2.0),
+                (LOCAL_BID_DEPTH_EVENT, 100.0, 2.0),
+            ],
+        );
+
+        // The side of a trade is the aggressor's side.
+        book.handle_mbo(&mbo(b'T', b'A', 0, 100.0, 1), &mut events);
+        book.handle_mbo(&mbo(b'T', b'B', 0, 101.0, 1), &mut events);
+        assert_changes(
+            &mut events,
+            vec![
+                (LOCAL_BUY_TRADE_EVENT, 101.0, 1.0),
+                (LOCAL_SELL_TRADE_EVENT, 100.0, 1.0),
+            ],
+        );
+
+        book.handle_mbo(&mbo(b'R', 0, 0, 0.0, 0), &mut events);
+        assert_changes(&mut events, vec![(LOCAL_DEPTH_CLEAR_EVENT, 0.0, 0.0)]);
+        assert!(book.orders.is_empty());
+    }
+
+    #[test]
+    fn test_mbp_to_events() {
+        let mut book = Book::default();
+        let mut events = Vec::new();
+
+        let levels = [
+            level(Some((100.0, 3)), Some((101.0, 4))),
+            level(Some((99.0, 1)), None),
+        ];
+        book.handle_mbp(1, b'A' as i8, (0, UNDEF_PRICE, 0), &levels, &mut events);
+        assert_changes(
+            &mut events,
+            vec![
+                (LOCAL_BID_DEPTH_EVENT, 99.0, 1.0),
+                (LOCAL_BID_DEPTH_EVENT, 100.0, 3.0),
+                (LOCAL_ASK_DEPTH_EVENT, 101.0, 4.0),
+            ],
+        );
+
+        // Only the changed levels are emitted, and a level that disappears is emitted with a zero
+        // quantity.
+        let levels = [level(Some((100.0, 2)), Some((101.0, 4))), level(None, None)];
+        let trade = (b'A' as i8, (100.0 * FIXED_PRICE_SCALE as f64) as i64, 1);
+        book.handle_mbp(2, b'T' as i8, trade, &levels, &mut events);
+        assert_changes(
+            &mut events,
+            vec![
+                (LOCAL_BID_DEPTH_EVENT, 99.0, 0.0),
+                (LOCAL_BID_DEPTH_EVENT, 100.0, 2.0),
+                (LOCAL_SELL_TRADE_EVENT, 100.0, 1.0),
+            ],
+        );
+    }
+}
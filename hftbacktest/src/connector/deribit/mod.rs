@@ -0,0 +1,331 @@
+use std::{
+    collections::HashMap,
+    sync::{mpsc::Sender, Arc, Mutex},
+    time::Duration,
+};
+
+use thiserror::Error;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tracing::error;
+
+use crate::{
+    connector::{
+        deribit::{
+            ordermanager::{OrderManager, OrderManagerWrapper},
+            ws::{connect_private, connect_public, OrderOp},
+        },
+        Connector,
+    },
+    live::Asset,
+    types::{BuildError, ErrorKind, LiveError, LiveEvent, Order, Side},
+};
+
+mod msg;
+mod ordermanager;
+mod ws;
+
+#[derive(Clone)]
+pub enum Endpoint {
+    Public,
+    Testnet,
+    Custom(String),
+}
+
+impl From<String> for Endpoint {
+    fn from(value: String) -> Self {
+        Endpoint::Custom(value)
+    }
+}
+
+impl From<&'static str> for Endpoint {
+    fn from(value: &'static str) -> Self {
+        Endpoint::Custom(value.to_string())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DeribitError {
+    #[error("asset not found")]
+    AssetNotFound,
+    #[error("auth error {0}: {1}")]
+    AuthError(i64, String),
+    #[error("order error {0}: {1}")]
+    OrderError(i64, String),
+}
+
+/// Deribit connector [`Deribit`] builder.
+///
+/// Both the market data and the order entry go through the JSON-RPC over WebSocket API. The
+/// symbol of an asset is the instrument name, such as `BTC-PERPETUAL` or `BTC-27DEC24-60000-C`,
+/// and the quantities are the amounts as Deribit defines them, which are in USD for the inverse
+/// perpetuals and futures, and in the base currency for the options. Since Deribit has only the
+/// net position, [`PositionMode::Hedge`](crate::types::PositionMode::Hedge) is not supported.
+///
+/// The tick size of an option varies with its price. The tick size of the asset should be the
+/// smallest one, and the order prices should be aligned with the tick size that applies.
+pub struct DeribitBuilder {
+    url: String,
+    client_id: String,
+    client_secret: String,
+    order_prefix: String,
+}
+
+impl DeribitBuilder {
+    /// Sets the WebSocket API endpoint url, which is used for both the market data and the order
+    /// entry.
+    pub fn endpoint<E: Into<Endpoint>>(self, endpoint: E) -> Self {
+        match endpoint.into() {
+            Endpoint::Public => Self {
+                url: "wss://www.deribit.com/ws/api/v2".to_string(),
+                ..self
+            },
+            Endpoint::Testnet => Self {
+                url: "wss://test.deribit.com/ws/api/v2".to_string(),
+                ..self
+            },
+            Endpoint::Custom(url) => Self { url, ..self },
+        }
+    }
+
+    /// Sets the client id of the API key
+    pub fn client_id(self, client_id: &str) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the client secret of the API key
+    pub fn client_secret(self, client_secret: &str) -> Self {
+        Self {
+            client_secret: client_secret.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the order prefix, which is used to differentiate the orders submitted through this
+    /// connector. The orders are labeled with the prefix.
+    pub fn order_prefix(self, order_prefix: &str) -> Self {
+        Self {
+            order_prefix: order_prefix.to_string(),
+            ..self
+        }
+    }
+
+    /// Builds [`Deribit`] connector.
+    pub fn build(self) -> Result<Deribit, BuildError> {
+        if self.url.is_empty() {
+            return Err(BuildError::BuilderIncomplete("url"));
+        }
+        if self.client_id.is_empty() {
+            return Err(BuildError::BuilderIncomplete("client_id"));
+        }
+        if self.client_secret.is_empty() {
+            return Err(BuildError::BuilderIncomplete("client_secret"));
+        }
+
+        if self.order_prefix.len() > 16 {
+            panic!("order prefix length should be not greater than 16.");
+        }
+        Ok(Deribit {
+            url: self.url,
+            assets: Default::default(),
+            inv_assets: Default::default(),
+            client_id: self.client_id,
+            client_secret: self.client_secret,
+            order_tx: None,
+            order_man: Arc::new(Mutex::new(OrderManager::new(&self.order_prefix))),
+        })
+    }
+}
+
+pub struct Deribit {
+    url: String,
+    assets: HashMap<String, Asset>,
+    inv_assets: HashMap<usize, Asset>,
+    client_id: String,
+    client_secret: String,
+    order_tx: Option<UnboundedSender<OrderOp>>,
+    order_man: OrderManagerWrapper,
+}
+
+impl Deribit {
+    pub fn builder() -> DeribitBuilder {
+        DeribitBuilder {
+            url: "".to_string(),
+            client_id: "".to_string(),
+            client_secret: "".to_string(),
+            order_prefix: "".to_string(),
+        }
+    }
+}
+
+impl Connector for Deribit {
+    fn add(
+        &mut self,
+        asset_no: usize,
+        symbol: String,
+        tick_size: f64,
+        lot_size: f64,
+    ) -> Result<(), anyhow::Error> {
+        let asset_info = Asset {
+            asset_no,
+            symbol: symbol.clone(),
+            tick_size,
+            lot_size,
+        };
+        self.assets.insert(symbol, asset_info.clone());
+        self.inv_assets.insert(asset_no, asset_info);
+        Ok(())
+    }
+
+    fn run(&mut self, ev_tx: Sender<LiveEvent>) -> Result<(), anyhow::Error> {
+        // Connects to the stream for the market data.
+        let url_public = self.url.clone();
+        let ev_tx_public = ev_tx.clone();
+        let assets_public = self.assets.clone();
+        let client_id_public = self.client_id.clone();
+        let client_secret_public = self.client_secret.clone();
+        let _ = tokio::spawn(async move {
+            let mut error_count = 0;
+            loop {
+                if error_count > 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                if let Err(error) = connect_public(
+                    &url_public,
+                    &client_id_public,
+                    &client_secret_public,
+                    ev_tx_public.clone(),
+                    assets_public.clone(),
+                )
+                .await
+                {
+                    error!(?error, "A connection error occurred.");
+                    ev_tx_public
+                        .send(LiveEvent::Error(LiveError::with(
+                            ErrorKind::ConnectionInterrupted,
+                            error,
+                        )))
+                        .unwrap();
+                } else {
+                    ev_tx_public
+                        .send(LiveEvent::Error(LiveError::new(
+                            ErrorKind::ConnectionInterrupted,
+                        )))
+                        .unwrap();
+                }
+                error_count += 1;
+            }
+        });
+
+        // Connects to the stream for the order entry, and the position and order data.
+        let url_private = self.url.clone();
+        let ev_tx_private = ev_tx.clone();
+        let assets_private = self.assets.clone();
+        let client_id_private = self.client_id.clone();
+        let client_secret_private = self.client_secret.clone();
+        let order_man_private = self.order_man.clone();
+        let (order_tx, mut order_rx) = unbounded_channel();
+        self.order_tx = Some(order_tx);
+        let _ = tokio::spawn(async move {
+            let mut error_count = 0;
+            loop {
+                if error_count > 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+
+                // All orders are canceled once the connection is authorized, in order to start
+                // with the clean state.
+                {
+                    let mut order_manager_ = order_man_private.lock().unwrap();
+                    let orders = order_manager_.clear_orders();
+                    for (asset_no, order) in orders {
+                        ev_tx_private
+                            .send(LiveEvent::Order { asset_no, order })
+                            .unwrap();
+                    }
+                }
+
+                if let Err(error) = connect_private(
+                    &url_private,
+                    &client_id_private,
+                    &client_secret_private,
+                    ev_tx_private.clone(),
+                    assets_private.clone(),
+                    &mut order_rx,
+                    order_man_private.clone(),
+                )
+                .await
+                {
+                    error!(?error, "A connection error occurred.");
+                    ev_tx_private
+                        .send(LiveEvent::Error(LiveError::with(
+                            ErrorKind::ConnectionInterrupted,
+                            error,
+                        )))
+                        .unwrap();
+                } else {
+                    ev_tx_private
+                        .send(LiveEvent::Error(LiveError::new(
+                            ErrorKind::ConnectionInterrupted,
+                        )))
+                        .unwrap();
+                }
+                error_count += 1;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn submit(
+        &self,
+        asset_no: usize,
+        order: Order,
+        _tx: Sender<LiveEvent>,
+    ) -> Result<(), anyhow::Error> {
+        let asset_info = self
+            .inv_assets
+            .get(&asset_no)
+            .ok_or(DeribitError::AssetNotFound)?;
+        let method = match order.side {
+            Side::Buy => "private/buy",
+            _ => "private/sell",
+        };
+        let mut order_man = self.order_man.lock().unwrap();
+        let deribit_order = order_man.new_order(&asset_info.symbol, asset_no, order)?;
+        self.order_tx.as_ref().unwrap().send(OrderOp::Submit {
+            method,
+            deribit_order,
+        })?;
+        Ok(())
+    }
+
+    fn cancel(
+        &self,
+        _asset_no: usize,
+        order: Order,
+        _tx: Sender<LiveEvent>,
+    ) -> Result<(), anyhow::Error> {
+        let mut order_man = self.order_man.lock().unwrap();
+        let label = order_man.cancel_order(order.order_id)?;
+        self.order_tx
+            .as_ref()
+            .unwrap()
+            .send(OrderOp::Cancel { label })?;
+        Ok(())
+    }
+
+    fn cancel_all(&self, asset_no: usize, _tx: Sender<LiveEvent>) -> Result<(), anyhow::Error> {
+        let asset_info = self
+            .inv_assets
+            .get(&asset_no)
+            .ok_or(DeribitError::AssetNotFound)?;
+        // The cancellation of each order is delivered through the user orders channel.
+        self.order_tx.as_ref().unwrap().send(OrderOp::CancelAll {
+            instrument_name: asset_info.symbol.clone(),
+        })?;
+        Ok(())
+    }
+}
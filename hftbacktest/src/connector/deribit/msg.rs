@@ -0,0 +1,174 @@
+use std::{fmt, fmt::Debug};
+
+use serde::{
+    de,
+    de::{Error, Unexpected, Visitor},
+    Deserialize,
+    Deserializer,
+    Serialize,
+};
+
+use crate::types::Side;
+
+struct SideVisitor;
+
+impl<'de> Visitor<'de> for SideVisitor {
+    type Value = Side;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string containing \"buy\" or \"sell\"")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match s {
+            "buy" => Ok(Side::Buy),
+            "sell" => Ok(Side::Sell),
+            s => Err(Error::invalid_value(Unexpected::Other(s), &"buy or sell")),
+        }
+    }
+}
+
+fn from_str_to_side<'de, D>(deserializer: D) -> Result<Side, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(SideVisitor)
+}
+
+#[derive(Serialize, Debug)]
+pub struct Request<T>
+where
+    T: Serialize + Debug,
+{
+    pub jsonrpc: &'static str,
+    pub id: u64,
+    pub method: String,
+    pub params: T,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum StreamMsg {
+    Response(Response),
+    Notification(Notification),
+}
+
+/// A response to a JSON-RPC request, which has either the result or the error.
+#[derive(Deserialize, Debug)]
+pub struct Response {
+    pub id: u64,
+    #[serde(default)]
+    pub result: Option<serde_json::Value>,
+    #[serde(default)]
+    pub error: Option<RpcError>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// A notification of a subscribed channel or a heartbeat.
+#[derive(Deserialize, Debug)]
+pub struct Notification {
+    pub method: String,
+    pub params: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Subscription {
+    pub channel: String,
+    pub data: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Heartbeat {
+    /// `heartbeat` or `test_request`, which should be answered with `public/test`.
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OrderBook {
+    /// `snapshot` or `change`.
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub timestamp: i64,
+    pub instrument_name: String,
+    #[serde(default)]
+    pub prev_change_id: Option<i64>,
+    pub change_id: i64,
+    /// Each level consists of the action, which is one of `new`, `change`, and `delete`, the
+    /// price, and the amount.
+    pub bids: Vec<(String, f64, f64)>,
+    pub asks: Vec<(String, f64, f64)>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Trade {
+    pub instrument_name: String,
+    pub price: f64,
+    pub amount: f64,
+    #[serde(deserialize_with = "from_str_to_side")]
+    pub direction: Side,
+    pub timestamp: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserOrder {
+    #[serde(default)]
+    pub label: String,
+    /// `open`, `filled`, `rejected`, `cancelled`, `untriggered`, or `triggered`.
+    pub order_state: String,
+    pub amount: f64,
+    pub filled_amount: f64,
+    pub last_update_timestamp: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserTrade {
+    pub instrument_name: String,
+    #[serde(default)]
+    pub label: String,
+    pub price: f64,
+    pub amount: f64,
+    pub timestamp: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Position {
+    pub instrument_name: String,
+    /// The signed position size, which is in USD for the inverse perpetuals and futures, and in
+    /// the base currency for the options and the linear instruments.
+    pub size: f64,
+}
+
+/// The parameters of the `private/buy` and `private/sell` methods.
+#[derive(Serialize, Debug)]
+pub struct Order {
+    pub instrument_name: String,
+    pub amount: f64,
+    #[serde(rename = "type")]
+    pub order_type: String,
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_in_force: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_only: Option<bool>,
+    /// Rejects a post-only order that would take liquidity, instead of repricing it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reject_post_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_price: Option<f64>,
+    /// `last_price`, `mark_price`, or `index_price`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    num::ParseIntError,
+    sync::{Arc, Mutex},
+};
+
+use thiserror::Error;
+
+use crate::{
+    connector::{
+        deribit::msg::{Order as DeribitOrder, UserOrder, UserTrade},
+        util::gen_random_string,
+    },
+    prelude::{get_precision, OrdType, OrderId, Side, TimeInForce},
+    types::{Order, Status, TriggerBy},
+};
+
+pub type OrderManagerWrapper = Arc<Mutex<OrderManager>>;
+
+#[derive(Error, Debug)]
+pub(super) enum HandleError {
+    #[error("order id parse error: {0}")]
+    InvalidOrderId(ParseIntError),
+    #[error("prefix unmatched")]
+    PrefixUnmatched,
+    #[error("order not found")]
+    OrderNotFound,
+    #[error("asset not found")]
+    AssetNotFound,
+    #[error("invalid argument")]
+    InvalidArg(&'static str),
+    #[error("order already exist")]
+    OrderAlreadyExist,
+    #[error("change id gap: expected {0}, got {1:?}")]
+    ChangeIdGap(i64, Option<i64>),
+    #[error("serde: {0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("tokio: {0}")]
+    TokioError(#[source] Box<tokio_tungstenite::tungstenite::Error>),
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for HandleError {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        HandleError::TokioError(Box::new(error))
+    }
+}
+
+pub struct OrderManager {
+    prefix: String,
+    orders: HashMap<OrderId, (usize, String, Order)>,
+}
+
+// Rounds the price to the tick size, so that it is serialized without the floating-point error.
+fn round_price(price_tick: i64, tick_size: f64) -> f64 {
+    format!(
+        "{:.prec$}",
+        price_tick as f64 * tick_size,
+        prec = get_precision(tick_size)
+    )
+    .parse()
+    .unwrap()
+}
+
+impl OrderManager {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            orders: Default::default(),
+        }
+    }
+
+    fn parse_order_id(&self, label: &str) -> Result<OrderId, HandleError> {
+        if !label.starts_with(&self.prefix) || label.len() < self.prefix.len() + 8 {
+            return Err(HandleError::PrefixUnmatched);
+        }
+        label[(self.prefix.len() + 8)..]
+            .parse()
+            .map_err(HandleError::InvalidOrderId)
+    }
+
+    pub fn update_order(&mut self, data: &UserOrder) -> Result<(usize, Order), HandleError> {
+        let order_id = self.parse_order_id(&data.label)?;
+        let (asset_no, _label, order) = self
+            .orders
+            .get_mut(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        order.req = Status::None;
+        order.status = match data.order_state.as_str() {
+            "open" if data.filled_amount > 0.0 => Status::PartiallyFilled,
+            "open" | "untriggered" | "triggered" => Status::New,
+            "filled" => Status::Filled,
+            "cancelled" => Status::Canceled,
+            "rejected" => Status::Expired,
+            _ => Status::Unsupported,
+        };
+        order.leaves_qty = data.amount - data.filled_amount;
+        order.exch_timestamp = data.last_update_timestamp * 1_000_000;
+        let is_active = order.active();
+        if !is_active {
+            let (asset_no, _label, order) = self.orders.remove(&order_id).unwrap();
+            Ok((asset_no, order))
+        } else {
+            Ok((*asset_no, order.clone()))
+        }
+    }
+
+    pub fn update_execution(&mut self, data: &UserTrade) -> Result<(usize, Order), HandleError> {
+        let order_id = self.parse_order_id(&data.label)?;
+        let (asset_no, _label, order) = self
+            .orders
+            .get_mut(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        order.exec_price_tick = (data.price / order.tick_size).round() as i64;
+        order.exec_qty = data.amount;
+        order.exch_timestamp = data.timestamp * 1_000_000;
+        Ok((*asset_no, order.clone()))
+    }
+
+    pub fn new_order(
+        &mut self,
+        instrument_name: &str,
+        asset_no: usize,
+        order: Order,
+    ) -> Result<DeribitOrder, HandleError> {
+        let rand_id = gen_random_string(8);
+        if matches!(order.side, Side::None | Side::Unsupported) {
+            return Err(HandleError::InvalidArg("side"));
+        }
+        let deribit_order = DeribitOrder {
+            instrument_name: instrument_name.to_string(),
+            amount: order.qty,
+            order_type: match order.order_type {
+                OrdType::Limit => "limit".to_string(),
+                OrdType::Market => "market".to_string(),
+                OrdType::StopLimit => "stop_limit".to_string(),
+                OrdType::StopMarket => "stop_market".to_string(),
+                OrdType::Unsupported => return Err(HandleError::InvalidArg("order_type")),
+            },
+            label: format!("{}{}{}", self.prefix, rand_id, order.order_id),
+            price: matches!(order.order_type, OrdType::Limit | OrdType::StopLimit)
+                .then(|| round_price(order.price_tick, order.tick_size)),
+            time_in_force: match order.time_in_force {
+                TimeInForce::GTC | TimeInForce::GTX => None,
+                TimeInForce::FOK => Some("fill_or_kill".to_string()),
+                TimeInForce::IOC => Some("immediate_or_cancel".to_string()),
+                TimeInForce::GTD | TimeInForce::Unsupported => {
+                    return Err(HandleError::InvalidArg("time_in_force"));
+                }
+            },
+            post_only: (order.time_in_force == TimeInForce::GTX).then_some(true),
+            reject_post_only: (order.time_in_force == TimeInForce::GTX).then_some(true),
+            reduce_only: order.reduce_only.then_some(true),
+            trigger_price: order
+                .order_type
+                .is_stop()
+                .then(|| round_price(order.trigger_price_tick, order.tick_size)),
+            trigger: order.order_type.is_stop().then(|| {
+                match order.trigger_by {
+                    TriggerBy::Default | TriggerBy::LastPrice => "last_price",
+                    TriggerBy::MarkPrice => "mark_price",
+                    TriggerBy::IndexPrice => "index_price",
+                }
+                .to_string()
+            }),
+        };
+        match self.orders.entry(order.order_id) {
+            Entry::Occupied(_) => {
+                return Err(HandleError::OrderAlreadyExist);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((asset_no, deribit_order.label.clone(), order));
+            }
+        }
+        Ok(deribit_order)
+    }
+
+    /// Returns the label of the order, by which the order is canceled.
+    pub fn cancel_order(&mut self, order_id: OrderId) -> Result<String, HandleError> {
+        let (_, label, _order) = self
+            .orders
+            .get(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        Ok(label.clone())
+    }
+
+    pub fn update_submit_fail(&mut self, label: &str) -> Result<(usize, Order), HandleError> {
+        let order_id = self.parse_order_id(label)?;
+        let (asset_no, _label, mut order) = self
+            .orders
+            .remove(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        order.req = Status::None;
+        order.status = Status::Expired;
+        Ok((asset_no, order))
+    }
+
+    pub fn update_cancel_fail(&mut self, label: &str) -> Result<(usize, Order), HandleError> {
+        let order_id = self.parse_order_id(label)?;
+        let (asset_no, _label, order) = self
+            .orders
+            .get_mut(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        order.req = Status::None;
+        Ok((*asset_no, order.clone()))
+    }
+
+    pub fn clear_orders(&mut self) -> Vec<(usize, Order)> {
+        let mut values: Vec<(usize, Order)> = Vec::new();
+        values.extend(self.orders.drain().map(|(_, (asset_no, _, mut order))| {
+            order.status = Status::Canceled;
+            (asset_no, order)
+        }));
+        values
+    }
+}
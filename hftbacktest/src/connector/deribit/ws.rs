@@ -0,0 +1,595 @@
+use std::{collections::HashMap, sync::mpsc::Sender, time::Duration};
+
+use chrono::Utc;
+use futures_util::{stream::SplitSink, SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::json;
+use tokio::{net::TcpStream, select, sync::mpsc::UnboundedReceiver, time};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, Message},
+    MaybeTlsStream,
+    WebSocketStream,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    connector::deribit::{
+        msg::{
+            Heartbeat,
+            Notification,
+            Order as DeribitOrder,
+            OrderBook,
+            Position,
+            Request,
+            Response,
+            StreamMsg,
+            Subscription,
+            Trade,
+            UserOrder,
+            UserTrade,
+        },
+        ordermanager::{HandleError, OrderManagerWrapper},
+        DeribitError,
+    },
+    live::Asset,
+    types::{
+        ErrorKind,
+        Event,
+        LiveError,
+        LiveEvent,
+        Side,
+        LOCAL_ASK_DEPTH_EVENT,
+        LOCAL_BID_DEPTH_EVENT,
+        LOCAL_BUY_TRADE_EVENT,
+        LOCAL_DEPTH_CLEAR_EVENT,
+        LOCAL_SELL_TRADE_EVENT,
+    },
+};
+
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+
+// The interval in seconds of the heartbeat, which the server requests to be answered.
+const HEARTBEAT_INTERVAL: u64 = 30;
+
+pub enum OrderOp {
+    /// `private/buy` or `private/sell`.
+    Submit {
+        method: &'static str,
+        deribit_order: DeribitOrder,
+    },
+    Cancel {
+        label: String,
+    },
+    CancelAll {
+        instrument_name: String,
+    },
+}
+
+/// Identifies the request that a response answers.
+enum RequestKind {
+    Auth,
+    Submit(String),
+    Cancel(String),
+    CancelAll,
+    Position,
+    Other,
+}
+
+/// Sends the JSON-RPC requests over the connection, numbering them.
+struct RpcWriter {
+    write: WsSink,
+    next_id: u64,
+}
+
+impl RpcWriter {
+    fn new(write: WsSink) -> Self {
+        Self { write, next_id: 1 }
+    }
+
+    async fn send<T: Serialize + std::fmt::Debug>(
+        &mut self,
+        method: &str,
+        params: T,
+    ) -> Result<u64, HandleError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let req = Request {
+            jsonrpc: "2.0",
+            id,
+            method: method.to_string(),
+            params,
+        };
+        let s = serde_json::to_string(&req)?;
+        self.write.send(Message::Text(s)).await?;
+        Ok(id)
+    }
+
+    async fn auth(&mut self, client_id: &str, client_secret: &str) -> Result<u64, HandleError> {
+        self.send(
+            "public/auth",
+            json!({
+                "grant_type": "client_credentials",
+                "client_id": client_id,
+                "client_secret": client_secret,
+            }),
+        )
+        .await
+    }
+
+    /// Subscribes to the channels and requests the heartbeat, which is needed to detect a dead
+    /// connection.
+    async fn subscribe(&mut self, method: &str, channels: Vec<String>) -> Result<(), HandleError> {
+        self.send(method, json!({ "channels": channels })).await?;
+        self.send(
+            "public/set_heartbeat",
+            json!({ "interval": HEARTBEAT_INTERVAL }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Answers the heartbeat test request.
+    async fn handle_heartbeat(&mut self, params: serde_json::Value) -> Result<(), HandleError> {
+        let heartbeat: Heartbeat = serde_json::from_value(params)?;
+        if heartbeat.ty == "test_request" {
+            self.send("public/test", json!({})).await?;
+        }
+        Ok(())
+    }
+}
+
+fn event(ev: u64, exch_ts: i64, px: f64, qty: f64) -> Event {
+    Event {
+        ev,
+        exch_ts,
+        local_ts: Utc::now().timestamp_nanos_opt().unwrap(),
+        order_id: 0,
+        px,
+        qty,
+        ival: 0,
+        fval: 0.0,
+    }
+}
+
+fn auth_error(resp: &Response, ev_tx: &Sender<LiveEvent>) -> bool {
+    match &resp.error {
+        Some(error) => {
+            error!(?error, "Authentication failed.");
+            ev_tx
+                .send(LiveEvent::Error(LiveError::with(
+                    ErrorKind::CriticalConnectionError,
+                    DeribitError::AuthError(error.code, error.message.clone()),
+                )))
+                .unwrap();
+            true
+        }
+        None => false,
+    }
+}
+
+fn handle_book(
+    data: OrderBook,
+    ev_tx: &Sender<LiveEvent>,
+    assets: &HashMap<String, Asset>,
+    change_ids: &mut HashMap<String, i64>,
+) -> Result<(), HandleError> {
+    let asset = assets
+        .get(&data.instrument_name)
+        .ok_or(HandleError::AssetNotFound)?;
+    let exch_ts = data.timestamp * 1_000_000;
+    let mut events = Vec::with_capacity(data.bids.len() + data.asks.len() + 1);
+    if data.ty == "snapshot" {
+        // The snapshot replaces the entire order book.
+        events.push(event(LOCAL_DEPTH_CLEAR_EVENT, exch_ts, 0.0, 0.0));
+    } else {
+        let change_id = change_ids.get(&data.instrument_name).copied().unwrap_or(-1);
+        if data.prev_change_id != Some(change_id) {
+            return Err(HandleError::ChangeIdGap(change_id, data.prev_change_id));
+        }
+    }
+    change_ids.insert(data.instrument_name.clone(), data.change_id);
+    // A deleted level has zero amount, so the action doesn't need to be distinguished.
+    events.extend(
+        data.bids
+            .iter()
+            .map(|(_, px, qty)| event(LOCAL_BID_DEPTH_EVENT, exch_ts, *px, *qty)),
+    );
+    events.extend(
+        data.asks
+            .iter()
+            .map(|(_, px, qty)| event(LOCAL_ASK_DEPTH_EVENT, exch_ts, *px, *qty)),
+    );
+    ev_tx
+        .send(LiveEvent::FeedBatch {
+            asset_no: asset.asset_no,
+            events,
+        })
+        .unwrap();
+    Ok(())
+}
+
+async fn handle_public_stream(
+    text: &str,
+    writer: &mut RpcWriter,
+    ev_tx: &Sender<LiveEvent>,
+    assets: &HashMap<String, Asset>,
+    auth_id: u64,
+    change_ids: &mut HashMap<String, i64>,
+) -> Result<(), HandleError> {
+    let stream = serde_json::from_str::<StreamMsg>(text)?;
+    match stream {
+        StreamMsg::Response(resp) => {
+            if resp.id == auth_id {
+                if !auth_error(&resp, ev_tx) {
+                    // The raw channels require the authorization.
+                    let mut channels = Vec::new();
+                    for instrument_name in assets.keys() {
+                        channels.push(format!("book.{instrument_name}.raw"));
+                        channels.push(format!("trades.{instrument_name}.raw"));
+                    }
+                    writer.subscribe("public/subscribe", channels).await?;
+                }
+            } else if let Some(error) = resp.error {
+                error!(?error, "Request failed.");
+            }
+        }
+        StreamMsg::Notification(Notification { method, params }) => {
+            if method == "heartbeat" {
+                writer.handle_heartbeat(params).await?;
+            } else if method == "subscription" {
+                let sub: Subscription = serde_json::from_value(params)?;
+                if sub.channel.starts_with("book.") {
+                    let data: OrderBook = serde_json::from_value(sub.data)?;
+                    handle_book(data, ev_tx, assets, change_ids)?;
+                } else if sub.channel.starts_with("trades.") {
+                    let data: Vec<Trade> = serde_json::from_value(sub.data)?;
+                    for item in data {
+                        let asset_info = assets
+                            .get(&item.instrument_name)
+                            .ok_or(HandleError::AssetNotFound)?;
+                        ev_tx
+                            .send(LiveEvent::FeedBatch {
+                                asset_no: asset_info.asset_no,
+                                events: vec![event(
+                                    if item.direction == Side::Sell {
+                                        LOCAL_SELL_TRADE_EVENT
+                                    } else {
+                                        LOCAL_BUY_TRADE_EVENT
+                                    },
+                                    item.timestamp * 1_000_000,
+                                    item.price,
+                                    item.amount,
+                                )],
+                            })
+                            .unwrap();
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn connect_public(
+    url: &str,
+    client_id: &str,
+    client_secret: &str,
+    ev_tx: Sender<LiveEvent>,
+    assets: HashMap<String, Asset>,
+) -> Result<(), HandleError> {
+    let request = url.into_client_request()?;
+    let (ws_stream, _) = connect_async(request).await?;
+    let (write, mut read) = ws_stream.split();
+    let mut writer = RpcWriter::new(write);
+    let mut change_ids = HashMap::new();
+    // Detects a dead connection if neither a message nor a heartbeat arrives.
+    let mut timeout = time::interval(Duration::from_secs(HEARTBEAT_INTERVAL * 2));
+    timeout.reset();
+
+    let auth_id = writer.auth(client_id, client_secret).await?;
+
+    loop {
+        select! {
+            _ = timeout.tick() => {
+                warn!("No message was received within the heartbeat interval.");
+                break;
+            }
+            message = read.next() => {
+                timeout.reset();
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        match handle_public_stream(
+                            &text,
+                            &mut writer,
+                            &ev_tx,
+                            &assets,
+                            auth_id,
+                            &mut change_ids
+                        ).await {
+                            Ok(_) => {}
+                            Err(error @ HandleError::ChangeIdGap(..)) => {
+                                // Reconnects to receive a new snapshot.
+                                return Err(error);
+                            }
+                            Err(error) => {
+                                error!(?error, %text, "Couldn't handle PublicStreamMsg.");
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {}
+                    Some(Ok(Message::Ping(_))) => {
+                        writer.write.send(Message::Pong(Vec::new())).await?;
+                    }
+                    Some(Ok(Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(close_frame))) => {
+                        info!(?close_frame, "close");
+                        break;
+                    }
+                    Some(Ok(Message::Frame(_))) => {}
+                    Some(Err(e)) => {
+                        return Err(HandleError::from(e));
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_response(
+    resp: Response,
+    kind: RequestKind,
+    assets: &HashMap<String, Asset>,
+    ev_tx: &Sender<LiveEvent>,
+    order_man: &OrderManagerWrapper,
+) -> Result<(), HandleError> {
+    match (kind, resp.error) {
+        (RequestKind::Position, None) => {
+            let position: Position = serde_json::from_value(resp.result.unwrap_or_default())?;
+            let asset = assets
+                .get(&position.instrument_name)
+                .ok_or(HandleError::AssetNotFound)?;
+            ev_tx
+                .send(LiveEvent::Position {
+                    asset_no: asset.asset_no,
+                    qty: position.size,
+                })
+                .unwrap();
+        }
+        (RequestKind::Submit(label), Some(error)) => {
+            let mut order_man_ = order_man.lock().unwrap();
+            let (asset_no, order) = order_man_.update_submit_fail(&label)?;
+            ev_tx.send(LiveEvent::Order { asset_no, order }).unwrap();
+            ev_tx
+                .send(LiveEvent::Error(LiveError::with(
+                    ErrorKind::OrderError,
+                    DeribitError::OrderError(error.code, error.message),
+                )))
+                .unwrap();
+        }
+        (RequestKind::Cancel(label), Some(error)) => {
+            let mut order_man_ = order_man.lock().unwrap();
+            let (asset_no, order) = order_man_.update_cancel_fail(&label)?;
+            ev_tx.send(LiveEvent::Order { asset_no, order }).unwrap();
+            ev_tx
+                .send(LiveEvent::Error(LiveError::with(
+                    ErrorKind::OrderError,
+                    DeribitError::OrderError(error.code, error.message),
+                )))
+                .unwrap();
+        }
+        (RequestKind::CancelAll, Some(error)) => {
+            ev_tx
+                .send(LiveEvent::Error(LiveError::with(
+                    ErrorKind::OrderError,
+                    DeribitError::OrderError(error.code, error.message),
+                )))
+                .unwrap();
+        }
+        (_, Some(error)) => {
+            error!(?error, "Request failed.");
+        }
+        // The order updates are delivered through the user channels.
+        (_, None) => {}
+    }
+    Ok(())
+}
+
+async fn handle_private_stream(
+    text: &str,
+    writer: &mut RpcWriter,
+    requests: &mut HashMap<u64, RequestKind>,
+    authorized: &mut bool,
+    assets: &HashMap<String, Asset>,
+    ev_tx: &Sender<LiveEvent>,
+    order_man: &OrderManagerWrapper,
+) -> Result<(), HandleError> {
+    let stream = serde_json::from_str::<StreamMsg>(text)?;
+    match stream {
+        StreamMsg::Response(resp) => {
+            let kind = requests.remove(&resp.id).unwrap_or(RequestKind::Other);
+            if let RequestKind::Auth = kind {
+                if !auth_error(&resp, ev_tx) {
+                    // Cancels all orders to start with the clean state, and fetches the initial
+                    // positions.
+                    let mut channels = Vec::new();
+                    for instrument_name in assets.keys() {
+                        let id = writer
+                            .send(
+                                "private/cancel_all_by_instrument",
+                                json!({ "instrument_name": instrument_name }),
+                            )
+                            .await?;
+                        requests.insert(id, RequestKind::CancelAll);
+                        let id = writer
+                            .send(
+                                "private/get_position",
+                                json!({ "instrument_name": instrument_name }),
+                            )
+                            .await?;
+                        requests.insert(id, RequestKind::Position);
+                        channels.push(format!("user.orders.{instrument_name}.raw"));
+                        channels.push(format!("user.trades.{instrument_name}.raw"));
+                    }
+                    writer.subscribe("private/subscribe", channels).await?;
+                    *authorized = true;
+                }
+            } else {
+                handle_response(resp, kind, assets, ev_tx, order_man)?;
+            }
+        }
+        StreamMsg::Notification(Notification { method, params }) => {
+            if method == "heartbeat" {
+                writer.handle_heartbeat(params).await?;
+            } else if method == "subscription" {
+                let sub: Subscription = serde_json::from_value(params)?;
+                if sub.channel.starts_with("user.orders.") {
+                    let data: UserOrder = serde_json::from_value(sub.data)?;
+                    debug!(?data, "Order");
+                    let mut order_man_ = order_man.lock().unwrap();
+                    match order_man_.update_order(&data) {
+                        Ok((asset_no, order)) => {
+                            ev_tx.send(LiveEvent::Order { asset_no, order }).unwrap();
+                        }
+                        Err(HandleError::PrefixUnmatched) => {
+                            warn!(?data, "PrefixUnmatched");
+                        }
+                        Err(error) => {
+                            error!(?error, ?data, "Couldn't update the order data");
+                        }
+                    }
+                } else if sub.channel.starts_with("user.trades.") {
+                    let data: Vec<UserTrade> = serde_json::from_value(sub.data)?;
+                    debug!(?data, "Trade");
+                    {
+                        let mut order_man_ = order_man.lock().unwrap();
+                        for item in &data {
+                            match order_man_.update_execution(item) {
+                                Ok((asset_no, order)) => {
+                                    ev_tx.send(LiveEvent::Order { asset_no, order }).unwrap();
+                                }
+                                Err(HandleError::PrefixUnmatched) => {
+                                    warn!(?item, "PrefixUnmatched");
+                                }
+                                Err(error) => {
+                                    error!(?error, ?item, "Couldn't update the execution data");
+                                }
+                            }
+                        }
+                    }
+                    // The user channels don't carry the position, so it's requested after the
+                    // fill.
+                    if let Some(item) = data.first() {
+                        let id = writer
+                            .send(
+                                "private/get_position",
+                                json!({ "instrument_name": item.instrument_name }),
+                            )
+                            .await?;
+                        requests.insert(id, RequestKind::Position);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn connect_private(
+    url: &str,
+    client_id: &str,
+    client_secret: &str,
+    ev_tx: Sender<LiveEvent>,
+    assets: HashMap<String, Asset>,
+    order_rx: &mut UnboundedReceiver<OrderOp>,
+    order_man: OrderManagerWrapper,
+) -> Result<(), HandleError> {
+    let request = url.into_client_request()?;
+    let (ws_stream, _) = connect_async(request).await?;
+    let (write, mut read) = ws_stream.split();
+    let mut writer = RpcWriter::new(write);
+    let mut requests = HashMap::new();
+    let mut authorized = false;
+    // Detects a dead connection if neither a message nor a heartbeat arrives.
+    let mut timeout = time::interval(Duration::from_secs(HEARTBEAT_INTERVAL * 2));
+    timeout.reset();
+
+    let id = writer.auth(client_id, client_secret).await?;
+    requests.insert(id, RequestKind::Auth);
+
+    loop {
+        select! {
+            _ = timeout.tick() => {
+                warn!("No message was received within the heartbeat interval.");
+                break;
+            }
+            // The orders are held until the authorization succeeds.
+            order = order_rx.recv(), if authorized => {
+                match order {
+                    Some(OrderOp::Submit { method, deribit_order }) => {
+                        let label = deribit_order.label.clone();
+                        let id = writer.send(method, deribit_order).await?;
+                        requests.insert(id, RequestKind::Submit(label));
+                    }
+                    Some(OrderOp::Cancel { label }) => {
+                        let id = writer
+                            .send("private/cancel_by_label", json!({ "label": label }))
+                            .await?;
+                        requests.insert(id, RequestKind::Cancel(label));
+                    }
+                    Some(OrderOp::CancelAll { instrument_name }) => {
+                        let id = writer
+                            .send(
+                                "private/cancel_all_by_instrument",
+                                json!({ "instrument_name": instrument_name }),
+                            )
+                            .await?;
+                        requests.insert(id, RequestKind::CancelAll);
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+            message = read.next() => {
+                timeout.reset();
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Err(error) = handle_private_stream(
+                            &text,
+                            &mut writer,
+                            &mut requests,
+                            &mut authorized,
+                            &assets,
+                            &ev_tx,
+                            &order_man
+                        ).await {
+                            error!(%text, ?error, "Couldn't properly handle PrivateStreamMsg");
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {}
+                    Some(Ok(Message::Ping(_))) => {
+                        writer.write.send(Message::Pong(Vec::new())).await?;
+                    }
+                    Some(Ok(Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(close_frame))) => {
+                        info!(?close_frame, "close");
+                        break;
+                    }
+                    Some(Ok(Message::Frame(_))) => {}
+                    Some(Err(e)) => {
+                        return Err(HandleError::from(e));
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -5,12 +5,29 @@ use crate::types::{LiveEvent, Order};
 #[cfg(feature = "binancefutures")]
 pub mod binancefutures;
 
+#[cfg(feature = "bitget")]
+pub mod bitget;
+
 #[cfg(feature = "bybit")]
 pub mod bybit;
 
+#[cfg(feature = "databentolive")]
+pub mod databento;
+
+#[cfg(feature = "deribit")]
+pub mod deribit;
+
+#[cfg(feature = "okx")]
+pub mod okx;
+
 pub mod util;
 
 /// Provides an interface for connecting with an exchange or broker for a live bot.
+///
+/// A data-only connector, which only provides the market data, returns `false` from
+/// [`order_entry`](`Connector::order_entry`) and doesn't need to implement the order methods.
+/// [`LiveBot`](`crate::live::LiveBot`) rejects the order requests for the assets added through such
+/// a connector, so that a strategy can use one venue's data while trading on another venue.
 pub trait Connector {
     /// Adds an asset to be traded through this connector.
     fn add(
@@ -27,23 +44,40 @@ pub trait Connector {
     /// exchange; instead, it should indicate a connector internal error.
     fn run(&mut self, tx: Sender<LiveEvent>) -> Result<(), anyhow::Error>;
 
+    /// Returns `true` if this connector can submit and cancel orders. The default value is `true`.
+    fn order_entry(&self) -> bool {
+        true
+    }
+
     /// Submits a new order. This method should not block, and the response should be returned
     /// through the channel using [`LiveEvent`]. The returned error should not be related to the
     /// exchange; instead, it should indicate a connector internal error.
     fn submit(
         &self,
-        asset_no: usize,
-        order: Order,
-        ev_tx: Sender<LiveEvent>,
-    ) -> Result<(), anyhow::Error>;
+        _asset_no: usize,
+        _order: Order,
+        _ev_tx: Sender<LiveEvent>,
+    ) -> Result<(), anyhow::Error> {
+        Err(anyhow::Error::msg("order entry is not supported"))
+    }
 
     /// Cancels an open order. This method should not block, and the response should be returned
     /// through the channel using [`LiveEvent`]. The returned error should not be related to the
     /// exchange; instead, it should indicate a connector internal error.
     fn cancel(
         &self,
-        asset_no: usize,
-        order: Order,
-        ev_tx: Sender<LiveEvent>,
-    ) -> Result<(), anyhow::Error>;
+        _asset_no: usize,
+        _order: Order,
+        _ev_tx: Sender<LiveEvent>,
+    ) -> Result<(), anyhow::Error> {
+        Err(anyhow::Error::msg("order entry is not supported"))
+    }
+
+    /// Cancels all open orders of the asset through the exchange's cancel-all endpoint with a
+    /// single request. This method should not block, and the cancellation of each order should be
+    /// returned through the channel using [`LiveEvent`]. The returned error should not be related
+    /// to the exchange; instead, it should indicate a connector internal error.
+    fn cancel_all(&self, _asset_no: usize, _ev_tx: Sender<LiveEvent>) -> Result<(), anyhow::Error> {
+        Err(anyhow::Error::msg("order entry is not supported"))
+    }
 }
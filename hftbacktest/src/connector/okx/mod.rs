@@ -0,0 +1,601 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{mpsc::Sender, Arc, Mutex},
+    time::Duration,
+};
+
+use thiserror::Error;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tracing::error;
+
+use crate::{
+    connector::{
+        okx::{
+            msg::Position,
+            ordermanager::{OrderManager, OrderManagerWrapper},
+            rest::OkxClient,
+            ws::{connect_private, connect_public, connect_trade, Credentials, OrderOp},
+        },
+        Connector,
+    },
+    live::Asset,
+    types::{BuildError, ErrorKind, LiveError, LiveEvent, Order, PositionMode, Side},
+};
+
+mod msg;
+mod ordermanager;
+mod rest;
+mod ws;
+
+#[derive(Clone)]
+pub enum Endpoint {
+    Public,
+    Demo,
+    Custom(String),
+}
+
+impl From<String> for Endpoint {
+    fn from(value: String) -> Self {
+        Endpoint::Custom(value)
+    }
+}
+
+impl From<&'static str> for Endpoint {
+    fn from(value: &'static str) -> Self {
+        Endpoint::Custom(value.to_string())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum OkxError {
+    #[error("asset not found")]
+    AssetNotFound,
+    #[error("auth error {0}: {1}")]
+    AuthError(i64, String),
+    #[error("order error {0}: {1}")]
+    OrderError(i64, String),
+}
+
+/// OKX V5 connector [`Okx`] builder.
+///
+/// The market depth is received through the `books-l2-tbt` channel by default, and the orders are
+/// submitted and canceled through the WebSocket order entry. The symbol of an asset is the
+/// instrument id, such as `BTC-USDT-SWAP`, and the quantities are in contracts for the
+/// derivatives.
+pub struct OkxBuilder {
+    public_url: String,
+    private_url: String,
+    rest_url: String,
+    simulated: bool,
+    book_channel: String,
+    channels: HashSet<String>,
+    api_key: String,
+    secret: String,
+    passphrase: String,
+    order_prefix: String,
+    position_mode: PositionMode,
+    td_mode: String,
+    verify_checksum: bool,
+}
+
+impl OkxBuilder {
+    /// Sets an endpoint to connect.
+    pub fn endpoint(self, endpoint: Endpoint) -> Self {
+        if let Endpoint::Custom(_) = endpoint {
+            panic!(
+                "Use `public_url`, `private_url`, and `rest_url` to set a custom endpoint instead"
+            );
+        }
+        self.public_url(endpoint.clone())
+            .private_url(endpoint.clone())
+            .rest_url(endpoint)
+    }
+
+    /// Sets the public Websocket stream endpoint url.
+    pub fn public_url<E: Into<Endpoint>>(self, endpoint: E) -> Self {
+        match endpoint.into() {
+            Endpoint::Public => Self {
+                public_url: "wss://ws.okx.com:8443/ws/v5/public".to_string(),
+                ..self
+            },
+            Endpoint::Demo => Self {
+                public_url: "wss://wspap.okx.com:8443/ws/v5/public".to_string(),
+                ..self
+            },
+            Endpoint::Custom(public_url) => Self { public_url, ..self },
+        }
+    }
+
+    /// Sets the private Websocket stream endpoint url, which is used for both the order updates
+    /// and the order entry.
+    pub fn private_url<E: Into<Endpoint>>(self, endpoint: E) -> Self {
+        match endpoint.into() {
+            Endpoint::Public => Self {
+                private_url: "wss://ws.okx.com:8443/ws/v5/private".to_string(),
+                ..self
+            },
+            Endpoint::Demo => Self {
+                private_url: "wss://wspap.okx.com:8443/ws/v5/private".to_string(),
+                ..self
+            },
+            Endpoint::Custom(private_url) => Self {
+                private_url,
+                ..self
+            },
+        }
+    }
+
+    /// Sets the REST API endpoint url. [`Endpoint::Demo`] also marks the REST requests as demo
+    /// trading.
+    pub fn rest_url<E: Into<Endpoint>>(self, endpoint: E) -> Self {
+        match endpoint.into() {
+            Endpoint::Public => Self {
+                rest_url: "https://www.okx.com".to_string(),
+                simulated: false,
+                ..self
+            },
+            Endpoint::Demo => Self {
+                rest_url: "https://www.okx.com".to_string(),
+                simulated: true,
+                ..self
+            },
+            Endpoint::Custom(rest_url) => Self { rest_url, ..self },
+        }
+    }
+
+    /// Sets the API key
+    pub fn api_key(self, api_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the secret key
+    pub fn secret(self, secret: &str) -> Self {
+        Self {
+            secret: secret.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the passphrase of the API key
+    pub fn passphrase(self, passphrase: &str) -> Self {
+        Self {
+            passphrase: passphrase.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the order prefix, which is used to differentiate the orders submitted through this
+    /// connector. Since the client order id should be alphanumeric and start with a letter, the
+    /// prefix should also be, and its length should not be greater than 6.
+    pub fn order_prefix(self, order_prefix: &str) -> Self {
+        Self {
+            order_prefix: order_prefix.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the position mode, which must match the account's position mode on OKX.
+    /// [`PositionMode::OneWay`] is the net mode, and [`PositionMode::Hedge`] is the long/short
+    /// mode, in which orders are submitted with the position side of the leg determined by
+    /// [`Order::hedge_leg`]. The default value is [`PositionMode::OneWay`].
+    pub fn position_mode(self, position_mode: PositionMode) -> Self {
+        Self {
+            position_mode,
+            ..self
+        }
+    }
+
+    /// Sets the trade mode of the orders, which is one of `cross`, `isolated`, and `cash`. The
+    /// default value is `cross`.
+    pub fn td_mode(self, td_mode: &str) -> Self {
+        Self {
+            td_mode: td_mode.to_string(),
+            ..self
+        }
+    }
+
+    /// Sets the incremental order book channel, such as `books-l2-tbt`, `books50-l2-tbt`, or
+    /// `books`. The default value is `books-l2-tbt`, which requires the VIP level that OKX
+    /// specifies.
+    pub fn book_channel(self, book_channel: &str) -> Self {
+        Self {
+            book_channel: book_channel.to_string(),
+            ..self
+        }
+    }
+
+    /// Adds an additional channel to receive through the public WebSocket stream, such as
+    /// `bbo-tbt`.
+    pub fn add_channel(mut self, channel: &str) -> Self {
+        self.channels.insert(channel.to_string());
+        self
+    }
+
+    /// Verifies the checksum of each order book message by maintaining a copy of the order book
+    /// in the connector, and reconnects to receive a new snapshot when the checksum doesn't match.
+    /// A gap in the sequence of the order book messages is always detected, regardless of this
+    /// option. The default value is `false`.
+    pub fn verify_checksum(self, verify_checksum: bool) -> Self {
+        Self {
+            verify_checksum,
+            ..self
+        }
+    }
+
+    /// Builds [`Okx`] connector.
+    pub fn build(self) -> Result<Okx, BuildError> {
+        if self.public_url.is_empty() {
+            return Err(BuildError::BuilderIncomplete("public_url"));
+        }
+        if self.private_url.is_empty() {
+            return Err(BuildError::BuilderIncomplete("private_url"));
+        }
+        if self.rest_url.is_empty() {
+            return Err(BuildError::BuilderIncomplete("rest_url"));
+        }
+        if self.api_key.is_empty() {
+            return Err(BuildError::BuilderIncomplete("api_key"));
+        }
+        if self.secret.is_empty() {
+            return Err(BuildError::BuilderIncomplete("secret"));
+        }
+        if self.passphrase.is_empty() {
+            return Err(BuildError::BuilderIncomplete("passphrase"));
+        }
+
+        if !self
+            .order_prefix
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic())
+        {
+            panic!("order prefix should start with a letter.");
+        }
+        if !self.order_prefix.chars().all(|c| c.is_ascii_alphanumeric()) {
+            panic!("order prefix should be alphanumeric.");
+        }
+        if self.order_prefix.len() > 6 {
+            panic!("order prefix length should be not greater than 6.");
+        }
+        Ok(Okx {
+            public_url: self.public_url,
+            private_url: self.private_url,
+            assets: Default::default(),
+            inv_assets: Default::default(),
+            book_channel: self.book_channel,
+            channels: self.channels,
+            credentials: Credentials {
+                api_key: self.api_key.clone(),
+                secret: self.secret.clone(),
+                passphrase: self.passphrase.clone(),
+            },
+            order_tx: None,
+            order_man: Arc::new(Mutex::new(OrderManager::new(
+                &self.order_prefix,
+                self.position_mode,
+                &self.td_mode,
+            ))),
+            verify_checksum: self.verify_checksum,
+            client: OkxClient::new(
+                &self.rest_url,
+                &self.api_key,
+                &self.secret,
+                &self.passphrase,
+                self.simulated,
+            ),
+        })
+    }
+}
+
+pub struct Okx {
+    public_url: String,
+    private_url: String,
+    assets: HashMap<String, Asset>,
+    inv_assets: HashMap<usize, Asset>,
+    book_channel: String,
+    channels: HashSet<String>,
+    credentials: Credentials,
+    order_tx: Option<UnboundedSender<OrderOp>>,
+    order_man: OrderManagerWrapper,
+    verify_checksum: bool,
+    client: OkxClient,
+}
+
+impl Okx {
+    pub fn builder() -> OkxBuilder {
+        OkxBuilder {
+            public_url: "".to_string(),
+            private_url: "".to_string(),
+            rest_url: "".to_string(),
+            simulated: false,
+            book_channel: "books-l2-tbt".to_string(),
+            channels: Default::default(),
+            api_key: "".to_string(),
+            secret: "".to_string(),
+            passphrase: "".to_string(),
+            order_prefix: "".to_string(),
+            position_mode: Default::default(),
+            td_mode: "cross".to_string(),
+            verify_checksum: false,
+        }
+    }
+}
+
+/// Converts the position into [`LiveEvent`]. In the long/short mode, OKX reports the long and
+/// short legs separately with the position side `long` and `short`, respectively.
+fn position_event(asset_no: usize, position: &Position) -> LiveEvent {
+    match position.pos_side.as_str() {
+        "long" => LiveEvent::PositionLeg {
+            asset_no,
+            side: Side::Buy,
+            qty: position.pos,
+            entry_price: position.avg_px.unwrap_or(0.0),
+        },
+        "short" => LiveEvent::PositionLeg {
+            asset_no,
+            side: Side::Sell,
+            qty: position.pos,
+            entry_price: position.avg_px.unwrap_or(0.0),
+        },
+        _ => LiveEvent::Position {
+            asset_no,
+            qty: position.pos,
+        },
+    }
+}
+
+impl Connector for Okx {
+    fn add(
+        &mut self,
+        asset_no: usize,
+        symbol: String,
+        tick_size: f64,
+        lot_size: f64,
+    ) -> Result<(), anyhow::Error> {
+        let asset_info = Asset {
+            asset_no,
+            symbol: symbol.clone(),
+            tick_size,
+            lot_size,
+        };
+        self.assets.insert(symbol, asset_info.clone());
+        self.inv_assets.insert(asset_no, asset_info);
+        Ok(())
+    }
+
+    fn run(&mut self, ev_tx: Sender<LiveEvent>) -> Result<(), anyhow::Error> {
+        // Connects to the public stream for the market data.
+        let public_url = self.public_url.clone();
+        let ev_tx_public = ev_tx.clone();
+        let assets_public = self.assets.clone();
+        let credentials_public = self.credentials.clone();
+        let verify_checksum = self.verify_checksum;
+        let mut channels = vec![self.book_channel.clone(), "trades".to_string()];
+        for channel in self.channels.iter() {
+            channels.push(channel.clone());
+        }
+
+        let _ = tokio::spawn(async move {
+            let mut error_count = 0;
+            loop {
+                if error_count > 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                if let Err(error) = connect_public(
+                    &public_url,
+                    &credentials_public,
+                    ev_tx_public.clone(),
+                    assets_public.clone(),
+                    channels.clone(),
+                    verify_checksum,
+                )
+                .await
+                {
+                    error!(?error, "A connection error occurred.");
+                    ev_tx_public
+                        .send(LiveEvent::Error(LiveError::with(
+                            ErrorKind::ConnectionInterrupted,
+                            error,
+                        )))
+                        .unwrap();
+                } else {
+                    ev_tx_public
+                        .send(LiveEvent::Error(LiveError::new(
+                            ErrorKind::ConnectionInterrupted,
+                        )))
+                        .unwrap();
+                }
+                error_count += 1;
+            }
+        });
+
+        // Connects to the private stream for the position and order data.
+        let private_url = self.private_url.clone();
+        let ev_tx_private = ev_tx.clone();
+        let assets_private = self.assets.clone();
+        let credentials_private = self.credentials.clone();
+        let order_man_private = self.order_man.clone();
+        let client_private = self.client.clone();
+        let _ = tokio::spawn(async move {
+            let mut error_count = 0;
+            'connection: loop {
+                if error_count > 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+
+                // Cancel all orders before connecting to the stream in order to start with the
+                // clean state.
+                for (symbol, _) in assets_private.iter() {
+                    if let Err(error) = client_private.cancel_all_orders(symbol).await {
+                        error!(?error, %symbol, "Couldn't cancel all open orders.");
+                        ev_tx_private
+                            .send(LiveEvent::Error(LiveError::with(
+                                ErrorKind::OrderError,
+                                error,
+                            )))
+                            .unwrap();
+                        error_count += 1;
+                        continue 'connection;
+                    }
+                }
+                {
+                    let mut order_manager_ = order_man_private.lock().unwrap();
+                    let orders = order_manager_.clear_orders();
+                    for (asset_no, order) in orders {
+                        ev_tx_private
+                            .send(LiveEvent::Order { asset_no, order })
+                            .unwrap();
+                    }
+                }
+
+                // Fetches the initial states such as positions.
+                for (symbol, asset_info) in assets_private.iter() {
+                    match client_private.get_positions(symbol).await {
+                        Ok(positions) => {
+                            for position in positions {
+                                ev_tx_private
+                                    .send(position_event(asset_info.asset_no, &position))
+                                    .unwrap();
+                            }
+                        }
+                        Err(error) => {
+                            error!(?error, "Couldn't get position information.");
+                            error_count += 1;
+                            continue 'connection;
+                        }
+                    }
+                }
+
+                if let Err(error) = connect_private(
+                    &private_url,
+                    &credentials_private,
+                    ev_tx_private.clone(),
+                    assets_private.clone(),
+                    order_man_private.clone(),
+                )
+                .await
+                {
+                    error!(?error, "A connection error occurred.");
+                    ev_tx_private
+                        .send(LiveEvent::Error(LiveError::with(
+                            ErrorKind::ConnectionInterrupted,
+                            error,
+                        )))
+                        .unwrap();
+                } else {
+                    ev_tx_private
+                        .send(LiveEvent::Error(LiveError::new(
+                            ErrorKind::ConnectionInterrupted,
+                        )))
+                        .unwrap();
+                }
+                error_count += 1;
+            }
+        });
+
+        // Connects to the private stream for order entry.
+        let trade_url = self.private_url.clone();
+        let ev_tx_trade = ev_tx.clone();
+        let credentials_trade = self.credentials.clone();
+        let order_man_trade = self.order_man.clone();
+        let (order_tx, mut order_rx) = unbounded_channel();
+        self.order_tx = Some(order_tx);
+        let _ = tokio::spawn(async move {
+            let mut error_count = 0;
+            loop {
+                if error_count > 0 {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                if let Err(error) = connect_trade(
+                    &trade_url,
+                    &credentials_trade,
+                    ev_tx_trade.clone(),
+                    &mut order_rx,
+                    order_man_trade.clone(),
+                )
+                .await
+                {
+                    error!(?error, "A connection error occurred.");
+                    ev_tx_trade
+                        .send(LiveEvent::Error(LiveError::with(
+                            ErrorKind::ConnectionInterrupted,
+                            error,
+                        )))
+                        .unwrap();
+                } else {
+                    ev_tx_trade
+                        .send(LiveEvent::Error(LiveError::new(
+                            ErrorKind::ConnectionInterrupted,
+                        )))
+                        .unwrap();
+                }
+                error_count += 1;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn submit(
+        &self,
+        asset_no: usize,
+        order: Order,
+        _tx: Sender<LiveEvent>,
+    ) -> Result<(), anyhow::Error> {
+        let asset_info = self
+            .inv_assets
+            .get(&asset_no)
+            .ok_or(OkxError::AssetNotFound)?;
+        let mut order_man = self.order_man.lock().unwrap();
+        let okx_order = order_man.new_order(&asset_info.symbol, asset_no, order)?;
+        self.order_tx.as_ref().unwrap().send(OrderOp {
+            op: "order".to_string(),
+            okx_order,
+        })?;
+        Ok(())
+    }
+
+    fn cancel(
+        &self,
+        asset_no: usize,
+        order: Order,
+        _tx: Sender<LiveEvent>,
+    ) -> Result<(), anyhow::Error> {
+        let asset_info = self
+            .inv_assets
+            .get(&asset_no)
+            .ok_or(OkxError::AssetNotFound)?;
+        let mut order_man = self.order_man.lock().unwrap();
+        let okx_order = order_man.cancel_order(&asset_info.symbol, order.order_id)?;
+        self.order_tx.as_ref().unwrap().send(OrderOp {
+            op: "cancel-order".to_string(),
+            okx_order,
+        })?;
+        Ok(())
+    }
+
+    fn cancel_all(&self, asset_no: usize, tx: Sender<LiveEvent>) -> Result<(), anyhow::Error> {
+        let asset_info = self
+            .inv_assets
+            .get(&asset_no)
+            .ok_or(OkxError::AssetNotFound)?;
+        let symbol = asset_info.symbol.clone();
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            // OKX doesn't provide a cancel-all endpoint, so the open orders are canceled in
+            // batches. The cancellation of each order is delivered through the private stream.
+            if let Err(error) = client.cancel_all_orders(&symbol).await {
+                error!(?error, %symbol, "Couldn't cancel all open orders.");
+                tx.send(LiveEvent::Error(LiveError::with(
+                    ErrorKind::OrderError,
+                    error,
+                )))
+                .unwrap();
+            }
+        });
+        Ok(())
+    }
+}
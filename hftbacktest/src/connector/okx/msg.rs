@@ -0,0 +1,277 @@
+use std::{fmt, fmt::Debug};
+
+use serde::{
+    de,
+    de::{Error, Unexpected, Visitor},
+    Deserialize,
+    Deserializer,
+    Serialize,
+};
+
+use crate::{
+    connector::util::{from_str_to_f64, from_str_to_f64_opt, from_str_to_i64},
+    types::{Side, Status},
+};
+
+struct SideVisitor;
+
+impl<'de> Visitor<'de> for SideVisitor {
+    type Value = Side;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string containing \"buy\" or \"sell\"")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match s {
+            "buy" => Ok(Side::Buy),
+            "sell" => Ok(Side::Sell),
+            s => Err(Error::invalid_value(Unexpected::Other(s), &"buy or sell")),
+        }
+    }
+}
+
+fn from_str_to_side<'de, D>(deserializer: D) -> Result<Side, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(SideVisitor)
+}
+
+struct StatusVisitor;
+
+impl<'de> Visitor<'de> for StatusVisitor {
+    type Value = Status;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string containing the order state")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match s {
+            "live" => Ok(Status::New),
+            "partially_filled" => Ok(Status::PartiallyFilled),
+            "filled" => Ok(Status::Filled),
+            "canceled" => Ok(Status::Canceled),
+            "mmp_canceled" => Ok(Status::Canceled),
+            s => Err(Error::invalid_value(
+                Unexpected::Other(s),
+                &"live, partially_filled, filled, canceled, or mmp_canceled",
+            )),
+        }
+    }
+}
+
+fn from_str_to_status<'de, D>(deserializer: D) -> Result<Status, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(StatusVisitor)
+}
+
+#[derive(Serialize, Debug)]
+pub struct Op<T>
+where
+    T: Serialize + Debug,
+{
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub op: String,
+    pub args: Vec<T>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LoginArg {
+    #[serde(rename = "apiKey")]
+    pub api_key: String,
+    pub passphrase: String,
+    pub timestamp: String,
+    pub sign: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Arg {
+    pub channel: String,
+    #[serde(rename = "instId")]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub inst_id: Option<String>,
+    #[serde(rename = "instType")]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub inst_type: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum StreamMsg {
+    Push(PushMsg),
+    Event(EventMsg),
+    OpResponse(OpResponse),
+}
+
+/// A message pushed through a subscribed channel.
+#[derive(Deserialize, Debug)]
+pub struct PushMsg {
+    pub arg: Arg,
+    /// `snapshot` or `update` for the incremental order book channels.
+    #[serde(default)]
+    pub action: Option<String>,
+    pub data: serde_json::Value,
+}
+
+/// A response to the login and the subscription, or an error.
+#[derive(Deserialize, Debug)]
+pub struct EventMsg {
+    pub event: String,
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub msg: Option<String>,
+}
+
+/// A response to the order entry operations.
+#[derive(Deserialize, Debug)]
+pub struct OpResponse {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub op: String,
+    pub code: String,
+    pub msg: String,
+    #[serde(default)]
+    pub data: Vec<OrderResponseData>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OrderResponseData {
+    #[serde(rename = "sCode")]
+    pub s_code: String,
+    #[serde(rename = "sMsg")]
+    pub s_msg: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OrderBook {
+    /// Each level consists of the price, the quantity, a deprecated field, and the number of
+    /// orders.
+    pub asks: Vec<Vec<String>>,
+    pub bids: Vec<Vec<String>>,
+    #[serde(deserialize_with = "from_str_to_i64")]
+    pub ts: i64,
+    #[serde(default)]
+    pub checksum: Option<i32>,
+    /// Not given for the `bbo-tbt` channel.
+    #[serde(rename = "prevSeqId", default)]
+    pub prev_seq_id: i64,
+    #[serde(rename = "seqId")]
+    pub seq_id: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Trade {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(deserialize_with = "from_str_to_f64")]
+    pub px: f64,
+    #[serde(deserialize_with = "from_str_to_f64")]
+    pub sz: f64,
+    #[serde(deserialize_with = "from_str_to_side")]
+    pub side: Side,
+    #[serde(deserialize_with = "from_str_to_i64")]
+    pub ts: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Position {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    /// `net` in the net mode, `long` or `short` in the long/short mode.
+    #[serde(rename = "posSide")]
+    pub pos_side: String,
+    /// The quantity in contracts, which is signed in the net mode.
+    #[serde(deserialize_with = "from_str_to_f64")]
+    pub pos: f64,
+    #[serde(rename = "avgPx", default)]
+    #[serde(deserialize_with = "from_str_to_f64_opt")]
+    pub avg_px: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PrivateOrder {
+    #[serde(rename = "clOrdId")]
+    pub cl_ord_id: String,
+    #[serde(deserialize_with = "from_str_to_f64")]
+    pub sz: f64,
+    #[serde(deserialize_with = "from_str_to_status")]
+    pub state: Status,
+    /// The price and the quantity of the last fill, which are empty if the push isn't caused by
+    /// a fill.
+    #[serde(rename = "fillPx", default)]
+    #[serde(deserialize_with = "from_str_to_f64_opt")]
+    pub fill_px: Option<f64>,
+    #[serde(rename = "fillSz", default)]
+    #[serde(deserialize_with = "from_str_to_f64_opt")]
+    pub fill_sz: Option<f64>,
+    #[serde(rename = "accFillSz")]
+    #[serde(deserialize_with = "from_str_to_f64")]
+    pub acc_fill_sz: f64,
+    #[serde(rename = "uTime")]
+    #[serde(deserialize_with = "from_str_to_i64")]
+    pub u_time: i64,
+}
+
+/// The order entry arguments of the `order` and `cancel-order` operations.
+#[derive(Serialize, Debug)]
+pub struct Order {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "tdMode")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub td_mode: Option<String>,
+    #[serde(rename = "clOrdId")]
+    pub cl_ord_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub side: Option<String>,
+    /// `long` or `short` in the long/short mode. Omitted in the net mode.
+    #[serde(rename = "posSide")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pos_side: Option<String>,
+    #[serde(rename = "ordType")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ord_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sz: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub px: Option<String>,
+    #[serde(rename = "reduceOnly")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PendingOrder {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "ordId")]
+    pub ord_id: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CancelOrder {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "ordId")]
+    pub ord_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RestResponse {
+    pub code: String,
+    pub msg: String,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    num::{ParseFloatError, ParseIntError},
+    sync::{Arc, Mutex},
+};
+
+use thiserror::Error;
+
+use crate::{
+    connector::{
+        okx::msg::{Order as OkxOrder, PrivateOrder},
+        util::gen_random_string,
+    },
+    prelude::{get_precision, OrdType, OrderId, Side, TimeInForce},
+    types::{Order, PositionMode, Status},
+};
+
+pub type OrderManagerWrapper = Arc<Mutex<OrderManager>>;
+
+// The length of the random string between the prefix and the order id in the client order id,
+// which keeps the client order ids unique across the sessions.
+const RAND_ID_LEN: usize = 6;
+
+#[derive(Error, Debug)]
+pub(super) enum HandleError {
+    #[error("px qty parse error: {0}")]
+    InvalidPxQty(#[from] ParseFloatError),
+    #[error("order id parse error: {0}")]
+    InvalidOrderId(ParseIntError),
+    #[error("prefix unmatched")]
+    PrefixUnmatched,
+    #[error("order not found")]
+    OrderNotFound,
+    #[error("asset not found")]
+    AssetNotFound,
+    #[error("invalid argument")]
+    InvalidArg(&'static str),
+    #[error("order already exist")]
+    OrderAlreadyExist,
+    #[error("sequence gap: expected {0}, got {1}")]
+    SequenceGap(i64, i64),
+    #[error("checksum mismatch: expected {0}, got {1}")]
+    ChecksumMismatch(i32, i32),
+    #[error("serde: {0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("tokio: {0}")]
+    TokioError(#[source] Box<tokio_tungstenite::tungstenite::Error>),
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for HandleError {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        HandleError::TokioError(Box::new(error))
+    }
+}
+
+pub struct OrderManager {
+    prefix: String,
+    position_mode: PositionMode,
+    td_mode: String,
+    orders: HashMap<OrderId, (usize, String, Order)>,
+}
+
+impl OrderManager {
+    pub fn new(prefix: &str, position_mode: PositionMode, td_mode: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            position_mode,
+            td_mode: td_mode.to_string(),
+            orders: Default::default(),
+        }
+    }
+
+    fn parse_order_id(&self, cl_ord_id: &str) -> Result<OrderId, HandleError> {
+        if !cl_ord_id.starts_with(&self.prefix) || cl_ord_id.len() < self.prefix.len() + RAND_ID_LEN
+        {
+            return Err(HandleError::PrefixUnmatched);
+        }
+        cl_ord_id[(self.prefix.len() + RAND_ID_LEN)..]
+            .parse()
+            .map_err(HandleError::InvalidOrderId)
+    }
+
+    /// Updates the order with the push of the `orders` channel, which also carries the last fill.
+    pub fn update_order(&mut self, data: &PrivateOrder) -> Result<(usize, Order), HandleError> {
+        let order_id = self.parse_order_id(&data.cl_ord_id)?;
+        let (asset_no, _cl_ord_id, order) = self
+            .orders
+            .get_mut(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        order.req = Status::None;
+        order.status = data.state;
+        order.leaves_qty = data.sz - data.acc_fill_sz;
+        order.exch_timestamp = data.u_time * 1_000_000;
+        if let (Some(fill_px), Some(fill_sz)) = (data.fill_px, data.fill_sz) {
+            if fill_sz > 0.0 {
+                order.exec_price_tick = (fill_px / order.tick_size).round() as i64;
+                order.exec_qty = fill_sz;
+            }
+        }
+        let is_active = order.active();
+        if !is_active {
+            let (asset_no, _cl_ord_id, order) = self.orders.remove(&order_id).unwrap();
+            Ok((asset_no, order))
+        } else {
+            Ok((*asset_no, order.clone()))
+        }
+    }
+
+    pub fn new_order(
+        &mut self,
+        inst_id: &str,
+        asset_no: usize,
+        order: Order,
+    ) -> Result<OkxOrder, HandleError> {
+        let price_prec = get_precision(order.tick_size);
+        let rand_id = gen_random_string(RAND_ID_LEN);
+        let ord_type = match (order.order_type, order.time_in_force) {
+            (OrdType::Market, _) => "market",
+            (OrdType::Limit, TimeInForce::GTC) => "limit",
+            (OrdType::Limit, TimeInForce::GTX) => "post_only",
+            (OrdType::Limit, TimeInForce::FOK) => "fok",
+            (OrdType::Limit, TimeInForce::IOC) => "ioc",
+            (OrdType::Limit, _) => return Err(HandleError::InvalidArg("time_in_force")),
+            // Stop orders are placed through the algo order API, which isn't supported.
+            _ => return Err(HandleError::InvalidArg("order_type")),
+        };
+        let okx_order = OkxOrder {
+            inst_id: inst_id.to_string(),
+            td_mode: Some(self.td_mode.clone()),
+            cl_ord_id: format!("{}{}{}", self.prefix, rand_id, order.order_id),
+            side: Some({
+                match order.side {
+                    Side::Buy => "buy".to_string(),
+                    Side::Sell => "sell".to_string(),
+                    Side::None | Side::Unsupported => return Err(HandleError::InvalidArg("side")),
+                }
+            }),
+            pos_side: match self.position_mode {
+                PositionMode::OneWay => None,
+                PositionMode::Hedge => match order.hedge_leg() {
+                    Side::Buy => Some("long".to_string()),
+                    _ => Some("short".to_string()),
+                },
+            },
+            ord_type: Some(ord_type.to_string()),
+            sz: Some(format!("{:.5}", order.qty)),
+            px: (order.order_type == OrdType::Limit).then(|| {
+                format!(
+                    "{:.prec$}",
+                    order.price_tick as f64 * order.tick_size,
+                    prec = price_prec
+                )
+            }),
+            // In the long/short mode, the position side determines whether the order closes the
+            // position.
+            reduce_only: (order.reduce_only && self.position_mode == PositionMode::OneWay)
+                .then_some(true),
+        };
+        match self.orders.entry(order.order_id) {
+            Entry::Occupied(_) => {
+                return Err(HandleError::OrderAlreadyExist);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((asset_no, okx_order.cl_ord_id.clone(), order));
+            }
+        }
+        Ok(okx_order)
+    }
+
+    pub fn cancel_order(
+        &mut self,
+        inst_id: &str,
+        order_id: OrderId,
+    ) -> Result<OkxOrder, HandleError> {
+        let (_, cl_ord_id, _order) = self
+            .orders
+            .get(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        let okx_order = OkxOrder {
+            inst_id: inst_id.to_string(),
+            td_mode: None,
+            cl_ord_id: cl_ord_id.clone(),
+            side: None,
+            pos_side: None,
+            ord_type: None,
+            sz: None,
+            px: None,
+            reduce_only: None,
+        };
+        Ok(okx_order)
+    }
+
+    pub fn update_submit_fail(&mut self, cl_ord_id: &str) -> Result<(usize, Order), HandleError> {
+        let order_id = self.parse_order_id(cl_ord_id)?;
+        let (asset_no, _cl_ord_id, mut order) = self
+            .orders
+            .remove(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        order.req = Status::None;
+        order.status = Status::Expired;
+        Ok((asset_no, order))
+    }
+
+    pub fn update_cancel_fail(&mut self, cl_ord_id: &str) -> Result<(usize, Order), HandleError> {
+        let order_id = self.parse_order_id(cl_ord_id)?;
+        let (asset_no, _cl_ord_id, order) = self
+            .orders
+            .get_mut(&order_id)
+            .ok_or(HandleError::OrderNotFound)?;
+        order.req = Status::None;
+        Ok((*asset_no, order.clone()))
+    }
+
+    pub fn clear_orders(&mut self) -> Vec<(usize, Order)> {
+        let mut values: Vec<(usize, Order)> = Vec::new();
+        values.extend(self.orders.drain().map(|(_, (asset_no, _, mut order))| {
+            order.status = Status::Canceled;
+            (asset_no, order)
+        }));
+        values
+    }
+}
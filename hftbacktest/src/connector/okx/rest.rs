@@ -0,0 +1,108 @@
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::connector::{
+    okx::msg::{CancelOrder, PendingOrder, Position, RestResponse},
+    util::sign_hmac_sha256_base64,
+};
+
+// The maximum number of orders that can be canceled by a single batch cancel request.
+const MAX_BATCH_CANCEL: usize = 20;
+
+#[derive(Clone)]
+pub struct OkxClient {
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    secret: String,
+    passphrase: String,
+    simulated: bool,
+}
+
+impl OkxClient {
+    pub fn new(url: &str, api_key: &str, secret: &str, passphrase: &str, simulated: bool) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            api_key: api_key.to_string(),
+            secret: secret.to_string(),
+            passphrase: passphrase.to_string(),
+            simulated,
+        }
+    }
+
+    async fn request<T: for<'a> Deserialize<'a>>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: String,
+    ) -> Result<T, reqwest::Error> {
+        let time = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+        let sign_body = format!("{time}{}{path}{body}", method.as_str());
+        let signature = sign_hmac_sha256_base64(&self.secret, &sign_body);
+        let mut req = self
+            .client
+            .request(method, format!("{}{}", self.url, path))
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .header("OK-ACCESS-KEY", &self.api_key)
+            .header("OK-ACCESS-SIGN", signature)
+            .header("OK-ACCESS-TIMESTAMP", time)
+            .header("OK-ACCESS-PASSPHRASE", &self.passphrase);
+        if self.simulated {
+            req = req.header("x-simulated-trading", "1");
+        }
+        let resp = req.body(body).send().await?.json().await?;
+        Ok(resp)
+    }
+
+    pub async fn cancel_all_orders(&self, inst_id: &str) -> Result<(), anyhow::Error> {
+        let resp: RestResponse = self
+            .request(
+                reqwest::Method::GET,
+                &format!("/api/v5/trade/orders-pending?instId={inst_id}"),
+                String::new(),
+            )
+            .await?;
+        if resp.code != "0" {
+            return Err(anyhow::Error::msg(resp.msg));
+        }
+        let orders: Vec<PendingOrder> = serde_json::from_value(resp.data)?;
+        let orders: Vec<CancelOrder> = orders
+            .into_iter()
+            .map(|order| CancelOrder {
+                inst_id: order.inst_id,
+                ord_id: order.ord_id,
+            })
+            .collect();
+        for chunk in orders.chunks(MAX_BATCH_CANCEL) {
+            let resp: RestResponse = self
+                .request(
+                    reqwest::Method::POST,
+                    "/api/v5/trade/cancel-batch-orders",
+                    serde_json::to_string(chunk)?,
+                )
+                .await?;
+            if resp.code != "0" {
+                return Err(anyhow::Error::msg(resp.msg));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn get_positions(&self, inst_id: &str) -> Result<Vec<Position>, anyhow::Error> {
+        let resp: RestResponse = self
+            .request(
+                reqwest::Method::GET,
+                &format!("/api/v5/account/positions?instId={inst_id}"),
+                String::new(),
+            )
+            .await?;
+        if resp.code != "0" {
+            Err(anyhow::Error::msg(resp.msg))
+        } else {
+            let positions: Vec<Position> = serde_json::from_value(resp.data)?;
+            Ok(positions)
+        }
+    }
+}
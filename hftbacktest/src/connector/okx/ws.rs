@@ -0,0 +1,652 @@
+use std::{collections::HashMap, sync::mpsc::Sender, time::Duration};
+
+use chrono::Utc;
+use futures_util::{stream::SplitSink, SinkExt, StreamExt};
+use tokio::{net::TcpStream, select, sync::mpsc::UnboundedReceiver, time};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, Message},
+    MaybeTlsStream,
+    WebSocketStream,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    connector::{
+        okx::{
+            msg::{
+                Arg,
+                EventMsg,
+                LoginArg,
+                Op,
+                OpResponse,
+                Order as OkxOrder,
+                OrderBook,
+                Position,
+                PrivateOrder,
+                StreamMsg,
+                Trade,
+            },
+            ordermanager::{HandleError, OrderManagerWrapper},
+            position_event,
+            OkxError,
+        },
+        util::sign_hmac_sha256_base64,
+    },
+    depth::{DepthChecksum, HashMapMarketDepth, L2MarketDepth},
+    live::Asset,
+    prelude::get_precision,
+    types::{
+        ErrorKind,
+        Event,
+        LiveError,
+        LiveEvent,
+        Side,
+        LOCAL_ASK_DEPTH_BBO_EVENT,
+        LOCAL_ASK_DEPTH_EVENT,
+        LOCAL_BID_DEPTH_BBO_EVENT,
+        LOCAL_BID_DEPTH_EVENT,
+        LOCAL_BUY_TRADE_EVENT,
+        LOCAL_DEPTH_CLEAR_EVENT,
+        LOCAL_SELL_TRADE_EVENT,
+    },
+};
+
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+
+pub struct OrderOp {
+    pub op: String,
+    pub okx_order: OkxOrder,
+}
+
+/// Keeps track of the order book stream of an instrument to detect a lost update.
+struct BookState {
+    seq_id: i64,
+    /// The order book maintained to verify the checksum, if enabled.
+    depth: Option<HashMapMarketDepth>,
+}
+
+fn event(ev: u64, exch_ts: i64, px: f64, qty: f64) -> Event {
+    Event {
+        ev,
+        exch_ts,
+        local_ts: Utc::now().timestamp_nanos_opt().unwrap(),
+        order_id: 0,
+        px,
+        qty,
+        ival: 0,
+        fval: 0.0,
+    }
+}
+
+fn parse_levels(levels: &[Vec<String>]) -> Result<Vec<(f64, f64)>, HandleError> {
+    let mut levels_ = Vec::with_capacity(levels.len());
+    for level in levels {
+        match level.as_slice() {
+            [px, qty, ..] => levels_.push((px.parse()?, qty.parse()?)),
+            _ => return Err(HandleError::InvalidArg("level")),
+        }
+    }
+    Ok(levels_)
+}
+
+/// The API key, the secret key, and the passphrase used to log in to the WebSocket streams.
+#[derive(Clone)]
+pub struct Credentials {
+    pub api_key: String,
+    pub secret: String,
+    pub passphrase: String,
+}
+
+fn login_op(credentials: &Credentials) -> String {
+    let timestamp = Utc::now().timestamp().to_string();
+    let sign = sign_hmac_sha256_base64(
+        &credentials.secret,
+        &format!("{timestamp}GET/users/self/verify"),
+    );
+    let op = Op {
+        id: None,
+        op: "login".to_string(),
+        args: vec![LoginArg {
+            api_key: credentials.api_key.clone(),
+            passphrase: credentials.passphrase.clone(),
+            timestamp,
+            sign,
+        }],
+    };
+    serde_json::to_string(&op).unwrap()
+}
+
+fn subscribe_op(args: Vec<Arg>) -> String {
+    let op = Op {
+        id: None,
+        op: "subscribe".to_string(),
+        args,
+    };
+    serde_json::to_string(&op).unwrap()
+}
+
+// The error codes of the login failures, which cannot be recovered by reconnecting: invalid
+// timestamp, invalid API key, expired timestamp, invalid signature, login failure, and wrong
+// passphrase.
+const AUTH_ERROR_CODES: [&str; 6] = ["60004", "60005", "60006", "60007", "60009", "60024"];
+
+/// Returns `true` if the event is the successful login. A login failure is reported as a critical
+/// error.
+fn check_login(resp: &EventMsg, ev_tx: &Sender<LiveEvent>) -> bool {
+    match resp.event.as_str() {
+        "login" if resp.code.as_deref().unwrap_or("0") == "0" => true,
+        "login" | "error" => {
+            let code = resp.code.as_deref().unwrap_or_default();
+            let msg = resp.msg.clone().unwrap_or_default();
+            error!(%code, %msg, "Login or subscription failed.");
+            if AUTH_ERROR_CODES.contains(&code) {
+                ev_tx
+                    .send(LiveEvent::Error(LiveError::with(
+                        ErrorKind::CriticalConnectionError,
+                        OkxError::AuthError(code.parse().unwrap_or_default(), msg),
+                    )))
+                    .unwrap();
+            }
+            false
+        }
+        _ => {
+            info!(?resp, "Event");
+            false
+        }
+    }
+}
+
+fn handle_book(
+    arg: &Arg,
+    snapshot: bool,
+    data: OrderBook,
+    ev_tx: &Sender<LiveEvent>,
+    assets: &HashMap<String, Asset>,
+    books: &mut HashMap<String, BookState>,
+    verify_checksum: bool,
+) -> Result<(), HandleError> {
+    let inst_id = arg.inst_id.as_ref().ok_or(HandleError::AssetNotFound)?;
+    let asset = assets.get(inst_id).ok_or(HandleError::AssetNotFound)?;
+    let bids = parse_levels(&data.bids)?;
+    let asks = parse_levels(&data.asks)?;
+    let exch_ts = data.ts * 1_000_000;
+
+    let mut events = Vec::with_capacity(bids.len() + asks.len() + 1);
+    if snapshot {
+        // The snapshot replaces the entire order book.
+        events.push(event(LOCAL_DEPTH_CLEAR_EVENT, exch_ts, 0.0, 0.0));
+        books.insert(
+            inst_id.clone(),
+            BookState {
+                seq_id: data.seq_id,
+                depth: verify_checksum
+                    .then(|| HashMapMarketDepth::new(asset.tick_size, asset.lot_size)),
+            },
+        );
+    } else {
+        let book = books
+            .get_mut(inst_id)
+            .ok_or(HandleError::SequenceGap(-1, data.prev_seq_id))?;
+        if data.prev_seq_id != book.seq_id {
+            return Err(HandleError::SequenceGap(book.seq_id, data.prev_seq_id));
+        }
+        book.seq_id = data.seq_id;
+    }
+    events.extend(
+        bids.iter()
+            .map(|&(px, qty)| event(LOCAL_BID_DEPTH_EVENT, exch_ts, px, qty)),
+    );
+    events.extend(
+        asks.iter()
+            .map(|&(px, qty)| event(LOCAL_ASK_DEPTH_EVENT, exch_ts, px, qty)),
+    );
+
+    if let (Some(depth), Some(checksum)) = (
+        books.get_mut(inst_id).and_then(|book| book.depth.as_mut()),
+        data.checksum,
+    ) {
+        for &(px, qty) in &bids {
+            depth.update_bid_depth(px, qty, exch_ts);
+        }
+        for &(px, qty) in &asks {
+            depth.update_ask_depth(px, qty, exch_ts);
+        }
+        let local_checksum = depth.okx_checksum(
+            get_precision(asset.tick_size),
+            get_precision(asset.lot_size),
+        );
+        if local_checksum != checksum {
+            books.remove(inst_id);
+            return Err(HandleError::ChecksumMismatch(checksum, local_checksum));
+        }
+    }
+
+    ev_tx
+        .send(LiveEvent::FeedBatch {
+            asset_no: asset.asset_no,
+            events,
+        })
+        .unwrap();
+    Ok(())
+}
+
+async fn handle_public_stream(
+    text: &str,
+    write: &mut WsSink,
+    ev_tx: &Sender<LiveEvent>,
+    assets: &HashMap<String, Asset>,
+    channels: &[String],
+    books: &mut HashMap<String, BookState>,
+    verify_checksum: bool,
+) -> Result<(), HandleError> {
+    if text == "pong" {
+        return Ok(());
+    }
+    let stream = serde_json::from_str::<StreamMsg>(text)?;
+    match stream {
+        StreamMsg::Event(resp) => {
+            if check_login(&resp, ev_tx) {
+                let mut args = Vec::new();
+                for channel in channels {
+                    for inst_id in assets.keys() {
+                        args.push(Arg {
+                            channel: channel.clone(),
+                            inst_id: Some(inst_id.clone()),
+                            inst_type: None,
+                        });
+                    }
+                }
+                write.send(Message::Text(subscribe_op(args))).await?;
+            }
+        }
+        StreamMsg::Push(stream) => {
+            if stream.arg.channel == "bbo-tbt" {
+                let data: Vec<OrderBook> = serde_json::from_value(stream.data)?;
+                let inst_id = stream
+                    .arg
+                    .inst_id
+                    .as_ref()
+                    .ok_or(HandleError::AssetNotFound)?;
+                let asset = assets.get(inst_id).ok_or(HandleError::AssetNotFound)?;
+                for item in data {
+                    let exch_ts = item.ts * 1_000_000;
+                    let mut events = Vec::new();
+                    for (px, qty) in parse_levels(&item.bids)? {
+                        events.push(event(LOCAL_BID_DEPTH_BBO_EVENT, exch_ts, px, qty));
+                    }
+                    for (px, qty) in parse_levels(&item.asks)? {
+                        events.push(event(LOCAL_ASK_DEPTH_BBO_EVENT, exch_ts, px, qty));
+                    }
+                    ev_tx
+                        .send(LiveEvent::FeedBatch {
+                            asset_no: asset.asset_no,
+                            events,
+                        })
+                        .unwrap();
+                }
+            } else if stream.arg.channel.starts_with("books") && stream.action.is_some() {
+                let snapshot = stream.action.as_deref() == Some("snapshot");
+                let data: Vec<OrderBook> = serde_json::from_value(stream.data)?;
+                for item in data {
+                    handle_book(
+                        &stream.arg,
+                        snapshot,
+                        item,
+                        ev_tx,
+                        assets,
+                        books,
+                        verify_checksum,
+                    )?;
+                }
+            } else if stream.arg.channel == "trades" {
+                let data: Vec<Trade> = serde_json::from_value(stream.data)?;
+                for item in data {
+                    let asset_info = assets
+                        .get(&item.inst_id)
+                        .ok_or(HandleError::AssetNotFound)?;
+                    ev_tx
+                        .send(LiveEvent::FeedBatch {
+                            asset_no: asset_info.asset_no,
+                            events: vec![event(
+                                if item.side == Side::Sell {
+                                    LOCAL_SELL_TRADE_EVENT
+                                } else {
+                                    LOCAL_BUY_TRADE_EVENT
+                                },
+                                item.ts * 1_000_000,
+                                item.px,
+                                item.sz,
+                            )],
+                        })
+                        .unwrap();
+                }
+            }
+        }
+        StreamMsg::OpResponse(resp) => {
+            info!(?resp, "OpResponse");
+        }
+    }
+    Ok(())
+}
+
+pub async fn connect_public(
+    url: &str,
+    credentials: &Credentials,
+    ev_tx: Sender<LiveEvent>,
+    assets: HashMap<String, Asset>,
+    channels: Vec<String>,
+    verify_checksum: bool,
+) -> Result<(), HandleError> {
+    let request = url.into_client_request()?;
+    let (ws_stream, _) = connect_async(request).await?;
+    let (mut write, mut read) = ws_stream.split();
+    let mut interval = time::interval(Duration::from_secs(15));
+    let mut books = HashMap::new();
+
+    // The tick-by-tick order book channels require the login. The channels are subscribed once
+    // the login succeeds.
+    write.send(Message::Text(login_op(credentials))).await?;
+
+    loop {
+        select! {
+            _ = interval.tick() => {
+                write.send(Message::Text("ping".to_string())).await?;
+            }
+            message = read.next() => {
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        match handle_public_stream(
+                            &text,
+                            &mut write,
+                            &ev_tx,
+                            &assets,
+                            &channels,
+                            &mut books,
+                            verify_checksum
+                        ).await {
+                            Ok(_) => {}
+                            Err(error @ HandleError::SequenceGap(..))
+                            | Err(error @ HandleError::ChecksumMismatch(..)) => {
+                                // Reconnects to receive a new snapshot.
+                                return Err(error);
+                            }
+                            Err(error) => {
+                                error!(?error, %text, "Couldn't handle PublicStreamMsg.");
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {}
+                    Some(Ok(Message::Ping(_))) => {
+                        write.send(Message::Pong(Vec::new())).await?;
+                    }
+                    Some(Ok(Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(close_frame))) => {
+                        info!(?close_frame, "close");
+                        break;
+                    }
+                    Some(Ok(Message::Frame(_))) => {}
+                    Some(Err(e)) => {
+                        return Err(HandleError::from(e));
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_private_stream(
+    text: &str,
+    write: &mut WsSink,
+    assets: &HashMap<String, Asset>,
+    ev_tx: &Sender<LiveEvent>,
+    order_man: &OrderManagerWrapper,
+) -> Result<(), HandleError> {
+    if text == "pong" {
+        return Ok(());
+    }
+    let stream = serde_json::from_str::<StreamMsg>(text)?;
+    match stream {
+        StreamMsg::Event(resp) => {
+            debug!(?resp, "Event");
+            if check_login(&resp, ev_tx) {
+                let args = ["orders", "positions"]
+                    .into_iter()
+                    .map(|channel| Arg {
+                        channel: channel.to_string(),
+                        inst_id: None,
+                        inst_type: Some("ANY".to_string()),
+                    })
+                    .collect();
+                write.send(Message::Text(subscribe_op(args))).await?;
+            }
+        }
+        StreamMsg::Push(stream) => {
+            if stream.arg.channel == "positions" {
+                let data: Vec<Position> = serde_json::from_value(stream.data)?;
+                debug!(?data, "Position");
+                for item in data {
+                    // Positions of the instruments that aren't traded through this connector
+                    // are also pushed.
+                    if let Some(asset) = assets.get(&item.inst_id) {
+                        ev_tx.send(position_event(asset.asset_no, &item)).unwrap();
+                    }
+                }
+            } else if stream.arg.channel == "orders" {
+                let data: Vec<PrivateOrder> = serde_json::from_value(stream.data)?;
+                debug!(?data, "Order");
+                let mut order_man_ = order_man.lock().unwrap();
+                for item in &data {
+                    match order_man_.update_order(item) {
+                        Ok((asset_no, order)) => {
+                            ev_tx.send(LiveEvent::Order { asset_no, order }).unwrap();
+                        }
+                        Err(HandleError::PrefixUnmatched) => {
+                            warn!(?item, "PrefixUnmatched");
+                        }
+                        Err(error) => {
+                            error!(?error, ?item, "Couldn't update the order data");
+                        }
+                    }
+                }
+            }
+        }
+        StreamMsg::OpResponse(resp) => {
+            info!(?resp, "OpResponse");
+        }
+    }
+    Ok(())
+}
+
+pub async fn connect_private(
+    url: &str,
+    credentials: &Credentials,
+    ev_tx: Sender<LiveEvent>,
+    assets: HashMap<String, Asset>,
+    order_man: OrderManagerWrapper,
+) -> Result<(), HandleError> {
+    let request = url.into_client_request()?;
+    let (ws_stream, _) = connect_async(request).await?;
+    let (mut write, mut read) = ws_stream.split();
+    let mut interval = time::interval(Duration::from_secs(15));
+
+    write.send(Message::Text(login_op(credentials))).await?;
+
+    loop {
+        select! {
+            _ = interval.tick() => {
+                write.send(Message::Text("ping".to_string())).await?;
+            }
+            message = read.next() => {
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Err(error) = handle_private_stream(
+                            &text,
+                            &mut write,
+                            &assets,
+                            &ev_tx,
+                            &order_man
+                        ).await {
+                            error!(%text, ?error, "Couldn't properly handle PrivateStreamMsg");
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {}
+                    Some(Ok(Message::Ping(_))) => {
+                        write.send(Message::Pong(Vec::new())).await?;
+                    }
+                    Some(Ok(Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(close_frame))) => {
+                        info!(?close_frame, "close");
+                        break;
+                    }
+                    Some(Ok(Message::Frame(_))) => {}
+                    Some(Err(e)) => {
+                        return Err(HandleError::from(e));
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn connect_trade(
+    url: &str,
+    credentials: &Credentials,
+    ev_tx: Sender<LiveEvent>,
+    order_rx: &mut UnboundedReceiver<OrderOp>,
+    order_man: OrderManagerWrapper,
+) -> Result<(), HandleError> {
+    let request = url.into_client_request()?;
+    let (ws_stream, _) = connect_async(request).await?;
+    let (mut write, mut read) = ws_stream.split();
+    let mut interval = time::interval(Duration::from_secs(15));
+    let mut logged_in = false;
+
+    write.send(Message::Text(login_op(credentials))).await?;
+
+    loop {
+        select! {
+            _ = interval.tick() => {
+                write.send(Message::Text("ping".to_string())).await?;
+            }
+            // The orders are held until the login succeeds, since they are rejected otherwise.
+            order = order_rx.recv(), if logged_in => {
+                match order {
+                    Some(order) => {
+                        // The message id is echoed in the response, which identifies the order
+                        // even if the response doesn't have the order data.
+                        let op = Op {
+                            id: Some(order.okx_order.cl_ord_id.clone()),
+                            op: order.op,
+                            args: vec![order.okx_order],
+                        };
+                        let s = serde_json::to_string(&op).unwrap();
+                        write.send(Message::Text(s)).await?;
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+            message = read.next() => {
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Err(error) = handle_trade_stream(
+                            &text,
+                            &ev_tx,
+                            &order_man,
+                            &mut logged_in
+                        ) {
+                            error!(?error, %text, "Couldn't properly handle TradeStreamMsg.");
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {}
+                    Some(Ok(Message::Ping(_))) => {
+                        write.send(Message::Pong(Vec::new())).await?;
+                    }
+                    Some(Ok(Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(close_frame))) => {
+                        info!(?close_frame, "close");
+                        break;
+                    }
+                    Some(Ok(Message::Frame(_))) => {}
+                    Some(Err(e)) => {
+                        return Err(HandleError::from(e));
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_order_response(
+    resp: OpResponse,
+    ev_tx: &Sender<LiveEvent>,
+    order_man: &OrderManagerWrapper,
+) -> Result<(), HandleError> {
+    if resp.code == "0" {
+        // The order updates are delivered through the `orders` channel.
+        return Ok(());
+    }
+    let (code, msg) = match resp.data.first() {
+        Some(data) if data.s_code != "0" => (data.s_code.clone(), data.s_msg.clone()),
+        _ => (resp.code.clone(), resp.msg.clone()),
+    };
+    let cl_ord_id = resp.id.ok_or(HandleError::OrderNotFound)?;
+    let mut order_man_ = order_man.lock().unwrap();
+    let (asset_no, order) = if resp.op == "order" {
+        order_man_.update_submit_fail(&cl_ord_id)?
+    } else {
+        order_man_.update_cancel_fail(&cl_ord_id)?
+    };
+    ev_tx.send(LiveEvent::Order { asset_no, order }).unwrap();
+    ev_tx
+        .send(LiveEvent::Error(LiveError::with(
+            ErrorKind::OrderError,
+            OkxError::OrderError(code.parse().unwrap_or_default(), msg),
+        )))
+        .unwrap();
+    Ok(())
+}
+
+fn handle_trade_stream(
+    text: &str,
+    ev_tx: &Sender<LiveEvent>,
+    order_man: &OrderManagerWrapper,
+    logged_in: &mut bool,
+) -> Result<(), HandleError> {
+    if text == "pong" {
+        return Ok(());
+    }
+    let stream = serde_json::from_str::<StreamMsg>(text)?;
+    match stream {
+        StreamMsg::Event(resp) => {
+            if check_login(&resp, ev_tx) {
+                *logged_in = true;
+            }
+        }
+        StreamMsg::OpResponse(resp) => {
+            if resp.op == "order" || resp.op == "cancel-order" {
+                handle_order_response(resp, ev_tx, order_man)?;
+            } else {
+                info!(?resp, "trade stream");
+            }
+        }
+        StreamMsg::Push(stream) => {
+            info!(?stream, "trade stream");
+        }
+    }
+    Ok(())
+}
@@ -109,6 +109,35 @@ pub fn sign_hmac_sha256(secret: &str, s: &str) -> String {
     tmp
 }
 
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut tmp = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                tmp.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                tmp.push('=');
+            }
+        }
+    }
+    tmp
+}
+
+/// Signs the string with HMAC-SHA256 and encodes the signature in Base64, as OKX requires.
+pub fn sign_hmac_sha256_base64(secret: &str, s: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(s.as_bytes());
+    encode_base64(&mac.finalize().into_bytes())
+}
+
 pub fn gen_random_string(len: usize) -> String {
     rand::thread_rng()
         .sample_iter(&Alphanumeric)
@@ -0,0 +1,375 @@
+use std::io::{BufRead, Error, ErrorKind, Result};
+
+use serde::Deserialize;
+
+use crate::{
+    data::convert::{
+        correct_event_order,
+        correct_local_timestamp,
+        open_input,
+        validate_event_order,
+        write_output,
+    },
+    types::{
+        Event,
+        BUY_EVENT,
+        DEPTH_BBO_EVENT,
+        DEPTH_CLEAR_EVENT,
+        DEPTH_EVENT,
+        DEPTH_SNAPSHOT_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    },
+};
+
+// Binance timestamps are in milliseconds, whereas the recorded local timestamps are in
+// nanoseconds.
+const TIMESTAMP_MUL: i64 = 1_000_000;
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "e")]
+enum Data {
+    #[serde(rename = "depthUpdate")]
+    DepthUpdate(Depth),
+    #[serde(rename = "trade")]
+    Trade(Trade),
+    #[serde(rename = "aggTrade")]
+    AggTrade(Trade),
+    #[serde(rename = "bookTicker")]
+    BookTicker(BookTicker),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+struct Depth {
+    #[serde(rename = "T")]
+    transaction_time: i64,
+    #[serde(rename = "u")]
+    last_update_id: i64,
+    #[serde(rename = "b")]
+    bids: Vec<(String, String)>,
+    #[serde(rename = "a")]
+    asks: Vec<(String, String)>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Trade {
+    #[serde(rename = "T")]
+    transaction_time: i64,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "q")]
+    qty: String,
+    // Only the `trade` stream has the order type.
+    #[serde(rename = "X")]
+    type_: Option<String>,
+    #[serde(rename = "m")]
+    is_the_buyer_the_market_maker: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct BookTicker {
+    #[serde(rename = "T")]
+    transaction_time: i64,
+    #[serde(rename = "b")]
+    bid_price: String,
+    #[serde(rename = "B")]
+    bid_qty: String,
+    #[serde(rename = "a")]
+    ask_price: String,
+    #[serde(rename = "A")]
+    ask_qty: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Snapshot {
+    #[serde(rename = "lastUpdateId")]
+    last_update_id: i64,
+    #[serde(rename = "T")]
+    transaction_time: i64,
+    bids: Vec<(String, String)>,
+    asks: Vec<(String, String)>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Message {
+    Combined { data: Data },
+    Snapshot(Snapshot),
+    Regular(Data),
+}
+
+fn parse_f64(s: &str) -> Result<f64> {
+    s.parse::<f64>()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("invalid number `{s}`")))
+}
+
+fn event(ev: u64, exch_ts: i64, local_ts: i64, px: f64, qty: f64) -> Event {
+    Event {
+        ev,
+        exch_ts,
+        local_ts,
+        px,
+        qty,
+        order_id: 0,
+        ival: 0,
+        fval: 0.0,
+    }
+}
+
+/// Converts the raw Binance Futures feed stream recorded by the collector into [`Event`] data.
+///
+/// Each line of the input consists of the local timestamp in nanoseconds followed by the raw
+/// message, which is either a combined or regular stream message of `depthUpdate`, `trade`,
+/// `aggTrade`, or `bookTicker`, or a depth snapshot from the REST API.
+///
+/// ```text
+/// 1660228023037049000 {"stream":"btcusdt@depth@0ms","data":{"e":"depthUpdate","E":1660228023941,"T":1660228023931,"s":"BTCUSDT","U":1801732831593,"u":1801732832589,"pu":1801732831561,"b":[["24427.70","4.350"]],"a":[["24671.00","20.812"]]}}
+/// 1660228023043260000 {"stream":"btcusdt@trade","data":{"e":"trade","E":1660228023980,"T":1660228023973,"s":"BTCUSDT","t":2691833663,"p":"24670.90","q":"0.022","X":"MARKET","m":true}}
+/// ```
+///
+/// A depth snapshot clears the market depth up to the farthest price in the snapshot and then
+/// inserts the snapshot. Depth updates received after the snapshot that are already reflected in
+/// it, whose last update ID is less than the snapshot's, are dropped so that stale quantities are
+/// not applied over the snapshot.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::data::convert::binancefutures::Converter;
+///
+/// let events = Converter::new()
+///     .base_latency(1_000_000)
+///     .convert_file("btcusdt_20240501.gz", Some("btcusdt_20240501.npy"))
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Converter {
+    base_latency: i64,
+    book_ticker: bool,
+}
+
+impl Converter {
+    /// Constructs an instance of `Converter`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the value added to the feed latency when the local timestamps are corrected. See
+    /// [`correct_local_timestamp`](crate::data::convert::correct_local_timestamp). The default
+    /// value is `0`.
+    pub fn base_latency(self, base_latency: i64) -> Self {
+        Self {
+            base_latency,
+            ..self
+        }
+    }
+
+    /// Sets whether to convert the `bookTicker` stream into [`DEPTH_BBO_EVENT`] events. The
+    /// default value is `false`.
+    pub fn book_ticker(self, book_ticker: bool) -> Self {
+        Self {
+            book_ticker,
+            ..self
+        }
+    }
+
+    /// Converts the raw feed stream into [`Event`] data, with the local timestamps and the event
+    /// order corrected.
+    pub fn convert<R: BufRead>(&self, reader: R) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        // The last update ID of the most recent snapshot, until a depth update that follows it is
+        // received.
+        let mut snapshot_update_id = None;
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (local_ts, message) = line
+                .split_once(' ')
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "missing local timestamp"))?;
+            let local_ts = local_ts.parse::<i64>().map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid local timestamp `{local_ts}`"),
+                )
+            })?;
+            let data = match serde_json::from_str::<Message>(message)? {
+                Message::Combined { data } | Message::Regular(data) => data,
+                Message::Snapshot(snapshot) => {
+                    let exch_ts = snapshot.transaction_time * TIMESTAMP_MUL;
+                    for (side, levels) in
+                        [(BUY_EVENT, &snapshot.bids), (SELL_EVENT, &snapshot.asks)]
+                    {
+                        if let Some((clear_upto, _)) = levels.last() {
+                            // Clears the existing market depth up to the farthest price in the
+                            // snapshot.
+                            events.push(event(
+                                DEPTH_CLEAR_EVENT | side,
+                                exch_ts,
+                                local_ts,
+                                parse_f64(clear_upto)?,
+                                0.0,
+                            ));
+                            for (px, qty) in levels {
+                                events.push(event(
+                                    DEPTH_SNAPSHOT_EVENT | side,
+                                    exch_ts,
+                                    local_ts,
+                                    parse_f64(px)?,
+                                    parse_f64(qty)?,
+                                ));
+                            }
+                        }
+                    }
+                    snapshot_update_id = Some(snapshot.last_update_id);
+                    continue;
+                }
+            };
+            match data {
+                Data::DepthUpdate(depth) => {
+                    if let Some(update_id) = snapshot_update_id {
+                        if depth.last_update_id < update_id {
+                            continue;
+                        }
+                        snapshot_update_id = None;
+                    }
+                    let exch_ts = depth.transaction_time * TIMESTAMP_MUL;
+                    for (side, levels) in [(BUY_EVENT, &depth.bids), (SELL_EVENT, &depth.asks)] {
+                        for (px, qty) in levels {
+                            events.push(event(
+                                DEPTH_EVENT | side,
+                                exch_ts,
+                                local_ts,
+                                parse_f64(px)?,
+                                parse_f64(qty)?,
+                            ));
+                        }
+                    }
+                }
+                Data::Trade(trade) | Data::AggTrade(trade) => {
+                    if matches!(&trade.type_, Some(type_) if type_ != "MARKET") {
+                        continue;
+                    }
+                    // The side is the trade initiator's side.
+                    let side = if trade.is_the_buyer_the_market_maker {
+                        SELL_EVENT
+                    } else {
+                        BUY_EVENT
+                    };
+                    events.push(event(
+                        TRADE_EVENT | side,
+                        trade.transaction_time * TIMESTAMP_MUL,
+                        local_ts,
+                        parse_f64(&trade.price)?,
+                        parse_f64(&trade.qty)?,
+                    ));
+                }
+                Data::BookTicker(ticker) => {
+                    if !self.book_ticker {
+                        continue;
+                    }
+                    let exch_ts = ticker.transaction_time * TIMESTAMP_MUL;
+                    events.push(event(
+                        DEPTH_BBO_EVENT | BUY_EVENT,
+                        exch_ts,
+                        local_ts,
+                        parse_f64(&ticker.bid_price)?,
+                        parse_f64(&ticker.bid_qty)?,
+                    ));
+                    events.push(event(
+                        DEPTH_BBO_EVENT | SELL_EVENT,
+                        exch_ts,
+                        local_ts,
+                        parse_f64(&ticker.ask_price)?,
+                        parse_f64(&ticker.ask_qty)?,
+                    ));
+                }
+                Data::Other => {}
+            }
+        }
+
+        correct_local_timestamp(&mut events, self.base_latency);
+        let events = correct_event_order(&events);
+        validate_event_order(&events)?;
+        Ok(events)
+    }
+
+    /// Converts the raw feed stream file into [`Event`] data. Files with the `.gz` extension are
+    /// decompressed. If `output_filename` is provided, the converted data is also saved to it in
+    /// `npy` format.
+    pub fn convert_file(
+        &self,
+        input_filename: &str,
+        output_filename: Option<&str>,
+    ) -> Result<Vec<Event>> {
+        let events = self.convert(open_input(input_filename)?)?;
+        if let Some(output_filename) = output_filename {
+            write_output(output_filename, &events)?;
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Converter;
+    use crate::types::{
+        BUY_EVENT,
+        DEPTH_CLEAR_EVENT,
+        DEPTH_EVENT,
+        DEPTH_SNAPSHOT_EVENT,
+        EXCH_EVENT,
+        LOCAL_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    };
+
+    #[test]
+    fn test_convert() {
+        let raw = r#"1000000000 {"stream":"btcusdt@depth@0ms","data":{"e":"depthUpdate","E":999,"T":998,"s":"BTCUSDT","U":1,"u":5,"pu":0,"b":[["100.0","1.0"]],"a":[["101.0","2.0"]]}}
+1002000000 {"lastUpdateId":8,"E":1001,"T":1000,"bids":[["100.0","3.0"],["99.0","1.0"]],"asks":[]}
+1003000000 {"e":"depthUpdate","E":1001,"T":1000,"s":"BTCUSDT","U":6,"u":7,"pu":5,"b":[["100.0","5.0"]],"a":[]}
+1004000000 {"stream":"btcusdt@depth@0ms","data":{"e":"depthUpdate","E":1003,"T":1002,"s":"BTCUSDT","U":8,"u":9,"pu":7,"b":[["99.0","0.0"]],"a":[]}}
+1005000000 {"stream":"btcusdt@trade","data":{"e":"trade","E":1004,"T":1003,"s":"BTCUSDT","t":1,"p":"100.0","q":"0.5","X":"MARKET","m":true}}
+1006000000 {"stream":"btcusdt@trade","data":{"e":"trade","E":1005,"T":1004,"s":"BTCUSDT","t":2,"p":"100.0","q":"0.5","X":"INSURANCE_FUND","m":true}}
+"#;
+        let events = Converter::new().convert(raw.as_bytes()).unwrap();
+        let summary = events
+            .iter()
+            .map(|ev| (ev.ev, ev.exch_ts, ev.px, ev.qty))
+            .collect::<Vec<_>>();
+        let both = EXCH_EVENT | LOCAL_EVENT;
+        assert_eq!(
+            summary,
+            vec![
+                (DEPTH_EVENT | BUY_EVENT | both, 998_000_000, 100.0, 1.0),
+                (DEPTH_EVENT | SELL_EVENT | both, 998_000_000, 101.0, 2.0),
+                (
+                    DEPTH_CLEAR_EVENT | BUY_EVENT | both,
+                    1_000_000_000,
+                    99.0,
+                    0.0
+                ),
+                (
+                    DEPTH_SNAPSHOT_EVENT | BUY_EVENT | both,
+                    1_000_000_000,
+                    100.0,
+                    3.0
+                ),
+                (
+                    DEPTH_SNAPSHOT_EVENT | BUY_EVENT | both,
+                    1_000_000_000,
+                    99.0,
+                    1.0
+                ),
+                // The depth update with the last update ID 7 is dropped as it precedes the
+                // snapshot.
+                (DEPTH_EVENT | BUY_EVENT | both, 1_002_000_000, 99.0, 0.0),
+                (TRADE_EVENT | SELL_EVENT | both, 1_003_000_000, 100.0, 0.5),
+            ]
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::io::{BufRead, Error, ErrorKind, Result};
+
+use serde::Deserialize;
+
+use crate::{
+    data::convert::{
+        correct_event_order,
+        correct_local_timestamp,
+        open_input,
+        validate_event_order,
+        write_output,
+    },
+    types::{
+        Event,
+        BUY_EVENT,
+        DEPTH_CLEAR_EVENT,
+        DEPTH_EVENT,
+        DEPTH_SNAPSHOT_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    },
+};
+
+// Bybit timestamps are in milliseconds, whereas the recorded local timestamps are in
+// nanoseconds.
+const TIMESTAMP_MUL: i64 = 1_000_000;
+
+#[derive(Deserialize, Debug)]
+struct Message {
+    // Subscription responses don't have a topic.
+    #[serde(default)]
+    topic: String,
+    #[serde(rename = "type", default)]
+    type_: String,
+    #[serde(default)]
+    ts: i64,
+    // The matching engine timestamp, which only the order book of derivatives has.
+    cts: Option<i64>,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct Depth {
+    #[serde(rename = "b")]
+    bids: Vec<(String, String)>,
+    #[serde(rename = "a")]
+    asks: Vec<(String, String)>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Trade {
+    #[serde(rename = "T")]
+    timestamp: i64,
+    #[serde(rename = "S")]
+    side: String,
+    #[serde(rename = "v")]
+    qty: String,
+    #[serde(rename = "p")]
+    price: String,
+}
+
+fn parse_f64(s: &str) -> Result<f64> {
+    s.parse::<f64>()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("invalid number `{s}`")))
+}
+
+fn event(ev: u64, exch_ts: i64, local_ts: i64, px: f64, qty: f64) -> Event {
+    Event {
+        ev,
+        exch_ts,
+        local_ts,
+        px,
+        qty,
+        order_id: 0,
+        ival: 0,
+        fval: 0.0,
+    }
+}
+
+/// Converts the raw Bybit feed stream recorded by the collector into [`Event`] data.
+///
+/// Each line of the input consists of the local timestamp in nanoseconds followed by the raw
+/// message of the `orderbook.{depth}.{symbol}`, such as `orderbook.50` or `orderbook.500`, or the
+/// `publicTrade.{symbol}` topic.
+///
+/// ```text
+/// 1672304484980132000 {"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1672304484978,"data":{"s":"BTCUSDT","b":[["16493.50","0.006"]],"a":[["16611.00","0"]],"u":18521288,"seq":7961638724},"cts":1672304484976}
+/// 1672304486870241000 {"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":1672304486868,"data":[{"T":1672304486865,"s":"BTCUSDT","S":"Buy","v":"0.001","p":"16578.50","L":"PlusTick","i":"20f43950-d8dd-5b31-9112-a178eb6023af","BT":false}]}
+/// ```
+///
+/// Since Bybit's order book snapshot replaces the entire order book, a snapshot message clears
+/// the market depth on both sides and then inserts the snapshot. The exchange timestamp of the
+/// order book is the matching engine timestamp, `cts`, if available, and the system timestamp,
+/// `ts`, otherwise.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::data::convert::bybit::Converter;
+///
+/// let events = Converter::new()
+///     .base_latency(1_000_000)
+///     .convert_file("btcusdt_20240501.gz", Some("btcusdt_20240501.npy"))
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Converter {
+    base_latency: i64,
+}
+
+impl Converter {
+    /// Constructs an instance of `Converter`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the value added to the feed latency when the local timestamps are corrected. See
+    /// [`correct_local_timestamp`](crate::data::convert::correct_local_timestamp). The default
+    /// value is `0`.
+    pub fn base_latency(self, base_latency: i64) -> Self {
+        Self {
+            base_latency,
+            ..self
+        }
+    }
+
+    /// Converts the raw feed stream into [`Event`] data, with the local timestamps and the event
+    /// order corrected.
+    pub fn convert<R: BufRead>(&self, reader: R) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (local_ts, message) = line
+                .split_once(' ')
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "missing local timestamp"))?;
+            let local_ts = local_ts.parse::<i64>().map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid local timestamp `{local_ts}`"),
+                )
+            })?;
+            let message = serde_json::from_str::<Message>(message)?;
+            if message.topic.starts_with("orderbook.") {
+                let depth = serde_json::from_value::<Depth>(message.data)?;
+                let exch_ts = message.cts.unwrap_or(message.ts) * TIMESTAMP_MUL;
+                let ev = if message.type_ == "snapshot" {
+                    events.push(event(DEPTH_CLEAR_EVENT, exch_ts, local_ts, 0.0, 0.0));
+                    DEPTH_SNAPSHOT_EVENT
+                } else {
+                    DEPTH_EVENT
+                };
+                for (side, levels) in [(BUY_EVENT, &depth.bids), (SELL_EVENT, &depth.asks)] {
+                    for (px, qty) in levels {
+                        events.push(event(
+                            ev | side,
+                            exch_ts,
+                            local_ts,
+                            parse_f64(px)?,
+                            parse_f64(qty)?,
+                        ));
+                    }
+                }
+            } else if message.topic.starts_with("publicTrade.") {
+                let trades = serde_json::from_value::<Vec<Trade>>(message.data)?;
+                for trade in trades {
+                    // The side is the taker's side.
+                    let side = match trade.side.as_str() {
+                        "Buy" => BUY_EVENT,
+                        "Sell" => SELL_EVENT,
+                        _ => 0,
+                    };
+                    events.push(event(
+                        TRADE_EVENT | side,
+                        trade.timestamp * TIMESTAMP_MUL,
+                        local_ts,
+                        parse_f64(&trade.price)?,
+                        parse_f64(&trade.qty)?,
+                    ));
+                }
+            }
+        }
+
+        correct_local_timestamp(&mut events, self.base_latency);
+        let events = correct_event_order(&events);
+        validate_event_order(&events)?;
+        Ok(events)
+    }
+
+    /// Converts the raw feed stream file into [`Event`] data. Files with the `.gz` extension are
+    /// decompressed. If `output_filename` is provided, the converted data is also saved to it in
+    /// `npy` format.
+    pub fn convert_file(
+        &self,
+        input_filename: &str,
+        output_filename: Option<&str>,
+    ) -> Result<Vec<Event>> {
+        let events = self.convert(open_input(input_filename)?)?;
+        if let Some(output_filename) = output_filename {
+            write_output(output_filename, &events)?;
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Converter;
+    use crate::types::{
+        BUY_EVENT,
+        DEPTH_CLEAR_EVENT,
+        DEPTH_EVENT,
+        DEPTH_SNAPSHOT_EVENT,
+        EXCH_EVENT,
+        LOCAL_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    };
+
+    #[test]
+    fn test_convert() {
+        let raw = r#"1000000000 {"success":true,"ret_msg":"","conn_id":"1","op":"subscribe"}
+1001000000 {"topic":"orderbook.50.BTCUSDT","type":"snapshot","ts":999,"data":{"s":"BTCUSDT","b":[["100.0","1.0"]],"a":[["101.0","2.0"]],"u":1,"seq":1},"cts":998}
+1002000000 {"topic":"orderbook.50.BTCUSDT","type":"delta","ts":1001,"data":{"s":"BTCUSDT","b":[["100.0","0"]],"a":[],"u":2,"seq":2}}
+1003000000 {"topic":"publicTrade.BTCUSDT","type":"snapshot","ts":1002,"data":[{"T":1001,"s":"BTCUSDT","S":"Sell","v":"0.5","p":"100.0","L":"MinusTick","i":"a","BT":false},{"T":1001,"s":"BTCUSDT","S":"Buy","v":"0.1","p":"101.0","L":"PlusTick","i":"b","BT":false}]}
+"#;
+        let events = Converter::new().convert(raw.as_bytes()).unwrap();
+        let summary = events
+            .iter()
+            .map(|ev| (ev.ev, ev.exch_ts, ev.px, ev.qty))
+            .collect::<Vec<_>>();
+        let both = EXCH_EVENT | LOCAL_EVENT;
+        assert_eq!(
+            summary,
+            vec![
+                (DEPTH_CLEAR_EVENT | both, 998_000_000, 0.0, 0.0),
+                (
+                    DEPTH_SNAPSHOT_EVENT | BUY_EVENT | both,
+                    998_000_000,
+                    100.0,
+                    1.0
+                ),
+                (
+                    DEPTH_SNAPSHOT_EVENT | SELL_EVENT | both,
+                    998_000_000,
+                    101.0,
+                    2.0
+                ),
+                (DEPTH_EVENT | BUY_EVENT | both, 1_001_000_000, 100.0, 0.0),
+                (TRADE_EVENT | SELL_EVENT | both, 1_001_000_000, 100.0, 0.5),
+                (TRADE_EVENT | BUY_EVENT | both, 1_001_000_000, 101.0, 0.1),
+            ]
+        );
+    }
+}
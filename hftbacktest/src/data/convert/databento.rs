@@ -0,0 +1,316 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Error, ErrorKind, Read, Result},
+};
+
+use dbn::{
+    decode::{DbnDecoder, DecodeRecord},
+    MboMsg,
+    Mbp10Msg,
+    FIXED_PRICE_SCALE,
+    UNDEF_PRICE,
+};
+
+use crate::{
+    data::convert::{
+        correct_event_order,
+        correct_local_timestamp,
+        validate_event_order,
+        write_output,
+    },
+    types::{
+        Event,
+        ADD_ORDER_EVENT,
+        BUY_EVENT,
+        CANCEL_ORDER_EVENT,
+        DEPTH_CLEAR_EVENT,
+        DEPTH_EVENT,
+        FILL_EVENT,
+        MODIFY_ORDER_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    },
+};
+
+fn to_io_error(error: dbn::Error) -> Error {
+    Error::new(ErrorKind::InvalidData, error)
+}
+
+fn to_px(price: i64) -> f64 {
+    price as f64 / FIXED_PRICE_SCALE as f64
+}
+
+fn to_side(side: i8) -> u64 {
+    match side as u8 {
+        b'B' => BUY_EVENT,
+        b'A' => SELL_EVENT,
+        _ => 0,
+    }
+}
+
+fn event(ev: u64, exch_ts: u64, local_ts: u64, px: f64, qty: f64, order_id: u64) -> Event {
+    Event {
+        ev,
+        exch_ts: exch_ts as i64,
+        local_ts: local_ts as i64,
+        px,
+        qty,
+        order_id,
+        ival: 0,
+        fval: 0.0,
+    }
+}
+
+fn open_decoder(filepath: &str) -> Result<DbnDecoder<Box<dyn Read>>> {
+    let file = BufReader::new(File::open(filepath)?);
+    if filepath.ends_with(".zst") {
+        let reader: Box<dyn Read> = Box::new(zstd::stream::Decoder::with_buffer(file)?);
+        DbnDecoder::new(reader).map_err(to_io_error)
+    } else {
+        DbnDecoder::new(Box::new(file) as Box<dyn Read>).map_err(to_io_error)
+    }
+}
+
+/// Converts Databento DBN data into [`Event`] data.
+///
+/// The exchange timestamp is taken from `ts_event` and the local timestamp from `ts_recv`, the
+/// time at which Databento's capture server received the message. Both are in nanoseconds.
+///
+/// * MBP-10 records are converted into Level-2 Market-By-Price [`DEPTH_EVENT`]s for the price
+///   levels that change between consecutive records, and trade actions into [`TRADE_EVENT`]s.
+/// * MBO records are converted into Level-3 Market-By-Order events: [`ADD_ORDER_EVENT`],
+///   [`MODIFY_ORDER_EVENT`], [`CANCEL_ORDER_EVENT`], [`FILL_EVENT`], and [`TRADE_EVENT`]. Since
+///   Databento's cancel action can partially cancel an order, a partial cancel is converted into
+///   a [`MODIFY_ORDER_EVENT`] with the remaining quantity. Level-3 events require the
+///   `unstable_l3` feature to be backtested.
+///
+/// A clear action is converted into a [`DEPTH_CLEAR_EVENT`] that clears both sides.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::data::convert::databento::Converter;
+///
+/// let events = Converter::new()
+///     .convert_mbo_file(
+///         "glbx-mdp3-20240501.mbo.dbn.zst",
+///         Some("esm4_20240501.npy"),
+///     )
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Converter {
+    base_latency: i64,
+}
+
+impl Converter {
+    /// Constructs an instance of `Converter`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the value added to the feed latency when the local timestamps are corrected. See
+    /// [`correct_local_timestamp`](crate::data::convert::correct_local_timestamp). The default
+    /// value is `0`.
+    pub fn base_latency(self, base_latency: i64) -> Self {
+        Self {
+            base_latency,
+            ..self
+        }
+    }
+
+    fn finish(&self, mut events: Vec<Event>) -> Result<Vec<Event>> {
+        correct_local_timestamp(&mut events, self.base_latency);
+        let events = correct_event_order(&events);
+        validate_event_order(&events)?;
+        Ok(events)
+    }
+
+    /// Converts DBN MBP-10 records into Level-2 [`Event`] data.
+    pub fn convert_mbp10<R: Read>(&self, decoder: &mut DbnDecoder<R>) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        let mut prev_bids: HashMap<i64, u32> = HashMap::new();
+        let mut prev_asks: HashMap<i64, u32> = HashMap::new();
+        while let Some(msg) = decoder.decode_record::<Mbp10Msg>().map_err(to_io_error)? {
+            let exch_ts = msg.hd.ts_event;
+            let local_ts = msg.ts_recv;
+            match msg.action as u8 {
+                b'R' => {
+                    events.push(event(DEPTH_CLEAR_EVENT, exch_ts, local_ts, 0.0, 0.0, 0));
+                    prev_bids.clear();
+                    prev_asks.clear();
+                    continue;
+                }
+                b'T' => {
+                    events.push(event(
+                        TRADE_EVENT | to_side(msg.side),
+                        exch_ts,
+                        local_ts,
+                        to_px(msg.price),
+                        msg.size as f64,
+                        0,
+                    ));
+                }
+                _ => {}
+            }
+
+            let mut bids = HashMap::new();
+            let mut asks = HashMap::new();
+            for level in &msg.levels {
+                if level.bid_px != UNDEF_PRICE && level.bid_sz > 0 {
+                    bids.insert(level.bid_px, level.bid_sz);
+                }
+                if level.ask_px != UNDEF_PRICE && level.ask_sz > 0 {
+                    asks.insert(level.ask_px, level.ask_sz);
+                }
+            }
+            for (side, curr, prev) in [
+                (BUY_EVENT, &bids, &prev_bids),
+                (SELL_EVENT, &asks, &prev_asks),
+            ] {
+                let mut changes = Vec::new();
+                for (&px, &qty) in curr {
+                    if prev.get(&px) != Some(&qty) {
+                        changes.push((px, qty));
+                    }
+                }
+                for &px in prev.keys() {
+                    if !curr.contains_key(&px) {
+                        changes.push((px, 0));
+                    }
+                }
+                // Keeps the output deterministic regardless of the hash map's iteration order.
+                changes.sort_unstable_by_key(|&(px, _)| px);
+                for (px, qty) in changes {
+                    events.push(event(
+                        DEPTH_EVENT | side,
+                        exch_ts,
+                        local_ts,
+                        to_px(px),
+                        qty as f64,
+                        0,
+                    ));
+                }
+            }
+            prev_bids = bids;
+            prev_asks = asks;
+        }
+        self.finish(events)
+    }
+
+    /// Converts DBN MBO records into Level-3 [`Event`] data.
+    pub fn convert_mbo<R: Read>(&self, decoder: &mut DbnDecoder<R>) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        // Tracks the remaining quantity of each order to detect partial cancels.
+        let mut orders: HashMap<u64, u32> = HashMap::new();
+        while let Some(msg) = decoder.decode_record::<MboMsg>().map_err(to_io_error)? {
+            let exch_ts = msg.hd.ts_event;
+            let local_ts = msg.ts_recv;
+            let side = to_side(msg.side);
+            let px = to_px(msg.price);
+            let qty = msg.size as f64;
+            match msg.action as u8 {
+                b'A' => {
+                    orders.insert(msg.order_id, msg.size);
+                    events.push(event(
+                        ADD_ORDER_EVENT | side,
+                        exch_ts,
+                        local_ts,
+                        px,
+                        qty,
+                        msg.order_id,
+                    ));
+                }
+                b'M' => {
+                    orders.insert(msg.order_id, msg.size);
+                    events.push(event(
+                        MODIFY_ORDER_EVENT | side,
+                        exch_ts,
+                        local_ts,
+                        px,
+                        qty,
+                        msg.order_id,
+                    ));
+                }
+                b'C' => {
+                    let remaining = match orders.get_mut(&msg.order_id) {
+                        Some(size) => {
+                            *size = size.saturating_sub(msg.size);
+                            *size
+                        }
+                        None => 0,
+                    };
+                    if remaining > 0 {
+                        events.push(event(
+                            MODIFY_ORDER_EVENT | side,
+                            exch_ts,
+                            local_ts,
+                            px,
+                            remaining as f64,
+                            msg.order_id,
+                        ));
+                    } else {
+                        orders.remove(&msg.order_id);
+                        events.push(event(
+                            CANCEL_ORDER_EVENT | side,
+                            exch_ts,
+                            local_ts,
+                            px,
+                            0.0,
+                            msg.order_id,
+                        ));
+                    }
+                }
+                b'F' => {
+                    events.push(event(
+                        FILL_EVENT | side,
+                        exch_ts,
+                        local_ts,
+                        px,
+                        qty,
+                        msg.order_id,
+                    ));
+                }
+                b'T' => {
+                    events.push(event(TRADE_EVENT | side, exch_ts, local_ts, px, qty, 0));
+                }
+                b'R' => {
+                    orders.clear();
+                    events.push(event(DEPTH_CLEAR_EVENT, exch_ts, local_ts, 0.0, 0.0, 0));
+                }
+                _ => {}
+            }
+        }
+        self.finish(events)
+    }
+
+    /// Converts a DBN file of MBP-10 records into Level-2 [`Event`] data. Files with the `.zst`
+    /// extension are decompressed. If `output_filename` is provided, the converted data is also
+    /// saved to it in `npy` format.
+    pub fn convert_mbp10_file(
+        &self,
+        input_filename: &str,
+        output_filename: Option<&str>,
+    ) -> Result<Vec<Event>> {
+        let events = self.convert_mbp10(&mut open_decoder(input_filename)?)?;
+        if let Some(output_filename) = output_filename {
+            write_output(output_filename, &events)?;
+        }
+        Ok(events)
+    }
+
+    /// Converts a DBN file of MBO records into Level-3 [`Event`] data. Files with the `.zst`
+    /// extension are decompressed. If `output_filename` is provided, the converted data is also
+    /// saved to it in `npy` format.
+    pub fn convert_mbo_file(
+        &self,
+        input_filename: &str,
+        output_filename: Option<&str>,
+    ) -> Result<Vec<Event>> {
+        let events = self.convert_mbo(&mut open_decoder(input_filename)?)?;
+        if let Some(output_filename) = output_filename {
+            write_output(output_filename, &events)?;
+        }
+        Ok(events)
+    }
+}
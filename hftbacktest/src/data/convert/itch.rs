@@ -0,0 +1,580 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Read, Result},
+};
+
+use tracing::warn;
+
+use crate::{
+    data::convert::{correct_event_order, open_input, validate_event_order, write_output},
+    types::{
+        Event,
+        ADD_ORDER_EVENT,
+        BUY_EVENT,
+        CANCEL_ORDER_EVENT,
+        DEPTH_EVENT,
+        FILL_EVENT,
+        MODIFY_ORDER_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    },
+};
+
+// ITCH prices are fixed-point numbers with 4 decimal places.
+const PRICE_SCALE: f64 = 10_000.0;
+
+fn to_px(price: u32) -> f64 {
+    price as f64 / PRICE_SCALE
+}
+
+fn to_side(indicator: u8) -> u64 {
+    match indicator {
+        b'B' => BUY_EVENT,
+        b'S' => SELL_EVENT,
+        _ => 0,
+    }
+}
+
+fn opposite(side: u64) -> u64 {
+    match side {
+        BUY_EVENT => SELL_EVENT,
+        SELL_EVENT => BUY_EVENT,
+        _ => 0,
+    }
+}
+
+fn u16_at(msg: &[u8], pos: usize) -> u16 {
+    u16::from_be_bytes(msg[pos..pos + 2].try_into().unwrap())
+}
+
+fn u32_at(msg: &[u8], pos: usize) -> u32 {
+    u32::from_be_bytes(msg[pos..pos + 4].try_into().unwrap())
+}
+
+fn u64_at(msg: &[u8], pos: usize) -> u64 {
+    u64::from_be_bytes(msg[pos..pos + 8].try_into().unwrap())
+}
+
+/// Reads the 6-byte timestamp in nanoseconds since midnight.
+fn timestamp_at(msg: &[u8], pos: usize) -> i64 {
+    let mut buf = [0u8; 8];
+    buf[2..].copy_from_slice(&msg[pos..pos + 6]);
+    i64::from_be_bytes(buf)
+}
+
+/// Returns the minimum length of the message type, covering the fields that are read, or `None`
+/// if the message type is not converted.
+fn min_len(ty: u8) -> Option<usize> {
+    match ty {
+        b'R' => Some(19),
+        b'A' | b'F' => Some(36),
+        b'E' => Some(31),
+        b'C' => Some(36),
+        b'X' => Some(23),
+        b'D' => Some(19),
+        b'U' => Some(35),
+        b'P' => Some(44),
+        b'Q' => Some(40),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Order {
+    side: u64,
+    price: u32,
+    shares: u32,
+}
+
+/// Reconstructs the order book of the stock from the messages and produces either Level-3
+/// Market-By-Order events or Level-2 Market-By-Price events.
+struct Book {
+    mbo: bool,
+    orders: HashMap<u64, Order>,
+    levels: HashMap<(u64, u32), u64>,
+    events: Vec<Event>,
+    exch_ts: i64,
+    local_ts: i64,
+    unknown: usize,
+}
+
+impl Book {
+    fn push(&mut self, ev: u64, px: f64, qty: f64, order_id: u64) -> &mut Event {
+        self.events.push(Event {
+            ev,
+            exch_ts: self.exch_ts,
+            local_ts: self.local_ts,
+            px,
+            qty,
+            order_id,
+            ival: 0,
+            fval: 0.0,
+        });
+        self.events.last_mut().unwrap()
+    }
+
+    fn update_level(&mut self, side: u64, price: u32, delta: i64) {
+        let qty = self.levels.entry((side, price)).or_insert(0);
+        *qty = (*qty as i64 + delta).max(0) as u64;
+        let qty = *qty;
+        if qty == 0 {
+            self.levels.remove(&(side, price));
+        }
+        if !self.mbo {
+            self.push(DEPTH_EVENT | side, to_px(price), qty as f64, 0);
+        }
+    }
+
+    fn add(&mut self, order_id: u64, order: Order) {
+        self.orders.insert(order_id, order);
+        if self.mbo {
+            self.push(
+                ADD_ORDER_EVENT | order.side,
+                to_px(order.price),
+                order.shares as f64,
+                order_id,
+            );
+        }
+        self.update_level(order.side, order.price, order.shares as i64);
+    }
+
+    fn order(&mut self, order_id: u64) -> Option<Order> {
+        let order = self.orders.get(&order_id).copied();
+        if order.is_none() {
+            self.unknown += 1;
+        }
+        order
+    }
+
+    /// Reduces the order by the executed or cancelled shares, deleting it if no shares remain.
+    fn reduce(&mut self, order_id: u64, shares: u32, executed: bool) {
+        let Some(order) = self.orders.get_mut(&order_id) else {
+            return;
+        };
+        let shares = shares.min(order.shares);
+        order.shares -= shares;
+        let order = *order;
+        if order.shares == 0 {
+            self.orders.remove(&order_id);
+        }
+        if self.mbo {
+            let px = to_px(order.price);
+            if executed {
+                self.push(FILL_EVENT | order.side, px, shares as f64, order_id);
+            }
+            if order.shares > 0 {
+                self.push(
+                    MODIFY_ORDER_EVENT | order.side,
+                    px,
+                    order.shares as f64,
+                    order_id,
+                );
+            } else {
+                self.push(CANCEL_ORDER_EVENT | order.side, px, 0.0, order_id);
+            }
+        }
+        self.update_level(order.side, order.price, -(shares as i64));
+    }
+
+    /// Processes an execution of the displayed order. A non-printable execution doesn't produce a
+    /// trade.
+    fn execute(&mut self, order_id: u64, shares: u32, price: Option<u32>, printable: bool) {
+        let Some(order) = self.order(order_id) else {
+            return;
+        };
+        if printable {
+            self.push(
+                TRADE_EVENT | opposite(order.side),
+                to_px(price.unwrap_or(order.price)),
+                shares as f64,
+                0,
+            );
+        }
+        self.reduce(order_id, shares, true);
+    }
+}
+
+/// Converts NASDAQ TotalView-ITCH 5.0 binary data of a single stock into [`Event`] data.
+///
+/// The input is the sequence of ITCH messages, each of which is prefixed by its length in a 2-byte
+/// big-endian integer, as in the files distributed by NASDAQ. ITCH timestamps are in nanoseconds
+/// since midnight and are converted into nanoseconds since the [`midnight`](Converter::midnight)
+/// timestamp. Since ITCH provides only the exchange timestamps, the local timestamps are the
+/// exchange timestamps plus the [`feed_latency`](Converter::feed_latency).
+///
+/// Messages of other stocks are skipped using the stock locate code assigned by the stock
+/// directory message, or by the first add order message of the stock.
+///
+/// * Level-3 Market-By-Order conversion: an add order message is converted into an
+///   [`ADD_ORDER_EVENT`], an order cancel message into a [`MODIFY_ORDER_EVENT`] with the remaining
+///   shares, and an order delete message into a [`CANCEL_ORDER_EVENT`]. Since an order replace
+///   message assigns a new order reference number, it is converted into a [`CANCEL_ORDER_EVENT`]
+///   of the original order and an [`ADD_ORDER_EVENT`] of the new order. An order executed message
+///   is converted into a [`TRADE_EVENT`] on the aggressor's side, a [`FILL_EVENT`] on the resting
+///   order, and then either a [`MODIFY_ORDER_EVENT`] or a [`CANCEL_ORDER_EVENT`] for the remaining
+///   shares. Level-3 events require the `unstable_l3` feature to be backtested.
+/// * Level-2 Market-By-Price conversion: the order book is reconstructed from the same messages,
+///   and a [`DEPTH_EVENT`] is produced with the new quantity of each price level that changes.
+///   Executions are converted into [`TRADE_EVENT`]s.
+///
+/// In both cases, a trade message, which is an execution of a non-displayed order, and a cross
+/// trade message are converted into a [`TRADE_EVENT`] only, since they don't change the displayed
+/// order book. Their trade side is unknown. Non-displayed executions are marked with `ival` set to
+/// `1`. Executions that are not printable don't produce a [`TRADE_EVENT`], since they are counted
+/// in the volume by a cross trade message.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::data::convert::itch::Converter;
+///
+/// let events = Converter::new("AAPL")
+///     .midnight(1_548_824_400_000_000_000)
+///     .convert_mbo_file("01302019.NASDAQ_ITCH50.gz", Some("aapl_20190130.npy"))
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Converter {
+    symbol: [u8; 8],
+    midnight: i64,
+    feed_latency: i64,
+}
+
+impl Converter {
+    /// Constructs an instance of `Converter` for the stock symbol.
+    pub fn new(symbol: &str) -> Self {
+        // Stock symbols are left-justified and padded with spaces to 8 characters.
+        let mut padded = [b' '; 8];
+        for (dst, src) in padded.iter_mut().zip(symbol.bytes()) {
+            *dst = src;
+        }
+        Self {
+            symbol: padded,
+            midnight: 0,
+            feed_latency: 0,
+        }
+    }
+
+    /// Sets the timestamp in nanoseconds of the midnight of the trading day in US Eastern time, to
+    /// which the ITCH timestamps are added. The default value is `0`.
+    pub fn midnight(self, midnight: i64) -> Self {
+        Self { midnight, ..self }
+    }
+
+    /// Sets the feed latency in nanoseconds added to the exchange timestamps to obtain the local
+    /// timestamps. The default value is `0`.
+    pub fn feed_latency(self, feed_latency: i64) -> Self {
+        Self {
+            feed_latency,
+            ..self
+        }
+    }
+
+    fn convert<R: Read>(&self, mut reader: R, mbo: bool) -> Result<Vec<Event>> {
+        let mut book = Book {
+            mbo,
+            orders: HashMap::new(),
+            levels: HashMap::new(),
+            events: Vec::new(),
+            exch_ts: 0,
+            local_ts: 0,
+            unknown: 0,
+        };
+        let mut locate = None;
+        let mut msg = Vec::new();
+        loop {
+            let mut len = [0u8; 2];
+            match reader.read_exact(&mut len) {
+                Ok(()) => {}
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error),
+            }
+            msg.resize(u16::from_be_bytes(len) as usize, 0);
+            reader.read_exact(&mut msg)?;
+
+            let Some(&ty) = msg.first() else {
+                continue;
+            };
+            let Some(min_len) = min_len(ty) else {
+                continue;
+            };
+            if msg.len() < min_len {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "message `{}` is {} bytes, shorter than {min_len} bytes",
+                        ty as char,
+                        msg.len()
+                    ),
+                ));
+            }
+
+            let msg_locate = u16_at(&msg, 1);
+            match ty {
+                b'R' if msg[11..19] == self.symbol => locate = Some(msg_locate),
+                b'A' | b'F' if locate.is_none() && msg[24..32] == self.symbol => {
+                    locate = Some(msg_locate)
+                }
+                _ => {}
+            }
+            if locate != Some(msg_locate) {
+                continue;
+            }
+
+            book.exch_ts = self.midnight + timestamp_at(&msg, 5);
+            book.local_ts = book.exch_ts + self.feed_latency;
+            match ty {
+                b'A' | b'F' => {
+                    book.add(
+                        u64_at(&msg, 11),
+                        Order {
+                            side: to_side(msg[19]),
+                            price: u32_at(&msg, 32),
+                            shares: u32_at(&msg, 20),
+                        },
+                    );
+                }
+                b'E' => book.execute(u64_at(&msg, 11), u32_at(&msg, 19), None, true),
+                b'C' => book.execute(
+                    u64_at(&msg, 11),
+                    u32_at(&msg, 19),
+                    Some(u32_at(&msg, 32)),
+                    msg[31] == b'Y',
+                ),
+                b'X' => {
+                    let order_id = u64_at(&msg, 11);
+                    if book.order(order_id).is_some() {
+                        book.reduce(order_id, u32_at(&msg, 19), false);
+                    }
+                }
+                b'D' => {
+                    let order_id = u64_at(&msg, 11);
+                    if book.order(order_id).is_some() {
+                        book.reduce(order_id, u32::MAX, false);
+                    }
+                }
+                b'U' => {
+                    let order_id = u64_at(&msg, 11);
+                    if let Some(order) = book.order(order_id) {
+                        book.reduce(order_id, u32::MAX, false);
+                        book.add(
+                            u64_at(&msg, 19),
+                            Order {
+                                side: order.side,
+                                price: u32_at(&msg, 31),
+                                shares: u32_at(&msg, 27),
+                            },
+                        );
+                    }
+                }
+                b'P' => {
+                    let ev = book.push(
+                        TRADE_EVENT,
+                        to_px(u32_at(&msg, 32)),
+                        u32_at(&msg, 20) as f64,
+                        0,
+                    );
+                    ev.ival = 1;
+                }
+                b'Q' => {
+                    let shares = u64_at(&msg, 11);
+                    if shares > 0 {
+                        book.push(TRADE_EVENT, to_px(u32_at(&msg, 27)), shares as f64, 0);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if locate.is_none() {
+            warn!(
+                symbol = %String::from_utf8_lossy(&self.symbol).trim_end(),
+                "symbol is not found"
+            );
+        }
+        if book.unknown > 0 {
+            warn!(unknown = %book.unknown, "messages about unknown orders are skipped");
+        }
+
+        let events = correct_event_order(&book.events);
+        validate_event_order(&events)?;
+        Ok(events)
+    }
+
+    /// Converts ITCH messages into Level-3 [`Event`] data.
+    pub fn convert_mbo<R: Read>(&self, reader: R) -> Result<Vec<Event>> {
+        self.convert(reader, true)
+    }
+
+    /// Converts ITCH messages into Level-2 [`Event`] data.
+    pub fn convert_mbp<R: Read>(&self, reader: R) -> Result<Vec<Event>> {
+        self.convert(reader, false)
+    }
+
+    /// Converts an ITCH file into Level-3 [`Event`] data. Files with the `.gz` extension are
+    /// decompressed. If `output_filename` is provided, the converted data is also saved to it in
+    /// `npy` format.
+    pub fn convert_mbo_file(
+        &self,
+        input_filename: &str,
+        output_filename: Option<&str>,
+    ) -> Result<Vec<Event>> {
+        let events = self.convert_mbo(open_input(input_filename)?)?;
+        if let Some(output_filename) = output_filename {
+            write_output(output_filename, &events)?;
+        }
+        Ok(events)
+    }
+
+    /// Converts an ITCH file into Level-2 [`Event`] data. Files with the `.gz` extension are
+    /// decompressed. If `output_filename` is provided, the converted data is also saved to it in
+    /// `npy` format.
+    pub fn convert_mbp_file(
+        &self,
+        input_filename: &str,
+        output_filename: Option<&str>,
+    ) -> Result<Vec<Event>> {
+        let events = self.convert_mbp(open_input(input_filename)?)?;
+        if let Some(output_filename) = output_filename {
+            write_output(output_filename, &events)?;
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Converter;
+    use crate::types::{
+        ADD_ORDER_EVENT,
+        BUY_EVENT,
+        CANCEL_ORDER_EVENT,
+        DEPTH_EVENT,
+        EXCH_EVENT,
+        FILL_EVENT,
+        LOCAL_EVENT,
+        MODIFY_ORDER_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    };
+
+    fn message(ty: u8, locate: u16, ts: u64, body: &[u8]) -> Vec<u8> {
+        let mut msg = vec![ty];
+        msg.extend(locate.to_be_bytes());
+        msg.extend(0u16.to_be_bytes());
+        msg.extend(&ts.to_be_bytes()[2..]);
+        msg.extend(body);
+        let mut framed = (msg.len() as u16).to_be_bytes().to_vec();
+        framed.extend(msg);
+        framed
+    }
+
+    fn add_order(
+        locate: u16,
+        ts: u64,
+        order_id: u64,
+        side: u8,
+        shares: u32,
+        stock: &[u8; 8],
+        price: u32,
+    ) -> Vec<u8> {
+        let mut body = order_id.to_be_bytes().to_vec();
+        body.push(side);
+        body.extend(shares.to_be_bytes());
+        body.extend(stock);
+        body.extend(price.to_be_bytes());
+        message(b'A', locate, ts, &body)
+    }
+
+    fn data() -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut directory = b"AAPL    ".to_vec();
+        directory.extend([0u8; 20]);
+        data.extend(message(b'R', 1, 0, &directory));
+        data.extend(add_order(1, 1, 10, b'B', 100, b"AAPL    ", 1_000_000));
+        // Another stock's order is skipped.
+        data.extend(add_order(2, 1, 11, b'B', 100, b"MSFT    ", 2_000_000));
+        data.extend(add_order(1, 2, 12, b'S', 50, b"AAPL    ", 1_001_000));
+        // Executes 30 shares of order 10.
+        let mut body = 10u64.to_be_bytes().to_vec();
+        body.extend(30u32.to_be_bytes());
+        body.extend(0u64.to_be_bytes());
+        data.extend(message(b'E', 1, 3, &body));
+        // Cancels 20 shares of order 12.
+        let mut body = 12u64.to_be_bytes().to_vec();
+        body.extend(20u32.to_be_bytes());
+        data.extend(message(b'X', 1, 4, &body));
+        // Replaces order 12 with order 13.
+        let mut body = 12u64.to_be_bytes().to_vec();
+        body.extend(13u64.to_be_bytes());
+        body.extend(40u32.to_be_bytes());
+        body.extend(1_002_000u32.to_be_bytes());
+        data.extend(message(b'U', 1, 5, &body));
+        // A non-displayed execution.
+        let mut body = 0u64.to_be_bytes().to_vec();
+        body.push(b'B');
+        body.extend(10u32.to_be_bytes());
+        body.extend(b"AAPL    ");
+        body.extend(1_000_500u32.to_be_bytes());
+        body.extend(0u64.to_be_bytes());
+        data.extend(message(b'P', 1, 6, &body));
+        // Deletes order 10.
+        data.extend(message(b'D', 1, 7, &10u64.to_be_bytes()));
+        data
+    }
+
+    #[test]
+    fn test_convert() {
+        let data = data();
+        let converter = Converter::new("AAPL").midnight(1_000).feed_latency(5);
+
+        let events = converter.convert_mbo(data.as_slice()).unwrap();
+        let summary = events
+            .iter()
+            .map(|ev| {
+                (
+                    ev.ev & !(EXCH_EVENT | LOCAL_EVENT),
+                    ev.px,
+                    ev.qty,
+                    ev.order_id,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (ADD_ORDER_EVENT | BUY_EVENT, 100.0, 100.0, 10),
+                (ADD_ORDER_EVENT | SELL_EVENT, 100.1, 50.0, 12),
+                (TRADE_EVENT | SELL_EVENT, 100.0, 30.0, 0),
+                (FILL_EVENT | BUY_EVENT, 100.0, 30.0, 10),
+                (MODIFY_ORDER_EVENT | BUY_EVENT, 100.0, 70.0, 10),
+                (MODIFY_ORDER_EVENT | SELL_EVENT, 100.1, 30.0, 12),
+                (CANCEL_ORDER_EVENT | SELL_EVENT, 100.1, 0.0, 12),
+                (ADD_ORDER_EVENT | SELL_EVENT, 100.2, 40.0, 13),
+                (TRADE_EVENT, 100.05, 10.0, 0),
+                (CANCEL_ORDER_EVENT | BUY_EVENT, 100.0, 0.0, 10),
+            ]
+        );
+        assert_eq!(events[0].exch_ts, 1_001);
+        assert_eq!(events[0].local_ts, 1_006);
+        assert_eq!(events[8].ival, 1);
+
+        let events = converter.convert_mbp(data.as_slice()).unwrap();
+        let summary = events
+            .iter()
+            .map(|ev| (ev.ev & !(EXCH_EVENT | LOCAL_EVENT), ev.px, ev.qty))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (DEPTH_EVENT | BUY_EVENT, 100.0, 100.0),
+                (DEPTH_EVENT | SELL_EVENT, 100.1, 50.0),
+                (TRADE_EVENT | SELL_EVENT, 100.0, 30.0),
+                (DEPTH_EVENT | BUY_EVENT, 100.0, 70.0),
+                (DEPTH_EVENT | SELL_EVENT, 100.1, 30.0),
+                (DEPTH_EVENT | SELL_EVENT, 100.1, 0.0),
+                (DEPTH_EVENT | SELL_EVENT, 100.2, 40.0),
+                (TRADE_EVENT, 100.05, 10.0),
+                (DEPTH_EVENT | BUY_EVENT, 100.0, 0.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,525 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Error, ErrorKind, Result},
+};
+
+use tracing::warn;
+
+use crate::{
+    data::convert::{correct_event_order, open_input, validate_event_order, write_output},
+    types::{
+        Event,
+        ADD_ORDER_EVENT,
+        BUY_EVENT,
+        CANCEL_ORDER_EVENT,
+        DEPTH_EVENT,
+        FILL_EVENT,
+        MODIFY_ORDER_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    },
+};
+
+// LOBSTER prices are in dollars multiplied by 10,000.
+const PRICE_SCALE: f64 = 10_000.0;
+
+// LOBSTER fills unoccupied price levels in the orderbook file with these dummy prices.
+const EMPTY_ASK_PRICE: i64 = 9_999_999_999;
+const EMPTY_BID_PRICE: i64 = -9_999_999_999;
+
+/// LOBSTER message type.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum MessageType {
+    /// Submission of a new limit order.
+    Submission,
+    /// Cancellation, which is a partial deletion of a limit order.
+    Cancellation,
+    /// Total deletion of a limit order.
+    Deletion,
+    /// Execution of a visible limit order.
+    VisibleExecution,
+    /// Execution of a hidden limit order.
+    HiddenExecution,
+    /// Cross trade, such as an auction trade.
+    CrossTrade,
+    /// Trading halt indicator.
+    TradingHalt,
+}
+
+#[derive(Clone, Debug)]
+struct Message {
+    timestamp: i64,
+    ty: MessageType,
+    order_id: u64,
+    size: i64,
+    price: i64,
+    // 1 for a buy limit order and -1 for a sell limit order. For executions, it is the side of the
+    // resting order.
+    direction: i64,
+}
+
+fn parse<T: std::str::FromStr>(s: &str) -> Result<T> {
+    s.trim()
+        .parse::<T>()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("invalid value `{s}`")))
+}
+
+/// Parses the LOBSTER time, which is in seconds after midnight with up to nanosecond precision,
+/// into nanoseconds, without the loss of precision of parsing it as a floating-point number.
+fn parse_time(s: &str) -> Result<i64> {
+    let s = s.trim();
+    let (secs, frac) = s.split_once('.').unwrap_or((s, ""));
+    if frac.len() > 9 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid time `{s}`"),
+        ));
+    }
+    let nanos = if frac.is_empty() {
+        0
+    } else {
+        parse::<i64>(frac)? * 10i64.pow(9 - frac.len() as u32)
+    };
+    Ok(parse::<i64>(secs)? * 1_000_000_000 + nanos)
+}
+
+fn parse_message(line: &str) -> Result<Message> {
+    let cols = line.split(',').collect::<Vec<_>>();
+    if cols.len() < 6 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid message `{line}`"),
+        ));
+    }
+    let ty = match parse::<u8>(cols[1])? {
+        1 => MessageType::Submission,
+        2 => MessageType::Cancellation,
+        3 => MessageType::Deletion,
+        4 => MessageType::VisibleExecution,
+        5 => MessageType::HiddenExecution,
+        6 => MessageType::CrossTrade,
+        7 => MessageType::TradingHalt,
+        ty => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid message type `{ty}`"),
+            ))
+        }
+    };
+    Ok(Message {
+        timestamp: parse_time(cols[0])?,
+        ty,
+        order_id: parse(cols[2])?,
+        size: parse(cols[3])?,
+        price: parse(cols[4])?,
+        direction: parse(cols[5])?,
+    })
+}
+
+/// Converts LOBSTER message files and orderbook files into [`Event`] data.
+///
+/// LOBSTER times are in seconds after midnight and are converted into nanoseconds since the
+/// [`midnight`](Converter::midnight) timestamp. Since LOBSTER provides only the exchange
+/// timestamps, the local timestamps are the exchange timestamps plus the
+/// [`feed_latency`](Converter::feed_latency).
+///
+/// * A message file is converted into Level-3 Market-By-Order events: a submission into an
+///   [`ADD_ORDER_EVENT`], a partial cancellation into a [`MODIFY_ORDER_EVENT`] with the remaining
+///   quantity, and a deletion into a [`CANCEL_ORDER_EVENT`]. An execution of a visible order is
+///   converted into a [`TRADE_EVENT`] on the aggressor's side, a [`FILL_EVENT`] on the resting
+///   order, and then either a [`MODIFY_ORDER_EVENT`] or a [`CANCEL_ORDER_EVENT`] for the remaining
+///   quantity. Level-3 events require the `unstable_l3` feature to be backtested.
+/// * An orderbook file, together with its message file, is converted into Level-2
+///   Market-By-Price [`DEPTH_EVENT`]s for the price levels that change between consecutive rows,
+///   and [`TRADE_EVENT`]s for the executions.
+///
+/// In both cases, an execution of a hidden order and a cross trade are converted into a
+/// [`TRADE_EVENT`] only, since they don't change the visible order book. The trade side of a
+/// cross trade is unknown. Hidden executions are marked with `ival` set to `1`. Trading halt
+/// indicators are ignored.
+///
+/// Orders submitted before the start of the message file are unknown, so messages about them are
+/// skipped in the Level-3 conversion.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::data::convert::lobster::Converter;
+///
+/// let events = Converter::new()
+///     .midnight(1_340_582_400_000_000_000)
+///     .convert_orderbook_files(
+///         "AAPL_2012-06-21_34200000_57600000_message_10.csv",
+///         "AAPL_2012-06-21_34200000_57600000_orderbook_10.csv",
+///         Some("aapl_20120621.npy"),
+///     )
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Converter {
+    midnight: i64,
+    feed_latency: i64,
+}
+
+impl Converter {
+    /// Constructs an instance of `Converter`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the timestamp in nanoseconds of the midnight of the trading day in the exchange's time
+    /// zone, to which the LOBSTER times are added. The default value is `0`.
+    pub fn midnight(self, midnight: i64) -> Self {
+        Self { midnight, ..self }
+    }
+
+    /// Sets the feed latency in nanoseconds added to the exchange timestamps to obtain the local
+    /// timestamps. The default value is `0`.
+    pub fn feed_latency(self, feed_latency: i64) -> Self {
+        Self {
+            feed_latency,
+            ..self
+        }
+    }
+
+    fn event(&self, ev: u64, msg: &Message, px: f64, qty: f64, order_id: u64) -> Event {
+        let exch_ts = self.midnight + msg.timestamp;
+        Event {
+            ev,
+            exch_ts,
+            local_ts: exch_ts + self.feed_latency,
+            px,
+            qty,
+            order_id,
+            ival: 0,
+            fval: 0.0,
+        }
+    }
+
+    /// Appends the trade of the execution or the cross trade. Returns `true` if the message is a
+    /// trade.
+    fn push_trade(&self, events: &mut Vec<Event>, msg: &Message) -> bool {
+        let px = msg.price as f64 / PRICE_SCALE;
+        let qty = msg.size as f64;
+        match msg.ty {
+            MessageType::VisibleExecution | MessageType::HiddenExecution => {
+                // The direction is the side of the resting order, so the aggressor is on the
+                // opposite side.
+                let side = if msg.direction > 0 {
+                    SELL_EVENT
+                } else {
+                    BUY_EVENT
+                };
+                let mut ev = self.event(TRADE_EVENT | side, msg, px, qty, 0);
+                if msg.ty == MessageType::HiddenExecution {
+                    ev.ival = 1;
+                }
+                events.push(ev);
+                true
+            }
+            MessageType::CrossTrade => {
+                events.push(self.event(TRADE_EVENT, msg, px, qty, 0));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn finish(&self, events: Vec<Event>) -> Result<Vec<Event>> {
+        let events = correct_event_order(&events);
+        validate_event_order(&events)?;
+        Ok(events)
+    }
+
+    /// Converts a LOBSTER message file into Level-3 [`Event`] data.
+    pub fn convert_messages<R: BufRead>(&self, messages: R) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        // Tracks the remaining quantity of each order to convert executions and partial
+        // cancellations.
+        let mut orders: HashMap<u64, i64> = HashMap::new();
+        let mut unknown = 0;
+        for line in messages.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let msg = parse_message(&line)?;
+            if self.push_trade(&mut events, &msg) && msg.ty != MessageType::VisibleExecution {
+                continue;
+            }
+            let side = if msg.direction > 0 {
+                BUY_EVENT
+            } else {
+                SELL_EVENT
+            };
+            let px = msg.price as f64 / PRICE_SCALE;
+            match msg.ty {
+                MessageType::Submission => {
+                    orders.insert(msg.order_id, msg.size);
+                    events.push(self.event(
+                        ADD_ORDER_EVENT | side,
+                        &msg,
+                        px,
+                        msg.size as f64,
+                        msg.order_id,
+                    ));
+                }
+                MessageType::Cancellation
+                | MessageType::Deletion
+                | MessageType::VisibleExecution => {
+                    let Some(size) = orders.get_mut(&msg.order_id) else {
+                        unknown += 1;
+                        continue;
+                    };
+                    if msg.ty == MessageType::VisibleExecution {
+                        events.push(self.event(
+                            FILL_EVENT | side,
+                            &msg,
+                            px,
+                            msg.size as f64,
+                            msg.order_id,
+                        ));
+                    }
+                    let remaining = if msg.ty == MessageType::Deletion {
+                        0
+                    } else {
+                        (*size - msg.size).max(0)
+                    };
+                    if remaining > 0 {
+                        *size = remaining;
+                        events.push(self.event(
+                            MODIFY_ORDER_EVENT | side,
+                            &msg,
+                            px,
+                            remaining as f64,
+                            msg.order_id,
+                        ));
+                    } else {
+                        orders.remove(&msg.order_id);
+                        events.push(self.event(
+                            CANCEL_ORDER_EVENT | side,
+                            &msg,
+                            px,
+                            0.0,
+                            msg.order_id,
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if unknown > 0 {
+            warn!(
+                %unknown,
+                "messages about orders submitted before the start of the file are skipped"
+            );
+        }
+        self.finish(events)
+    }
+
+    /// Converts a LOBSTER orderbook file, together with its message file, into Level-2 [`Event`]
+    /// data. Each row of the orderbook file is the state of the order book after the message in
+    /// the same row of the message file.
+    pub fn convert_orderbook<M: BufRead, O: BufRead>(
+        &self,
+        messages: M,
+        orderbook: O,
+    ) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        let mut prev_bids: HashMap<i64, i64> = HashMap::new();
+        let mut prev_asks: HashMap<i64, i64> = HashMap::new();
+        let mut orderbook = orderbook.lines();
+        for line in messages.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let msg = parse_message(&line)?;
+            let book = orderbook.next().ok_or_else(|| {
+                Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "orderbook file has fewer rows than message file",
+                )
+            })??;
+            self.push_trade(&mut events, &msg);
+
+            let cols = book
+                .split(',')
+                .map(parse::<i64>)
+                .collect::<Result<Vec<_>>>()?;
+            if cols.len() % 4 != 0 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid orderbook row `{book}`"),
+                ));
+            }
+            let mut bids = HashMap::new();
+            let mut asks = HashMap::new();
+            for level in cols.chunks(4) {
+                let (ask_px, ask_qty, bid_px, bid_qty) = (level[0], level[1], level[2], level[3]);
+                if ask_px != EMPTY_ASK_PRICE && ask_qty > 0 {
+                    asks.insert(ask_px, ask_qty);
+                }
+                if bid_px != EMPTY_BID_PRICE && bid_qty > 0 {
+                    bids.insert(bid_px, bid_qty);
+                }
+            }
+            for (side, curr, prev) in [
+                (BUY_EVENT, &bids, &prev_bids),
+                (SELL_EVENT, &asks, &prev_asks),
+            ] {
+                let mut changes = Vec::new();
+                for (&px, &qty) in curr {
+                    if prev.get(&px) != Some(&qty) {
+                        changes.push((px, qty));
+                    }
+                }
+                for &px in prev.keys() {
+                    if !curr.contains_key(&px) {
+                        changes.push((px, 0));
+                    }
+                }
+                // Keeps the output deterministic regardless of the hash map's iteration order.
+                changes.sort_unstable_by_key(|&(px, _)| px);
+                for (px, qty) in changes {
+                    events.push(self.event(
+                        DEPTH_EVENT | side,
+                        &msg,
+                        px as f64 / PRICE_SCALE,
+                        qty as f64,
+                        0,
+                    ));
+                }
+            }
+            prev_bids = bids;
+            prev_asks = asks;
+        }
+        self.finish(events)
+    }
+
+    /// Converts a LOBSTER message file into Level-3 [`Event`] data. Files with the `.gz`
+    /// extension are decompressed. If `output_filename` is provided, the converted data is also
+    /// saved to it in `npy` format.
+    pub fn convert_messages_file(
+        &self,
+        message_filename: &str,
+        output_filename: Option<&str>,
+    ) -> Result<Vec<Event>> {
+        let events = self.convert_messages(open_input(message_filename)?)?;
+        if let Some(output_filename) = output_filename {
+            write_output(output_filename, &events)?;
+        }
+        Ok(events)
+    }
+
+    /// Converts a LOBSTER orderbook file, together with its message file, into Level-2 [`Event`]
+    /// data. Files with the `.gz` extension are decompressed. If `output_filename` is provided,
+    /// the converted data is also saved to it in `npy` format.
+    pub fn convert_orderbook_files(
+        &self,
+        message_filename: &str,
+        orderbook_filename: &str,
+        output_filename: Option<&str>,
+    ) -> Result<Vec<Event>> {
+        let events = self.convert_orderbook(
+            open_input(message_filename)?,
+            open_input(orderbook_filename)?,
+        )?;
+        if let Some(output_filename) = output_filename {
+            write_output(output_filename, &events)?;
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_time, Converter};
+    use crate::types::{
+        ADD_ORDER_EVENT,
+        BUY_EVENT,
+        CANCEL_ORDER_EVENT,
+        DEPTH_EVENT,
+        EXCH_EVENT,
+        FILL_EVENT,
+        LOCAL_EVENT,
+        MODIFY_ORDER_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    };
+
+    const MESSAGES: &str = "34200.000000001,1,1,100,1000000,1
+34200.5,1,2,50,1001000,-1
+34201,4,1,30,1000000,1
+34201.25,5,0,10,1000500,-1
+34202,2,2,20,1001000,-1
+34203,3,2,30,1001000,-1
+";
+
+    const ORDERBOOK: &str = "9999999999,0,1000000,100
+1001000,50,1000000,100
+1001000,50,1000000,70
+1001000,50,1000000,70
+1001000,30,1000000,70
+9999999999,0,1000000,70
+";
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("34200.004241176").unwrap(), 34_200_004_241_176);
+        assert_eq!(parse_time("34200.5").unwrap(), 34_200_500_000_000);
+        assert_eq!(parse_time("34200").unwrap(), 34_200_000_000_000);
+        assert!(parse_time("34200.0042411761").is_err());
+    }
+
+    #[test]
+    fn test_convert_messages() {
+        let events = Converter::new()
+            .feed_latency(5)
+            .convert_messages(MESSAGES.as_bytes())
+            .unwrap();
+        let summary = events
+            .iter()
+            .map(|ev| (ev.ev & !(EXCH_EVENT | LOCAL_EVENT), ev.qty, ev.order_id))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (ADD_ORDER_EVENT | BUY_EVENT, 100.0, 1),
+                (ADD_ORDER_EVENT | SELL_EVENT, 50.0, 2),
+                (TRADE_EVENT | SELL_EVENT, 30.0, 0),
+                (FILL_EVENT | BUY_EVENT, 30.0, 1),
+                (MODIFY_ORDER_EVENT | BUY_EVENT, 70.0, 1),
+                (TRADE_EVENT | BUY_EVENT, 10.0, 0),
+                (MODIFY_ORDER_EVENT | SELL_EVENT, 30.0, 2),
+                (CANCEL_ORDER_EVENT | SELL_EVENT, 0.0, 2),
+            ]
+        );
+        assert_eq!(events[0].exch_ts, 34_200_000_000_001);
+        assert_eq!(events[0].local_ts, 34_200_000_000_006);
+        assert_eq!(events[0].px, 100.0);
+        assert_eq!(events[5].ival, 1);
+    }
+
+    #[test]
+    fn test_convert_orderbook() {
+        let events = Converter::new()
+            .convert_orderbook(MESSAGES.as_bytes(), ORDERBOOK.as_bytes())
+            .unwrap();
+        let summary = events
+            .iter()
+            .map(|ev| (ev.ev & !(EXCH_EVENT | LOCAL_EVENT), ev.px, ev.qty))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (DEPTH_EVENT | BUY_EVENT, 100.0, 100.0),
+                (DEPTH_EVENT | SELL_EVENT, 100.1, 50.0),
+                (TRADE_EVENT | SELL_EVENT, 100.0, 30.0),
+                (DEPTH_EVENT | BUY_EVENT, 100.0, 70.0),
+                (TRADE_EVENT | BUY_EVENT, 100.05, 10.0),
+                (DEPTH_EVENT | SELL_EVENT, 100.1, 30.0),
+                (DEPTH_EVENT | SELL_EVENT, 100.1, 0.0),
+            ]
+        );
+    }
+}
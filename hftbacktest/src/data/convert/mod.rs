@@ -0,0 +1,205 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Result},
+};
+
+use flate2::read::GzDecoder;
+
+use crate::{
+    backtest::reader::write_npy,
+    types::{Event, EXCH_EVENT, LOCAL_EVENT},
+};
+
+/// Binance Futures raw feed converter.
+pub mod binancefutures;
+
+/// Bybit raw feed converter.
+pub mod bybit;
+
+/// OKX raw feed converter.
+pub mod okx;
+
+/// Tardis.dev CSV data converter.
+pub mod tardis;
+
+/// LOBSTER message and orderbook data converter.
+pub mod lobster;
+
+/// Databento DBN data converter.
+#[cfg(feature = "databento")]
+pub mod databento;
+
+/// NASDAQ TotalView-ITCH 5.0 data converter.
+#[cfg(feature = "itch")]
+pub mod itch;
+
+/// Opens the input file for reading line by line. Files with the `.gz` extension are decompressed.
+pub(crate) fn open_input(filepath: &str) -> Result<Box<dyn BufRead>> {
+    let file = File::open(filepath)?;
+    if filepath.ends_with(".gz") {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Writes the converted events to the output file in `npy` format.
+pub(crate) fn write_output(filepath: &str, events: &Vec<Event>) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(filepath)?);
+    write_npy(&mut writer, events)
+}
+
+/// Adjusts the local timestamps in place if the feed latency is negative, by offsetting them by the
+/// maximum negative latency as follows:
+///
+/// ```text
+/// feed_latency = local_ts - exch_ts
+/// adjusted_local_ts = local_ts - min(feed_latency, 0) + base_latency
+/// ```
+///
+/// Due to discrepancies in system time between the exchange and the local machine, latency may be
+/// measured inaccurately, resulting in negative latency values. The adjustment may still produce
+/// zero latency, so `base_latency` can be added to obtain more realistic values. Its unit should
+/// be the same as the feed data's timestamp unit.
+///
+/// Returns the applied offset, which is zero if no adjustment was needed.
+pub fn correct_local_timestamp(events: &mut [Event], base_latency: i64) -> i64 {
+    let latency = events
+        .iter()
+        .map(|ev| ev.local_ts - ev.exch_ts)
+        .min()
+        .unwrap_or(0);
+    if latency < 0 {
+        let offset = -latency + base_latency;
+        for ev in events.iter_mut() {
+            ev.local_ts += offset;
+        }
+        offset
+    } else {
+        0
+    }
+}
+
+/// Corrects exchange timestamps that are reversed by splitting each row into separate exchange
+/// and local events, which are then ordered by both exchange and local timestamps through
+/// duplication. The events are expected to have neither [`EXCH_EVENT`] nor [`LOCAL_EVENT`] set.
+pub fn correct_event_order(events: &[Event]) -> Vec<Event> {
+    let n = events.len();
+    let mut sorted_exch = (0..n).collect::<Vec<_>>();
+    sorted_exch.sort_by_key(|&i| events[i].exch_ts);
+    let mut sorted_local = (0..n).collect::<Vec<_>>();
+    sorted_local.sort_by_key(|&i| events[i].local_ts);
+
+    let with_flag = |ev: &Event, flag: u64| Event {
+        ev: ev.ev | flag,
+        ..ev.clone()
+    };
+
+    let mut sorted = Vec::with_capacity(n * 2);
+    let mut exch_rn = 0;
+    let mut local_rn = 0;
+    loop {
+        if exch_rn < n && local_rn < n {
+            let exch = &events[sorted_exch[exch_rn]];
+            let local = &events[sorted_local[local_rn]];
+            if exch.exch_ts == local.exch_ts && exch.local_ts == local.local_ts {
+                sorted.push(with_flag(exch, EXCH_EVENT | LOCAL_EVENT));
+                exch_rn += 1;
+                local_rn += 1;
+            } else if exch.exch_ts < local.exch_ts
+                || (exch.exch_ts == local.exch_ts && exch.local_ts < local.local_ts)
+            {
+                sorted.push(with_flag(exch, EXCH_EVENT));
+                exch_rn += 1;
+            } else {
+                sorted.push(with_flag(local, LOCAL_EVENT));
+                local_rn += 1;
+            }
+        } else if local_rn < n {
+            sorted.push(with_flag(&events[sorted_local[local_rn]], LOCAL_EVENT));
+            local_rn += 1;
+        } else if exch_rn < n {
+            sorted.push(with_flag(&events[sorted_exch[exch_rn]], EXCH_EVENT));
+            exch_rn += 1;
+        } else {
+            break;
+        }
+    }
+    sorted
+}
+
+/// Validates that exchange events are ordered by exchange timestamp and local events are ordered
+/// by local timestamp.
+pub fn validate_event_order(events: &[Event]) -> Result<()> {
+    let mut last_exch_ts = i64::MIN;
+    let mut last_local_ts = i64::MIN;
+    for ev in events {
+        if ev.is(EXCH_EVENT) {
+            if ev.exch_ts < last_exch_ts {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "exchange events are out of order",
+                ));
+            }
+            last_exch_ts = ev.exch_ts;
+        }
+        if ev.is(LOCAL_EVENT) {
+            if ev.local_ts < last_local_ts {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "local events are out of order",
+                ));
+            }
+            last_local_ts = ev.local_ts;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{correct_event_order, correct_local_timestamp, validate_event_order};
+    use crate::types::{Event, DEPTH_EVENT, EXCH_EVENT, LOCAL_EVENT};
+
+    fn event(exch_ts: i64, local_ts: i64) -> Event {
+        Event {
+            ev: DEPTH_EVENT,
+            exch_ts,
+            local_ts,
+            px: 1.0,
+            qty: 1.0,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_correct_local_timestamp() {
+        let mut events = vec![event(10, 8), event(20, 25)];
+        assert_eq!(correct_local_timestamp(&mut events, 1), 3);
+        assert_eq!(events[0].local_ts, 11);
+        assert_eq!(events[1].local_ts, 28);
+    }
+
+    #[test]
+    fn test_correct_event_order() {
+        // The second event occurs earlier on the exchange but is received later.
+        let events = vec![event(10, 15), event(5, 20), event(30, 35)];
+        let sorted = correct_event_order(&events);
+        let summary = sorted
+            .iter()
+            .map(|ev| (ev.ev & (EXCH_EVENT | LOCAL_EVENT), ev.exch_ts))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (EXCH_EVENT, 5),
+                (EXCH_EVENT | LOCAL_EVENT, 10),
+                (LOCAL_EVENT, 5),
+                (EXCH_EVENT | LOCAL_EVENT, 30),
+            ]
+        );
+        assert!(validate_event_order(&sorted).is_ok());
+    }
+}
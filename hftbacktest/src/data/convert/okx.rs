@@ -0,0 +1,275 @@
+use std::io::{BufRead, Error, ErrorKind, Result};
+
+use serde::Deserialize;
+
+use crate::{
+    data::convert::{
+        correct_event_order,
+        correct_local_timestamp,
+        open_input,
+        validate_event_order,
+        write_output,
+    },
+    types::{
+        Event,
+        BUY_EVENT,
+        DEPTH_CLEAR_EVENT,
+        DEPTH_EVENT,
+        DEPTH_SNAPSHOT_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    },
+};
+
+// OKX timestamps are in milliseconds, whereas the recorded local timestamps are in nanoseconds.
+const TIMESTAMP_MUL: i64 = 1_000_000;
+
+#[derive(Deserialize, Debug)]
+struct Arg {
+    channel: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Message {
+    // Subscription responses don't have data.
+    arg: Option<Arg>,
+    // Only the incremental order book channels have the action.
+    action: Option<String>,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct Book {
+    // Each level consists of the price, the quantity, a deprecated field, and the number of
+    // orders.
+    asks: Vec<Vec<String>>,
+    bids: Vec<Vec<String>>,
+    ts: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Trade {
+    px: String,
+    sz: String,
+    side: String,
+    ts: String,
+}
+
+fn parse_f64(s: &str) -> Result<f64> {
+    s.parse::<f64>()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("invalid number `{s}`")))
+}
+
+fn parse_ts(s: &str) -> Result<i64> {
+    s.parse::<i64>()
+        .map(|ts| ts * TIMESTAMP_MUL)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("invalid timestamp `{s}`")))
+}
+
+fn parse_level(level: &[String]) -> Result<(f64, f64)> {
+    match level {
+        [px, qty, ..] => Ok((parse_f64(px)?, parse_f64(qty)?)),
+        _ => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid level `{level:?}`"),
+        )),
+    }
+}
+
+fn event(ev: u64, exch_ts: i64, local_ts: i64, px: f64, qty: f64) -> Event {
+    Event {
+        ev,
+        exch_ts,
+        local_ts,
+        px,
+        qty,
+        order_id: 0,
+        ival: 0,
+        fval: 0.0,
+    }
+}
+
+/// Converts the raw OKX feed stream recorded by the collector into [`Event`] data.
+///
+/// Each line of the input consists of the local timestamp in nanoseconds followed by the raw
+/// message of an order book channel, such as `books-l2-tbt`, `books50-l2-tbt`, or `books`, or the
+/// `trades` channel.
+///
+/// ```text
+/// 1597026383087312000 {"arg":{"channel":"books-l2-tbt","instId":"BTC-USDT-SWAP"},"action":"update","data":[{"asks":[["8476.98","415","0","13"]],"bids":[["8476.97","0","0","0"]],"ts":"1597026383085","checksum":-855196043,"prevSeqId":123455,"seqId":123456}]}
+/// 1630048897899581000 {"arg":{"channel":"trades","instId":"BTC-USDT-SWAP"},"data":[{"instId":"BTC-USDT-SWAP","tradeId":"130639474","px":"42219.9","sz":"12","side":"buy","ts":"1630048897897","count":"3"}]}
+/// ```
+///
+/// Since OKX's order book snapshot replaces the entire order book, a snapshot message clears the
+/// market depth on both sides and then inserts the snapshot. A message of an order book channel
+/// without an action, such as `books5`, is a snapshot.
+///
+/// The quantities are given as is, which are in contracts for derivatives and in the base currency
+/// for spot.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::data::convert::okx::Converter;
+///
+/// let events = Converter::new()
+///     .base_latency(1_000_000)
+///     .convert_file("btc-usdt-swap_20240501.gz", Some("btc-usdt-swap_20240501.npy"))
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Converter {
+    base_latency: i64,
+}
+
+impl Converter {
+    /// Constructs an instance of `Converter`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the value added to the feed latency when the local timestamps are corrected. See
+    /// [`correct_local_timestamp`](crate::data::convert::correct_local_timestamp). The default
+    /// value is `0`.
+    pub fn base_latency(self, base_latency: i64) -> Self {
+        Self {
+            base_latency,
+            ..self
+        }
+    }
+
+    /// Converts the raw feed stream into [`Event`] data, with the local timestamps and the event
+    /// order corrected.
+    pub fn convert<R: BufRead>(&self, reader: R) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (local_ts, message) = line
+                .split_once(' ')
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "missing local timestamp"))?;
+            let local_ts = local_ts.parse::<i64>().map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid local timestamp `{local_ts}`"),
+                )
+            })?;
+            let message = serde_json::from_str::<Message>(message)?;
+            let Some(arg) = message.arg else {
+                continue;
+            };
+            if message.data.is_null() {
+                continue;
+            }
+            if arg.channel.starts_with("books") {
+                let books = serde_json::from_value::<Vec<Book>>(message.data)?;
+                let snapshot = message.action.as_deref().unwrap_or("snapshot") == "snapshot";
+                for book in books {
+                    let exch_ts = parse_ts(&book.ts)?;
+                    let ev = if snapshot {
+                        events.push(event(DEPTH_CLEAR_EVENT, exch_ts, local_ts, 0.0, 0.0));
+                        DEPTH_SNAPSHOT_EVENT
+                    } else {
+                        DEPTH_EVENT
+                    };
+                    for (side, levels) in [(BUY_EVENT, &book.bids), (SELL_EVENT, &book.asks)] {
+                        for level in levels {
+                            let (px, qty) = parse_level(level)?;
+                            events.push(event(ev | side, exch_ts, local_ts, px, qty));
+                        }
+                    }
+                }
+            } else if arg.channel.starts_with("trades") {
+                let trades = serde_json::from_value::<Vec<Trade>>(message.data)?;
+                for trade in trades {
+                    // The side is the taker's side.
+                    let side = match trade.side.as_str() {
+                        "buy" => BUY_EVENT,
+                        "sell" => SELL_EVENT,
+                        _ => 0,
+                    };
+                    events.push(event(
+                        TRADE_EVENT | side,
+                        parse_ts(&trade.ts)?,
+                        local_ts,
+                        parse_f64(&trade.px)?,
+                        parse_f64(&trade.sz)?,
+                    ));
+                }
+            }
+        }
+
+        correct_local_timestamp(&mut events, self.base_latency);
+        let events = correct_event_order(&events);
+        validate_event_order(&events)?;
+        Ok(events)
+    }
+
+    /// Converts the raw feed stream file into [`Event`] data. Files with the `.gz` extension are
+    /// decompressed. If `output_filename` is provided, the converted data is also saved to it in
+    /// `npy` format.
+    pub fn convert_file(
+        &self,
+        input_filename: &str,
+        output_filename: Option<&str>,
+    ) -> Result<Vec<Event>> {
+        let events = self.convert(open_input(input_filename)?)?;
+        if let Some(output_filename) = output_filename {
+            write_output(output_filename, &events)?;
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Converter;
+    use crate::types::{
+        BUY_EVENT,
+        DEPTH_CLEAR_EVENT,
+        DEPTH_EVENT,
+        DEPTH_SNAPSHOT_EVENT,
+        EXCH_EVENT,
+        LOCAL_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    };
+
+    #[test]
+    fn test_convert() {
+        let raw = r#"1000000000 {"event":"subscribe","arg":{"channel":"books-l2-tbt","instId":"BTC-USDT-SWAP"},"connId":"a"}
+1001000000 {"arg":{"channel":"books-l2-tbt","instId":"BTC-USDT-SWAP"},"action":"snapshot","data":[{"asks":[["101.0","2","0","1"]],"bids":[["100.0","1","0","1"]],"ts":"998","checksum":0,"prevSeqId":-1,"seqId":1}]}
+1002000000 {"arg":{"channel":"books-l2-tbt","instId":"BTC-USDT-SWAP"},"action":"update","data":[{"asks":[],"bids":[["100.0","0","0","0"]],"ts":"1001","checksum":0,"prevSeqId":1,"seqId":2}]}
+1003000000 {"arg":{"channel":"trades","instId":"BTC-USDT-SWAP"},"data":[{"instId":"BTC-USDT-SWAP","tradeId":"1","px":"100.0","sz":"5","side":"sell","ts":"1001","count":"1"}]}
+"#;
+        let events = Converter::new().convert(raw.as_bytes()).unwrap();
+        let summary = events
+            .iter()
+            .map(|ev| (ev.ev, ev.exch_ts, ev.px, ev.qty))
+            .collect::<Vec<_>>();
+        let both = EXCH_EVENT | LOCAL_EVENT;
+        assert_eq!(
+            summary,
+            vec![
+                (DEPTH_CLEAR_EVENT | both, 998_000_000, 0.0, 0.0),
+                (
+                    DEPTH_SNAPSHOT_EVENT | BUY_EVENT | both,
+                    998_000_000,
+                    100.0,
+                    1.0
+                ),
+                (
+                    DEPTH_SNAPSHOT_EVENT | SELL_EVENT | both,
+                    998_000_000,
+                    101.0,
+                    2.0
+                ),
+                (DEPTH_EVENT | BUY_EVENT | both, 1_001_000_000, 100.0, 0.0),
+                (TRADE_EVENT | SELL_EVENT | both, 1_001_000_000, 100.0, 5.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use std::io::{BufRead, Error, ErrorKind, Result};
+
+use tracing::warn;
+
+use crate::{
+    data::convert::{
+        correct_event_order,
+        correct_local_timestamp,
+        open_input,
+        validate_event_order,
+        write_output,
+    },
+    types::{
+        Event,
+        BUY_EVENT,
+        DEPTH_CLEAR_EVENT,
+        DEPTH_EVENT,
+        DEPTH_SNAPSHOT_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    },
+};
+
+// Tardis timestamps are in microseconds.
+const TIMESTAMP_MUL: i64 = 1_000;
+
+const TRADES_HEADER: [&str; 8] = [
+    "exchange",
+    "symbol",
+    "timestamp",
+    "local_timestamp",
+    "id",
+    "side",
+    "price",
+    "amount",
+];
+
+const INCREMENTAL_BOOK_L2_HEADER: [&str; 8] = [
+    "exchange",
+    "symbol",
+    "timestamp",
+    "local_timestamp",
+    "is_snapshot",
+    "side",
+    "price",
+    "amount",
+];
+
+/// Determines how the snapshots in the `incremental_book_L2` data are processed.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum SnapshotMode {
+    /// All snapshots are processed.
+    #[default]
+    Process,
+    /// The start-of-day snapshot is ignored. Since Tardis intentionally adds the start-of-day
+    /// snapshot, not due to a message ID gap or disconnection, there might not be a need to
+    /// process it to build a complete order book. See
+    /// <https://docs.tardis.dev/historical-data-details#collected-order-book-data-details>.
+    IgnoreSod,
+    /// All snapshots are ignored. The order book will converge to a complete order book over
+    /// time.
+    Ignore,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum FileType {
+    Trades,
+    IncrementalBookL2,
+}
+
+fn parse<T: std::str::FromStr>(s: &str) -> Result<T> {
+    s.parse::<T>()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("invalid value `{s}`")))
+}
+
+/// Converts Tardis.dev `trades` and `incremental_book_L2` CSV data into [`Event`] data.
+///
+/// Tardis timestamps are in microseconds and are converted into nanoseconds. For Binance Futures,
+/// Tardis uses the `E` event time, which is the sending time, as `timestamp` rather than the `T`
+/// transaction time, which is when the matching occurs. So the feed latency is slightly less than
+/// it actually is.
+///
+/// A snapshot, a consecutive run of rows with `is_snapshot` set to `true`, is converted into a
+/// [`DEPTH_CLEAR_EVENT`] that clears the market depth up to the farthest price in the snapshot,
+/// followed by [`DEPTH_SNAPSHOT_EVENT`]s, for each side.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::data::convert::tardis::{Converter, SnapshotMode};
+///
+/// let events = Converter::new()
+///     .snapshot_mode(SnapshotMode::IgnoreSod)
+///     .convert_files(
+///         &[
+///             "binance-futures_incremental_book_L2_2024-05-01_BTCUSDT.csv.gz",
+///             "binance-futures_trades_2024-05-01_BTCUSDT.csv.gz",
+///         ],
+///         Some("btcusdt_20240501.npy"),
+///     )
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Converter {
+    base_latency: i64,
+    snapshot_mode: SnapshotMode,
+}
+
+impl Converter {
+    /// Constructs an instance of `Converter`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the value added to the feed latency when the local timestamps are corrected. See
+    /// [`correct_local_timestamp`](crate::data::convert::correct_local_timestamp). The default
+    /// value is `0`.
+    pub fn base_latency(self, base_latency: i64) -> Self {
+        Self {
+            base_latency,
+            ..self
+        }
+    }
+
+    /// Sets the snapshot mode. The default value is [`SnapshotMode::Process`].
+    pub fn snapshot_mode(self, snapshot_mode: SnapshotMode) -> Self {
+        Self {
+            snapshot_mode,
+            ..self
+        }
+    }
+
+    fn read<R: BufRead>(&self, reader: R, events: &mut Vec<Event>) -> Result<()> {
+        let mut file_type = None;
+        let mut ss_bid = Vec::new();
+        let mut ss_ask = Vec::new();
+        let mut is_sod_snapshot = true;
+        for line in reader.lines() {
+            let line = line?;
+            let cols = line.trim().split(',').collect::<Vec<_>>();
+            if cols.len() < 8 {
+                warn!(%line, "invalid data row");
+                continue;
+            }
+            let Some(file_type) = file_type else {
+                file_type = if cols[..8] == TRADES_HEADER {
+                    Some(FileType::Trades)
+                } else if cols[..8] == INCREMENTAL_BOOK_L2_HEADER {
+                    Some(FileType::IncrementalBookL2)
+                } else {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "unsupported file; only trades and incremental_book_L2 are supported",
+                    ));
+                };
+                continue;
+            };
+            let exch_ts = parse::<i64>(cols[2])? * TIMESTAMP_MUL;
+            let local_ts = parse::<i64>(cols[3])? * TIMESTAMP_MUL;
+            let px = parse::<f64>(cols[6])?;
+            let qty = parse::<f64>(cols[7])?;
+            let event = |ev| Event {
+                ev,
+                exch_ts,
+                local_ts,
+                px,
+                qty,
+                order_id: 0,
+                ival: 0,
+                fval: 0.0,
+            };
+            match file_type {
+                FileType::Trades => {
+                    let side = if cols[5] == "buy" {
+                        BUY_EVENT
+                    } else {
+                        SELL_EVENT
+                    };
+                    events.push(event(TRADE_EVENT | side));
+                }
+                FileType::IncrementalBookL2 => {
+                    if cols[4] == "true" {
+                        if self.snapshot_mode == SnapshotMode::Ignore
+                            || (self.snapshot_mode == SnapshotMode::IgnoreSod && is_sod_snapshot)
+                        {
+                            continue;
+                        }
+                        if cols[5] == "bid" {
+                            ss_bid.push(event(DEPTH_SNAPSHOT_EVENT | BUY_EVENT));
+                        } else {
+                            ss_ask.push(event(DEPTH_SNAPSHOT_EVENT | SELL_EVENT));
+                        }
+                    } else {
+                        is_sod_snapshot = false;
+                        flush_snapshot(events, &mut ss_bid, BUY_EVENT);
+                        flush_snapshot(events, &mut ss_ask, SELL_EVENT);
+                        let side = if cols[5] == "bid" {
+                            BUY_EVENT
+                        } else {
+                            SELL_EVENT
+                        };
+                        events.push(event(DEPTH_EVENT | side));
+                    }
+                }
+            }
+        }
+        flush_snapshot(events, &mut ss_bid, BUY_EVENT);
+        flush_snapshot(events, &mut ss_ask, SELL_EVENT);
+        Ok(())
+    }
+
+    /// Converts the Tardis.dev CSV data, such as both `incremental_book_L2` and `trades` of the
+    /// same symbol, into [`Event`] data, with the local timestamps and the event order corrected.
+    /// The file type is detected by its header row.
+    pub fn convert<R: BufRead>(&self, readers: Vec<R>) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        for reader in readers {
+            self.read(reader, &mut events)?;
+        }
+        correct_local_timestamp(&mut events, self.base_latency);
+        let events = correct_event_order(&events);
+        validate_event_order(&events)?;
+        Ok(events)
+    }
+
+    /// Converts the Tardis.dev CSV files into [`Event`] data. Files with the `.gz` extension are
+    /// decompressed. If `output_filename` is provided, the converted data is also saved to it in
+    /// `npy` format.
+    pub fn convert_files(
+        &self,
+        input_filenames: &[&str],
+        output_filename: Option<&str>,
+    ) -> Result<Vec<Event>> {
+        let readers = input_filenames
+            .iter()
+            .map(|filename| open_input(filename))
+            .collect::<Result<Vec<_>>>()?;
+        let events = self.convert(readers)?;
+        if let Some(output_filename) = output_filename {
+            write_output(output_filename, &events)?;
+        }
+        Ok(events)
+    }
+}
+
+/// Appends the snapshot of one side, preceded by a [`DEPTH_CLEAR_EVENT`] that clears the market
+/// depth up to the farthest price in the snapshot.
+fn flush_snapshot(events: &mut Vec<Event>, snapshot: &mut Vec<Event>, side: u64) {
+    if let (Some(first), Some(last)) = (snapshot.first(), snapshot.last()) {
+        events.push(Event {
+            ev: DEPTH_CLEAR_EVENT | side,
+            exch_ts: first.exch_ts,
+            local_ts: first.local_ts,
+            px: last.px,
+            qty: 0.0,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        });
+        events.append(snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Converter, SnapshotMode};
+    use crate::types::{
+        BUY_EVENT,
+        DEPTH_CLEAR_EVENT,
+        DEPTH_EVENT,
+        DEPTH_SNAPSHOT_EVENT,
+        EXCH_EVENT,
+        LOCAL_EVENT,
+        SELL_EVENT,
+        TRADE_EVENT,
+    };
+
+    const BOOK: &str = "exchange,symbol,timestamp,local_timestamp,is_snapshot,side,price,amount
+binance-futures,BTCUSDT,100,110,true,bid,10.0,1.0
+binance-futures,BTCUSDT,100,110,true,bid,9.0,2.0
+binance-futures,BTCUSDT,100,110,true,ask,11.0,3.0
+binance-futures,BTCUSDT,200,210,false,bid,10.0,0.0
+";
+
+    const TRADES: &str = "exchange,symbol,timestamp,local_timestamp,id,side,price,amount
+binance-futures,BTCUSDT,150,160,1,sell,10.0,0.5
+";
+
+    fn summary(mode: SnapshotMode) -> Vec<(u64, i64, f64, f64)> {
+        Converter::new()
+            .snapshot_mode(mode)
+            .convert(vec![BOOK.as_bytes(), TRADES.as_bytes()])
+            .unwrap()
+            .iter()
+            .map(|ev| {
+                (
+                    ev.ev & !(EXCH_EVENT | LOCAL_EVENT),
+                    ev.exch_ts,
+                    ev.px,
+                    ev.qty,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(
+            summary(SnapshotMode::Process),
+            vec![
+                (DEPTH_CLEAR_EVENT | BUY_EVENT, 100_000, 9.0, 0.0),
+                (DEPTH_SNAPSHOT_EVENT | BUY_EVENT, 100_000, 10.0, 1.0),
+                (DEPTH_SNAPSHOT_EVENT | BUY_EVENT, 100_000, 9.0, 2.0),
+                (DEPTH_CLEAR_EVENT | SELL_EVENT, 100_000, 11.0, 0.0),
+                (DEPTH_SNAPSHOT_EVENT | SELL_EVENT, 100_000, 11.0, 3.0),
+                (TRADE_EVENT | SELL_EVENT, 150_000, 10.0, 0.5),
+                (DEPTH_EVENT | BUY_EVENT, 200_000, 10.0, 0.0),
+            ]
+        );
+        assert_eq!(
+            summary(SnapshotMode::IgnoreSod),
+            vec![
+                (TRADE_EVENT | SELL_EVENT, 150_000, 10.0, 0.5),
+                (DEPTH_EVENT | BUY_EVENT, 200_000, 10.0, 0.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,13 @@
+/// Provides converters from raw feed data into [`Event`](crate::types::Event) data.
+#[cfg(feature = "convert")]
+pub mod convert;
+
+/// Provides a utility to derive order and feed latency from packet captures.
+#[cfg(feature = "pcap")]
+pub mod pcap;
+
+/// Provides a utility to build a market depth snapshot from incremental depth data.
+pub mod snapshot;
+
+/// Provides a utility to report the quality of event data before running a backtest.
+pub mod quality;
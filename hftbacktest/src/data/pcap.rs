@@ -0,0 +1,506 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Error, ErrorKind, Read, Result},
+    net::{Ipv4Addr, SocketAddrV4},
+    path::Path,
+};
+
+use hftbacktest_derive::NpyDTyped;
+
+use crate::backtest::{
+    models::OrderLatencyRow,
+    reader::{write_npz, POD},
+};
+
+// The link-layer header types.
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const IPPROTO_TCP: u8 = 6;
+
+/// A TCP segment captured on the wire.
+#[derive(Clone, Debug)]
+pub struct Packet {
+    /// The capture timestamp in nanoseconds.
+    pub timestamp: i64,
+    /// The source address.
+    pub src: SocketAddrV4,
+    /// The destination address.
+    pub dst: SocketAddrV4,
+    /// The TCP payload.
+    pub payload: Vec<u8>,
+}
+
+/// Reads TCP segments over IPv4 from a classic `pcap` file, with either microsecond or nanosecond
+/// timestamp resolution. Ethernet, Linux cooked capture, and raw IP link types are supported.
+/// Packets other than TCP segments with a payload are skipped. The segments are not reassembled,
+/// so a message split across segments is seen in pieces.
+pub struct PcapReader<R> {
+    reader: R,
+    big_endian: bool,
+    nanosecond: bool,
+    link_type: u32,
+}
+
+impl PcapReader<BufReader<File>> {
+    /// Opens the `pcap` file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R> PcapReader<R>
+where
+    R: Read,
+{
+    /// Constructs an instance of `PcapReader` by reading the global header.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut header = [0u8; 24];
+        reader.read_exact(&mut header)?;
+        let (big_endian, nanosecond) = match header[..4] {
+            [0xd4, 0xc3, 0xb2, 0xa1] => (false, false),
+            [0xa1, 0xb2, 0xc3, 0xd4] => (true, false),
+            [0x4d, 0x3c, 0xb2, 0xa1] => (false, true),
+            [0xa1, 0xb2, 0x3c, 0x4d] => (true, true),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "not a pcap file; pcapng is not supported",
+                ));
+            }
+        };
+        let mut this = Self {
+            reader,
+            big_endian,
+            nanosecond,
+            link_type: 0,
+        };
+        this.link_type = this.u32(&header[20..24]);
+        match this.link_type {
+            LINKTYPE_ETHERNET | LINKTYPE_RAW | LINKTYPE_LINUX_SLL => Ok(this),
+            link_type => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported link type {link_type}"),
+            )),
+        }
+    }
+
+    fn u32(&self, buf: &[u8]) -> u32 {
+        let buf = buf.try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(buf)
+        } else {
+            u32::from_le_bytes(buf)
+        }
+    }
+
+    /// Returns the next TCP segment, or `None` at the end of the file.
+    pub fn next_packet(&mut self) -> Result<Option<Packet>> {
+        loop {
+            let mut header = [0u8; 16];
+            match self.reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(error) => return Err(error),
+            }
+            let sec = self.u32(&header[0..4]) as i64;
+            let frac = self.u32(&header[4..8]) as i64;
+            let incl_len = self.u32(&header[8..12]) as usize;
+            let mut frame = vec![0u8; incl_len];
+            self.reader.read_exact(&mut frame)?;
+
+            let timestamp = sec * 1_000_000_000 + if self.nanosecond { frac } else { frac * 1_000 };
+            if let Some(packet) = self.parse(timestamp, &frame) {
+                return Ok(Some(packet));
+            }
+        }
+    }
+
+    fn parse(&self, timestamp: i64, frame: &[u8]) -> Option<Packet> {
+        let ip = match self.link_type {
+            LINKTYPE_ETHERNET => {
+                let mut pos = 12;
+                let mut ether_type = u16::from_be_bytes(frame.get(pos..pos + 2)?.try_into().ok()?);
+                while ether_type == ETHERTYPE_VLAN {
+                    pos += 4;
+                    ether_type = u16::from_be_bytes(frame.get(pos..pos + 2)?.try_into().ok()?);
+                }
+                if ether_type != ETHERTYPE_IPV4 {
+                    return None;
+                }
+                frame.get(pos + 2..)?
+            }
+            LINKTYPE_LINUX_SLL => {
+                let ether_type = u16::from_be_bytes(frame.get(14..16)?.try_into().ok()?);
+                if ether_type != ETHERTYPE_IPV4 {
+                    return None;
+                }
+                frame.get(16..)?
+            }
+            _ => frame,
+        };
+
+        if ip.first()? >> 4 != 4 || *ip.get(9)? != IPPROTO_TCP {
+            return None;
+        }
+        let ihl = ((ip[0] & 0x0f) as usize) * 4;
+        let total_len = u16::from_be_bytes(ip.get(2..4)?.try_into().ok()?) as usize;
+        // The frame may have the Ethernet padding after the IP packet.
+        let ip = ip.get(..total_len.min(ip.len()))?;
+        if ihl < 20 || ip.len() < ihl {
+            return None;
+        }
+        let src_ip = Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]);
+        let dst_ip = Ipv4Addr::new(ip[16], ip[17], ip[18], ip[19]);
+
+        let tcp = ip.get(ihl..)?;
+        let src_port = u16::from_be_bytes(tcp.get(0..2)?.try_into().ok()?);
+        let dst_port = u16::from_be_bytes(tcp.get(2..4)?.try_into().ok()?);
+        let data_offset = ((tcp.get(12)? >> 4) as usize) * 4;
+        let payload = tcp.get(data_offset..)?;
+        if payload.is_empty() {
+            return None;
+        }
+        Some(Packet {
+            timestamp,
+            src: SocketAddrV4::new(src_ip, src_port),
+            dst: SocketAddrV4::new(dst_ip, dst_port),
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// Decodes the WebSocket frames in the TCP payload and returns the unmasked payloads of the text
+/// and binary frames. Decoding stops at a frame that is cut off at the end of the segment. This
+/// only works for plaintext WebSocket, such as the traffic captured before TLS termination.
+pub fn websocket_messages(payload: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = Vec::new();
+    let mut pos = 0;
+    while pos + 2 <= payload.len() {
+        let opcode = payload[pos] & 0x0f;
+        let masked = payload[pos + 1] & 0x80 != 0;
+        let mut len = (payload[pos + 1] & 0x7f) as usize;
+        pos += 2;
+        if len == 126 {
+            let Some(buf) = payload.get(pos..pos + 2) else {
+                break;
+            };
+            len = u16::from_be_bytes(buf.try_into().unwrap()) as usize;
+            pos += 2;
+        } else if len == 127 {
+            let Some(buf) = payload.get(pos..pos + 8) else {
+                break;
+            };
+            len = u64::from_be_bytes(buf.try_into().unwrap()) as usize;
+            pos += 8;
+        }
+        let mask = if masked {
+            let Some(mask) = payload.get(pos..pos + 4) else {
+                break;
+            };
+            pos += 4;
+            Some([mask[0], mask[1], mask[2], mask[3]])
+        } else {
+            None
+        };
+        let Some(data) = payload.get(pos..pos.saturating_add(len)) else {
+            break;
+        };
+        pos += len;
+        // Continuation frames, 0x0, are not supported, since the message is not reassembled.
+        if opcode == 0x1 || opcode == 0x2 {
+            let mut data = data.to_vec();
+            if let Some(mask) = mask {
+                for (i, byte) in data.iter_mut().enumerate() {
+                    *byte ^= mask[i % 4];
+                }
+            }
+            messages.push(data);
+        }
+    }
+    messages
+}
+
+/// A message classified by [`MessageClassifier`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WireMessage {
+    /// An order request sent to the exchange, identified by the key, such as the client order ID.
+    Request { key: u64 },
+    /// The exchange's response to the order request with the key, with the timestamp in
+    /// nanoseconds at which the exchange processed the request.
+    Response { key: u64, exch_ts: i64 },
+    /// The exchange's rejection of the order request with the key due to technical reasons.
+    Rejection { key: u64 },
+    /// A market data message with the exchange timestamp in nanoseconds.
+    Feed { exch_ts: i64 },
+}
+
+/// Classifies the messages in a captured packet. Since the message formats vary by exchange and
+/// protocol, this needs to be implemented by the user, typically with [`websocket_messages`] and
+/// a JSON or binary decoder. It's implemented for closures.
+pub trait MessageClassifier {
+    /// Returns the messages in the packet that are relevant to the latency.
+    fn classify(&mut self, packet: &Packet) -> Vec<WireMessage>;
+}
+
+impl<F> MessageClassifier for F
+where
+    F: FnMut(&Packet) -> Vec<WireMessage>,
+{
+    fn classify(&mut self, packet: &Packet) -> Vec<WireMessage> {
+        self(packet)
+    }
+}
+
+/// The feed latency, the difference between the capture timestamp and the exchange timestamp of
+/// a market data message.
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq, Eq, NpyDTyped)]
+pub struct FeedLatencyRow {
+    /// Timestamp at which the exchange sends the message.
+    pub exch_ts: i64,
+    /// Timestamp at which the message is captured.
+    pub local_ts: i64,
+}
+
+unsafe impl POD for FeedLatencyRow {}
+
+/// Derives the order round-trip latency and feed latency series from packet captures, using the
+/// capture timestamps as the wire timestamps. The order latency is emitted in the format that
+/// [`IntpOrderLatency`](crate::backtest::models::IntpOrderLatency) reads: the request is matched
+/// with its response by the key, and a rejected request has an exchange timestamp of zero.
+/// Requests without a response are dropped.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::data::pcap::{websocket_messages, LatencyExtractor, Packet, WireMessage};
+///
+/// let mut extractor = LatencyExtractor::new(|packet: &Packet| {
+///     websocket_messages(&packet.payload)
+///         .iter()
+///         .filter_map(|_message| {
+///             // Decodes the exchange's message format here.
+///             None::<WireMessage>
+///         })
+///         .collect::<Vec<_>>()
+/// });
+/// extractor.process_file("capture.pcap").unwrap();
+/// extractor.order_latency_to_npz("latency.npz").unwrap();
+/// ```
+pub struct LatencyExtractor<C> {
+    classifier: C,
+    pending: HashMap<u64, i64>,
+    order_latency: Vec<OrderLatencyRow>,
+    feed_latency: Vec<FeedLatencyRow>,
+}
+
+impl<C> LatencyExtractor<C>
+where
+    C: MessageClassifier,
+{
+    /// Constructs an instance of `LatencyExtractor`.
+    pub fn new(classifier: C) -> Self {
+        Self {
+            classifier,
+            pending: Default::default(),
+            order_latency: Default::default(),
+            feed_latency: Default::default(),
+        }
+    }
+
+    /// Processes the `pcap` file. Multiple files can be processed in chronological order.
+    pub fn process_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.process(PcapReader::open(path)?)
+    }
+
+    /// Processes the packets read by the [`PcapReader`].
+    pub fn process<R: Read>(&mut self, mut reader: PcapReader<R>) -> Result<()> {
+        while let Some(packet) = reader.next_packet()? {
+            self.process_packet(&packet);
+        }
+        Ok(())
+    }
+
+    /// Processes the packet.
+    pub fn process_packet(&mut self, packet: &Packet) {
+        for message in self.classifier.classify(packet) {
+            match message {
+                WireMessage::Request { key } => {
+                    self.pending.insert(key, packet.timestamp);
+                }
+                WireMessage::Response { key, exch_ts } => {
+                    if let Some(req_ts) = self.pending.remove(&key) {
+                        self.order_latency.push(OrderLatencyRow {
+                            req_ts,
+                            exch_ts,
+                            resp_ts: packet.timestamp,
+                            _padding: 0,
+                        });
+                    }
+                }
+                WireMessage::Rejection { key } => {
+                    if let Some(req_ts) = self.pending.remove(&key) {
+                        self.order_latency.push(OrderLatencyRow {
+                            req_ts,
+                            exch_ts: 0,
+                            resp_ts: packet.timestamp,
+                            _padding: 0,
+                        });
+                    }
+                }
+                WireMessage::Feed { exch_ts } => {
+                    self.feed_latency.push(FeedLatencyRow {
+                        exch_ts,
+                        local_ts: packet.timestamp,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns the order latency, sorted by the request timestamp.
+    pub fn order_latency(&self) -> Vec<OrderLatencyRow> {
+        let mut rows = self.order_latency.clone();
+        rows.sort_by_key(|row| row.req_ts);
+        rows
+    }
+
+    /// Returns the feed latency in the captured order.
+    pub fn feed_latency(&self) -> &[FeedLatencyRow] {
+        &self.feed_latency
+    }
+
+    /// Saves the order latency into the `npz` file that
+    /// [`IntpOrderLatency`](crate::backtest::models::IntpOrderLatency) reads.
+    pub fn order_latency_to_npz<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut file = File::create(path)?;
+        write_npz(&mut file, "data", &self.order_latency())
+    }
+
+    /// Saves the feed latency into the `npz` file.
+    pub fn feed_latency_to_npz<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut file = File::create(path)?;
+        write_npz(&mut file, "data", &self.feed_latency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{websocket_messages, LatencyExtractor, Packet, PcapReader, WireMessage};
+
+    fn frame(src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&0x0800u16.to_be_bytes());
+        let total_len = (20 + 20 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&total_len.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 64, 6, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        frame.extend_from_slice(&src_port.to_be_bytes());
+        frame.extend_from_slice(&dst_port.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0x50, 0x18, 0, 0, 0, 0, 0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn websocket(message: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+        let mut buf = vec![
+            0x81,
+            message.len() as u8 | if mask.is_some() { 0x80 } else { 0 },
+        ];
+        match mask {
+            Some(mask) => {
+                buf.extend_from_slice(&mask);
+                buf.extend(message.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+            }
+            None => buf.extend_from_slice(message),
+        }
+        buf
+    }
+
+    fn pcap(records: &[(u32, u32, Vec<u8>)]) -> Vec<u8> {
+        let mut buf = vec![0x4d, 0x3c, 0xb2, 0xa1, 2, 0, 4, 0];
+        buf.extend_from_slice(&[0; 8]);
+        buf.extend_from_slice(&65535u32.to_le_bytes());
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        for (sec, nsec, frame) in records {
+            buf.extend_from_slice(&sec.to_le_bytes());
+            buf.extend_from_slice(&nsec.to_le_bytes());
+            buf.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            buf.extend_from_slice(frame);
+        }
+        buf
+    }
+
+    #[test]
+    fn test_latency_extractor() {
+        let buf = pcap(&[
+            (
+                1,
+                0,
+                frame(5000, 443, &websocket(b"new 1", Some([1, 2, 3, 4]))),
+            ),
+            (
+                1,
+                100,
+                frame(443, 5000, &websocket(b"book 999999950", None)),
+            ),
+            (
+                1,
+                500,
+                frame(443, 5000, &websocket(b"ack 1 1000000300", None)),
+            ),
+            (
+                2,
+                0,
+                frame(5000, 443, &websocket(b"new 2", Some([5, 6, 7, 8]))),
+            ),
+            (2, 900, frame(443, 5000, &websocket(b"rej 2", None))),
+        ]);
+        let mut extractor = LatencyExtractor::new(|packet: &Packet| {
+            websocket_messages(&packet.payload)
+                .iter()
+                .filter_map(|message| {
+                    let message = String::from_utf8(message.clone()).unwrap();
+                    let fields = message.split(' ').collect::<Vec<_>>();
+                    let value = |i: usize| fields[i].parse::<u64>().unwrap();
+                    match fields[0] {
+                        "new" => Some(WireMessage::Request { key: value(1) }),
+                        "ack" => Some(WireMessage::Response {
+                            key: value(1),
+                            exch_ts: value(2) as i64,
+                        }),
+                        "rej" => Some(WireMessage::Rejection { key: value(1) }),
+                        "book" => Some(WireMessage::Feed {
+                            exch_ts: value(1) as i64,
+                        }),
+                        _ => None,
+                    }
+                })
+                .collect()
+        });
+        extractor
+            .process(PcapReader::new(Cursor::new(buf)).unwrap())
+            .unwrap();
+
+        let rows = extractor.order_latency();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            (rows[0].req_ts, rows[0].exch_ts, rows[0].resp_ts),
+            (1_000_000_000, 1_000_000_300, 1_000_000_500)
+        );
+        assert_eq!(
+            (rows[1].req_ts, rows[1].exch_ts, rows[1].resp_ts),
+            (2_000_000_000, 0, 2_000_000_900)
+        );
+        let feed = extractor.feed_latency();
+        assert_eq!(feed.len(), 1);
+        assert_eq!(feed[0].local_ts - feed[0].exch_ts, 150);
+    }
+}
@@ -0,0 +1,451 @@
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    backtest::{
+        reader::{Cache, DataSource, Reader},
+        BacktestError,
+    },
+    data::snapshot::apply,
+    depth::{BTreeMarketDepth, MarketDepth, INVALID_MAX, INVALID_MIN},
+    types::{Event, EXCH_EVENT, LOCAL_EVENT},
+};
+
+/// A period longer than the gap threshold without any local events.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Gap {
+    /// The local timestamp of the last event before the gap.
+    pub begin_ts: i64,
+    /// The local timestamp of the first event after the gap.
+    pub end_ts: i64,
+}
+
+/// A period during which the local market depth is crossed, that is, the best bid is at or above
+/// the best ask.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CrossedBook {
+    /// The local timestamp at which the market depth becomes crossed.
+    pub begin_ts: i64,
+    /// The local timestamp at which the market depth becomes uncrossed, or the local timestamp of
+    /// the last event if it remains crossed until the end of the data.
+    pub end_ts: i64,
+    /// The best bid at the time the market depth becomes crossed.
+    pub best_bid: f64,
+    /// The best ask at the time the market depth becomes crossed.
+    pub best_ask: f64,
+}
+
+/// The distribution of the feed latency, `local_ts - exch_ts`, of the local events.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub min: i64,
+    pub max: i64,
+    pub mean: f64,
+    pub p50: i64,
+    pub p90: i64,
+    pub p99: i64,
+    pub p999: i64,
+}
+
+impl LatencyStats {
+    fn from_latencies(mut latencies: Vec<i64>) -> Self {
+        if latencies.is_empty() {
+            return Default::default();
+        }
+        latencies.sort_unstable();
+        let n = latencies.len();
+        let percentile = |p: f64| latencies[((n - 1) as f64 * p).round() as usize];
+        Self {
+            count: n,
+            min: latencies[0],
+            max: latencies[n - 1],
+            mean: latencies.iter().map(|&v| v as f64).sum::<f64>() / n as f64,
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            p999: percentile(0.999),
+        }
+    }
+}
+
+/// The result of [`QualityCheck`].
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct QualityReport {
+    /// The number of events.
+    pub num_events: usize,
+    /// The local timestamp of the first local event.
+    pub begin_ts: i64,
+    /// The local timestamp of the last local event.
+    pub end_ts: i64,
+    /// The number of local events in each interval, given as the local timestamp at the start of
+    /// the interval and the number of events. Intervals without events are included.
+    pub update_rate: Vec<(i64, usize)>,
+    /// The periods longer than the gap threshold without any local events.
+    pub gaps: Vec<Gap>,
+    /// The periods during which the local market depth is crossed.
+    pub crossed_books: Vec<CrossedBook>,
+    /// The number of exchange events whose exchange timestamp equals that of the previous exchange
+    /// event received at a different local timestamp, which indicates that the exchange timestamps
+    /// are too coarse to order the events.
+    pub duplicate_timestamps: usize,
+    /// The number of exchange events identical to an earlier event with the same exchange
+    /// timestamp except for the local timestamp, which indicates that messages are received or
+    /// recorded more than once.
+    pub duplicate_events: usize,
+    /// The distribution of the feed latency.
+    pub feed_latency: LatencyStats,
+}
+
+impl Display for QualityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "events: {} from {} to {}",
+            self.num_events, self.begin_ts, self.end_ts
+        )?;
+        let (min_rate, max_rate) = self
+            .update_rate
+            .iter()
+            .fold((usize::MAX, 0), |(min, max), &(_, n)| {
+                (min.min(n), max.max(n))
+            });
+        if !self.update_rate.is_empty() {
+            writeln!(
+                f,
+                "update rate: {min_rate} to {max_rate} events per interval over {} intervals",
+                self.update_rate.len()
+            )?;
+        }
+        writeln!(f, "gaps: {}", self.gaps.len())?;
+        for gap in &self.gaps {
+            writeln!(
+                f,
+                "  {} to {} ({} ns)",
+                gap.begin_ts,
+                gap.end_ts,
+                gap.end_ts - gap.begin_ts
+            )?;
+        }
+        writeln!(f, "crossed books: {}", self.crossed_books.len())?;
+        for crossed in &self.crossed_books {
+            writeln!(
+                f,
+                "  {} to {} (bid {} >= ask {})",
+                crossed.begin_ts, crossed.end_ts, crossed.best_bid, crossed.best_ask
+            )?;
+        }
+        writeln!(f, "duplicate timestamps: {}", self.duplicate_timestamps)?;
+        writeln!(f, "duplicate events: {}", self.duplicate_events)?;
+        let latency = &self.feed_latency;
+        write!(
+            f,
+            "feed latency (ns): min {} / mean {:.0} / p50 {} / p90 {} / p99 {} / p99.9 {} / max {}",
+            latency.min,
+            latency.mean,
+            latency.p50,
+            latency.p90,
+            latency.p99,
+            latency.p999,
+            latency.max
+        )
+    }
+}
+
+/// Scans event data and reports the update rate over time, gaps, crossed market depth, duplicate
+/// timestamps and events, and the feed latency distribution, so that data can be vetted before
+/// running a long backtest.
+///
+/// The market depth is built from the local depth events in the same way as the local processor
+/// does, and it is checked whether it is crossed once all events with the same local timestamp
+/// have been applied. It's built with [`BTreeMarketDepth`], which keeps the crossed price levels
+/// instead of uncrossing the best bid and ask, so that the crossing is reported.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::{backtest::DataSource, data::quality::QualityCheck};
+///
+/// let report = QualityCheck::new(0.1, 0.001)
+///     .gap_threshold(5_000_000_000)
+///     .run(vec![DataSource::File("btcusdt_20240501.npz".to_string())])
+///     .unwrap();
+/// println!("{report}");
+/// ```
+#[derive(Clone, Debug)]
+pub struct QualityCheck {
+    tick_size: f64,
+    lot_size: f64,
+    interval: i64,
+    gap_threshold: i64,
+}
+
+impl QualityCheck {
+    /// Constructs an instance of `QualityCheck` with the tick size and the lot size of the asset.
+    pub fn new(tick_size: f64, lot_size: f64) -> Self {
+        Self {
+            tick_size,
+            lot_size,
+            interval: 60_000_000_000,
+            gap_threshold: 1_000_000_000,
+        }
+    }
+
+    /// Sets the interval in nanoseconds over which the update rate is counted. The default value
+    /// is 1 minute.
+    pub fn interval(self, interval: i64) -> Self {
+        Self {
+            interval: interval.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the minimum period in nanoseconds without any local events that is reported as a gap.
+    /// The default value is 1 second.
+    pub fn gap_threshold(self, gap_threshold: i64) -> Self {
+        Self {
+            gap_threshold,
+            ..self
+        }
+    }
+
+    /// Scans the data in order and returns the report.
+    pub fn run(&self, data: Vec<DataSource<Event>>) -> Result<QualityReport, BacktestError> {
+        let mut reader = Reader::new(Cache::new());
+        for item in data {
+            match item {
+                DataSource::File(filename) => reader.add_file(filename),
+                DataSource::Data(data) => reader.add_data(data),
+                DataSource::Url(url) => reader.add_url(url),
+                DataSource::Query(query) => reader.add_query(query),
+                DataSource::Iterator(generator) => reader.add_generator(generator),
+            }
+        }
+
+        let mut scanner = Scanner::new(self);
+        loop {
+            let data = match reader.next() {
+                Ok(data) => data,
+                Err(BacktestError::EndOfData) => break,
+                Err(e) => return Err(e),
+            };
+            for row in 0..data.len() {
+                scanner.scan(&data[row]);
+            }
+            reader.release(data);
+        }
+        Ok(scanner.finish())
+    }
+}
+
+struct Scanner<'a> {
+    check: &'a QualityCheck,
+    report: QualityReport,
+    depth: BTreeMarketDepth,
+    latencies: Vec<i64>,
+    prev_local_ts: Option<i64>,
+    crossed: Option<CrossedBook>,
+    prev_exch: Option<(i64, i64)>,
+    // The exchange events with the current exchange timestamp, excluding the local timestamps.
+    same_exch_ts: HashSet<(u64, u64, u64, u64)>,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(check: &'a QualityCheck) -> Self {
+        Self {
+            check,
+            report: Default::default(),
+            depth: BTreeMarketDepth::new(check.tick_size, check.lot_size),
+            latencies: Vec::new(),
+            prev_local_ts: None,
+            crossed: None,
+            prev_exch: None,
+            same_exch_ts: HashSet::new(),
+        }
+    }
+
+    fn scan(&mut self, ev: &Event) {
+        self.report.num_events += 1;
+        if ev.is(EXCH_EVENT) {
+            self.scan_exch(ev);
+        }
+        if ev.is(LOCAL_EVENT) {
+            self.scan_local(ev);
+        }
+    }
+
+    fn scan_exch(&mut self, ev: &Event) {
+        match self.prev_exch {
+            Some((exch_ts, local_ts)) if exch_ts == ev.exch_ts => {
+                if local_ts != ev.local_ts {
+                    self.report.duplicate_timestamps += 1;
+                }
+            }
+            _ => self.same_exch_ts.clear(),
+        }
+        self.prev_exch = Some((ev.exch_ts, ev.local_ts));
+        let key = (
+            ev.ev & !(EXCH_EVENT | LOCAL_EVENT),
+            ev.px.to_bits(),
+            ev.qty.to_bits(),
+            ev.order_id,
+        );
+        if !self.same_exch_ts.insert(key) {
+            self.report.duplicate_events += 1;
+        }
+    }
+
+    fn scan_local(&mut self, ev: &Event) {
+        self.latencies.push(ev.local_ts - ev.exch_ts);
+        match self.prev_local_ts {
+            None => {
+                self.report.begin_ts = ev.local_ts;
+            }
+            Some(prev_local_ts) if prev_local_ts != ev.local_ts => {
+                self.check_crossed(prev_local_ts);
+                if ev.local_ts - prev_local_ts > self.check.gap_threshold {
+                    self.report.gaps.push(Gap {
+                        begin_ts: prev_local_ts,
+                        end_ts: ev.local_ts,
+                    });
+                }
+            }
+            _ => {}
+        }
+        self.prev_local_ts = Some(ev.local_ts);
+        self.report.end_ts = ev.local_ts;
+
+        let interval = self.check.interval;
+        let bucket = ev.local_ts.div_euclid(interval) * interval;
+        match self.report.update_rate.last_mut() {
+            Some((ts, n)) if *ts == bucket => *n += 1,
+            Some(&mut (ts, _)) => {
+                let mut ts = ts + interval;
+                while ts < bucket {
+                    self.report.update_rate.push((ts, 0));
+                    ts += interval;
+                }
+                self.report.update_rate.push((bucket, 1));
+            }
+            None => self.report.update_rate.push((bucket, 1)),
+        }
+
+        apply(&mut self.depth, ev);
+    }
+
+    /// Checks whether the market depth is crossed after all events with the local timestamp have
+    /// been applied.
+    fn check_crossed(&mut self, local_ts: i64) {
+        let best_bid_tick = self.depth.best_bid_tick();
+        let best_ask_tick = self.depth.best_ask_tick();
+        let is_crossed = best_bid_tick != INVALID_MIN
+            && best_ask_tick != INVALID_MAX
+            && best_bid_tick >= best_ask_tick;
+        match (is_crossed, self.crossed.take()) {
+            (true, None) => {
+                self.crossed = Some(CrossedBook {
+                    begin_ts: local_ts,
+                    end_ts: local_ts,
+                    best_bid: self.depth.best_bid(),
+                    best_ask: self.depth.best_ask(),
+                });
+            }
+            (true, Some(crossed)) => self.crossed = Some(crossed),
+            (false, Some(crossed)) => {
+                self.report.crossed_books.push(CrossedBook {
+                    end_ts: local_ts,
+                    ..crossed
+                });
+            }
+            (false, None) => {}
+        }
+    }
+
+    fn finish(mut self) -> QualityReport {
+        if let Some(local_ts) = self.prev_local_ts {
+            self.check_crossed(local_ts);
+            if let Some(crossed) = self.crossed.take() {
+                self.report.crossed_books.push(CrossedBook {
+                    end_ts: local_ts,
+                    ..crossed
+                });
+            }
+        }
+        self.report.feed_latency = LatencyStats::from_latencies(self.latencies);
+        self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrossedBook, Gap, QualityCheck};
+    use crate::{
+        backtest::{reader::Data, DataSource},
+        types::{Event, BUY_EVENT, DEPTH_EVENT, EXCH_EVENT, LOCAL_EVENT, SELL_EVENT, TRADE_EVENT},
+    };
+
+    fn event(ev: u64, exch_ts: i64, local_ts: i64, px: f64, qty: f64) -> Event {
+        Event {
+            ev: EXCH_EVENT | LOCAL_EVENT | ev,
+            exch_ts,
+            local_ts,
+            px,
+            qty,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let data = Data::from_slice(&[
+            event(DEPTH_EVENT | BUY_EVENT, 0, 10, 100.0, 1.0),
+            event(DEPTH_EVENT | SELL_EVENT, 0, 10, 101.0, 1.0),
+            // Crossed until the ask is updated at the same local timestamp.
+            event(DEPTH_EVENT | BUY_EVENT, 20, 30, 101.0, 1.0),
+            event(DEPTH_EVENT | SELL_EVENT, 20, 30, 101.0, 0.0),
+            event(DEPTH_EVENT | SELL_EVENT, 20, 30, 102.0, 1.0),
+            // Crossed from 50 until the bid is removed at 3_000.
+            event(DEPTH_EVENT | BUY_EVENT, 40, 50, 102.0, 1.0),
+            event(TRADE_EVENT | BUY_EVENT, 40, 60, 102.0, 1.0),
+            event(TRADE_EVENT | BUY_EVENT, 40, 60, 102.0, 1.0),
+            event(DEPTH_EVENT | BUY_EVENT, 60, 3_000, 102.0, 0.0),
+        ]);
+        let report = QualityCheck::new(1.0, 1.0)
+            .interval(1_000)
+            .gap_threshold(1_000)
+            .run(vec![DataSource::Data(data)])
+            .unwrap();
+
+        assert_eq!(report.num_events, 9);
+        assert_eq!((report.begin_ts, report.end_ts), (10, 3_000));
+        assert_eq!(
+            report.update_rate,
+            vec![(0, 8), (1_000, 0), (2_000, 0), (3_000, 1)]
+        );
+        assert_eq!(
+            report.gaps,
+            vec![Gap {
+                begin_ts: 60,
+                end_ts: 3_000
+            }]
+        );
+        assert_eq!(
+            report.crossed_books,
+            vec![CrossedBook {
+                begin_ts: 50,
+                end_ts: 3_000,
+                best_bid: 102.0,
+                best_ask: 102.0,
+            }]
+        );
+        assert_eq!(report.duplicate_timestamps, 1);
+        assert_eq!(report.duplicate_events, 1);
+        assert_eq!(report.feed_latency.count, 9);
+        assert_eq!(report.feed_latency.min, 10);
+        assert_eq!(report.feed_latency.max, 2_940);
+        assert_eq!(report.feed_latency.p50, 10);
+    }
+}
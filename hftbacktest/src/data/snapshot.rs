@@ -0,0 +1,262 @@
+use std::{fs::File, io::BufWriter};
+
+use crate::{
+    backtest::{
+        reader::{write_npz, Cache, Data, DataSource, Reader},
+        BacktestError,
+    },
+    depth::{ApplySnapshot, HashMapMarketDepth, L2MarketDepth},
+    types::{
+        Event,
+        Side,
+        LOCAL_ASK_DEPTH_CLEAR_EVENT,
+        LOCAL_ASK_DEPTH_EVENT,
+        LOCAL_ASK_DEPTH_SNAPSHOT_EVENT,
+        LOCAL_BID_DEPTH_CLEAR_EVENT,
+        LOCAL_BID_DEPTH_EVENT,
+        LOCAL_BID_DEPTH_SNAPSHOT_EVENT,
+        LOCAL_DEPTH_CLEAR_EVENT,
+        LOCAL_EVENT,
+    },
+};
+
+/// Applies the local depth event to the market depth, in the same way as the local processor
+/// does. Other events are ignored.
+pub(crate) fn apply<MD>(depth: &mut MD, ev: &Event)
+where
+    MD: L2MarketDepth,
+{
+    if ev.is(LOCAL_BID_DEPTH_CLEAR_EVENT) {
+        depth.clear_depth(Side::Buy, ev.px);
+    } else if ev.is(LOCAL_ASK_DEPTH_CLEAR_EVENT) {
+        depth.clear_depth(Side::Sell, ev.px);
+    } else if ev.is(LOCAL_DEPTH_CLEAR_EVENT) {
+        depth.clear_depth(Side::None, 0.0);
+    } else if ev.is(LOCAL_BID_DEPTH_EVENT) || ev.is(LOCAL_BID_DEPTH_SNAPSHOT_EVENT) {
+        depth.update_bid_depth(ev.px, ev.qty, ev.local_ts);
+    } else if ev.is(LOCAL_ASK_DEPTH_EVENT) || ev.is(LOCAL_ASK_DEPTH_SNAPSHOT_EVENT) {
+        depth.update_ask_depth(ev.px, ev.qty, ev.local_ts);
+    }
+}
+
+/// Applies the local depth events in the data whose local timestamps are earlier than the
+/// `timestamp` to the market depth, in the same way as the local processor does. Returns `true` if
+/// a local event at or after the `timestamp` is reached, which means that the remaining data
+/// doesn't need to be replayed.
+pub fn replay<MD>(depth: &mut MD, data: &Data<Event>, timestamp: i64) -> bool
+where
+    MD: L2MarketDepth,
+{
+    for row in 0..data.len() {
+        let ev = &data[row];
+        if !ev.is(LOCAL_EVENT) {
+            continue;
+        }
+        if ev.local_ts >= timestamp {
+            return true;
+        }
+        apply(depth, ev);
+    }
+    false
+}
+
+/// Saves the market depth as a snapshot to the npz file, which can be restored by [`restore`] or
+/// used as the initial snapshot of a backtest. This allows checkpointing the market depth in the
+/// middle of the data, so that subsequent backtests can warm-start from it instead of replaying the
+/// data from the beginning.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::{
+///     backtest::DataSource,
+///     data::snapshot,
+///     depth::ROIVectorMarketDepth,
+/// };
+///
+/// let mut depth = ROIVectorMarketDepth::new(0.1, 0.001, 50_000.0, 80_000.0);
+/// // ...
+/// snapshot::save(&depth, "btcusdt_20240501_1200.npz").unwrap();
+///
+/// let mut restored = ROIVectorMarketDepth::new(0.1, 0.001, 50_000.0, 80_000.0);
+/// snapshot::restore(
+///     &mut restored,
+///     DataSource::File("btcusdt_20240501_1200.npz".to_string()),
+/// )
+/// .unwrap();
+/// ```
+pub fn save<MD>(depth: &MD, filename: &str) -> Result<(), BacktestError>
+where
+    MD: ApplySnapshot<Event>,
+{
+    let mut writer = BufWriter::new(File::create(filename)?);
+    write_npz(&mut writer, "data", &depth.snapshot())?;
+    Ok(())
+}
+
+/// Restores the market depth from the snapshot, such as the one saved by [`save`] or built by
+/// [`build`], replacing its current state.
+pub fn restore<MD>(depth: &mut MD, snapshot: DataSource<Event>) -> Result<(), BacktestError>
+where
+    MD: ApplySnapshot<Event>,
+{
+    let mut reader = Reader::new(Cache::new());
+    match snapshot {
+        DataSource::File(filename) => reader.add_file(filename),
+        DataSource::Data(data) => reader.add_data(data),
+        DataSource::Url(url) => reader.add_url(url),
+        DataSource::Query(query) => reader.add_query(query),
+        DataSource::Iterator(generator) => reader.add_generator(generator),
+    }
+    let data = reader.next()?;
+    depth.apply_snapshot(&data);
+    reader.release(data);
+    Ok(())
+}
+
+/// Builds the market depth snapshot at the `timestamp` by replaying the incremental depth events in
+/// the data, starting from the `initial_snapshot` if provided. The snapshot reflects the local
+/// depth events whose local timestamps are earlier than the `timestamp`, so passing [`i64::MAX`]
+/// builds the snapshot of the last market depth. The data is read in order, and reading stops once
+/// the `timestamp` is reached.
+///
+/// The snapshot can be used as the initial snapshot for subsequent data, which allows stitching
+/// multi-day backtests.
+///
+/// **Example**
+/// ```no_run
+/// use hftbacktest::{backtest::DataSource, data::snapshot};
+///
+/// let snapshot = snapshot::build(
+///     vec![DataSource::File("btcusdt_20240501.npz".to_string())],
+///     0.1,
+///     0.001,
+///     Some(DataSource::File("btcusdt_20240430_eod.npz".to_string())),
+///     i64::MAX,
+///     Some("btcusdt_20240501_eod.npz"),
+/// )
+/// .unwrap();
+/// ```
+pub fn build(
+    data: Vec<DataSource<Event>>,
+    tick_size: f64,
+    lot_size: f64,
+    initial_snapshot: Option<DataSource<Event>>,
+    timestamp: i64,
+    output_filename: Option<&str>,
+) -> Result<Vec<Event>, BacktestError> {
+    let mut depth = HashMapMarketDepth::new(tick_size, lot_size);
+    if let Some(initial_snapshot) = initial_snapshot {
+        restore(&mut depth, initial_snapshot)?;
+    }
+
+    let mut reader = Reader::new(Cache::new());
+    for item in data {
+        match item {
+            DataSource::File(filename) => reader.add_file(filename),
+            DataSource::Data(data) => reader.add_data(data),
+            DataSource::Url(url) => reader.add_url(url),
+            DataSource::Query(query) => reader.add_query(query),
+            DataSource::Iterator(generator) => reader.add_generator(generator),
+        }
+    }
+    loop {
+        let data = match reader.next() {
+            Ok(data) => data,
+            Err(BacktestError::EndOfData) => break,
+            Err(e) => return Err(e),
+        };
+        let reached = replay(&mut depth, &data, timestamp);
+        reader.release(data);
+        if reached {
+            break;
+        }
+    }
+
+    let snapshot = depth.snapshot();
+    if let Some(output_filename) = output_filename {
+        let mut writer = BufWriter::new(File::create(output_filename)?);
+        write_npz(&mut writer, "data", &snapshot)?;
+    }
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replay, restore, save};
+    use crate::{
+        backtest::{reader::Data, DataSource},
+        depth::{ApplySnapshot, HashMapMarketDepth, MarketDepth, ROIVectorMarketDepth},
+        types::{
+            Event,
+            EXCH_EVENT,
+            LOCAL_ASK_DEPTH_EVENT,
+            LOCAL_BID_DEPTH_CLEAR_EVENT,
+            LOCAL_BID_DEPTH_EVENT,
+            LOCAL_EVENT,
+        },
+    };
+
+    fn event(ev: u64, local_ts: i64, px: f64, qty: f64) -> Event {
+        Event {
+            ev: EXCH_EVENT | ev,
+            exch_ts: local_ts,
+            local_ts,
+            px,
+            qty,
+            order_id: 0,
+            ival: 0,
+            fval: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let data = Data::from_slice(&[
+            event(LOCAL_BID_DEPTH_EVENT, 1, 10.0, 1.0),
+            event(LOCAL_BID_DEPTH_EVENT, 1, 9.0, 2.0),
+            event(LOCAL_ASK_DEPTH_EVENT, 2, 11.0, 3.0),
+            event(LOCAL_BID_DEPTH_CLEAR_EVENT, 3, 9.5, 0.0),
+            // An exchange-only event is not replayed.
+            Event {
+                ev: (LOCAL_ASK_DEPTH_EVENT & !LOCAL_EVENT) | EXCH_EVENT,
+                ..event(0, 3, 12.0, 1.0)
+            },
+            event(LOCAL_BID_DEPTH_EVENT, 5, 9.0, 5.0),
+        ]);
+
+        let mut depth = HashMapMarketDepth::new(0.5, 1.0);
+        assert!(replay(&mut depth, &data, 5));
+        assert_eq!(depth.best_bid(), 9.0);
+        assert_eq!(depth.best_ask(), 11.0);
+
+        let snapshot = depth.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!((snapshot[0].px, snapshot[0].qty), (9.0, 2.0));
+        assert_eq!((snapshot[1].px, snapshot[1].qty), (11.0, 3.0));
+    }
+
+    #[test]
+    fn test_save_restore() {
+        let data = Data::from_slice(&[
+            event(LOCAL_BID_DEPTH_EVENT, 1, 10.0, 1.0),
+            event(LOCAL_BID_DEPTH_EVENT, 1, 9.0, 2.0),
+            event(LOCAL_ASK_DEPTH_EVENT, 2, 11.0, 3.0),
+            event(LOCAL_ASK_DEPTH_EVENT, 2, 13.5, 4.0),
+        ]);
+        let mut depth = ROIVectorMarketDepth::new(0.5, 1.0, 5.0, 20.0);
+        replay(&mut depth, &data, i64::MAX);
+
+        let filename = std::env::temp_dir().join("hftbacktest_test_save_restore.npz");
+        let filename = filename.to_str().unwrap();
+        save(&depth, filename).unwrap();
+
+        let mut restored = ROIVectorMarketDepth::new(0.5, 1.0, 5.0, 20.0);
+        restore(&mut restored, DataSource::File(filename.to_string())).unwrap();
+        std::fs::remove_file(filename).unwrap();
+        assert_eq!(restored.best_bid(), 10.0);
+        assert_eq!(restored.best_ask(), 11.0);
+        assert_eq!(restored.bid_qty_at_tick(18), 2.0);
+        assert_eq!(restored.ask_qty_at_tick(27), 4.0);
+        assert_eq!(restored.snapshot().len(), 4);
+    }
+}
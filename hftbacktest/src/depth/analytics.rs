@@ -0,0 +1,195 @@
+use super::{MarketDepth, INVALID_MAX, INVALID_MIN};
+
+/// Provides the order book measures commonly used by strategies, for every [`MarketDepth`].
+///
+/// The levels are counted in ticks from the best price, including the empty ones, so that the cost
+/// doesn't depend on how sparse the market depth is.
+pub trait DepthAnalytics: MarketDepth {
+    /// Returns the mid-price.
+    /// If there is no best bid or ask, it returns [`f64::NAN`].
+    fn mid_price(&self) -> f64 {
+        (self.best_bid() + self.best_ask()) / 2.0
+    }
+
+    /// Returns the microprice, which is the mid-price weighted by the quantities at the best bid and
+    /// ask, leaning toward the side with the lesser quantity.
+    /// If there is no best bid or ask, it returns [`f64::NAN`].
+    fn microprice(&self) -> f64 {
+        let bid_qty = self.bid_qty_at_tick(self.best_bid_tick());
+        let ask_qty = self.ask_qty_at_tick(self.best_ask_tick());
+        (self.best_bid() * ask_qty + self.best_ask() * bid_qty) / (bid_qty + ask_qty)
+    }
+
+    /// Returns the cumulative quantity of the bid market depth over the given number of levels
+    /// from the best bid.
+    fn bid_qty_over(&self, levels: usize) -> f64 {
+        let best_bid_tick = self.best_bid_tick();
+        if best_bid_tick == INVALID_MIN {
+            return 0.0;
+        }
+        (0..levels as i64)
+            .map(|i| self.bid_qty_at_tick(best_bid_tick - i))
+            .sum()
+    }
+
+    /// Returns the cumulative quantity of the ask market depth over the given number of levels
+    /// from the best ask.
+    fn ask_qty_over(&self, levels: usize) -> f64 {
+        let best_ask_tick = self.best_ask_tick();
+        if best_ask_tick == INVALID_MAX {
+            return 0.0;
+        }
+        (0..levels as i64)
+            .map(|i| self.ask_qty_at_tick(best_ask_tick + i))
+            .sum()
+    }
+
+    /// Returns the volume imbalance over the given number of levels from the best bid and ask,
+    /// which is `(bid qty - ask qty) / (bid qty + ask qty)`, ranging from `-1` to `1`.
+    /// If there is no quantity on both sides, it returns [`f64::NAN`].
+    fn imbalance(&self, levels: usize) -> f64 {
+        let bid_qty = self.bid_qty_over(levels);
+        let ask_qty = self.ask_qty_over(levels);
+        (bid_qty - ask_qty) / (bid_qty + ask_qty)
+    }
+
+    /// Returns the cumulative quantity of the bid market depth at the prices within the given
+    /// basis points below the mid-price.
+    fn bid_qty_within_bps(&self, bps: f64) -> f64 {
+        let mid_price = self.mid_price();
+        if mid_price.is_nan() {
+            return 0.0;
+        }
+        let lowest_tick = (mid_price * (1.0 - bps / 10_000.0) / self.tick_size()).ceil() as i64;
+        (lowest_tick..=self.best_bid_tick())
+            .map(|t| self.bid_qty_at_tick(t))
+            .sum()
+    }
+
+    /// Returns the cumulative quantity of the ask market depth at the prices within the given
+    /// basis points above the mid-price.
+    fn ask_qty_within_bps(&self, bps: f64) -> f64 {
+        let mid_price = self.mid_price();
+        if mid_price.is_nan() {
+            return 0.0;
+        }
+        let highest_tick = (mid_price * (1.0 + bps / 10_000.0) / self.tick_size()).floor() as i64;
+        (self.best_ask_tick()..=highest_tick)
+            .map(|t| self.ask_qty_at_tick(t))
+            .sum()
+    }
+}
+
+impl<MD> DepthAnalytics for MD where MD: MarketDepth + ?Sized {}
+
+/// Measures the book pressure as the exponentially weighted moving average of the volume
+/// imbalance over time, updated incrementally by each observation of the market depth.
+///
+/// The weight of an observation halves every `half_life`, so that the measure doesn't depend on
+/// how frequently it is updated.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::depth::BookPressure;
+///
+/// // The imbalance over 5 levels, with a half-life of 100ms.
+/// let pressure = BookPressure::new(5, 100_000_000);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BookPressure {
+    levels: usize,
+    half_life: i64,
+    value: f64,
+    last_ts: Option<i64>,
+}
+
+impl BookPressure {
+    /// Constructs an instance of `BookPressure` of the volume imbalance over the given number of
+    /// levels, with the `half_life` of the weight.
+    pub fn new(levels: usize, half_life: i64) -> Self {
+        assert!(half_life > 0);
+        Self {
+            levels,
+            half_life,
+            value: f64::NAN,
+            last_ts: None,
+        }
+    }
+
+    /// Updates the measure by the market depth at the timestamp and returns the updated value.
+    /// An observation without quantity on both sides is ignored.
+    pub fn update<MD>(&mut self, depth: &MD, timestamp: i64) -> f64
+    where
+        MD: MarketDepth + ?Sized,
+    {
+        let imbalance = depth.imbalance(self.levels);
+        if imbalance.is_nan() {
+            return self.value;
+        }
+        match self.last_ts {
+            Some(last_ts) if !self.value.is_nan() => {
+                let elapsed = (timestamp - last_ts).max(0) as f64;
+                let alpha = 1.0 - 0.5f64.powf(elapsed / self.half_life as f64);
+                self.value += alpha * (imbalance - self.value);
+            }
+            _ => {
+                self.value = imbalance;
+            }
+        }
+        self.last_ts = Some(timestamp);
+        self.value
+    }
+
+    /// Returns the current value, or [`f64::NAN`] if there has been no observation.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BookPressure, DepthAnalytics};
+    use crate::depth::{HashMapMarketDepth, L2MarketDepth};
+
+    #[test]
+    fn test_depth_analytics() {
+        let mut depth = HashMapMarketDepth::new(1.0, 1.0);
+        assert!(depth.microprice().is_nan());
+        assert!(depth.imbalance(3).is_nan());
+        assert_eq!(depth.bid_qty_within_bps(100.0), 0.0);
+
+        depth.update_bid_depth(100.0, 3.0, 0);
+        depth.update_bid_depth(98.0, 2.0, 0);
+        depth.update_bid_depth(90.0, 10.0, 0);
+        depth.update_ask_depth(102.0, 1.0, 0);
+        depth.update_ask_depth(103.0, 4.0, 0);
+
+        assert_eq!(depth.mid_price(), 101.0);
+        // (100 * 1 + 102 * 3) / (3 + 1)
+        assert_eq!(depth.microprice(), 101.5);
+        assert_eq!(depth.bid_qty_over(3), 5.0);
+        assert_eq!(depth.ask_qty_over(3), 5.0);
+        assert_eq!(depth.imbalance(1), 0.5);
+        assert_eq!(depth.imbalance(3), 0.0);
+        // 101 * (1 - 0.03) = 97.97
+        assert_eq!(depth.bid_qty_within_bps(300.0), 5.0);
+        // 101 * (1 + 0.015) = 102.515
+        assert_eq!(depth.ask_qty_within_bps(150.0), 1.0);
+    }
+
+    #[test]
+    fn test_book_pressure() {
+        let mut depth = HashMapMarketDepth::new(1.0, 1.0);
+        let mut pressure = BookPressure::new(1, 100);
+        assert!(pressure.update(&depth, 0).is_nan());
+
+        depth.update_bid_depth(100.0, 3.0, 0);
+        depth.update_ask_depth(101.0, 1.0, 0);
+        assert_eq!(pressure.update(&depth, 10), 0.5);
+
+        // Half of the gap is closed after the half-life.
+        depth.update_bid_depth(100.0, 1.0, 110);
+        assert_eq!(pressure.update(&depth, 110), 0.25);
+        assert_eq!(pressure.value(), 0.25);
+    }
+}
@@ -0,0 +1,289 @@
+use super::{ApplySnapshot, L1MarketDepth, MarketDepth, INVALID_MAX, INVALID_MIN};
+use crate::{
+    backtest::reader::Data,
+    types::{Event, BUY_EVENT, DEPTH_SNAPSHOT_EVENT, EXCH_EVENT, LOCAL_EVENT, SELL_EVENT},
+};
+
+/// A minimal market depth that holds only the best bid and ask, for Level-1 feed data consisting
+/// of the best bid and offer updates, [`DEPTH_BBO_EVENT`](crate::types::DEPTH_BBO_EVENT)s, and
+/// trades.
+///
+/// Each update replaces the best of its side, and an update with zero quantity empties the side.
+/// If an update crosses the opposite best, the opposite best is stale and is emptied until its
+/// next update. The quantity is known only at the best, and is zero at any other price.
+pub struct BBOMarketDepth {
+    tick_size: f64,
+    lot_size: f64,
+    best_bid_tick: i64,
+    best_ask_tick: i64,
+    best_bid_qty: f64,
+    best_ask_qty: f64,
+}
+
+impl BBOMarketDepth {
+    /// Constructs an instance of `BBOMarketDepth`.
+    pub fn new(tick_size: f64, lot_size: f64) -> Self {
+        Self {
+            tick_size,
+            lot_size,
+            best_bid_tick: INVALID_MIN,
+            best_ask_tick: INVALID_MAX,
+            best_bid_qty: 0.0,
+            best_ask_qty: 0.0,
+        }
+    }
+
+    #[inline(always)]
+    fn is_empty_qty(&self, qty: f64) -> bool {
+        (qty / self.lot_size).round() as i64 <= 0
+    }
+}
+
+impl L1MarketDepth for BBOMarketDepth {
+    fn update_best_bid(
+        &mut self,
+        px: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        let price_tick = (px / self.tick_size).round() as i64;
+        let prev_best_bid_tick = self.best_bid_tick;
+        let prev_qty = if price_tick == self.best_bid_tick {
+            self.best_bid_qty
+        } else {
+            0.0
+        };
+        if self.is_empty_qty(qty) {
+            self.best_bid_tick = INVALID_MIN;
+            self.best_bid_qty = 0.0;
+        } else {
+            self.best_bid_tick = price_tick;
+            self.best_bid_qty = qty;
+            if self.best_bid_tick >= self.best_ask_tick {
+                self.best_ask_tick = INVALID_MAX;
+                self.best_ask_qty = 0.0;
+            }
+        }
+        (
+            price_tick,
+            prev_best_bid_tick,
+            self.best_bid_tick,
+            prev_qty,
+            qty,
+            timestamp,
+        )
+    }
+
+    fn update_best_ask(
+        &mut self,
+        px: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        let price_tick = (px / self.tick_size).round() as i64;
+        let prev_best_ask_tick = self.best_ask_tick;
+        let prev_qty = if price_tick == self.best_ask_tick {
+            self.best_ask_qty
+        } else {
+            0.0
+        };
+        if self.is_empty_qty(qty) {
+            self.best_ask_tick = INVALID_MAX;
+            self.best_ask_qty = 0.0;
+        } else {
+            self.best_ask_tick = price_tick;
+            self.best_ask_qty = qty;
+            if self.best_bid_tick >= self.best_ask_tick {
+                self.best_bid_tick = INVALID_MIN;
+                self.best_bid_qty = 0.0;
+            }
+        }
+        (
+            price_tick,
+            prev_best_ask_tick,
+            self.best_ask_tick,
+            prev_qty,
+            qty,
+            timestamp,
+        )
+    }
+}
+
+impl MarketDepth for BBOMarketDepth {
+    #[inline(always)]
+    fn best_bid(&self) -> f64 {
+        if self.best_bid_tick == INVALID_MIN {
+            f64::NAN
+        } else {
+            self.best_bid_tick as f64 * self.tick_size
+        }
+    }
+
+    #[inline(always)]
+    fn best_ask(&self) -> f64 {
+        if self.best_ask_tick == INVALID_MAX {
+            f64::NAN
+        } else {
+            self.best_ask_tick as f64 * self.tick_size
+        }
+    }
+
+    #[inline(always)]
+    fn best_bid_tick(&self) -> i64 {
+        self.best_bid_tick
+    }
+
+    #[inline(always)]
+    fn best_ask_tick(&self) -> i64 {
+        self.best_ask_tick
+    }
+
+    #[inline(always)]
+    fn tick_size(&self) -> f64 {
+        self.tick_size
+    }
+
+    #[inline(always)]
+    fn lot_size(&self) -> f64 {
+        self.lot_size
+    }
+
+    #[inline(always)]
+    fn bid_qty_at_tick(&self, price_tick: i64) -> f64 {
+        if price_tick == self.best_bid_tick {
+            self.best_bid_qty
+        } else {
+            0.0
+        }
+    }
+
+    #[inline(always)]
+    fn ask_qty_at_tick(&self, price_tick: i64) -> f64 {
+        if price_tick == self.best_ask_tick {
+            self.best_ask_qty
+        } else {
+            0.0
+        }
+    }
+
+    fn bid_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        (self.best_bid_tick != INVALID_MIN)
+            .then_some((self.best_bid_tick, self.best_bid_qty))
+            .into_iter()
+    }
+
+    fn ask_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        (self.best_ask_tick != INVALID_MAX)
+            .then_some((self.best_ask_tick, self.best_ask_qty))
+            .into_iter()
+    }
+}
+
+impl ApplySnapshot<Event> for BBOMarketDepth {
+    fn apply_snapshot(&mut self, data: &Data<Event>) {
+        self.best_bid_tick = INVALID_MIN;
+        self.best_ask_tick = INVALID_MAX;
+        self.best_bid_qty = 0.0;
+        self.best_ask_qty = 0.0;
+        for row_num in 0..data.len() {
+            let ev = &data[row_num];
+            let price_tick = (ev.px / self.tick_size).round() as i64;
+            if self.is_empty_qty(ev.qty) {
+                continue;
+            }
+            // Only the best of each side is kept.
+            if ev.ev & BUY_EVENT == BUY_EVENT && price_tick > self.best_bid_tick {
+                self.best_bid_tick = price_tick;
+                self.best_bid_qty = ev.qty;
+            } else if ev.ev & SELL_EVENT == SELL_EVENT && price_tick < self.best_ask_tick {
+                self.best_ask_tick = price_tick;
+                self.best_ask_qty = ev.qty;
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Vec<Event> {
+        let bid = (self.best_bid_tick != INVALID_MIN).then_some((
+            BUY_EVENT,
+            self.best_bid_tick,
+            self.best_bid_qty,
+        ));
+        let ask = (self.best_ask_tick != INVALID_MAX).then_some((
+            SELL_EVENT,
+            self.best_ask_tick,
+            self.best_ask_qty,
+        ));
+        bid.into_iter()
+            .chain(ask)
+            .map(|(side_ev, px_tick, qty)| Event {
+                ev: EXCH_EVENT | LOCAL_EVENT | side_ev | DEPTH_SNAPSHOT_EVENT,
+                exch_ts: 0,
+                local_ts: 0,
+                px: px_tick as f64 * self.tick_size,
+                qty,
+                order_id: 0,
+                ival: 0,
+                fval: 0.0,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backtest::reader::Data,
+        depth::{
+            ApplySnapshot,
+            BBOMarketDepth,
+            L1MarketDepth,
+            MarketDepth,
+            INVALID_MAX,
+            INVALID_MIN,
+        },
+    };
+
+    #[test]
+    fn test_bbo_market_depth() {
+        let mut depth = BBOMarketDepth::new(0.1, 1.0);
+        assert!(depth.best_bid().is_nan());
+
+        depth.update_best_bid(100.0, 5.0, 0);
+        depth.update_best_ask(100.2, 3.0, 0);
+        assert_eq!(depth.best_bid_tick(), 1000);
+        assert_eq!(depth.best_ask_tick(), 1002);
+        assert_eq!(depth.bid_qty_at_tick(1000), 5.0);
+        assert_eq!(depth.bid_qty_at_tick(999), 0.0);
+
+        let (price_tick, prev_best_tick, best_tick, prev_qty, qty, _) =
+            depth.update_best_bid(100.0, 2.0, 1);
+        assert_eq!(
+            (price_tick, prev_best_tick, best_tick, prev_qty, qty),
+            (1000, 1000, 1000, 5.0, 2.0)
+        );
+
+        // The bid crossing the ask makes the ask stale.
+        depth.update_best_bid(100.2, 1.0, 2);
+        assert_eq!(depth.best_bid_tick(), 1002);
+        assert_eq!(depth.best_ask_tick(), INVALID_MAX);
+
+        // A zero quantity empties the side.
+        depth.update_best_bid(100.2, 0.0, 3);
+        assert_eq!(depth.best_bid_tick(), INVALID_MIN);
+    }
+
+    #[test]
+    fn test_bbo_snapshot() {
+        let mut depth = BBOMarketDepth::new(0.1, 1.0);
+        depth.update_best_bid(100.0, 5.0, 0);
+        depth.update_best_ask(100.2, 3.0, 0);
+        let snapshot = depth.snapshot();
+        assert_eq!(snapshot.len(), 2);
+
+        let mut restored = BBOMarketDepth::new(0.1, 1.0);
+        restored.apply_snapshot(&Data::from_slice(&snapshot));
+        assert_eq!(restored.best_bid_tick(), 1000);
+        assert_eq!(restored.best_ask_tick(), 1002);
+        assert_eq!(restored.ask_qty_at_tick(1002), 3.0);
+    }
+}
@@ -12,14 +12,24 @@ use super::{
 use crate::{
     backtest::{reader::Data, BacktestError},
     prelude::{OrderId, Side},
-    types::{Event, BUY_EVENT, SELL_EVENT},
+    types::{Event, BUY_EVENT, DEPTH_SNAPSHOT_EVENT, EXCH_EVENT, LOCAL_EVENT, SELL_EVENT},
 };
 
-/// L2 Market depth implementation based on a B-Tree map.
+/// L2/L3 Market depth implementation based on a B-Tree map, which only stores the price levels that
+/// have quantity.
 ///
-/// If feed data is missing, it may result in the crossing of the best bid and ask, making it
-/// impossible to restore them to the most recent values through natural refreshing.
-/// Ensuring data integrity is imperative.
+/// Unlike [`HashMapMarketDepth`](super::HashMapMarketDepth), which scans the price levels tick by
+/// tick to find the next best price, and [`ROIVectorMarketDepth`](super::ROIVectorMarketDepth),
+/// which allocates every price level within the range of interest, the cost of maintaining the
+/// best prices and the memory usage don't depend on the price range. This suits instruments with
+/// very wide or sparse price ranges, such as options and illiquid assets.
+///
+/// Since the best bid and ask are the highest and lowest prices stored, a price level whose
+/// deletion is missing from the feed remains in the depth, which can cross the best bid and ask.
+/// By default, the crossed levels are kept, so ensuring data integrity is imperative. With
+/// [`remove_crossed_levels`](Self::remove_crossed_levels), a price level that crosses the opposite
+/// side removes the crossed levels of the opposite side as stale instead, which restores the market
+/// depth through natural refreshing.
 #[derive(Debug)]
 pub struct BTreeMarketDepth {
     pub tick_size: f64,
@@ -30,6 +40,7 @@ pub struct BTreeMarketDepth {
     pub best_bid_tick: i64,
     pub best_ask_tick: i64,
     pub orders: HashMap<OrderId, L3Order>,
+    pub remove_crossed_levels: bool,
 }
 
 impl BTreeMarketDepth {
@@ -44,6 +55,16 @@ impl BTreeMarketDepth {
             best_bid_tick: INVALID_MIN,
             best_ask_tick: INVALID_MAX,
             orders: Default::default(),
+            remove_crossed_levels: false,
+        }
+    }
+
+    /// Sets whether a price level that crosses the opposite side removes the crossed levels of the
+    /// opposite side as stale. The default is `false`.
+    pub fn remove_crossed_levels(self, remove_crossed_levels: bool) -> Self {
+        Self {
+            remove_crossed_levels,
+            ..self
         }
     }
 
@@ -69,13 +90,18 @@ impl L2MarketDepth for BTreeMarketDepth {
         timestamp: i64,
     ) -> (i64, i64, i64, f64, f64, i64) {
         let price_tick = (price / self.tick_size).round() as i64;
-        let prev_best_bid_tick = *self.bid_depth.keys().last().unwrap_or(&INVALID_MIN);
-        let prev_qty = *self.bid_depth.get(&prev_best_bid_tick).unwrap_or(&0.0);
+        let prev_best_bid_tick = self.best_bid_tick;
+        let prev_qty = self.bid_qty_at_tick(price_tick);
 
         if (qty / self.lot_size).round() as i64 == 0 {
             self.bid_depth.remove(&price_tick);
         } else {
-            *self.bid_depth.entry(price_tick).or_insert(qty) = qty;
+            self.bid_depth.insert(price_tick, qty);
+            if self.remove_crossed_levels && price_tick >= self.best_ask_tick {
+                // The ask levels crossed by the bid are stale.
+                self.ask_depth = self.ask_depth.split_off(&(price_tick + 1));
+                self.best_ask_tick = *self.ask_depth.keys().next().unwrap_or(&INVALID_MAX);
+            }
         }
         self.best_bid_tick = *self.bid_depth.keys().last().unwrap_or(&INVALID_MIN);
         (
@@ -95,13 +121,18 @@ impl L2MarketDepth for BTreeMarketDepth {
         timestamp: i64,
     ) -> (i64, i64, i64, f64, f64, i64) {
         let price_tick = (price / self.tick_size).round() as i64;
-        let prev_best_ask_tick = *self.bid_depth.keys().next().unwrap_or(&INVALID_MAX);
-        let prev_qty = *self.ask_depth.get(&prev_best_ask_tick).unwrap_or(&0.0);
+        let prev_best_ask_tick = self.best_ask_tick;
+        let prev_qty = self.ask_qty_at_tick(price_tick);
 
         if (qty / self.lot_size).round() as i64 == 0 {
             self.ask_depth.remove(&price_tick);
         } else {
-            *self.ask_depth.entry(price_tick).or_insert(qty) = qty;
+            self.ask_depth.insert(price_tick, qty);
+            if self.remove_crossed_levels && price_tick <= self.best_bid_tick {
+                // The bid levels crossed by the ask are stale.
+                self.bid_depth.split_off(&price_tick);
+                self.best_bid_tick = *self.bid_depth.keys().last().unwrap_or(&INVALID_MIN);
+            }
         }
         self.best_ask_tick = *self.ask_depth.keys().next().unwrap_or(&INVALID_MAX);
         (
@@ -117,29 +148,15 @@ impl L2MarketDepth for BTreeMarketDepth {
     fn clear_depth(&mut self, side: Side, clear_upto_price: f64) {
         let clear_upto = (clear_upto_price / self.tick_size).round() as i64;
         if side == Side::Buy {
-            let best_bid_tick = self.best_bid_tick();
-            if best_bid_tick != INVALID_MIN {
-                for t in clear_upto..(best_bid_tick + 1) {
-                    if self.bid_depth.contains_key(&t) {
-                        self.bid_depth.remove(&t);
-                    }
-                }
-            }
-            self.best_bid_tick = *self.bid_depth.keys().last().unwrap_or(&INVALID_MIN);
+            self.bid_depth.split_off(&clear_upto);
         } else if side == Side::Sell {
-            let best_ask_tick = self.best_ask_tick();
-            if best_ask_tick != INVALID_MAX {
-                for t in best_ask_tick..(clear_upto + 1) {
-                    if self.ask_depth.contains_key(&t) {
-                        self.ask_depth.remove(&t);
-                    }
-                }
-            }
-            self.best_ask_tick = *self.ask_depth.keys().next().unwrap_or(&INVALID_MAX);
+            self.ask_depth = self.ask_depth.split_off(&(clear_upto + 1));
         } else {
             self.bid_depth.clear();
             self.ask_depth.clear();
         }
+        self.best_bid_tick = *self.bid_depth.keys().last().unwrap_or(&INVALID_MIN);
+        self.best_ask_tick = *self.ask_depth.keys().next().unwrap_or(&INVALID_MAX);
     }
 }
 
@@ -191,6 +208,19 @@ impl MarketDepth for BTreeMarketDepth {
     fn ask_qty_at_tick(&self, price_tick: i64) -> f64 {
         *self.ask_depth.get(&price_tick).unwrap_or(&0.0)
     }
+
+    fn bid_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.bid_depth
+            .iter()
+            .rev()
+            .map(|(&price_tick, &qty)| (price_tick, qty))
+    }
+
+    fn ask_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.ask_depth
+            .iter()
+            .map(|(&price_tick, &qty)| (price_tick, qty))
+    }
 }
 
 impl ApplySnapshot<Event> for BTreeMarketDepth {
@@ -215,27 +245,33 @@ impl ApplySnapshot<Event> for BTreeMarketDepth {
     fn snapshot(&self) -> Vec<Event> {
         let mut events = Vec::new();
 
-        // for (&px_tick, &qty) in self.bid_depth.iter().rev() {
-        //     events.push(Event {
-        //         ev: EXCH_EVENT | LOCAL_EVENT | BUY | DEPTH_SNAPSHOT_EVENT,
-        //         // todo: it's not a problem now, but it would be better to have valid timestamps.
-        //         exch_ts: 0,
-        //         local_ts: 0,
-        //         px: px_tick as f64 * self.tick_size,
-        //         qty,
-        //     });
-        // }
-        //
-        // for (&px_tick, &qty) in self.ask_depth.iter() {
-        //     events.push(Event {
-        //         ev: EXCH_EVENT | LOCAL_EVENT | SELL | DEPTH_SNAPSHOT_EVENT,
-        //         // todo: it's not a problem now, but it would be better to have valid timestamps.
-        //         exch_ts: 0,
-        //         local_ts: 0,
-        //         px: px_tick as f64 * self.tick_size,
-        //         qty,
-        //     });
-        // }
+        for (&px_tick, &qty) in self.bid_depth.iter().rev() {
+            events.push(Event {
+                ev: EXCH_EVENT | LOCAL_EVENT | BUY_EVENT | DEPTH_SNAPSHOT_EVENT,
+                // todo: it's not a problem now, but it would be better to have valid timestamps.
+                exch_ts: 0,
+                local_ts: 0,
+                px: px_tick as f64 * self.tick_size,
+                qty,
+                order_id: 0,
+                ival: 0,
+                fval: 0.0,
+            });
+        }
+
+        for (&px_tick, &qty) in self.ask_depth.iter() {
+            events.push(Event {
+                ev: EXCH_EVENT | LOCAL_EVENT | SELL_EVENT | DEPTH_SNAPSHOT_EVENT,
+                // todo: it's not a problem now, but it would be better to have valid timestamps.
+                exch_ts: 0,
+                local_ts: 0,
+                px: px_tick as f64 * self.tick_size,
+                qty,
+                order_id: 0,
+                ival: 0,
+                fval: 0.0,
+            });
+        }
 
         events
     }
@@ -305,7 +341,7 @@ impl L3MarketDepth for BTreeMarketDepth {
             if (*depth_qty / self.lot_size).round() as i64 == 0 {
                 self.bid_depth.remove(&order.price_tick).unwrap();
                 if order.price_tick == self.best_bid_tick {
-                    self.best_bid_tick = *self.bid_depth.keys().next().unwrap_or(&INVALID_MIN);
+                    self.best_bid_tick = *self.bid_depth.keys().last().unwrap_or(&INVALID_MIN);
                 }
             }
             Ok((Side::Buy, prev_best_tick, self.best_bid_tick))
@@ -399,11 +435,15 @@ impl L3MarketDepth for BTreeMarketDepth {
     fn clear_depth(&mut self, side: Side) {
         if side == Side::Buy {
             self.bid_depth.clear();
+            self.best_bid_tick = INVALID_MIN;
         } else if side == Side::Sell {
             self.ask_depth.clear();
+            self.best_ask_tick = INVALID_MAX;
         } else {
             self.bid_depth.clear();
             self.ask_depth.clear();
+            self.best_bid_tick = INVALID_MIN;
+            self.best_ask_tick = INVALID_MAX;
         }
     }
 
@@ -415,8 +455,16 @@ impl L3MarketDepth for BTreeMarketDepth {
 #[cfg(test)]
 mod tests {
     use crate::{
-        depth::{BTreeMarketDepth, L3MarketDepth, MarketDepth, INVALID_MAX, INVALID_MIN},
-        types::Side,
+        depth::{
+            ApplySnapshot,
+            BTreeMarketDepth,
+            L2MarketDepth,
+            L3MarketDepth,
+            MarketDepth,
+            INVALID_MAX,
+            INVALID_MIN,
+        },
+        types::{Side, BUY_EVENT},
     };
 
     macro_rules! assert_eq_qty {
@@ -428,6 +476,55 @@ mod tests {
         }};
     }
 
+    #[test]
+    fn test_l2_update_sparse_depth() {
+        let lot_size = 0.001;
+        let mut depth = BTreeMarketDepth::new(0.01, lot_size).remove_crossed_levels(true);
+
+        let (price_tick, prev_best, best, _, _, _) = depth.update_bid_depth(0.05, 1.0, 0);
+        assert_eq!((price_tick, prev_best, best), (5, INVALID_MIN, 5));
+        depth.update_bid_depth(95.0, 2.0, 0);
+        depth.update_ask_depth(100_000.0, 3.0, 0);
+        assert_eq!(depth.best_bid_tick(), 9500);
+        assert_eq!(depth.best_ask_tick(), 10_000_000);
+
+        let (_, prev_best, best, prev_qty, qty, _) = depth.update_bid_depth(95.0, 0.0, 0);
+        assert_eq!((prev_best, best), (9500, 5));
+        assert_eq_qty!(prev_qty, 2.0, lot_size);
+        assert_eq_qty!(qty, 0.0, lot_size);
+
+        // The ask level crossed by the new best bid is removed as stale.
+        depth.update_ask_depth(200.0, 1.0, 0);
+        assert_eq!(depth.best_ask_tick(), 20000);
+        depth.update_bid_depth(300.0, 1.0, 0);
+        assert_eq!(depth.best_bid_tick(), 30000);
+        assert_eq!(depth.best_ask_tick(), 10_000_000);
+        assert_eq_qty!(depth.ask_qty_at_tick(20000), 0.0, lot_size);
+
+        let snapshot = depth.snapshot();
+        assert_eq!(snapshot.len(), 3);
+        assert!(snapshot[0].ev & BUY_EVENT == BUY_EVENT);
+        assert_eq!(snapshot[0].px, 300.0);
+
+        L2MarketDepth::clear_depth(&mut depth, Side::Buy, 1.0);
+        assert_eq!(depth.best_bid_tick(), 5);
+        L2MarketDepth::clear_depth(&mut depth, Side::None, 0.0);
+        assert_eq!(depth.best_bid_tick(), INVALID_MIN);
+        assert_eq!(depth.best_ask_tick(), INVALID_MAX);
+    }
+
+    #[test]
+    fn test_l2_keep_crossed_levels() {
+        let lot_size = 0.001;
+        let mut depth = BTreeMarketDepth::new(0.01, lot_size);
+
+        depth.update_ask_depth(200.0, 1.0, 0);
+        depth.update_bid_depth(300.0, 1.0, 0);
+        assert_eq!(depth.best_bid_tick(), 30000);
+        assert_eq!(depth.best_ask_tick(), 20000);
+        assert_eq_qty!(depth.ask_qty_at_tick(20000), 1.0, lot_size);
+    }
+
     #[test]
     fn test_l3_add_delete_buy_order() {
         let lot_size = 0.001;
@@ -639,4 +736,25 @@ mod tests {
         assert_eq_qty!(depth.ask_qty_at_tick(4981), 0.0, lot_size);
         assert_eq_qty!(depth.ask_qty_at_tick(5002), 0.002, lot_size);
     }
+
+    #[test]
+    fn test_cumulative_depth() {
+        let mut depth = BTreeMarketDepth::new(0.5, 0.1);
+        depth.update_bid_depth(100.0, 1.0, 0);
+        depth.update_bid_depth(99.0, 2.0, 0);
+        depth.update_bid_depth(97.5, 3.0, 0);
+        depth.update_ask_depth(100.5, 0.5, 0);
+        depth.update_ask_depth(101.5, 0.7, 0);
+
+        assert_eq!(depth.qty_up_to_price(Side::Buy, 99.0), 3.0);
+        assert_eq!(depth.qty_up_to_price(Side::Buy, 90.0), 6.0);
+        assert_eq!(depth.qty_up_to_price(Side::Buy, 100.5), 0.0);
+        assert_eq!(depth.qty_up_to_price(Side::Sell, 101.0), 0.5);
+
+        assert_eq!(depth.price_for_qty(Side::Buy, 1.0), 100.0);
+        assert_eq!(depth.price_for_qty(Side::Buy, 3.5), 97.5);
+        assert!(depth.price_for_qty(Side::Buy, 6.5).is_nan());
+        assert_eq!(depth.price_for_qty(Side::Sell, 1.2), 101.5);
+        assert!(depth.price_for_qty(Side::None, 1.0).is_nan());
+    }
 }
@@ -0,0 +1,126 @@
+use super::MarketDepth;
+
+/// Computes the CRC-32 (IEEE 802.3) of the bytes, which the venues use for the order book
+/// checksum.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// Formats the value rounded to the decimals, without the trailing zeros.
+fn format_trimmed(value: f64, decimals: usize) -> String {
+    let s = format!("{value:.decimals$}");
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        s
+    }
+}
+
+// Formats the value rounded to the decimals, without the decimal point and the leading zeros.
+fn format_digits(value: f64, decimals: usize) -> String {
+    let s = format!("{value:.decimals$}").replace('.', "");
+    let s = s.trim_start_matches('0');
+    if s.is_empty() {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Provides the order book checksums published by the venues, for every [`MarketDepth`], so that
+/// a locally maintained market depth can be verified against the venue's book.
+///
+/// The checksums are computed on the venue's textual representation of the prices and
+/// quantities, so `price_decimals` and `qty_decimals` should be the precision the venue uses for
+/// the instrument.
+///
+/// Bybit isn't covered, since its V5 order book stream carries only the update ID and the cross
+/// sequence, not a checksum, so there is nothing to verify the book against. The OKX connector
+/// verifies [`okx_checksum`](Self::okx_checksum) when `OkxBuilder::verify_checksum` is set, while
+/// there is no Kraken connector yet.
+pub trait DepthChecksum: MarketDepth {
+    /// Returns the OKX order book checksum, which is the signed CRC-32 of the best 25 levels of
+    /// each side, alternating the bid and the ask levels, as `bid_px:bid_qty:ask_px:ask_qty:...`.
+    /// The numbers are written without the trailing zeros.
+    fn okx_checksum(&self, price_decimals: usize, qty_decimals: usize) -> i32 {
+        let tick_size = self.tick_size();
+        let mut bids = self.bid_levels().take(25);
+        let mut asks = self.ask_levels().take(25);
+        let mut fields = Vec::with_capacity(100);
+        loop {
+            let bid = bids.next();
+            let ask = asks.next();
+            if bid.is_none() && ask.is_none() {
+                break;
+            }
+            for (price_tick, qty) in [bid, ask].into_iter().flatten() {
+                fields.push(format_trimmed(
+                    price_tick as f64 * tick_size,
+                    price_decimals,
+                ));
+                fields.push(format_trimmed(qty, qty_decimals));
+            }
+        }
+        crc32(fields.join(":").as_bytes()) as i32
+    }
+
+    /// Returns the Kraken order book checksum, which is the CRC-32 of the best 10 ask levels from
+    /// the best ask upward, followed by the best 10 bid levels from the best bid downward. Each
+    /// price and quantity is written without the decimal point and the leading zeros.
+    fn kraken_checksum(&self, price_decimals: usize, qty_decimals: usize) -> u32 {
+        let tick_size = self.tick_size();
+        let mut s = String::new();
+        for (price_tick, qty) in self.ask_levels().take(10).chain(self.bid_levels().take(10)) {
+            s.push_str(&format_digits(
+                price_tick as f64 * tick_size,
+                price_decimals,
+            ));
+            s.push_str(&format_digits(qty, qty_decimals));
+        }
+        crc32(s.as_bytes())
+    }
+}
+
+impl<MD> DepthChecksum for MD where MD: MarketDepth + ?Sized {}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32, DepthChecksum};
+    use crate::depth::{HashMapMarketDepth, L2MarketDepth};
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_checksum() {
+        let mut depth = HashMapMarketDepth::new(0.1, 0.001);
+        depth.update_bid_depth(3366.1, 7.0, 0);
+        depth.update_bid_depth(3366.0, 6.0, 0);
+        depth.update_bid_depth(3365.0, 0.5, 0);
+        depth.update_ask_depth(3366.8, 9.0, 0);
+        depth.update_ask_depth(3368.0, 8.0, 0);
+
+        assert_eq!(
+            depth.okx_checksum(1, 3),
+            crc32(b"3366.1:7:3366.8:9:3366:6:3368:8:3365:0.5") as i32
+        );
+        assert_eq!(
+            depth.kraken_checksum(1, 3),
+            crc32(b"33668900033680800033661700033660600033650500")
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+
+use super::{ApplySnapshot, HashMapMarketDepth, L2MarketDepth, MarketDepth, OrderCountDepth};
+use crate::{
+    backtest::reader::Data,
+    types::{Event, Side, BUY_EVENT, SELL_EVENT},
+};
+
+/// L2 market depth implementation based on [`HashMapMarketDepth`] that also stores the number of
+/// orders at each price level, for feeds that publish order counts per level, such as CME MBP.
+///
+/// The order count is carried in the `ival` of the depth event. A depth event without the count,
+/// where `ival` is `0`, makes the count at the price unknown, in which case `0` is returned.
+pub struct CountMarketDepth {
+    depth: HashMapMarketDepth,
+    bid_count: HashMap<i64, i64>,
+    ask_count: HashMap<i64, i64>,
+}
+
+impl CountMarketDepth {
+    /// Constructs an instance of `CountMarketDepth`.
+    pub fn new(tick_size: f64, lot_size: f64) -> Self {
+        Self {
+            depth: HashMapMarketDepth::new(tick_size, lot_size),
+            bid_count: HashMap::new(),
+            ask_count: HashMap::new(),
+        }
+    }
+}
+
+#[inline(always)]
+fn set_count(counts: &mut HashMap<i64, i64>, price_tick: i64, qty: f64, count: i64) {
+    if qty > 0.0 && count > 0 {
+        counts.insert(price_tick, count);
+    } else {
+        counts.remove(&price_tick);
+    }
+}
+
+impl L2MarketDepth for CountMarketDepth {
+    fn update_bid_depth(
+        &mut self,
+        price: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        self.update_bid_depth_with_count(price, qty, 0, timestamp)
+    }
+
+    fn update_ask_depth(
+        &mut self,
+        price: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        self.update_ask_depth_with_count(price, qty, 0, timestamp)
+    }
+
+    fn update_bid_depth_with_count(
+        &mut self,
+        price: f64,
+        qty: f64,
+        count: i64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        let result = self.depth.update_bid_depth(price, qty, timestamp);
+        set_count(
+            &mut self.bid_count,
+            result.0,
+            self.depth.bid_qty_at_tick(result.0),
+            count,
+        );
+        result
+    }
+
+    fn update_ask_depth_with_count(
+        &mut self,
+        price: f64,
+        qty: f64,
+        count: i64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        let result = self.depth.update_ask_depth(price, qty, timestamp);
+        set_count(
+            &mut self.ask_count,
+            result.0,
+            self.depth.ask_qty_at_tick(result.0),
+            count,
+        );
+        result
+    }
+
+    fn clear_depth(&mut self, side: Side, clear_upto_price: f64) {
+        self.depth.clear_depth(side, clear_upto_price);
+        let depth = &self.depth;
+        self.bid_count
+            .retain(|&price_tick, _| depth.bid_qty_at_tick(price_tick) > 0.0);
+        self.ask_count
+            .retain(|&price_tick, _| depth.ask_qty_at_tick(price_tick) > 0.0);
+    }
+}
+
+impl MarketDepth for CountMarketDepth {
+    #[inline(always)]
+    fn best_bid(&self) -> f64 {
+        self.depth.best_bid()
+    }
+
+    #[inline(always)]
+    fn best_ask(&self) -> f64 {
+        self.depth.best_ask()
+    }
+
+    #[inline(always)]
+    fn best_bid_tick(&self) -> i64 {
+        self.depth.best_bid_tick()
+    }
+
+    #[inline(always)]
+    fn best_ask_tick(&self) -> i64 {
+        self.depth.best_ask_tick()
+    }
+
+    #[inline(always)]
+    fn tick_size(&self) -> f64 {
+        self.depth.tick_size()
+    }
+
+    #[inline(always)]
+    fn lot_size(&self) -> f64 {
+        self.depth.lot_size()
+    }
+
+    #[inline(always)]
+    fn bid_qty_at_tick(&self, price_tick: i64) -> f64 {
+        self.depth.bid_qty_at_tick(price_tick)
+    }
+
+    #[inline(always)]
+    fn ask_qty_at_tick(&self, price_tick: i64) -> f64 {
+        self.depth.ask_qty_at_tick(price_tick)
+    }
+
+    fn bid_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.depth.bid_levels()
+    }
+
+    fn ask_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.depth.ask_levels()
+    }
+}
+
+impl OrderCountDepth for CountMarketDepth {
+    #[inline(always)]
+    fn bid_count_at_tick(&self, price_tick: i64) -> i64 {
+        *self.bid_count.get(&price_tick).unwrap_or(&0)
+    }
+
+    #[inline(always)]
+    fn ask_count_at_tick(&self, price_tick: i64) -> i64 {
+        *self.ask_count.get(&price_tick).unwrap_or(&0)
+    }
+}
+
+impl ApplySnapshot<Event> for CountMarketDepth {
+    fn apply_snapshot(&mut self, data: &Data<Event>) {
+        self.depth.apply_snapshot(data);
+        self.bid_count.clear();
+        self.ask_count.clear();
+        let tick_size = self.depth.tick_size();
+        for row_num in 0..data.len() {
+            let price_tick = (data[row_num].px / tick_size).round() as i64;
+            if data[row_num].ev & BUY_EVENT == BUY_EVENT {
+                set_count(
+                    &mut self.bid_count,
+                    price_tick,
+                    data[row_num].qty,
+                    data[row_num].ival,
+                );
+            } else if data[row_num].ev & SELL_EVENT == SELL_EVENT {
+                set_count(
+                    &mut self.ask_count,
+                    price_tick,
+                    data[row_num].qty,
+                    data[row_num].ival,
+                );
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Vec<Event> {
+        let tick_size = self.depth.tick_size();
+        let mut events = self.depth.snapshot();
+        for ev in events.iter_mut() {
+            let price_tick = (ev.px / tick_size).round() as i64;
+            if ev.ev & BUY_EVENT == BUY_EVENT {
+                ev.ival = self.bid_count_at_tick(price_tick);
+            } else if ev.ev & SELL_EVENT == SELL_EVENT {
+                ev.ival = self.ask_count_at_tick(price_tick);
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backtest::reader::Data,
+        depth::{
+            ApplySnapshot,
+            CountMarketDepth,
+            L2MarketDepth,
+            MarketDepth,
+            OrderCountDepth,
+            INVALID_MIN,
+        },
+        types::Side,
+    };
+
+    #[test]
+    fn test_count_market_depth() {
+        let mut depth = CountMarketDepth::new(0.1, 1.0);
+
+        depth.update_bid_depth_with_count(100.0, 10.0, 5, 0);
+        depth.update_bid_depth_with_count(99.9, 4.0, 2, 0);
+        depth.update_ask_depth_with_count(100.1, 3.0, 1, 0);
+        assert_eq!(depth.best_bid_tick(), 1000);
+        assert_eq!(depth.bid_count_at_tick(1000), 5);
+        assert_eq!(depth.bid_count_at_tick(999), 2);
+        assert_eq!(depth.ask_count_at_tick(1001), 1);
+        assert_eq!(
+            depth.bid_levels_with_count().collect::<Vec<_>>(),
+            vec![(1000, 10.0, 5), (999, 4.0, 2)]
+        );
+
+        // A depth update without the count makes the count unknown.
+        depth.update_bid_depth(99.9, 5.0, 0);
+        assert_eq!(depth.bid_count_at_tick(999), 0);
+
+        // The count is removed along with the level.
+        let (_, prev_best, best, prev_qty, new_qty, _) =
+            depth.update_bid_depth_with_count(100.0, 0.0, 0, 0);
+        assert_eq!((prev_best, best), (1000, 999));
+        assert_eq!((prev_qty, new_qty), (10.0, 0.0));
+        assert_eq!(depth.bid_count_at_tick(1000), 0);
+
+        depth.update_bid_depth_with_count(100.0, 8.0, 3, 0);
+        let snapshot = depth.snapshot();
+        assert_eq!(snapshot[0].ival, 3);
+
+        depth.clear_depth(Side::Buy, 99.9);
+        assert_eq!(depth.best_bid_tick(), INVALID_MIN);
+        assert_eq!(depth.bid_count_at_tick(1000), 0);
+        assert_eq!(depth.ask_count_at_tick(1001), 1);
+
+        let mut restored = CountMarketDepth::new(0.1, 1.0);
+        restored.apply_snapshot(&Data::from_slice(&snapshot));
+        assert_eq!(restored.bid_count_at_tick(1000), 3);
+        assert_eq!(restored.ask_count_at_tick(1001), 1);
+    }
+}
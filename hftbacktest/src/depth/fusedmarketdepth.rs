@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+
+use super::{MarketDepth, INVALID_MAX, INVALID_MIN};
+use crate::types::Side;
+
+/// A change of the market depth of an asset, which is recorded by the local to maintain
+/// [`FusedMarketDepth`] and is reported by
+/// [`Backtest::take_depth_changes`](crate::backtest::Backtest::take_depth_changes).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DepthChange {
+    /// The quantity at the price has been updated.
+    Level { side: Side, price: f64, qty: f64 },
+    /// The price levels on the side up to the price have been cleared, following
+    /// [`L2MarketDepth::clear_depth`](super::L2MarketDepth::clear_depth). If the side is
+    /// [`Side::None`], both sides have been cleared.
+    Clear { side: Side, price: f64 },
+}
+
+/// Consolidated market depth that aggregates the market depths of several assets, such as the same
+/// instrument listed on multiple venues, into a single price-level view, keeping the quantity of
+/// each venue at each price.
+///
+/// The venues are the assets given in order, and their prices are expressed in the tick size of the
+/// fused depth, which should be the finest among the venues. [`MarketDepth`] provides the
+/// aggregated view, and [`bid_qty_by_venue`](Self::bid_qty_by_venue) and
+/// [`ask_qty_by_venue`](Self::ask_qty_by_venue) provide the attribution to the venues.
+///
+/// [`Backtest`](crate::backtest::Backtest) maintains the fused depth incrementally as the local
+/// processes the depth feed of each venue, and it is accessible by
+/// [`Backtest::fused_depth`](crate::backtest::Backtest::fused_depth). Only the changes made by the
+/// feed are reflected, so an initial snapshot should be included in the data rather than applied to
+/// the market depth in the depth builder.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::depth::FusedMarketDepth;
+///
+/// // Consolidates the books of assets 0 and 2.
+/// let fused = FusedMarketDepth::new(0.01, 0.001, vec![0, 2]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FusedMarketDepth {
+    tick_size: f64,
+    lot_size: f64,
+    venues: Vec<usize>,
+    // The quantity of each venue at the price in ticks.
+    bid_depth: BTreeMap<i64, Vec<f64>>,
+    ask_depth: BTreeMap<i64, Vec<f64>>,
+}
+
+impl FusedMarketDepth {
+    /// Constructs an instance of `FusedMarketDepth` that consolidates the market depths of the
+    /// assets, the `venues`.
+    pub fn new(tick_size: f64, lot_size: f64, venues: Vec<usize>) -> Self {
+        Self {
+            tick_size,
+            lot_size,
+            venues,
+            bid_depth: Default::default(),
+            ask_depth: Default::default(),
+        }
+    }
+
+    /// Returns the asset numbers of the venues.
+    pub fn venues(&self) -> &[usize] {
+        &self.venues
+    }
+
+    /// Applies the change of the market depth of the asset. The changes of an asset that is not a
+    /// venue are ignored.
+    pub fn apply(&mut self, asset_no: usize, change: &DepthChange) {
+        let Some(venue) = self.venues.iter().position(|&v| v == asset_no) else {
+            return;
+        };
+        match *change {
+            DepthChange::Level { side, price, qty } => {
+                let price_tick = (price / self.tick_size).round() as i64;
+                let num_venues = self.venues.len();
+                let depth = if side == Side::Buy {
+                    &mut self.bid_depth
+                } else {
+                    &mut self.ask_depth
+                };
+                let level = depth
+                    .entry(price_tick)
+                    .or_insert_with(|| vec![0.0; num_venues]);
+                level[venue] = qty;
+                let lot_size = self.lot_size;
+                if level.iter().all(|qty| (qty / lot_size).round() as i64 == 0) {
+                    depth.remove(&price_tick);
+                }
+            }
+            DepthChange::Clear { side, price } => {
+                let clear_upto = (price / self.tick_size).round() as i64;
+                if side == Side::Buy || side == Side::None {
+                    Self::clear_venue(&mut self.bid_depth, venue, self.lot_size, |t| {
+                        side == Side::None || t >= clear_upto
+                    });
+                }
+                if side == Side::Sell || side == Side::None {
+                    Self::clear_venue(&mut self.ask_depth, venue, self.lot_size, |t| {
+                        side == Side::None || t <= clear_upto
+                    });
+                }
+            }
+        }
+    }
+
+    fn clear_venue<F>(depth: &mut BTreeMap<i64, Vec<f64>>, venue: usize, lot_size: f64, clears: F)
+    where
+        F: Fn(i64) -> bool,
+    {
+        depth.retain(|&price_tick, level| {
+            if clears(price_tick) {
+                level[venue] = 0.0;
+            }
+            level.iter().any(|qty| (qty / lot_size).round() as i64 != 0)
+        });
+    }
+
+    /// Returns the quantity of each venue, in the order of [`venues`](Self::venues), at the bid
+    /// market depth for a given price in ticks, or `None` if no venue has quantity at the price.
+    pub fn bid_qty_by_venue(&self, price_tick: i64) -> Option<&[f64]> {
+        self.bid_depth
+            .get(&price_tick)
+            .map(|level| level.as_slice())
+    }
+
+    /// Returns the quantity of each venue, in the order of [`venues`](Self::venues), at the ask
+    /// market depth for a given price in ticks, or `None` if no venue has quantity at the price.
+    pub fn ask_qty_by_venue(&self, price_tick: i64) -> Option<&[f64]> {
+        self.ask_depth
+            .get(&price_tick)
+            .map(|level| level.as_slice())
+    }
+}
+
+impl MarketDepth for FusedMarketDepth {
+    #[inline(always)]
+    fn best_bid(&self) -> f64 {
+        let best_bid_tick = self.best_bid_tick();
+        if best_bid_tick == INVALID_MIN {
+            f64::NAN
+        } else {
+            best_bid_tick as f64 * self.tick_size
+        }
+    }
+
+    #[inline(always)]
+    fn best_ask(&self) -> f64 {
+        let best_ask_tick = self.best_ask_tick();
+        if best_ask_tick == INVALID_MAX {
+            f64::NAN
+        } else {
+            best_ask_tick as f64 * self.tick_size
+        }
+    }
+
+    #[inline(always)]
+    fn best_bid_tick(&self) -> i64 {
+        *self.bid_depth.keys().last().unwrap_or(&INVALID_MIN)
+    }
+
+    #[inline(always)]
+    fn best_ask_tick(&self) -> i64 {
+        *self.ask_depth.keys().next().unwrap_or(&INVALID_MAX)
+    }
+
+    #[inline(always)]
+    fn tick_size(&self) -> f64 {
+        self.tick_size
+    }
+
+    #[inline(always)]
+    fn lot_size(&self) -> f64 {
+        self.lot_size
+    }
+
+    #[inline(always)]
+    fn bid_qty_at_tick(&self, price_tick: i64) -> f64 {
+        self.bid_depth
+            .get(&price_tick)
+            .map_or(0.0, |level| level.iter().sum())
+    }
+
+    #[inline(always)]
+    fn ask_qty_at_tick(&self, price_tick: i64) -> f64 {
+        self.ask_depth
+            .get(&price_tick)
+            .map_or(0.0, |level| level.iter().sum())
+    }
+
+    fn bid_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.bid_depth
+            .iter()
+            .rev()
+            .map(|(&price_tick, level)| (price_tick, level.iter().sum::<f64>()))
+    }
+
+    fn ask_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.ask_depth
+            .iter()
+            .map(|(&price_tick, level)| (price_tick, level.iter().sum::<f64>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DepthChange, FusedMarketDepth};
+    use crate::{
+        backtest::{
+            proc::test_utils::{asset_builder, event},
+            reader::Data,
+            Backtest,
+        },
+        depth::MarketDepth,
+        types::{Bot, Event, Side, BUY_EVENT, DEPTH_EVENT, SELL_EVENT},
+    };
+
+    #[test]
+    fn test_apply() {
+        let mut fused = FusedMarketDepth::new(0.5, 1.0, vec![3, 1]);
+        let level = |side, price, qty| DepthChange::Level { side, price, qty };
+        fused.apply(3, &level(Side::Buy, 100.0, 2.0));
+        fused.apply(1, &level(Side::Buy, 100.0, 3.0));
+        fused.apply(1, &level(Side::Buy, 100.5, 1.0));
+        fused.apply(0, &level(Side::Buy, 101.0, 1.0));
+        fused.apply(3, &level(Side::Sell, 101.0, 4.0));
+        assert_eq!(fused.best_bid_tick(), 201);
+        assert_eq!(fused.best_ask(), 101.0);
+        assert_eq!(fused.bid_qty_at_tick(200), 5.0);
+        assert_eq!(fused.bid_qty_by_venue(200), Some(&[2.0, 3.0][..]));
+
+        fused.apply(1, &level(Side::Buy, 100.5, 0.0));
+        assert_eq!(fused.best_bid_tick(), 200);
+        assert_eq!(fused.bid_qty_by_venue(201), None);
+
+        fused.apply(
+            1,
+            &DepthChange::Clear {
+                side: Side::None,
+                price: 0.0,
+            },
+        );
+        assert_eq!(fused.bid_qty_by_venue(200), Some(&[2.0, 0.0][..]));
+        assert_eq!(fused.ask_qty_at_tick(202), 4.0);
+    }
+
+    #[test]
+    fn test_fused_depth_backtest() {
+        let asset = |events: &[Event], tick_size: f64| {
+            asset_builder(Data::from_slice(events), tick_size, 1.0)
+                .build()
+                .unwrap()
+        };
+        let mut hbt = Backtest::builder()
+            .add(asset(
+                &[
+                    event(DEPTH_EVENT | BUY_EVENT, 1, 100.0, 10.0),
+                    event(DEPTH_EVENT | SELL_EVENT, 2, 101.0, 10.0),
+                    event(DEPTH_EVENT | SELL_EVENT, 20, 101.0, 0.0),
+                    event(DEPTH_EVENT | BUY_EVENT, 50, 100.0, 10.0),
+                ],
+                1.0,
+            ))
+            .add(asset(
+                &[
+                    event(DEPTH_EVENT | BUY_EVENT, 3, 100.0, 4.0),
+                    event(DEPTH_EVENT | SELL_EVENT, 4, 100.5, 2.0),
+                    event(DEPTH_EVENT | SELL_EVENT, 30, 102.0, 1.0),
+                ],
+                0.5,
+            ))
+            .fused_depth(FusedMarketDepth::new(0.5, 1.0, vec![0, 1]))
+            .build()
+            .unwrap();
+
+        hbt.elapse(10).unwrap();
+        let fused = hbt.fused_depth().unwrap();
+        assert_eq!(fused.best_bid(), 100.0);
+        assert_eq!(fused.best_ask(), 100.5);
+        assert_eq!(fused.bid_qty_by_venue(200), Some(&[10.0, 4.0][..]));
+        assert_eq!(fused.ask_qty_at_tick(202), 10.0);
+
+        hbt.elapse(30).unwrap();
+        let fused = hbt.fused_depth().unwrap();
+        assert_eq!(fused.ask_qty_by_venue(202), None);
+        assert_eq!(fused.ask_qty_by_venue(204), Some(&[0.0, 1.0][..]));
+    }
+}
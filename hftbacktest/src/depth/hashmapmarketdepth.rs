@@ -203,7 +203,7 @@ impl L2MarketDepth for HashMapMarketDepth {
                     }
                 }
             }
-            self.best_bid_tick = depth_below(&self.bid_depth, clear_upto - 1, self.low_bid_tick);
+            self.best_bid_tick = depth_below(&self.bid_depth, clear_upto, self.low_bid_tick);
             if self.best_bid_tick == INVALID_MIN {
                 self.low_bid_tick = INVALID_MAX;
             }
@@ -215,7 +215,7 @@ impl L2MarketDepth for HashMapMarketDepth {
                     }
                 }
             }
-            self.best_ask_tick = depth_above(&self.ask_depth, clear_upto + 1, self.high_ask_tick);
+            self.best_ask_tick = depth_above(&self.ask_depth, clear_upto, self.high_ask_tick);
             if self.best_ask_tick == INVALID_MAX {
                 self.high_ask_tick = INVALID_MIN;
             }
@@ -278,6 +278,19 @@ impl MarketDepth for HashMapMarketDepth {
     fn ask_qty_at_tick(&self, price_tick: i64) -> f64 {
         *self.ask_depth.get(&price_tick).unwrap_or(&0.0)
     }
+
+    fn bid_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        (self.low_bid_tick..=self.best_bid_tick)
+            .rev()
+            .filter_map(|t| self.bid_depth.get(&t).map(|&qty| (t, qty)))
+            .filter(|&(_, qty)| qty > 0.0)
+    }
+
+    fn ask_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        (self.best_ask_tick..=self.high_ask_tick)
+            .filter_map(|t| self.ask_depth.get(&t).map(|&qty| (t, qty)))
+            .filter(|&(_, qty)| qty > 0.0)
+    }
 }
 
 impl ApplySnapshot<Event> for HashMapMarketDepth {
@@ -0,0 +1,480 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use super::{ApplySnapshot, L3MarketDepth, L3Order, MarketDepth, INVALID_MAX, INVALID_MIN};
+use crate::{
+    backtest::{reader::Data, BacktestError},
+    prelude::{OrderId, Side},
+    types::{Event, BUY_EVENT, DEPTH_SNAPSHOT_EVENT, EXCH_EVENT, LOCAL_EVENT, SELL_EVENT},
+};
+
+/// A price level of [`MBOMarketDepth`], which holds the aggregated quantity and the IDs of the
+/// orders resting at the price in the order of their queue priority.
+#[derive(Debug, Default)]
+pub struct MBOLevel {
+    pub qty: f64,
+    pub queue: VecDeque<OrderId>,
+}
+
+/// L3 Market-By-Order depth implementation, which keeps the individual orders at each price level
+/// in the order of their queue priority, on top of the aggregated Market-By-Price view provided
+/// through [`MarketDepth`].
+///
+/// An order joins the back of the queue at its price when it is added. A modification that changes
+/// the price or increases the quantity moves the order to the back of the queue at the new price,
+/// while a modification that only decreases the quantity keeps its priority, as most venues do. An
+/// execution, applied by [`MBOMarketDepth::execute_order`], reduces the quantity of the order
+/// without affecting its priority and removes the order once it is fully executed.
+#[derive(Debug)]
+pub struct MBOMarketDepth {
+    pub tick_size: f64,
+    pub lot_size: f64,
+    pub timestamp: i64,
+    pub bid_depth: BTreeMap<i64, MBOLevel>,
+    pub ask_depth: BTreeMap<i64, MBOLevel>,
+    pub best_bid_tick: i64,
+    pub best_ask_tick: i64,
+    pub orders: HashMap<OrderId, L3Order>,
+}
+
+impl MBOMarketDepth {
+    /// Constructs an instance of `MBOMarketDepth`.
+    pub fn new(tick_size: f64, lot_size: f64) -> Self {
+        Self {
+            tick_size,
+            lot_size,
+            timestamp: 0,
+            bid_depth: Default::default(),
+            ask_depth: Default::default(),
+            best_bid_tick: INVALID_MIN,
+            best_ask_tick: INVALID_MAX,
+            orders: Default::default(),
+        }
+    }
+
+    /// Executes the given quantity of the order in the order book and returns a tuple containing
+    /// (side, the previous best in ticks, the current best in ticks). The order keeps its queue
+    /// priority and is removed once it is fully executed.
+    pub fn execute_order(
+        &mut self,
+        order_id: OrderId,
+        qty: f64,
+        timestamp: i64,
+    ) -> Result<(Side, i64, i64), BacktestError> {
+        let order = self
+            .orders
+            .get_mut(&order_id)
+            .ok_or(BacktestError::OrderNotFound)?;
+        let exec_qty = qty.min(order.qty);
+        if ((order.qty - exec_qty) / self.lot_size).round() as i64 == 0 {
+            return self.delete_order(order_id, timestamp);
+        }
+        order.qty -= exec_qty;
+        let side = order.side;
+        let level = if side == Side::Buy {
+            self.bid_depth.get_mut(&order.price_tick).unwrap()
+        } else {
+            self.ask_depth.get_mut(&order.price_tick).unwrap()
+        };
+        level.qty -= exec_qty;
+        self.timestamp = timestamp;
+        let best_tick = if side == Side::Buy {
+            self.best_bid_tick
+        } else {
+            self.best_ask_tick
+        };
+        Ok((side, best_tick, best_tick))
+    }
+
+    /// Returns the orders resting at the price in ticks on the given side in the order of their
+    /// queue priority.
+    pub fn orders_at_tick(&self, side: Side, price_tick: i64) -> impl Iterator<Item = &L3Order> {
+        let level = if side == Side::Buy {
+            self.bid_depth.get(&price_tick)
+        } else {
+            self.ask_depth.get(&price_tick)
+        };
+        level
+            .into_iter()
+            .flat_map(|level| level.queue.iter())
+            .map(|order_id| &self.orders[order_id])
+    }
+
+    /// Returns the quantity of the orders ahead of the order in the queue at its price, or `None`
+    /// if the order is not in the order book.
+    pub fn qty_ahead(&self, order_id: OrderId) -> Option<f64> {
+        let order = self.orders.get(&order_id)?;
+        Some(
+            self.orders_at_tick(order.side, order.price_tick)
+                .take_while(|ahead| ahead.order_id != order_id)
+                .map(|ahead| ahead.qty)
+                .sum(),
+        )
+    }
+
+    fn enqueue(&mut self, order: L3Order) {
+        let level = if order.side == Side::Buy {
+            self.bid_depth.entry(order.price_tick).or_default()
+        } else {
+            self.ask_depth.entry(order.price_tick).or_default()
+        };
+        level.qty += order.qty;
+        level.queue.push_back(order.order_id);
+        self.orders.insert(order.order_id, order);
+    }
+
+    fn dequeue(&mut self, order_id: OrderId) -> Option<L3Order> {
+        let order = self.orders.remove(&order_id)?;
+        let depth = if order.side == Side::Buy {
+            &mut self.bid_depth
+        } else {
+            &mut self.ask_depth
+        };
+        let level = depth.get_mut(&order.price_tick).unwrap();
+        level.queue.retain(|&queued| queued != order_id);
+        level.qty -= order.qty;
+        if level.queue.is_empty() {
+            depth.remove(&order.price_tick);
+        }
+        Some(order)
+    }
+
+    fn update_best(&mut self, side: Side) -> (i64, i64) {
+        if side == Side::Buy {
+            let prev_best_tick = self.best_bid_tick;
+            self.best_bid_tick = *self.bid_depth.keys().last().unwrap_or(&INVALID_MIN);
+            (prev_best_tick, self.best_bid_tick)
+        } else {
+            let prev_best_tick = self.best_ask_tick;
+            self.best_ask_tick = *self.ask_depth.keys().next().unwrap_or(&INVALID_MAX);
+            (prev_best_tick, self.best_ask_tick)
+        }
+    }
+
+    fn add(&mut self, order: L3Order) -> Result<(i64, i64), BacktestError> {
+        if self.orders.contains_key(&order.order_id) {
+            return Err(BacktestError::OrderIdExist);
+        }
+        let side = order.side;
+        self.timestamp = order.timestamp;
+        self.enqueue(order);
+        Ok(self.update_best(side))
+    }
+}
+
+impl MarketDepth for MBOMarketDepth {
+    #[inline(always)]
+    fn best_bid(&self) -> f64 {
+        if self.best_bid_tick == INVALID_MIN {
+            f64::NAN
+        } else {
+            self.best_bid_tick as f64 * self.tick_size
+        }
+    }
+
+    #[inline(always)]
+    fn best_ask(&self) -> f64 {
+        if self.best_ask_tick == INVALID_MAX {
+            f64::NAN
+        } else {
+            self.best_ask_tick as f64 * self.tick_size
+        }
+    }
+
+    #[inline(always)]
+    fn best_bid_tick(&self) -> i64 {
+        self.best_bid_tick
+    }
+
+    #[inline(always)]
+    fn best_ask_tick(&self) -> i64 {
+        self.best_ask_tick
+    }
+
+    #[inline(always)]
+    fn tick_size(&self) -> f64 {
+        self.tick_size
+    }
+
+    #[inline(always)]
+    fn lot_size(&self) -> f64 {
+        self.lot_size
+    }
+
+    #[inline(always)]
+    fn bid_qty_at_tick(&self, price_tick: i64) -> f64 {
+        self.bid_depth
+            .get(&price_tick)
+            .map_or(0.0, |level| level.qty)
+    }
+
+    #[inline(always)]
+    fn ask_qty_at_tick(&self, price_tick: i64) -> f64 {
+        self.ask_depth
+            .get(&price_tick)
+            .map_or(0.0, |level| level.qty)
+    }
+
+    fn bid_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.bid_depth
+            .iter()
+            .rev()
+            .map(|(&price_tick, level)| (price_tick, level.qty))
+    }
+
+    fn ask_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.ask_depth
+            .iter()
+            .map(|(&price_tick, level)| (price_tick, level.qty))
+    }
+}
+
+impl ApplySnapshot<Event> for MBOMarketDepth {
+    fn apply_snapshot(&mut self, data: &Data<Event>) {
+        L3MarketDepth::clear_depth(self, Side::None);
+        for row_num in 0..data.len() {
+            let ev = &data[row_num];
+            let side = if ev.ev & BUY_EVENT == BUY_EVENT {
+                Side::Buy
+            } else if ev.ev & SELL_EVENT == SELL_EVENT {
+                Side::Sell
+            } else {
+                continue;
+            };
+            // The orders at the same price are queued in the order they appear in the snapshot.
+            if !self.orders.contains_key(&ev.order_id) {
+                self.enqueue(L3Order {
+                    order_id: ev.order_id,
+                    side,
+                    price_tick: (ev.px / self.tick_size).round() as i64,
+                    qty: ev.qty,
+                    timestamp: ev.exch_ts,
+                });
+            }
+        }
+        self.update_best(Side::Buy);
+        self.update_best(Side::Sell);
+    }
+
+    fn snapshot(&self) -> Vec<Event> {
+        let mut events = Vec::new();
+        let bid_orders = self
+            .bid_depth
+            .values()
+            .rev()
+            .flat_map(|level| level.queue.iter().map(|order_id| (BUY_EVENT, order_id)));
+        let ask_orders = self
+            .ask_depth
+            .values()
+            .flat_map(|level| level.queue.iter().map(|order_id| (SELL_EVENT, order_id)));
+        for (side_ev, order_id) in bid_orders.chain(ask_orders) {
+            let order = &self.orders[order_id];
+            events.push(Event {
+                ev: EXCH_EVENT | LOCAL_EVENT | side_ev | DEPTH_SNAPSHOT_EVENT,
+                exch_ts: order.timestamp,
+                local_ts: order.timestamp,
+                px: order.price_tick as f64 * self.tick_size,
+                qty: order.qty,
+                order_id: order.order_id,
+                ival: 0,
+                fval: 0.0,
+            });
+        }
+        events
+    }
+}
+
+impl L3MarketDepth for MBOMarketDepth {
+    type Error = BacktestError;
+
+    fn add_buy_order(
+        &mut self,
+        order_id: OrderId,
+        px: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> Result<(i64, i64), Self::Error> {
+        self.add(L3Order {
+            order_id,
+            side: Side::Buy,
+            price_tick: (px / self.tick_size).round() as i64,
+            qty,
+            timestamp,
+        })
+    }
+
+    fn add_sell_order(
+        &mut self,
+        order_id: OrderId,
+        px: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> Result<(i64, i64), Self::Error> {
+        self.add(L3Order {
+            order_id,
+            side: Side::Sell,
+            price_tick: (px / self.tick_size).round() as i64,
+            qty,
+            timestamp,
+        })
+    }
+
+    fn delete_order(
+        &mut self,
+        order_id: OrderId,
+        timestamp: i64,
+    ) -> Result<(Side, i64, i64), Self::Error> {
+        let order = self.dequeue(order_id).ok_or(BacktestError::OrderNotFound)?;
+        self.timestamp = timestamp;
+        let (prev_best_tick, best_tick) = self.update_best(order.side);
+        Ok((order.side, prev_best_tick, best_tick))
+    }
+
+    fn modify_order(
+        &mut self,
+        order_id: OrderId,
+        px: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> Result<(Side, i64, i64), Self::Error> {
+        let order = self
+            .orders
+            .get_mut(&order_id)
+            .ok_or(BacktestError::OrderNotFound)?;
+        let price_tick = (px / self.tick_size).round() as i64;
+        let side = order.side;
+        self.timestamp = timestamp;
+        if price_tick == order.price_tick && qty <= order.qty {
+            // Decreasing the quantity keeps the queue priority.
+            let level = if side == Side::Buy {
+                self.bid_depth.get_mut(&price_tick).unwrap()
+            } else {
+                self.ask_depth.get_mut(&price_tick).unwrap()
+            };
+            level.qty += qty - order.qty;
+            order.qty = qty;
+            let best_tick = if side == Side::Buy {
+                self.best_bid_tick
+            } else {
+                self.best_ask_tick
+            };
+            return Ok((side, best_tick, best_tick));
+        }
+
+        let mut order = self.dequeue(order_id).unwrap();
+        order.price_tick = price_tick;
+        order.qty = qty;
+        order.timestamp = timestamp;
+        self.enqueue(order);
+        let (prev_best_tick, best_tick) = self.update_best(side);
+        Ok((side, prev_best_tick, best_tick))
+    }
+
+    fn clear_depth(&mut self, side: Side) {
+        if side == Side::Buy {
+            self.bid_depth.clear();
+            self.orders.retain(|_, order| order.side != Side::Buy);
+        } else if side == Side::Sell {
+            self.ask_depth.clear();
+            self.orders.retain(|_, order| order.side != Side::Sell);
+        } else {
+            self.bid_depth.clear();
+            self.ask_depth.clear();
+            self.orders.clear();
+        }
+        self.update_best(Side::Buy);
+        self.update_best(Side::Sell);
+    }
+
+    fn orders(&self) -> &HashMap<OrderId, L3Order> {
+        &self.orders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        depth::{L3MarketDepth, MBOMarketDepth, MarketDepth, INVALID_MAX},
+        types::Side,
+    };
+
+    macro_rules! assert_eq_qty {
+        ( $a:expr, $b:expr, $lot_size:ident ) => {{
+            assert_eq!(
+                ($a / $lot_size).round() as i64,
+                ($b / $lot_size).round() as i64
+            );
+        }};
+    }
+
+    fn queue(depth: &MBOMarketDepth, side: Side, price_tick: i64) -> Vec<u64> {
+        depth
+            .orders_at_tick(side, price_tick)
+            .map(|order| order.order_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_mbo_queue_priority() {
+        let lot_size = 0.001;
+        let mut depth = MBOMarketDepth::new(0.1, lot_size);
+
+        depth.add_buy_order(1, 500.1, 0.001, 0).unwrap();
+        depth.add_buy_order(2, 500.1, 0.005, 1).unwrap();
+        depth.add_buy_order(3, 500.1, 0.002, 2).unwrap();
+        let (prev_best, best) = depth.add_buy_order(4, 500.3, 0.004, 3).unwrap();
+        assert_eq!((prev_best, best), (5001, 5003));
+        assert!(depth.add_buy_order(4, 500.0, 0.001, 4).is_err());
+        assert_eq!(queue(&depth, Side::Buy, 5001), vec![1, 2, 3]);
+        assert_eq_qty!(depth.bid_qty_at_tick(5001), 0.008, lot_size);
+        assert_eq_qty!(depth.qty_ahead(3).unwrap(), 0.006, lot_size);
+
+        // Decreasing the quantity keeps the priority, while increasing it loses the priority.
+        depth.modify_order(2, 500.1, 0.003, 5).unwrap();
+        assert_eq!(queue(&depth, Side::Buy, 5001), vec![1, 2, 3]);
+        depth.modify_order(1, 500.1, 0.002, 6).unwrap();
+        assert_eq!(queue(&depth, Side::Buy, 5001), vec![2, 3, 1]);
+        assert_eq_qty!(depth.bid_qty_at_tick(5001), 0.007, lot_size);
+
+        // Changing the price moves the order to the back of the queue at the new price.
+        let (side, prev_best, best) = depth.modify_order(2, 500.3, 0.003, 7).unwrap();
+        assert_eq!((side, prev_best, best), (Side::Buy, 5003, 5003));
+        assert_eq!(queue(&depth, Side::Buy, 5003), vec![4, 2]);
+        assert_eq!(queue(&depth, Side::Buy, 5001), vec![3, 1]);
+
+        // An execution reduces the quantity in place and removes the fully executed order.
+        depth.execute_order(4, 0.001, 8).unwrap();
+        assert_eq!(queue(&depth, Side::Buy, 5003), vec![4, 2]);
+        assert_eq_qty!(depth.bid_qty_at_tick(5003), 0.006, lot_size);
+        assert_eq_qty!(depth.qty_ahead(2).unwrap(), 0.003, lot_size);
+        depth.execute_order(4, 0.003, 9).unwrap();
+        assert_eq!(queue(&depth, Side::Buy, 5003), vec![2]);
+        assert!(depth.qty_ahead(4).is_none());
+        let (side, prev_best, best) = depth.execute_order(2, 0.003, 10).unwrap();
+        assert_eq!((side, prev_best, best), (Side::Buy, 5003, 5001));
+        assert_eq!(depth.best_bid_tick(), 5001);
+        assert!(depth.execute_order(2, 0.001, 11).is_err());
+    }
+
+    #[test]
+    fn test_mbo_delete_sell_order() {
+        let lot_size = 0.001;
+        let mut depth = MBOMarketDepth::new(0.1, lot_size);
+
+        depth.add_sell_order(1, 500.1, 0.001, 0).unwrap();
+        depth.add_sell_order(2, 499.3, 0.005, 0).unwrap();
+        depth.add_sell_order(3, 499.3, 0.002, 0).unwrap();
+        assert_eq!(depth.best_ask_tick(), 4993);
+
+        let (side, prev_best, best) = depth.delete_order(2, 0).unwrap();
+        assert_eq!((side, prev_best, best), (Side::Sell, 4993, 4993));
+        assert_eq!(queue(&depth, Side::Sell, 4993), vec![3]);
+        assert_eq_qty!(depth.ask_qty_at_tick(4993), 0.002, lot_size);
+
+        let (_, prev_best, best) = depth.delete_order(3, 0).unwrap();
+        assert_eq!((prev_best, best), (4993, 5001));
+        let (_, prev_best, best) = depth.delete_order(1, 0).unwrap();
+        assert_eq!((prev_best, best), (5001, INVALID_MAX));
+        assert!(depth.delete_order(1, 0).is_err());
+        assert!(depth.orders().is_empty());
+    }
+}
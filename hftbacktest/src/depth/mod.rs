@@ -1,14 +1,30 @@
 use std::collections::HashMap;
 
+pub use analytics::{BookPressure, DepthAnalytics};
+pub use bbomarketdepth::BBOMarketDepth;
 pub use btreemarketdepth::BTreeMarketDepth;
+pub use checksum::{crc32, DepthChecksum};
+pub use countmarketdepth::CountMarketDepth;
+pub use fusedmarketdepth::{DepthChange, FusedMarketDepth};
 pub use hashmapmarketdepth::HashMapMarketDepth;
-pub use roivectormarketdepth::ROIVectorMarketDepth;
+pub use mbomarketdepth::{MBOLevel, MBOMarketDepth};
+pub use pagedroimarketdepth::PagedROIMarketDepth;
+pub use roivectormarketdepth::{OutOfROIPolicy, ROIAdjustment, ROIVectorMarketDepth};
+pub use timestampmarketdepth::TimestampMarketDepth;
 
 use crate::prelude::Side;
 
+mod analytics;
+mod bbomarketdepth;
 mod btreemarketdepth;
+mod checksum;
+mod countmarketdepth;
+mod fusedmarketdepth;
 mod hashmapmarketdepth;
+mod mbomarketdepth;
+mod pagedroimarketdepth;
 mod roivectormarketdepth;
+mod timestampmarketdepth;
 
 #[cfg(any(feature = "unstable_fuse", doc))]
 mod fuse;
@@ -56,6 +72,86 @@ pub trait MarketDepth {
 
     /// Returns the quantity at the ask market depth for a given price in ticks.
     fn ask_qty_at_tick(&self, price_tick: i64) -> f64;
+
+    /// Returns an iterator over the bid price levels that have quantity, in descending order of
+    /// price from the best bid, yielding tuples of (the price in ticks, the quantity). It can be
+    /// bounded by the number of levels with [`take`](Iterator::take), or by the price with
+    /// [`take_while`](Iterator::take_while).
+    ///
+    /// **Example**
+    /// ```
+    /// use hftbacktest::depth::{HashMapMarketDepth, L2MarketDepth, MarketDepth};
+    ///
+    /// let mut depth = HashMapMarketDepth::new(0.1, 1.0);
+    /// depth.update_bid_depth(100.0, 5.0, 0);
+    /// depth.update_bid_depth(99.5, 3.0, 0);
+    /// depth.update_bid_depth(99.0, 1.0, 0);
+    ///
+    /// // The best 2 levels.
+    /// let top = depth.bid_levels().take(2).collect::<Vec<_>>();
+    /// assert_eq!(top, vec![(1000, 5.0), (995, 3.0)]);
+    ///
+    /// // The levels within 5 ticks from the best bid.
+    /// let best_bid_tick = depth.best_bid_tick();
+    /// let qty: f64 = depth
+    ///     .bid_levels()
+    ///     .take_while(|&(price_tick, _)| price_tick >= best_bid_tick - 5)
+    ///     .map(|(_, qty)| qty)
+    ///     .sum();
+    /// assert_eq!(qty, 8.0);
+    /// ```
+    fn bid_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_;
+
+    /// Returns an iterator over the ask price levels that have quantity, in ascending order of
+    /// price from the best ask, yielding tuples of (the price in ticks, the quantity). See
+    /// [`bid_levels`](Self::bid_levels).
+    fn ask_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_;
+
+    /// Returns the cumulative quantity of the market depth on the `side`, [`Side::Buy`] for the bid
+    /// and [`Side::Sell`] for the ask, from the best price through the given price, which is the
+    /// quantity available to a sweep that doesn't go beyond the price.
+    /// If the `side` is neither [`Side::Buy`] nor [`Side::Sell`], it returns `0`.
+    fn qty_up_to_price(&self, side: Side, price: f64) -> f64 {
+        let price_tick = (price / self.tick_size()).round() as i64;
+        match side {
+            Side::Buy => self
+                .bid_levels()
+                .take_while(|&(tick, _)| tick >= price_tick)
+                .map(|(_, qty)| qty)
+                .sum(),
+            Side::Sell => self
+                .ask_levels()
+                .take_while(|&(tick, _)| tick <= price_tick)
+                .map(|(_, qty)| qty)
+                .sum(),
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the worst price reached by sweeping the quantity from the best price of the market
+    /// depth on the `side`, [`Side::Buy`] for the bid and [`Side::Sell`] for the ask, which is the
+    /// limit price needed to fill the quantity immediately.
+    /// If the market depth doesn't have enough quantity, or the `side` is neither [`Side::Buy`] nor
+    /// [`Side::Sell`], it returns [`f64::NAN`].
+    fn price_for_qty(&self, side: Side, qty: f64) -> f64 {
+        fn sweep(levels: impl Iterator<Item = (i64, f64)>, qty: f64, lot_size: f64) -> Option<i64> {
+            let mut remaining = qty;
+            for (price_tick, level_qty) in levels {
+                remaining -= level_qty;
+                if (remaining / lot_size).round() <= 0.0 {
+                    return Some(price_tick);
+                }
+            }
+            None
+        }
+
+        let price_tick = match side {
+            Side::Buy => sweep(self.bid_levels(), qty, self.lot_size()),
+            Side::Sell => sweep(self.ask_levels(), qty, self.lot_size()),
+            _ => None,
+        };
+        price_tick.map_or(f64::NAN, |price_tick| price_tick as f64 * self.tick_size())
+    }
 }
 
 /// Provides Level2-specific market depth functions.
@@ -86,11 +182,79 @@ pub trait L2MarketDepth {
         timestamp: i64,
     ) -> (i64, i64, i64, f64, f64, i64);
 
+    /// Updates the bid-side market depth along with the number of orders at the price, which is
+    /// carried in the `ival` of the depth event by feeds that publish order counts per level. The
+    /// returned tuple is the same as [`update_bid_depth`](Self::update_bid_depth). The default
+    /// implementation discards the count.
+    fn update_bid_depth_with_count(
+        &mut self,
+        price: f64,
+        qty: f64,
+        _count: i64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        self.update_bid_depth(price, qty, timestamp)
+    }
+
+    /// Updates the ask-side market depth along with the number of orders at the price, which is
+    /// carried in the `ival` of the depth event by feeds that publish order counts per level. The
+    /// returned tuple is the same as [`update_ask_depth`](Self::update_ask_depth). The default
+    /// implementation discards the count.
+    fn update_ask_depth_with_count(
+        &mut self,
+        price: f64,
+        qty: f64,
+        _count: i64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        self.update_ask_depth(price, qty, timestamp)
+    }
+
     /// Clears the market depth. If the `side` is neither [Side::Buy] nor [Side::Sell], both sides
     /// are cleared. In this case, `clear_upto_price` is ignored.
     fn clear_depth(&mut self, side: Side, clear_upto_price: f64);
 }
 
+/// Provides the number of orders at each price level, for feeds that publish order counts per
+/// level.
+pub trait OrderCountDepth: MarketDepth {
+    /// Returns the number of orders at the bid market depth for a given price in ticks. If the
+    /// count is unknown, it returns `0`.
+    fn bid_count_at_tick(&self, price_tick: i64) -> i64;
+
+    /// Returns the number of orders at the ask market depth for a given price in ticks. If the
+    /// count is unknown, it returns `0`.
+    fn ask_count_at_tick(&self, price_tick: i64) -> i64;
+
+    /// Returns an iterator over the price levels of the bid market depth, along with the number of
+    /// orders, as `(price_tick, qty, count)`, from the best bid downward. See
+    /// [`MarketDepth::bid_levels`].
+    fn bid_levels_with_count(&self) -> impl Iterator<Item = (i64, f64, i64)> + '_ {
+        self.bid_levels()
+            .map(|(price_tick, qty)| (price_tick, qty, self.bid_count_at_tick(price_tick)))
+    }
+
+    /// Returns an iterator over the price levels of the ask market depth, along with the number of
+    /// orders, as `(price_tick, qty, count)`, from the best ask upward. See
+    /// [`MarketDepth::ask_levels`].
+    fn ask_levels_with_count(&self) -> impl Iterator<Item = (i64, f64, i64)> + '_ {
+        self.ask_levels()
+            .map(|(price_tick, qty)| (price_tick, qty, self.ask_count_at_tick(price_tick)))
+    }
+}
+
+/// Provides the timestamp of the last update of each price level, which is used to measure the age
+/// of the levels, such as to detect stale levels.
+pub trait LevelTimestampDepth: MarketDepth {
+    /// Returns the timestamp of the last update of the bid market depth for a given price in
+    /// ticks, or `None` if there is no quantity at the price.
+    fn bid_timestamp_at_tick(&self, price_tick: i64) -> Option<i64>;
+
+    /// Returns the timestamp of the last update of the ask market depth for a given price in
+    /// ticks, or `None` if there is no quantity at the price.
+    fn ask_timestamp_at_tick(&self, price_tick: i64) -> Option<i64>;
+}
+
 /// Provides a method to initialize the `MarketDepth` from the given snapshot data, such as
 /// Start-Of-Day snapshot or End-Of-Day snapshot, for backtesting purpose.
 pub trait ApplySnapshot<EventT>
@@ -163,7 +327,12 @@ pub trait L3MarketDepth: MarketDepth {
     fn orders(&self) -> &HashMap<OrderId, L3Order>;
 }
 
+/// Provides Level1-specific market depth functions, which update the market depth by the best bid
+/// and offer.
 pub trait L1MarketDepth {
+    /// Updates the best bid and returns a tuple containing (the price in ticks, the previous best
+    /// bid price in ticks, the current best bid price in ticks, the previous quantity at the price,
+    /// the current quantity at the price, and the timestamp).
     fn update_best_bid(
         &mut self,
         px: f64,
@@ -171,6 +340,9 @@ pub trait L1MarketDepth {
         timestamp: i64,
     ) -> (i64, i64, i64, f64, f64, i64);
 
+    /// Updates the best ask and returns a tuple containing (the price in ticks, the previous best
+    /// ask price in ticks, the current best ask price in ticks, the previous quantity at the price,
+    /// the current quantity at the price, and the timestamp).
     fn update_best_ask(
         &mut self,
         px: f64,
@@ -0,0 +1,517 @@
+use std::iter;
+
+use super::{ApplySnapshot, L2MarketDepth, MarketDepth, INVALID_MAX, INVALID_MIN};
+use crate::{
+    backtest::reader::Data,
+    types::{Event, Side, BUY_EVENT, DEPTH_SNAPSHOT_EVENT, EXCH_EVENT, LOCAL_EVENT, SELL_EVENT},
+};
+
+// The number of price levels in a page.
+const PAGE_SIZE: usize = 1024;
+
+struct Page {
+    qty: Box<[f64; PAGE_SIZE]>,
+    // The number of price levels that have quantity in this page.
+    levels: usize,
+}
+
+/// The quantities of a side of the market depth, stored in fixed-size pages that are allocated
+/// only while they have quantity.
+struct Pages {
+    pages: Vec<Option<Page>>,
+}
+
+impl Pages {
+    fn new(len: usize) -> Self {
+        Self {
+            pages: (0..len.div_ceil(PAGE_SIZE)).map(|_| None).collect(),
+        }
+    }
+
+    #[inline(always)]
+    fn get(&self, i: usize) -> f64 {
+        match &self.pages[i / PAGE_SIZE] {
+            Some(page) => page.qty[i % PAGE_SIZE],
+            None => 0.0,
+        }
+    }
+
+    /// Sets the quantity at the index and returns the previous quantity.
+    fn set(&mut self, i: usize, qty: f64) -> f64 {
+        let slot = &mut self.pages[i / PAGE_SIZE];
+        if slot.is_none() {
+            if qty == 0.0 {
+                return 0.0;
+            }
+            *slot = Some(Page {
+                qty: Box::new([0.0; PAGE_SIZE]),
+                levels: 0,
+            });
+        }
+        let page = slot.as_mut().unwrap();
+        let v = &mut page.qty[i % PAGE_SIZE];
+        let prev_qty = *v;
+        *v = qty;
+        if prev_qty == 0.0 && qty != 0.0 {
+            page.levels += 1;
+        } else if prev_qty != 0.0 && qty == 0.0 {
+            page.levels -= 1;
+        }
+        if page.levels == 0 {
+            *slot = None;
+        }
+        prev_qty
+    }
+
+    /// Returns the highest index below `start` that has quantity, skipping the unallocated pages.
+    fn last_below(&self, start: usize) -> Option<usize> {
+        let mut i = start;
+        while i > 0 {
+            let page_no = (i - 1) / PAGE_SIZE;
+            let page_start = page_no * PAGE_SIZE;
+            if let Some(page) = &self.pages[page_no] {
+                for j in (page_start..i).rev() {
+                    if page.qty[j - page_start] > 0.0 {
+                        return Some(j);
+                    }
+                }
+            }
+            i = page_start;
+        }
+        None
+    }
+
+    /// Returns the lowest index from `start` that has quantity, skipping the unallocated pages.
+    fn first_from(&self, start: usize) -> Option<usize> {
+        let mut i = start;
+        while i < self.pages.len() * PAGE_SIZE {
+            let page_no = i / PAGE_SIZE;
+            let page_start = page_no * PAGE_SIZE;
+            if let Some(page) = &self.pages[page_no] {
+                for j in i..(page_start + PAGE_SIZE) {
+                    if page.qty[j - page_start] > 0.0 {
+                        return Some(j);
+                    }
+                }
+            }
+            i = page_start + PAGE_SIZE;
+        }
+        None
+    }
+
+    fn clear(&mut self) {
+        for page in &mut self.pages {
+            *page = None;
+        }
+    }
+
+    fn num_pages(&self) -> usize {
+        self.pages.iter().filter(|page| page.is_some()).count()
+    }
+}
+
+/// L2 market depth implementation within the range of interest, like [`ROIVectorMarketDepth`],
+/// whose storage is divided into pages of 1,024 price levels that are allocated only while they
+/// have quantity.
+///
+/// [`ROIVectorMarketDepth`] allocates the whole range of interest up front, which can take a lot of
+/// memory for a wide range of interest with a fine tick size, such as BTC with a tick size of 0.1,
+/// especially in a multi-asset backtest. Since only the pages around the market are allocated,
+/// this keeps the memory proportional to the price range the market depth actually spans, while
+/// the lookups remain a direct index. Searching for the next best price skips the unallocated
+/// pages, so a sparse market depth doesn't need to be scanned tick by tick.
+///
+/// The price levels outside the range of interest are ignored. Unlike [`ROIVectorMarketDepth`],
+/// the market depth isn't exposed as contiguous slices.
+///
+/// [`ROIVectorMarketDepth`]: super::ROIVectorMarketDepth
+///
+/// **Example**
+/// ```
+/// use hftbacktest::depth::PagedROIMarketDepth;
+///
+/// // Covers BTC prices up to 1,000,000 with a tick size of 0.1.
+/// let depth = PagedROIMarketDepth::new(0.1, 0.001, 0.0, 1_000_000.0);
+/// ```
+pub struct PagedROIMarketDepth {
+    tick_size: f64,
+    lot_size: f64,
+    bid_depth: Pages,
+    ask_depth: Pages,
+    best_bid_tick: i64,
+    best_ask_tick: i64,
+    roi_lb: i64,
+    roi_ub: i64,
+}
+
+impl PagedROIMarketDepth {
+    /// Constructs an instance of `PagedROIMarketDepth` with the range of interest from `roi_lb` to
+    /// `roi_ub`.
+    pub fn new(tick_size: f64, lot_size: f64, roi_lb: f64, roi_ub: f64) -> Self {
+        let roi_lb = (roi_lb / tick_size).round() as i64;
+        let roi_ub = (roi_ub / tick_size).round() as i64;
+        let roi_range = (roi_ub + 1 - roi_lb) as usize;
+        Self {
+            tick_size,
+            lot_size,
+            bid_depth: Pages::new(roi_range),
+            ask_depth: Pages::new(roi_range),
+            best_bid_tick: INVALID_MIN,
+            best_ask_tick: INVALID_MAX,
+            roi_lb,
+            roi_ub,
+        }
+    }
+
+    /// Returns the lower bound of the range of interest in ticks.
+    pub fn roi_lb(&self) -> i64 {
+        self.roi_lb
+    }
+
+    /// Returns the upper bound of the range of interest in ticks.
+    pub fn roi_ub(&self) -> i64 {
+        self.roi_ub
+    }
+
+    /// Returns the number of pages currently allocated for both sides.
+    pub fn num_pages(&self) -> usize {
+        self.bid_depth.num_pages() + self.ask_depth.num_pages()
+    }
+
+    #[inline(always)]
+    fn in_roi(&self, price_tick: i64) -> bool {
+        price_tick >= self.roi_lb && price_tick <= self.roi_ub
+    }
+
+    /// Returns the highest bid price in ticks below the price in ticks.
+    fn bid_below(&self, price_tick: i64) -> i64 {
+        let start = price_tick
+            .saturating_sub(self.roi_lb)
+            .clamp(0, self.roi_ub + 1 - self.roi_lb);
+        self.bid_depth
+            .last_below(start as usize)
+            .map_or(INVALID_MIN, |i| i as i64 + self.roi_lb)
+    }
+
+    /// Returns the lowest ask price in ticks above the price in ticks.
+    fn ask_above(&self, price_tick: i64) -> i64 {
+        let start = price_tick
+            .saturating_add(1)
+            .saturating_sub(self.roi_lb)
+            .clamp(0, self.roi_ub + 1 - self.roi_lb);
+        self.ask_depth
+            .first_from(start as usize)
+            .filter(|&i| i as i64 <= self.roi_ub - self.roi_lb)
+            .map_or(INVALID_MAX, |i| i as i64 + self.roi_lb)
+    }
+}
+
+impl L2MarketDepth for PagedROIMarketDepth {
+    fn update_bid_depth(
+        &mut self,
+        price: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        let price_tick = (price / self.tick_size).round() as i64;
+        let qty_lot = (qty / self.lot_size).round() as i64;
+        let prev_best_bid_tick = self.best_bid_tick;
+        if !self.in_roi(price_tick) {
+            // This is outside the range of interest.
+            return (
+                price_tick,
+                prev_best_bid_tick,
+                self.best_bid_tick,
+                0.0,
+                qty,
+                timestamp,
+            );
+        }
+
+        let i = (price_tick - self.roi_lb) as usize;
+        let prev_qty = self.bid_depth.set(i, if qty_lot == 0 { 0.0 } else { qty });
+        if qty_lot == 0 {
+            if price_tick == self.best_bid_tick {
+                self.best_bid_tick = self.bid_below(price_tick);
+            }
+        } else if price_tick > self.best_bid_tick {
+            self.best_bid_tick = price_tick;
+            if self.best_bid_tick >= self.best_ask_tick {
+                self.best_ask_tick = self.ask_above(self.best_bid_tick);
+            }
+        }
+        (
+            price_tick,
+            prev_best_bid_tick,
+            self.best_bid_tick,
+            prev_qty,
+            qty,
+            timestamp,
+        )
+    }
+
+    fn update_ask_depth(
+        &mut self,
+        price: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        let price_tick = (price / self.tick_size).round() as i64;
+        let qty_lot = (qty / self.lot_size).round() as i64;
+        let prev_best_ask_tick = self.best_ask_tick;
+        if !self.in_roi(price_tick) {
+            // This is outside the range of interest.
+            return (
+                price_tick,
+                prev_best_ask_tick,
+                self.best_ask_tick,
+                0.0,
+                qty,
+                timestamp,
+            );
+        }
+
+        let i = (price_tick - self.roi_lb) as usize;
+        let prev_qty = self.ask_depth.set(i, if qty_lot == 0 { 0.0 } else { qty });
+        if qty_lot == 0 {
+            if price_tick == self.best_ask_tick {
+                self.best_ask_tick = self.ask_above(price_tick);
+            }
+        } else if price_tick < self.best_ask_tick {
+            self.best_ask_tick = price_tick;
+            if self.best_bid_tick >= self.best_ask_tick {
+                self.best_bid_tick = self.bid_below(self.best_ask_tick);
+            }
+        }
+        (
+            price_tick,
+            prev_best_ask_tick,
+            self.best_ask_tick,
+            prev_qty,
+            qty,
+            timestamp,
+        )
+    }
+
+    fn clear_depth(&mut self, side: Side, clear_upto_price: f64) {
+        let clear_upto = (clear_upto_price / self.tick_size).round() as i64;
+        if side == Side::Buy {
+            while self.best_bid_tick != INVALID_MIN && self.best_bid_tick >= clear_upto {
+                self.bid_depth
+                    .set((self.best_bid_tick - self.roi_lb) as usize, 0.0);
+                self.best_bid_tick = self.bid_below(self.best_bid_tick);
+            }
+        } else if side == Side::Sell {
+            while self.best_ask_tick != INVALID_MAX && self.best_ask_tick <= clear_upto {
+                self.ask_depth
+                    .set((self.best_ask_tick - self.roi_lb) as usize, 0.0);
+                self.best_ask_tick = self.ask_above(self.best_ask_tick);
+            }
+        } else {
+            self.bid_depth.clear();
+            self.ask_depth.clear();
+            self.best_bid_tick = INVALID_MIN;
+            self.best_ask_tick = INVALID_MAX;
+        }
+    }
+}
+
+impl MarketDepth for PagedROIMarketDepth {
+    #[inline(always)]
+    fn best_bid(&self) -> f64 {
+        if self.best_bid_tick == INVALID_MIN {
+            f64::NAN
+        } else {
+            self.best_bid_tick as f64 * self.tick_size
+        }
+    }
+
+    #[inline(always)]
+    fn best_ask(&self) -> f64 {
+        if self.best_ask_tick == INVALID_MAX {
+            f64::NAN
+        } else {
+            self.best_ask_tick as f64 * self.tick_size
+        }
+    }
+
+    #[inline(always)]
+    fn best_bid_tick(&self) -> i64 {
+        self.best_bid_tick
+    }
+
+    #[inline(always)]
+    fn best_ask_tick(&self) -> i64 {
+        self.best_ask_tick
+    }
+
+    #[inline(always)]
+    fn tick_size(&self) -> f64 {
+        self.tick_size
+    }
+
+    #[inline(always)]
+    fn lot_size(&self) -> f64 {
+        self.lot_size
+    }
+
+    #[inline(always)]
+    fn bid_qty_at_tick(&self, price_tick: i64) -> f64 {
+        if !self.in_roi(price_tick) {
+            // This is outside the range of interest.
+            0.0
+        } else {
+            self.bid_depth.get((price_tick - self.roi_lb) as usize)
+        }
+    }
+
+    #[inline(always)]
+    fn ask_qty_at_tick(&self, price_tick: i64) -> f64 {
+        if !self.in_roi(price_tick) {
+            // This is outside the range of interest.
+            0.0
+        } else {
+            self.ask_depth.get((price_tick - self.roi_lb) as usize)
+        }
+    }
+
+    fn bid_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        let best_bid_tick = Some(self.best_bid_tick).filter(|&t| t != INVALID_MIN);
+        iter::successors(best_bid_tick, |&t| {
+            Some(self.bid_below(t)).filter(|&t| t != INVALID_MIN)
+        })
+        .map(|t| (t, self.bid_qty_at_tick(t)))
+    }
+
+    fn ask_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        let best_ask_tick = Some(self.best_ask_tick).filter(|&t| t != INVALID_MAX);
+        iter::successors(best_ask_tick, |&t| {
+            Some(self.ask_above(t)).filter(|&t| t != INVALID_MAX)
+        })
+        .map(|t| (t, self.ask_qty_at_tick(t)))
+    }
+}
+
+impl ApplySnapshot<Event> for PagedROIMarketDepth {
+    fn apply_snapshot(&mut self, data: &Data<Event>) {
+        self.bid_depth.clear();
+        self.ask_depth.clear();
+        self.best_bid_tick = INVALID_MIN;
+        self.best_ask_tick = INVALID_MAX;
+        for row_num in 0..data.len() {
+            let price = data[row_num].px;
+            let qty = data[row_num].qty;
+
+            let price_tick = (price / self.tick_size).round() as i64;
+            if !self.in_roi(price_tick) || (qty / self.lot_size).round() as i64 == 0 {
+                continue;
+            }
+            let i = (price_tick - self.roi_lb) as usize;
+            if data[row_num].ev & BUY_EVENT == BUY_EVENT {
+                self.best_bid_tick = self.best_bid_tick.max(price_tick);
+                self.bid_depth.set(i, qty);
+            } else if data[row_num].ev & SELL_EVENT == SELL_EVENT {
+                self.best_ask_tick = self.best_ask_tick.min(price_tick);
+                self.ask_depth.set(i, qty);
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Vec<Event> {
+        let bid_events = self
+            .bid_levels()
+            .map(|(px_tick, qty)| (BUY_EVENT, px_tick, qty));
+        let ask_events = self
+            .ask_levels()
+            .map(|(px_tick, qty)| (SELL_EVENT, px_tick, qty));
+        bid_events
+            .chain(ask_events)
+            .map(|(side_ev, px_tick, qty)| Event {
+                ev: EXCH_EVENT | LOCAL_EVENT | side_ev | DEPTH_SNAPSHOT_EVENT,
+                exch_ts: 0,
+                local_ts: 0,
+                px: px_tick as f64 * self.tick_size,
+                qty,
+                order_id: 0,
+                ival: 0,
+                fval: 0.0,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backtest::reader::Data,
+        depth::{
+            ApplySnapshot,
+            L2MarketDepth,
+            MarketDepth,
+            PagedROIMarketDepth,
+            INVALID_MAX,
+            INVALID_MIN,
+        },
+        types::Side,
+    };
+
+    #[test]
+    fn test_l2_update_paged_depth() {
+        let mut depth = PagedROIMarketDepth::new(1.0, 1.0, 0.0, 1_000_000.0);
+        assert_eq!(depth.num_pages(), 0);
+
+        depth.update_bid_depth(500_000.0, 2.0, 0);
+        depth.update_bid_depth(490_000.0, 3.0, 0);
+        depth.update_ask_depth(500_001.0, 1.0, 0);
+        depth.update_ask_depth(510_000.0, 4.0, 0);
+        assert_eq!(depth.num_pages(), 4);
+        assert_eq!(depth.best_bid_tick(), 500_000);
+        assert_eq!(depth.best_ask_tick(), 500_001);
+        assert_eq!(depth.bid_qty_at_tick(490_000), 3.0);
+        assert_eq!(depth.bid_qty_at_tick(490_001), 0.0);
+        assert_eq!(
+            depth.ask_levels().collect::<Vec<_>>(),
+            vec![(500_001, 1.0), (510_000, 4.0)]
+        );
+
+        // The next best price is found across the unallocated pages, and the emptied page is
+        // freed.
+        let (_, prev_best, best, prev_qty, _, _) = depth.update_bid_depth(500_000.0, 0.0, 0);
+        assert_eq!((prev_best, best, prev_qty), (500_000, 490_000, 2.0));
+        assert_eq!(depth.num_pages(), 3);
+
+        // A crossing bid moves the best ask.
+        depth.update_bid_depth(500_001.0, 1.0, 0);
+        assert_eq!(depth.best_ask_tick(), 510_000);
+
+        // Outside the range of interest.
+        depth.update_ask_depth(2_000_000.0, 1.0, 0);
+        assert_eq!(depth.ask_qty_at_tick(2_000_000), 0.0);
+        assert_eq!(depth.best_ask_tick(), 510_000);
+    }
+
+    #[test]
+    fn test_l2_clear_paged_depth() {
+        let mut depth = PagedROIMarketDepth::new(0.5, 1.0, 0.0, 1000.0);
+        for i in 0..10 {
+            depth.update_bid_depth(100.0 - i as f64, 1.0, 0);
+            depth.update_ask_depth(101.0 + i as f64, 1.0, 0);
+        }
+        depth.clear_depth(Side::Buy, 96.0);
+        assert_eq!(depth.best_bid(), 95.0);
+        depth.clear_depth(Side::Sell, 105.0);
+        assert_eq!(depth.best_ask(), 106.0);
+        assert_eq!(depth.bid_levels().count(), 5);
+
+        let mut restored = PagedROIMarketDepth::new(0.5, 1.0, 0.0, 1000.0);
+        restored.apply_snapshot(&Data::from_slice(&depth.snapshot()));
+        assert_eq!(restored.best_bid(), 95.0);
+        assert_eq!(restored.best_ask(), 106.0);
+        assert_eq!(restored.ask_levels().count(), 5);
+
+        depth.clear_depth(Side::None, 0.0);
+        assert_eq!(depth.best_bid_tick(), INVALID_MIN);
+        assert_eq!(depth.best_ask_tick(), INVALID_MAX);
+        assert_eq!(depth.num_pages(), 0);
+    }
+}
@@ -1,17 +1,67 @@
-use std::collections::{hash_map::Entry, HashMap};
+use std::collections::{BTreeMap, HashMap};
 
 use super::{ApplySnapshot, L3MarketDepth, L3Order, MarketDepth, INVALID_MAX, INVALID_MIN};
 use crate::{
     backtest::{reader::Data, BacktestError},
     prelude::{L2MarketDepth, OrderId, Side},
-    types::{Event, BUY_EVENT, SELL_EVENT},
+    types::{Event, BUY_EVENT, DEPTH_SNAPSHOT_EVENT, EXCH_EVENT, LOCAL_EVENT, SELL_EVENT},
 };
 
+/// Specifies how [`ROIVectorMarketDepth`] adjusts its range of interest when the market depth is
+/// updated at a price outside it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ROIAdjustment {
+    /// Keeps the range of interest fixed, ignoring the price levels outside it.
+    #[default]
+    Fixed,
+    /// Shifts the range of interest, keeping its width, so that it is centered on the price. The
+    /// price levels that fall outside the shifted range are dropped.
+    Recenter,
+    /// Extends the range of interest to cover the price, by at least its current width to amortize
+    /// the reallocation. No price levels are dropped, at the cost of memory.
+    Grow,
+}
+
+/// Specifies what [`ROIVectorMarketDepth`] does with an L2 update at a price outside its range of
+/// interest, after the range is adjusted by the [`ROIAdjustment`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OutOfROIPolicy {
+    /// Drops the update.
+    #[default]
+    Drop,
+    /// Keeps the price levels outside the range of interest and accumulates their quantity into the
+    /// nearest boundary of the range, so that the quantity beyond the range is still accounted for
+    /// and the best prices are clamped to the range.
+    Clamp,
+    /// Keeps the price levels outside the range of interest, so that the best prices and the
+    /// quantities at them remain correct even when the market moves out of the range, although
+    /// they aren't stored in the vectors.
+    TrackBest,
+    /// Panics, which reveals a range of interest that is too narrow for the data.
+    Panic,
+}
+
 /// L2/L3 market depth implementation based on a vector within the range of interest.
 ///
 /// This is a variant of the HashMap-based market depth implementation, which only handles the
 /// specific range of interest. By doing so, it improves performance, especially when the strategy
 /// requires computing values based on the order book around the mid-price.
+///
+/// By default, the price levels outside the range of interest are ignored. With
+/// [`ROIAdjustment::Recenter`] or [`ROIAdjustment::Grow`], the range of interest follows the market
+/// instead when a price level is added outside it, so that a long backtest through a trending
+/// market doesn't require a wide static range. Since the slices returned by
+/// [`bid_depth`](Self::bid_depth) and [`ask_depth`](Self::ask_depth) start at `roi_lb`, which can
+/// change by the adjustment, they should be indexed relative to the current `roi_lb`. In L3, the
+/// range of interest is adjusted by the addition of an order, and the orders at the prices newly
+/// covered are aggregated into the depth.
+///
+/// What happens to the L2 updates that remain outside the range of interest is set by
+/// [`OutOfROIPolicy`].
+///
+/// As the whole range of interest is allocated up front, a wide range of interest with a fine tick
+/// size can take a lot of memory. [`PagedROIMarketDepth`](super::PagedROIMarketDepth) allocates
+/// only the parts of the range that have quantity, for L2.
 pub struct ROIVectorMarketDepth {
     pub tick_size: f64,
     pub lot_size: f64,
@@ -24,13 +74,17 @@ pub struct ROIVectorMarketDepth {
     pub high_ask_tick: i64,
     pub roi_ub: i64,
     pub roi_lb: i64,
+    pub roi_adjustment: ROIAdjustment,
+    pub out_of_roi_policy: OutOfROIPolicy,
+    pub outside_bid_depth: BTreeMap<i64, f64>,
+    pub outside_ask_depth: BTreeMap<i64, f64>,
     pub orders: HashMap<OrderId, L3Order>,
 }
 
 #[inline(always)]
 fn depth_below(depth: &Vec<f64>, start: i64, end: i64, roi_lb: i64, roi_ub: i64) -> i64 {
-    let start = (start.min(roi_ub) - roi_lb) as usize;
-    let end = (end.max(roi_lb) - roi_lb) as usize;
+    let start = (start.clamp(roi_lb, roi_ub + 1) - roi_lb) as usize;
+    let end = (end.clamp(roi_lb, roi_ub + 1) - roi_lb) as usize;
     for t in (end..start).rev() {
         if unsafe { *depth.get_unchecked(t) } > 0f64 {
             return t as i64 + roi_lb;
@@ -41,11 +95,11 @@ fn depth_below(depth: &Vec<f64>, start: i64, end: i64, roi_lb: i64, roi_ub: i64)
 
 #[inline(always)]
 fn depth_above(depth: &Vec<f64>, start: i64, end: i64, roi_lb: i64, roi_ub: i64) -> i64 {
-    let start = (start.max(roi_lb) - roi_lb) as usize;
-    let end = (end.min(roi_ub) - roi_lb) as usize;
+    let start = start.clamp(roi_lb - 1, roi_ub) - roi_lb;
+    let end = end.clamp(roi_lb - 1, roi_ub) - roi_lb;
     for t in (start + 1)..(end + 1) {
-        if unsafe { *depth.get_unchecked(t) } > 0f64 {
-            return t as i64 + roi_lb;
+        if unsafe { *depth.get_unchecked(t as usize) } > 0f64 {
+            return t + roi_lb;
         }
     }
     return INVALID_MAX;
@@ -77,16 +131,177 @@ impl ROIVectorMarketDepth {
             high_ask_tick: INVALID_MIN,
             roi_lb,
             roi_ub,
+            roi_adjustment: ROIAdjustment::Fixed,
+            out_of_roi_policy: OutOfROIPolicy::Drop,
+            outside_bid_depth: BTreeMap::new(),
+            outside_ask_depth: BTreeMap::new(),
             orders: HashMap::new(),
         }
     }
 
-    fn add(&mut self, order: L3Order) -> Result<(), BacktestError> {
-        let order = match self.orders.entry(order.order_id) {
-            Entry::Occupied(_) => return Err(BacktestError::OrderIdExist),
-            Entry::Vacant(entry) => entry.insert(order),
+    /// Sets how the range of interest is adjusted when the market depth is updated at a price
+    /// outside it. The default is [`ROIAdjustment::Fixed`].
+    pub fn roi_adjustment(self, roi_adjustment: ROIAdjustment) -> Self {
+        Self {
+            roi_adjustment,
+            ..self
+        }
+    }
+
+    /// Sets what happens to an L2 update at a price outside the range of interest. The default is
+    /// [`OutOfROIPolicy::Drop`].
+    pub fn out_of_roi_policy(self, out_of_roi_policy: OutOfROIPolicy) -> Self {
+        Self {
+            out_of_roi_policy,
+            ..self
+        }
+    }
+
+    /// Applies the [`OutOfROIPolicy`] to an L2 update at a price in ticks outside the range of
+    /// interest, and returns the previous quantity at the price.
+    fn update_outside(&mut self, side: Side, price_tick: i64, qty: f64, qty_lot: i64) -> f64 {
+        let depth = match self.out_of_roi_policy {
+            OutOfROIPolicy::Drop => return 0.0,
+            OutOfROIPolicy::Panic if qty_lot > 0 => {
+                panic!(
+                    "the price tick {price_tick} is outside the range of interest [{}, {}]",
+                    self.roi_lb, self.roi_ub
+                );
+            }
+            // A deletion doesn't lose any information.
+            OutOfROIPolicy::Panic => return 0.0,
+            OutOfROIPolicy::Clamp | OutOfROIPolicy::TrackBest => {
+                if side == Side::Buy {
+                    &mut self.outside_bid_depth
+                } else {
+                    &mut self.outside_ask_depth
+                }
+            }
+        };
+        if qty_lot > 0 {
+            depth.insert(price_tick, qty).unwrap_or(0.0)
+        } else {
+            depth.remove(&price_tick).unwrap_or(0.0)
+        }
+    }
+
+    /// Returns the best bid in ticks including the price levels outside the range of interest.
+    fn outside_best_bid_tick(&self) -> i64 {
+        let best_bid_tick = self.best_bid_tick.max(
+            *self
+                .outside_bid_depth
+                .keys()
+                .next_back()
+                .unwrap_or(&INVALID_MIN),
+        );
+        if self.out_of_roi_policy == OutOfROIPolicy::Clamp && best_bid_tick != INVALID_MIN {
+            best_bid_tick.clamp(self.roi_lb, self.roi_ub)
+        } else {
+            best_bid_tick
+        }
+    }
+
+    /// Returns the best ask in ticks including the price levels outside the range of interest.
+    fn outside_best_ask_tick(&self) -> i64 {
+        let best_ask_tick = self
+            .best_ask_tick
+            .min(*self.outside_ask_depth.keys().next().unwrap_or(&INVALID_MAX));
+        if self.out_of_roi_policy == OutOfROIPolicy::Clamp && best_ask_tick != INVALID_MAX {
+            best_ask_tick.clamp(self.roi_lb, self.roi_ub)
+        } else {
+            best_ask_tick
+        }
+    }
+
+    /// Returns the quantity of the price levels outside the range of interest that is accumulated
+    /// into the boundary of the range at the price in ticks, by [`OutOfROIPolicy::Clamp`].
+    fn clamped_qty(&self, depth: &BTreeMap<i64, f64>, price_tick: i64) -> f64 {
+        let mut qty = 0.0;
+        if price_tick == self.roi_lb {
+            qty += depth.range(..self.roi_lb).map(|(_, qty)| qty).sum::<f64>();
+        }
+        if price_tick == self.roi_ub {
+            qty += depth
+                .range(self.roi_ub + 1..)
+                .map(|(_, qty)| qty)
+                .sum::<f64>();
+        }
+        qty
+    }
+
+    /// Adjusts the range of interest to cover the price in ticks following the
+    /// [`ROIAdjustment`], and returns `true` if the price is within the range of interest.
+    fn cover(&mut self, price_tick: i64) -> bool {
+        if price_tick >= self.roi_lb && price_tick <= self.roi_ub {
+            return true;
+        }
+        let width = self.roi_ub - self.roi_lb;
+        let (roi_lb, roi_ub) = match self.roi_adjustment {
+            ROIAdjustment::Fixed => return false,
+            ROIAdjustment::Recenter => {
+                let roi_lb = price_tick - width / 2;
+                (roi_lb, roi_lb + width)
+            }
+            ROIAdjustment::Grow => {
+                if price_tick < self.roi_lb {
+                    (price_tick.min(self.roi_lb - width - 1), self.roi_ub)
+                } else {
+                    (self.roi_lb, price_tick.max(self.roi_ub + width + 1))
+                }
+            }
         };
-        if order.price_tick < self.roi_lb || order.price_tick > self.roi_ub {
+        self.set_roi(roi_lb, roi_ub);
+        true
+    }
+
+    fn set_roi(&mut self, roi_lb: i64, roi_ub: i64) {
+        let roi_range = (roi_ub + 1 - roi_lb) as usize;
+        let mut bid_depth = vec![0.0; roi_range];
+        let mut ask_depth = vec![0.0; roi_range];
+        for t in roi_lb.max(self.roi_lb)..(roi_ub.min(self.roi_ub) + 1) {
+            bid_depth[(t - roi_lb) as usize] = self.bid_depth[(t - self.roi_lb) as usize];
+            ask_depth[(t - roi_lb) as usize] = self.ask_depth[(t - self.roi_lb) as usize];
+        }
+        // The orders at the prices newly covered are aggregated into the depth.
+        for order in self.orders.values() {
+            let t = order.price_tick;
+            if t >= roi_lb && t <= roi_ub && (t < self.roi_lb || t > self.roi_ub) {
+                if order.side == Side::Buy {
+                    bid_depth[(t - roi_lb) as usize] += order.qty;
+                } else {
+                    ask_depth[(t - roi_lb) as usize] += order.qty;
+                }
+            }
+        }
+        self.bid_depth = bid_depth;
+        self.ask_depth = ask_depth;
+        self.roi_lb = roi_lb;
+        self.roi_ub = roi_ub;
+
+        // The best prices may have been dropped from the range of interest.
+        let ticks = |depth: &Vec<f64>| {
+            depth
+                .iter()
+                .enumerate()
+                .filter(|(_, qty)| **qty > 0.0)
+                .map(|(t, _)| t as i64 + roi_lb)
+                .collect::<Vec<_>>()
+        };
+        let bid_ticks = ticks(&self.bid_depth);
+        self.best_bid_tick = *bid_ticks.last().unwrap_or(&INVALID_MIN);
+        self.low_bid_tick = *bid_ticks.first().unwrap_or(&INVALID_MAX);
+        let ask_ticks = ticks(&self.ask_depth);
+        self.best_ask_tick = *ask_ticks.first().unwrap_or(&INVALID_MAX);
+        self.high_ask_tick = *ask_ticks.last().unwrap_or(&INVALID_MIN);
+    }
+
+    fn add(&mut self, order: L3Order) -> Result<(), BacktestError> {
+        if self.orders.contains_key(&order.order_id) {
+            return Err(BacktestError::OrderIdExist);
+        }
+        let covered = self.cover(order.price_tick);
+        let order = self.orders.entry(order.order_id).or_insert(order);
+        if !covered {
             // This is outside the range of interest.
             return Ok(());
         }
@@ -121,16 +336,22 @@ impl L2MarketDepth for ROIVectorMarketDepth {
     ) -> (i64, i64, i64, f64, f64, i64) {
         let price_tick = (price / self.tick_size).round() as i64;
         let qty_lot = (qty / self.lot_size).round() as i64;
-        let prev_best_bid_tick = self.best_bid_tick;
+        let prev_best_bid_tick = MarketDepth::best_bid_tick(self);
         let prev_qty;
 
-        if price_tick < self.roi_lb || price_tick > self.roi_ub {
+        let in_roi = if qty_lot > 0 {
+            self.cover(price_tick)
+        } else {
+            price_tick >= self.roi_lb && price_tick <= self.roi_ub
+        };
+        if !in_roi {
             // This is outside the range of interest.
+            let prev_qty = self.update_outside(Side::Buy, price_tick, qty, qty_lot);
             return (
                 price_tick,
                 prev_best_bid_tick,
-                self.best_bid_tick,
-                0.0,
+                MarketDepth::best_bid_tick(self),
+                prev_qty,
                 qty,
                 timestamp,
             );
@@ -173,7 +394,7 @@ impl L2MarketDepth for ROIVectorMarketDepth {
         (
             price_tick,
             prev_best_bid_tick,
-            self.best_bid_tick,
+            MarketDepth::best_bid_tick(self),
             prev_qty,
             qty,
             timestamp,
@@ -188,16 +409,22 @@ impl L2MarketDepth for ROIVectorMarketDepth {
     ) -> (i64, i64, i64, f64, f64, i64) {
         let price_tick = (price / self.tick_size).round() as i64;
         let qty_lot = (qty / self.lot_size).round() as i64;
-        let prev_best_ask_tick = self.best_ask_tick;
+        let prev_best_ask_tick = MarketDepth::best_ask_tick(self);
         let prev_qty;
 
-        if price_tick < self.roi_lb || price_tick > self.roi_ub {
+        let in_roi = if qty_lot > 0 {
+            self.cover(price_tick)
+        } else {
+            price_tick >= self.roi_lb && price_tick <= self.roi_ub
+        };
+        if !in_roi {
             // This is outside the range of interest.
+            let prev_qty = self.update_outside(Side::Sell, price_tick, qty, qty_lot);
             return (
                 price_tick,
                 prev_best_ask_tick,
-                self.best_ask_tick,
-                0.0,
+                MarketDepth::best_ask_tick(self),
+                prev_qty,
                 qty,
                 timestamp,
             );
@@ -240,7 +467,7 @@ impl L2MarketDepth for ROIVectorMarketDepth {
         (
             price_tick,
             prev_best_ask_tick,
-            self.best_ask_tick,
+            MarketDepth::best_ask_tick(self),
             prev_qty,
             qty,
             timestamp,
@@ -253,13 +480,15 @@ impl L2MarketDepth for ROIVectorMarketDepth {
             if self.best_bid_tick != INVALID_MIN {
                 for t in clear_upto.max(self.roi_lb)..(self.best_bid_tick + 1) {
                     unsafe {
-                        *self.bid_depth.get_unchecked_mut(t as usize) = 0.0;
+                        *self.bid_depth.get_unchecked_mut((t - self.roi_lb) as usize) = 0.0;
                     }
                 }
             }
+            self.outside_bid_depth
+                .retain(|&price_tick, _| price_tick < clear_upto);
             self.best_bid_tick = depth_below(
                 &self.bid_depth,
-                clear_upto - 1,
+                clear_upto,
                 self.low_bid_tick,
                 self.roi_lb,
                 self.roi_ub,
@@ -271,13 +500,15 @@ impl L2MarketDepth for ROIVectorMarketDepth {
             if self.best_ask_tick != INVALID_MAX {
                 for t in self.best_ask_tick..(clear_upto.min(self.roi_ub) + 1) {
                     unsafe {
-                        *self.ask_depth.get_unchecked_mut(t as usize) = 0.0;
+                        *self.ask_depth.get_unchecked_mut((t - self.roi_lb) as usize) = 0.0;
                     }
                 }
             }
+            self.outside_ask_depth
+                .retain(|&price_tick, _| price_tick > clear_upto);
             self.best_ask_tick = depth_above(
                 &self.ask_depth,
-                clear_upto + 1,
+                clear_upto,
                 self.high_ask_tick,
                 self.roi_lb,
                 self.roi_ub,
@@ -292,6 +523,8 @@ impl L2MarketDepth for ROIVectorMarketDepth {
             for qty in &mut self.ask_depth {
                 *qty = 0.0;
             }
+            self.outside_bid_depth.clear();
+            self.outside_ask_depth.clear();
             self.best_bid_tick = INVALID_MIN;
             self.best_ask_tick = INVALID_MAX;
             self.low_bid_tick = INVALID_MAX;
@@ -303,30 +536,40 @@ impl L2MarketDepth for ROIVectorMarketDepth {
 impl MarketDepth for ROIVectorMarketDepth {
     #[inline(always)]
     fn best_bid(&self) -> f64 {
-        if self.best_bid_tick == INVALID_MIN {
+        let best_bid_tick = self.best_bid_tick();
+        if best_bid_tick == INVALID_MIN {
             f64::NAN
         } else {
-            self.best_bid_tick as f64 * self.tick_size
+            best_bid_tick as f64 * self.tick_size
         }
     }
 
     #[inline(always)]
     fn best_ask(&self) -> f64 {
-        if self.best_ask_tick == INVALID_MAX {
+        let best_ask_tick = self.best_ask_tick();
+        if best_ask_tick == INVALID_MAX {
             f64::NAN
         } else {
-            self.best_ask_tick as f64 * self.tick_size
+            best_ask_tick as f64 * self.tick_size
         }
     }
 
     #[inline(always)]
     fn best_bid_tick(&self) -> i64 {
-        self.best_bid_tick
+        if self.outside_bid_depth.is_empty() {
+            self.best_bid_tick
+        } else {
+            self.outside_best_bid_tick()
+        }
     }
 
     #[inline(always)]
     fn best_ask_tick(&self) -> i64 {
-        self.best_ask_tick
+        if self.outside_ask_depth.is_empty() {
+            self.best_ask_tick
+        } else {
+            self.outside_best_ask_tick()
+        }
     }
 
     #[inline(always)]
@@ -343,12 +586,22 @@ impl MarketDepth for ROIVectorMarketDepth {
     fn bid_qty_at_tick(&self, price_tick: i64) -> f64 {
         if price_tick < self.roi_lb || price_tick > self.roi_ub {
             // This is outside the range of interest.
-            0.0
+            if self.out_of_roi_policy == OutOfROIPolicy::TrackBest {
+                *self.outside_bid_depth.get(&price_tick).unwrap_or(&0.0)
+            } else {
+                0.0
+            }
         } else {
-            unsafe {
+            let qty = unsafe {
                 *self
                     .bid_depth
                     .get_unchecked((price_tick - self.roi_lb) as usize)
+            };
+            if self.out_of_roi_policy == OutOfROIPolicy::Clamp && !self.outside_bid_depth.is_empty()
+            {
+                qty + self.clamped_qty(&self.outside_bid_depth, price_tick)
+            } else {
+                qty
             }
         }
     }
@@ -357,14 +610,92 @@ impl MarketDepth for ROIVectorMarketDepth {
     fn ask_qty_at_tick(&self, price_tick: i64) -> f64 {
         if price_tick < self.roi_lb || price_tick > self.roi_ub {
             // This is outside the range of interest.
-            0.0
+            if self.out_of_roi_policy == OutOfROIPolicy::TrackBest {
+                *self.outside_ask_depth.get(&price_tick).unwrap_or(&0.0)
+            } else {
+                0.0
+            }
         } else {
-            unsafe {
+            let qty = unsafe {
                 *self
                     .ask_depth
                     .get_unchecked((price_tick - self.roi_lb) as usize)
+            };
+            if self.out_of_roi_policy == OutOfROIPolicy::Clamp && !self.outside_ask_depth.is_empty()
+            {
+                qty + self.clamped_qty(&self.outside_ask_depth, price_tick)
+            } else {
+                qty
+            }
+        }
+    }
+
+    fn bid_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        // The price levels outside the range of interest are only listed by
+        // `OutOfROIPolicy::TrackBest`, while `OutOfROIPolicy::Clamp` lists them at the boundaries.
+        let track = self.out_of_roi_policy == OutOfROIPolicy::TrackBest;
+        let clamp = self.out_of_roi_policy == OutOfROIPolicy::Clamp;
+        let mut low = self.low_bid_tick.max(self.roi_lb);
+        let mut high = self.best_bid_tick.min(self.roi_ub);
+        if clamp && !self.outside_bid_depth.is_empty() {
+            high = self.outside_best_bid_tick();
+            low = low.min(high);
+            if self.outside_bid_depth.range(..self.roi_lb).next().is_some() {
+                low = self.roi_lb;
+            }
+        }
+        let outside = |(&t, &qty): (&i64, &f64)| (t, qty);
+        let above = self
+            .outside_bid_depth
+            .range(self.roi_ub + 1..)
+            .rev()
+            .filter(move |_| track)
+            .map(outside);
+        let below = self
+            .outside_bid_depth
+            .range(..self.roi_lb)
+            .rev()
+            .filter(move |_| track)
+            .map(outside);
+        let inside = (low..=high)
+            .rev()
+            .map(|t| (t, self.bid_qty_at_tick(t)))
+            .filter(|&(_, qty)| qty > 0.0);
+        above.chain(inside).chain(below)
+    }
+
+    fn ask_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        let track = self.out_of_roi_policy == OutOfROIPolicy::TrackBest;
+        let clamp = self.out_of_roi_policy == OutOfROIPolicy::Clamp;
+        let mut low = self.best_ask_tick.max(self.roi_lb);
+        let mut high = self.high_ask_tick.min(self.roi_ub);
+        if clamp && !self.outside_ask_depth.is_empty() {
+            low = self.outside_best_ask_tick();
+            high = high.max(low);
+            if self
+                .outside_ask_depth
+                .range(self.roi_ub + 1..)
+                .next()
+                .is_some()
+            {
+                high = self.roi_ub;
             }
         }
+        let outside = |(&t, &qty): (&i64, &f64)| (t, qty);
+        let below = self
+            .outside_ask_depth
+            .range(..self.roi_lb)
+            .filter(move |_| track)
+            .map(outside);
+        let above = self
+            .outside_ask_depth
+            .range(self.roi_ub + 1..)
+            .filter(move |_| track)
+            .map(outside);
+        let inside = (low..=high)
+            .map(|t| (t, self.ask_qty_at_tick(t)))
+            .filter(|&(_, qty)| qty > 0.0);
+        below.chain(inside).chain(above)
     }
 }
 
@@ -380,12 +711,20 @@ impl ApplySnapshot<Event> for ROIVectorMarketDepth {
         for qty in &mut self.ask_depth {
             *qty = 0.0;
         }
+        self.outside_bid_depth.clear();
+        self.outside_ask_depth.clear();
         for row_num in 0..data.len() {
             let price = data[row_num].px;
             let qty = data[row_num].qty;
 
             let price_tick = (price / self.tick_size).round() as i64;
             if price_tick < self.roi_lb || price_tick > self.roi_ub {
+                let qty_lot = (qty / self.lot_size).round() as i64;
+                if data[row_num].ev & BUY_EVENT == BUY_EVENT {
+                    self.update_outside(Side::Buy, price_tick, qty, qty_lot);
+                } else if data[row_num].ev & SELL_EVENT == SELL_EVENT {
+                    self.update_outside(Side::Sell, price_tick, qty, qty_lot);
+                }
                 continue;
             }
             if data[row_num].ev & BUY_EVENT == BUY_EVENT {
@@ -407,7 +746,25 @@ impl ApplySnapshot<Event> for ROIVectorMarketDepth {
     }
 
     fn snapshot(&self) -> Vec<Event> {
-        unimplemented!();
+        let bid_events = self
+            .bid_levels()
+            .map(|(px_tick, qty)| (BUY_EVENT, px_tick, qty));
+        let ask_events = self
+            .ask_levels()
+            .map(|(px_tick, qty)| (SELL_EVENT, px_tick, qty));
+        bid_events
+            .chain(ask_events)
+            .map(|(side_ev, px_tick, qty)| Event {
+                ev: EXCH_EVENT | LOCAL_EVENT | side_ev | DEPTH_SNAPSHOT_EVENT,
+                exch_ts: 0,
+                local_ts: 0,
+                px: px_tick as f64 * self.tick_size,
+                qty,
+                order_id: 0,
+                ival: 0,
+                fval: 0.0,
+            })
+            .collect()
     }
 }
 
@@ -695,7 +1052,16 @@ impl L3MarketDepth for ROIVectorMarketDepth {
 #[cfg(test)]
 mod tests {
     use crate::{
-        depth::{L3MarketDepth, MarketDepth, ROIVectorMarketDepth, INVALID_MAX, INVALID_MIN},
+        depth::{
+            L2MarketDepth,
+            L3MarketDepth,
+            MarketDepth,
+            OutOfROIPolicy,
+            ROIAdjustment,
+            ROIVectorMarketDepth,
+            INVALID_MAX,
+            INVALID_MIN,
+        },
         types::Side,
     };
 
@@ -708,6 +1074,144 @@ mod tests {
         }};
     }
 
+    #[test]
+    fn test_l2_recenter_roi() {
+        let mut depth =
+            ROIVectorMarketDepth::new(1.0, 1.0, 0.0, 10.0).roi_adjustment(ROIAdjustment::Recenter);
+        depth.update_bid_depth(5.0, 1.0, 0);
+        depth.update_ask_depth(7.0, 1.0, 0);
+
+        // A deletion outside the range of interest doesn't move it.
+        depth.update_bid_depth(20.0, 0.0, 0);
+        assert_eq!((depth.roi_lb, depth.roi_ub), (0, 10));
+
+        // The market trends up, and the range of interest follows it, dropping the old levels.
+        let (_, prev_best, best, _, _, _) = depth.update_bid_depth(14.0, 2.0, 0);
+        assert_eq!((prev_best, best), (5, 14));
+        assert_eq!((depth.roi_lb, depth.roi_ub), (9, 19));
+        assert_eq!(depth.bid_depth().len(), 11);
+        assert_eq!(depth.bid_qty_at_tick(5), 0.0);
+        assert_eq!(depth.bid_qty_at_tick(14), 2.0);
+        assert_eq!(depth.best_ask_tick(), INVALID_MAX);
+
+        depth.update_ask_depth(15.0, 3.0, 0);
+        assert_eq!(depth.best_ask_tick(), 15);
+        assert_eq!(depth.ask_qty_at_tick(15), 3.0);
+    }
+
+    #[test]
+    fn test_l2_grow_roi() {
+        let mut depth =
+            ROIVectorMarketDepth::new(1.0, 1.0, 0.0, 10.0).roi_adjustment(ROIAdjustment::Grow);
+        depth.update_bid_depth(5.0, 1.0, 0);
+        depth.update_ask_depth(7.0, 1.0, 0);
+
+        depth.update_ask_depth(14.0, 2.0, 0);
+        assert_eq!((depth.roi_lb, depth.roi_ub), (0, 21));
+        assert_eq!(depth.ask_depth().len(), 22);
+        assert_eq!(depth.bid_qty_at_tick(5), 1.0);
+        assert_eq!(depth.ask_qty_at_tick(14), 2.0);
+        assert_eq!(depth.best_ask_tick(), 7);
+
+        depth.update_bid_depth(-30.0, 1.0, 0);
+        assert_eq!((depth.roi_lb, depth.roi_ub), (-30, 21));
+        assert_eq!(depth.best_bid_tick(), 5);
+        assert_eq!(depth.bid_qty_at_tick(-30), 1.0);
+        assert_eq!(
+            depth.bid_levels().collect::<Vec<_>>(),
+            vec![(5, 1.0), (-30, 1.0)]
+        );
+        assert_eq!(
+            depth.ask_levels().collect::<Vec<_>>(),
+            vec![(7, 1.0), (14, 2.0)]
+        );
+    }
+
+    #[test]
+    fn test_l2_clamp_out_of_roi() {
+        let mut depth = ROIVectorMarketDepth::new(1.0, 1.0, 10.0, 20.0)
+            .out_of_roi_policy(OutOfROIPolicy::Clamp);
+        depth.update_bid_depth(12.0, 1.0, 0);
+        depth.update_bid_depth(8.0, 2.0, 0);
+        depth.update_bid_depth(5.0, 3.0, 0);
+        depth.update_ask_depth(25.0, 4.0, 0);
+        assert_eq!(depth.best_bid_tick(), 12);
+        assert_eq!(depth.bid_qty_at_tick(10), 5.0);
+        assert_eq!(depth.bid_qty_at_tick(8), 0.0);
+        assert_eq!(depth.best_ask_tick(), 20);
+        assert_eq!(depth.ask_qty_at_tick(20), 4.0);
+        assert_eq!(
+            depth.bid_levels().collect::<Vec<_>>(),
+            vec![(12, 1.0), (10, 5.0)]
+        );
+
+        let (_, prev_best, best, prev_qty, _, _) = depth.update_bid_depth(12.0, 0.0, 0);
+        assert_eq!((prev_best, best, prev_qty), (12, 10, 1.0));
+        let (_, _, _, prev_qty, _, _) = depth.update_bid_depth(5.0, 0.0, 0);
+        assert_eq!(prev_qty, 3.0);
+        assert_eq!(depth.bid_qty_at_tick(10), 2.0);
+
+        L2MarketDepth::clear_depth(&mut depth, Side::Sell, 30.0);
+        assert_eq!(depth.best_ask_tick(), INVALID_MAX);
+    }
+
+    #[test]
+    fn test_l2_track_best_out_of_roi() {
+        let mut depth = ROIVectorMarketDepth::new(1.0, 1.0, 10.0, 20.0)
+            .out_of_roi_policy(OutOfROIPolicy::TrackBest);
+        depth.update_bid_depth(12.0, 1.0, 0);
+        depth.update_ask_depth(13.0, 1.0, 0);
+        depth.update_ask_depth(13.0, 0.0, 0);
+        depth.update_ask_depth(22.0, 2.0, 0);
+        depth.update_ask_depth(25.0, 3.0, 0);
+        assert_eq!(depth.best_ask_tick(), 22);
+        assert_eq!(depth.best_ask(), 22.0);
+        assert_eq!(depth.ask_qty_at_tick(22), 2.0);
+        assert_eq!(
+            depth.ask_levels().collect::<Vec<_>>(),
+            vec![(22, 2.0), (25, 3.0)]
+        );
+
+        let (_, prev_best, best, _, _, _) = depth.update_ask_depth(22.0, 0.0, 0);
+        assert_eq!((prev_best, best), (22, 25));
+
+        // The default policy drops the update.
+        let mut depth = ROIVectorMarketDepth::new(1.0, 1.0, 10.0, 20.0);
+        depth.update_ask_depth(22.0, 2.0, 0);
+        assert_eq!(depth.best_ask_tick(), INVALID_MAX);
+        assert_eq!(depth.ask_qty_at_tick(22), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_l2_panic_out_of_roi() {
+        let mut depth = ROIVectorMarketDepth::new(1.0, 1.0, 10.0, 20.0)
+            .out_of_roi_policy(OutOfROIPolicy::Panic);
+        depth.update_bid_depth(12.0, 1.0, 0);
+        // A deletion outside the range of interest is fine.
+        depth.update_bid_depth(5.0, 0.0, 0);
+        depth.update_bid_depth(5.0, 1.0, 0);
+    }
+
+    #[test]
+    fn test_l3_recenter_roi() {
+        let mut depth =
+            ROIVectorMarketDepth::new(1.0, 1.0, 0.0, 10.0).roi_adjustment(ROIAdjustment::Recenter);
+        depth.add_buy_order(1, 5.0, 1.0, 0).unwrap();
+        depth.add_sell_order(2, 30.0, 1.0, 0).unwrap();
+        assert_eq!((depth.roi_lb, depth.roi_ub), (25, 35));
+        assert_eq!(depth.best_bid_tick(), INVALID_MIN);
+        assert_eq!(depth.best_ask_tick(), 30);
+
+        // The order dropped from the depth is aggregated again once the range covers its price.
+        let (prev_best, best) = depth.add_buy_order(3, 2.0, 1.0, 0).unwrap();
+        assert_eq!((depth.roi_lb, depth.roi_ub), (-3, 7));
+        assert_eq!((prev_best, best), (5, 5));
+        assert_eq!(depth.bid_qty_at_tick(5), 1.0);
+        assert_eq!(depth.bid_qty_at_tick(2), 1.0);
+        assert_eq!(depth.best_ask_tick(), INVALID_MAX);
+    }
+
     #[test]
     fn test_l3_add_delete_buy_order() {
         let lot_size = 0.001;
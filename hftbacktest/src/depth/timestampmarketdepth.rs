@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use super::{ApplySnapshot, L2MarketDepth, LevelTimestampDepth, MarketDepth, OrderCountDepth};
+use crate::{
+    backtest::reader::Data,
+    types::{Event, Side, BUY_EVENT, SELL_EVENT},
+};
+
+/// L2 market depth that wraps another L2 market depth and stores the timestamp of the last update
+/// of each price level, so that the age of the levels can be measured.
+///
+/// The timestamp is the one given with the depth update, which is the exchange timestamp of the
+/// depth event both in backtesting and in live trading. A level that is deleted or cleared loses
+/// its timestamp.
+///
+/// **Example**
+/// ```
+/// use hftbacktest::depth::{HashMapMarketDepth, TimestampMarketDepth};
+///
+/// let depth = TimestampMarketDepth::new(HashMapMarketDepth::new(0.01, 0.001));
+/// ```
+pub struct TimestampMarketDepth<MD> {
+    depth: MD,
+    bid_timestamp: HashMap<i64, i64>,
+    ask_timestamp: HashMap<i64, i64>,
+}
+
+impl<MD> TimestampMarketDepth<MD> {
+    /// Constructs an instance of `TimestampMarketDepth` that wraps the market depth.
+    pub fn new(depth: MD) -> Self {
+        Self {
+            depth,
+            bid_timestamp: HashMap::new(),
+            ask_timestamp: HashMap::new(),
+        }
+    }
+}
+
+#[inline(always)]
+fn set_timestamp(timestamps: &mut HashMap<i64, i64>, price_tick: i64, qty: f64, timestamp: i64) {
+    if qty > 0.0 {
+        timestamps.insert(price_tick, timestamp);
+    } else {
+        timestamps.remove(&price_tick);
+    }
+}
+
+impl<MD> L2MarketDepth for TimestampMarketDepth<MD>
+where
+    MD: MarketDepth + L2MarketDepth,
+{
+    fn update_bid_depth(
+        &mut self,
+        price: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        let result = self.depth.update_bid_depth(price, qty, timestamp);
+        set_timestamp(
+            &mut self.bid_timestamp,
+            result.0,
+            self.depth.bid_qty_at_tick(result.0),
+            timestamp,
+        );
+        result
+    }
+
+    fn update_ask_depth(
+        &mut self,
+        price: f64,
+        qty: f64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        let result = self.depth.update_ask_depth(price, qty, timestamp);
+        set_timestamp(
+            &mut self.ask_timestamp,
+            result.0,
+            self.depth.ask_qty_at_tick(result.0),
+            timestamp,
+        );
+        result
+    }
+
+    fn update_bid_depth_with_count(
+        &mut self,
+        price: f64,
+        qty: f64,
+        count: i64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        let result = self
+            .depth
+            .update_bid_depth_with_count(price, qty, count, timestamp);
+        set_timestamp(
+            &mut self.bid_timestamp,
+            result.0,
+            self.depth.bid_qty_at_tick(result.0),
+            timestamp,
+        );
+        result
+    }
+
+    fn update_ask_depth_with_count(
+        &mut self,
+        price: f64,
+        qty: f64,
+        count: i64,
+        timestamp: i64,
+    ) -> (i64, i64, i64, f64, f64, i64) {
+        let result = self
+            .depth
+            .update_ask_depth_with_count(price, qty, count, timestamp);
+        set_timestamp(
+            &mut self.ask_timestamp,
+            result.0,
+            self.depth.ask_qty_at_tick(result.0),
+            timestamp,
+        );
+        result
+    }
+
+    fn clear_depth(&mut self, side: Side, clear_upto_price: f64) {
+        self.depth.clear_depth(side, clear_upto_price);
+        let depth = &self.depth;
+        self.bid_timestamp
+            .retain(|&price_tick, _| depth.bid_qty_at_tick(price_tick) > 0.0);
+        self.ask_timestamp
+            .retain(|&price_tick, _| depth.ask_qty_at_tick(price_tick) > 0.0);
+    }
+}
+
+impl<MD> MarketDepth for TimestampMarketDepth<MD>
+where
+    MD: MarketDepth,
+{
+    #[inline(always)]
+    fn best_bid(&self) -> f64 {
+        self.depth.best_bid()
+    }
+
+    #[inline(always)]
+    fn best_ask(&self) -> f64 {
+        self.depth.best_ask()
+    }
+
+    #[inline(always)]
+    fn best_bid_tick(&self) -> i64 {
+        self.depth.best_bid_tick()
+    }
+
+    #[inline(always)]
+    fn best_ask_tick(&self) -> i64 {
+        self.depth.best_ask_tick()
+    }
+
+    #[inline(always)]
+    fn tick_size(&self) -> f64 {
+        self.depth.tick_size()
+    }
+
+    #[inline(always)]
+    fn lot_size(&self) -> f64 {
+        self.depth.lot_size()
+    }
+
+    #[inline(always)]
+    fn bid_qty_at_tick(&self, price_tick: i64) -> f64 {
+        self.depth.bid_qty_at_tick(price_tick)
+    }
+
+    #[inline(always)]
+    fn ask_qty_at_tick(&self, price_tick: i64) -> f64 {
+        self.depth.ask_qty_at_tick(price_tick)
+    }
+
+    fn bid_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.depth.bid_levels()
+    }
+
+    fn ask_levels(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.depth.ask_levels()
+    }
+}
+
+impl<MD> LevelTimestampDepth for TimestampMarketDepth<MD>
+where
+    MD: MarketDepth,
+{
+    #[inline(always)]
+    fn bid_timestamp_at_tick(&self, price_tick: i64) -> Option<i64> {
+        self.bid_timestamp.get(&price_tick).copied()
+    }
+
+    #[inline(always)]
+    fn ask_timestamp_at_tick(&self, price_tick: i64) -> Option<i64> {
+        self.ask_timestamp.get(&price_tick).copied()
+    }
+}
+
+impl<MD> OrderCountDepth for TimestampMarketDepth<MD>
+where
+    MD: OrderCountDepth,
+{
+    #[inline(always)]
+    fn bid_count_at_tick(&self, price_tick: i64) -> i64 {
+        self.depth.bid_count_at_tick(price_tick)
+    }
+
+    #[inline(always)]
+    fn ask_count_at_tick(&self, price_tick: i64) -> i64 {
+        self.depth.ask_count_at_tick(price_tick)
+    }
+}
+
+impl<MD> ApplySnapshot<Event> for TimestampMarketDepth<MD>
+where
+    MD: MarketDepth + ApplySnapshot<Event>,
+{
+    fn apply_snapshot(&mut self, data: &Data<Event>) {
+        self.depth.apply_snapshot(data);
+        self.bid_timestamp.clear();
+        self.ask_timestamp.clear();
+        let tick_size = self.depth.tick_size();
+        for row_num in 0..data.len() {
+            let price_tick = (data[row_num].px / tick_size).round() as i64;
+            if data[row_num].ev & BUY_EVENT == BUY_EVENT {
+                set_timestamp(
+                    &mut self.bid_timestamp,
+                    price_tick,
+                    data[row_num].qty,
+                    data[row_num].exch_ts,
+                );
+            } else if data[row_num].ev & SELL_EVENT == SELL_EVENT {
+                set_timestamp(
+                    &mut self.ask_timestamp,
+                    price_tick,
+                    data[row_num].qty,
+                    data[row_num].exch_ts,
+                );
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Vec<Event> {
+        self.depth.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        depth::{
+            CountMarketDepth,
+            HashMapMarketDepth,
+            L2MarketDepth,
+            LevelTimestampDepth,
+            OrderCountDepth,
+            TimestampMarketDepth,
+        },
+        types::Side,
+    };
+
+    #[test]
+    fn test_timestamp_market_depth() {
+        let mut depth = TimestampMarketDepth::new(HashMapMarketDepth::new(0.5, 1.0));
+        depth.update_bid_depth(100.0, 2.0, 10);
+        depth.update_bid_depth(99.5, 1.0, 20);
+        depth.update_ask_depth(100.5, 3.0, 30);
+        assert_eq!(depth.bid_timestamp_at_tick(200), Some(10));
+        assert_eq!(depth.bid_timestamp_at_tick(199), Some(20));
+        assert_eq!(depth.ask_timestamp_at_tick(201), Some(30));
+        assert_eq!(depth.ask_timestamp_at_tick(202), None);
+
+        depth.update_bid_depth(100.0, 5.0, 40);
+        assert_eq!(depth.bid_timestamp_at_tick(200), Some(40));
+
+        depth.update_bid_depth(99.5, 0.0, 50);
+        assert_eq!(depth.bid_timestamp_at_tick(199), None);
+
+        depth.clear_depth(Side::Sell, 100.5);
+        assert_eq!(depth.ask_timestamp_at_tick(201), None);
+        assert_eq!(depth.bid_timestamp_at_tick(200), Some(40));
+    }
+
+    #[test]
+    fn test_timestamp_count_market_depth() {
+        let mut depth = TimestampMarketDepth::new(CountMarketDepth::new(0.5, 1.0));
+        depth.update_ask_depth_with_count(100.5, 3.0, 2, 30);
+        assert_eq!(depth.ask_timestamp_at_tick(201), Some(30));
+        assert_eq!(depth.ask_count_at_tick(201), 2);
+    }
+}
@@ -19,12 +19,24 @@
 //!
 //! ## Feature flags
 //!
-//! Currently, `default` enables `backtest`, `live`, `binancefutures`, `bybit` features.
+//! Currently, `default` enables `backtest`, `live`, `binancefutures`, `bitget`, `bybit`, `deribit`, `okx` features.
 //!
 //! - `backtest`: Enables backtesting features.
 //! - `live`: Enables a live trading bot.
-//! - `binancefutures`: Enables Binance Futures USDM connector for a live trading bot.
+//! - `binancefutures`: Enables Binance Futures USDM and COIN-M connector for a live trading bot.
+//! - `bitget`: Enables Bitget V2 futures connector for a live trading bot.
 //! - `bybit`: Enables Bybit linear USDM futures connector for a live trading bot.
+//! - `databentolive`: Enables Databento live market data connector, which is data-only, for a live
+//!   trading bot.
+//! - `deribit`: Enables Deribit connector for a live trading bot.
+//! - `okx`: Enables OKX V5 connector for a live trading bot.
+//! - `parquet`: Enables reading feed data from Parquet files.
+//! - `arrow`: Enables reading feed data from Arrow IPC (Feather V2) files.
+//! - `mmap`: Memory-maps `.npy` feed data files instead of loading them into memory.
+//! - `remote`: Enables downloading feed data from HTTP(S) URLs and S3 URIs.
+//! - `convert`: Enables converters from raw exchange feed data into HftBacktest's format.
+//! - `databento`: Enables the Databento DBN data converter.
+//! - `pcap`: Enables deriving order and feed latency from packet captures.
 //! - `unstable_l3`: Enables Level3 Market-By-Order backtesting.
 //! - `unstable_fuse`: Enables the market depth fusion feature, which aggregates different market
 //!                    depth streams to provide the finest granularity and the most frequent,
@@ -35,6 +47,10 @@
 #[cfg(any(feature = "backtest", doc))]
 pub mod backtest;
 
+/// Provides feed data utilities.
+#[cfg(feature = "backtest")]
+pub mod data;
+
 /// Provides exchange connectors.
 #[cfg(feature = "live")]
 pub mod connector;
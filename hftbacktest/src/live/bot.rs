@@ -19,6 +19,7 @@ use crate::{
     live::Asset,
     prelude::{OrderId, OrderRequest, WaitOrderResponse},
     types::{
+        BatchRequest,
         Bot,
         BuildError,
         Event,
@@ -28,13 +29,18 @@ use crate::{
         OrdType,
         Order,
         Request,
+        Roll,
         Side,
         StateValues,
         Status,
         TimeInForce,
+        TriggerBy,
+        LOCAL_ASK_DEPTH_CLEAR_EVENT,
         LOCAL_ASK_DEPTH_EVENT,
+        LOCAL_BID_DEPTH_CLEAR_EVENT,
         LOCAL_BID_DEPTH_EVENT,
         LOCAL_BUY_TRADE_EVENT,
+        LOCAL_DEPTH_CLEAR_EVENT,
         LOCAL_SELL_TRADE_EVENT,
     },
 };
@@ -49,6 +55,8 @@ pub enum BotError {
     OrderNotFound,
     #[error("order status is invalid")]
     InvalidOrderStatus,
+    #[error("order entry is not supported by the connector")]
+    OrderEntryNotSupported,
     #[error("{0}")]
     Custom(String),
 }
@@ -96,6 +104,18 @@ async fn thread_main(
                             }
                         }
                     }
+                    Some(Request::CancelAll { asset_no }) => {
+                        if let Some((connector_name, _)) = mapping.get(asset_no) {
+                            let conn_ = conns.get_mut(connector_name).unwrap();
+                            if let Err(error) = conn_.cancel_all(asset_no, ev_tx.clone()) {
+                                error!(
+                                    %connector_name,
+                                    ?error,
+                                    "Unable to cancel all open orders due to an internal error in the connector."
+                                );
+                            }
+                        }
+                    }
                     None => {
                         debug!("req_rx channel is closed.");
                         break;
@@ -209,6 +229,7 @@ impl<MD> LiveBotBuilder<MD> {
     pub fn build(self) -> Result<LiveBot<MD>, BuildError> {
         let mut dup = HashSet::new();
         let mut conns = self.conns;
+        let mut order_entry = Vec::with_capacity(self.assets.len());
         for (asset_no, (name, asset_info)) in self.assets.iter().enumerate() {
             if !dup.insert(format!("{}/{}", name, asset_info.symbol)) {
                 Err(BuildError::Duplicate(
@@ -225,6 +246,7 @@ impl<MD> LiveBotBuilder<MD> {
                 asset_info.tick_size,
                 asset_info.lot_size,
             )?;
+            order_entry.push(conn.order_entry());
         }
 
         let (ev_tx, ev_rx) = channel();
@@ -247,6 +269,7 @@ impl<MD> LiveBotBuilder<MD> {
             .map(|_| Vec::with_capacity(self.trade_len))
             .collect();
         let last_feed_latency = self.assets.iter().map(|_| None).collect();
+        let aux = self.assets.iter().map(|_| HashMap::new()).collect();
         let last_order_latency = self.assets.iter().map(|_| None).collect();
 
         Ok(LiveBot {
@@ -259,12 +282,14 @@ impl<MD> LiveBotBuilder<MD> {
             state,
             conns: Some(conns),
             assets: self.assets,
+            order_entry,
             trade,
             trade_len: self.trade_len,
             error_handler: self.error_handler,
             order_hook: self.order_hook,
             last_feed_latency,
             last_order_latency,
+            aux,
         })
     }
 }
@@ -294,11 +319,13 @@ pub struct LiveBot<MD> {
     trade_len: usize,
     conns: Option<HashMap<String, Box<dyn Connector + Send + 'static>>>,
     assets: Vec<(String, Asset)>,
+    order_entry: Vec<bool>,
     error_handler: Option<ErrorHandler>,
     order_hook: Option<OrderRecvHook>,
     last_feed_latency: Vec<Option<(i64, i64)>>,
     last_order_latency: Vec<Option<(i64, i64, i64)>>,
     state: Vec<StateValues>,
+    aux: Vec<HashMap<u64, Event>>,
 }
 
 impl<MD> LiveBot<MD>
@@ -330,6 +357,14 @@ where
         Ok(())
     }
 
+    fn check_order_entry(&self, asset_no: usize) -> Result<(), BotError> {
+        match self.order_entry.get(asset_no) {
+            Some(true) => Ok(()),
+            Some(false) => Err(BotError::OrderEntryNotSupported),
+            None => Err(BotError::AssetNotFound),
+        }
+    }
+
     fn elapse_<const WAIT_NEXT_FEED: bool>(
         &mut self,
         duration: i64,
@@ -350,6 +385,16 @@ where
                         } else if event.is(LOCAL_ASK_DEPTH_EVENT) {
                             let depth = unsafe { self.depth.get_unchecked_mut(asset_no) };
                             depth.update_ask_depth(event.px, event.qty, event.exch_ts);
+                        } else if event.is(LOCAL_BID_DEPTH_CLEAR_EVENT) {
+                            let depth = unsafe { self.depth.get_unchecked_mut(asset_no) };
+                            depth.clear_depth(Side::Buy, event.px);
+                        } else if event.is(LOCAL_ASK_DEPTH_CLEAR_EVENT) {
+                            let depth = unsafe { self.depth.get_unchecked_mut(asset_no) };
+                            depth.clear_depth(Side::Sell, event.px);
+                        } else if event.is(LOCAL_DEPTH_CLEAR_EVENT) {
+                            // Precedes a snapshot that replaces the entire market depth.
+                            let depth = unsafe { self.depth.get_unchecked_mut(asset_no) };
+                            depth.clear_depth(Side::None, 0.0);
                         } else if event.is(LOCAL_BUY_TRADE_EVENT)
                             || event.is(LOCAL_SELL_TRADE_EVENT)
                         {
@@ -357,6 +402,9 @@ where
                                 let trade = unsafe { self.trade.get_unchecked_mut(asset_no) };
                                 trade.push(event);
                             }
+                        } else if event.is_aux() {
+                            let aux = unsafe { self.aux.get_unchecked_mut(asset_no) };
+                            aux.insert(event.ev & 0xff, event);
                         }
                     }
                     if WAIT_NEXT_FEED {
@@ -422,6 +470,35 @@ where
                 Ok(LiveEvent::Position { asset_no, qty }) => {
                     unsafe { self.state.get_unchecked_mut(asset_no) }.position = qty;
                 }
+                Ok(LiveEvent::PositionLeg {
+                    asset_no,
+                    side,
+                    qty,
+                    entry_price,
+                }) => {
+                    let state = unsafe { self.state.get_unchecked_mut(asset_no) };
+                    match side {
+                        Side::Buy => {
+                            state.long_position = qty;
+                            state.long_entry_price = entry_price;
+                        }
+                        _ => {
+                            state.short_position = qty;
+                            state.short_entry_price = entry_price;
+                        }
+                    }
+                    state.position = state.long_position - state.short_position;
+                }
+                Ok(LiveEvent::Fill {
+                    asset_no,
+                    qty,
+                    amount,
+                }) => {
+                    let state = unsafe { self.state.get_unchecked_mut(asset_no) };
+                    state.num_trades += 1;
+                    state.trading_volume += qty;
+                    state.trading_value += amount;
+                }
                 Ok(LiveEvent::Error(error)) => {
                     if let Some(handler) = self.error_handler.as_mut() {
                         handler(error)?;
@@ -450,9 +527,14 @@ where
         qty: f64,
         time_in_force: TimeInForce,
         order_type: OrdType,
+        trigger_price: f64,
+        expire_timestamp: i64,
+        reduce_only: bool,
+        trigger_by: TriggerBy,
         wait: bool,
         side: Side,
     ) -> Result<bool, BotError> {
+        self.check_order_entry(asset_no)?;
         let orders = self
             .orders
             .get_mut(asset_no)
@@ -479,6 +561,10 @@ where
             // Invalid information
             q: Box::new(()),
             maker: false,
+            trigger_price_tick: (trigger_price / tick_size).round() as i64,
+            expire_timestamp,
+            reduce_only,
+            trigger_by,
         };
         let order_id = order.order_id;
         orders.insert(order_id, order.clone());
@@ -567,6 +653,10 @@ where
             qty,
             time_in_force,
             order_type,
+            0.0,
+            0,
+            false,
+            TriggerBy::Default,
             wait,
             Side::Buy,
         )
@@ -590,6 +680,10 @@ where
             qty,
             time_in_force,
             order_type,
+            0.0,
+            0,
+            false,
+            TriggerBy::Default,
             wait,
             Side::Sell,
         )
@@ -608,11 +702,40 @@ where
             order.qty,
             order.time_in_force,
             order.order_type,
+            order.trigger_price,
+            order.expire_timestamp,
+            order.reduce_only,
+            order.trigger_by,
             wait,
             order.side,
         )
     }
 
+    fn submit_batch(
+        &mut self,
+        asset_no: usize,
+        requests: Vec<BatchRequest>,
+        wait: bool,
+    ) -> Result<bool, Self::Error> {
+        // The connectors don't support batch orders yet, so the requests are sent individually.
+        let last_order_id = requests.last().map(|request| request.order_id());
+        for request in requests {
+            match request {
+                BatchRequest::Submit(order) => {
+                    Bot::<MD>::submit_order(self, asset_no, order, false)?;
+                }
+                BatchRequest::Cancel(order_id) => {
+                    Bot::<MD>::cancel(self, asset_no, order_id, false)?;
+                }
+            }
+        }
+        if let (true, Some(order_id)) = (wait, last_order_id) {
+            // fixme: timeout should be specified by the argument.
+            return self.wait_order_response(asset_no, order_id, 60_000_000_000);
+        }
+        Ok(true)
+    }
+
     #[inline]
     fn cancel(
         &mut self,
@@ -620,6 +743,7 @@ where
         order_id: OrderId,
         wait: bool,
     ) -> Result<bool, Self::Error> {
+        self.check_order_entry(asset_no)?;
         let orders = self
             .orders
             .get_mut(asset_no)
@@ -643,6 +767,45 @@ where
         Ok(true)
     }
 
+    #[inline]
+    fn cancel_all(&mut self, asset_no: usize, wait: bool) -> Result<bool, Self::Error> {
+        self.check_order_entry(asset_no)?;
+        self.req_tx.send(Request::CancelAll { asset_no }).unwrap();
+        if wait {
+            // fixme: timeout should be specified by the argument.
+            return self.elapse_::<false>(60_000_000_000, WaitOrderResponse::Any);
+        }
+        Ok(true)
+    }
+
+    #[inline]
+    fn modify(
+        &mut self,
+        _asset_no: usize,
+        _order_id: OrderId,
+        _price: f64,
+        _qty: f64,
+        _wait: bool,
+    ) -> Result<bool, Self::Error> {
+        // None of the connectors supports in-place order modification yet.
+        Err(BotError::Custom(
+            "order modification is not supported in live trading".to_string(),
+        ))
+    }
+
+    #[inline]
+    fn query_order(
+        &mut self,
+        _asset_no: usize,
+        _order_id: OrderId,
+        _wait: bool,
+    ) -> Result<bool, Self::Error> {
+        // None of the connectors supports order queries yet.
+        Err(BotError::Custom(
+            "order query is not supported in live trading".to_string(),
+        ))
+    }
+
     #[inline]
     fn clear_inactive_orders(&mut self, asset_no: Option<usize>) {
         match asset_no {
@@ -703,4 +866,19 @@ where
     fn order_latency(&self, asset_no: usize) -> Option<(i64, i64, i64)> {
         *self.last_order_latency.get(asset_no).unwrap()
     }
+
+    fn feed_gap_count(&self, _asset_no: usize) -> usize {
+        // In live trading, feed interruptions are reported through the error handler as
+        // `ErrorKind::ConnectionInterrupted`.
+        0
+    }
+
+    fn aux_event(&self, asset_no: usize, kind: u64) -> Option<&Event> {
+        self.aux.get(asset_no).unwrap().get(&kind)
+    }
+
+    fn rolls(&self, _asset_no: usize) -> &[Roll] {
+        // Contract rolls only apply to continuous futures constructed from historical data.
+        &[]
+    }
 }
@@ -60,10 +60,38 @@ pub enum ErrorKind {
 /// Events occurring in a live bot sent by a [`Connector`](`crate::connector::Connector`).
 #[derive(Clone, Debug)]
 pub enum LiveEvent {
-    FeedBatch { asset_no: usize, events: Vec<Event> },
-    Feed { asset_no: usize, event: Event },
-    Order { asset_no: usize, order: Order },
-    Position { asset_no: usize, qty: f64 },
+    FeedBatch {
+        asset_no: usize,
+        events: Vec<Event>,
+    },
+    Feed {
+        asset_no: usize,
+        event: Event,
+    },
+    Order {
+        asset_no: usize,
+        order: Order,
+    },
+    Position {
+        asset_no: usize,
+        qty: f64,
+    },
+    /// A leg of the position in [`PositionMode::Hedge`]. `side` is [`Side::Buy`] for the long leg
+    /// and [`Side::Sell`] for the short leg, and `qty` is positive.
+    PositionLeg {
+        asset_no: usize,
+        side: Side,
+        qty: f64,
+        entry_price: f64,
+    },
+    /// A fill of an order, which accumulates into the trading statistics of the
+    /// [`StateValues`]. `amount` is the value amount of the fill according to the asset type, such
+    /// as in the base coin for an inverse contract.
+    Fill {
+        asset_no: usize,
+        qty: f64,
+        amount: f64,
+    },
     Error(LiveError),
 }
 
@@ -77,6 +105,20 @@ pub const BUY_EVENT: u64 = 1 << 29;
 /// event, it means that the trade initiator is a seller.
 pub const SELL_EVENT: u64 = 1 << 28;
 
+/// Indicates that the trade is a liquidation of a position by the venue, when combined with a
+/// trade event. A liquidation print doesn't reflect the flow that resting orders compete for, so it
+/// can be excluded from the fill simulation while still being delivered to the strategy. See
+/// [`AssetBuilder::excluded_trades`](crate::backtest::AssetBuilder::excluded_trades).
+pub const LIQUIDATION_TRADE_FLAG: u64 = 1 << 27;
+
+/// Indicates that the trade is a block trade negotiated off the order book, when combined with a
+/// trade event.
+pub const BLOCK_TRADE_FLAG: u64 = 1 << 26;
+
+/// Indicates that the trade is executed against a retail price improvement (RPI) order, which is
+/// not visible in the order book, when combined with a trade event.
+pub const RPI_TRADE_FLAG: u64 = 1 << 25;
+
 /// Indicates that the market depth is changed.
 pub const DEPTH_EVENT: u64 = 1;
 
@@ -92,6 +134,27 @@ pub const DEPTH_SNAPSHOT_EVENT: u64 = 4;
 /// Indicates that the best bid and best ask update event is received.
 pub const DEPTH_BBO_EVENT: u64 = 5;
 
+/// Indicates that a continuous futures asset rolls from one contract to the next. `order_id` is the
+/// index of the contract rolled into, and `px` is the price adjustment at the roll. See [`Roll`].
+pub const ROLL_EVENT: u64 = 6;
+
+/// Indicates that a funding payment of a perpetual contract is settled against the open position.
+/// `fval` is the funding rate, and `px` is the price at which the payment is calculated, such as the
+/// mark price, or `0` to use the mid-price. A positive funding rate means that long positions pay
+/// short positions. See [`FundingSchedule`](crate::backtest::proc::FundingSchedule) for funding
+/// settled on a fixed schedule instead.
+pub const FUNDING_EVENT: u64 = 7;
+
+/// Indicates that the price limits of the asset are updated. `px` is the lower limit price, and
+/// `fval` is the upper limit price; `0` means no limit on that side. See
+/// [`PriceBand`](crate::backtest::proc::PriceBand).
+pub const PRICE_BAND_EVENT: u64 = 8;
+
+/// Indicates a transition of the opening or closing auction. `ival` is `1` when the call period
+/// begins, and `0` at the uncross, where `px` is the uncross price published by the venue, or `0`
+/// to have the exchange calculate it. See [`Auction`](crate::backtest::proc::Auction).
+pub const AUCTION_EVENT: u64 = 9;
+
 /// Indicates that an order has been added to the order book.
 pub const ADD_ORDER_EVENT: u64 = 10;
 
@@ -104,6 +167,30 @@ pub const MODIFY_ORDER_EVENT: u64 = 12;
 /// Indicates that an order in the order book has been filled.
 pub const FILL_EVENT: u64 = 13;
 
+/// Indicates that trading halts or resumes. `ival` is `1` when trading halts, and `0` when it
+/// resumes. See [`TradingHalt`](crate::backtest::proc::TradingHalt).
+pub const HALT_EVENT: u64 = 14;
+
+/// Indicates an OHLCV bar that closes at the timestamp, for the bar-driven backtesting. A bar
+/// consists of a pair of consecutive events: the one with [`BUY_EVENT`] carries the high price in
+/// `px` and the open price in `fval`, and the following one with [`SELL_EVENT`] carries the low price
+/// in `px` and the close price in `fval`. Both carry the volume in `qty`. See
+/// [`BarExchange`](crate::backtest::proc::BarExchange).
+pub const BAR_EVENT: u64 = 15;
+
+/// Indicates the settlement price of a dated contract, which is `px`, at which the position is
+/// settled upon the contract expiry. See
+/// [`ContractExpiry`](crate::backtest::proc::ContractExpiry).
+pub const SETTLEMENT_EVENT: u64 = 16;
+
+/// Indicates the first of the user-defined auxiliary event kinds, which carry side-channel signals
+/// such as funding rates, open interest, index prices, and news flags in the feed data. Event kinds
+/// from `AUX_EVENT` to `0xff` are not interpreted by the backtester, and the payload can be carried
+/// in any of `px`, `qty`, `order_id`, `ival`, and `fval`. The latest event of each kind is
+/// delivered to the local processor at its local timestamp, like other feed events, and is
+/// available through [`Bot::aux_event`].
+pub const AUX_EVENT: u64 = 128;
+
 /// Indicates that it is a valid event to be handled by the exchange processor at the exchange
 /// timestamp.
 pub const EXCH_EVENT: u64 = 1 << 31;
@@ -150,6 +237,15 @@ pub const LOCAL_BUY_TRADE_EVENT: u64 = LOCAL_TRADE_EVENT | BUY_EVENT;
 /// Represents a combination of [`LOCAL_TRADE_EVENT`] and [`SELL_EVENT`].
 pub const LOCAL_SELL_TRADE_EVENT: u64 = LOCAL_TRADE_EVENT | SELL_EVENT;
 
+/// Represents a combination of [`ROLL_EVENT`], and [`LOCAL_EVENT`].
+pub const LOCAL_ROLL_EVENT: u64 = ROLL_EVENT | LOCAL_EVENT;
+
+/// Represents a combination of [`FUNDING_EVENT`], and [`LOCAL_EVENT`].
+pub const LOCAL_FUNDING_EVENT: u64 = FUNDING_EVENT | LOCAL_EVENT;
+
+/// Represents a combination of [`BAR_EVENT`], and [`LOCAL_EVENT`].
+pub const LOCAL_BAR_EVENT: u64 = BAR_EVENT | LOCAL_EVENT;
+
 /// Represents a combination of [`DEPTH_EVENT`], [`BUY_EVENT`], and [`EXCH_EVENT`].
 pub const EXCH_BID_DEPTH_EVENT: u64 = DEPTH_EVENT | BUY_EVENT | EXCH_EVENT;
 
@@ -183,6 +279,24 @@ pub const EXCH_BUY_TRADE_EVENT: u64 = EXCH_TRADE_EVENT | BUY_EVENT;
 /// Represents a combination of [`EXCH_TRADE_EVENT`] and [`SELL_EVENT`].
 pub const EXCH_SELL_TRADE_EVENT: u64 = EXCH_TRADE_EVENT | SELL_EVENT;
 
+/// Represents a combination of [`FUNDING_EVENT`], and [`EXCH_EVENT`].
+pub const EXCH_FUNDING_EVENT: u64 = FUNDING_EVENT | EXCH_EVENT;
+
+/// Represents a combination of [`PRICE_BAND_EVENT`], and [`EXCH_EVENT`].
+pub const EXCH_PRICE_BAND_EVENT: u64 = PRICE_BAND_EVENT | EXCH_EVENT;
+
+/// Represents a combination of [`AUCTION_EVENT`], and [`EXCH_EVENT`].
+pub const EXCH_AUCTION_EVENT: u64 = AUCTION_EVENT | EXCH_EVENT;
+
+/// Represents a combination of [`HALT_EVENT`], and [`EXCH_EVENT`].
+pub const EXCH_HALT_EVENT: u64 = HALT_EVENT | EXCH_EVENT;
+
+/// Represents a combination of [`BAR_EVENT`], and [`EXCH_EVENT`].
+pub const EXCH_BAR_EVENT: u64 = BAR_EVENT | EXCH_EVENT;
+
+/// Represents a combination of [`SETTLEMENT_EVENT`], and [`EXCH_EVENT`].
+pub const EXCH_SETTLEMENT_EVENT: u64 = SETTLEMENT_EVENT | EXCH_EVENT;
+
 /// Represents a combination of [`LOCAL_EVENT`] and [`ADD_ORDER_EVENT`].
 pub const LOCAL_ADD_ORDER_EVENT: u64 = LOCAL_EVENT | ADD_ORDER_EVENT;
 
@@ -256,6 +370,12 @@ pub struct Event {
 unsafe impl POD for Event {}
 
 impl Event {
+    /// Checks if this `Event` is a user-defined auxiliary event. See [`AUX_EVENT`].
+    #[inline(always)]
+    pub fn is_aux(&self) -> bool {
+        self.ev & 0xff >= AUX_EVENT
+    }
+
     /// Checks if this `Event` corresponds to the given event.
     #[inline(always)]
     pub fn is(&self, event: u64) -> bool {
@@ -323,6 +443,11 @@ pub enum Status {
     Canceled = 4,
     PartiallyFilled = 5,
     Rejected = 6,
+    /// Used as the request status for a request to modify an opened order.
+    Replaced = 7,
+    /// Used as the request status for a request to query the current state of an order, and its
+    /// response. See [`Bot::query_order`].
+    Queried = 8,
     /// This occurs when the [`Connector`](`crate::connector::Connector`) receives an order status
     /// value that does not have a corresponding enum value.
     Unsupported = 255,
@@ -340,6 +465,9 @@ pub enum TimeInForce {
     FOK = 2,
     /// Immediate or Cancel
     IOC = 3,
+    /// Good 'Til Date, which the exchange expires at the order's
+    /// [`expire_timestamp`](Order::expire_timestamp) if it has not been executed or canceled.
+    GTD = 4,
     /// This occurs when the [`Connector`](`crate::connector::Connector`) receives a time-in-force
     /// value that does not have a corresponding enum value.
     Unsupported = 255,
@@ -352,6 +480,7 @@ impl AsRef<str> for TimeInForce {
             TimeInForce::GTX => "GTX",
             TimeInForce::FOK => "FOK",
             TimeInForce::IOC => "IOC",
+            TimeInForce::GTD => "GTD",
             TimeInForce::Unsupported => panic!("TimeInForce::Unsupported"),
         }
     }
@@ -363,6 +492,10 @@ impl AsRef<str> for TimeInForce {
 pub enum OrdType {
     Limit = 0,
     Market = 1,
+    /// A market order that is submitted when the market reaches the trigger price.
+    StopMarket = 2,
+    /// A limit order that is submitted when the market reaches the trigger price.
+    StopLimit = 3,
     Unsupported = 255,
 }
 
@@ -371,11 +504,51 @@ impl AsRef<str> for OrdType {
         match self {
             OrdType::Limit => "LIMIT",
             OrdType::Market => "MARKET",
+            OrdType::StopMarket => "STOP_MARKET",
+            OrdType::StopLimit => "STOP_LIMIT",
             OrdType::Unsupported => panic!("OrdType::Unsupported"),
         }
     }
 }
 
+impl OrdType {
+    /// Returns whether this order type is a stop order that is triggered by the market reaching
+    /// the trigger price.
+    pub fn is_stop(&self) -> bool {
+        *self == OrdType::StopMarket || *self == OrdType::StopLimit
+    }
+}
+
+/// The time unit of timestamps and durations.
+///
+/// The engine works in nanoseconds, the same as the live [Bot](crate::live::LiveBot), so that
+/// [`elapse`](Bot::elapse), latency models, and recorders interpret durations consistently. Data
+/// recorded in another unit is converted into nanoseconds when it is read, without needing to be
+/// rewritten.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum TimeUnit {
+    #[default]
+    Nanosecond,
+    Microsecond,
+    Millisecond,
+}
+
+impl TimeUnit {
+    /// Returns the number of nanoseconds in one unit.
+    pub fn nanos(&self) -> i64 {
+        match self {
+            TimeUnit::Nanosecond => 1,
+            TimeUnit::Microsecond => 1_000,
+            TimeUnit::Millisecond => 1_000_000,
+        }
+    }
+
+    /// Converts the value in this unit into nanoseconds.
+    pub fn to_nanos(&self, value: i64) -> i64 {
+        value.saturating_mul(self.nanos())
+    }
+}
+
 /// Provides cloning of `Box<dyn Any>`, which is utilized in [Order] for the additional data used in
 /// [`QueueModel`](`crate::backtest::models::QueueModel`).
 ///
@@ -407,6 +580,34 @@ impl AnyClone for () {
     }
 }
 
+/// Position mode, which determines how the fills of the orders make up the position.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum PositionMode {
+    /// The position is a single net position, which the fills on both sides offset.
+    #[default]
+    OneWay,
+    /// The long and short positions are held separately as legs. An order opens or increases the
+    /// leg of its side, a buy order the long leg and a sell order the short leg, while a
+    /// reduce-only order closes the opposite leg. See [`Order::hedge_leg`].
+    Hedge,
+}
+
+/// The reference price whose movement triggers a stop order.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+#[repr(u8)]
+pub enum TriggerBy {
+    /// Follows the trigger price source configured for the asset, which is the last price unless
+    /// otherwise configured.
+    #[default]
+    Default = 0,
+    /// The last traded price, along with the best bid and offer.
+    LastPrice = 1,
+    /// The mark price.
+    MarkPrice = 2,
+    /// The index price.
+    IndexPrice = 3,
+}
+
 /// Order
 #[derive(Clone)]
 #[repr(C)]
@@ -440,10 +641,21 @@ pub struct Order {
     /// Request status:
     ///   * [`Status::New`]: Request to open a new order.
     ///   * [`Status::Canceled`]: Request to cancel an opened order.
+    ///   * [`Status::Replaced`]: Request to modify an opened order.
+    ///   * [`Status::Queried`]: Request to query the current state of an order.
     pub req: Status,
     pub status: Status,
     pub side: Side,
     pub time_in_force: TimeInForce,
+    /// Trigger price in ticks (`trigger_price / tick_size`), only available for stop orders.
+    pub trigger_price_tick: i64,
+    /// The exchange timestamp at which the order expires, only available for
+    /// [`TimeInForce::GTD`] orders.
+    pub expire_timestamp: i64,
+    /// Whether the order can only reduce the position.
+    pub reduce_only: bool,
+    /// The reference price that triggers the order, only available for stop orders.
+    pub trigger_by: TriggerBy,
 }
 
 impl Order {
@@ -474,6 +686,10 @@ impl Order {
             q: Box::new(()),
             maker: false,
             order_type,
+            trigger_price_tick: 0,
+            expire_timestamp: 0,
+            reduce_only: false,
+            trigger_by: TriggerBy::Default,
         }
     }
 
@@ -482,6 +698,11 @@ impl Order {
         self.price_tick as f64 * self.tick_size
     }
 
+    /// Returns the trigger price, only available for stop orders.
+    pub fn trigger_price(&self) -> f64 {
+        self.trigger_price_tick as f64 * self.tick_size
+    }
+
     /// Returns the executed price, only available when this order is executed.
     pub fn exec_price(&self) -> f64 {
         self.exec_price_tick as f64 * self.tick_size
@@ -498,6 +719,17 @@ impl Order {
         self.status == Status::New || self.status == Status::PartiallyFilled
     }
 
+    /// Returns the leg that the order opens or closes in [`PositionMode::Hedge`]: [`Side::Buy`]
+    /// for the long leg and [`Side::Sell`] for the short leg. A reduce-only order closes the leg
+    /// opposite to its side.
+    pub fn hedge_leg(&self) -> Side {
+        match (self.side, self.reduce_only) {
+            (Side::Buy, false) | (Side::Sell, true) => Side::Buy,
+            (Side::Sell, false) | (Side::Buy, true) => Side::Sell,
+            (side, _) => side,
+        }
+    }
+
     /// Returns whether this order has an ongoing request.
     pub fn pending(&self) -> bool {
         self.req != Status::None
@@ -528,6 +760,10 @@ impl Order {
         self.q = order.q.clone();
         self.maker = order.maker;
         self.order_type = order.order_type;
+        self.trigger_price_tick = order.trigger_price_tick;
+        self.expire_timestamp = order.expire_timestamp;
+        self.reduce_only = order.reduce_only;
+        self.trigger_by = order.trigger_by;
     }
 }
 
@@ -549,6 +785,10 @@ impl Debug for Order {
             .field("order_id", &self.order_id)
             .field("maker", &self.maker)
             .field("order_type", &self.order_type)
+            .field("trigger_price_tick", &self.trigger_price_tick)
+            .field("expire_timestamp", &self.expire_timestamp)
+            .field("reduce_only", &self.reduce_only)
+            .field("trigger_by", &self.trigger_by)
             .finish()
     }
 }
@@ -558,12 +798,16 @@ impl Debug for Order {
 pub enum Request {
     /// An order request, a tuple consisting of an asset number and an [`Order`].
     Order { asset_no: usize, order: Order },
+    /// A request to cancel all open orders of the asset.
+    CancelAll { asset_no: usize },
 }
 
 /// Provides state values.
 ///
 /// **Note:** In a live bot, currently only `position` value is delivered correctly, and other
-/// values are invalid.
+/// values are invalid, except for `num_trades`, `trading_volume`, and `trading_value`, which are
+/// delivered by the connectors that report the fills through [`LiveEvent::Fill`], such as the
+/// Binance Futures connector for COIN-M futures.
 #[repr(C)]
 #[derive(PartialEq, Clone, Debug, Default)]
 pub struct StateValues {
@@ -574,12 +818,40 @@ pub struct StateValues {
     pub fee: f64,
     // todo: currently, they are cumulative values, but they need to be values within the record
     //       interval.
-    /// Backtest only
     pub num_trades: i64,
-    /// Backtest only
     pub trading_volume: f64,
-    /// Backtest only
     pub trading_value: f64,
+    /// Backtest only. The cumulative funding payment on the position of a perpetual contract,
+    /// which is positive when paid and negative when received. Like the fee, it is deducted from
+    /// the equity separately from the balance.
+    pub funding: f64,
+    /// The quantity of the long position. In [`PositionMode::OneWay`], it is the net position if
+    /// it is long, and zero otherwise.
+    pub long_position: f64,
+    /// The quantity of the short position, which is positive. In [`PositionMode::OneWay`], it is
+    /// the absolute net position if it is short, and zero otherwise.
+    pub short_position: f64,
+    /// The quantity-weighted average entry price of the long position, or zero if there is none.
+    pub long_entry_price: f64,
+    /// The quantity-weighted average entry price of the short position, or zero if there is none.
+    pub short_entry_price: f64,
+    /// Backtest only. The cumulative borrow interest on the short position of a spot or margin
+    /// asset. Like the fee, it is deducted from the equity separately from the balance. See
+    /// [`BorrowCost`](crate::backtest::proc::BorrowCost).
+    pub borrow_cost: f64,
+}
+
+/// A roll of a continuous futures asset from one contract to the next. See
+/// [`Contract`](crate::backtest::reader::Contract).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Roll {
+    /// The timestamp at which the roll occurs.
+    pub timestamp: i64,
+    /// The index of the contract rolled into, in the order the contracts are given.
+    pub contract: usize,
+    /// The price adjustment at the roll, which is the price of the contract rolled into minus the
+    /// price of the previous contract.
+    pub adjustment: f64,
 }
 
 /// Provides errors that can occur in builders.
@@ -605,6 +877,35 @@ pub struct OrderRequest {
     pub side: Side,
     pub time_in_force: TimeInForce,
     pub order_type: OrdType,
+    /// The trigger price of a stop order. This is ignored for other order types.
+    pub trigger_price: f64,
+    /// The exchange timestamp at which a [`TimeInForce::GTD`] order expires. This is ignored for
+    /// other time-in-force options.
+    pub expire_timestamp: i64,
+    /// Whether the order can only reduce the position. See the exchange model for how an order
+    /// that would increase the position is handled.
+    pub reduce_only: bool,
+    /// The reference price that triggers a stop order. [`TriggerBy::Default`] follows the asset's
+    /// configuration. This is ignored for other order types.
+    pub trigger_by: TriggerBy,
+}
+
+/// A request in a batch submitted through [`Bot::submit_batch`].
+pub enum BatchRequest {
+    /// Places an order.
+    Submit(OrderRequest),
+    /// Cancels the order with the order ID.
+    Cancel(OrderId),
+}
+
+impl BatchRequest {
+    /// Returns the order ID of the order that the request targets.
+    pub fn order_id(&self) -> OrderId {
+        match self {
+            BatchRequest::Submit(order) => order.order_id,
+            BatchRequest::Cancel(order_id) => *order_id,
+        }
+    }
 }
 
 /// Provides a bot interface for backtesting and live trading.
@@ -708,6 +1009,21 @@ where
         wait: bool,
     ) -> Result<bool, Self::Error>;
 
+    /// Submits the order requests together as a batch, as through a venue's batch order endpoint.
+    /// In backtesting, the batch shares one order entry latency and the exchange processes the
+    /// requests atomically in the given order. The batch is rejected as a whole if any request is
+    /// invalid.
+    ///
+    /// * `asset_no` - Asset number at which this command will be executed.
+    /// * `requests` - Order requests to submit.
+    /// * `wait` - If true, wait until the response to the last request is received.
+    fn submit_batch(
+        &mut self,
+        asset_no: usize,
+        requests: Vec<BatchRequest>,
+        wait: bool,
+    ) -> Result<bool, Self::Error>;
+
     /// Cancels the specified order.
     ///
     /// * `asset_no` - Asset number at which this command will be executed.
@@ -720,6 +1036,48 @@ where
         wait: bool,
     ) -> Result<bool, Self::Error>;
 
+    /// Cancels all open orders of the asset with a single mass-cancel request, which reaches the
+    /// exchange as one message, as the venue's cancel-all endpoint does. The cancellation of each
+    /// order is reported individually.
+    ///
+    /// * `asset_no` - Asset number at which this command will be executed.
+    /// * `wait` - If true, wait until the mass-cancel response is received. In live trading, it
+    ///            waits until the first order response is received.
+    fn cancel_all(&mut self, asset_no: usize, wait: bool) -> Result<bool, Self::Error>;
+
+    /// Modifies the price and quantity of the specified order in place. Whether the order keeps
+    /// its queue position depends on the venue; see the exchange model for details.
+    ///
+    /// * `asset_no` - Asset number at which this command will be executed.
+    /// * `order_id` - Order ID to modify.
+    /// * `price` - New order price.
+    /// * `qty` - New order quantity.
+    /// * `wait` - If true, wait until the order modification response is received.
+    fn modify(
+        &mut self,
+        asset_no: usize,
+        order_id: OrderId,
+        price: f64,
+        qty: f64,
+        wait: bool,
+    ) -> Result<bool, Self::Error>;
+
+    /// Queries the current state of the specified order at the exchange, as the venue's query
+    /// order endpoint does, so that an order whose state is unknown, for instance, due to a lost
+    /// response, can be reconciled. The local order is updated with the state in the response. An
+    /// open order that the exchange no longer has is marked as [`Status::Canceled`] if a cancel is
+    /// pending, or [`Status::Expired`] otherwise.
+    ///
+    /// * `asset_no` - Asset number at which this command will be executed.
+    /// * `order_id` - Order ID to query.
+    /// * `wait` - If true, wait until the query response is received.
+    fn query_order(
+        &mut self,
+        asset_no: usize,
+        order_id: OrderId,
+        wait: bool,
+    ) -> Result<bool, Self::Error>;
+
     /// Clears inactive orders from the local orders whose status is neither [`Status::New`] nor
     /// [`Status::PartiallyFilled`].
     fn clear_inactive_orders(&mut self, asset_no: Option<usize>);
@@ -742,8 +1100,8 @@ where
     /// Elapses the specified duration.
     ///
     /// Args:
-    /// * `duration` - Duration to elapse. Nanoseconds is the default unit. However, unit should be
-    ///                the same as the data's timestamp unit.
+    /// * `duration` - Duration to elapse in nanoseconds. Data recorded in another time unit is
+    ///                converted into nanoseconds as it is read. See [`TimeUnit`].
     ///
     /// Returns:
     ///   `Ok(true)` if the method reaches the specified timestamp within the data. If the end of
@@ -757,8 +1115,8 @@ where
     /// utilized to simulate such processing times.
     ///
     /// Args:
-    /// * `duration` - Duration to elapse. Nanoseconds is the default unit. However, unit should be
-    ///                the same as the data's timestamp unit.
+    /// * `duration` - Duration to elapse in nanoseconds. Data recorded in another time unit is
+    ///                converted into nanoseconds as it is read. See [`TimeUnit`].
     ///
     /// Returns:
     ///   `Ok(true)` if the method reaches the specified timestamp within the data. If the end of
@@ -774,6 +1132,26 @@ where
     /// Returns the last order's request timestamp, exchange timestamp, and response receipt
     /// timestamp.
     fn order_latency(&self, asset_no: usize) -> Option<(i64, i64, i64)>;
+
+    /// Returns the number of feed gaps detected so far, in which the market depth became stale
+    /// until the next snapshot. This allows a strategy to react to the gap, for example, by
+    /// stopping quoting until the market depth is recovered, as it would in live trading. In live
+    /// mode, it always returns `0`.
+    fn feed_gap_count(&self, asset_no: usize) -> usize;
+
+    /// Returns the contract rolls of a continuous futures asset that have occurred so far, in the
+    /// order of occurrence. The strategy can detect a new roll by the change in the number of rolls
+    /// and, for example, cancel the orders placed at the previous contract's prices. In live mode,
+    /// it always returns an empty slice.
+    fn rolls(&self, asset_no: usize) -> &[Roll];
+
+    /// Returns the latest user-defined auxiliary event of the specified kind, such as a funding
+    /// rate or an open interest update, received so far. See [`AUX_EVENT`].
+    ///
+    /// Args:
+    /// * `asset_no` - Asset number from which the auxiliary event will be retrieved.
+    /// * `kind` - Kind of the auxiliary event, from [`AUX_EVENT`] to `0xff`.
+    fn aux_event(&self, asset_no: usize, kind: u64) -> Option<&Event>;
 }
 
 /// Provides bot statistics and [`StateValues`] recording features for backtesting result analysis
@@ -818,6 +1196,7 @@ mod tests {
         assert!(!event.is(LOCAL_BID_DEPTH_EVENT));
         assert!(!event.is(LOCAL_BUY_TRADE_EVENT));
         assert!(event.is(LOCAL_BID_DEPTH_CLEAR_EVENT));
+        assert!(!event.is_aux());
 
         let event = Event {
             ev: LOCAL_EVENT | BUY_EVENT | 0xff,
@@ -836,5 +1215,6 @@ mod tests {
         assert!(!event.is(LOCAL_BID_DEPTH_SNAPSHOT_EVENT));
         assert!(event.is(LOCAL_EVENT));
         assert!(event.is(BUY_EVENT));
+        assert!(event.is_aux());
     }
 }
@@ -27,6 +27,12 @@ from .order import (
     GTX,
     LIMIT,
     MARKET,
+    STOP_MARKET,
+    STOP_LIMIT,
+    TRIGGER_BY_DEFAULT,
+    LAST_PRICE,
+    MARK_PRICE,
+    INDEX_PRICE,
 )
 from .recorder import Recorder
 from .types import (
@@ -39,7 +45,10 @@ from .types import (
     EXCH_EVENT,
     LOCAL_EVENT,
     BUY_EVENT,
-    SELL_EVENT
+    SELL_EVENT,
+    LIQUIDATION_TRADE_FLAG,
+    BLOCK_TRADE_FLAG,
+    RPI_TRADE_FLAG
 )
 
 __all__ = (
@@ -58,6 +67,9 @@ __all__ = (
     'LOCAL_EVENT'
     'BUY_EVENT',
     'SELL_EVENT',
+    'LIQUIDATION_TRADE_FLAG',
+    'BLOCK_TRADE_FLAG',
+    'RPI_TRADE_FLAG',
 
     # Side
     'BUY',
@@ -76,6 +88,14 @@ __all__ = (
 
     'LIMIT',
     'MARKET',
+    'STOP_MARKET',
+    'STOP_LIMIT',
+
+    # Stop trigger price
+    'TRIGGER_BY_DEFAULT',
+    'LAST_PRICE',
+    'MARK_PRICE',
+    'INDEX_PRICE',
     
     'Recorder'
 )
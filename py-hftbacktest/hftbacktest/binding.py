@@ -507,6 +507,10 @@ hashmapbt_cancel = lib.hashmapbt_cancel
 hashmapbt_cancel.restype = c_int64
 hashmapbt_cancel.argtypes = [c_void_p, c_uint64, c_uint64, c_bool]
 
+hashmapbt_modify = lib.hashmapbt_modify
+hashmapbt_modify.restype = c_int64
+hashmapbt_modify.argtypes = [c_void_p, c_uint64, c_uint64, c_double, c_double, c_bool]
+
 hashmapbt_clear_last_trades = lib.hashmapbt_clear_last_trades
 hashmapbt_clear_last_trades.restype = c_void_p
 hashmapbt_clear_last_trades.argtypes = [c_void_p, c_uint64]
@@ -723,6 +727,25 @@ class HashMapMarketDepthBacktest:
         """
         return hashmapbt_cancel(self.ptr, asset_no, order_id, wait)
 
+    def modify(self, asset_no: uint64, order_id: uint64, price: float64, qty: float64, wait: bool) -> int64:
+        """
+        Modifies the price and quantity of the specified order in place. Whether the order keeps its queue position
+        is determined by the exchange model's amend rule.
+
+        Args:
+            asset_no: Asset number at which this command will be executed.
+            order_id: Order ID to modify.
+            price: New order price.
+            qty: New order quantity.
+            wait: If `True`, wait until the order modification response is received.
+
+        Returns:
+            * `0` when it successfully modifies an order.
+            * `1` when it reaches the end of the data, if `wait` is `True`.
+            * Otherwise, an error occurred.
+        """
+        return hashmapbt_modify(self.ptr, asset_no, order_id, price, qty, wait)
+
     def clear_inactive_orders(self, asset_no: uint64) -> None:
         """
         Clears inactive orders from the local order dictionary whose status is neither :const:`NEW` nor
@@ -925,6 +948,10 @@ roivecbt_cancel = lib.roivecbt_cancel
 roivecbt_cancel.restype = c_int64
 roivecbt_cancel.argtypes = [c_void_p, c_uint64, c_uint64, c_bool]
 
+roivecbt_modify = lib.roivecbt_modify
+roivecbt_modify.restype = c_int64
+roivecbt_modify.argtypes = [c_void_p, c_uint64, c_uint64, c_double, c_double, c_bool]
+
 roivecbt_clear_last_trades = lib.roivecbt_clear_last_trades
 roivecbt_clear_last_trades.restype = c_void_p
 roivecbt_clear_last_trades.argtypes = [c_void_p, c_uint64]
@@ -1137,6 +1164,25 @@ class ROIVectorMarketDepthBacktest:
         """
         return roivecbt_cancel(self.ptr, asset_no, order_id, wait)
 
+    def modify(self, asset_no: uint64, order_id: uint64, price: float64, qty: float64, wait: bool) -> int64:
+        """
+        Modifies the price and quantity of the specified order in place. Whether the order keeps its queue position
+        is determined by the exchange model's amend rule.
+
+        Args:
+            asset_no: Asset number at which this command will be executed.
+            order_id: Order ID to modify.
+            price: New order price.
+            qty: New order quantity.
+            wait: If `True`, wait until the order modification response is received.
+
+        Returns:
+            * `0` when it successfully modifies an order.
+            * `1` when it reaches the end of the data, if `wait` is `True`.
+            * Otherwise, an error occurred.
+        """
+        return roivecbt_modify(self.ptr, asset_no, order_id, price, qty, wait)
+
     def clear_inactive_orders(self, asset_no: uint64) -> None:
         """
         Clears inactive orders from the local order dictionary whose status is neither :const:`NEW` nor
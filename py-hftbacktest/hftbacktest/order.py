@@ -24,9 +24,17 @@ GTC = 0  # Good 'till cancel
 GTX = 1  # Post only
 FOK = 2  # Fill or kill
 IOC = 3  # Immediate or cancel
+GTD = 4  # Good 'till date
 
 LIMIT = 0
 MARKET = 1
+STOP_MARKET = 2
+STOP_LIMIT = 3
+
+TRIGGER_BY_DEFAULT = 0  # Follows the asset's configuration
+LAST_PRICE = 1
+MARK_PRICE = 2
+INDEX_PRICE = 3
 
 
 class Order:
@@ -42,6 +50,37 @@ class Order:
         """
         return self.arr[0].price_tick * self.arr[0].tick_size
 
+    @property
+    def trigger_price(self) -> float64:
+        """
+        Returns the trigger price. This is only valid if :obj:`order_type` is :const:`STOP_MARKET` or
+        :const:`STOP_LIMIT`.
+        """
+        return self.arr[0].trigger_price_tick * self.arr[0].tick_size
+
+    @property
+    def expire_timestamp(self) -> int64:
+        """
+        Returns the exchange timestamp at which the order expires. This is only valid if
+        :obj:`time_in_force` is ``GTD``.
+        """
+        return self.arr[0].expire_timestamp
+
+    @property
+    def reduce_only(self) -> bool:
+        """
+        Returns whether the order can only reduce the position.
+        """
+        return self.arr[0].reduce_only
+
+    @property
+    def trigger_by(self) -> uint8:
+        """
+        Returns the reference price that triggers the order, one of :const:`TRIGGER_BY_DEFAULT`, :const:`LAST_PRICE`,
+        :const:`MARK_PRICE` and :const:`INDEX_PRICE`. This is only valid for stop orders.
+        """
+        return self.arr[0].trigger_by
+
     @property
     def exec_price(self) -> float64:
         """
@@ -132,6 +171,8 @@ class Order:
 
             * :const:`MARKET`
             * :const:`LIMIT`
+            * :const:`STOP_MARKET`
+            * :const:`STOP_LIMIT`
         """
         return self.arr[0].order_type
 
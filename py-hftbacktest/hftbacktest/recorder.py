@@ -30,6 +30,8 @@ class Recorder_:
             self.records[self.i, asset_no].num_trades = state_values.num_trades
             self.records[self.i, asset_no].trading_volume = state_values.trading_volume
             self.records[self.i, asset_no].trading_value = state_values.trading_value
+            self.records[self.i, asset_no].funding = state_values.funding
+            self.records[self.i, asset_no].borrow_cost = state_values.borrow_cost
 
         self.i += 1
         if self.i == len(self.records):
@@ -37,5 +37,29 @@ class StateValues:
     def trading_value(self) -> float64:
         return self.arr[0].trading_value
 
+    @property
+    def funding(self) -> float64:
+        return self.arr[0].funding
+
+    @property
+    def long_position(self) -> float64:
+        return self.arr[0].long_position
+
+    @property
+    def short_position(self) -> float64:
+        return self.arr[0].short_position
+
+    @property
+    def long_entry_price(self) -> float64:
+        return self.arr[0].long_entry_price
+
+    @property
+    def short_entry_price(self) -> float64:
+        return self.arr[0].short_entry_price
+
+    @property
+    def borrow_cost(self) -> float64:
+        return self.arr[0].borrow_cost
+
 
 StateValues_ = jitclass(StateValues)
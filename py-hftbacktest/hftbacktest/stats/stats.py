@@ -351,6 +351,16 @@ class LinearAssetRecord(Record):
                     pl.col('balance') + pl.col('position') * pl.col('price') * self._contract_size
                 ).alias('equity_wo_fee')
             )
+            if 'funding' in self.df:
+                # The funding payments are part of the trading P&L rather than the fees.
+                self.df = self.df.with_columns(
+                    (pl.col('equity_wo_fee') - pl.col('funding')).alias('equity_wo_fee')
+                )
+            if 'borrow_cost' in self.df:
+                # So is the borrow interest on the short position.
+                self.df = self.df.with_columns(
+                    (pl.col('equity_wo_fee') - pl.col('borrow_cost')).alias('equity_wo_fee')
+                )
 
         if 'trading_value_' not in self.df:
             if 'trading_value' not in self.df:
@@ -374,6 +384,16 @@ class InverseAssetRecord(Record):
                     -pl.col('balance') - pl.col('position') / pl.col('price') * self._contract_size
                 ).alias('equity_wo_fee')
             )
+            if 'funding' in self.df:
+                # The funding payments are part of the trading P&L rather than the fees.
+                self.df = self.df.with_columns(
+                    (pl.col('equity_wo_fee') - pl.col('funding')).alias('equity_wo_fee')
+                )
+            if 'borrow_cost' in self.df:
+                # So is the borrow interest on the short position.
+                self.df = self.df.with_columns(
+                    (pl.col('equity_wo_fee') - pl.col('borrow_cost')).alias('equity_wo_fee')
+                )
 
         if 'trading_value_' not in self.df:
             if 'trading_value' not in self.df:
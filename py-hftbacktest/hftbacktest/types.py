@@ -22,6 +22,10 @@ LOCAL_EVENT = 1 << 30
 BUY_EVENT = 1 << 29
 SELL_EVENT = 1 << 28
 
+LIQUIDATION_TRADE_FLAG = 1 << 27
+BLOCK_TRADE_FLAG = 1 << 26
+RPI_TRADE_FLAG = 1 << 25
+
 state_values_dtype = np.dtype(
     [
         ('position', 'f8'),
@@ -29,7 +33,13 @@ state_values_dtype = np.dtype(
         ('fee', 'f8'),
         ('num_trades', 'i8'),
         ('trading_volume', 'f8'),
-        ('trading_value', 'f8')
+        ('trading_value', 'f8'),
+        ('funding', 'f8'),
+        ('long_position', 'f8'),
+        ('short_position', 'f8'),
+        ('long_entry_price', 'f8'),
+        ('short_entry_price', 'f8'),
+        ('borrow_cost', 'f8')
     ],
     align=True
 )
@@ -68,7 +78,11 @@ order_dtype = np.dtype(
         ('req', 'u1'),
         ('status', 'u1'),
         ('side', 'i1'),
-        ('time_in_force', 'u1')
+        ('time_in_force', 'u1'),
+        ('trigger_price_tick', 'i8'),
+        ('expire_timestamp', 'i8'),
+        ('reduce_only', 'bool'),
+        ('trigger_by', 'u1')
     ],
     align=True
 )
@@ -82,7 +96,9 @@ record_dtype = np.dtype(
         ('fee', 'f8'),
         ('num_trades', 'i8'),
         ('trading_volume', 'f8'),
-        ('trading_value', 'f8')
+        ('trading_value', 'f8'),
+        ('funding', 'f8'),
+        ('borrow_cost', 'f8')
     ],
     align=True
 )
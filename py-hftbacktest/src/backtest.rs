@@ -60,6 +60,8 @@ pub extern "C" fn hashmapbt_close(hbt_ptr: *mut HashMapMarketDepthBacktest) -> i
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -76,6 +78,8 @@ pub extern "C" fn hashmapbt_elapse(hbt_ptr: *mut HashMapMarketDepthBacktest, dur
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -95,6 +99,8 @@ pub extern "C" fn hashmapbt_elapse_bt(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -122,6 +128,8 @@ pub extern "C" fn hashmapbt_wait_order_response(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -142,6 +150,8 @@ pub extern "C" fn hashmapbt_wait_next_feed(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -176,6 +186,8 @@ pub extern "C" fn hashmapbt_submit_buy_order(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -209,6 +221,8 @@ pub extern "C" fn hashmapbt_submit_sell_order(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -230,6 +244,33 @@ pub extern "C" fn hashmapbt_cancel(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
+        Err(BacktestError::DataError(_)) => 100,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn hashmapbt_modify(
+    hbt_ptr: *mut HashMapMarketDepthBacktest,
+    asset_no: usize,
+    order_id: u64,
+    price: f64,
+    qty: f64,
+    wait: bool,
+) -> i64 {
+    let hbt = unsafe { &mut *hbt_ptr };
+    match hbt.modify(asset_no, order_id, price, qty, wait) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(BacktestError::OrderIdExist) => 10,
+        Err(BacktestError::OrderRequestInProcess) => 11,
+        Err(BacktestError::OrderNotFound) => 12,
+        Err(BacktestError::InvalidOrderRequest) => 13,
+        Err(BacktestError::InvalidOrderStatus) => 14,
+        Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -332,6 +373,8 @@ pub extern "C" fn hashmapbt_goto_end(hbt_ptr: *mut HashMapMarketDepthBacktest) -
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -386,6 +429,8 @@ pub extern "C" fn roivecbt_close(hbt_ptr: *mut ROIVectorMarketDepthBacktest) ->
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -405,6 +450,8 @@ pub extern "C" fn roivecbt_elapse(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -424,6 +471,8 @@ pub extern "C" fn roivecbt_elapse_bt(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -451,6 +500,8 @@ pub extern "C" fn roivecbt_wait_order_response(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -471,6 +522,8 @@ pub extern "C" fn roivecbt_wait_next_feed(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -505,6 +558,8 @@ pub extern "C" fn roivecbt_submit_buy_order(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -538,6 +593,8 @@ pub extern "C" fn roivecbt_submit_sell_order(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
@@ -559,6 +616,33 @@ pub extern "C" fn roivecbt_cancel(
         Err(BacktestError::InvalidOrderRequest) => 13,
         Err(BacktestError::InvalidOrderStatus) => 14,
         Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
+        Err(BacktestError::DataError(_)) => 100,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn roivecbt_modify(
+    hbt_ptr: *mut ROIVectorMarketDepthBacktest,
+    asset_no: usize,
+    order_id: u64,
+    price: f64,
+    qty: f64,
+    wait: bool,
+) -> i64 {
+    let hbt = unsafe { &mut *hbt_ptr };
+    match hbt.modify(asset_no, order_id, price, qty, wait) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(BacktestError::OrderIdExist) => 10,
+        Err(BacktestError::OrderRequestInProcess) => 11,
+        Err(BacktestError::OrderNotFound) => 12,
+        Err(BacktestError::InvalidOrderRequest) => 13,
+        Err(BacktestError::InvalidOrderStatus) => 14,
+        Err(BacktestError::EndOfData) => 15,
+        Err(BacktestError::ConstraintViolation(_)) => 16,
+        Err(BacktestError::SendQueueFull) => 17,
         Err(BacktestError::DataError(_)) => 100,
     }
 }
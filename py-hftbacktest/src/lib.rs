@@ -7,6 +7,7 @@ use hftbacktest::{
         assettype::{InverseAsset, LinearAsset},
         models::{
             ConstantLatency,
+            FlatFee,
             IntpOrderLatency,
             LogProbQueueFunc,
             LogProbQueueFunc2,
@@ -26,10 +27,11 @@ use hftbacktest::{
         DataSource,
     },
     prelude::{ApplySnapshot, Event, HashMapMarketDepth, ROIVectorMarketDepth},
+    types::BuildError,
 };
 use hftbacktest_derive::build_asset;
 pub use order::*;
-use pyo3::prelude::*;
+use pyo3::{exceptions::PyValueError, prelude::*};
 
 mod backtest;
 mod depth;
@@ -380,7 +382,8 @@ pub fn build_hashmap_backtest(assets: Vec<PyRefMut<BacktestAsset>>) -> PyResult<
                 PowerProbQueueModel3 { n }
             ],
             [NoPartialFillExchange {}, PartialFillExchange {}]
-        );
+        )
+        .map_err(|error| PyValueError::new_err(error.to_string()))?;
         local.push(asst.local);
         exch.push(asst.exch);
     }
@@ -417,7 +420,8 @@ pub fn build_roivec_backtest(assets: Vec<PyRefMut<BacktestAsset>>) -> PyResult<u
                 PowerProbQueueModel3 { n }
             ],
             [NoPartialFillExchange {}, PartialFillExchange {}]
-        );
+        )
+        .map_err(|error| PyValueError::new_err(error.to_string()))?;
         local.push(asst.local);
         exch.push(asst.exch);
     }